@@ -6,6 +6,7 @@ use criterion::criterion_group;
 use criterion::criterion_main;
 use criterion::BenchmarkId;
 use criterion::Criterion;
+use ministark::hash::Blake3HashFn;
 use ministark::hash::ElementHashFn;
 use ministark::hash::Sha256HashFn;
 use ministark::merkle::MatrixMerkleTree;
@@ -41,6 +42,7 @@ fn build_merkle_tree_bench<F: GpuField + Field, H: ElementHashFn<F>>(
 
 fn build_merkle_tree_benches(c: &mut Criterion) {
     build_merkle_tree_bench::<Fp, Sha256HashFn>(c, "Sha256");
+    build_merkle_tree_bench::<Fp, Blake3HashFn>(c, "Blake3");
 }
 
 criterion_group!(benches, build_merkle_tree_benches);
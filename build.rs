@@ -1,5 +1,28 @@
+use std::env;
+
 fn main() {
     // create a cfg alias for apple_silicon
-    #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
-    println!("cargo:rustc-cfg=apple_silicon")
+    // NOTE: target cfgs must be read from the environment since this build
+    // script is compiled for the host and not the target
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    println!("cargo:rustc-check-cfg=cfg(apple_silicon)");
+    println!("cargo:rustc-check-cfg=cfg(metal_gpu)");
+    println!("cargo:rustc-check-cfg=cfg(wgpu_gpu)");
+    let mut metal_gpu = false;
+    if target_arch == "aarch64" && target_os == "macos" {
+        println!("cargo:rustc-cfg=apple_silicon");
+
+        // the `gpu` feature is only backed by Metal on Apple Silicon. All other
+        // platforms fall back to the CPU implementations.
+        if env::var_os("CARGO_FEATURE_GPU").is_some() {
+            println!("cargo:rustc-cfg=metal_gpu");
+            metal_gpu = true;
+        }
+    }
+
+    // the `wgpu` feature works on all platforms but Metal is preferred
+    if env::var_os("CARGO_FEATURE_WGPU").is_some() && !metal_gpu {
+        println!("cargo:rustc-cfg=wgpu_gpu");
+    }
 }
@@ -6,6 +6,7 @@ use ark_poly::Radix2EvaluationDomain;
 use ark_serialize::CanonicalSerialize;
 use ministark::air::AirConfig;
 use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Assertion;
 use ministark::constraints::Constraint;
 use ministark::constraints::ExecutionTraceColumn;
 use ministark::hash::HashFn;
@@ -73,41 +74,11 @@ impl AirConfig for FibAirConfig {
     fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
         use AlgebraicItem::*;
         let trace_xs = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
-        // NOTE: =1
-        let first_trace_x = Constant(FieldVariant::Fp(trace_xs.element(0)));
         // NOTE: =trace_xs.group_gen_inv()
         let last_trace_x = Constant(FieldVariant::Fp(trace_xs.element(trace_len - 1)));
         let one = Constant(FieldVariant::Fp(Fp::one()));
 
-        let boundary_constraints = {
-            let v0 = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
-            let v1 = v0 + v0;
-            let v2 = &v1 * v0;
-            let v3 = &v1 * &v2;
-            let v4 = &v2 * &v3;
-            let v5 = &v3 * &v4;
-            let v6 = &v4 * &v5;
-            let v7 = &v5 * &v6;
-
-            vec![
-                0.curr() - v0,
-                1.curr() - v1,
-                2.curr() - v2,
-                3.curr() - v3,
-                4.curr() - v4,
-                5.curr() - v5,
-                6.curr() - v6,
-                7.curr() - v7,
-            ]
-        }
-        .into_iter()
-        .map(|constraint| {
-            // ensure constraint holds in the first row
-            // symbolically divide `(x - t_0)`
-            constraint / (X - first_trace_x)
-        });
-
-        let transition_constraints = vec![
+        vec![
             0.next() - 6.curr() * 7.curr(),
             1.next() - 7.curr() * 0.next(),
             2.next() - 0.next() * 1.next(),
@@ -124,22 +95,35 @@ impl AirConfig for FibAirConfig {
             // NOTE: `x^trace_len - 1 = (x - t_0)(x - t_1)...(x - t_(n-1))`
             // NOTE: `t^(n-1) = t^(-1)`
             constraint * ((X - last_trace_x) / (X.pow(trace_len) - one))
-        });
-
-        let terminal_constraints =
-            vec![7.curr() - AlgebraicItem::Hint(FibHint::ClaimedNthFibNum as usize)]
-                .into_iter()
-                .map(|constraint| {
-                    // ensure constraint holds in the last row
-                    // symbolically divide `(x - t_0)`
-                    constraint / (X - last_trace_x)
-                });
-
-        boundary_constraints
-            .chain(terminal_constraints)
-            .chain(transition_constraints)
-            .map(Constraint::new)
-            .collect()
+        })
+        .map(Constraint::new)
+        .collect()
+    }
+
+    fn assertions(trace_len: usize) -> Vec<Assertion<FieldVariant<Self::Fp, Self::Fq>>> {
+        let v0 = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let v1 = v0 + v0;
+        let v2 = &v1 * v0;
+        let v3 = &v1 * &v2;
+        let v4 = &v2 * &v3;
+        let v5 = &v3 * &v4;
+        let v6 = &v4 * &v5;
+        let v7 = &v5 * &v6;
+
+        // the first row holds the first fibonacci numbers and the last row
+        // holds the claimed nth fibonacci number
+        let claimed_nth_fib_num = AlgebraicItem::Hint(FibHint::ClaimedNthFibNum as usize);
+        vec![
+            Assertion::single(0, 0, v0),
+            Assertion::single(1, 0, v1),
+            Assertion::single(2, 0, v2),
+            Assertion::single(3, 0, v3),
+            Assertion::single(4, 0, v4),
+            Assertion::single(5, 0, v5),
+            Assertion::single(6, 0, v6),
+            Assertion::single(7, 0, v7),
+            Assertion::single(7, trace_len - 1, claimed_nth_fib_num),
+        ]
     }
 }
 
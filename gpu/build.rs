@@ -1,5 +1,58 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
 fn main() {
     // create a cfg alias for apple_silicon
-    #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
-    println!("cargo:rustc-cfg=apple_silicon")
+    // NOTE: target cfgs must be read from the environment since this build
+    // script is compiled for the host and not the target
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    println!("cargo:rustc-check-cfg=cfg(apple_silicon)");
+    if target_arch == "aarch64" && target_os == "macos" {
+        println!("cargo:rustc-cfg=apple_silicon");
+        build_metal_library();
+    }
+}
+
+/// Compiles the Metal shaders into `$OUT_DIR/shaders.metallib` so the library
+/// always has the kernels of the shader sources. Falls back to the
+/// precompiled library in `src/metal` (see the Makefile) if the Metal compiler
+/// isn't installed.
+fn build_metal_library() {
+    println!("cargo:rerun-if-changed=src/metal");
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let air = out_dir.join("shaders.air");
+    let metallib = out_dir.join("shaders.metallib");
+    let shaders = OsStr::new("src/metal/shaders.metal");
+    let compiled = xcrun(&[
+        OsStr::new("metal"),
+        OsStr::new("-c"),
+        shaders,
+        OsStr::new("-o"),
+        air.as_os_str(),
+    ]) && xcrun(&[
+        OsStr::new("metallib"),
+        air.as_os_str(),
+        OsStr::new("-o"),
+        metallib.as_os_str(),
+    ]);
+    if !compiled {
+        println!(
+            "cargo:warning=failed to compile the Metal shaders. Using the precompiled library \
+             which may be missing kernels. Install Xcode to compile the shaders."
+        );
+        fs::copy("src/metal/shaders.metallib", &metallib).unwrap();
+    }
+}
+
+/// Runs an `xcrun` tool from the macOS SDK. Returns true if it succeeded.
+fn xcrun(args: &[&OsStr]) -> bool {
+    Command::new("xcrun")
+        .args(["-sdk", "macosx"])
+        .args(args)
+        .status()
+        .is_ok_and(|status| status.success())
 }
@@ -0,0 +1,333 @@
+#![cfg(all(feature = "arkworks", feature = "parallel"))]
+//! Multithreaded CPU backend for machines without a GPU. Exposes the same
+//! planner interface as [`plan`](crate::plan) so FFTs are planned, encoded and
+//! executed identically on every backend.
+//!
+//! FFTs are decimation in frequency with radix-4 butterflies (two radix-2
+//! layers per pass over memory). Layers with butterflies that span more than
+//! [`Planner::block_size`] values are parallelized within each group of
+//! butterflies. All remaining layers only touch values within a block so each
+//! block is transformed by a single thread while it's in cache.
+use crate::utils::bit_reverse;
+use crate::utils::fill_twiddles;
+use crate::GpuField;
+use alloc::vec::Vec;
+use ark_ff::One;
+use ark_poly::domain::DomainCoeff;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::iter::zip;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+
+/// Number of values that fit in a typical L1 data cache
+const DEFAULT_BLOCK_SIZE: usize = 1 << 12;
+
+/// Minimum number of butterflies given to a thread
+const MIN_CHUNK_SIZE: usize = 1 << 10;
+
+static PLANNER: Lazy<Planner> = Lazy::new(Planner::default);
+
+pub fn get_planner() -> &'static Planner {
+    &PLANNER
+}
+
+pub struct Planner {
+    block_size: usize,
+}
+
+impl Planner {
+    /// Creates a planner that transforms blocks of `block_size` values at a
+    /// time once butterflies no longer span more than a block
+    pub fn new(block_size: usize) -> Self {
+        assert!(block_size.is_power_of_two());
+        Self { block_size }
+    }
+
+    pub const fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn plan_fft<F: GpuField + DomainCoeff<F::FftField>>(
+        &self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> GpuFft<'_, F>
+    where
+        F::FftField: ark_ff::FftField,
+    {
+        GpuFft::new(FftEncoder::new(self, FftDirection::Forward, domain))
+    }
+
+    pub fn plan_ifft<F: GpuField + DomainCoeff<F::FftField>>(
+        &self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> GpuIfft<'_, F>
+    where
+        F::FftField: ark_ff::FftField,
+    {
+        GpuIfft::new(FftEncoder::new(self, FftDirection::Inverse, domain))
+    }
+}
+
+impl Default for Planner {
+    fn default() -> Self {
+        Self::new(DEFAULT_BLOCK_SIZE)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+enum FftDirection {
+    /// FFT
+    Forward,
+    /// IFFT
+    Inverse,
+}
+
+struct FftEncoder<'a, F: GpuField + DomainCoeff<F::FftField>>
+where
+    F::FftField: ark_ff::FftField,
+{
+    block_size: usize,
+    n: usize,
+    direction: FftDirection,
+    // powers of the domain generator (or its inverse) in natural order
+    twiddles: Vec<F::FftField>,
+    // (shift, scale factor) of the scale and normalize stage
+    scale_and_normalize: Option<(F::FftField, F::FftField)>,
+    // inputs along with whether their evaluations are left in bit-reversed order
+    inputs: Vec<(&'a mut [F], bool)>,
+}
+
+impl<'a, F: GpuField + DomainCoeff<F::FftField>> FftEncoder<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    fn new(
+        planner: &Planner,
+        direction: FftDirection,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> Self {
+        let n = domain.size();
+        let root = match direction {
+            FftDirection::Forward => domain.group_gen,
+            FftDirection::Inverse => domain.group_gen_inv,
+        };
+
+        let mut twiddles = alloc::vec![F::FftField::one(); n / 2];
+        fill_twiddles(&mut twiddles, root);
+
+        let scale_and_normalize = match direction {
+            FftDirection::Forward if domain.offset.is_one() => None,
+            FftDirection::Forward => Some((domain.offset, F::FftField::one())),
+            FftDirection::Inverse => Some((domain.offset_inv, domain.size_inv)),
+        };
+
+        Self {
+            block_size: planner.block_size,
+            n,
+            direction,
+            twiddles,
+            scale_and_normalize,
+            inputs: Vec::new(),
+        }
+    }
+
+    fn encode(&mut self, input: &'a mut [F], out_of_order: bool) {
+        assert_eq!(self.n, input.len());
+        self.inputs.push((input, out_of_order));
+    }
+
+    fn execute(mut self) {
+        let inputs = core::mem::take(&mut self.inputs);
+        inputs.into_par_iter().for_each(|(input, out_of_order)| {
+            if self.direction == FftDirection::Forward {
+                self.scale(input);
+            }
+            self.butterflies(input);
+            if !out_of_order {
+                bit_reverse(input);
+            }
+            if self.direction == FftDirection::Inverse {
+                self.scale(input);
+            }
+        });
+    }
+
+    /// Multiplies the value at index `i` by `scale_factor * shift^i`
+    fn scale(&self, values: &mut [F]) {
+        if let Some((shift, scale_factor)) = self.scale_and_normalize {
+            values
+                .par_chunks_mut(MIN_CHUNK_SIZE)
+                .enumerate()
+                .for_each(|(i, chunk)| {
+                    let mut factor = scale_factor * shift.pow([(i * MIN_CHUNK_SIZE) as u64]);
+                    for value in chunk {
+                        *value *= factor;
+                        factor *= shift;
+                    }
+                });
+        }
+    }
+
+    /// Decimation in frequency butterflies. Outputs are in bit-reversed order.
+    fn butterflies(&self, values: &mut [F]) {
+        let n = values.len();
+        let mut len = n;
+        while len > self.block_size && len >= 4 {
+            let stride = n / len;
+            let chunk_size = MIN_CHUNK_SIZE.min(len / 4);
+            for group in values.chunks_exact_mut(len) {
+                let (lo, hi) = group.split_at_mut(len / 2);
+                let (q0, q1) = lo.split_at_mut(len / 4);
+                let (q2, q3) = hi.split_at_mut(len / 4);
+                q0.par_chunks_mut(chunk_size)
+                    .zip(q1.par_chunks_mut(chunk_size))
+                    .zip(q2.par_chunks_mut(chunk_size))
+                    .zip(q3.par_chunks_mut(chunk_size))
+                    .enumerate()
+                    .for_each(|(i, (((q0, q1), q2), q3))| {
+                        let quarters = [q0, q1, q2, q3];
+                        radix4_butterflies(quarters, i * chunk_size, len, &self.twiddles, stride);
+                    });
+            }
+            len /= 4;
+        }
+        values
+            .par_chunks_mut(len)
+            .for_each(|block| block_butterflies(block, n, &self.twiddles));
+    }
+}
+
+/// Applies all remaining layers to a block of values. `n` is the size of the
+/// whole FFT.
+fn block_butterflies<F: DomainCoeff<Fp>, Fp: ark_ff::FftField>(
+    block: &mut [F],
+    n: usize,
+    twiddles: &[Fp],
+) {
+    let mut len = block.len();
+    while len >= 4 {
+        let stride = n / len;
+        for group in block.chunks_exact_mut(len) {
+            let (lo, hi) = group.split_at_mut(len / 2);
+            let (q0, q1) = lo.split_at_mut(len / 4);
+            let (q2, q3) = hi.split_at_mut(len / 4);
+            radix4_butterflies([q0, q1, q2, q3], 0, len, twiddles, stride);
+        }
+        len /= 4;
+    }
+    if len == 2 {
+        for pair in block.chunks_exact_mut(2) {
+            let (u, v) = (pair[0], pair[1]);
+            pair[0] = u + v;
+            pair[1] = u - v;
+        }
+    }
+}
+
+/// Applies two layers of butterflies to groups of `len` values. Value `j` of
+/// each quarter is at index `j0 + j`, `j0 + j + len / 4`, ... of the group.
+fn radix4_butterflies<F: DomainCoeff<Fp>, Fp: ark_ff::FftField>(
+    [q0, q1, q2, q3]: [&mut [F]; 4],
+    j0: usize,
+    len: usize,
+    twiddles: &[Fp],
+    stride: usize,
+) {
+    let quarter = len / 4;
+    for (j, (((a0, a1), a2), a3)) in zip(zip(zip(q0, q1), q2), q3).enumerate() {
+        let j = j0 + j;
+        let w1 = twiddles[j * stride];
+        let w2 = twiddles[2 * j * stride];
+        let w3 = twiddles[(j + quarter) * stride];
+        // first layer over groups of `len` values
+        let y0 = *a0 + *a2;
+        let y1 = *a1 + *a3;
+        let mut y2 = *a0 - *a2;
+        y2 *= w1;
+        let mut y3 = *a1 - *a3;
+        y3 *= w3;
+        // second layer over groups of `len / 2` values
+        *a0 = y0 + y1;
+        *a1 = y0 - y1;
+        *a1 *= w2;
+        *a2 = y2 + y3;
+        *a3 = y2 - y3;
+        *a3 *= w2;
+    }
+}
+
+pub struct GpuFft<'a, F: GpuField + DomainCoeff<F::FftField>>
+where
+    F::FftField: ark_ff::FftField,
+{
+    encoder: FftEncoder<'a, F>,
+}
+
+impl<'a, F: GpuField + DomainCoeff<F::FftField>> GpuFft<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    fn new(encoder: FftEncoder<'a, F>) -> Self {
+        GpuFft { encoder }
+    }
+
+    pub fn encode(&mut self, buffer: &'a mut [F]) {
+        self.encoder.encode(buffer, false);
+    }
+
+    /// Encodes an FFT that outputs evaluations in bit-reversed order
+    pub fn encode_out_of_order(&mut self, buffer: &'a mut [F]) {
+        self.encoder.encode(buffer, true);
+    }
+
+    pub fn execute(self) {
+        self.encoder.execute()
+    }
+}
+
+impl<'a, F: GpuField + DomainCoeff<F::FftField>> From<Radix2EvaluationDomain<F::FftField>>
+    for GpuFft<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    fn from(domain: Radix2EvaluationDomain<F::FftField>) -> Self {
+        let planner = get_planner();
+        planner.plan_fft(domain)
+    }
+}
+
+pub struct GpuIfft<'a, F: GpuField + DomainCoeff<F::FftField>>
+where
+    F::FftField: ark_ff::FftField,
+{
+    encoder: FftEncoder<'a, F>,
+}
+
+impl<'a, F: GpuField + DomainCoeff<F::FftField>> GpuIfft<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    fn new(encoder: FftEncoder<'a, F>) -> Self {
+        GpuIfft { encoder }
+    }
+
+    pub fn encode(&mut self, input: &'a mut [F]) {
+        self.encoder.encode(input, false);
+    }
+
+    pub fn execute(self) {
+        self.encoder.execute()
+    }
+}
+
+impl<'a, F: GpuField + DomainCoeff<F::FftField>> From<Radix2EvaluationDomain<F::FftField>>
+    for GpuIfft<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    fn from(domain: Radix2EvaluationDomain<F::FftField>) -> Self {
+        let planner = get_planner();
+        planner.plan_ifft(domain)
+    }
+}
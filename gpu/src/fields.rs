@@ -41,6 +41,8 @@ pub mod p18446744069414584321 {
         use alloc::string::ToString;
         use ark_ff::BigInt;
         use ark_ff::Field;
+        use ark_ff::Fp2;
+        use ark_ff::Fp2Config;
         use ark_ff::Fp3;
         use ark_ff::Fp3Config;
         use ark_ff::FpConfig;
@@ -215,6 +217,141 @@ pub mod p18446744069414584321 {
                 "p18446744069414584321_fq3".to_string()
             }
         }
+
+        pub struct Fq2Config;
+
+        impl Fp2Config for Fq2Config {
+            type Fp = Fp;
+            const NONRESIDUE: Fp = /* =7 */ ark_ff::Fp(BigInt([30064771065]), PhantomData);
+
+            // `NONRESIDUE^((p^i - 1) / 2)` for `i = 0, 1`
+            const FROBENIUS_COEFF_FP2_C1: &'static [Fp] = &[
+                /* =1 */ ark_ff::Fp(BigInt([4294967295]), PhantomData),
+                /* =-1 */ ark_ff::Fp(BigInt([18446744065119617026]), PhantomData),
+            ];
+        }
+
+        wrap_field!(Fq2; Fp2<Fq2Config>);
+
+        impl MulAssign<&Fp> for Fq2 {
+            fn mul_assign(&mut self, rhs: &Fp) {
+                self.0.mul_assign_by_basefield(rhs)
+            }
+        }
+
+        impl MulAssign<Fp> for Fq2 {
+            fn mul_assign(&mut self, rhs: Fp) {
+                self.0.mul_assign_by_basefield(&rhs)
+            }
+        }
+
+        impl AddAssign<Fp> for Fq2 {
+            fn add_assign(&mut self, rhs: Fp) {
+                *self += Fq2::from(rhs);
+            }
+        }
+
+        impl AddAssign<&Fp> for Fq2 {
+            fn add_assign(&mut self, rhs: &Fp) {
+                *self += Fq2::from(*rhs);
+            }
+        }
+
+        impl core::ops::Add<&Fp> for Fq2 {
+            type Output = Fq2;
+
+            fn add(self, rhs: &Fp) -> Self::Output {
+                self + Fq2::from(*rhs)
+            }
+        }
+
+        impl core::ops::Add<Fp> for Fq2 {
+            type Output = Fq2;
+
+            fn add(self, rhs: Fp) -> Self::Output {
+                self + Fq2::from(rhs)
+            }
+        }
+
+        impl core::ops::SubAssign<Fp> for Fq2 {
+            fn sub_assign(&mut self, rhs: Fp) {
+                *self -= Fq2::from(rhs);
+            }
+        }
+
+        impl core::ops::SubAssign<&Fp> for Fq2 {
+            fn sub_assign(&mut self, rhs: &Fp) {
+                *self -= Fq2::from(*rhs);
+            }
+        }
+
+        impl core::ops::Sub<&Fp> for Fq2 {
+            type Output = Fq2;
+
+            fn sub(self, rhs: &Fp) -> Self::Output {
+                self - Fq2::from(*rhs)
+            }
+        }
+
+        impl core::ops::Sub<Fp> for Fq2 {
+            type Output = Fq2;
+
+            fn sub(self, rhs: Fp) -> Self::Output {
+                self - Fq2::from(rhs)
+            }
+        }
+
+        impl core::ops::Mul<&Fp> for Fq2 {
+            type Output = Fq2;
+
+            fn mul(mut self, rhs: &Fp) -> Self::Output {
+                self.0.mul_assign_by_basefield(rhs);
+                self
+            }
+        }
+
+        impl core::ops::Mul<Fp> for Fq2 {
+            type Output = Fq2;
+
+            fn mul(mut self, rhs: Fp) -> Self::Output {
+                self.0.mul_assign_by_basefield(&rhs);
+                self
+            }
+        }
+
+        impl From<Fp> for Fq2 {
+            fn from(value: Fp) -> Self {
+                Fq2(Fp2::<Fq2Config>::from_base_prime_field(value))
+            }
+        }
+
+        impl GpuFrom<Fp> for Fq2 {}
+
+        impl GpuFrom<Fq2> for Fq2 {}
+
+        impl GpuMul<Fp> for Fq2 {}
+
+        impl GpuMul<&Fp> for Fq2 {}
+
+        impl GpuMul<Fq2> for Fq2 {}
+
+        impl GpuMul<&Fq2> for Fq2 {}
+
+        impl GpuAdd<Fp> for Fq2 {}
+
+        impl GpuAdd<&Fp> for Fq2 {}
+
+        impl GpuAdd<Fq2> for Fq2 {}
+
+        impl GpuAdd<&Fq2> for Fq2 {}
+
+        impl GpuField for Fq2 {
+            type FftField = Fp;
+
+            fn field_name() -> String {
+                "p18446744069414584321_fq2".to_string()
+            }
+        }
     }
 }
 
@@ -245,7 +382,6 @@ pub mod p36185027886661312136973227830950701056231072153315966999730920561358720
         /// Field has modulus `2^251 + 17 * 2^192 + 1`
         pub type Fp = Fp256<MontBackend<FpMontConfig, 4>>;
 
-        // TODO: GPU field implementation
         impl GpuField for Fp {
             type FftField = Self;
 
@@ -268,3 +404,101 @@ pub mod p36185027886661312136973227830950701056231072153315966999730920561358720
         impl GpuFftField for Fp {}
     }
 }
+
+// BabyBear field
+pub mod p2013265921 {
+    pub const MODULUS: &str = "2013265921";
+
+    #[cfg(feature = "arkworks")]
+    pub mod ark {
+        use crate::GpuAdd;
+        use crate::GpuFftField;
+        use crate::GpuField;
+        use crate::GpuFrom;
+        use crate::GpuMul;
+        use alloc::string::ToString;
+        use ark_ff::Fp64;
+        use ark_ff::MontBackend;
+        use ark_ff::MontConfig;
+        use ark_std::string::String;
+
+        #[derive(MontConfig)]
+        #[modulus = "2013265921"]
+        #[generator = "31"]
+        pub struct FpMontConfig;
+
+        /// The 31-bit BabyBear prime field
+        /// Field has modulus `2^31 - 2^27 + 1` and two-adicity 27
+        pub type Fp = Fp64<MontBackend<FpMontConfig, 1>>;
+
+        impl GpuField for Fp {
+            type FftField = Self;
+
+            fn field_name() -> String {
+                "p2013265921_fp".to_string()
+            }
+        }
+
+        impl GpuFrom<Fp> for Fp {}
+
+        impl GpuMul<Fp> for Fp {}
+
+        impl GpuMul<&Fp> for Fp {}
+
+        impl GpuAdd<Fp> for Fp {}
+
+        impl GpuAdd<&Fp> for Fp {}
+
+        impl GpuFftField for Fp {}
+    }
+}
+
+// Mersenne31 field
+pub mod p2147483647 {
+    pub const MODULUS: &str = "2147483647";
+
+    #[cfg(feature = "arkworks")]
+    pub mod ark {
+        use crate::GpuAdd;
+        use crate::GpuFftField;
+        use crate::GpuField;
+        use crate::GpuFrom;
+        use crate::GpuMul;
+        use alloc::string::ToString;
+        use ark_ff::Fp64;
+        use ark_ff::MontBackend;
+        use ark_ff::MontConfig;
+        use ark_std::string::String;
+
+        #[derive(MontConfig)]
+        #[modulus = "2147483647"]
+        #[generator = "7"]
+        pub struct FpMontConfig;
+
+        /// The 31-bit Mersenne prime field
+        /// Field has modulus `2^31 - 1` and two-adicity 1 so radix-2 FFTs are
+        /// only possible over domains of size 2. The circle group over this
+        /// field has order `2^31` which is used for FFTs instead.
+        pub type Fp = Fp64<MontBackend<FpMontConfig, 1>>;
+
+        impl GpuField for Fp {
+            type FftField = Self;
+
+            fn field_name() -> String {
+                "p2147483647_fp".to_string()
+            }
+        }
+
+        impl GpuFrom<Fp> for Fp {}
+
+        impl GpuMul<Fp> for Fp {}
+
+        impl GpuMul<&Fp> for Fp {}
+
+        impl GpuAdd<Fp> for Fp {}
+
+        impl GpuAdd<&Fp> for Fp {}
+
+        impl GpuFftField for Fp {}
+    }
+}
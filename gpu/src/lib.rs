@@ -7,12 +7,17 @@ extern crate alloc;
 
 #[macro_use]
 pub mod macros;
+#[cfg(all(feature = "arkworks", feature = "parallel"))]
+pub mod cpu_plan;
 pub mod fields;
+pub mod occupancy;
 pub mod plan;
 pub mod prelude;
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub mod stage;
 pub mod utils;
+#[cfg(feature = "wgpu")]
+pub mod wgpu_plan;
 
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use metal;
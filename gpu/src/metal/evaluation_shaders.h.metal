@@ -4,6 +4,7 @@
 #include <metal_stdlib>
 #include "felt_u128.h.metal"
 #include "felt_u64.h.metal"
+#include "felt_u32.h.metal"
 #include "permute.h.metal"
 using namespace metal;
 
@@ -167,6 +168,139 @@ FillBuff(device FieldT *dst [[ buffer(0) ]],
     dst[global_tid] = value;
 }
 
+// Writes a column of N values into column `col` of a row-major matrix with
+// `num_cols` columns. Transposing a matrix encodes this once per column.
+template<typename FieldT> kernel void
+TransposeColumn(device FieldT *dst [[ buffer(0) ]],
+        constant FieldT *src [[ buffer(1) ]],
+        constant unsigned &num_cols [[ buffer(2) ]],
+        constant unsigned &col [[ buffer(3) ]],
+        unsigned global_tid [[ thread_position_in_grid ]]) {
+    dst[global_tid * num_cols + col] = src[global_tid];
+}
+
+// Opcodes of constraint programs. Must match `Opcode` in `src/codegen.rs`.
+enum ConstraintOp : unsigned {
+    LOAD_X = 0,
+    LOAD_FP = 1,
+    LOAD_FQ = 2,
+    LOAD_BASE = 3,
+    LOAD_EXTENSION = 4,
+    NEG_FP = 5,
+    NEG_FQ = 6,
+    ADD_FP = 7,
+    ADD_FQ = 8,
+    ADD_FQ_FP = 9,
+    MUL_FP = 10,
+    MUL_FQ = 11,
+    MUL_FQ_FP = 12,
+    DIV_FP = 13,
+    DIV_FQ_FP = 14,
+    POW_FP = 15,
+    POW_FQ = 16,
+    RETURN_FP = 17,
+    RETURN_FQ = 18,
+};
+
+// Must match `MAX_REGISTERS` in `src/codegen.rs`
+#define MAX_CONSTRAINT_REGISTERS 32
+
+// Interprets a constraint program for a single row of the constraint
+// evaluation domain. Instructions are of the form `[op, dst, a, b]` and
+// address separate base field and extension field registers. Trace columns
+// are passed as arrays of device addresses. Row offsets of trace reads are
+// stored in `b` in two's complement.
+template<typename FpT, typename FqT> kernel void
+EvalConstraintProgram(device FqT *result [[ buffer(0) ]],
+        device const FpT *x [[ buffer(1) ]],
+        device const FpT* constant *base [[ buffer(2) ]],
+        device const FqT* constant *extension [[ buffer(3) ]],
+        constant uint4 *program [[ buffer(4) ]],
+        constant unsigned &program_len [[ buffer(5) ]],
+        constant FpT *fp_constants [[ buffer(6) ]],
+        constant FqT *fq_constants [[ buffer(7) ]],
+        constant unsigned &step [[ buffer(8) ]],
+        unsigned i [[ thread_position_in_grid ]],
+        unsigned n [[ threads_per_grid ]]) {
+    const unsigned mask = n - 1;
+    FpT fp[MAX_CONSTRAINT_REGISTERS];
+    FqT fq[MAX_CONSTRAINT_REGISTERS];
+    for (unsigned pc = 0; pc < program_len; pc++) {
+        const uint4 instruction = program[pc];
+        const unsigned dst = instruction.y;
+        const unsigned a = instruction.z;
+        const unsigned b = instruction.w;
+        switch (instruction.x) {
+            case LOAD_X:
+                fp[dst] = x[i];
+                break;
+            case LOAD_FP:
+                fp[dst] = fp_constants[a];
+                break;
+            case LOAD_FQ:
+                fq[dst] = fq_constants[a];
+                break;
+            case LOAD_BASE:
+                fp[dst] = base[a][(i + b * step) & mask];
+                break;
+            case LOAD_EXTENSION:
+                fq[dst] = extension[a][(i + b * step) & mask];
+                break;
+            case NEG_FP:
+                fp[dst] = fp[a].neg();
+                break;
+            case NEG_FQ:
+                fq[dst] = fq[a].neg();
+                break;
+            case ADD_FP:
+                fp[dst] = fp[a] + fp[b];
+                break;
+            case ADD_FQ:
+                fq[dst] = fq[a] + fq[b];
+                break;
+            case ADD_FQ_FP:
+                fq[dst] = fq[a] + fp[b];
+                break;
+            case MUL_FP:
+                fp[dst] = fp[a] * fp[b];
+                break;
+            case MUL_FQ:
+                fq[dst] = fq[a] * fq[b];
+                break;
+            case MUL_FQ_FP:
+                fq[dst] = fq[a] * fp[b];
+                break;
+            case DIV_FP: {
+                FpT divisor = fp[b];
+                fp[dst] = fp[a] * divisor.inverse();
+                break;
+            }
+            case DIV_FQ_FP: {
+                FpT divisor = fp[b];
+                fq[dst] = fq[a] * divisor.inverse();
+                break;
+            }
+            // `pow` modifies the value it's called on so operate on a copy
+            case POW_FP: {
+                FpT v = fp[a];
+                fp[dst] = v.pow(b);
+                break;
+            }
+            case POW_FQ: {
+                FqT v = fq[a];
+                fq[dst] = v.pow(b);
+                break;
+            }
+            case RETURN_FP:
+                result[i] = FqT(fp[a]);
+                return;
+            case RETURN_FQ:
+                result[i] = fq[a];
+                return;
+        }
+    }
+}
+
 // ===========================================================
 // Evaluation for Fp=18446744069414584321
 template [[ host_name("add_assign_LHS_p18446744069414584321_fp_RHS_p18446744069414584321_fp") ]] kernel void
@@ -180,6 +314,11 @@ ConvertInto<p18446744069414584321::Fq3, p18446744069414584321::Fp>(
         device p18446744069414584321::Fq3*,
         constant p18446744069414584321::Fp*,
         unsigned);
+template [[ host_name("convert_into_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+ConvertInto<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp*,
+        unsigned);
 template [[ host_name("convert_into_LHS_p18446744069414584321_fp_RHS_p18446744069414584321_fp") ]] kernel void
 ConvertInto<p18446744069414584321::Fp>(
         device p18446744069414584321::Fp*,
@@ -268,6 +407,26 @@ FillBuff<p18446744069414584321::Fp>(
         device p18446744069414584321::Fp*,
         constant p18446744069414584321::Fp&,
         unsigned);
+template [[ host_name("transpose_column_p18446744069414584321_fp") ]] kernel void
+TransposeColumn<p18446744069414584321::Fp>(
+        device p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("eval_constraint_program_BASE_p18446744069414584321_fp_EXTENSION_p18446744069414584321_fp") ]] kernel void
+EvalConstraintProgram<p18446744069414584321::Fp, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fp*,
+        device const p18446744069414584321::Fp*,
+        device const p18446744069414584321::Fp* constant*,
+        device const p18446744069414584321::Fp* constant*,
+        constant uint4*,
+        constant unsigned&,
+        constant p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned,
+        unsigned);
 // ===========================================================
 // Evaluation for cubic extension of Fp=18446744069414584321
 template [[ host_name("add_assign_LHS_p18446744069414584321_fq3_RHS_p18446744069414584321_fq3") ]] kernel void
@@ -347,6 +506,13 @@ FillBuff<p18446744069414584321::Fq3>(
         device p18446744069414584321::Fq3*,
         constant p18446744069414584321::Fq3&,
         unsigned);
+template [[ host_name("transpose_column_p18446744069414584321_fq3") ]] kernel void
+TransposeColumn<p18446744069414584321::Fq3>(
+        device p18446744069414584321::Fq3*,
+        constant p18446744069414584321::Fq3*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
 template [[ host_name("exp_in_place_p18446744069414584321_fq3") ]] kernel void
 ExpInPlace<p18446744069414584321::Fq3>(
         device p18446744069414584321::Fq3*,
@@ -390,19 +556,19 @@ MulPow<p18446744069414584321::Fq3, p18446744069414584321::Fp>(
         constant unsigned&,
         constant unsigned&,
         unsigned);
-// template [[ host_name("inverse_in_place_p18446744069414584321_fq3") ]] kernel void
-// InverseInPlace<p18446744069414584321::Fq3>(
-//         device p18446744069414584321::Fq3*,
-//         unsigned);
+template [[ host_name("inverse_in_place_p18446744069414584321_fq3") ]] kernel void
+InverseInPlace<p18446744069414584321::Fq3>(
+        device p18446744069414584321::Fq3*,
+        unsigned);
 template [[ host_name("neg_in_place_p18446744069414584321_fq3") ]] kernel void
 NegInPlace<p18446744069414584321::Fq3>(
         device p18446744069414584321::Fq3*,
         unsigned);
-// template [[ host_name("inverse_into_p18446744069414584321_fq3") ]] kernel void
-// InverseInto<p18446744069414584321::Fq3>(
-//         device p18446744069414584321::Fq3*,
-//         constant p18446744069414584321::Fq3*,
-//         unsigned);
+template [[ host_name("inverse_into_p18446744069414584321_fq3") ]] kernel void
+InverseInto<p18446744069414584321::Fq3>(
+        device p18446744069414584321::Fq3*,
+        constant p18446744069414584321::Fq3*,
+        unsigned);
 template [[ host_name("neg_into_p18446744069414584321_fq3") ]] kernel void
 NegInto<p18446744069414584321::Fq3>(
         device p18446744069414584321::Fq3*,
@@ -414,6 +580,185 @@ ExpInto<p18446744069414584321::Fq3>(
         constant p18446744069414584321::Fq3*,
         constant unsigned&,
         unsigned);
+template [[ host_name("eval_constraint_program_BASE_p18446744069414584321_fp_EXTENSION_p18446744069414584321_fq3") ]] kernel void
+EvalConstraintProgram<p18446744069414584321::Fp, p18446744069414584321::Fq3>(
+        device p18446744069414584321::Fq3*,
+        device const p18446744069414584321::Fp*,
+        device const p18446744069414584321::Fp* constant*,
+        device const p18446744069414584321::Fq3* constant*,
+        constant uint4*,
+        constant unsigned&,
+        constant p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fq3*,
+        constant unsigned&,
+        unsigned,
+        unsigned);
+// ===========================================================
+// Evaluation for quadratic extension of Fp=18446744069414584321
+template [[ host_name("add_assign_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fq2") ]] kernel void
+AddAssign<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("add_assign_const_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fq2") ]] kernel void
+AddAssignConst<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2&,
+        unsigned);
+template [[ host_name("add_assign_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+AddAssign<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("add_assign_const_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+AddAssignConst<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp&,
+        unsigned);
+template [[ host_name("add_into_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fq2") ]] kernel void
+AddInto<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("add_into_const_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fq2") ]] kernel void
+AddIntoConst<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2&,
+        unsigned);
+template [[ host_name("add_into_const_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+AddIntoConst<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp&,
+        unsigned);
+template [[ host_name("add_into_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+AddInto<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_assign_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fq2") ]] kernel void
+MulAssign<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_assign_const_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fq2") ]] kernel void
+MulAssignConst<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2&,
+        unsigned);
+template [[ host_name("mul_into_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fq2") ]] kernel void
+MulInto<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_into_const_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fq2") ]] kernel void
+MulIntoConst<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2&,
+        unsigned);
+template [[ host_name("fill_buff_p18446744069414584321_fq2") ]] kernel void
+FillBuff<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2&,
+        unsigned);
+template [[ host_name("transpose_column_p18446744069414584321_fq2") ]] kernel void
+TransposeColumn<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("exp_in_place_p18446744069414584321_fq2") ]] kernel void
+ExpInPlace<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_assign_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+MulAssign<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_assign_const_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+MulAssignConst<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp&,
+        unsigned);
+template [[ host_name("mul_into_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+MulInto<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_into_const_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+MulIntoConst<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp&,
+        unsigned);
+template [[ host_name("mul_pow_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fq2") ]] kernel void
+MulPow<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_pow_LHS_p18446744069414584321_fq2_RHS_p18446744069414584321_fp") ]] kernel void
+MulPow<p18446744069414584321::Fq2, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("inverse_in_place_p18446744069414584321_fq2") ]] kernel void
+InverseInPlace<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        unsigned);
+template [[ host_name("neg_in_place_p18446744069414584321_fq2") ]] kernel void
+NegInPlace<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        unsigned);
+template [[ host_name("inverse_into_p18446744069414584321_fq2") ]] kernel void
+InverseInto<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        unsigned);
+template [[ host_name("neg_into_p18446744069414584321_fq2") ]] kernel void
+NegInto<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        unsigned);
+template [[ host_name("exp_into_p18446744069414584321_fq2") ]] kernel void
+ExpInto<p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        constant p18446744069414584321::Fq2*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("eval_constraint_program_BASE_p18446744069414584321_fp_EXTENSION_p18446744069414584321_fq2") ]] kernel void
+EvalConstraintProgram<p18446744069414584321::Fp, p18446744069414584321::Fq2>(
+        device p18446744069414584321::Fq2*,
+        device const p18446744069414584321::Fp*,
+        device const p18446744069414584321::Fp* constant*,
+        device const p18446744069414584321::Fq2* constant*,
+        constant uint4*,
+        constant unsigned&,
+        constant p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fq2*,
+        constant unsigned&,
+        unsigned,
+        unsigned);
 // ===========================================================
 // Evaluation for Fp=3618502788666131213697322783095070105623107215331596699973092056135872020481
 template [[ host_name("add_assign_LHS_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp_RHS_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
@@ -510,6 +855,260 @@ FillBuff<p3618502788666131213697322783095070105623107215331596699973092056135872
         device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
         constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp&,
         unsigned);
+template [[ host_name("transpose_column_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
+TransposeColumn<p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp>(
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("eval_constraint_program_BASE_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp_EXTENSION_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
+EvalConstraintProgram<p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp, p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp>(
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        device const p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        device const p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp* constant*,
+        device const p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp* constant*,
+        constant uint4*,
+        constant unsigned&,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant unsigned&,
+        unsigned,
+        unsigned);
+// ===========================================================
+// Evaluation for Fp=2013265921
+// BabyBear field
+template [[ host_name("add_assign_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+AddAssign<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("convert_into_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+ConvertInto<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        unsigned);
+template [[ host_name("add_assign_const_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+AddAssignConst<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp&,
+        unsigned);
+template [[ host_name("mul_assign_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+MulAssign<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_assign_const_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+MulAssignConst<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp&,
+        unsigned);
+template [[ host_name("add_into_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+AddInto<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("add_into_const_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+AddIntoConst<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant p2013265921::Fp&,
+        unsigned);
+template [[ host_name("mul_into_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+MulInto<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_into_const_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+MulIntoConst<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant p2013265921::Fp&,
+        unsigned);
+template [[ host_name("mul_pow_LHS_p2013265921_fp_RHS_p2013265921_fp") ]] kernel void
+MulPow<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("inverse_in_place_p2013265921_fp") ]] kernel void
+InverseInPlace<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        unsigned);
+template [[ host_name("exp_in_place_p2013265921_fp") ]] kernel void
+ExpInPlace<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("neg_in_place_p2013265921_fp") ]] kernel void
+NegInPlace<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        unsigned);
+template [[ host_name("inverse_into_p2013265921_fp") ]] kernel void
+InverseInto<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        unsigned);
+template [[ host_name("exp_into_p2013265921_fp") ]] kernel void
+ExpInto<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("neg_into_p2013265921_fp") ]] kernel void
+NegInto<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        unsigned);
+template [[ host_name("fill_buff_p2013265921_fp") ]] kernel void
+FillBuff<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp&,
+        unsigned);
+template [[ host_name("transpose_column_p2013265921_fp") ]] kernel void
+TransposeColumn<p2013265921::Fp>(
+        device p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("eval_constraint_program_BASE_p2013265921_fp_EXTENSION_p2013265921_fp") ]] kernel void
+EvalConstraintProgram<p2013265921::Fp, p2013265921::Fp>(
+        device p2013265921::Fp*,
+        device const p2013265921::Fp*,
+        device const p2013265921::Fp* constant*,
+        device const p2013265921::Fp* constant*,
+        constant uint4*,
+        constant unsigned&,
+        constant p2013265921::Fp*,
+        constant p2013265921::Fp*,
+        constant unsigned&,
+        unsigned,
+        unsigned);
+// ===========================================================
+// Evaluation for Fp=2147483647
+// Mersenne31 field
+template [[ host_name("add_assign_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+AddAssign<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("convert_into_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+ConvertInto<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        unsigned);
+template [[ host_name("add_assign_const_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+AddAssignConst<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp&,
+        unsigned);
+template [[ host_name("mul_assign_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+MulAssign<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_assign_const_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+MulAssignConst<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp&,
+        unsigned);
+template [[ host_name("add_into_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+AddInto<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("add_into_const_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+AddIntoConst<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant p2147483647::Fp&,
+        unsigned);
+template [[ host_name("mul_into_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+MulInto<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("mul_into_const_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+MulIntoConst<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant p2147483647::Fp&,
+        unsigned);
+template [[ host_name("mul_pow_LHS_p2147483647_fp_RHS_p2147483647_fp") ]] kernel void
+MulPow<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("inverse_in_place_p2147483647_fp") ]] kernel void
+InverseInPlace<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        unsigned);
+template [[ host_name("exp_in_place_p2147483647_fp") ]] kernel void
+ExpInPlace<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("neg_in_place_p2147483647_fp") ]] kernel void
+NegInPlace<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        unsigned);
+template [[ host_name("inverse_into_p2147483647_fp") ]] kernel void
+InverseInto<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        unsigned);
+template [[ host_name("exp_into_p2147483647_fp") ]] kernel void
+ExpInto<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("neg_into_p2147483647_fp") ]] kernel void
+NegInto<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        unsigned);
+template [[ host_name("fill_buff_p2147483647_fp") ]] kernel void
+FillBuff<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp&,
+        unsigned);
+template [[ host_name("transpose_column_p2147483647_fp") ]] kernel void
+TransposeColumn<p2147483647::Fp>(
+        device p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant unsigned&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("eval_constraint_program_BASE_p2147483647_fp_EXTENSION_p2147483647_fp") ]] kernel void
+EvalConstraintProgram<p2147483647::Fp, p2147483647::Fp>(
+        device p2147483647::Fp*,
+        device const p2147483647::Fp*,
+        device const p2147483647::Fp* constant*,
+        device const p2147483647::Fp* constant*,
+        constant uint4*,
+        constant unsigned&,
+        constant p2147483647::Fp*,
+        constant p2147483647::Fp*,
+        constant unsigned&,
+        unsigned,
+        unsigned);
 // ===========================================================
 
 #endif /* evaluation_shaders_h */
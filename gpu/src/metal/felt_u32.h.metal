@@ -0,0 +1,204 @@
+#ifndef felt_u32_h
+#define felt_u32_h
+
+// Fields that use prime 2013265921 (BabyBear)
+namespace p2013265921
+{
+
+    // Prime field
+    // Elements are stored in 64 bits to match the memory layout of arkworks
+    // i.e. Montgomery form with auxiliary modulus `R = 2^64`
+    class Fp
+    {
+    public:
+        Fp() = default;
+        constexpr Fp(unsigned long v) : inner(v) {}
+
+        constexpr Fp operator+(const Fp rhs) const
+        {
+            return Fp(add(inner, rhs.inner));
+        }
+
+        constexpr Fp operator-(const Fp rhs) const
+        {
+            return Fp(sub(inner, rhs.inner));
+        }
+
+        constexpr explicit operator unsigned long() const
+        {
+            return inner;
+        }
+
+        Fp operator*(const Fp rhs) const
+        {
+            return Fp(mul(inner, rhs.inner));
+        }
+
+        Fp pow(unsigned exp)
+        {
+            Fp res = ONE;
+
+            while (exp > 0)
+            {
+                if (exp & 1)
+                {
+                    res = res * *this;
+                }
+                exp >>= 1;
+                *this = *this * *this;
+            }
+
+            return res;
+        }
+
+        Fp inverse()
+        {
+            // Fermat's little theorem i.e. `a^(p - 2) = a^(-1)`
+            Fp base = *this;
+            return base.pow(N - 2);
+        }
+
+        Fp neg()
+        {
+            return Fp(sub(0, inner));
+        }
+
+        // 1 in Montgomery representation
+        constexpr static const constant unsigned long ONE = 1172168163;
+
+    private:
+        unsigned long inner;
+
+        // Field modulus `p = 2^31 - 2^27 + 1`
+        constexpr static const constant unsigned N = 2013265921;
+
+        // `-p^(-1) mod 2^64` used for Montgomery reduction
+        constexpr static const constant unsigned long N_PRIME = 14393504411089371135;
+
+        inline unsigned long add(const unsigned long a, const unsigned long b) const
+        {
+            // both operands are less than `p < 2^31` so this can't overflow
+            unsigned long sum = a + b;
+            return sum >= N ? sum - N : sum;
+        }
+
+        inline unsigned long sub(const unsigned long a, const unsigned long b) const
+        {
+            return a >= b ? a - b : a + N - b;
+        }
+
+        inline unsigned long mul(const unsigned long lhs, const unsigned long rhs) const
+        {
+            // Montgomery reduction of `t = lhs * rhs < p^2 < 2^62`. The low
+            // 64 bits of `t + m * p` are zero by construction so the carry
+            // out of them is set iff `t` is non-zero.
+            unsigned long t = lhs * rhs;
+            unsigned long m = t * N_PRIME;
+            unsigned long r = metal::mulhi(m, (unsigned long) N) + (t != 0);
+            return r >= N ? r - N : r;
+        }
+    };
+
+}
+
+// Fields that use prime 2147483647 (Mersenne31)
+namespace p2147483647
+{
+
+    // Prime field
+    // Elements are stored in 64 bits to match the memory layout of arkworks
+    // i.e. Montgomery form with auxiliary modulus `R = 2^64`
+    class Fp
+    {
+    public:
+        Fp() = default;
+        constexpr Fp(unsigned long v) : inner(v) {}
+
+        constexpr Fp operator+(const Fp rhs) const
+        {
+            return Fp(add(inner, rhs.inner));
+        }
+
+        constexpr Fp operator-(const Fp rhs) const
+        {
+            return Fp(sub(inner, rhs.inner));
+        }
+
+        constexpr explicit operator unsigned long() const
+        {
+            return inner;
+        }
+
+        Fp operator*(const Fp rhs) const
+        {
+            return Fp(mul(inner, rhs.inner));
+        }
+
+        Fp pow(unsigned exp)
+        {
+            Fp res = ONE;
+
+            while (exp > 0)
+            {
+                if (exp & 1)
+                {
+                    res = res * *this;
+                }
+                exp >>= 1;
+                *this = *this * *this;
+            }
+
+            return res;
+        }
+
+        Fp inverse()
+        {
+            // Fermat's little theorem i.e. `a^(p - 2) = a^(-1)`
+            Fp base = *this;
+            return base.pow(N - 2);
+        }
+
+        Fp neg()
+        {
+            return Fp(sub(0, inner));
+        }
+
+        // 1 in Montgomery representation
+        constexpr static const constant unsigned long ONE = 4;
+
+    private:
+        unsigned long inner;
+
+        // Field modulus `p = 2^31 - 1`
+        constexpr static const constant unsigned N = 2147483647;
+
+        // `-p^(-1) mod 2^64` used for Montgomery reduction
+        constexpr static const constant unsigned long N_PRIME = 4611686020574871553;
+
+        inline unsigned long add(const unsigned long a, const unsigned long b) const
+        {
+            // both operands are less than `p < 2^31` so this can't overflow
+            unsigned long sum = a + b;
+            return sum >= N ? sum - N : sum;
+        }
+
+        inline unsigned long sub(const unsigned long a, const unsigned long b) const
+        {
+            return a >= b ? a - b : a + N - b;
+        }
+
+        inline unsigned long mul(const unsigned long lhs, const unsigned long rhs) const
+        {
+            // Montgomery reduction of `t = lhs * rhs < p^2 < 2^62`. The low
+            // 64 bits of `t + m * p` are zero by construction so the carry
+            // out of them is set iff `t` is non-zero.
+            unsigned long t = lhs * rhs;
+            unsigned long m = t * N_PRIME;
+            unsigned long r = metal::mulhi(m, (unsigned long) N) + (t != 0);
+            return r >= N ? r - N : r;
+        }
+    };
+
+}
+
+#endif /* felt_u32_h */
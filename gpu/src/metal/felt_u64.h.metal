@@ -264,10 +264,25 @@ namespace p18446744069414584321
             return Fq3(Fp(0) - c0, Fp(0) - c1, Fp(0) - c2);
         }
 
-        // Fq3 inverse() 
-        // {
-        //     // TODO
-        // }
+        // adapted from arkworks
+        Fq3 inverse()
+        {
+            // From "High-Speed Software Implementation of the Optimal Ate Pairing
+            // over Barreto-Naehrig Curves"; Algorithm 17
+            Fp nonresidue = Fp(NONREDIDUE);
+            Fp t0 = c0 * c0;
+            Fp t1 = c1 * c1;
+            Fp t2 = c2 * c2;
+            Fp t3 = c0 * c1;
+            Fp t4 = c0 * c2;
+            Fp t5 = c1 * c2;
+            Fp s0 = t0 - t5 * nonresidue;
+            Fp s1 = t2 * nonresidue - t3;
+            Fp s2 = t1 - t4;
+            Fp a3 = (c2 * s1 + c1 * s2) * nonresidue;
+            Fp t6 = (c0 * s0 + a3).inverse();
+            return Fq3(t6 * s0, t6 * s1, t6 * s2);
+        }
 
     private:
         Fp c0, c1, c2;
@@ -278,6 +293,83 @@ namespace p18446744069414584321
         constexpr static const constant unsigned long NONREDIDUE = /* =2 */ 8589934590;
     };
 
+    // Quadratic extension field over irreducible polynomial x^2 - 7
+    // adapted from arkworks
+    class Fq2
+    {
+    public:
+        Fq2() = default;
+        constexpr Fq2(Fp c) : c0(c), c1(Fp(0)) {}
+        constexpr Fq2(Fp c0, Fp c1) : c0(c0), c1(c1) {}
+
+        constexpr Fq2 operator+(const Fp rhs) const
+        {
+            return Fq2(c0 + rhs, c1);
+        }
+
+        constexpr Fq2 operator+(const Fq2 rhs) const
+        {
+            return Fq2(c0 + rhs.c0, c1 + rhs.c1);
+        }
+
+        constexpr Fq2 operator-(const Fq2 rhs) const
+        {
+            return Fq2(c0 - rhs.c0, c1 - rhs.c1);
+        }
+
+        Fq2 operator*(const Fq2 rhs) const
+        {
+            // Karatsuba multiplication
+            Fp v0 = c0 * rhs.c0;
+            Fp v1 = c1 * rhs.c1;
+            return Fq2(
+                /* =c0 */ v0 + v1 * Fp(NONREDIDUE),
+                /* =c1 */ (c0 + c1) * (rhs.c0 + rhs.c1) - v0 - v1
+            );
+        }
+
+        Fq2 operator*(const Fp rhs) const
+        {
+            return Fq2(c0 * rhs, c1 * rhs);
+        }
+
+        Fq2 pow(unsigned exp)
+        {
+            Fq2 res = Fq2(Fp(Fp::ONE), Fp(0));
+
+            while (exp > 0) {
+                if (exp & 1) {
+                    res = res * *this;
+                }
+                exp >>= 1;
+                *this = *this * *this;
+            }
+
+            return res;
+        }
+
+        Fq2 neg()
+        {
+            return Fq2(Fp(0) - c0, Fp(0) - c1);
+        }
+
+        Fq2 inverse()
+        {
+            // `1 / (c0 + c1 * X) = (c0 - c1 * X) / (c0^2 - NONRESIDUE * c1^2)`
+            Fp norm = c0 * c0 - c1 * c1 * Fp(NONREDIDUE);
+            Fp norm_inv = norm.inverse();
+            return Fq2(c0 * norm_inv, (Fp(0) - c1) * norm_inv);
+        }
+
+    private:
+        Fp c0, c1;
+
+        // Quadratic non-residue used to construct the extension field in montgomery
+        // representation. That is, `NONRESIDUE` is such that the quadratic polynomial
+        // `f(X) = X^2 - NONRESIDUE` in Fp\[X\] is irreducible in `Fp`.
+        constexpr static const constant unsigned long NONREDIDUE = /* =7 */ 30064771065;
+    };
+
 }
 
 #endif /* felt_u64_h */
@@ -379,6 +379,105 @@ Rpo256GenMerkleNodesRow(device Rpo256Digest *nodes [[ buffer(0) ]],
     nodes[(N >> round) + global_id] = *((threadgroup Rpo256Digest*) (shared + local_state_offset + CAPACITY));
 }
 
+// Poseidon parameters (see ministark's poseidon module)
+constant const unsigned POSEIDON_WIDTH = 12;
+constant const unsigned POSEIDON_RATE = 8;
+constant const unsigned POSEIDON_NUM_FULL_ROUNDS = 8;
+constant const unsigned POSEIDON_NUM_PARTIAL_ROUNDS = 22;
+constant const unsigned POSEIDON_NUM_ROUNDS = POSEIDON_NUM_FULL_ROUNDS + POSEIDON_NUM_PARTIAL_ROUNDS;
+
+// Duplex sponge state of a Poseidon transcript
+struct PoseidonTranscriptState {
+    Fp state[POSEIDON_WIDTH];
+    // number of elements absorbed into the rate since the last permutation
+    unsigned num_absorbed;
+    // number of unread elements in the rate that can be squeezed
+    unsigned num_squeezable;
+};
+
+// Round constants, MDS matrix and the integers `0..=RATE` in Montgomery form.
+// These are derived on the host.
+struct PoseidonParams {
+    Fp round_constants[POSEIDON_NUM_ROUNDS * POSEIDON_WIDTH];
+    Fp mds[POSEIDON_WIDTH * POSEIDON_WIDTH];
+    Fp small_ints[POSEIDON_RATE + 1];
+};
+
+void poseidon_permute(thread Fp *state, constant PoseidonParams &params) {
+    const unsigned half_full_rounds = POSEIDON_NUM_FULL_ROUNDS / 2;
+    for (unsigned round = 0; round < POSEIDON_NUM_ROUNDS; round++) {
+        for (unsigned i = 0; i < POSEIDON_WIDTH; i++) {
+            state[i] = state[i] + params.round_constants[round * POSEIDON_WIDTH + i];
+        }
+
+        bool is_partial_round = round >= half_full_rounds && round < half_full_rounds + POSEIDON_NUM_PARTIAL_ROUNDS;
+        if (is_partial_round) {
+            state[0] = state[0].pow7();
+        } else {
+            for (unsigned i = 0; i < POSEIDON_WIDTH; i++) {
+                state[i] = state[i].pow7();
+            }
+        }
+
+        Fp prev[POSEIDON_WIDTH];
+        for (unsigned i = 0; i < POSEIDON_WIDTH; i++) {
+            prev[i] = state[i];
+        }
+        for (unsigned i = 0; i < POSEIDON_WIDTH; i++) {
+            Fp acc = Fp(0);
+            for (unsigned j = 0; j < POSEIDON_WIDTH; j++) {
+                acc = acc + prev[j] * params.mds[i * POSEIDON_WIDTH + j];
+            }
+            state[i] = acc;
+        }
+    }
+}
+
+// Permutes the transcript after adding the number of absorbed elements to
+// the capacity. Mirrors `PoseidonPublicCoin::permute`.
+void poseidon_transcript_permute(thread PoseidonTranscriptState &transcript, constant PoseidonParams &params) {
+    transcript.state[POSEIDON_RATE] = transcript.state[POSEIDON_RATE] + params.small_ints[transcript.num_absorbed];
+    poseidon_permute(transcript.state, params);
+    transcript.num_absorbed = 0;
+}
+
+// Absorbs `num_elements` elements into a Poseidon transcript and then squeezes
+// `num_squeezed` elements out of it. Runs on a single thread. Keeping the
+// transcript in device memory lets commitments computed on the GPU be
+// absorbed without reading them back to the host.
+[[ host_name("poseidon_transcript_p18446744069414584321_fp") ]] kernel void
+PoseidonTranscript(device PoseidonTranscriptState *transcript_state [[ buffer(0) ]],
+        constant PoseidonParams &params [[ buffer(1) ]],
+        constant Fp *elements [[ buffer(2) ]],
+        constant unsigned &num_elements [[ buffer(3) ]],
+        device Fp *squeezed [[ buffer(4) ]],
+        constant unsigned &num_squeezed [[ buffer(5) ]]) {
+    PoseidonTranscriptState transcript = *transcript_state;
+
+    for (unsigned i = 0; i < num_elements; i++) {
+        if (transcript.num_absorbed == POSEIDON_RATE) {
+            poseidon_transcript_permute(transcript, params);
+        }
+        transcript.state[transcript.num_absorbed] = elements[i];
+        transcript.num_absorbed++;
+    }
+    // previously squeezed outputs must not be reused after absorbing
+    if (num_elements != 0) {
+        transcript.num_squeezable = 0;
+    }
+
+    for (unsigned i = 0; i < num_squeezed; i++) {
+        if (transcript.num_squeezable == 0) {
+            poseidon_transcript_permute(transcript, params);
+            transcript.num_squeezable = POSEIDON_RATE;
+        }
+        squeezed[i] = transcript.state[POSEIDON_RATE - transcript.num_squeezable];
+        transcript.num_squeezable--;
+    }
+
+    *transcript_state = transcript;
+}
+
 }
 
 #endif /* hash_shaders_h */
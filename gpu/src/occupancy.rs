@@ -0,0 +1,128 @@
+//! Threadgroup size selection based on GPU occupancy.
+//!
+//! Element-wise kernels used to dispatch with the largest threadgroup a
+//! pipeline supports. On small buffers this leaves most GPU cores idle, and
+//! the best size differs between a base M-series chip and an Ultra-class part.
+//! Sizes here are picked per device, kernel and buffer length.
+
+/// Number of threadgroups each GPU core should get so that memory latency can
+/// be hidden by switching between resident threadgroups.
+pub const THREADGROUPS_PER_CORE: usize = 4;
+
+/// Estimates the number of GPU cores from a Metal device name, e.g. "Apple M2
+/// Max". Metal does not expose the core count, so this uses the smallest
+/// configuration of each chip. Unknown devices get a conservative estimate.
+pub fn estimated_gpu_cores(device_name: &str) -> usize {
+    const DEFAULT_CORES: usize = 8;
+    let mut words = device_name
+        .split_whitespace()
+        .skip_while(|w| !w.starts_with('M'));
+    let Some(chip) = words.next() else {
+        return DEFAULT_CORES;
+    };
+    let base = match chip {
+        "M1" => 7,
+        "M2" => 8,
+        "M3" | "M4" => 10,
+        _ => return DEFAULT_CORES,
+    };
+    match words.next() {
+        Some("Pro") => base * 2,
+        Some("Max") => base * 4,
+        Some("Ultra") => base * 8,
+        _ => base,
+    }
+}
+
+/// Returns the number of threads per threadgroup for a 1D dispatch of `n`
+/// threads. The result is a multiple of `execution_width` (the SIMD group
+/// width) no larger than `max_threads`. It is reduced from `max_threads` until
+/// there are enough threadgroups to occupy all `num_cores` cores.
+pub fn threadgroup_size(
+    n: usize,
+    max_threads: usize,
+    execution_width: usize,
+    num_cores: usize,
+) -> usize {
+    let execution_width = execution_width.max(1);
+    let mut size = (max_threads / execution_width).max(1) * execution_width;
+    let target_threadgroups = num_cores * THREADGROUPS_PER_CORE;
+    while size > execution_width && n.div_ceil(size) < target_threadgroups {
+        size = (size / 2 / execution_width).max(1) * execution_width;
+    }
+    size
+}
+
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+mod metal_occupancy {
+    use super::estimated_gpu_cores;
+    use super::threadgroup_size;
+    use alloc::collections::BTreeMap;
+    use once_cell::sync::Lazy;
+    use std::sync::Mutex;
+
+    /// Cache key: device registry ID, pipeline thread limit, SIMD width and
+    /// `log2(n)`. The thread limit depends on the kernel's register usage so
+    /// it distinguishes kernels that need different sizes.
+    type Key = (u64, u64, u64, u32);
+
+    static THREADGROUP_SIZES: Lazy<Mutex<BTreeMap<Key, u64>>> =
+        Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+    /// Returns the threadgroup dimensions to dispatch `n` threads of
+    /// `pipeline` on `device`. Results are cached per device.
+    pub fn threadgroup_dim(
+        device: &metal::DeviceRef,
+        pipeline: &metal::ComputePipelineStateRef,
+        n: usize,
+    ) -> metal::MTLSize {
+        let max_threads = pipeline.max_total_threads_per_threadgroup();
+        let execution_width = pipeline.thread_execution_width();
+        let key = (
+            device.registry_id(),
+            max_threads,
+            execution_width,
+            n.max(1).ilog2(),
+        );
+        let mut cache = THREADGROUP_SIZES.lock().unwrap();
+        let size = *cache.entry(key).or_insert_with(|| {
+            // bucket by log2(n) so each entry holds for every length in the bucket
+            let n = 1 << key.3;
+            threadgroup_size(
+                n,
+                max_threads as usize,
+                execution_width as usize,
+                estimated_gpu_cores(device.name()),
+            ) as u64
+        });
+        metal::MTLSize::new(size, 1, 1)
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+pub use metal_occupancy::threadgroup_dim;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_cores_from_device_name() {
+        assert_eq!(7, estimated_gpu_cores("Apple M1"));
+        assert_eq!(32, estimated_gpu_cores("Apple M2 Max"));
+        assert_eq!(56, estimated_gpu_cores("Apple M1 Ultra"));
+        assert_eq!(8, estimated_gpu_cores("AMD Radeon Pro 5500M"));
+    }
+
+    #[test]
+    fn threadgroup_size_fills_all_cores() {
+        // large buffers use the pipeline's maximum
+        assert_eq!(1024, threadgroup_size(1 << 20, 1024, 32, 64));
+        // small buffers are spread across all cores
+        assert_eq!(64, threadgroup_size(1 << 14, 1024, 32, 64));
+        // never below the SIMD group width
+        assert_eq!(32, threadgroup_size(16, 1024, 32, 64));
+        // always a multiple of the SIMD group width
+        assert_eq!(0, threadgroup_size(1 << 12, 1000, 32, 8) % 32);
+    }
+}
@@ -16,7 +16,7 @@ use crate::utils::buffer_no_copy;
 use crate::utils::is_page_aligned;
 use crate::utils::page_aligned_uninit_vector;
 use crate::GpuField;
-use alloc::rc::Rc;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 #[cfg(feature = "arkworks")]
 use ark_ff::One;
@@ -27,13 +27,15 @@ use ark_poly::Radix2EvaluationDomain;
 use metal::CommandBufferRef;
 use once_cell::sync::Lazy;
 
-const LIBRARY_DATA: &[u8] = include_bytes!("metal/shaders.metallib");
+// compiled from the shader sources by the build script
+const LIBRARY_DATA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/shaders.metallib"));
 
 pub struct GpuRpo256ColumnMajor<'a, F: GpuField> {
     n: usize,
     _requires_padding: bool,
     stage: Rpo256AbsorbColumnsStage<F>,
     state: Vec<&'a [F]>,
+    command_queue: &'static metal::CommandQueue,
     command_buffer: Option<&'a CommandBufferRef>,
 }
 
@@ -46,6 +48,7 @@ impl<'a, F: GpuField + From<u32> + Copy> GpuRpo256ColumnMajor<'a, F> {
             _requires_padding: requires_padding,
             stage: Rpo256AbsorbColumnsStage::new(&get_planner().library, n, requires_padding),
             state: Vec::new(),
+            command_queue: get_planner().command_queue(),
             command_buffer: None,
         }
     }
@@ -54,7 +57,7 @@ impl<'a, F: GpuField + From<u32> + Copy> GpuRpo256ColumnMajor<'a, F> {
         assert!(is_page_aligned(col));
         self.state.push(col);
         if self.state.len() % Self::RATE == 0 {
-            let command_buffer = get_planner().command_queue.new_command_buffer();
+            let command_buffer = self.command_queue.new_command_buffer();
             #[cfg(debug_assertions)]
             command_buffer.set_label("rpo update columns");
             let state = &core::mem::take(&mut self.state)[0..8];
@@ -96,8 +99,7 @@ impl<'a, F: GpuField + From<u32> + Copy> GpuRpo256ColumnMajor<'a, F> {
             }
         }
 
-        let planner = get_planner();
-        let command_buffer = planner.command_queue.new_command_buffer();
+        let command_buffer = self.command_queue.new_command_buffer();
         let state = &self.state[0..8];
         self.stage.encode(command_buffer, state.try_into().unwrap());
         command_buffer.commit();
@@ -109,6 +111,7 @@ impl<'a, F: GpuField + From<u32> + Copy> GpuRpo256ColumnMajor<'a, F> {
 pub struct GpuRpo256RowMajor<'a, F: GpuField> {
     _requires_padding: bool,
     stage: Rpo256AbsorbRowsStage<F>,
+    command_queue: &'static metal::CommandQueue,
     command_buffer: Option<&'a CommandBufferRef>,
 }
 
@@ -119,14 +122,14 @@ impl<'a, F: GpuField + From<u32> + Copy> GpuRpo256RowMajor<'a, F> {
         Self {
             _requires_padding: requires_padding,
             stage: Rpo256AbsorbRowsStage::new(&get_planner().library, n, requires_padding),
+            command_queue: get_planner().command_queue(),
             command_buffer: None,
         }
     }
 
     pub fn update(&mut self, rows: &'a [[F; 8]]) {
         assert!(is_page_aligned(rows));
-        let planner = get_planner();
-        let command_buffer = planner.command_queue.new_command_buffer();
+        let command_buffer = self.command_queue.new_command_buffer();
         #[cfg(debug_assertions)]
         command_buffer.set_label("rpo update rows");
         self.stage.encode(command_buffer, rows);
@@ -160,7 +163,7 @@ pub async fn gen_rpo_merkle_tree<F: GpuField + From<u32> + Copy>(leaves: &[[F; 4
     let first_row_stage = Rpo256GenMerkleNodesFirstRowStage::<F>::new(&planner.library, num_leaves);
     let nth_row_stage = Rpo256GenMerkleNodesRowStage::<F>::new(&planner.library, num_leaves);
 
-    let command_buffer = planner.command_queue.new_command_buffer();
+    let command_buffer = planner.command_queue().new_command_buffer();
     #[cfg(debug_assertions)]
     command_buffer.set_label("rpo merkle tree");
     first_row_stage.encode(command_buffer, &leaves_buffer, &nodes_buffer);
@@ -182,20 +185,52 @@ enum FftDirection {
     Inverse,
 }
 
+/// Twiddles of an FFT over domains of size `n`. Twiddles don't depend on the
+/// domain offset or on the field of the values being transformed so they're
+/// shared by the plans of all domains of the same size.
 #[cfg(feature = "arkworks")]
-pub struct FftEncoder<'a, F: GpuField + ark_ff::Field>
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+struct Twiddles<F: ark_ff::FftField> {
+    direction: FftDirection,
+    n: usize,
+    // buffer references this memory
+    // field exists to keep the memory around
+    _twiddles: Vec<F>,
+    buffer: metal::Buffer,
+}
+
+/// Twiddles and pipelines needed to run an FFT over a specific domain. Plans
+/// are immutable so they can be built ahead of time and shared between FFTs.
+#[cfg(feature = "arkworks")]
+struct FftPlan<F: GpuField + ark_ff::Field>
 where
     F::FftField: ark_ff::FftField,
 {
     n: usize,
-    command_queue: Rc<metal::CommandQueue>,
-    // twiddles_buffer references this memory
-    // field exists to keep the memory around
-    _twiddles: Vec<F::FftField>,
-    twiddles_buffer: metal::Buffer,
+    twiddles: Arc<Twiddles<F::FftField>>,
     scale_and_normalize_stage: Option<ScaleAndNormalizeGpuStage<F, F::FftField>>,
     butterfly_stages: Vec<FftGpuStage<F>>,
     bit_reverse_stage: BitReverseGpuStage<F>,
+}
+
+/// An [`FftPlan`] cached by a [`Planner`]
+#[cfg(all(feature = "arkworks", feature = "std"))]
+struct CachedFftPlan<F: GpuField + ark_ff::Field>
+where
+    F::FftField: ark_ff::FftField,
+{
+    direction: FftDirection,
+    domain: Radix2EvaluationDomain<F::FftField>,
+    plan: Arc<FftPlan<F>>,
+}
+
+#[cfg(feature = "arkworks")]
+pub struct FftEncoder<'a, F: GpuField + ark_ff::Field>
+where
+    F::FftField: ark_ff::FftField,
+{
+    plan: Arc<FftPlan<F>>,
+    command_queue: metal::CommandQueue,
     command_buffer: &'a metal::CommandBufferRef,
 }
 
@@ -209,18 +244,23 @@ where
     F::FftField: ark_ff::FftField,
 {
     fn encode_butterfly_stages(&self, input_buffer: &mut metal::Buffer) {
-        for stage in &self.butterfly_stages {
-            stage.encode(self.command_buffer, input_buffer, &self.twiddles_buffer);
+        for stage in &self.plan.butterfly_stages {
+            stage.encode(
+                self.command_buffer,
+                input_buffer,
+                &self.plan.twiddles.buffer,
+            );
         }
     }
 
     fn encode_bit_reverse_stage(&self, input_buffer: &mut metal::Buffer) {
-        self.bit_reverse_stage
+        self.plan
+            .bit_reverse_stage
             .encode(self.command_buffer, input_buffer);
     }
 
     fn encode_scale_stage(&self, input_buffer: &mut metal::Buffer) {
-        if let Some(scale_stage) = &self.scale_and_normalize_stage {
+        if let Some(scale_stage) = &self.plan.scale_and_normalize_stage {
             scale_stage.encode(self.command_buffer, input_buffer);
         }
     }
@@ -254,7 +294,7 @@ where
     pub fn encode(&mut self, buffer: &mut [F]) {
         assert!(is_page_aligned(buffer));
         let encoder = &self.encoder;
-        assert_eq!(encoder.n, buffer.len());
+        assert_eq!(encoder.plan.n, buffer.len());
         let mut input_buffer =
             crate::utils::buffer_mut_no_copy(encoder.command_queue.device(), buffer);
         encoder.encode_scale_stage(&mut input_buffer);
@@ -262,6 +302,18 @@ where
         encoder.encode_bit_reverse_stage(&mut input_buffer);
     }
 
+    /// Encodes an FFT that outputs evaluations in bit-reversed order. Skips
+    /// the bit reversal stage of [`Self::encode`].
+    pub fn encode_out_of_order(&mut self, buffer: &mut [F]) {
+        assert!(is_page_aligned(buffer));
+        let encoder = &self.encoder;
+        assert_eq!(encoder.plan.n, buffer.len());
+        let mut input_buffer =
+            crate::utils::buffer_mut_no_copy(encoder.command_queue.device(), buffer);
+        encoder.encode_scale_stage(&mut input_buffer);
+        encoder.encode_butterfly_stages(&mut input_buffer);
+    }
+
     pub fn execute(self) {
         self.encoder.execute()
     }
@@ -300,7 +352,7 @@ where
     pub fn encode(&mut self, input: &mut [F]) {
         assert!(is_page_aligned(input));
         let encoder = &self.encoder;
-        assert_eq!(encoder.n, input.len());
+        assert_eq!(encoder.plan.n, input.len());
         let mut input_buffer =
             crate::utils::buffer_mut_no_copy(encoder.command_queue.device(), input);
         encoder.encode_butterfly_stages(&mut input_buffer);
@@ -324,15 +376,106 @@ where
     }
 }
 
-static PLANNER: Lazy<Planner> = Lazy::new(Planner::default);
+/// One planner per Metal device. The system default device comes first.
+static PLANNER: Lazy<Vec<Planner>> = Lazy::new(|| {
+    let default_device = metal::Device::system_default().expect("no device found");
+    let default_id = default_device.registry_id();
+    let mut planners = alloc::vec![Planner::new(&default_device)];
+    for device in metal::Device::all() {
+        if device.registry_id() != default_id {
+            planners.push(Planner::new(&device));
+        }
+    }
+    planners
+});
 
+/// Returns the planner for the system default device
 pub fn get_planner() -> &'static Planner {
+    &PLANNER[0]
+}
+
+/// Returns a planner for every available device
+pub fn get_planners() -> &'static [Planner] {
     &PLANNER
 }
 
+/// Builds the FFT and IFFT plans for `domains` on every device (see
+/// [`Planner::reserve`])
+#[cfg(all(feature = "arkworks", feature = "std"))]
+pub fn reserve<F: GpuField + ark_ff::Field>(domains: &[Radix2EvaluationDomain<F::FftField>])
+where
+    F::FftField: ark_ff::FftField,
+{
+    for planner in get_planners() {
+        planner.reserve::<F>(domains);
+    }
+}
+
+/// Splits `items` into contiguous batches and processes each batch on a
+/// different device. Results are returned in the same order as the batches.
+/// Batches are processed concurrently if the `std` feature is enabled.
+pub fn split_across_planners<T: Send, R: Send>(
+    items: &mut [T],
+    f: impl Fn(&Planner, &mut [T]) -> R + Sync,
+) -> Vec<R> {
+    let planners = get_planners();
+    if planners.len() == 1 || items.len() <= 1 {
+        return alloc::vec![f(&planners[0], items)];
+    }
+
+    let batch_size = items.len().div_ceil(planners.len());
+    let batches = items.chunks_mut(batch_size).zip(planners);
+
+    #[cfg(not(feature = "std"))]
+    return batches.map(|(batch, planner)| f(planner, batch)).collect();
+
+    #[cfg(feature = "std")]
+    return std::thread::scope(|s| {
+        let f = &f;
+        let handles = batches
+            .map(|(batch, planner)| s.spawn(move || f(planner, batch)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+}
+
+/// Index of the calling thread. Indices are handed out in the order threads
+/// first ask for one.
+#[cfg(feature = "std")]
+fn thread_index() -> usize {
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering;
+    static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+    std::thread_local! {
+        static INDEX: usize = NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+    }
+    INDEX.with(|index| *index)
+}
+
+#[cfg(not(feature = "std"))]
+fn thread_index() -> usize {
+    0
+}
+
 pub struct Planner {
     pub library: metal::Library,
-    pub command_queue: Rc<metal::CommandQueue>,
+    /// Command queues over the planner's device. Threads are spread across
+    /// the queues so submissions from different threads don't contend on a
+    /// single queue. See [`Planner::command_queue`].
+    command_queues: Vec<metal::CommandQueue>,
+    /// FFT plans keyed by field, direction and domain (size and offset). Plans
+    /// are built the first time an FFT over a domain is planned (or by
+    /// [`Planner::reserve`]) and reused by later FFTs within and across
+    /// proofs. Each item is a [`CachedFftPlan`] for some field.
+    #[cfg(all(feature = "arkworks", feature = "std"))]
+    fft_plans: std::sync::Mutex<Vec<alloc::boxed::Box<dyn core::any::Any>>>,
+    /// Twiddles keyed by field, direction and domain size. Each item is an
+    /// `Arc<Twiddles<F>>` for some field.
+    #[cfg(all(feature = "arkworks", feature = "std"))]
+    twiddles: std::sync::Mutex<Vec<alloc::boxed::Box<dyn core::any::Any>>>,
 }
 
 // TODO: unsafe
@@ -342,13 +485,155 @@ unsafe impl Sync for Planner {}
 impl Planner {
     pub fn new(device: &metal::DeviceRef) -> Self {
         let library = device.new_library_with_data(LIBRARY_DATA).unwrap();
-        let command_queue = Rc::new(device.new_command_queue());
+        #[cfg(feature = "std")]
+        let num_queues = std::thread::available_parallelism().map_or(1, |n| n.get());
+        #[cfg(not(feature = "std"))]
+        let num_queues = 1;
+        let command_queues = (0..num_queues)
+            .map(|_| device.new_command_queue())
+            .collect();
         Self {
             library,
-            command_queue,
+            command_queues,
+            #[cfg(all(feature = "arkworks", feature = "std"))]
+            fft_plans: std::sync::Mutex::new(Vec::new()),
+            #[cfg(all(feature = "arkworks", feature = "std"))]
+            twiddles: std::sync::Mutex::new(Vec::new()),
         }
     }
 
+    /// Returns true if the library has a kernel named `name`. Libraries that
+    /// weren't compiled from the current shader sources (see the build
+    /// script) can be missing kernels so callers can fall back to the CPU.
+    pub fn has_kernel(&self, name: &str) -> bool {
+        self.library.function_names().iter().any(|n| n == name)
+    }
+
+    /// Returns true if the library has the kernels needed to plan FFTs over
+    /// `F`. FFTs should fall back to the CPU otherwise.
+    #[cfg(feature = "arkworks")]
+    pub fn supports_fft<F: GpuField>(&self) -> bool {
+        let field = F::field_name();
+        let fft_field = F::FftField::field_name();
+        [
+            crate::stage::fft_kernel_name::<F>(FftVariant::Single),
+            crate::stage::fft_kernel_name::<F>(FftVariant::Multiple),
+            alloc::format!("bit_reverse_{field}"),
+            alloc::format!("mul_assign_LHS_{field}_RHS_{fft_field}"),
+        ]
+        .iter()
+        .all(|name| self.has_kernel(name))
+    }
+
+    /// Returns the command queue of the calling thread. Each thread is assigned
+    /// a queue the first time it calls this method so command buffers
+    /// committed by a thread execute in the order they were committed.
+    pub fn command_queue(&self) -> &metal::CommandQueue {
+        &self.command_queues[thread_index() % self.command_queues.len()]
+    }
+
+    /// Builds the FFT and IFFT plans (twiddles and pipelines) for `domains`
+    /// ahead of time. FFTs planned over these domains later on reuse them
+    /// rather than building them on the critical path. Intended to be called
+    /// during application startup with the domains of expected trace lengths.
+    #[cfg(all(feature = "arkworks", feature = "std"))]
+    pub fn reserve<F: GpuField + ark_ff::Field>(
+        &self,
+        domains: &[Radix2EvaluationDomain<F::FftField>],
+    ) where
+        F::FftField: ark_ff::FftField,
+    {
+        for &domain in domains {
+            if domain.size() < GpuFft::<F>::MIN_SIZE {
+                continue;
+            }
+            for direction in [FftDirection::Forward, FftDirection::Inverse] {
+                self.fft_plan::<F>(direction, domain);
+            }
+        }
+    }
+
+    /// Drops all cached FFT plans and twiddles to free their memory. FFTs that
+    /// have already been planned keep their plans alive.
+    #[cfg(all(feature = "arkworks", feature = "std"))]
+    pub fn clear_fft_plans(&self) {
+        self.fft_plans.lock().unwrap().clear();
+        self.twiddles.lock().unwrap().clear();
+    }
+
+    /// Returns the plan for an FFT over `domain`. Plans are cached so they're
+    /// only built the first time they're needed.
+    #[cfg(feature = "arkworks")]
+    fn fft_plan<F: GpuField + ark_ff::Field>(
+        &self,
+        direction: FftDirection,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> Arc<FftPlan<F>>
+    where
+        F::FftField: ark_ff::FftField,
+    {
+        #[cfg(feature = "std")]
+        {
+            let mut plans = self.fft_plans.lock().unwrap();
+            let cached = plans.iter().find_map(|cached| {
+                let cached = cached.downcast_ref::<CachedFftPlan<F>>()?;
+                (cached.direction == direction && cached.domain == domain)
+                    .then(|| Arc::clone(&cached.plan))
+            });
+            if let Some(plan) = cached {
+                return plan;
+            }
+            let plan = Arc::new(self.create_fft_plan::<F>(direction, domain));
+            plans.push(alloc::boxed::Box::new(CachedFftPlan {
+                direction,
+                domain,
+                plan: Arc::clone(&plan),
+            }));
+            plan
+        }
+        #[cfg(not(feature = "std"))]
+        Arc::new(self.create_fft_plan::<F>(direction, domain))
+    }
+
+    /// Returns the twiddles for an FFT over domains of size `n` with root of
+    /// unity `root`. Twiddles are cached like plans.
+    #[cfg(feature = "arkworks")]
+    fn twiddles<F: ark_ff::FftField>(
+        &self,
+        direction: FftDirection,
+        n: usize,
+        root: F,
+    ) -> Arc<Twiddles<F>> {
+        let create_twiddles = || {
+            let mut twiddles = unsafe { page_aligned_uninit_vector(n / 2) };
+            crate::utils::fill_twiddles(&mut twiddles, root);
+            crate::utils::bit_reverse(&mut twiddles);
+            let buffer = crate::utils::buffer_no_copy(self.library.device(), &twiddles);
+            Arc::new(Twiddles {
+                direction,
+                n,
+                _twiddles: twiddles,
+                buffer,
+            })
+        };
+        #[cfg(feature = "std")]
+        {
+            let mut cache = self.twiddles.lock().unwrap();
+            let cached = cache.iter().find_map(|cached| {
+                let cached = cached.downcast_ref::<Arc<Twiddles<F>>>()?;
+                (cached.direction == direction && cached.n == n).then(|| Arc::clone(cached))
+            });
+            if let Some(twiddles) = cached {
+                return twiddles;
+            }
+            let twiddles = create_twiddles();
+            cache.push(alloc::boxed::Box::new(Arc::clone(&twiddles)));
+            twiddles
+        }
+        #[cfg(not(feature = "std"))]
+        create_twiddles()
+    }
+
     #[cfg(feature = "arkworks")]
     pub fn plan_fft<F: GpuField + ark_ff::Field>(
         &self,
@@ -380,22 +665,34 @@ impl Planner {
         direction: FftDirection,
         domain: Radix2EvaluationDomain<F::FftField>,
     ) -> FftEncoder<F>
+    where
+        F::FftField: ark_ff::FftField,
+    {
+        FftEncoder {
+            plan: self.fft_plan(direction, domain),
+            command_queue: self.command_queue().to_owned(),
+            command_buffer: self.command_queue().new_command_buffer(),
+        }
+    }
+
+    #[cfg(feature = "arkworks")]
+    fn create_fft_plan<F: GpuField + ark_ff::Field>(
+        &self,
+        direction: FftDirection,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> FftPlan<F>
     where
         F::FftField: ark_ff::FftField,
     {
         let n = domain.size();
-        let device = self.command_queue.device();
+        let device = self.library.device();
 
         let root = match direction {
             FftDirection::Forward => domain.group_gen,
             FftDirection::Inverse => domain.group_gen_inv,
         };
 
-        // generate twiddles buffer
-        let mut _twiddles = unsafe { page_aligned_uninit_vector(n / 2) };
-        crate::utils::fill_twiddles(&mut _twiddles, root);
-        crate::utils::bit_reverse(&mut _twiddles);
-        let twiddles_buffer = crate::utils::buffer_no_copy(device, &_twiddles);
+        let twiddles = self.twiddles(direction, n, root);
 
         // in-place FFT requires a bit reversal
         let bit_reverse_stage = BitReverseGpuStage::new(&self.library, n);
@@ -407,7 +704,7 @@ impl Planner {
             } else {
                 Some(ScaleAndNormalizeGpuStage::new(
                     &self.library,
-                    &self.command_queue,
+                    self.command_queue(),
                     n,
                     domain.offset,
                     F::FftField::one(),
@@ -416,7 +713,7 @@ impl Planner {
         } else {
             Some(ScaleAndNormalizeGpuStage::new(
                 &self.library,
-                &self.command_queue,
+                self.command_queue(),
                 n,
                 domain.offset_inv,
                 domain.size_inv,
@@ -449,15 +746,12 @@ impl Planner {
             }
         }
 
-        FftEncoder {
+        FftPlan {
             n,
-            _twiddles,
-            twiddles_buffer,
+            twiddles,
             scale_and_normalize_stage,
             butterfly_stages,
             bit_reverse_stage,
-            command_queue: Rc::clone(&self.command_queue),
-            command_buffer: self.command_queue.new_command_buffer(),
         }
     }
 }
@@ -1,5 +1,9 @@
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use crate::plan::get_planner;
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+pub use crate::plan::get_planners;
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+pub use crate::plan::split_across_planners;
 #[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "arkworks"))]
 pub use crate::plan::GpuFft;
 #[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "arkworks"))]
@@ -7,13 +11,34 @@ pub use crate::plan::GpuIfft;
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use crate::stage::AddAssignStage;
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+pub use crate::stage::EvalConstraintProgramStage;
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use crate::stage::FillBuffStage;
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use crate::stage::MulPowStage;
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+pub use crate::stage::TransposeColumnStage;
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use crate::utils::buffer_mut_no_copy;
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use crate::utils::buffer_no_copy;
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use crate::utils::page_aligned_uninit_vector;
+#[cfg(all(
+    feature = "wgpu",
+    not(all(target_arch = "aarch64", target_os = "macos"))
+))]
+pub use crate::wgpu_plan::get_planner;
+#[cfg(all(
+    feature = "wgpu",
+    feature = "arkworks",
+    not(all(target_arch = "aarch64", target_os = "macos"))
+))]
+pub use crate::wgpu_plan::GpuFft;
+#[cfg(all(
+    feature = "wgpu",
+    feature = "arkworks",
+    not(all(target_arch = "aarch64", target_os = "macos"))
+))]
+pub use crate::wgpu_plan::GpuIfft;
 pub use crate::GpuField;
@@ -1,5 +1,6 @@
 #![cfg(all(target_arch = "aarch64", target_os = "macos"))]
 use super::GpuField;
+use crate::occupancy::threadgroup_dim;
 use crate::plan::get_planner;
 use crate::prelude::buffer_mut_no_copy;
 use crate::utils::buffer_no_copy;
@@ -23,7 +24,7 @@ pub enum FftVariant {
 }
 
 /// GPU FFT kernel name as declared at the bottom of `fft.metal`
-fn fft_kernel_name<F: GpuField>(variant: FftVariant) -> String {
+pub(crate) fn fft_kernel_name<F: GpuField>(variant: FftVariant) -> String {
     alloc::format!(
         "fft_{}_{}",
         match variant {
@@ -137,8 +138,7 @@ impl<LhsF: GpuField + GpuMul<RhsF>, RhsF: GpuField> MulIntoStage<LhsF, RhsF> {
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         MulIntoStage {
@@ -198,8 +198,7 @@ impl<LhsF: GpuField + GpuMul<RhsF>, RhsF: GpuField> MulAssignStage<LhsF, RhsF> {
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         MulAssignStage {
@@ -305,8 +304,7 @@ impl<F: GpuField> BitReverseGpuStage<F> {
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         BitReverseGpuStage {
@@ -356,8 +354,7 @@ impl<LhsF: GpuField + GpuMul<RhsF>, RhsF: GpuField> MulPowStage<LhsF, RhsF> {
             .unwrap();
 
         // TODO: remove
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         MulPowStage {
@@ -419,8 +416,7 @@ impl<LhsF: GpuField + GpuAdd<RhsF>, RhsF: GpuField> AddAssignStage<LhsF, RhsF> {
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         AddAssignStage {
@@ -483,8 +479,7 @@ impl<LhsF: GpuField + GpuAdd<RhsF>, RhsF: GpuField> AddIntoStage<LhsF, RhsF> {
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         AddIntoStage {
@@ -545,8 +540,7 @@ impl<LhsF: GpuField + GpuAdd<RhsF>, RhsF: GpuField> AddIntoConstStage<LhsF, RhsF
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         AddIntoConstStage {
@@ -603,8 +597,7 @@ impl<LhsF: GpuField + GpuFrom<RhsF>, RhsF: GpuField> ConvertIntoStage<LhsF, RhsF
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         ConvertIntoStage {
@@ -659,8 +652,7 @@ impl<LhsF: GpuField + GpuAdd<RhsF>, RhsF: GpuField> AddAssignConstStage<LhsF, Rh
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         AddAssignConstStage {
@@ -716,8 +708,7 @@ impl<LhsF: GpuField + GpuMul<RhsF>, RhsF: GpuField> MulIntoConstStage<LhsF, RhsF
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         MulIntoConstStage {
@@ -774,8 +765,7 @@ impl<LhsF: GpuField + GpuMul<RhsF>, RhsF: GpuField> MulAssignConstStage<LhsF, Rh
             .new_compute_pipeline_state_with_function(&func)
             .unwrap();
 
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         MulAssignConstStage {
@@ -827,8 +817,7 @@ impl<F: GpuField> InverseInPlaceStage<F> {
             .unwrap();
 
         let n = n as u32;
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         InverseInPlaceStage {
@@ -871,8 +860,7 @@ impl<F: GpuField> NegInPlaceStage<F> {
             .unwrap();
 
         let n = n as u32;
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         NegInPlaceStage {
@@ -915,8 +903,7 @@ impl<F: GpuField> NegIntoStage<F> {
             .unwrap();
 
         let n = n as u32;
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         NegIntoStage {
@@ -965,8 +952,7 @@ impl<F: GpuField> InverseIntoStage<F> {
             .unwrap();
 
         let n = n as u32;
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         InverseIntoStage {
@@ -1015,8 +1001,7 @@ impl<F: GpuField> ExpIntoStage<F> {
             .unwrap();
 
         let n = n as u32;
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         ExpIntoStage {
@@ -1072,8 +1057,7 @@ impl<F: GpuField> ExpInPlaceStage<F> {
             .unwrap();
 
         let n = n as u32;
-        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
-        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         ExpInPlaceStage {
@@ -1127,7 +1111,7 @@ impl<F: GpuField> FillBuffStage<F> {
             .unwrap();
 
         let n = n as u32;
-        let threadgroup_dim = metal::MTLSize::new(1024, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
         FillBuffStage {
@@ -1154,6 +1138,153 @@ impl<F: GpuField> FillBuffStage<F> {
     }
 }
 
+/// Writes columns into a row-major buffer. Transposes a matrix when encoded
+/// once for each of its columns.
+pub struct TransposeColumnStage<F> {
+    pipeline: metal::ComputePipelineState,
+    threadgroup_dim: metal::MTLSize,
+    grid_dim: metal::MTLSize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: GpuField> TransposeColumnStage<F> {
+    /// Stage for columns of `n` rows
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `library` doesn't have a transpose kernel for the
+    /// field e.g. if the library was compiled before the kernel was added.
+    pub fn new(library: &metal::LibraryRef, n: usize) -> Result<Self, String> {
+        // Create the compute pipeline
+        let func = library.get_function(
+            &alloc::format!("transpose_column_{}", F::field_name()),
+            None,
+        )?;
+        let pipeline = library
+            .device()
+            .new_compute_pipeline_state_with_function(&func)?;
+
+        let n = n as u32;
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
+        let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
+
+        Ok(TransposeColumnStage {
+            threadgroup_dim,
+            pipeline,
+            grid_dim,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Writes `src` into column `col` of `dst` which holds a row-major matrix
+    /// with `num_cols` columns
+    pub fn encode(
+        &self,
+        command_buffer: &metal::CommandBufferRef,
+        dst_buffer: &metal::BufferRef,
+        src_buffer: &metal::BufferRef,
+        num_cols: usize,
+        col: usize,
+    ) {
+        let num_cols = u32::try_from(num_cols).unwrap();
+        let col = u32::try_from(col).unwrap();
+        let command_encoder = command_buffer
+            .compute_command_encoder_with_dispatch_type(metal::MTLDispatchType::Concurrent);
+        command_encoder.set_compute_pipeline_state(&self.pipeline);
+        command_encoder.set_buffer(0, Some(dst_buffer), 0);
+        command_encoder.set_buffer(1, Some(src_buffer), 0);
+        command_encoder.set_bytes(2, size_of::<u32>().try_into().unwrap(), void_ptr(&num_cols));
+        command_encoder.set_bytes(3, size_of::<u32>().try_into().unwrap(), void_ptr(&col));
+        command_encoder.dispatch_threads(self.grid_dim, self.threadgroup_dim);
+        command_encoder.end_encoding()
+    }
+}
+
+/// Interprets a constraint program over every row of the constraint
+/// evaluation domain. Programs are lowered from composition constraints with
+/// `ministark::codegen::ConstraintProgram`.
+pub struct EvalConstraintProgramStage<Fp, Fq = Fp> {
+    pipeline: metal::ComputePipelineState,
+    threadgroup_dim: metal::MTLSize,
+    grid_dim: metal::MTLSize,
+    _phantom: PhantomData<(Fp, Fq)>,
+}
+
+impl<Fp: GpuField, Fq: GpuField + GpuMul<Fp> + GpuAdd<Fp>> EvalConstraintProgramStage<Fp, Fq> {
+    /// Stage for a constraint evaluation domain of size `n`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `library` doesn't have an interpreter kernel for
+    /// the fields e.g. if the library was compiled before the kernel was added.
+    pub fn new(library: &metal::LibraryRef, n: usize) -> Result<Self, String> {
+        // Create the compute pipeline
+        let kernel_name = alloc::format!(
+            "eval_constraint_program_BASE_{}_EXTENSION_{}",
+            Fp::field_name(),
+            Fq::field_name()
+        );
+        let func = library.get_function(&kernel_name, None)?;
+        let pipeline = library
+            .device()
+            .new_compute_pipeline_state_with_function(&func)?;
+
+        let n = n as u32;
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
+        let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
+
+        Ok(EvalConstraintProgramStage {
+            threadgroup_dim,
+            pipeline,
+            grid_dim,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Evaluates `program` into `result`. `base_addresses` and
+    /// `extension_addresses` hold the device addresses of the `columns`
+    /// buffers. Rows of a column are `step` apart.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode(
+        &self,
+        command_buffer: &metal::CommandBufferRef,
+        result: &metal::BufferRef,
+        x: &metal::BufferRef,
+        base_addresses: &metal::BufferRef,
+        extension_addresses: &metal::BufferRef,
+        columns: &[&metal::BufferRef],
+        program: &metal::BufferRef,
+        program_len: usize,
+        fp_constants: &metal::BufferRef,
+        fq_constants: &metal::BufferRef,
+        step: usize,
+    ) {
+        let program_len = u32::try_from(program_len).unwrap();
+        let step = u32::try_from(step).unwrap();
+        let command_encoder = command_buffer.new_compute_command_encoder();
+        command_encoder.set_compute_pipeline_state(&self.pipeline);
+        command_encoder.set_buffer(0, Some(result), 0);
+        command_encoder.set_buffer(1, Some(x), 0);
+        command_encoder.set_buffer(2, Some(base_addresses), 0);
+        command_encoder.set_buffer(3, Some(extension_addresses), 0);
+        command_encoder.set_buffer(4, Some(program), 0);
+        command_encoder.set_bytes(
+            5,
+            size_of::<u32>().try_into().unwrap(),
+            void_ptr(&program_len),
+        );
+        command_encoder.set_buffer(6, Some(fp_constants), 0);
+        command_encoder.set_buffer(7, Some(fq_constants), 0);
+        command_encoder.set_bytes(8, size_of::<u32>().try_into().unwrap(), void_ptr(&step));
+        // columns are only referenced by address so must be made resident
+        for column in columns {
+            command_encoder.use_resource(column, metal::MTLResourceUsage::Read);
+        }
+        command_encoder.dispatch_threads(self.grid_dim, self.threadgroup_dim);
+        command_encoder.end_encoding()
+    }
+}
+
 pub struct GenerateTwiddlesStage<F> {
     pipeline: metal::ComputePipelineState,
     threadgroup_dim: metal::MTLSize,
@@ -1162,7 +1293,11 @@ pub struct GenerateTwiddlesStage<F> {
 }
 
 impl<F: GpuField> GenerateTwiddlesStage<F> {
-    pub fn new(library: &metal::LibraryRef, n: usize) -> Self {
+    /// # Errors
+    ///
+    /// Returns an error if `library` doesn't have a twiddle kernel for the
+    /// field. Twiddles can be generated on the CPU instead.
+    pub fn new(library: &metal::LibraryRef, n: usize) -> Result<Self, String> {
         // Create the compute pipeline
         let constants = metal::FunctionConstantValues::new();
         let n = n as u32;
@@ -1171,26 +1306,23 @@ impl<F: GpuField> GenerateTwiddlesStage<F> {
             metal::MTLDataType::UInt,
             0,
         );
-        let func = library
-            .get_function(
-                &alloc::format!("generate_twiddles_{}", F::field_name()),
-                Some(constants),
-            )
-            .unwrap();
+        let func = library.get_function(
+            &alloc::format!("generate_twiddles_{}", F::field_name()),
+            Some(constants),
+        )?;
         let pipeline = library
             .device()
-            .new_compute_pipeline_state_with_function(&func)
-            .unwrap();
+            .new_compute_pipeline_state_with_function(&func)?;
 
-        let threadgroup_dim = metal::MTLSize::new(1024, 1, 1);
+        let threadgroup_dim = threadgroup_dim(library.device(), &pipeline, n as usize);
         let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
 
-        GenerateTwiddlesStage {
+        Ok(GenerateTwiddlesStage {
             threadgroup_dim,
             pipeline,
             grid_dim,
             _phantom: PhantomData,
-        }
+        })
     }
 
     pub fn encode(
@@ -1495,3 +1627,60 @@ impl<F: GpuField> Rpo256GenMerkleNodesRowStage<F> {
         command_encoder.end_encoding()
     }
 }
+
+/// Absorbs elements into and squeezes elements out of a Poseidon transcript
+/// whose state is held in a GPU buffer. The transcript state and parameter
+/// layouts are declared in `hash_shaders.h.metal`.
+pub struct PoseidonTranscriptStage<F: GpuField> {
+    pipeline: metal::ComputePipelineState,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: GpuField> PoseidonTranscriptStage<F> {
+    /// # Errors
+    ///
+    /// Returns an error if `library` doesn't have a transcript kernel for the
+    /// field e.g. if the library was compiled before the kernel was added.
+    pub fn new(library: &metal::LibraryRef) -> Result<Self, String> {
+        let kernel_name = alloc::format!("poseidon_transcript_{}", F::field_name());
+        let func = library.get_function(&kernel_name, None)?;
+        let pipeline = library
+            .device()
+            .new_compute_pipeline_state_with_function(&func)?;
+
+        Ok(PoseidonTranscriptStage {
+            pipeline,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Encodes absorbing the first `num_elements` elements of `elements` into
+    /// the transcript followed by squeezing `num_squeezed` elements into
+    /// `squeezed`
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode(
+        &self,
+        command_buffer: &metal::CommandBufferRef,
+        transcript: &metal::BufferRef,
+        params: &metal::BufferRef,
+        elements: &metal::BufferRef,
+        num_elements: u32,
+        squeezed: &metal::BufferRef,
+        num_squeezed: u32,
+    ) {
+        let command_encoder = command_buffer.new_compute_command_encoder();
+        #[cfg(debug_assertions)]
+        command_encoder.set_label("poseidon transcript");
+        command_encoder.set_compute_pipeline_state(&self.pipeline);
+        command_encoder.set_buffer(0, Some(transcript), 0);
+        command_encoder.set_buffer(1, Some(params), 0);
+        command_encoder.set_buffer(2, Some(elements), 0);
+        command_encoder.set_bytes(3, size_of::<u32>() as NSUInteger, void_ptr(&num_elements));
+        command_encoder.set_buffer(4, Some(squeezed), 0);
+        command_encoder.set_bytes(5, size_of::<u32>() as NSUInteger, void_ptr(&num_squeezed));
+        let single_thread = metal::MTLSize::new(1, 1, 1);
+        command_encoder.dispatch_threads(single_thread, single_thread);
+        command_encoder.memory_barrier_with_resources(&[transcript, squeezed]);
+        command_encoder.end_encoding()
+    }
+}
@@ -1,6 +1,10 @@
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
+/// Metal source for arithmetic in the field with prime `2^64 - 2^32 + 1`.
+/// Included by kernels that are compiled at runtime.
+pub const FELT_U64_METAL_SOURCE: &str = include_str!("metal/felt_u64.h.metal");
+
 pub fn bit_reverse_index(n: usize, i: usize) -> usize {
     assert!(n.is_power_of_two());
     i.reverse_bits() >> (usize::BITS - n.ilog2())
@@ -0,0 +1,551 @@
+#![cfg(feature = "wgpu")]
+//! Portable backend built on [wgpu](https://wgpu.rs). Kernels are written in
+//! WGSL so they run on Vulkan, DX12 and Metal. Currently only supports the
+//! 64-bit prime field `p = 2^64 - 2^32 + 1`. Field elements are copied to
+//! the GPU as is so the host must be little-endian.
+use crate::GpuAdd;
+use crate::GpuField;
+use crate::GpuMul;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "arkworks")]
+use ark_ff::One;
+#[cfg(feature = "arkworks")]
+use ark_poly::EvaluationDomain;
+#[cfg(feature = "arkworks")]
+use ark_poly::Radix2EvaluationDomain;
+use core::mem::size_of;
+use core::mem::size_of_val;
+use once_cell::sync::Lazy;
+use wgpu::util::DeviceExt;
+
+/// WGSL source for all kernels
+pub const SHADER_SOURCE: &str = concat!(
+    include_str!("wgsl/felt_u64.wgsl"),
+    include_str!("wgsl/shaders.wgsl")
+);
+
+/// Kernels declared in `shaders.wgsl`
+const KERNELS: [&str; 5] = [
+    "fft_single",
+    "bit_reverse",
+    "scale_and_normalize",
+    "mul_assign",
+    "add_assign",
+];
+
+/// Fields with kernels declared in `shaders.wgsl`
+const FIELDS: [&str; 1] = ["p18446744069414584321_fp"];
+
+// must match `WORKGROUP_SIZE` in `shaders.wgsl`
+const WORKGROUP_SIZE: usize = 256;
+const MAX_WORKGROUPS_PER_DIMENSION: usize = 32768;
+
+static PLANNER: Lazy<Option<Planner>> = Lazy::new(|| pollster::block_on(Planner::new()));
+
+/// Returns the planner or `None` if there is no compatible device
+pub fn try_get_planner() -> Option<&'static Planner> {
+    PLANNER.as_ref()
+}
+
+pub fn get_planner() -> &'static Planner {
+    try_get_planner().expect("no device found")
+}
+
+pub struct Planner {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipelines: BTreeMap<String, wgpu::ComputePipeline>,
+}
+
+impl Planner {
+    pub async fn new() -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("ministark"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: adapter.limits(),
+                },
+                None,
+            )
+            .await
+            .ok()?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shaders.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER_SOURCE)),
+        });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                storage_entry(0, false),
+                storage_entry(1, true),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let mut pipelines = BTreeMap::new();
+        for field in FIELDS {
+            for kernel in KERNELS {
+                let entry_point = alloc::format!("{kernel}_{field}");
+                let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(&entry_point),
+                    layout: Some(&pipeline_layout),
+                    module: &module,
+                    entry_point: &entry_point,
+                });
+                pipelines.insert(entry_point, pipeline);
+            }
+        }
+
+        Some(Self {
+            device,
+            queue,
+            bind_group_layout,
+            pipelines,
+        })
+    }
+
+    /// Returns true if the field has kernels in the wgpu backend
+    pub fn supports<F: GpuField>() -> bool {
+        FIELDS.contains(&F::field_name().as_str())
+    }
+
+    fn pipeline<F: GpuField>(&self, kernel: &str) -> &wgpu::ComputePipeline {
+        let entry_point = alloc::format!("{kernel}_{}", F::field_name());
+        self.pipelines
+            .get(&entry_point)
+            .unwrap_or_else(|| panic!("{entry_point} is not supported by the wgpu backend"))
+    }
+
+    fn encode_stage<F: GpuField>(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        kernel: &str,
+        num_threads: usize,
+        vals: &wgpu::Buffer,
+        aux: &wgpu::Buffer,
+        params: &Params,
+    ) {
+        let params_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: &params.to_bytes(),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: vals.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: aux.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let (x, y) = dispatch_size(num_threads);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some(kernel),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(self.pipeline::<F>(kernel));
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(x, y, 1);
+    }
+
+    fn storage_buffer<T>(&self, v: &[T], writable: bool) -> wgpu::Buffer {
+        let usage = if writable {
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC
+        } else {
+            wgpu::BufferUsages::STORAGE
+        };
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: as_bytes(v),
+                usage,
+            })
+    }
+
+    /// Submits the commands and copies the contents of `src` back into `dst`
+    fn submit_and_read<T>(
+        &self,
+        mut encoder: wgpu::CommandEncoder,
+        src: &wgpu::Buffer,
+        dst: &mut [T],
+    ) {
+        let size = size_of_val(dst) as wgpu::BufferAddress;
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_buffer_to_buffer(src, 0, &staging_buffer, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| sender.send(res).unwrap());
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().unwrap().expect("failed to read buffer");
+        as_bytes_mut(dst).copy_from_slice(&slice.get_mapped_range());
+        staging_buffer.unmap();
+    }
+
+    fn pointwise<F: GpuField>(&self, kernel: &str, dst: &mut [F], src: &[F]) {
+        assert_eq!(dst.len(), src.len());
+        let n = dst.len();
+        let dst_buffer = self.storage_buffer(dst, true);
+        let src_buffer = self.storage_buffer(src, false);
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        let params = Params::new(n);
+        self.encode_stage::<F>(&mut encoder, kernel, n, &dst_buffer, &src_buffer, &params);
+        self.submit_and_read(encoder, &dst_buffer, dst);
+    }
+
+    /// Computes `dst[i] *= src[i]`
+    pub fn mul_assign<F: GpuField + GpuMul<F>>(&self, dst: &mut [F], src: &[F]) {
+        self.pointwise("mul_assign", dst, src);
+    }
+
+    /// Computes `dst[i] += src[i]`
+    pub fn add_assign<F: GpuField + GpuAdd<F>>(&self, dst: &mut [F], src: &[F]) {
+        self.pointwise("add_assign", dst, src);
+    }
+
+    #[cfg(feature = "arkworks")]
+    pub fn plan_fft<F: GpuField + ark_ff::Field>(
+        &self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> GpuFft<'_, F>
+    where
+        F::FftField: ark_ff::FftField,
+    {
+        assert!(domain.size() >= GpuFft::<F>::MIN_SIZE);
+        GpuFft::new(FftEncoder::new(self, FftDirection::Forward, domain))
+    }
+
+    #[cfg(feature = "arkworks")]
+    pub fn plan_ifft<F: GpuField + ark_ff::Field>(
+        &self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> GpuIfft<'_, F>
+    where
+        F::FftField: ark_ff::FftField,
+    {
+        assert!(domain.size() >= GpuIfft::<F>::MIN_SIZE);
+        GpuIfft::new(FftEncoder::new(self, FftDirection::Inverse, domain))
+    }
+}
+
+/// Uniform parameters. Layout must match `Params` in `shaders.wgsl`
+struct Params {
+    n: u32,
+    num_boxes: u32,
+    log_n: u32,
+    scale_factor: [u8; 8],
+    shift: [u8; 8],
+}
+
+impl Params {
+    fn new(n: usize) -> Self {
+        Self {
+            n: n.try_into().unwrap(),
+            num_boxes: 0,
+            log_n: n.ilog2(),
+            scale_factor: [0; 8],
+            shift: [0; 8],
+        }
+    }
+
+    fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[0..4].copy_from_slice(&self.n.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.num_boxes.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.log_n.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.scale_factor);
+        bytes[24..32].copy_from_slice(&self.shift);
+        bytes
+    }
+}
+
+/// Returns the number of workgroups to dispatch in the x and y dimension
+fn dispatch_size(num_threads: usize) -> (u32, u32) {
+    let num_workgroups = num_threads.div_ceil(WORKGROUP_SIZE);
+    let x = num_workgroups.min(MAX_WORKGROUPS_PER_DIMENSION);
+    let y = num_workgroups.div_ceil(x);
+    (x.try_into().unwrap(), y.try_into().unwrap())
+}
+
+fn as_bytes<T>(v: &[T]) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(v.as_ptr().cast(), size_of_val(v)) }
+}
+
+fn as_bytes_mut<T>(v: &mut [T]) -> &mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut(v.as_mut_ptr().cast(), size_of_val(v)) }
+}
+
+fn element_bytes<T>(v: &T) -> [u8; 8] {
+    assert_eq!(size_of::<T>(), 8, "only 64-bit fields are supported");
+    as_bytes(core::slice::from_ref(v)).try_into().unwrap()
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg(feature = "arkworks")]
+enum FftDirection {
+    /// FFT
+    Forward,
+    /// IFFT
+    Inverse,
+}
+
+#[cfg(feature = "arkworks")]
+struct FftEncoder<'a, F: GpuField + ark_ff::Field>
+where
+    F::FftField: ark_ff::FftField,
+{
+    planner: &'a Planner,
+    n: usize,
+    direction: FftDirection,
+    twiddles: Vec<F::FftField>,
+    // (shift, scale factor) of the scale and normalize stage
+    scale_and_normalize: Option<(F::FftField, F::FftField)>,
+    inputs: Vec<&'a mut [F]>,
+}
+
+#[cfg(feature = "arkworks")]
+impl<'a, F: GpuField + ark_ff::Field> FftEncoder<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    fn new(
+        planner: &'a Planner,
+        direction: FftDirection,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> Self {
+        let n = domain.size();
+        let root = match direction {
+            FftDirection::Forward => domain.group_gen,
+            FftDirection::Inverse => domain.group_gen_inv,
+        };
+
+        let mut twiddles = vec![F::FftField::one(); n / 2];
+        crate::utils::fill_twiddles(&mut twiddles, root);
+        crate::utils::bit_reverse(&mut twiddles);
+
+        let scale_and_normalize = match direction {
+            FftDirection::Forward if domain.offset.is_one() => None,
+            FftDirection::Forward => Some((domain.offset, F::FftField::one())),
+            FftDirection::Inverse => Some((domain.offset_inv, domain.size_inv)),
+        };
+
+        Self {
+            planner,
+            n,
+            direction,
+            twiddles,
+            scale_and_normalize,
+            inputs: Vec::new(),
+        }
+    }
+
+    fn encode(&mut self, input: &'a mut [F]) {
+        assert_eq!(self.n, input.len());
+        self.inputs.push(input);
+    }
+
+    fn execute(mut self) {
+        let planner = self.planner;
+        let twiddles_buffer = planner.storage_buffer(&self.twiddles, false);
+        for input in core::mem::take(&mut self.inputs) {
+            let vals_buffer = planner.storage_buffer(input, true);
+            let mut encoder = planner.device.create_command_encoder(&Default::default());
+            if self.direction == FftDirection::Forward {
+                self.encode_scale_stage(&mut encoder, &vals_buffer, &twiddles_buffer);
+            }
+            self.encode_butterfly_stages(&mut encoder, &vals_buffer, &twiddles_buffer);
+            self.encode_bit_reverse_stage(&mut encoder, &vals_buffer, &twiddles_buffer);
+            if self.direction == FftDirection::Inverse {
+                self.encode_scale_stage(&mut encoder, &vals_buffer, &twiddles_buffer);
+            }
+            planner.submit_and_read(encoder, &vals_buffer, input);
+        }
+    }
+
+    fn encode_butterfly_stages(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        vals: &wgpu::Buffer,
+        twiddles: &wgpu::Buffer,
+    ) {
+        for stage in 0..self.n.ilog2() {
+            let params = Params {
+                num_boxes: 1 << stage,
+                ..Params::new(self.n)
+            };
+            let num_threads = self.n / 2;
+            self.planner.encode_stage::<F>(
+                encoder,
+                "fft_single",
+                num_threads,
+                vals,
+                twiddles,
+                &params,
+            );
+        }
+    }
+
+    fn encode_bit_reverse_stage(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        vals: &wgpu::Buffer,
+        twiddles: &wgpu::Buffer,
+    ) {
+        let params = Params::new(self.n);
+        self.planner
+            .encode_stage::<F>(encoder, "bit_reverse", self.n, vals, twiddles, &params);
+    }
+
+    fn encode_scale_stage(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        vals: &wgpu::Buffer,
+        twiddles: &wgpu::Buffer,
+    ) {
+        if let Some((shift, scale_factor)) = self.scale_and_normalize {
+            let params = Params {
+                shift: element_bytes(&shift),
+                scale_factor: element_bytes(&scale_factor),
+                ..Params::new(self.n)
+            };
+            let kernel = "scale_and_normalize";
+            self.planner
+                .encode_stage::<F>(encoder, kernel, self.n, vals, twiddles, &params);
+        }
+    }
+}
+
+#[cfg(feature = "arkworks")]
+pub struct GpuFft<'a, F: GpuField + ark_ff::Field>
+where
+    F::FftField: ark_ff::FftField,
+{
+    encoder: FftEncoder<'a, F>,
+}
+
+#[cfg(feature = "arkworks")]
+impl<'a, F: GpuField + ark_ff::Field> GpuFft<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    pub const MIN_SIZE: usize = 2048;
+
+    fn new(encoder: FftEncoder<'a, F>) -> Self {
+        GpuFft { encoder }
+    }
+
+    pub fn encode(&mut self, buffer: &'a mut [F]) {
+        self.encoder.encode(buffer);
+    }
+
+    pub fn execute(self) {
+        self.encoder.execute()
+    }
+}
+
+#[cfg(feature = "arkworks")]
+impl<'a, F: GpuField + ark_ff::Field> From<Radix2EvaluationDomain<F::FftField>> for GpuFft<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    fn from(domain: Radix2EvaluationDomain<F::FftField>) -> Self {
+        let planner = get_planner();
+        planner.plan_fft(domain)
+    }
+}
+
+#[cfg(feature = "arkworks")]
+pub struct GpuIfft<'a, F: GpuField + ark_ff::Field>
+where
+    F::FftField: ark_ff::FftField,
+{
+    encoder: FftEncoder<'a, F>,
+}
+
+#[cfg(feature = "arkworks")]
+impl<'a, F: GpuField + ark_ff::Field> GpuIfft<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    pub const MIN_SIZE: usize = 2048;
+
+    fn new(encoder: FftEncoder<'a, F>) -> Self {
+        GpuIfft { encoder }
+    }
+
+    pub fn encode(&mut self, input: &'a mut [F]) {
+        self.encoder.encode(input);
+    }
+
+    pub fn execute(self) {
+        self.encoder.execute()
+    }
+}
+
+#[cfg(feature = "arkworks")]
+impl<'a, F: GpuField + ark_ff::Field> From<Radix2EvaluationDomain<F::FftField>> for GpuIfft<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    fn from(domain: Radix2EvaluationDomain<F::FftField>) -> Self {
+        let planner = get_planner();
+        planner.plan_ifft(domain)
+    }
+}
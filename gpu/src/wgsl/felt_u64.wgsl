@@ -0,0 +1,116 @@
+// Fields that use prime 18446744069414584321
+// WGSL has no 64-bit integers so field elements are stored as `vec2<u32>` in
+// the form `(lo, hi)`. This matches the memory layout of a little-endian `u64`.
+// Arithmetic is a port of `felt_u64.h.metal` and operates on values in
+// Montgomery representation.
+
+// Field modulus `p = 2^64 - 2^32 + 1`
+const P18446744069414584321_N = vec2<u32>(1u, 4294967295u);
+
+// 1 in Montgomery representation
+const P18446744069414584321_ONE = vec2<u32>(4294967295u, 0u);
+
+// Returns `a + b` and the carry
+fn u64_add(a: vec2<u32>, b: vec2<u32>) -> vec3<u32> {
+    let lo = a.x + b.x;
+    let c0 = select(0u, 1u, lo < a.x);
+    let hi0 = a.y + b.y;
+    let c1 = select(0u, 1u, hi0 < a.y);
+    let hi = hi0 + c0;
+    let c2 = select(0u, 1u, hi < hi0);
+    return vec3<u32>(lo, hi, c1 | c2);
+}
+
+// Returns `a - b` and the borrow
+fn u64_sub(a: vec2<u32>, b: vec2<u32>) -> vec3<u32> {
+    let lo = a.x - b.x;
+    let b0 = select(0u, 1u, a.x < b.x);
+    let hi0 = a.y - b.y;
+    let b1 = select(0u, 1u, a.y < b.y);
+    let hi = hi0 - b0;
+    let b2 = select(0u, 1u, hi0 < b0);
+    return vec3<u32>(lo, hi, b1 | b2);
+}
+
+// Returns the 64-bit product of two 32-bit values
+fn u32_mul_wide(a: u32, b: u32) -> vec2<u32> {
+    let a0 = a & 0xFFFFu;
+    let a1 = a >> 16u;
+    let b0 = b & 0xFFFFu;
+    let b1 = b >> 16u;
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+    // can't overflow: (2^16 - 1)^2 + 2 * (2^16 - 1) < 2^32
+    let mid = p01 + (p00 >> 16u) + (p10 & 0xFFFFu);
+    let lo = (mid << 16u) | (p00 & 0xFFFFu);
+    let hi = p11 + (mid >> 16u) + (p10 >> 16u);
+    return vec2<u32>(lo, hi);
+}
+
+// Returns the 128-bit product of two 64-bit values as `(lo, hi)`
+fn u64_mul_wide(a: vec2<u32>, b: vec2<u32>) -> array<vec2<u32>, 2> {
+    let p00 = u32_mul_wide(a.x, b.x);
+    let p01 = u32_mul_wide(a.x, b.y);
+    let p10 = u32_mul_wide(a.y, b.x);
+    let p11 = u32_mul_wide(a.y, b.y);
+
+    let r0 = p00.x;
+
+    let s1 = p00.y + p01.x;
+    let c1 = select(0u, 1u, s1 < p00.y);
+    let r1 = s1 + p10.x;
+    let c2 = select(0u, 1u, r1 < s1);
+
+    let s2 = p01.y + p10.y;
+    let c3 = select(0u, 1u, s2 < p01.y);
+    let s3 = s2 + p11.x;
+    let c4 = select(0u, 1u, s3 < s2);
+    let r2 = s3 + c1 + c2;
+    let c5 = select(0u, 1u, r2 < s3);
+
+    let r3 = p11.y + c3 + c4 + c5;
+    return array<vec2<u32>, 2>(vec2<u32>(r0, r1), vec2<u32>(r2, r3));
+}
+
+fn p18446744069414584321_fp_add(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    // We compute a + b = a - (p - b).
+    let tmp = u64_sub(P18446744069414584321_N, b).xy;
+    let x1 = u64_sub(a, tmp);
+    let adj = select(0u, 0xFFFFFFFFu, x1.z == 1u);
+    return u64_sub(x1.xy, vec2<u32>(adj, 0u)).xy;
+}
+
+fn p18446744069414584321_fp_sub(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let x1 = u64_sub(a, b);
+    let adj = select(0u, 0xFFFFFFFFu, x1.z == 1u);
+    return u64_sub(x1.xy, vec2<u32>(adj, 0u)).xy;
+}
+
+fn p18446744069414584321_fp_mul(lhs: vec2<u32>, rhs: vec2<u32>) -> vec2<u32> {
+    let x = u64_mul_wide(lhs, rhs);
+    let xl = x[0];
+    let xh = x[1];
+    let tmp = vec2<u32>(0u, xl.x);
+    let a = u64_add(xl, tmp);
+    let b0 = u64_sub(a.xy, vec2<u32>(a.y, 0u)).xy;
+    let b = u64_sub(b0, vec2<u32>(a.z, 0u)).xy;
+    let r = u64_sub(xh, b);
+    let adj = select(0u, 0xFFFFFFFFu, r.z == 1u);
+    return u64_sub(r.xy, vec2<u32>(adj, 0u)).xy;
+}
+
+fn p18446744069414584321_fp_pow(base: vec2<u32>, exp: u32) -> vec2<u32> {
+    var res = P18446744069414584321_ONE;
+    var b = base;
+    var e = exp;
+    while (e > 0u) {
+        if ((e & 1u) == 1u) {
+            res = p18446744069414584321_fp_mul(res, b);
+        }
+        e = e >> 1u;
+        b = p18446744069414584321_fp_mul(b, b);
+    }
+    return res;
+}
@@ -0,0 +1,114 @@
+// Kernels for the wgpu backend. `felt_u64.wgsl` is prepended to this file
+// before it's compiled (WGSL has no includes).
+
+const WORKGROUP_SIZE = 256u;
+
+struct Params {
+    // number of field elements in `vals`
+    n: u32,
+    // number of butterfly boxes in the current FFT stage
+    num_boxes: u32,
+    // log2(n)
+    log_n: u32,
+    _padding: u32,
+    scale_factor: vec2<u32>,
+    shift: vec2<u32>,
+}
+
+@group(0) @binding(0) var<storage, read_write> vals: array<vec2<u32>>;
+// twiddles for FFT stages or the right hand side of pointwise stages
+@group(0) @binding(1) var<storage, read> aux: array<vec2<u32>>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+fn thread_index(gid: vec3<u32>, num_workgroups: vec3<u32>) -> u32 {
+    return gid.x + gid.y * num_workgroups.x * WORKGROUP_SIZE;
+}
+
+// ===========================================================
+// Fp=18446744069414584321
+
+// Performs a single itteration of Cooley-Tuckey FFT
+// Mirrors `FftSingle` in `fft_shaders.h.metal`
+@compute @workgroup_size(256)
+fn fft_single_p18446744069414584321_fp(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let global_tid = thread_index(gid, num_workgroups);
+    if (global_tid >= params.n / 2u) {
+        return;
+    }
+
+    let input_step = (params.n / params.num_boxes) / 2u;
+    let box_id = global_tid / input_step;
+    let target_index = box_id * input_step * 2u + (global_tid % input_step);
+
+    let twiddle = aux[box_id];
+    let p = vals[target_index];
+    let q = p18446744069414584321_fp_mul(vals[target_index + input_step], twiddle);
+
+    vals[target_index] = p18446744069414584321_fp_add(p, q);
+    vals[target_index + input_step] = p18446744069414584321_fp_sub(p, q);
+}
+
+// Performs bit reversal.
+// A useful transformation after a Cooley-Tuckey FFT to put outputs in order.
+@compute @workgroup_size(256)
+fn bit_reverse_p18446744069414584321_fp(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let i = thread_index(gid, num_workgroups);
+    if (i >= params.n) {
+        return;
+    }
+
+    let ri = reverseBits(i) >> (32u - params.log_n);
+    if (i < ri) {
+        let tmp = vals[i];
+        vals[i] = vals[ri];
+        vals[ri] = tmp;
+    }
+}
+
+// Multiplies the i-th value by `scale_factor * shift^i`
+@compute @workgroup_size(256)
+fn scale_and_normalize_p18446744069414584321_fp(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let i = thread_index(gid, num_workgroups);
+    if (i >= params.n) {
+        return;
+    }
+
+    let shift = p18446744069414584321_fp_pow(params.shift, i);
+    let scale = p18446744069414584321_fp_mul(params.scale_factor, shift);
+    vals[i] = p18446744069414584321_fp_mul(vals[i], scale);
+}
+
+@compute @workgroup_size(256)
+fn mul_assign_p18446744069414584321_fp(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let i = thread_index(gid, num_workgroups);
+    if (i >= params.n) {
+        return;
+    }
+
+    vals[i] = p18446744069414584321_fp_mul(vals[i], aux[i]);
+}
+
+@compute @workgroup_size(256)
+fn add_assign_p18446744069414584321_fp(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let i = thread_index(gid, num_workgroups);
+    if (i >= params.n) {
+        return;
+    }
+
+    vals[i] = p18446744069414584321_fp_add(vals[i], aux[i]);
+}
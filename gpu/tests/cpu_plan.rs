@@ -0,0 +1,54 @@
+#![cfg(all(feature = "parallel", feature = "arkworks"))]
+
+use ark_ff::FftField;
+use ark_ff_optimized::fp64::Fp;
+use ark_poly::domain::Radix2EvaluationDomain;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::DenseUVPolynomial;
+use ark_poly::EvaluationDomain;
+use core::iter::zip;
+use ministark_gpu::cpu_plan::get_planner;
+use ministark_gpu::cpu_plan::Planner;
+use ministark_gpu::utils::bit_reverse;
+
+#[test]
+fn fft_matches_ark_poly() {
+    // a small block size so FFTs have layers that span multiple blocks
+    let planners = [get_planner(), &Planner::new(16)];
+    let domains = [
+        Radix2EvaluationDomain::new(1).unwrap(),
+        Radix2EvaluationDomain::new(2).unwrap(),
+        Radix2EvaluationDomain::new(128).unwrap(),
+        Radix2EvaluationDomain::new(2048).unwrap(),
+        Radix2EvaluationDomain::new(65536).unwrap(),
+        Radix2EvaluationDomain::new_coset(512, Fp::GENERATOR).unwrap(),
+        Radix2EvaluationDomain::new_coset(8192, Fp::GENERATOR).unwrap(),
+    ];
+
+    for planner in planners {
+        for (i, domain) in domains.into_iter().enumerate() {
+            let n = domain.size();
+            let poly = DensePolynomial::<Fp>::rand(n - 1, &mut ark_std::test_rng());
+            let expected_evals = domain.fft(&poly.coeffs);
+            let mut evals = poly.coeffs.clone();
+            let mut fft = planner.plan_fft(domain);
+            fft.encode(&mut evals);
+            fft.execute();
+            for (j, (expected, actual)) in zip(&expected_evals, &evals).enumerate() {
+                assert_eq!(expected, actual, "fft domain ({i}) mismatch at index {j}");
+            }
+
+            let mut out_of_order_evals = poly.coeffs.clone();
+            let mut fft = planner.plan_fft(domain);
+            fft.encode_out_of_order(&mut out_of_order_evals);
+            fft.execute();
+            bit_reverse(&mut out_of_order_evals);
+            assert_eq!(expected_evals, out_of_order_evals, "domain ({i}) mismatch");
+
+            let mut ifft = planner.plan_ifft(domain);
+            ifft.encode(&mut evals);
+            ifft.execute();
+            assert_eq!(poly.coeffs, evals, "ifft domain ({i}) mismatch");
+        }
+    }
+}
@@ -10,8 +10,10 @@ pub mod p18446744069414584321 {
     use super::*;
     use ark_ff::Field;
     use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fq2;
     use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
     use ministark_gpu::prelude::get_planner;
+    use ministark_gpu::stage::InverseInPlaceStage;
     use ministark_gpu::utils::page_aligned_uninit_vector;
 
     #[test]
@@ -115,4 +117,219 @@ pub mod p18446744069414584321 {
             assert_eq!(expected, actual, "mismatch at index {i}");
         }
     }
+
+    #[test]
+    fn mul_pow_fq2_by_fp() {
+        let n = 2048;
+        let mut rng = &mut ark_std::test_rng();
+        let mut a = unsafe { page_aligned_uninit_vector(n) };
+        a.fill_with(|| Fq2::rand(&mut rng));
+        let mut b = unsafe { page_aligned_uninit_vector(n) };
+        b.fill_with(|| Fp::rand(&mut rng));
+        let expected = a
+            .iter()
+            .copied()
+            .zip(&b)
+            .map(|(mut a, b)| {
+                a *= b;
+                a
+            })
+            .collect::<Vec<Fq2>>();
+        let planner = get_planner();
+        let command_queue = &planner.command_queue;
+        let mut a_buffer = buffer_mut_no_copy(command_queue.device(), &mut a);
+        let b_buffer = buffer_no_copy(command_queue.device(), &b);
+        let command_buffer = command_queue.new_command_buffer();
+
+        let multiplier = MulPowStage::<Fq2, Fp>::new(&planner.library, n);
+        multiplier.encode(command_buffer, &mut a_buffer, &b_buffer, 1, 0);
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        for (i, (expected, actual)) in expected.into_iter().zip(a).enumerate() {
+            assert_eq!(expected, actual, "mismatch at index {i}");
+        }
+    }
+
+    #[test]
+    fn mul_pow_fq2() {
+        let n = 2048;
+        let mut rng = &mut ark_std::test_rng();
+        let mut a = unsafe { page_aligned_uninit_vector(n) };
+        a.fill_with(|| Fq2::rand(&mut rng));
+        let mut b = unsafe { page_aligned_uninit_vector(n) };
+        b.fill_with(|| Fq2::rand(&mut rng));
+        let expected = a
+            .iter()
+            .copied()
+            .zip(&b)
+            .map(|(mut a, b)| {
+                a *= b.square() * b;
+                a
+            })
+            .collect::<Vec<Fq2>>();
+        let planner = get_planner();
+        let command_queue = &planner.command_queue;
+        let mut a_buffer = buffer_mut_no_copy(command_queue.device(), &mut a);
+        let b_buffer = buffer_no_copy(command_queue.device(), &b);
+        let command_buffer = command_queue.new_command_buffer();
+
+        let multiplier = MulPowStage::<Fq2>::new(&planner.library, n);
+        multiplier.encode(command_buffer, &mut a_buffer, &b_buffer, 3, 0);
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        for (i, (expected, actual)) in expected.into_iter().zip(a).enumerate() {
+            assert_eq!(expected, actual, "mismatch at index {i}");
+        }
+    }
+
+    #[test]
+    fn inverse_in_place_fq2() {
+        let n = 2048;
+        let mut rng = &mut ark_std::test_rng();
+        let mut a = unsafe { page_aligned_uninit_vector(n) };
+        a.fill_with(|| Fq2::rand(&mut rng));
+        let expected = a.iter().map(|a| a.inverse().unwrap()).collect::<Vec<Fq2>>();
+        let planner = get_planner();
+        let command_queue = &planner.command_queue;
+        let a_buffer = buffer_mut_no_copy(command_queue.device(), &mut a);
+        let command_buffer = command_queue.new_command_buffer();
+
+        let inverter = InverseInPlaceStage::<Fq2>::new(&planner.library, n);
+        inverter.encode(command_buffer, &a_buffer);
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        for (i, (expected, actual)) in expected.into_iter().zip(a).enumerate() {
+            assert_eq!(expected, actual, "mismatch at index {i}");
+        }
+    }
+
+    #[test]
+    fn inverse_in_place_fq3() {
+        let n = 2048;
+        let mut rng = &mut ark_std::test_rng();
+        let mut a = unsafe { page_aligned_uninit_vector(n) };
+        a.fill_with(|| Fq3::rand(&mut rng));
+        let expected = a.iter().map(|a| a.inverse().unwrap()).collect::<Vec<Fq3>>();
+        let planner = get_planner();
+        let command_queue = &planner.command_queue;
+        let a_buffer = buffer_mut_no_copy(command_queue.device(), &mut a);
+        let command_buffer = command_queue.new_command_buffer();
+
+        let inverter = InverseInPlaceStage::<Fq3>::new(&planner.library, n);
+        inverter.encode(command_buffer, &a_buffer);
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        for (i, (expected, actual)) in expected.into_iter().zip(a).enumerate() {
+            assert_eq!(expected, actual, "mismatch at index {i}");
+        }
+    }
+}
+
+pub mod p2013265921 {
+    use super::*;
+    use ark_ff::Field;
+    use ministark_gpu::fields::p2013265921::ark::Fp;
+    use ministark_gpu::prelude::get_planner;
+    use ministark_gpu::utils::page_aligned_uninit_vector;
+
+    #[test]
+    fn mul_pow_fp() {
+        let n = 2048;
+        let mut rng = &mut ark_std::test_rng();
+        let mut a = unsafe { page_aligned_uninit_vector(n) };
+        a.fill_with(|| Fp::rand(&mut rng));
+        let mut b = unsafe { page_aligned_uninit_vector(n) };
+        b.fill_with(|| Fp::rand(&mut rng));
+        let expected = a
+            .iter()
+            .copied()
+            .zip(&b)
+            .map(|(mut a, b)| {
+                a *= b.square() * b;
+                a
+            })
+            .collect::<Vec<Fp>>();
+        let planner = get_planner();
+        let command_queue = &planner.command_queue;
+        let mut a_buffer = buffer_mut_no_copy(command_queue.device(), &mut a);
+        let b_buffer = buffer_no_copy(command_queue.device(), &b);
+        let command_buffer = command_queue.new_command_buffer();
+
+        let multiplier = MulPowStage::<Fp>::new(&planner.library, n);
+        multiplier.encode(command_buffer, &mut a_buffer, &b_buffer, 3, 0);
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        for (i, (expected, actual)) in expected.into_iter().zip(a).enumerate() {
+            assert_eq!(expected, actual, "mismatch at index {i}");
+        }
+    }
+}
+
+pub mod p3618502788666131213697322783095070105623107215331596699973092056135872020481 {
+    use super::*;
+    use ark_ff::Field;
+    use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+    use ministark_gpu::prelude::get_planner;
+    use ministark_gpu::stage::InverseInPlaceStage;
+    use ministark_gpu::utils::page_aligned_uninit_vector;
+
+    #[test]
+    fn mul_pow_fp() {
+        let n = 2048;
+        let mut rng = &mut ark_std::test_rng();
+        let mut a = unsafe { page_aligned_uninit_vector(n) };
+        a.fill_with(|| Fp::rand(&mut rng));
+        let mut b = unsafe { page_aligned_uninit_vector(n) };
+        b.fill_with(|| Fp::rand(&mut rng));
+        let expected = a
+            .iter()
+            .copied()
+            .zip(&b)
+            .map(|(mut a, b)| {
+                a *= b.square() * b;
+                a
+            })
+            .collect::<Vec<Fp>>();
+        let planner = get_planner();
+        let command_queue = &planner.command_queue;
+        let mut a_buffer = buffer_mut_no_copy(command_queue.device(), &mut a);
+        let b_buffer = buffer_no_copy(command_queue.device(), &b);
+        let command_buffer = command_queue.new_command_buffer();
+
+        let multiplier = MulPowStage::<Fp>::new(&planner.library, n);
+        multiplier.encode(command_buffer, &mut a_buffer, &b_buffer, 3, 0);
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        for (i, (expected, actual)) in expected.into_iter().zip(a).enumerate() {
+            assert_eq!(expected, actual, "mismatch at index {i}");
+        }
+    }
+
+    #[test]
+    fn inverse_in_place_fp() {
+        let n = 2048;
+        let mut rng = &mut ark_std::test_rng();
+        let mut a = unsafe { page_aligned_uninit_vector(n) };
+        a.fill_with(|| Fp::rand(&mut rng));
+        let expected = a.iter().map(|a| a.inverse().unwrap()).collect::<Vec<Fp>>();
+        let planner = get_planner();
+        let command_queue = &planner.command_queue;
+        let a_buffer = buffer_mut_no_copy(command_queue.device(), &mut a);
+        let command_buffer = command_queue.new_command_buffer();
+
+        let inverter = InverseInPlaceStage::<Fp>::new(&planner.library, n);
+        inverter.encode(command_buffer, &a_buffer);
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        for (i, (expected, actual)) in expected.into_iter().zip(a).enumerate() {
+            assert_eq!(expected, actual, "mismatch at index {i}");
+        }
+    }
 }
@@ -8,9 +8,13 @@ use ark_poly::domain::Radix2EvaluationDomain;
 use ark_poly::univariate::DensePolynomial;
 use ark_poly::DenseUVPolynomial;
 use ark_poly::EvaluationDomain;
+use ministark_gpu::fields::p18446744069414584321::ark::Fq2;
 use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+use ministark_gpu::fields::p2013265921::ark::Fp as BabyBear;
+use ministark_gpu::fields::p2147483647::ark::Fp as Mersenne31;
 use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp as Fp252;
 use ministark_gpu::prelude::*;
+use ministark_gpu::stage::GenerateTwiddlesStage;
 use ministark_gpu::utils::page_aligned_uninit_vector;
 
 #[test]
@@ -65,6 +69,32 @@ fn fft_with_extension_field() {
     }
 }
 
+#[test]
+fn fft_with_quadratic_extension_field() {
+    let domains = [
+        Radix2EvaluationDomain::new(2048).unwrap(),
+        Radix2EvaluationDomain::new(4096).unwrap(),
+        Radix2EvaluationDomain::new(65536).unwrap(),
+        Radix2EvaluationDomain::new_coset(2048, Fp::GENERATOR).unwrap(),
+        Radix2EvaluationDomain::new_coset(4096, Fp::GENERATOR).unwrap(),
+    ];
+
+    for (i, domain) in domains.into_iter().enumerate() {
+        let n = domain.size();
+        let poly = DensePolynomial::<Fq2>::rand(n - 1, &mut ark_std::test_rng());
+        let cpu_evals = domain.fft(&poly.coeffs);
+        let mut gpu_evals = unsafe { page_aligned_uninit_vector(n) };
+        gpu_evals.copy_from_slice(&poly.coeffs);
+        let mut fft = GpuFft::from(domain);
+        fft.encode(&mut gpu_evals);
+        fft.execute();
+
+        for (j, (expected, actual)) in zip(cpu_evals, gpu_evals).enumerate() {
+            assert_eq!(expected, actual, "domain ({i}) mismatch at index {j}");
+        }
+    }
+}
+
 #[test]
 fn fft_with_256_bit_field() {
     let domains = [
@@ -90,6 +120,63 @@ fn fft_with_256_bit_field() {
     }
 }
 
+#[test]
+fn fft_with_31_bit_field() {
+    let domains = [
+        Radix2EvaluationDomain::new(2048).unwrap(),
+        Radix2EvaluationDomain::new(4096).unwrap(),
+        Radix2EvaluationDomain::new_coset(2048, BabyBear::GENERATOR).unwrap(),
+        Radix2EvaluationDomain::new_coset(4096, BabyBear::GENERATOR).unwrap(),
+    ];
+
+    for (i, domain) in domains.into_iter().enumerate() {
+        let n = domain.size();
+        let poly = DensePolynomial::<BabyBear>::rand(n - 1, &mut ark_std::test_rng());
+        let cpu_evals = domain.fft(&poly.coeffs);
+        let mut gpu_evals = unsafe { page_aligned_uninit_vector(n) };
+        gpu_evals.copy_from_slice(&poly);
+        let mut fft = GpuFft::from(domain);
+        fft.encode(&mut gpu_evals);
+        fft.execute();
+
+        for (j, (expected, actual)) in zip(cpu_evals, gpu_evals).enumerate() {
+            assert_eq!(expected, actual, "domain ({i}) mismatch at index {j}");
+        }
+    }
+}
+
+#[test]
+fn cached_fft_plans_match_cpu_fft() {
+    // the first two domains share twiddles and each domain is planned twice so
+    // the last two FFTs run with cached plans. Plans are rebuilt after clearing.
+    let domains = [
+        Radix2EvaluationDomain::new(2048).unwrap(),
+        Radix2EvaluationDomain::new_coset(2048, Fp::GENERATOR).unwrap(),
+        Radix2EvaluationDomain::new(2048).unwrap(),
+        Radix2EvaluationDomain::new_coset(2048, Fp::GENERATOR).unwrap(),
+    ];
+    let planner = get_planner();
+    planner.clear_fft_plans();
+
+    for _ in 0..2 {
+        for (i, domain) in domains.into_iter().enumerate() {
+            let n = domain.size();
+            let poly = DensePolynomial::<Fq3>::rand(n - 1, &mut ark_std::test_rng());
+            let cpu_evals = domain.fft(&poly.coeffs);
+            let mut gpu_evals = unsafe { page_aligned_uninit_vector(n) };
+            gpu_evals.copy_from_slice(&poly.coeffs);
+            let mut fft = planner.plan_fft(domain);
+            fft.encode(&mut gpu_evals);
+            fft.execute();
+
+            for (j, (expected, actual)) in zip(cpu_evals, gpu_evals).enumerate() {
+                assert_eq!(expected, actual, "domain ({i}) mismatch at index {j}");
+            }
+        }
+        planner.clear_fft_plans();
+    }
+}
+
 #[test]
 fn ifft() {
     let domains = [
@@ -115,3 +202,73 @@ fn ifft() {
         }
     }
 }
+
+#[test]
+fn library_has_poseidon_transcript_kernel() {
+    let planner = get_planner();
+    assert!(planner.has_kernel("poseidon_transcript_p18446744069414584321_fp"));
+    assert!(ministark_gpu::stage::PoseidonTranscriptStage::<Fp>::new(&planner.library).is_ok());
+}
+
+#[test]
+fn library_has_transpose_kernels() {
+    let planner = get_planner();
+    let library = &planner.library;
+    assert!(TransposeColumnStage::<Fp>::new(library, 2048).is_ok());
+    assert!(TransposeColumnStage::<Fq2>::new(library, 2048).is_ok());
+    assert!(TransposeColumnStage::<Fq3>::new(library, 2048).is_ok());
+    assert!(TransposeColumnStage::<Fp252>::new(library, 2048).is_ok());
+    assert!(TransposeColumnStage::<BabyBear>::new(library, 2048).is_ok());
+}
+
+#[test]
+fn library_has_31_bit_field_kernels() {
+    let planner = get_planner();
+    assert!(planner.supports_fft::<BabyBear>());
+    assert!(planner.has_kernel("transpose_column_p2013265921_fp"));
+}
+
+#[test]
+fn library_has_mersenne31_kernels() {
+    let planner = get_planner();
+    let field = Mersenne31::field_name();
+    for kernel in [
+        format!("mul_pow_LHS_{field}_RHS_{field}"),
+        format!("add_assign_LHS_{field}_RHS_{field}"),
+        format!("inverse_in_place_{field}"),
+        format!("fill_buff_{field}"),
+        format!("transpose_column_{field}"),
+        format!("eval_constraint_program_BASE_{field}_EXTENSION_{field}"),
+    ] {
+        assert!(planner.has_kernel(&kernel), "missing kernel {kernel}");
+    }
+    // the field has no radix-2 FFT kernels. Circle FFTs run on the CPU
+    assert!(!planner.supports_fft::<Mersenne31>());
+}
+
+#[test]
+fn library_has_256_bit_field_kernels() {
+    let planner = get_planner();
+    assert!(planner.supports_fft::<Fp252>());
+    assert!(GenerateTwiddlesStage::<Fp252>::new(&planner.library, 2048).is_ok());
+    assert!(GenerateTwiddlesStage::<Fp>::new(&planner.library, 2048).is_ok());
+}
+
+#[test]
+fn library_has_64_bit_field_extension_kernels() {
+    let planner = get_planner();
+    assert!(planner.supports_fft::<Fq2>());
+    assert!(planner.supports_fft::<Fq3>());
+    let base = Fp::field_name();
+    for extension in [Fq2::field_name(), Fq3::field_name()] {
+        for kernel in [
+            format!("mul_pow_LHS_{extension}_RHS_{base}"),
+            format!("mul_pow_LHS_{extension}_RHS_{extension}"),
+            format!("mul_into_LHS_{extension}_RHS_{base}"),
+            format!("add_assign_LHS_{extension}_RHS_{base}"),
+            format!("eval_constraint_program_BASE_{base}_EXTENSION_{extension}"),
+        ] {
+            assert!(planner.has_kernel(&kernel), "missing kernel {kernel}");
+        }
+    }
+}
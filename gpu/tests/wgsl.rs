@@ -0,0 +1,77 @@
+#![cfg(all(feature = "wgpu", feature = "arkworks"))]
+
+use ark_ff::FftField;
+use ark_ff::UniformRand;
+use ark_ff_optimized::fp64::Fp;
+use ark_poly::domain::Radix2EvaluationDomain;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::DenseUVPolynomial;
+use ark_poly::EvaluationDomain;
+use core::iter::zip;
+use ministark_gpu::wgpu_plan::try_get_planner;
+use ministark_gpu::wgpu_plan::SHADER_SOURCE;
+
+#[test]
+fn shaders_are_valid_wgsl() {
+    let module = naga::front::wgsl::parse_str(SHADER_SOURCE).unwrap();
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .unwrap();
+}
+
+#[test]
+fn fft_matches_cpu() {
+    let Some(planner) = try_get_planner() else {
+        eprintln!("skipping: no wgpu adapter found");
+        return;
+    };
+
+    let domains = [
+        Radix2EvaluationDomain::new(2048).unwrap(),
+        Radix2EvaluationDomain::new(65536).unwrap(),
+        Radix2EvaluationDomain::new_coset(2048, Fp::GENERATOR).unwrap(),
+    ];
+
+    for (i, domain) in domains.into_iter().enumerate() {
+        let n = domain.size();
+        let poly = DensePolynomial::<Fp>::rand(n - 1, &mut ark_std::test_rng());
+        let cpu_evals = domain.fft(&poly.coeffs);
+        let mut gpu_evals = poly.coeffs.clone();
+        let mut fft = planner.plan_fft(domain);
+        fft.encode(&mut gpu_evals);
+        fft.execute();
+        for (j, (expected, actual)) in zip(&cpu_evals, &gpu_evals).enumerate() {
+            assert_eq!(expected, actual, "fft domain ({i}) mismatch at index {j}");
+        }
+
+        let mut ifft = planner.plan_ifft(domain);
+        ifft.encode(&mut gpu_evals);
+        ifft.execute();
+        assert_eq!(poly.coeffs, gpu_evals, "ifft domain ({i}) mismatch");
+    }
+}
+
+#[test]
+fn pointwise_matches_cpu() {
+    let Some(planner) = try_get_planner() else {
+        eprintln!("skipping: no wgpu adapter found");
+        return;
+    };
+
+    let mut rng = ark_std::test_rng();
+    let lhs = (0..1000).map(|_| Fp::rand(&mut rng)).collect::<Vec<_>>();
+    let rhs = (0..1000).map(|_| Fp::rand(&mut rng)).collect::<Vec<_>>();
+
+    let mut products = lhs.clone();
+    planner.mul_assign(&mut products, &rhs);
+    let mut sums = lhs.clone();
+    planner.add_assign(&mut sums, &rhs);
+
+    for i in 0..lhs.len() {
+        assert_eq!(lhs[i] * rhs[i], products[i], "mul mismatch at index {i}");
+        assert_eq!(lhs[i] + rhs[i], sums[i], "add mismatch at index {i}");
+    }
+}
@@ -1,35 +1,133 @@
 use crate::challenges::Challenges;
 use crate::constraints::AlgebraicItem;
+use crate::constraints::Assertion;
 use crate::constraints::CompositionConstraint;
 use crate::constraints::CompositionItem;
 use crate::constraints::Constraint;
+use crate::constraints::ConstraintGroup;
+use crate::constraints::PeriodicColumn;
+use crate::domain::TraceDomain;
+use crate::expression::CompiledExpr;
 use crate::expression::Expr;
+use crate::expression::Instruction;
+use crate::hash::HashFn;
 use crate::hints::Hints;
+use crate::random::QuerySampling;
+use crate::trace::CellType;
 use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
+use crate::InvalidProofOptions;
 use crate::Matrix;
 use crate::ProofOptions;
 use crate::StarkExtensionOf;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::collections::BTreeSet;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use ark_ff::FftField;
 use ark_poly::EvaluationDomain;
 use ark_poly::Radix2EvaluationDomain;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use core::any::Any;
+use core::any::TypeId;
 use ministark_gpu::GpuFftField;
 use num_traits::Pow;
-use std::time::Instant;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 pub trait AirConfig: Send + Sync + Sized + 'static {
     const NUM_BASE_COLUMNS: usize;
     const NUM_EXTENSION_COLUMNS: usize = 0;
 
+    /// Number of preprocessed columns. These are the last base columns and
+    /// are committed to once rather than in each proof (see
+    /// [`crate::preprocessed`]).
+    const NUM_PREPROCESSED_COLUMNS: usize = 0;
+
+    /// Rounds the extension columns are built and committed to in. Each round
+    /// draws fresh challenges after the previous round's commitment so
+    /// columns can depend on challenges drawn after other extension columns
+    /// were committed to (e.g. a permutation over lookup columns). Columns of
+    /// later segments are numbered after those of earlier segments and the
+    /// widths must add up to `NUM_EXTENSION_COLUMNS`. If empty all extension
+    /// columns are built in a single round (see [`Air::aux_segments`]).
+    const AUX_SEGMENTS: &'static [AuxSegment] = &[];
+
+    /// Types of the declared base columns. Range constraints for these columns
+    /// are added to [`AirConfig::constraints`] automatically. If empty all base
+    /// columns are treated as [`CellType::Felt`]. Use [`trace::num_columns`] to
+    /// derive `NUM_BASE_COLUMNS` which includes range check helper columns.
+    ///
+    /// [`trace::num_columns`]: crate::trace::num_columns
+    const BASE_COLUMN_TYPES: &'static [CellType] = &[];
+
+    /// Degree bounds of base column polynomials as `(column, degree)` pairs.
+    /// Constant or low degree columns (e.g. selectors and flags) are extended
+    /// with small FFTs over cosets that partition the LDE domain rather than a
+    /// full size FFT. A degree of zero marks a constant column which is
+    /// extended without any FFTs. Proofs are unchanged. Bounds are checked
+    /// when [`AirConfig::SANITIZE_WITNESS`] is set.
+    const BASE_COLUMN_DEGREE_HINTS: &'static [(usize, usize)] = &[];
+
+    /// Evaluates the composition constraint with a Metal kernel generated for
+    /// this AIR instead of on the CPU. Only has an effect with the `gpu`
+    /// feature on Apple Silicon. Evaluation falls back to the CPU if a kernel
+    /// can't be generated for the AIR (see [`crate::codegen`]).
+    const SPECIALIZE_GPU_KERNEL: bool = false;
+
+    /// Evaluates the composition constraint on the GPU by lowering it to a
+    /// program for a precompiled interpreter kernel (see
+    /// [`crate::codegen::ConstraintProgram`]). Used when a specialized kernel
+    /// isn't. Only has an effect with the `gpu` feature on Apple Silicon and
+    /// falls back to the CPU if the constraint can't be lowered or the Metal
+    /// library doesn't have the interpreter kernel.
+    const INTERPRET_GPU_CONSTRAINTS: bool = false;
+
+    /// Checks the execution trace holds canonical field elements and values
+    /// within the range of their [`AirConfig::BASE_COLUMN_TYPES`] before it's
+    /// committed to. Enabled in debug builds by default. See
+    /// [`debug::sanitize_base_trace`](crate::debug::sanitize_base_trace).
+    const SANITIZE_WITNESS: bool = cfg!(debug_assertions);
+
     type Fp: GpuFftField<FftField = Self::Fp> + FftField;
     type Fq: StarkExtensionOf<Self::Fp>;
     type PublicInputs: CanonicalSerialize + CanonicalDeserialize + Clone;
 
-    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>>;
+    /// Domain the execution trace is interpolated over and extended to. The
+    /// prover currently only supports radix-2 domains. Other domains, such as
+    /// [`CircleDomain`](crate::circle::CircleDomain) for fields like
+    /// Mersenne31, can be used to evaluate traces with [`Air::extend_trace`].
+    type Domain: TraceDomain<Self::Fp> = Radix2EvaluationDomain<Self::Fp>;
+
+    fn constraints(_trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
+        Vec::new()
+    }
+
+    /// Groups of constraints that share a divisor. These are added to
+    /// [`AirConfig::constraints`].
+    fn constraint_groups(
+        _trace_len: usize,
+    ) -> Vec<ConstraintGroup<FieldVariant<Self::Fp, Self::Fq>>> {
+        Vec::new()
+    }
+
+    /// Values of trace cells pinned in specific rows e.g. the inputs in the
+    /// first row and the output in the last row. Divisors are derived from
+    /// the asserted rows. These are added to [`AirConfig::constraints`].
+    fn assertions(_trace_len: usize) -> Vec<Assertion<FieldVariant<Self::Fp, Self::Fq>>> {
+        Vec::new()
+    }
+
+    /// Values of periodic columns such as round constants. Column `i` repeats
+    /// every `values.len()` rows, which must be a power of two no larger than
+    /// the trace length. Constraints reference column `i` with
+    /// [`periodic_column`].
+    fn periodic_columns() -> Vec<Vec<Self::Fp>> {
+        Vec::new()
+    }
 
     fn gen_hints(
         _trace_len: usize,
@@ -45,7 +143,9 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
 
     /// Combines multiple constraints into a single constraint (the composition
     /// constraint). Constraints are composed with verifiers randomness.
-    /// This verifier randomness is expressed symbolically.
+    /// This verifier randomness is expressed symbolically. Constraint `i` uses
+    /// composition coefficients `2i` and `2i + 1` (see
+    /// [`Stark::gen_composition_constraint_coeffs`](crate::stark::Stark::gen_composition_constraint_coeffs)).
     /// <https://medium.com/starkware/starkdex-deep-dive-the-stark-core-engine-497942d0f0ab>
     fn composition_constraint(
         trace_len: usize,
@@ -64,7 +164,7 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
             .iter()
             .map(|constraint| {
                 let (numerator_degree, denominator_degree) = constraint.degree(trace_degree);
-                let evaluation_degree = numerator_degree - denominator_degree;
+                let evaluation_degree = numerator_degree.saturating_sub(denominator_degree);
                 assert!(evaluation_degree <= composition_degree);
                 let degree_adjustment = composition_degree - evaluation_degree;
                 // TODO: if degree_adjustment is 0 then we only need one challenge
@@ -74,17 +174,13 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
                 &constraint * (x.clone().pow(degree_adjustment) * alpha + beta)
             })
             .sum::<Expr<CompositionItem<FieldVariant<Self::Fp, Self::Fq>>>>();
-        // TODO: remove log and timing
-        let now = Instant::now();
-        let expr = expr.reuse_shared_nodes();
-        println!("Reuse took: {:?}", now.elapsed());
-        CompositionConstraint::new(expr)
+        CompositionConstraint::new(expr.reuse_shared_nodes())
     }
 
     // TODO: maybe move this into a constraint evaluator
     #[allow(clippy::too_many_arguments)]
     fn eval_constraint(
-        composition_constraint: &CompositionConstraint<FieldVariant<Self::Fp, Self::Fq>>,
+        composition_constraint: &CompiledExpr<CompositionItem<FieldVariant<Self::Fp, Self::Fq>>>,
         challenges: &[Self::Fq],
         hints: &[Self::Fq],
         composition_constraint_coeffs: &[Self::Fq],
@@ -93,14 +189,56 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
         base_trace_lde_cols: &[&[Self::Fp]],
         extension_trace_lde_cols: Option<&[&[Self::Fq]]>,
     ) -> Matrix<Self::Fq> {
+        #[cfg(metal_gpu)]
+        if Self::SPECIALIZE_GPU_KERNEL {
+            let kernel = crate::codegen::MetalConstraintKernel::generate(
+                composition_constraint,
+                Self::NUM_BASE_COLUMNS,
+                Self::NUM_EXTENSION_COLUMNS,
+            );
+            if let Ok(res) = kernel.and_then(|kernel| {
+                kernel.eval(
+                    challenges,
+                    hints,
+                    composition_constraint_coeffs,
+                    lde_step,
+                    &x_lde,
+                    base_trace_lde_cols,
+                    extension_trace_lde_cols,
+                )
+            }) {
+                return res;
+            }
+        }
+
+        #[cfg(metal_gpu)]
+        if Self::INTERPRET_GPU_CONSTRAINTS {
+            let program = crate::codegen::ConstraintProgram::lower(
+                composition_constraint,
+                Self::NUM_BASE_COLUMNS,
+                Self::NUM_EXTENSION_COLUMNS,
+            );
+            if let Ok(res) = program.and_then(|program| {
+                program.eval(
+                    challenges,
+                    hints,
+                    composition_constraint_coeffs,
+                    lde_step,
+                    &x_lde,
+                    base_trace_lde_cols,
+                    extension_trace_lde_cols,
+                )
+            }) {
+                return res;
+            }
+        }
+
         let eval_expr = composition_constraint.map_leaves(&mut |leaf| match leaf {
             CompositionItem::Item(item) => *item,
             CompositionItem::CompositionCoeff(i) => {
                 AlgebraicItem::Constant(FieldVariant::Fq(composition_constraint_coeffs[*i]))
             }
         });
-        // TODO: add back in
-        // .reuse_shared_nodes();
         // TODO: GPU constraint eval is currently slower than CPU
         // #[cfg(feature = "gpu")]
         // return crate::eval_gpu::eval::<Self::Fp, Self::Fq>(
@@ -132,29 +270,268 @@ pub fn trace_domain<A: AirConfig>(trace_len: usize) -> Radix2EvaluationDomain<A:
     Radix2EvaluationDomain::new(trace_len).unwrap()
 }
 
+/// Returns periodic column `i` of [`AirConfig::periodic_columns`] for use in
+/// constraints.
+///
+/// The columns of an AIR are interpolated once, the first time one of them is
+/// referenced. The prover extends them over the LDE domain.
+///
+/// # Panics
+/// Panics if the AIR has no periodic column `i`.
+pub fn periodic_column<C: AirConfig>(i: usize) -> Expr<AlgebraicItem<FieldVariant<C::Fp, C::Fq>>> {
+    Expr::Leaf(AlgebraicItem::Periodic(periodic_columns::<C>()[i]))
+}
+
+/// Returns the interpolated periodic columns of an AIR. Columns borrow their
+/// coefficients for `'static` so they are cached per AIR type.
+fn periodic_columns<C: AirConfig>() -> &'static [PeriodicColumn<'static, FieldVariant<C::Fp, C::Fq>>]
+{
+    type Columns = &'static (dyn Any + Send + Sync);
+    static COLUMNS: spin::Mutex<BTreeMap<TypeId, Columns>> = spin::Mutex::new(BTreeMap::new());
+    let columns = *COLUMNS.lock().entry(TypeId::of::<C>()).or_insert_with(|| {
+        let columns = C::periodic_columns()
+            .into_iter()
+            .map(|values| {
+                let interval_size = values.len();
+                let domain = Radix2EvaluationDomain::<C::Fp>::new(interval_size).unwrap();
+                let coeffs = domain.ifft(&values).into_iter().map(FieldVariant::Fp);
+                let coeffs = coeffs.collect::<Vec<FieldVariant<C::Fp, C::Fq>>>().leak();
+                PeriodicColumn::new(coeffs, interval_size)
+            })
+            .collect::<Vec<_>>();
+        Box::leak(Box::new(columns))
+    });
+    columns
+        .downcast_ref::<Vec<PeriodicColumn<'static, FieldVariant<C::Fp, C::Fq>>>>()
+        .unwrap()
+}
+
+/// Returns the AIR's constraints, constraint groups and assertions along with
+/// the range constraints of its typed base columns
+pub(crate) fn all_constraints<C: AirConfig>(
+    trace_len: usize,
+) -> Vec<Constraint<FieldVariant<C::Fp, C::Fq>>> {
+    let mut constraints = C::constraints(trace_len);
+    for group in C::constraint_groups(trace_len) {
+        constraints.extend(group.into_constraints());
+    }
+    for assertion in C::assertions(trace_len) {
+        constraints.push(assertion.into_constraint(trace_len));
+    }
+    if !C::BASE_COLUMN_TYPES.is_empty() {
+        assert_eq!(
+            crate::trace::num_columns(C::BASE_COLUMN_TYPES),
+            C::NUM_BASE_COLUMNS
+        );
+        constraints.extend(crate::trace::range_constraints(
+            C::BASE_COLUMN_TYPES,
+            trace_len,
+        ));
+    }
+    constraints
+}
+
+/// Encodes the column layout and the compiled composition constraint of `C`
+fn describe<C: AirConfig>(
+    composition_constraint: &CompiledExpr<CompositionItem<FieldVariant<C::Fp, C::Fq>>>,
+) -> Vec<u8> {
+    use AlgebraicItem::*;
+    let mut bytes = Vec::new();
+    let write_int = |bytes: &mut Vec<u8>, tag: u8, values: &[u64]| {
+        bytes.push(tag);
+        for value in values {
+            bytes.extend(value.to_le_bytes());
+        }
+    };
+    let write_value = |bytes: &mut Vec<u8>, value: &FieldVariant<C::Fp, C::Fq>| match value {
+        FieldVariant::Fp(v) => {
+            bytes.push(0);
+            v.serialize_compressed(bytes).unwrap();
+        }
+        FieldVariant::Fq(v) => {
+            bytes.push(1);
+            v.serialize_compressed(bytes).unwrap();
+        }
+    };
+    write_int(
+        &mut bytes,
+        0,
+        &[
+            C::NUM_BASE_COLUMNS as u64,
+            C::NUM_EXTENSION_COLUMNS as u64,
+            C::NUM_PREPROCESSED_COLUMNS as u64,
+        ],
+    );
+    C::domain_offset().serialize_compressed(&mut bytes).unwrap();
+    for instruction in composition_constraint.instructions() {
+        match instruction {
+            Instruction::Leaf(CompositionItem::CompositionCoeff(i)) => {
+                write_int(&mut bytes, 0, &[*i as u64]);
+            }
+            Instruction::Leaf(CompositionItem::Item(item)) => match item {
+                X => write_int(&mut bytes, 1, &[]),
+                Constant(v) => {
+                    write_int(&mut bytes, 2, &[]);
+                    write_value(&mut bytes, v);
+                }
+                Challenge(i) => write_int(&mut bytes, 3, &[*i as u64]),
+                Periodic(column) => {
+                    let coeffs = column.coeffs();
+                    write_int(
+                        &mut bytes,
+                        4,
+                        &[column.interval_size() as u64, coeffs.len() as u64],
+                    );
+                    for coeff in coeffs {
+                        write_value(&mut bytes, coeff);
+                    }
+                }
+                Hint(i) => write_int(&mut bytes, 5, &[*i as u64]),
+                Trace(col, offset) => {
+                    let offset = u64::from_le_bytes((*offset as i64).to_le_bytes());
+                    write_int(&mut bytes, 6, &[*col as u64, offset]);
+                }
+            },
+            Instruction::Neg(a) => write_int(&mut bytes, 7, &[*a as u64]),
+            Instruction::Add(a, b) => write_int(&mut bytes, 8, &[*a as u64, *b as u64]),
+            Instruction::Mul(a, b) => write_int(&mut bytes, 9, &[*a as u64, *b as u64]),
+            Instruction::Div(a, b) => write_int(&mut bytes, 10, &[*a as u64, *b as u64]),
+            Instruction::Pow(a, n) => write_int(&mut bytes, 11, &[*a as u64, *n as u64]),
+        }
+    }
+    bytes
+}
+
+/// Builds the GPU FFT plans for proving traces of each length in `trace_lens`.
+///
+/// Twiddles and pipelines are built ahead of time so call this during
+/// application startup to keep them off the critical path of the first proof.
+/// Has no effect when proofs are generated on the CPU.
+#[cfg_attr(not(metal_gpu), allow(clippy::missing_const_for_fn))]
+pub fn reserve_gpu_domains<C: AirConfig>(trace_lens: &[usize], options: ProofOptions) {
+    #[cfg(metal_gpu)]
+    {
+        let offset = C::domain_offset();
+        let lde_blowup_factor = usize::from(options.lde_blowup_factor);
+        let mut domains = Vec::new();
+        for &trace_len in trace_lens {
+            let constraints = all_constraints::<C>(trace_len);
+            let composition_constraint = C::composition_constraint(trace_len, &constraints);
+            let ce_blowup_factor = composition_constraint.blowup_factor(trace_len);
+            let ce_domain_size = trace_len * ce_blowup_factor;
+            let lde_domain_size = trace_len * lde_blowup_factor;
+            domains.push(trace_domain::<C>(trace_len));
+            domains.push(Radix2EvaluationDomain::new_coset(ce_domain_size, offset).unwrap());
+            domains.push(Radix2EvaluationDomain::new_coset(lde_domain_size, offset).unwrap());
+        }
+        ministark_gpu::plan::reserve::<C::Fp>(&domains);
+        ministark_gpu::plan::reserve::<C::Fq>(&domains);
+    }
+    #[cfg(not(metal_gpu))]
+    let _ = (trace_lens, options);
+}
+
+/// Extension columns built and committed to in the same round (see
+/// [`AirConfig::AUX_SEGMENTS`])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuxSegment {
+    /// Number of extension columns in the segment
+    pub num_columns: usize,
+    /// Number of challenges drawn before the segment is built. Challenges are
+    /// indexed after the challenges of previous segments.
+    pub num_challenges: usize,
+}
+
 pub struct Air<AC: AirConfig> {
-    constraints: Vec<Constraint<FieldVariant<AC::Fp, AC::Fq>>>,
-    composition_constraint: CompositionConstraint<FieldVariant<AC::Fp, AC::Fq>>,
-    ce_blowup_factor: usize,
+    constraints: Arc<AirConstraints<AC>>,
     trace_len: usize,
     options: ProofOptions,
     public_inputs: AC::PublicInputs,
 }
 
+/// Constraints of an AIR. These only depend on the trace length so they are
+/// shared between AIRs with different public inputs.
+struct AirConstraints<AC: AirConfig> {
+    constraints: Vec<Constraint<FieldVariant<AC::Fp, AC::Fq>>>,
+    composition_constraint: CompositionConstraint<FieldVariant<AC::Fp, AC::Fq>>,
+    compiled_composition_constraint: CompiledExpr<CompositionItem<FieldVariant<AC::Fp, AC::Fq>>>,
+    ce_blowup_factor: usize,
+    description: Vec<u8>,
+}
+
+impl<C: AirConfig> Clone for Air<C> {
+    fn clone(&self) -> Self {
+        Self {
+            constraints: Arc::clone(&self.constraints),
+            trace_len: self.trace_len,
+            options: self.options,
+            public_inputs: self.public_inputs.clone(),
+        }
+    }
+}
+
 impl<C: AirConfig> Air<C> {
+    /// # Panics
+    /// Panics if the LDE blowup factor is smaller than the constraint
+    /// evaluation blowup factor. See [`Air::try_new`].
     pub fn new(trace_len: usize, public_inputs: C::PublicInputs, options: ProofOptions) -> Self {
-        let constraints = C::constraints(trace_len);
+        Self::try_new(trace_len, public_inputs, options).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Builds the AIR for a trace of length `trace_len`. The degree of each
+    /// constraint is inferred from its expression tree which determines the
+    /// constraint evaluation blowup factor and composition degree. An error is
+    /// returned if the LDE blowup factor in `options` is too small to evaluate
+    /// the constraints.
+    pub fn try_new(
+        trace_len: usize,
+        public_inputs: C::PublicInputs,
+        options: ProofOptions,
+    ) -> Result<Self, InvalidProofOptions> {
+        let constraints = all_constraints::<C>(trace_len);
         let composition_constraint = C::composition_constraint(trace_len, &constraints);
         let ce_blowup_factor = composition_constraint.blowup_factor(trace_len);
-        assert!(ce_blowup_factor <= options.lde_blowup_factor.into());
+        if ce_blowup_factor > options.lde_blowup_factor.into() {
+            return Err(InvalidProofOptions::InsufficientLdeBlowupFactor {
+                lde_blowup_factor: options.lde_blowup_factor,
+                ce_blowup_factor,
+            });
+        }
+        let lde_domain_size = trace_len * usize::from(options.lde_blowup_factor);
+        if options.query_sampling == QuerySampling::Distinct
+            && usize::from(options.num_queries) > lde_domain_size
+        {
+            return Err(InvalidProofOptions::TooManyDistinctQueries {
+                num_queries: options.num_queries,
+                lde_domain_size,
+            });
+        }
+        let compiled_composition_constraint = composition_constraint.compile();
+        let description = describe::<C>(&compiled_composition_constraint);
 
-        Self {
-            constraints,
-            composition_constraint,
-            ce_blowup_factor,
+        Ok(Self {
+            constraints: Arc::new(AirConstraints {
+                constraints,
+                composition_constraint,
+                compiled_composition_constraint,
+                ce_blowup_factor,
+                description,
+            }),
             trace_len,
             options,
             public_inputs,
+        })
+    }
+
+    /// Returns the AIR for different public inputs. Constraints are shared
+    /// with `self` rather than built again.
+    #[must_use]
+    pub fn with_public_inputs(&self, public_inputs: C::PublicInputs) -> Self {
+        Self {
+            constraints: Arc::clone(&self.constraints),
+            trace_len: self.trace_len,
+            options: self.options,
+            public_inputs,
         }
     }
 
@@ -170,19 +547,19 @@ impl<C: AirConfig> Air<C> {
         &self.public_inputs
     }
 
-    pub const fn ce_blowup_factor(&self) -> usize {
-        self.ce_blowup_factor
+    pub fn ce_blowup_factor(&self) -> usize {
+        self.constraints.ce_blowup_factor
     }
 
     /// Returns a degree that all constraint polynomials must be normalized to.
-    pub const fn composition_degree(&self) -> usize {
+    pub fn composition_degree(&self) -> usize {
         let ce_domain_size = self.trace_len * self.ce_blowup_factor();
         ce_domain_size - 1
     }
 
     pub fn num_challenges(&self) -> usize {
         let mut num_challenges = 0;
-        for constraint in &self.constraints {
+        for constraint in &self.constraints.constraints {
             constraint.traverse(&mut |node| {
                 if let Expr::Leaf(AlgebraicItem::Challenge(i)) = node {
                     num_challenges = core::cmp::max(num_challenges, *i + 1);
@@ -192,17 +569,46 @@ impl<C: AirConfig> Air<C> {
         num_challenges
     }
 
+    /// Returns the auxiliary segments of the AIR (see
+    /// [`AirConfig::AUX_SEGMENTS`]). AIRs that don't declare segments have a
+    /// single segment with every extension column and challenge.
+    pub fn aux_segments(&self) -> Vec<AuxSegment> {
+        if C::AUX_SEGMENTS.is_empty() {
+            return vec![AuxSegment {
+                num_columns: C::NUM_EXTENSION_COLUMNS,
+                num_challenges: self.num_challenges(),
+            }];
+        }
+        assert!(
+            C::AUX_SEGMENTS.iter().all(|s| s.num_columns != 0),
+            "segments must have at least one column"
+        );
+        let num_columns = C::AUX_SEGMENTS.iter().map(|s| s.num_columns).sum::<usize>();
+        assert_eq!(
+            num_columns,
+            C::NUM_EXTENSION_COLUMNS,
+            "segment widths don't match"
+        );
+        C::AUX_SEGMENTS.to_vec()
+    }
+
+    pub fn num_aux_segments(&self) -> usize {
+        self.aux_segments().len()
+    }
+
     pub fn gen_hints(&self, challenges: &Challenges<C::Fq>) -> Hints<C::Fq> {
         C::gen_hints(self.trace_len(), self.public_inputs(), challenges)
     }
 
     pub fn num_composition_constraint_coeffs(&self) -> usize {
         let mut num_coeffs = 0;
-        self.composition_constraint.traverse(&mut |node| {
-            if let Expr::Leaf(CompositionItem::CompositionCoeff(i)) = node {
-                num_coeffs = num_coeffs.max(i + 1);
-            }
-        });
+        self.constraints
+            .composition_constraint
+            .traverse(&mut |node| {
+                if let Expr::Leaf(CompositionItem::CompositionCoeff(i)) = node {
+                    num_coeffs = num_coeffs.max(i + 1);
+                }
+            });
         num_coeffs
     }
 
@@ -210,6 +616,28 @@ impl<C: AirConfig> Air<C> {
         trace_domain::<C>(self.trace_len)
     }
 
+    /// Trace domain of [`AirConfig::Domain`]
+    pub fn evaluation_domain(&self) -> C::Domain {
+        C::Domain::trace_domain(self.trace_len).unwrap()
+    }
+
+    /// Low degree extends trace columns over the LDE domain of
+    /// [`AirConfig::Domain`]. Unlike the prover this supports any domain.
+    pub fn extend_trace(&self, columns: &Matrix<C::Fp>) -> Matrix<C::Fp> {
+        let trace_domain = self.evaluation_domain();
+        let lde_domain = trace_domain
+            .lde_domain(self.lde_blowup_factor(), C::domain_offset())
+            .unwrap();
+        Matrix::new(
+            ark_std::cfg_iter!(columns)
+                .map(|column| {
+                    let coeffs = trace_domain.interpolate(column);
+                    lde_domain.evaluate(&coeffs).to_vec_in(GpuAllocator)
+                })
+                .collect(),
+        )
+    }
+
     /// Low degree extension domain
     pub fn lde_domain(&self) -> Radix2EvaluationDomain<C::Fp> {
         let offset = C::domain_offset();
@@ -232,14 +660,37 @@ impl<C: AirConfig> Air<C> {
         self.options.lde_blowup_factor as usize
     }
 
-    pub const fn composition_constraint(
+    pub fn composition_constraint(&self) -> &CompositionConstraint<FieldVariant<C::Fp, C::Fq>> {
+        &self.constraints.composition_constraint
+    }
+
+    /// Returns the composition constraint lowered into a form that's cheap to
+    /// evaluate. The same compiled constraint is used by the prover to
+    /// evaluate over the constraint evaluation domain and by the verifier to
+    /// evaluate at the out-of-domain point.
+    pub fn compiled_composition_constraint(
         &self,
-    ) -> &CompositionConstraint<FieldVariant<C::Fp, C::Fq>> {
-        &self.composition_constraint
+    ) -> &CompiledExpr<CompositionItem<FieldVariant<C::Fp, C::Fq>>> {
+        &self.constraints.compiled_composition_constraint
+    }
+
+    /// Canonical encoding of the constraints of the AIR. AIRs with the same
+    /// description for a trace length accept the same traces. The description
+    /// is absorbed into the public coin so a proof is bound to the AIR it was
+    /// generated for (see [`crate::channel::absorb_instance`]).
+    pub fn description(&self) -> &[u8] {
+        &self.constraints.description
+    }
+
+    /// Hash of [`Air::description`] that identifies the AIR e.g. when
+    /// aggregating proofs of different AIRs
+    pub fn digest<H: HashFn>(&self) -> H::Digest {
+        H::hash(self.description().iter().copied())
     }
 
     pub fn trace_arguments(&self) -> BTreeSet<(usize, isize)> {
         self.constraints
+            .constraints
             .iter()
             .map(Constraint::trace_arguments)
             .fold(BTreeSet::new(), |a, b| &a | &b)
@@ -0,0 +1,358 @@
+//! Proving several independent AIRs in a single proof.
+//!
+//! [`AirComposition`] combines two AIRs over the same fields and trace length
+//! into one AIR. The traces of both AIRs are committed to together and share
+//! a single composition polynomial and FRI instance so e.g. a CPU AIR and a
+//! hash coprocessor AIR can be proven with one commitment phase. Compositions
+//! nest to combine more than two AIRs.
+//!
+//! Columns are laid out as:
+//!
+//! ```text
+//! ┌────────────┬────────────┬─────────────────┬─────────────────┐
+//! │ A base     │ B base     │ A extension     │ B extension     │
+//! └────────────┴────────────┴─────────────────┴─────────────────┘
+//! ```
+//!
+//! Challenges and hints of `B` are numbered after those of `A`.
+//!
+//! Tables don't need the same number of rows. A table with fewer rows (e.g.
+//! a rarely used coprocessor) is composed as a [`Subdomain`] which proves it
+//! over its own smaller trace domain with its own divisors.
+use crate::air::all_constraints;
+use crate::air::AirConfig;
+use crate::air::AuxSegment;
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::expression::Expr;
+use crate::hints::Hints;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use crate::Trace;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use core::marker::PhantomData;
+use num_traits::Pow;
+
+/// AIR that constrains the trace of `A` and the trace of `B` side by side
+pub struct AirComposition<A, B>(PhantomData<(A, B)>);
+
+impl<A: AirConfig, B: AirConfig<Fp = A::Fp, Fq = A::Fq>> AirComposition<A, B> {
+    /// Returns the number of challenges and hints used by the constraints of
+    /// `A`. These offset the challenges and hints of `B`.
+    fn offsets(trace_len: usize) -> (usize, usize) {
+        let mut num_challenges = 0;
+        let mut num_hints = 0;
+        for constraint in all_constraints::<A>(trace_len) {
+            constraint.traverse(&mut |node| match *node {
+                Expr::Leaf(AlgebraicItem::Challenge(i)) => {
+                    num_challenges = num_challenges.max(i + 1);
+                }
+                Expr::Leaf(AlgebraicItem::Hint(i)) => {
+                    num_hints = num_hints.max(i + 1);
+                }
+                _ => {}
+            });
+        }
+        (num_challenges, num_hints)
+    }
+
+    /// Combines the traces of `A` and `B`
+    ///
+    /// # Panics
+    /// Panics if the traces have different lengths
+    pub fn compose_traces<TA, TB>(a: TA, b: TB) -> TraceComposition<TA, TB>
+    where
+        TA: Trace<Fp = A::Fp, Fq = A::Fq>,
+        TB: Trace<Fp = A::Fp, Fq = A::Fq>,
+    {
+        assert_eq!(a.len(), b.len(), "traces must have the same length");
+        let (num_a_challenges, _) = Self::offsets(a.len());
+        let base_columns = Matrix::join(vec![a.base_columns().clone(), b.base_columns().clone()]);
+        TraceComposition {
+            a,
+            b,
+            base_columns,
+            num_a_challenges,
+        }
+    }
+}
+
+impl<A: AirConfig, B: AirConfig<Fp = A::Fp, Fq = A::Fq>> AirConfig for AirComposition<A, B> {
+    const NUM_BASE_COLUMNS: usize = A::NUM_BASE_COLUMNS + B::NUM_BASE_COLUMNS;
+    const NUM_EXTENSION_COLUMNS: usize = A::NUM_EXTENSION_COLUMNS + B::NUM_EXTENSION_COLUMNS;
+    const SANITIZE_WITNESS: bool = A::SANITIZE_WITNESS || B::SANITIZE_WITNESS;
+    type Fp = A::Fp;
+    type Fq = A::Fq;
+    type PublicInputs = (A::PublicInputs, B::PublicInputs);
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<A::Fp, A::Fq>>> {
+        assert!(
+            A::NUM_PREPROCESSED_COLUMNS == 0 && B::NUM_PREPROCESSED_COLUMNS == 0,
+            "composed AIRs can't have preprocessed columns"
+        );
+        assert!(
+            A::AUX_SEGMENTS.is_empty() && B::AUX_SEGMENTS.is_empty(),
+            "composed AIRs must build their extension columns in a single round"
+        );
+        assert!(
+            A::domain_offset() == B::domain_offset(),
+            "composed AIRs must use the same domain offset"
+        );
+        let (num_a_challenges, num_a_hints) = Self::offsets(trace_len);
+        let a_constraints = all_constraints::<A>(trace_len)
+            .into_iter()
+            .map(|constraint| {
+                translate(
+                    &constraint,
+                    A::NUM_BASE_COLUMNS,
+                    0,
+                    B::NUM_BASE_COLUMNS,
+                    0,
+                    0,
+                )
+            });
+        let b_constraints = all_constraints::<B>(trace_len)
+            .into_iter()
+            .map(|constraint| {
+                translate(
+                    &constraint,
+                    B::NUM_BASE_COLUMNS,
+                    A::NUM_BASE_COLUMNS,
+                    A::NUM_BASE_COLUMNS + A::NUM_EXTENSION_COLUMNS,
+                    num_a_challenges,
+                    num_a_hints,
+                )
+            });
+        a_constraints.chain(b_constraints).collect()
+    }
+
+    fn gen_hints(
+        trace_len: usize,
+        (a_public_inputs, b_public_inputs): &Self::PublicInputs,
+        challenges: &Challenges<A::Fq>,
+    ) -> Hints<A::Fq> {
+        let (num_a_challenges, num_a_hints) = Self::offsets(trace_len);
+        let (a_challenges, b_challenges) = challenges.split_at(num_a_challenges);
+        let a_challenges = Challenges::new(a_challenges.to_vec());
+        let b_challenges = Challenges::new(b_challenges.to_vec());
+        let a_hints = A::gen_hints(trace_len, a_public_inputs, &a_challenges);
+        let b_hints = B::gen_hints(trace_len, b_public_inputs, &b_challenges);
+        assert!(a_hints.len() >= num_a_hints);
+        let a_hints = a_hints.iter().take(num_a_hints).copied().enumerate();
+        let b_hints = b_hints
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (num_a_hints + i, v));
+        Hints::new(a_hints.chain(b_hints).collect())
+    }
+
+    fn domain_offset() -> A::Fp {
+        A::domain_offset()
+    }
+}
+
+/// Moves the columns, challenges and hints of a constraint to their position
+/// in the composed AIR. Base columns are shifted by `base_shift` and
+/// extension columns (those at or after `num_base_columns`) by
+/// `extension_shift`.
+fn translate<Fp: Field, Fq: Field>(
+    constraint: &Constraint<FieldVariant<Fp, Fq>>,
+    num_base_columns: usize,
+    base_shift: usize,
+    extension_shift: usize,
+    challenge_shift: usize,
+    hint_shift: usize,
+) -> Constraint<FieldVariant<Fp, Fq>> {
+    use AlgebraicItem::*;
+    let expression = constraint.map_leaves(&mut |leaf| match *leaf {
+        Trace(col, offset) if col < num_base_columns => Trace(col + base_shift, offset),
+        Trace(col, offset) => Trace(col + extension_shift, offset),
+        Challenge(i) => Challenge(i + challenge_shift),
+        Hint(i) => Hint(i + hint_shift),
+        leaf => leaf,
+    });
+    match constraint.name() {
+        Some(name) => Constraint::named(name, expression),
+        None => Constraint::new(expression),
+    }
+}
+
+/// Execution trace of an [`AirComposition`] (see
+/// [`AirComposition::compose_traces`])
+pub struct TraceComposition<TA: Trace, TB> {
+    a: TA,
+    b: TB,
+    base_columns: Matrix<TA::Fp>,
+    num_a_challenges: usize,
+}
+
+impl<TA: Trace, TB: Trace<Fp = TA::Fp, Fq = TA::Fq>> Trace for TraceComposition<TA, TB> {
+    type Fp = TA::Fp;
+    type Fq = TA::Fq;
+
+    fn base_columns(&self) -> &Matrix<TA::Fp> {
+        &self.base_columns
+    }
+
+    fn build_extension_columns(&self, challenges: &Challenges<TA::Fq>) -> Option<Matrix<TA::Fq>> {
+        let (a_challenges, b_challenges) = challenges.split_at(self.num_a_challenges);
+        let a_challenges = Challenges::new(a_challenges.to_vec());
+        let b_challenges = Challenges::new(b_challenges.to_vec());
+        let columns = [
+            self.a.build_extension_columns(&a_challenges),
+            self.b.build_extension_columns(&b_challenges),
+        ];
+        let columns = columns.into_iter().flatten().collect::<Vec<_>>();
+        (!columns.is_empty()).then(|| Matrix::join(columns))
+    }
+}
+
+/// AIR `A` over a trace domain `RATIO` times smaller than the trace length it
+/// is proven with.
+///
+/// A table of `n` rows is proven alongside a trace of `RATIO * n` rows
+/// without padding the table. The trace polynomial `t` of each column of `A`
+/// is committed to as `t(x^RATIO)`, whose evaluations over the larger trace
+/// domain repeat the `n` rows of the table `RATIO` times, and the constraints
+/// of `A` are evaluated at `x^RATIO`. Constraints and divisors therefore hold
+/// over the subgroup of order `n` and the constraints of `A` are generated
+/// for a trace of length `n`. `RATIO` must be a power of two.
+///
+/// Use [`Subdomain::extend_trace`] to extend the trace of `A` to the longer
+/// trace length.
+pub struct Subdomain<A, const RATIO: usize>(PhantomData<A>);
+
+impl<A: AirConfig, const RATIO: usize> Subdomain<A, RATIO> {
+    /// Returns the trace length of `A`
+    ///
+    /// # Panics
+    /// Panics if `RATIO` isn't a power of two or exceeds `trace_len`
+    fn subdomain_len(trace_len: usize) -> usize {
+        assert!(RATIO.is_power_of_two(), "ratio must be a power of two");
+        assert!(
+            trace_len >= RATIO,
+            "trace length {trace_len} is shorter than the ratio {RATIO}"
+        );
+        trace_len / RATIO
+    }
+
+    /// Extends the trace of `A` to `RATIO` times its length
+    pub fn extend_trace<T: Trace<Fp = A::Fp, Fq = A::Fq>>(trace: T) -> SubdomainTrace<T> {
+        let base_columns = repeat_rows(trace.base_columns(), RATIO);
+        SubdomainTrace {
+            trace,
+            base_columns,
+            ratio: RATIO,
+        }
+    }
+}
+
+impl<A: AirConfig, const RATIO: usize> AirConfig for Subdomain<A, RATIO> {
+    const NUM_BASE_COLUMNS: usize = A::NUM_BASE_COLUMNS;
+    const NUM_EXTENSION_COLUMNS: usize = A::NUM_EXTENSION_COLUMNS;
+    const AUX_SEGMENTS: &'static [AuxSegment] = A::AUX_SEGMENTS;
+    const SANITIZE_WITNESS: bool = A::SANITIZE_WITNESS;
+    type Fp = A::Fp;
+    type Fq = A::Fq;
+    type PublicInputs = A::PublicInputs;
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<A::Fp, A::Fq>>> {
+        assert!(
+            A::NUM_PREPROCESSED_COLUMNS == 0,
+            "AIRs over a subdomain can't have preprocessed columns"
+        );
+        // periodic columns repeat every `interval_size` rows of the larger
+        // trace as well so only `x` is substituted
+        all_constraints::<A>(Self::subdomain_len(trace_len))
+            .into_iter()
+            .map(|constraint| {
+                let expression = constraint.map(&mut |node| match node {
+                    Expr::Leaf(AlgebraicItem::X) => Expr::Leaf(AlgebraicItem::X).pow(RATIO),
+                    node => node,
+                });
+                match constraint.name() {
+                    Some(name) => Constraint::named(name, expression),
+                    None => Constraint::new(expression),
+                }
+            })
+            .collect()
+    }
+
+    fn gen_hints(
+        trace_len: usize,
+        public_inputs: &A::PublicInputs,
+        challenges: &Challenges<A::Fq>,
+    ) -> Hints<A::Fq> {
+        A::gen_hints(Self::subdomain_len(trace_len), public_inputs, challenges)
+    }
+
+    fn domain_offset() -> A::Fp {
+        A::domain_offset()
+    }
+}
+
+/// Execution trace of a [`Subdomain`] AIR (see [`Subdomain::extend_trace`])
+pub struct SubdomainTrace<T: Trace> {
+    trace: T,
+    base_columns: Matrix<T::Fp>,
+    ratio: usize,
+}
+
+impl<T: Trace> Trace for SubdomainTrace<T> {
+    type Fp = T::Fp;
+    type Fq = T::Fq;
+
+    fn base_columns(&self) -> &Matrix<T::Fp> {
+        &self.base_columns
+    }
+
+    fn build_extension_columns(&self, challenges: &Challenges<T::Fq>) -> Option<Matrix<T::Fq>> {
+        let columns = self.trace.build_extension_columns(challenges)?;
+        Some(repeat_rows(&columns, self.ratio))
+    }
+
+    fn build_aux_segment(
+        &self,
+        segment: usize,
+        aux_segments: &[Matrix<T::Fq>],
+        challenges: &Challenges<T::Fq>,
+    ) -> Option<Matrix<T::Fq>> {
+        // previous segments are passed to `T` with their original length
+        let n = self.trace.len();
+        let aux_segments = aux_segments
+            .iter()
+            .map(|columns| {
+                Matrix::new(
+                    columns
+                        .iter()
+                        .map(|column| column[..n].to_vec_in(GpuAllocator))
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let columns = self
+            .trace
+            .build_aux_segment(segment, &aux_segments, challenges)?;
+        Some(repeat_rows(&columns, self.ratio))
+    }
+}
+
+/// Returns a matrix holding the rows of `matrix` repeated `times` times
+fn repeat_rows<F: Field>(matrix: &Matrix<F>, times: usize) -> Matrix<F> {
+    Matrix::new(
+        matrix
+            .iter()
+            .map(|col| {
+                let mut repeated = GpuVec::with_capacity_in(col.len() * times, GpuAllocator);
+                for _ in 0..times {
+                    repeated.extend_from_slice(col);
+                }
+                repeated
+            })
+            .collect(),
+    )
+}
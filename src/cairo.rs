@@ -0,0 +1,852 @@
+//! Proving executions of Cairo programs.
+//!
+//! [`CairoExecution::read`] parses the binary trace and memory files written
+//! by `cairo-run --trace_file <trace> --memory_file <memory>` and
+//! [`CairoTrace::new`] lays the execution out as a trace of
+//! [`CairoAirConfig`] over the Stark252 field. Programs must be run with
+//! `--proof_mode` so the number of steps is a power of two and the program
+//! ends in an infinite loop (`jmp rel 0`).
+//!
+//! Each row holds one step of the CPU: the registers, the instruction decoded
+//! into its 15 flags and 3 offsets and the 4 memory cells the step accesses
+//! (instruction, `dst`, `op0` and `op1`). Transition constraints are those of
+//! section 4.5 of the [Cairo paper](https://eprint.iacr.org/2021/1063).
+//!
+//! Memory is checked with a permutation argument between the cells accessed
+//! and the same cells sorted by address. Sorted addresses increase by at most
+//! one from one cell to the next and each address holds a single value. An
+//! extra access per row fills addresses the program never touches. Unused
+//! extra accesses are `(0, 0)` dummies and the verifier swaps dummies for
+//! public memory cells (e.g. the program) when checking the permutation. This
+//! binds the execution to the public memory. Address `0` is reserved for
+//! dummies, which `cairo-run` never uses.
+//!
+//! Offsets are range checked to 16 bits the same way. Sorted offsets start at
+//! [`CairoPublicInputs::rc_min`], end at [`CairoPublicInputs::rc_max`] and
+//! increase by at most one. An extra offset per row fills gaps.
+//!
+//! Builtins aren't supported.
+use crate::air::AirConfig;
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Assertion;
+use crate::constraints::ConstraintGroup;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::Hint;
+use crate::constraints::VerifierChallenge;
+use crate::expression::Expr;
+use crate::hash::HashFn;
+use crate::hash::Sha256HashFn;
+use crate::hints::Hints;
+use crate::merkle::MatrixMerkleTreeImpl;
+use crate::random::PublicCoin;
+use crate::random::PublicCoinImpl;
+use crate::stark::Stark;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::utils::SerdeOutput;
+use crate::Air;
+use crate::Matrix;
+use crate::Trace;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::array;
+use core::iter::zip;
+use ark_ff::batch_inversion;
+use ark_ff::BigInt;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+use sha2::Sha256;
+use snafu::OptionExt;
+use snafu::ResultExt;
+use snafu::Snafu;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Size of an entry of the trace file: `ap`, `fp` and `pc`
+const TRACE_ENTRY_SIZE: usize = 3 * 8;
+
+/// Size of an entry of the memory file: an address and a field element
+const MEMORY_ENTRY_SIZE: usize = 8 + 32;
+
+/// Offsets are encoded with this bias so they fit in 16 bits
+const OFFSET_BIAS: u64 = 1 << 15;
+
+/// Errors reading an execution or laying it out as a trace
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("failed to read {path:?}: {source}"))]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("{len} bytes isn't a whole number of {entry_size} byte entries"))]
+    Truncated { len: usize, entry_size: usize },
+    #[snafu(display("memory cell {address} holds a value that isn't a field element"))]
+    InvalidValue { address: u64 },
+    #[snafu(display("memory cell {address} is assigned more than once"))]
+    DuplicateAddress { address: u64 },
+    #[snafu(display("{len} steps isn't a power of two (run cairo-run with `--proof_mode`)"))]
+    TraceLength { len: usize },
+    #[snafu(display("step {step} accesses an address that isn't a 64-bit integer"))]
+    InvalidAddress { step: usize },
+    #[snafu(display("memory cell {address} is accessed but has no value"))]
+    MissingMemory { address: u64 },
+    #[snafu(display("step {step} has an invalid instruction"))]
+    InvalidInstruction { step: usize },
+    #[snafu(display("{num_unused} unused memory cells and {num_public} public memory cells don't fit in {num_rows} rows"))]
+    TooManyMemoryCells {
+        num_unused: usize,
+        num_public: usize,
+        num_rows: usize,
+    },
+    #[snafu(display("{num_unused} unused offsets don't fit in {num_rows} rows"))]
+    TooManyOffsets { num_unused: usize, num_rows: usize },
+}
+
+/// Registers of the CPU before a step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegisterState {
+    pub ap: u64,
+    pub fp: u64,
+    pub pc: u64,
+}
+
+/// Parses a trace file written by `cairo-run --trace_file`
+pub fn parse_trace(bytes: &[u8]) -> Result<Vec<RegisterState>, Error> {
+    let entries = bytes.chunks_exact(TRACE_ENTRY_SIZE);
+    if !entries.remainder().is_empty() {
+        return TruncatedSnafu {
+            len: bytes.len(),
+            entry_size: TRACE_ENTRY_SIZE,
+        }
+        .fail();
+    }
+    Ok(entries
+        .map(|entry| {
+            let [ap, fp, pc] = [0, 1, 2].map(|i| read_u64(&entry[i * 8..]));
+            RegisterState { ap, fp, pc }
+        })
+        .collect())
+}
+
+/// Parses a memory file written by `cairo-run --memory_file`
+pub fn parse_memory(bytes: &[u8]) -> Result<BTreeMap<u64, Fp>, Error> {
+    let entries = bytes.chunks_exact(MEMORY_ENTRY_SIZE);
+    if !entries.remainder().is_empty() {
+        return TruncatedSnafu {
+            len: bytes.len(),
+            entry_size: MEMORY_ENTRY_SIZE,
+        }
+        .fail();
+    }
+    let mut memory = BTreeMap::new();
+    for entry in entries {
+        let address = read_u64(entry);
+        let limbs = [0, 1, 2, 3].map(|i| read_u64(&entry[8 + i * 8..]));
+        let value = Fp::from_bigint(BigInt(limbs)).context(InvalidValueSnafu { address })?;
+        if memory.insert(address, value).is_some() {
+            return DuplicateAddressSnafu { address }.fail();
+        }
+    }
+    Ok(memory)
+}
+
+/// Reads a little-endian `u64` from the start of `bytes`
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+/// Returns the value as a `u64` if it's small enough
+fn to_u64(value: Fp) -> Option<u64> {
+    let BigInt([limb, rest @ ..]) = value.into_bigint();
+    (rest == [0; 3]).then_some(limb)
+}
+
+/// Registers at each step and the final memory of a run of `cairo-run`
+#[derive(Clone, Debug)]
+pub struct CairoExecution {
+    pub registers: Vec<RegisterState>,
+    pub memory: BTreeMap<u64, Fp>,
+}
+
+impl CairoExecution {
+    /// Reads the trace and memory files written by `cairo-run`
+    pub fn read(
+        trace_path: impl AsRef<Path>,
+        memory_path: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        let read = |path: &Path| std::fs::read(path).context(IoSnafu { path });
+        let registers = parse_trace(&read(trace_path.as_ref())?)?;
+        let memory = parse_memory(&read(memory_path.as_ref())?)?;
+        Ok(Self { registers, memory })
+    }
+}
+
+/// Public inputs of [`CairoAirConfig`]
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CairoPublicInputs {
+    pub initial_pc: u64,
+    /// Value of `ap` and `fp` in the first step
+    pub initial_ap: u64,
+    pub final_pc: u64,
+    pub final_ap: u64,
+    /// Smallest biased offset of any instruction
+    pub rc_min: u16,
+    /// Largest biased offset of any instruction
+    pub rc_max: u16,
+    /// Memory cells known to the verifier e.g. the program and its output
+    pub public_memory: Vec<(u64, Fp)>,
+}
+
+/// Indices of the flags of an instruction
+pub mod flag {
+    pub const DST_REG: usize = 0;
+    pub const OP0_REG: usize = 1;
+    pub const OP1_IMM: usize = 2;
+    pub const OP1_FP: usize = 3;
+    pub const OP1_AP: usize = 4;
+    pub const RES_ADD: usize = 5;
+    pub const RES_MUL: usize = 6;
+    pub const PC_JUMP_ABS: usize = 7;
+    pub const PC_JUMP_REL: usize = 8;
+    pub const PC_JNZ: usize = 9;
+    pub const AP_ADD: usize = 10;
+    pub const AP_ADD1: usize = 11;
+    pub const OPCODE_CALL: usize = 12;
+    pub const OPCODE_RET: usize = 13;
+    pub const OPCODE_ASSERT_EQ: usize = 14;
+    pub const NUM_FLAGS: usize = 15;
+}
+
+/// Columns of [`CairoAirConfig`]
+pub mod column {
+    use super::flag::NUM_FLAGS;
+
+    /// One column per flag of the instruction
+    pub const FLAGS: usize = 0;
+    /// Biased offsets of the instruction
+    pub const OFF_DST: usize = FLAGS + NUM_FLAGS;
+    pub const OFF_OP0: usize = OFF_DST + 1;
+    pub const OFF_OP1: usize = OFF_OP0 + 1;
+    pub const PC: usize = OFF_OP1 + 1;
+    pub const AP: usize = PC + 1;
+    pub const FP: usize = AP + 1;
+    pub const INST: usize = FP + 1;
+    pub const DST_ADDR: usize = INST + 1;
+    pub const DST: usize = DST_ADDR + 1;
+    pub const OP0_ADDR: usize = DST + 1;
+    pub const OP0: usize = OP0_ADDR + 1;
+    pub const OP1_ADDR: usize = OP0 + 1;
+    pub const OP1: usize = OP1_ADDR + 1;
+    pub const RES: usize = OP1 + 1;
+    /// `op0 * op1`
+    pub const MUL: usize = RES + 1;
+    /// `dst` of conditional jumps
+    pub const T0: usize = MUL + 1;
+    /// `dst * res` of conditional jumps
+    pub const T1: usize = T0 + 1;
+    /// Address and value of the extra memory access
+    pub const EXTRA_ADDR: usize = T1 + 1;
+    pub const EXTRA_VALUE: usize = EXTRA_ADDR + 1;
+    /// Address and value pairs of the memory accesses sorted by address
+    pub const SORTED_MEMORY: usize = EXTRA_VALUE + 1;
+    /// Extra offset for the range check
+    pub const EXTRA_OFFSET: usize = SORTED_MEMORY + 2 * MEMORY_ACCESSES.len();
+    /// Offsets sorted in increasing order
+    pub const SORTED_OFFSETS: usize = EXTRA_OFFSET + 1;
+    pub const NUM_BASE_COLUMNS: usize = SORTED_OFFSETS + OFFSETS.len();
+
+    /// Running product of the memory permutation argument
+    pub const MEMORY_PRODUCT: usize = NUM_BASE_COLUMNS;
+    /// Running product of the range check permutation argument
+    pub const OFFSET_PRODUCT: usize = MEMORY_PRODUCT + 1;
+
+    /// Address and value columns of the memory accesses of a row
+    pub const MEMORY_ACCESSES: [(usize, usize); 5] = [
+        (PC, INST),
+        (DST_ADDR, DST),
+        (OP0_ADDR, OP0),
+        (OP1_ADDR, OP1),
+        (EXTRA_ADDR, EXTRA_VALUE),
+    ];
+
+    /// Offset columns of a row
+    pub const OFFSETS: [usize; 4] = [OFF_DST, OFF_OP0, OFF_OP1, EXTRA_OFFSET];
+
+    /// Address and value columns of the sorted memory accesses of a row
+    pub const SORTED_MEMORY_ACCESSES: [(usize, usize); 5] = [
+        (SORTED_MEMORY, SORTED_MEMORY + 1),
+        (SORTED_MEMORY + 2, SORTED_MEMORY + 3),
+        (SORTED_MEMORY + 4, SORTED_MEMORY + 5),
+        (SORTED_MEMORY + 6, SORTED_MEMORY + 7),
+        (SORTED_MEMORY + 8, SORTED_MEMORY + 9),
+    ];
+
+    /// Sorted offset columns of a row
+    pub const SORTED_OFFSET_COLUMNS: [usize; 4] = [
+        SORTED_OFFSETS,
+        SORTED_OFFSETS + 1,
+        SORTED_OFFSETS + 2,
+        SORTED_OFFSETS + 3,
+    ];
+}
+
+/// Indices of the challenges of [`CairoAirConfig`]
+mod challenge {
+    /// Point the memory permutation argument is evaluated at
+    pub const MEMORY_Z: usize = 0;
+    /// Combines an address and value into a single element
+    pub const MEMORY_ALPHA: usize = 1;
+    /// Point the range check permutation argument is evaluated at
+    pub const OFFSET_Z: usize = 2;
+}
+
+/// Indices of the hints of [`CairoAirConfig`]
+mod hint {
+    pub const INITIAL_PC: usize = 0;
+    pub const INITIAL_AP: usize = 1;
+    pub const FINAL_PC: usize = 2;
+    pub const FINAL_AP: usize = 3;
+    pub const RC_MIN: usize = 4;
+    pub const RC_MAX: usize = 5;
+    /// Final value of the memory running product. Accounts for the dummy
+    /// accesses swapped for public memory cells.
+    pub const MEMORY_QUOTIENT: usize = 6;
+}
+
+/// Execution of a Cairo program laid out as a trace of [`CairoAirConfig`]
+pub struct CairoTrace {
+    base_columns: Matrix<Fp>,
+    public_inputs: CairoPublicInputs,
+}
+
+impl CairoTrace {
+    /// Lays out the execution as a trace. The cells at `public_addresses` are
+    /// made public e.g. the addresses of the program and its output.
+    pub fn new(
+        execution: &CairoExecution,
+        public_addresses: impl IntoIterator<Item = u64>,
+    ) -> Result<Self, Error> {
+        use column::*;
+        let CairoExecution { registers, memory } = execution;
+        let num_rows = registers.len();
+        if num_rows < 2 || !num_rows.is_power_of_two() {
+            return TraceLengthSnafu { len: num_rows }.fail();
+        }
+        let public_memory = public_addresses
+            .into_iter()
+            .map(|address| {
+                let value = memory
+                    .get(&address)
+                    .context(MissingMemorySnafu { address })?;
+                Ok((address, *value))
+            })
+            .collect::<Result<Vec<(u64, Fp)>, Error>>()?;
+
+        let mut columns = vec![Vec::with_capacity(num_rows); NUM_BASE_COLUMNS];
+        let mut accesses = Vec::with_capacity(MEMORY_ACCESSES.len() * num_rows);
+        let mut offsets = Vec::with_capacity(OFFSETS.len() * num_rows);
+        for (step, registers) in registers.iter().enumerate() {
+            let row = cpu_row(step, *registers, memory, &mut accesses, &mut offsets)?;
+            // the extra and sorted columns are filled once all rows are known
+            for (column, value) in zip(&mut columns[..EXTRA_ADDR], row) {
+                column.push(value);
+            }
+        }
+
+        let extra_accesses = memory_accesses(num_rows, memory, &mut accesses, &public_memory)?;
+        let sorted_rows = accesses.chunks_exact(MEMORY_ACCESSES.len());
+        for ((address, value), sorted_row) in zip(extra_accesses, sorted_rows) {
+            columns[EXTRA_ADDR].push(Fp::from(address));
+            columns[EXTRA_VALUE].push(value);
+            for (&(address, value), (addr_column, value_column)) in
+                zip(sorted_row, SORTED_MEMORY_ACCESSES)
+            {
+                columns[addr_column].push(Fp::from(address));
+                columns[value_column].push(value);
+            }
+        }
+
+        let rc_min = *offsets.iter().min().unwrap();
+        let rc_max = *offsets.iter().max().unwrap();
+        let extra_offsets = range_check_offsets(num_rows, &mut offsets)?;
+        let sorted_rows = offsets.chunks_exact(OFFSETS.len());
+        for (offset, sorted_row) in zip(extra_offsets, sorted_rows) {
+            columns[EXTRA_OFFSET].push(Fp::from(offset));
+            for (&offset, column) in zip(sorted_row, SORTED_OFFSET_COLUMNS) {
+                columns[column].push(Fp::from(offset));
+            }
+        }
+
+        let first = registers[0];
+        let last = registers[num_rows - 1];
+        let public_inputs = CairoPublicInputs {
+            initial_pc: first.pc,
+            initial_ap: first.ap,
+            final_pc: last.pc,
+            final_ap: last.ap,
+            rc_min,
+            rc_max,
+            public_memory,
+        };
+        let columns = columns
+            .into_iter()
+            .map(|column| column.to_vec_in(GpuAllocator))
+            .collect();
+        Ok(Self {
+            base_columns: Matrix::new(columns),
+            public_inputs,
+        })
+    }
+
+    /// Public inputs of the execution. A verifier needs these along with the
+    /// proof.
+    pub const fn public_inputs(&self) -> &CairoPublicInputs {
+        &self.public_inputs
+    }
+}
+
+/// Returns the row of a step. The step's memory accesses and biased offsets
+/// are appended to `accesses` and `offsets`.
+fn cpu_row(
+    step: usize,
+    RegisterState { ap, fp, pc }: RegisterState,
+    memory: &BTreeMap<u64, Fp>,
+    accesses: &mut Vec<(u64, Fp)>,
+    offsets: &mut Vec<u16>,
+) -> Result<[Fp; column::NUM_BASE_COLUMNS], Error> {
+    use column::*;
+    use flag::*;
+    let mut read = |address: Fp| {
+        let address = to_u64(address).context(InvalidAddressSnafu { step })?;
+        let value = *memory
+            .get(&address)
+            .context(MissingMemorySnafu { address })?;
+        accesses.push((address, value));
+        Ok::<Fp, Error>(value)
+    };
+
+    let mut row = [Fp::ZERO; NUM_BASE_COLUMNS];
+    row[PC] = Fp::from(pc);
+    row[AP] = Fp::from(ap);
+    row[FP] = Fp::from(fp);
+    row[INST] = read(row[PC])?;
+    let inst = to_u64(row[INST])
+        .filter(|inst| inst >> 63 == 0)
+        .context(InvalidInstructionSnafu { step })?;
+    for (i, column) in [OFF_DST, OFF_OP0, OFF_OP1].into_iter().enumerate() {
+        let offset = u16::try_from((inst >> (16 * i)) & 0xFFFF).unwrap();
+        row[column] = Fp::from(offset);
+        offsets.push(offset);
+    }
+    let flags: [Fp; NUM_FLAGS] = array::from_fn(|i| Fp::from((inst >> (48 + i)) & 1));
+    row[FLAGS..FLAGS + NUM_FLAGS].copy_from_slice(&flags);
+    let f = |i: usize| flags[i];
+
+    let bias = Fp::from(OFFSET_BIAS);
+    row[DST_ADDR] = f(DST_REG) * row[FP] + (Fp::ONE - f(DST_REG)) * row[AP] + row[OFF_DST] - bias;
+    row[DST] = read(row[DST_ADDR])?;
+    row[OP0_ADDR] = f(OP0_REG) * row[FP] + (Fp::ONE - f(OP0_REG)) * row[AP] + row[OFF_OP0] - bias;
+    row[OP0] = read(row[OP0_ADDR])?;
+    let op1_src = f(OP1_IMM) * row[PC]
+        + f(OP1_AP) * row[AP]
+        + f(OP1_FP) * row[FP]
+        + (Fp::ONE - f(OP1_IMM) - f(OP1_AP) - f(OP1_FP)) * row[OP0];
+    row[OP1_ADDR] = op1_src + row[OFF_OP1] - bias;
+    row[OP1] = read(row[OP1_ADDR])?;
+
+    let (dst, op0, op1) = (row[DST], row[OP0], row[OP1]);
+    row[MUL] = op0 * op1;
+    row[RES] = if f(PC_JNZ) == Fp::ONE {
+        // conditional jumps don't use the result so it holds the inverse of
+        // `dst` which shows `dst` is non-zero when jumping
+        dst.inverse().unwrap_or(Fp::ZERO)
+    } else {
+        f(RES_ADD) * (op0 + op1) + f(RES_MUL) * row[MUL] + (Fp::ONE - f(RES_ADD) - f(RES_MUL)) * op1
+    };
+    row[T0] = f(PC_JNZ) * dst;
+    row[T1] = row[T0] * row[RES];
+    Ok(row)
+}
+
+/// Returns the extra memory access of each row and sorts `accesses` into the
+/// order of the sorted memory columns. Extra accesses fill addresses that
+/// aren't accessed so sorted addresses are continuous. The remaining extra
+/// accesses are dummies which are swapped for the public memory in the
+/// sorted accesses.
+fn memory_accesses(
+    num_rows: usize,
+    memory: &BTreeMap<u64, Fp>,
+    accesses: &mut Vec<(u64, Fp)>,
+    public_memory: &[(u64, Fp)],
+) -> Result<Vec<(u64, Fp)>, Error> {
+    // dummies use address 0 so it's included even if no dummies are needed
+    let addresses = accesses
+        .iter()
+        .chain(public_memory)
+        .map(|&(address, _)| address)
+        .chain([0])
+        .collect::<BTreeSet<u64>>();
+    let mut extra_accesses = gaps(&addresses)
+        .map(|address| (address, memory.get(&address).copied().unwrap_or(Fp::ZERO)))
+        .collect::<Vec<(u64, Fp)>>();
+    if extra_accesses.len() + public_memory.len() > num_rows {
+        return TooManyMemoryCellsSnafu {
+            num_unused: extra_accesses.len(),
+            num_public: public_memory.len(),
+            num_rows,
+        }
+        .fail();
+    }
+
+    let num_dummies = num_rows - extra_accesses.len();
+    accesses.extend(&extra_accesses);
+    accesses.extend(public_memory);
+    accesses.extend(vec![(0, Fp::ZERO); num_dummies - public_memory.len()]);
+    accesses.sort_by_key(|&(address, _)| address);
+    extra_accesses.extend(vec![(0, Fp::ZERO); num_dummies]);
+    Ok(extra_accesses)
+}
+
+/// Returns the extra offset of each row and sorts `offsets` into the order of
+/// the sorted offset columns. Extra offsets fill offsets that aren't used so
+/// sorted offsets are continuous.
+fn range_check_offsets(num_rows: usize, offsets: &mut Vec<u16>) -> Result<Vec<u16>, Error> {
+    let rc_min = *offsets.iter().min().unwrap();
+    let values = offsets.iter().map(|&offset| u64::from(offset)).collect();
+    let mut extra_offsets = gaps(&values)
+        .map(|offset| u16::try_from(offset).unwrap())
+        .collect::<Vec<u16>>();
+    if extra_offsets.len() > num_rows {
+        return TooManyOffsetsSnafu {
+            num_unused: extra_offsets.len(),
+            num_rows,
+        }
+        .fail();
+    }
+    extra_offsets.resize(num_rows, rc_min);
+    offsets.extend(&extra_offsets);
+    offsets.sort_unstable();
+    Ok(extra_offsets)
+}
+
+/// Returns the values missing between the smallest and largest value
+fn gaps(values: &BTreeSet<u64>) -> impl Iterator<Item = u64> + '_ {
+    let pairs = values.iter().zip(values.iter().skip(1));
+    pairs.flat_map(|(&a, &b)| a + 1..b)
+}
+
+impl Trace for CairoTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> &Matrix<Fp> {
+        &self.base_columns
+    }
+
+    fn build_extension_columns(&self, challenges: &Challenges<Fp>) -> Option<Matrix<Fp>> {
+        use column::*;
+        let trace = &self.base_columns;
+        let memory_z = challenges[challenge::MEMORY_Z];
+        let alpha = challenges[challenge::MEMORY_ALPHA];
+        let offset_z = challenges[challenge::OFFSET_Z];
+        let memory_factors = |accesses: &[(usize, usize)], row: usize| {
+            accesses
+                .iter()
+                .map(|&(addr, value)| memory_z - (trace[addr][row] + alpha * trace[value][row]))
+                .product::<Fp>()
+        };
+        let offset_factors = |offsets: &[usize], row: usize| {
+            offsets
+                .iter()
+                .map(|&column| offset_z - trace[column][row])
+                .product::<Fp>()
+        };
+        let memory_product = running_product(
+            trace.num_rows(),
+            |row| memory_factors(&MEMORY_ACCESSES, row),
+            |row| memory_factors(&SORTED_MEMORY_ACCESSES, row),
+        );
+        let offset_product = running_product(
+            trace.num_rows(),
+            |row| offset_factors(&OFFSETS, row),
+            |row| offset_factors(&SORTED_OFFSET_COLUMNS, row),
+        );
+        Some(Matrix::new(vec![memory_product, offset_product]))
+    }
+}
+
+/// Returns the running product of `numerator(row) / denominator(row)`
+fn running_product(
+    num_rows: usize,
+    numerator: impl Fn(usize) -> Fp,
+    denominator: impl Fn(usize) -> Fp,
+) -> GpuVec<Fp> {
+    let mut denominators = (0..num_rows).map(denominator).collect::<Vec<Fp>>();
+    batch_inversion(&mut denominators);
+    let mut product = Fp::ONE;
+    let mut column = Vec::with_capacity_in(num_rows, GpuAllocator);
+    for (row, denominator_inv) in denominators.into_iter().enumerate() {
+        product *= numerator(row) * denominator_inv;
+        column.push(product);
+    }
+    column
+}
+
+type CairoExpr = Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>;
+
+fn constant(value: u64) -> CairoExpr {
+    AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(value))).into()
+}
+
+/// Constraints of the CPU from section 4.5 of the Cairo paper
+fn cpu_constraints(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+    use column::*;
+    use flag::*;
+    let curr = |column: usize| -> CairoExpr { column.curr() };
+    let next = |column: usize| -> CairoExpr { column.next() };
+    let f = |i: usize| curr(FLAGS + i);
+    let one = || constant(1);
+    let bias = || constant(OFFSET_BIAS);
+    let (pc, ap, fp) = (curr(PC), curr(AP), curr(FP));
+    let (dst, op0, op1, res) = (curr(DST), curr(OP0), curr(OP1), curr(RES));
+    let inst_size = f(OP1_IMM) + one();
+
+    let mut constraints = (0..NUM_FLAGS)
+        .map(|i| f(i) * (f(i) - one()))
+        .collect::<Vec<CairoExpr>>();
+    // the instruction is its biased offsets followed by its flags
+    let flags = (0..NUM_FLAGS)
+        .rev()
+        .map(f)
+        .reduce(|acc, flag| acc * constant(2) + flag)
+        .unwrap();
+    constraints.push(
+        curr(INST)
+            - (curr(OFF_DST)
+                + curr(OFF_OP0) * constant(1 << 16)
+                + curr(OFF_OP1) * constant(1 << 32)
+                + flags * constant(1 << 48)),
+    );
+    // operand addresses
+    constraints.push(
+        curr(DST_ADDR)
+            - (f(DST_REG) * fp.clone() + (one() - f(DST_REG)) * ap.clone() + curr(OFF_DST)
+                - bias()),
+    );
+    constraints.push(
+        curr(OP0_ADDR)
+            - (f(OP0_REG) * fp.clone() + (one() - f(OP0_REG)) * ap.clone() + curr(OFF_OP0)
+                - bias()),
+    );
+    let op1_src = f(OP1_IMM) * pc.clone()
+        + f(OP1_AP) * ap.clone()
+        + f(OP1_FP) * fp.clone()
+        + (one() - f(OP1_IMM) - f(OP1_AP) - f(OP1_FP)) * op0.clone();
+    constraints.push(curr(OP1_ADDR) - (op1_src + curr(OFF_OP1) - bias()));
+    // result
+    constraints.push(curr(MUL) - op0.clone() * op1.clone());
+    constraints.push(
+        (one() - f(PC_JNZ)) * res.clone()
+            - (f(RES_ADD) * (op0.clone() + op1.clone())
+                + f(RES_MUL) * curr(MUL)
+                + (one() - f(RES_ADD) - f(RES_MUL) - f(PC_JNZ)) * op1.clone()),
+    );
+    constraints.push(curr(T0) - f(PC_JNZ) * dst.clone());
+    constraints.push(curr(T1) - curr(T0) * res.clone());
+    // opcodes
+    constraints.push(f(OPCODE_CALL) * (dst.clone() - fp.clone()));
+    constraints.push(f(OPCODE_CALL) * (op0 - (pc.clone() + inst_size.clone())));
+    constraints.push(f(OPCODE_ASSERT_EQ) * (dst.clone() - res.clone()));
+
+    // register updates
+    let regular_pc = one() - f(PC_JUMP_ABS) - f(PC_JUMP_REL) - f(PC_JNZ);
+    let transitions = vec![
+        (curr(T1) - f(PC_JNZ)) * (next(PC) - (pc.clone() + inst_size.clone())),
+        curr(T0) * (next(PC) - (pc.clone() + op1)) + (one() - f(PC_JNZ)) * next(PC)
+            - (regular_pc * (pc.clone() + inst_size)
+                + f(PC_JUMP_ABS) * res.clone()
+                + f(PC_JUMP_REL) * (pc + res.clone())),
+        next(AP) - (ap.clone() + f(AP_ADD) * res + f(AP_ADD1) + f(OPCODE_CALL) * constant(2)),
+        next(FP)
+            - (f(OPCODE_RET) * dst
+                + f(OPCODE_CALL) * (ap + constant(2))
+                + (one() - f(OPCODE_RET) - f(OPCODE_CALL)) * fp),
+    ];
+
+    vec![
+        ConstraintGroup::all_rows(trace_len, constraints),
+        ConstraintGroup::transitions(trace_len, transitions),
+    ]
+}
+
+/// Constraints of the memory and the range check of the offsets
+fn permutation_constraints(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+    use column::*;
+    let at = |column: usize, offset: isize| -> CairoExpr { column.offset(offset) };
+    let public = |index: usize| -> CairoExpr { index.hint() };
+    let one = || constant(1);
+    let memory_z: CairoExpr = challenge::MEMORY_Z.challenge();
+    let alpha: CairoExpr = challenge::MEMORY_ALPHA.challenge();
+    let offset_z: CairoExpr = challenge::OFFSET_Z.challenge();
+    // product of `z - (address + alpha * value)` of the accesses `offset` rows away
+    let memory_factors = |accesses: &[(usize, usize)], offset: isize| {
+        accesses
+            .iter()
+            .map(|&(addr, value)| {
+                memory_z.clone() - (at(addr, offset) + alpha.clone() * at(value, offset))
+            })
+            .product::<CairoExpr>()
+    };
+    let offset_factors = |offsets: &[usize], offset: isize| {
+        offsets
+            .iter()
+            .map(|&column| offset_z.clone() - at(column, offset))
+            .product::<CairoExpr>()
+    };
+    // addresses increase by at most one and each address has a single value
+    let memory_continuity =
+        |(addr, value): (usize, usize), (next_addr, next_value): (usize, usize), offset: isize| {
+            let diff = at(next_addr, offset) - at(addr, 0);
+            [
+                diff.clone() * (diff.clone() - one()),
+                (at(next_value, offset) - at(value, 0)) * (diff - one()),
+            ]
+        };
+    let offset_continuity = |column: usize, next_column: usize, offset: isize| {
+        let diff = at(next_column, offset) - at(column, 0);
+        diff.clone() * (diff - one())
+    };
+
+    let mut all_rows = Vec::new();
+    for [a, b] in SORTED_MEMORY_ACCESSES.array_windows() {
+        all_rows.extend(memory_continuity(*a, *b, 0));
+    }
+    for [a, b] in SORTED_OFFSET_COLUMNS.array_windows() {
+        all_rows.push(offset_continuity(*a, *b, 0));
+    }
+
+    let last_access = SORTED_MEMORY_ACCESSES[SORTED_MEMORY_ACCESSES.len() - 1];
+    let last_offset = SORTED_OFFSET_COLUMNS[SORTED_OFFSET_COLUMNS.len() - 1];
+    let mut transitions = Vec::from(memory_continuity(last_access, SORTED_MEMORY_ACCESSES[0], 1));
+    transitions.push(offset_continuity(last_offset, SORTED_OFFSET_COLUMNS[0], 1));
+    transitions.push(
+        at(MEMORY_PRODUCT, 1) * memory_factors(&SORTED_MEMORY_ACCESSES, 1)
+            - at(MEMORY_PRODUCT, 0) * memory_factors(&MEMORY_ACCESSES, 1),
+    );
+    transitions.push(
+        at(OFFSET_PRODUCT, 1) * offset_factors(&SORTED_OFFSET_COLUMNS, 1)
+            - at(OFFSET_PRODUCT, 0) * offset_factors(&OFFSETS, 1),
+    );
+
+    vec![
+        ConstraintGroup::all_rows(trace_len, all_rows),
+        ConstraintGroup::transitions(trace_len, transitions),
+        ConstraintGroup::first_row(vec![
+            at(MEMORY_PRODUCT, 0) * memory_factors(&SORTED_MEMORY_ACCESSES, 0)
+                - memory_factors(&MEMORY_ACCESSES, 0),
+            at(OFFSET_PRODUCT, 0) * offset_factors(&SORTED_OFFSET_COLUMNS, 0)
+                - offset_factors(&OFFSETS, 0),
+        ]),
+        ConstraintGroup::last_row(
+            trace_len,
+            vec![
+                at(MEMORY_PRODUCT, 0) - public(hint::MEMORY_QUOTIENT),
+                at(OFFSET_PRODUCT, 0) - one(),
+            ],
+        ),
+    ]
+}
+
+/// AIR of the Cairo CPU with the memory and range check of the offsets
+pub struct CairoAirConfig;
+
+impl AirConfig for CairoAirConfig {
+    const NUM_BASE_COLUMNS: usize = column::NUM_BASE_COLUMNS;
+    const NUM_EXTENSION_COLUMNS: usize = 2;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = CairoPublicInputs;
+
+    fn gen_hints(
+        _trace_len: usize,
+        public_inputs: &CairoPublicInputs,
+        challenges: &Challenges<Fp>,
+    ) -> Hints<Fp> {
+        let z = challenges[challenge::MEMORY_Z];
+        let alpha = challenges[challenge::MEMORY_ALPHA];
+        let public_memory = &public_inputs.public_memory;
+        // each public memory cell replaces a dummy `(0, 0)` access
+        let denominator = public_memory
+            .iter()
+            .map(|&(address, value)| z - (Fp::from(address) + alpha * value))
+            .product::<Fp>();
+        let memory_quotient =
+            z.pow([public_memory.len() as u64]) * denominator.inverse().unwrap_or(Fp::ZERO);
+        Hints::new(vec![
+            (hint::INITIAL_PC, Fp::from(public_inputs.initial_pc)),
+            (hint::INITIAL_AP, Fp::from(public_inputs.initial_ap)),
+            (hint::FINAL_PC, Fp::from(public_inputs.final_pc)),
+            (hint::FINAL_AP, Fp::from(public_inputs.final_ap)),
+            (hint::RC_MIN, Fp::from(public_inputs.rc_min)),
+            (hint::RC_MAX, Fp::from(public_inputs.rc_max)),
+            (hint::MEMORY_QUOTIENT, memory_quotient),
+        ])
+    }
+
+    fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+        let mut groups = cpu_constraints(trace_len);
+        groups.extend(permutation_constraints(trace_len));
+        groups
+    }
+
+    fn assertions(trace_len: usize) -> Vec<Assertion<FieldVariant<Fp, Fp>>> {
+        use column::*;
+        let public = |index: usize| -> CairoExpr { index.hint() };
+        let last_row = trace_len - 1;
+        let last_offset = SORTED_OFFSET_COLUMNS[SORTED_OFFSET_COLUMNS.len() - 1];
+        vec![
+            Assertion::single(PC, 0, public(hint::INITIAL_PC)),
+            Assertion::single(AP, 0, public(hint::INITIAL_AP)),
+            Assertion::single(FP, 0, public(hint::INITIAL_AP)),
+            Assertion::single(PC, last_row, public(hint::FINAL_PC)),
+            Assertion::single(AP, last_row, public(hint::FINAL_AP)),
+            Assertion::single(SORTED_OFFSET_COLUMNS[0], 0, public(hint::RC_MIN)),
+            Assertion::single(last_offset, last_row, public(hint::RC_MAX)),
+        ]
+    }
+}
+
+/// Claims a Cairo program with the given public inputs ran to completion
+pub struct CairoClaim(pub CairoPublicInputs);
+
+impl Stark for CairoClaim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = CairoAirConfig;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = CairoTrace;
+    type Trace = CairoTrace;
+
+    fn get_public_inputs(&self) -> CairoPublicInputs {
+        self.0.clone()
+    }
+
+    fn gen_public_coin(&self, air: &Air<CairoAirConfig>) -> Self::PublicCoin {
+        let mut seed = Vec::new();
+        air.public_inputs().serialize_compressed(&mut seed).unwrap();
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
+    }
+
+    fn generate_trace(&self, trace: CairoTrace) -> CairoTrace {
+        trace
+    }
+}
@@ -1,25 +1,74 @@
 use crate::constraints::VerifierChallenge;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use ark_serialize::Valid;
 use core::ops::Deref;
 use core::ops::Index;
+use spin::Mutex;
 
-#[derive(Default, Clone, Debug, CanonicalDeserialize, CanonicalSerialize)]
-pub struct Challenges<F: Field>(Vec<F>);
+#[derive(Default, Clone, Debug)]
+pub struct Challenges<F: Field> {
+    values: Vec<F>,
+    /// Records accesses when auditing (see [`Self::new_recorded`])
+    accesses: Option<Arc<Mutex<ChallengeAccesses>>>,
+}
 
 impl<F: Field> Challenges<F> {
-    pub fn new(challenges: Vec<F>) -> Self {
-        Self(challenges)
+    pub const fn new(challenges: Vec<F>) -> Self {
+        Self {
+            values: challenges,
+            accesses: None,
+        }
+    }
+
+    /// Creates challenges that record every access made by indexing. Accesses
+    /// through [`Deref`] aren't recorded. Used to audit how an AIR consumes
+    /// challenges (see [`crate::debug::audit_challenge_accesses`]).
+    pub fn new_recorded(challenges: Vec<F>) -> Self {
+        let accesses = ChallengeAccesses {
+            first_access_order: Vec::new(),
+            counts: vec![0; challenges.len()],
+        };
+        Self {
+            values: challenges,
+            accesses: Some(Arc::new(Mutex::new(accesses))),
+        }
+    }
+
+    /// Returns the accesses recorded since the last call and resets the
+    /// recorder. Returns `None` if accesses aren't recorded.
+    pub fn take_accesses(&self) -> Option<ChallengeAccesses> {
+        self.accesses.as_ref().map(|accesses| {
+            let mut accesses = accesses.lock();
+            let counts = vec![0; accesses.counts.len()];
+            core::mem::replace(
+                &mut *accesses,
+                ChallengeAccesses {
+                    first_access_order: Vec::new(),
+                    counts,
+                },
+            )
+        })
     }
 }
 
+/// Accesses made to [`Challenges`] created with [`Challenges::new_recorded`]
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct ChallengeAccesses {
+    /// Challenge indices in the order they were first accessed
+    pub first_access_order: Vec<usize>,
+    /// Number of times each challenge was accessed
+    pub counts: Vec<usize>,
+}
+
 impl<F: Field> Deref for Challenges<F> {
     type Target = Vec<F>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.values
     }
 }
 
@@ -27,6 +76,46 @@ impl<F: Field, C: VerifierChallenge> Index<C> for Challenges<F> {
     type Output = F;
 
     fn index(&self, challenge: C) -> &Self::Output {
-        &self.0[challenge.index()]
+        let index = challenge.index();
+        if let Some(accesses) = &self.accesses {
+            let mut accesses = accesses.lock();
+            if accesses.counts[index] == 0 {
+                accesses.first_access_order.push(index);
+            }
+            accesses.counts[index] += 1;
+        }
+        &self.values[index]
+    }
+}
+
+impl<F: Field> CanonicalSerialize for Challenges<F> {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        self.values.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.values.serialized_size(compress)
+    }
+}
+
+impl<F: Field> Valid for Challenges<F> {
+    fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+        self.values.check()
+    }
+}
+
+impl<F: Field> CanonicalDeserialize for Challenges<F> {
+    fn deserialize_with_mode<R: ark_serialize::Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        Ok(Self::new(Vec::deserialize_with_mode(
+            reader, compress, validate,
+        )?))
     }
 }
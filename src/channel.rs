@@ -1,3 +1,4 @@
+use crate::air::AirConfig;
 use crate::challenges::Challenges;
 use crate::fri;
 use crate::fri::FriProof;
@@ -7,17 +8,18 @@ use crate::stark::Stark;
 use crate::trace::Queries;
 use crate::Air;
 use crate::Proof;
+use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
-use std::collections::BTreeSet;
 
 pub struct ProverChannel<'a, S: Stark> {
     air: &'a Air<S::AirConfig>,
     pub public_coin: S::PublicCoin,
     base_trace_commitment: S::Digest,
     extension_trace_commitment: Option<S::Digest>,
+    aux_trace_commitments: Vec<S::Digest>,
     composition_trace_commitment: S::Digest,
     fri_layer_commitments: Vec<S::Digest>,
     fri_remainder_coeffs: Vec<S::Fq>,
@@ -26,13 +28,48 @@ pub struct ProverChannel<'a, S: Stark> {
     pow_nonce: u64,
 }
 
+/// Absorbs the proof options, the shape of the trace and the public inputs
+/// into the public coin followed by the description of the AIR's constraints
+/// (see [`Air::description`]).
+///
+/// Must happen before any commitment so a proof can't be reinterpreted as a
+/// proof of a different instance, a different AIR or with different options.
+pub fn absorb_instance<S: Stark>(public_coin: &mut S::PublicCoin, air: &Air<S::AirConfig>) {
+    absorb_instance_inputs::<S>(public_coin, air);
+    public_coin.reseed_with_bytes(air.description());
+}
+
+/// Absorbs everything [`absorb_instance`] does except the AIR description
+pub(crate) fn absorb_instance_inputs<S: Stark>(
+    public_coin: &mut S::PublicCoin,
+    air: &Air<S::AirConfig>,
+) {
+    let mut bytes = Vec::new();
+    air.options().serialize_compressed(&mut bytes).unwrap();
+    air.trace_len().serialize_compressed(&mut bytes).unwrap();
+    S::AirConfig::NUM_BASE_COLUMNS
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    S::AirConfig::NUM_EXTENSION_COLUMNS
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    air.public_inputs()
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    public_coin.reseed_with_bytes(&bytes);
+}
+
 // impl<'a, A: Air, D: Digest> ProverChannel<'a, A, D> {
 impl<'a, S: Stark> ProverChannel<'a, S> {
-    pub fn new(air: &'a Air<S::AirConfig>, public_coin: S::PublicCoin) -> Self {
+    /// Creates a channel and absorbs the instance into the public coin (see
+    /// [`absorb_instance`])
+    pub fn new(air: &'a Air<S::AirConfig>, mut public_coin: S::PublicCoin) -> Self {
+        absorb_instance::<S>(&mut public_coin, air);
         ProverChannel {
             air,
             public_coin,
             extension_trace_commitment: None,
+            aux_trace_commitments: Vec::new(),
             base_trace_commitment: S::Digest::default(),
             composition_trace_commitment: S::Digest::default(),
             execution_trace_ood_evals: Vec::new(),
@@ -43,18 +80,55 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         }
     }
 
+    /// Returns true if the next prover message should be absorbed into the
+    /// public coin. Always true unless a [`Fault::SkipTranscriptAbsorb`] fault
+    /// is injected.
+    ///
+    /// [`Fault::SkipTranscriptAbsorb`]: crate::chaos::Fault::SkipTranscriptAbsorb
+    #[cfg_attr(not(feature = "chaos"), allow(clippy::missing_const_for_fn))]
+    fn should_absorb() -> bool {
+        #[cfg(feature = "chaos")]
+        return crate::chaos::fire(crate::chaos::Fault::SkipTranscriptAbsorb).is_none();
+        #[cfg(not(feature = "chaos"))]
+        return true;
+    }
+
+    /// Absorbs the commitment to the preprocessed columns. Unlike the other
+    /// commitments this isn't part of the proof since verifiers already have
+    /// it.
+    pub fn commit_preprocessed_trace(&mut self, commitment: &S::Digest) {
+        if Self::should_absorb() {
+            self.public_coin.reseed_with_digest(commitment);
+        }
+    }
+
     pub fn commit_base_trace(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
+        if Self::should_absorb() {
+            self.public_coin.reseed_with_digest(&commitment);
+        }
         self.base_trace_commitment = commitment;
     }
 
     pub fn commit_extension_trace(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
+        if Self::should_absorb() {
+            self.public_coin.reseed_with_digest(&commitment);
+        }
         self.extension_trace_commitment = Some(commitment);
     }
 
+    /// Commits to an auxiliary segment after the first. The first segment is
+    /// committed to with [`Self::commit_extension_trace`].
+    pub fn commit_aux_trace(&mut self, commitment: S::Digest) {
+        if Self::should_absorb() {
+            self.public_coin.reseed_with_digest(&commitment);
+        }
+        self.aux_trace_commitments.push(commitment);
+    }
+
     pub fn commit_composition_trace(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
+        if Self::should_absorb() {
+            self.public_coin.reseed_with_digest(&commitment);
+        }
         self.composition_trace_commitment = commitment;
     }
 
@@ -68,7 +142,9 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         composition_trace_oods: Vec<S::Fq>,
     ) {
         let ood_evals = [execution_trace_oods.clone(), composition_trace_oods.clone()].concat();
-        self.public_coin.reseed_with_field_elements(&ood_evals);
+        if Self::should_absorb() {
+            self.public_coin.reseed_with_field_elements(&ood_evals);
+        }
         self.execution_trace_ood_evals = execution_trace_oods;
         self.composition_trace_ood_evals = composition_trace_oods;
     }
@@ -81,22 +157,34 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         }
 
         let nonce = self
-            .public_coin
-            .grind_proof_of_work(grinding_factor)
+            .air
+            .options()
+            .pow_hash
+            .grind(&mut self.public_coin, grinding_factor)
             .expect("nonce not found");
-        assert!(self
-            .public_coin
-            .verify_proof_of_work(grinding_factor, nonce));
 
         self.pow_nonce = nonce;
-        self.public_coin.reseed_with_int(self.pow_nonce);
+        if Self::should_absorb() {
+            self.public_coin.reseed_with_int(self.pow_nonce);
+        }
     }
 
     pub fn get_fri_query_positions(&mut self) -> BTreeSet<usize> {
-        // TODO: voulnerability if multiple positions are the same
         let lde_domain_size = self.air.trace_len() * self.air.lde_blowup_factor();
-        let num_queries = self.air.options().num_queries as usize;
-        self.public_coin.draw_queries(num_queries, lde_domain_size)
+        let options = self.air.options();
+        #[allow(unused_mut)]
+        let mut positions = options.query_sampling.draw(
+            &mut self.public_coin,
+            options.num_queries.into(),
+            lde_domain_size,
+        );
+        #[cfg(feature = "chaos")]
+        if let Some(v) = crate::chaos::fire(crate::chaos::Fault::DropQuery) {
+            let i = usize::try_from(v % positions.len() as u64).unwrap();
+            let position = *positions.iter().nth(i).unwrap();
+            positions.remove(&position);
+        }
+        positions
     }
 
     pub fn build_proof(
@@ -109,6 +197,7 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
             trace_len: self.air.trace_len(),
             base_trace_commitment: self.base_trace_commitment,
             extension_trace_commitment: self.extension_trace_commitment,
+            aux_trace_commitments: self.aux_trace_commitments,
             composition_trace_commitment: self.composition_trace_commitment,
             execution_trace_ood_evals: self.execution_trace_ood_evals,
             composition_trace_ood_evals: self.composition_trace_ood_evals,
@@ -126,13 +215,17 @@ impl<'a, S: Stark> fri::ProverChannel for ProverChannel<'a, S> {
     type Field = S::Fq;
 
     fn commit_fri_layer(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
+        if Self::should_absorb() {
+            self.public_coin.reseed_with_digest(&commitment);
+        }
         self.fri_layer_commitments.push(commitment);
     }
 
     fn commit_remainder(&mut self, remainder_coeffs: &[Self::Field]) {
-        self.public_coin
-            .reseed_with_field_element_vector(remainder_coeffs);
+        if Self::should_absorb() {
+            self.public_coin
+                .reseed_with_field_element_vector(remainder_coeffs);
+        }
         self.fri_remainder_coeffs = remainder_coeffs.to_vec();
     }
 
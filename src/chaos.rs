@@ -0,0 +1,100 @@
+//! Fault injection for mutation testing the verifier.
+//!
+//! A fault makes the prover misbehave in a small and specific way e.g. by
+//! corrupting a single merkle node. Injecting every fault at every site it can
+//! occur and checking the verifier rejects each resulting proof shows that
+//! every check in the verifier is load-bearing.
+//!
+//! Faults are process wide so tests that inject faults shouldn't run
+//! concurrently with other proofs.
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::sync::Mutex;
+
+/// Fault that can be injected into the prover
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Replaces a node of a merkle proof with the default digest
+    CorruptMerkleNode,
+    /// Skips absorbing a prover message into the public coin
+    SkipTranscriptAbsorb,
+    /// Removes a query position before queries are opened
+    DropQuery,
+}
+
+struct ActiveFault {
+    fault: Fault,
+    occurrence: usize,
+    num_occurrences: usize,
+    rng: ChaCha20Rng,
+}
+
+static ACTIVE_FAULT: Mutex<Option<ActiveFault>> = Mutex::new(None);
+
+/// Injects `fault` at the `occurrence`-th (zero indexed) site it can occur.
+///
+/// Values picked by the fault (e.g. which merkle node is corrupted) are
+/// sampled from an RNG seeded with `seed`. The fault stays active until the
+/// returned guard is dropped.
+///
+/// # Panics
+///
+/// Panics if another fault is already active.
+#[must_use]
+pub fn inject(fault: Fault, occurrence: usize, seed: u64) -> FaultGuard {
+    let previous_fault = ACTIVE_FAULT.lock().unwrap().replace(ActiveFault {
+        fault,
+        occurrence,
+        num_occurrences: 0,
+        rng: ChaCha20Rng::seed_from_u64(seed),
+    });
+    assert!(previous_fault.is_none(), "a fault is already active");
+    FaultGuard(())
+}
+
+/// Removes the active fault when dropped
+pub struct FaultGuard(());
+
+impl FaultGuard {
+    /// Returns the number of sites reached so far where the fault can occur.
+    /// The fault was injected if this exceeds the requested occurrence.
+    pub fn num_occurrences(&self) -> usize {
+        ACTIVE_FAULT
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .num_occurrences
+    }
+
+    /// Returns true if the fault has been injected
+    pub fn is_injected(&self) -> bool {
+        ACTIVE_FAULT
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|active| active.num_occurrences > active.occurrence)
+    }
+}
+
+impl Drop for FaultGuard {
+    fn drop(&mut self) {
+        *ACTIVE_FAULT.lock().unwrap() = None;
+    }
+}
+
+/// Called by the prover at each site `fault` can occur. Returns a random
+/// value if the fault should be injected at this site.
+pub(crate) fn fire(fault: Fault) -> Option<u64> {
+    ACTIVE_FAULT
+        .lock()
+        .unwrap()
+        .as_mut()
+        .filter(|active| active.fault == fault)
+        .and_then(|active| {
+            let occurrence = active.num_occurrences;
+            active.num_occurrences += 1;
+            (occurrence == active.occurrence).then(|| active.rng.gen())
+        })
+}
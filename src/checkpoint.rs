@@ -0,0 +1,253 @@
+//! Persisting completed proving phases so an interrupted prover can resume.
+//!
+//! Proving traces with `2^25` rows or more takes long enough that losing a
+//! proof to a crash or a preempted machine is costly. A [`CheckpointStore`]
+//! passed to [`prove_with_checkpoints`] receives the state of each completed
+//! [`Checkpoint`]. The next run with the same store skips every phase that was
+//! checkpointed and picks up where the previous run stopped.
+//!
+//! Each checkpoint stores the polynomials or evaluations a phase produced along
+//! with a fingerprint of the public inputs, proof options and every commitment
+//! made before the phase. Merkle trees are rebuilt from the stored state and
+//! challenges are redrawn so the proof is the same as one generated in a single
+//! run. Checkpoints whose fingerprint doesn't match (e.g. they were saved for
+//! other public inputs) or that can't be deserialized are ignored and the
+//! phase is recomputed.
+//!
+//! [`prove_with_checkpoints`]: crate::prover::prove_with_checkpoints
+use crate::utils::vec_to_gpu_vec;
+use crate::Matrix;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::Read;
+use ark_serialize::SerializationError;
+use ark_serialize::Write;
+use snafu::ResultExt;
+use snafu::Snafu;
+use std::path::PathBuf;
+
+/// Proving phases that can be checkpointed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Checkpoint {
+    /// Base and auxiliary trace polynomials
+    TraceCommitment,
+    /// Composition trace polynomials
+    ConstraintEvaluation,
+    /// Evaluations of each FRI layer and the remainder
+    Fri,
+}
+
+impl Checkpoint {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::TraceCommitment => "trace_commitment",
+            Self::ConstraintEvaluation => "constraint_evaluation",
+            Self::Fri => "fri",
+        }
+    }
+}
+
+/// Storage for the state of completed proving phases
+pub trait CheckpointStore: Send {
+    /// Stores the state of a completed phase. Replaces previous state.
+    fn save(&mut self, checkpoint: Checkpoint, state: &[u8]) -> Result<(), CheckpointError>;
+
+    /// Returns the stored state of a phase or `None` if it wasn't saved
+    fn load(&mut self, checkpoint: Checkpoint) -> Result<Option<Vec<u8>>, CheckpointError>;
+
+    /// Removes all stored state e.g. once a proof is complete
+    fn clear(&mut self) -> Result<(), CheckpointError>;
+}
+
+/// Errors that can occur when accessing a [`CheckpointStore`]
+#[derive(Debug, Snafu)]
+pub enum CheckpointError {
+    #[snafu(display("failed to access the {checkpoint:?} checkpoint: {source}"))]
+    Io {
+        checkpoint: Checkpoint,
+        source: std::io::Error,
+    },
+    #[snafu(display("the {checkpoint:?} checkpoint doesn't match the AIR"))]
+    Mismatch { checkpoint: Checkpoint },
+}
+
+/// Keeps checkpoints in memory. Useful for tests or provers that recover from
+/// errors within the same process.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryCheckpointStore(BTreeMap<Checkpoint, Vec<u8>>);
+
+impl MemoryCheckpointStore {
+    /// Returns true if the state of `checkpoint` is stored
+    pub fn contains(&self, checkpoint: Checkpoint) -> bool {
+        self.0.contains_key(&checkpoint)
+    }
+
+    /// Removes the state of `checkpoint` so the phase is recomputed
+    pub fn remove(&mut self, checkpoint: Checkpoint) -> Option<Vec<u8>> {
+        self.0.remove(&checkpoint)
+    }
+}
+
+impl CheckpointStore for MemoryCheckpointStore {
+    fn save(&mut self, checkpoint: Checkpoint, state: &[u8]) -> Result<(), CheckpointError> {
+        self.0.insert(checkpoint, state.to_vec());
+        Ok(())
+    }
+
+    fn load(&mut self, checkpoint: Checkpoint) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(self.0.get(&checkpoint).cloned())
+    }
+
+    fn clear(&mut self) -> Result<(), CheckpointError> {
+        self.0.clear();
+        Ok(())
+    }
+}
+
+/// Keeps each checkpoint in a file in a directory. Files are written to a
+/// temporary path and renamed so a crash mid-write never leaves a partial
+/// checkpoint behind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCheckpointStore {
+    dir: PathBuf,
+}
+
+impl FileCheckpointStore {
+    /// Stores checkpoints in `dir`. The directory is created on the first save.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, checkpoint: Checkpoint) -> PathBuf {
+        self.dir.join(format!("{}.ckpt", checkpoint.name()))
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn save(&mut self, checkpoint: Checkpoint, state: &[u8]) -> Result<(), CheckpointError> {
+        let path = self.path(checkpoint);
+        let tmp_path = path.with_extension("ckpt.tmp");
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|()| std::fs::write(&tmp_path, state))
+            .and_then(|()| std::fs::rename(&tmp_path, &path))
+            .context(IoSnafu { checkpoint })
+    }
+
+    fn load(&mut self, checkpoint: Checkpoint) -> Result<Option<Vec<u8>>, CheckpointError> {
+        match std::fs::read(self.path(checkpoint)) {
+            Ok(state) => Ok(Some(state)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(CheckpointError::Io { checkpoint, source }),
+        }
+    }
+
+    fn clear(&mut self) -> Result<(), CheckpointError> {
+        for checkpoint in [
+            Checkpoint::TraceCommitment,
+            Checkpoint::ConstraintEvaluation,
+            Checkpoint::Fri,
+        ] {
+            match std::fs::remove_file(self.path(checkpoint)) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(CheckpointError::Io {
+                        checkpoint,
+                        source: err,
+                    })
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Saves and loads checkpoints on behalf of the prover. Does nothing if there
+/// is no store.
+pub(crate) struct Checkpoints<'a> {
+    store: Option<&'a mut (dyn CheckpointStore + Send + 'a)>,
+    /// Public inputs, proof options and the commitments made so far
+    fingerprint: Vec<u8>,
+}
+
+impl<'a> Checkpoints<'a> {
+    pub fn new(store: Option<&'a mut (dyn CheckpointStore + Send + 'a)>) -> Self {
+        Self {
+            store,
+            fingerprint: Vec::new(),
+        }
+    }
+
+    /// Adds public inputs, options or a commitment to the fingerprint of
+    /// subsequent checkpoints
+    pub fn absorb(&mut self, value: &impl CanonicalSerialize) {
+        if self.store.is_some() {
+            value.serialize_compressed(&mut self.fingerprint).unwrap();
+        }
+    }
+
+    /// Returns the state of `checkpoint` if it was saved with the current
+    /// fingerprint and `read` can deserialize it
+    pub fn load<T>(
+        &mut self,
+        checkpoint: Checkpoint,
+        read: impl FnOnce(&mut &[u8]) -> Result<T, SerializationError>,
+    ) -> Result<Option<T>, CheckpointError> {
+        let Some(store) = self.store.as_deref_mut() else {
+            return Ok(None);
+        };
+        let Some(state) = store.load(checkpoint)? else {
+            return Ok(None);
+        };
+        let mut reader = &*state;
+        let Ok(fingerprint) = Vec::<u8>::deserialize_compressed(&mut reader) else {
+            return Ok(None);
+        };
+        if fingerprint != self.fingerprint {
+            return Ok(None);
+        }
+        Ok(read(&mut reader).ok().filter(|_| reader.is_empty()))
+    }
+
+    /// Saves the state written by `write` under the current fingerprint
+    pub fn save(
+        &mut self,
+        checkpoint: Checkpoint,
+        write: impl FnOnce(&mut Vec<u8>) -> Result<(), SerializationError>,
+    ) -> Result<(), CheckpointError> {
+        let Some(store) = self.store.as_deref_mut() else {
+            return Ok(());
+        };
+        let mut state = Vec::new();
+        self.fingerprint.serialize_compressed(&mut state).unwrap();
+        write(&mut state).unwrap();
+        store.save(checkpoint, &state)
+    }
+}
+
+/// Serializes the columns of a matrix in the format of `Vec<Vec<F>>`
+pub(crate) fn write_matrix<F: Field>(
+    matrix: &Matrix<F>,
+    mut writer: impl Write,
+) -> Result<(), SerializationError> {
+    matrix.num_cols().serialize_compressed(&mut writer)?;
+    for column in matrix.iter() {
+        column.len().serialize_compressed(&mut writer)?;
+        for value in column {
+            value.serialize_compressed(&mut writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Deserializes a matrix written by [`write_matrix`]
+pub(crate) fn read_matrix<F: Field>(
+    mut reader: impl Read,
+) -> Result<Matrix<F>, SerializationError> {
+    let columns = Vec::<Vec<F>>::deserialize_compressed(&mut reader)?;
+    Ok(Matrix::new(
+        columns.into_iter().map(vec_to_gpu_vec).collect(),
+    ))
+}
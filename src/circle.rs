@@ -0,0 +1,287 @@
+//! Circle domains and the circle FFT.
+//!
+//! Fields such as Mersenne31 have a small two-adicity so they lack the
+//! multiplicative subgroups radix-2 FFTs need. The circle `x^2 + y^2 = 1` over
+//! these fields is a group of order `p + 1` under
+//! `(x0, y0) + (x1, y1) = (x0 * x1 - y0 * y1, x0 * y1 + y0 * x1)` which for
+//! Mersenne31 is `2^31`. Domains are cosets of its power of two subgroups and
+//! polynomials are represented in the FFT basis of
+//! <https://eprint.iacr.org/2024/278>. The coefficient at index `j` with bits
+//! `j_0, j_1, ..., j_k` is for the basis function:
+//!
+//! ```text
+//! y^(j_0) * x^(j_1) * π(x)^(j_2) * ... * π^(k - 1)(x)^(j_k)
+//! ```
+//!
+//! where `π(x) = 2 * x^2 - 1` is the x-coordinate of doubling a point. The
+//! basis doesn't depend on the domain so polynomials interpolated over a
+//! domain are extended to a larger domain by padding coefficients with zeros.
+use crate::domain::DomainKind;
+use crate::domain::TraceDomain;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use core::ops::Add;
+use core::ops::Mul;
+use ministark_gpu::fields::p2147483647::ark::Fp as Mersenne31;
+
+/// A point on the circle `x^2 + y^2 = 1`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CirclePoint<F> {
+    pub x: F,
+    pub y: F,
+}
+
+impl<F: Field> CirclePoint<F> {
+    /// Identity of the circle group
+    pub const IDENTITY: Self = Self {
+        x: F::ONE,
+        y: F::ZERO,
+    };
+
+    pub const fn new(x: F, y: F) -> Self {
+        Self { x, y }
+    }
+
+    pub fn double(self) -> Self {
+        self + self
+    }
+
+    /// Inverse of the point in the circle group i.e. `(x, -y)`
+    pub fn conjugate(self) -> Self {
+        Self::new(self.x, -self.y)
+    }
+
+    /// Point with order two added to `self` i.e. `(-x, -y)`
+    pub fn antipode(self) -> Self {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+impl<F: Field> Add for CirclePoint<F> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(
+            self.x * rhs.x - self.y * rhs.y,
+            self.x * rhs.y + self.y * rhs.x,
+        )
+    }
+}
+
+impl<F: Field> Mul<usize> for CirclePoint<F> {
+    type Output = Self;
+
+    fn mul(mut self, mut n: usize) -> Self {
+        let mut res = Self::IDENTITY;
+        while n > 0 {
+            if n & 1 == 1 {
+                res = res + self;
+            }
+            self = self.double();
+            n >>= 1;
+        }
+        res
+    }
+}
+
+/// Fields with a circle group that has a large power of two subgroup
+pub trait CircleField: FftField {
+    /// `k` such that `2^k` is the order of the largest power of two subgroup
+    /// of the circle group
+    const CIRCLE_TWO_ADICITY: u32;
+
+    /// Generator of the subgroup of order `2^CIRCLE_TWO_ADICITY`
+    fn circle_generator() -> CirclePoint<Self>;
+
+    /// Generator of the subgroup of order `2^log_order`
+    fn circle_subgroup_generator(log_order: u32) -> CirclePoint<Self> {
+        assert!(log_order <= Self::CIRCLE_TWO_ADICITY);
+        (log_order..Self::CIRCLE_TWO_ADICITY).fold(Self::circle_generator(), |g, _| g.double())
+    }
+}
+
+impl CircleField for Mersenne31 {
+    const CIRCLE_TWO_ADICITY: u32 = 31;
+
+    fn circle_generator() -> CirclePoint<Self> {
+        CirclePoint::new(Self::from(2u32), Self::from(1_268_011_823u32))
+    }
+}
+
+/// Canonic coset of the circle group of size `n = 2^k` i.e. the points
+/// `(2i + 1) * Q` for `0 <= i < n` where `Q` has order `2n`. Points `i` and
+/// `n - 1 - i` are conjugates and doubling maps the domain onto the canonic
+/// coset of size `n / 2` which gives the circle FFT its recursive structure.
+/// Canonic cosets of different sizes are disjoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircleDomain<F> {
+    log_size: u32,
+    _phantom: core::marker::PhantomData<F>,
+}
+
+impl<F: CircleField> CircleDomain<F> {
+    /// Returns the canonic coset of size `2^log_size` or `None` if the circle
+    /// group doesn't have a subgroup of order `2^(log_size + 1)`
+    pub fn new(log_size: u32) -> Option<Self> {
+        (log_size < F::CIRCLE_TWO_ADICITY).then_some(Self {
+            log_size,
+            _phantom: core::marker::PhantomData,
+        })
+    }
+
+    pub const fn log_size(&self) -> u32 {
+        self.log_size
+    }
+
+    pub const fn size(&self) -> usize {
+        1 << self.log_size
+    }
+
+    pub fn element(&self, i: usize) -> CirclePoint<F> {
+        assert!(i < self.size());
+        F::circle_subgroup_generator(self.log_size + 1) * (2 * i + 1)
+    }
+
+    pub fn elements(&self) -> Vec<CirclePoint<F>> {
+        let q = F::circle_subgroup_generator(self.log_size + 1);
+        let step = q.double();
+        core::iter::successors(Some(q), |&point| Some(point + step))
+            .take(self.size())
+            .collect()
+    }
+
+    /// Twiddles of each layer of the FFT. The first layer holds the
+    /// y-coordinates of the first half of the domain. Every subsequent layer
+    /// holds the first half of the x-coordinates of the previous layer
+    /// mapped by `π(x) = 2 * x^2 - 1`.
+    fn twiddles(&self) -> Vec<Vec<F>> {
+        if self.log_size == 0 {
+            return Vec::new();
+        }
+        let mut half = self.elements();
+        half.truncate(self.size() / 2);
+        let mut layers = Vec::with_capacity(self.log_size as usize);
+        layers.push(half.iter().map(|p| p.y).collect::<Vec<F>>());
+        let mut xs = half.iter().map(|p| p.x).collect::<Vec<F>>();
+        while layers.len() < self.log_size as usize {
+            xs.truncate(xs.len() / 2);
+            layers.push(xs.clone());
+            xs.iter_mut()
+                .for_each(|x| *x = x.square().double() - F::ONE);
+        }
+        layers
+    }
+
+    /// Returns the coefficients of the polynomial that interpolates `evals`
+    /// over the domain
+    pub fn interpolate(&self, evals: &[F]) -> Vec<F> {
+        assert_eq!(evals.len(), self.size());
+        let twiddles = self.twiddles();
+        let mut coeffs = circle_ifft(evals, &twiddles);
+        let n_inv = F::from(self.size() as u64).inverse().unwrap();
+        coeffs.iter_mut().for_each(|c| *c *= n_inv);
+        coeffs
+    }
+
+    /// Evaluates the polynomial with coefficients `coeffs` over the domain.
+    /// Coefficients are padded with zeros to the size of the domain.
+    pub fn evaluate(&self, coeffs: &[F]) -> Vec<F> {
+        assert!(coeffs.len() <= self.size());
+        let mut coeffs = coeffs.to_vec();
+        coeffs.resize(self.size(), F::ZERO);
+        circle_fft(&coeffs, &self.twiddles())
+    }
+}
+
+impl<F: CircleField> TraceDomain<F> for CircleDomain<F> {
+    const KIND: DomainKind = DomainKind::Circle;
+
+    fn trace_domain(size: usize) -> Option<Self> {
+        if size.is_power_of_two() {
+            Self::new(size.ilog2())
+        } else {
+            None
+        }
+    }
+
+    fn domain_size(&self) -> usize {
+        self.size()
+    }
+
+    /// Circle domains don't use an offset. The canonic coset of the larger
+    /// size is disjoint from `self` for blowup factors greater than one.
+    fn lde_domain(&self, blowup_factor: usize, _offset: F) -> Option<Self> {
+        if blowup_factor.is_power_of_two() {
+            Self::new(self.log_size + blowup_factor.ilog2())
+        } else {
+            None
+        }
+    }
+
+    fn interpolate(&self, evals: &[F]) -> Vec<F> {
+        self.interpolate(evals)
+    }
+
+    fn evaluate(&self, coeffs: &[F]) -> Vec<F> {
+        self.evaluate(coeffs)
+    }
+}
+
+/// Evaluates a polynomial in the circle FFT basis at an arbitrary point
+pub fn evaluate_at_point<F: Field>(coeffs: &[F], point: CirclePoint<F>) -> F {
+    assert!(coeffs.is_empty() || coeffs.len().is_power_of_two());
+    let mut coeffs = coeffs.to_vec();
+    let mut twiddle = point.y;
+    let mut x = point.x;
+    while coeffs.len() > 1 {
+        coeffs = coeffs
+            .chunks_exact(2)
+            .map(|pair| pair[0] + twiddle * pair[1])
+            .collect();
+        twiddle = x;
+        x = x.square().double() - F::ONE;
+    }
+    coeffs.first().copied().unwrap_or(F::ZERO)
+}
+
+/// Circle FFT. Splits `f(P) = f_0(π(P)) + t(P) * f_1(π(P))` where `t` is the
+/// twiddle of the current layer.
+fn circle_fft<F: Field>(coeffs: &[F], twiddles: &[Vec<F>]) -> Vec<F> {
+    let n = coeffs.len();
+    if n == 1 {
+        return coeffs.to_vec();
+    }
+    let (even, odd): (Vec<F>, Vec<F>) = coeffs.chunks_exact(2).map(|c| (c[0], c[1])).unzip();
+    let f0 = circle_fft(&even, &twiddles[1..]);
+    let f1 = circle_fft(&odd, &twiddles[1..]);
+    let mut evals = alloc::vec![F::ZERO; n];
+    for (i, &t) in twiddles[0].iter().enumerate() {
+        let t_f1 = t * f1[i];
+        evals[i] = f0[i] + t_f1;
+        evals[n - 1 - i] = f0[i] - t_f1;
+    }
+    evals
+}
+
+/// Inverse of [`circle_fft`] without the scaling by `1 / n`
+fn circle_ifft<F: Field>(evals: &[F], twiddles: &[Vec<F>]) -> Vec<F> {
+    let n = evals.len();
+    if n == 1 {
+        return evals.to_vec();
+    }
+    let (f0, f1): (Vec<F>, Vec<F>) = twiddles[0]
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let (a, b) = (evals[i], evals[n - 1 - i]);
+            (a + b, (a - b) * t.inverse().unwrap())
+        })
+        .unzip();
+    let c0 = circle_ifft(&f0, &twiddles[1..]);
+    let c1 = circle_ifft(&f1, &twiddles[1..]);
+    c0.into_iter()
+        .zip(c1)
+        .flat_map(|(c0, c1)| [c0, c1])
+        .collect()
+}
@@ -0,0 +1,484 @@
+//! Machine-readable description of the verifier's computation.
+//!
+//! Teams wrapping ministark proofs inside a SNARK (halo2, plonkish arithmetic
+//! etc.) need to know exactly what the verifier computes and where each value
+//! lives in a proof. [`VerifierCircuit`] describes the Merkle path structure
+//! of every commitment, counts of hashes and field operations and an index map
+//! from named proof elements to their position in the proof so wrapper circuits
+//! can be generated programmatically.
+use crate::air::AirConfig;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::CompositionItem;
+use crate::expression::Instruction;
+use crate::random::PowHash;
+use crate::Air;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::ops::AddAssign;
+
+/// Type of a value in a proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementKind {
+    Digest,
+    Fp,
+    Fq,
+    U64,
+}
+
+impl ElementKind {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Digest => "digest",
+            Self::Fp => "fp",
+            Self::Fq => "fq",
+            Self::U64 => "u64",
+        }
+    }
+}
+
+/// A named, fixed size group of values in a proof.
+///
+/// `offset` is the index of the first value amongst all values of the same
+/// [`ElementKind`] in the order they appear in a serialized [`Proof`].
+/// Merkle proof nodes have a data dependent length and are not included (see
+/// [`CommitmentLayout`]).
+///
+/// [`Proof`]: crate::Proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofElement {
+    pub name: String,
+    pub kind: ElementKind,
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// Shape of a Merkle commitment opened by the verifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitmentLayout {
+    pub name: String,
+    /// Type of the values in each leaf's row
+    pub kind: ElementKind,
+    /// Number of values hashed into each leaf
+    pub row_width: usize,
+    pub num_leaves: usize,
+    /// Length of an authentication path
+    pub depth: u32,
+    /// Maximum number of distinct leaves opened by the verifier
+    pub max_openings: usize,
+}
+
+impl CommitmentLayout {
+    /// Upper bound on the number of two-to-one hashes needed to verify the
+    /// batched Merkle proof. Paths that share nodes need fewer hashes.
+    pub const fn max_merges(&self) -> usize {
+        self.max_openings * self.depth as usize
+    }
+}
+
+/// Number of field operations performed by the verifier
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FieldOpCounts {
+    pub add: usize,
+    pub mul: usize,
+    pub div: usize,
+    pub neg: usize,
+    pub pow: usize,
+}
+
+impl FieldOpCounts {
+    const fn scale(self, n: usize) -> Self {
+        Self {
+            add: self.add * n,
+            mul: self.mul * n,
+            div: self.div * n,
+            neg: self.neg * n,
+            pow: self.pow * n,
+        }
+    }
+}
+
+impl AddAssign for FieldOpCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.add += rhs.add;
+        self.mul += rhs.mul;
+        self.div += rhs.div;
+        self.neg += rhs.neg;
+        self.pow += rhs.pow;
+    }
+}
+
+/// Operations the verifier performs on the public coin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TranscriptOps {
+    pub reseeds: usize,
+    pub field_draws: usize,
+    /// Positions drawn if there are no duplicates. Sampling distinct positions
+    /// (see [`crate::random::QuerySampling`]) redraws duplicates.
+    pub query_draws: usize,
+    pub proof_of_work_checks: usize,
+}
+
+/// Description of the computation performed by the verifier for an AIR
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifierCircuit {
+    pub trace_len: usize,
+    pub lde_domain_size: usize,
+    pub num_queries: usize,
+    pub grinding_factor: u8,
+    pub fri_folding_factor: usize,
+    pub commitments: Vec<CommitmentLayout>,
+    pub proof_elements: Vec<ProofElement>,
+    pub transcript: TranscriptOps,
+    /// Evaluating the composition constraint at the out-of-domain point
+    pub ood_constraint_ops: FieldOpCounts,
+    /// Evaluating the DEEP composition polynomial at all query positions
+    pub deep_composition_ops: FieldOpCounts,
+    /// Folding FRI layers and checking the remainder at all query positions
+    pub fri_ops: FieldOpCounts,
+}
+
+impl VerifierCircuit {
+    #[allow(clippy::too_many_lines)]
+    pub fn new<A: AirConfig>(air: &Air<A>) -> Self {
+        let options = air.options();
+        let trace_len = air.trace_len();
+        let num_queries = usize::from(options.num_queries);
+        let lde_blowup_factor = air.lde_blowup_factor();
+        let lde_domain_size = trace_len * lde_blowup_factor;
+        let ce_blowup_factor = air.ce_blowup_factor();
+        let num_trace_arguments = air.trace_arguments().len();
+        let fri_options = options.into_fri_options();
+        let fri_folding_factor = usize::from(options.fri_folding_factor);
+        let num_fri_layers = fri_options.num_layers(lde_domain_size);
+        let num_remainder_coeffs = fri_options.remainder_size(lde_domain_size) / lde_blowup_factor;
+        let has_extension = A::NUM_EXTENSION_COLUMNS != 0;
+
+        let mut commitments = Vec::new();
+        let mut add_commitment = |name: &str, kind, row_width, num_leaves: usize| {
+            commitments.push(CommitmentLayout {
+                name: name.into(),
+                kind,
+                row_width,
+                num_leaves,
+                depth: num_leaves.ilog2(),
+                max_openings: num_queries.min(num_leaves),
+            });
+        };
+        add_commitment(
+            "base_trace",
+            ElementKind::Fp,
+            A::NUM_BASE_COLUMNS,
+            lde_domain_size,
+        );
+        if has_extension {
+            add_commitment(
+                "extension_trace",
+                ElementKind::Fq,
+                A::NUM_EXTENSION_COLUMNS,
+                lde_domain_size,
+            );
+        }
+        add_commitment(
+            "composition_trace",
+            ElementKind::Fq,
+            ce_blowup_factor,
+            lde_domain_size,
+        );
+        let mut layer_size = lde_domain_size;
+        for i in 0..num_fri_layers {
+            layer_size /= fri_folding_factor;
+            add_commitment(
+                &format!("fri_layer_{i}"),
+                ElementKind::Fq,
+                fri_folding_factor,
+                layer_size,
+            );
+        }
+
+        // proof elements in the order they are serialized
+        let mut index_map = IndexMap::default();
+        index_map.push("base_trace_commitment", ElementKind::Digest, 1);
+        if has_extension {
+            index_map.push("extension_trace_commitment", ElementKind::Digest, 1);
+        }
+        index_map.push("composition_trace_commitment", ElementKind::Digest, 1);
+        for i in 0..num_fri_layers {
+            let num_values = num_queries * fri_folding_factor;
+            index_map.push(
+                &format!("fri_layer_{i}_values"),
+                ElementKind::Fq,
+                num_values,
+            );
+            index_map.push(&format!("fri_layer_{i}_commitment"), ElementKind::Digest, 1);
+        }
+        index_map.push(
+            "fri_remainder_coeffs",
+            ElementKind::Fq,
+            num_remainder_coeffs,
+        );
+        index_map.push("pow_nonce", ElementKind::U64, 1);
+        let num_base_values = num_queries * A::NUM_BASE_COLUMNS;
+        index_map.push("base_trace_values", ElementKind::Fp, num_base_values);
+        if has_extension {
+            let num_extension_values = num_queries * A::NUM_EXTENSION_COLUMNS;
+            index_map.push(
+                "extension_trace_values",
+                ElementKind::Fq,
+                num_extension_values,
+            );
+        }
+        let num_composition_values = num_queries * ce_blowup_factor;
+        index_map.push(
+            "composition_trace_values",
+            ElementKind::Fq,
+            num_composition_values,
+        );
+        index_map.push(
+            "execution_trace_ood_evals",
+            ElementKind::Fq,
+            num_trace_arguments,
+        );
+        index_map.push(
+            "composition_trace_ood_evals",
+            ElementKind::Fq,
+            ce_blowup_factor,
+        );
+
+        let transcript = TranscriptOps {
+            // instance, AIR description, base trace, composition trace, OOD evaluations
+            // and FRI remainder
+            reseeds: 6 + usize::from(has_extension) + num_fri_layers,
+            field_draws: air.num_challenges()
+                + air.num_composition_constraint_coeffs()
+                + 1
+                + num_trace_arguments
+                + ce_blowup_factor
+                + 2
+                + num_fri_layers
+                + usize::from(
+                    options.grinding_factor != 0 && options.pow_hash != PowHash::PublicCoin,
+                ),
+            query_draws: num_queries,
+            proof_of_work_checks: usize::from(options.grinding_factor != 0),
+        };
+
+        Self {
+            trace_len,
+            lde_domain_size,
+            num_queries,
+            grinding_factor: options.grinding_factor,
+            fri_folding_factor,
+            commitments,
+            proof_elements: index_map.elements,
+            transcript,
+            ood_constraint_ops: ood_constraint_ops(air),
+            deep_composition_ops: deep_composition_ops(num_trace_arguments, ce_blowup_factor)
+                .scale(num_queries),
+            fri_ops: fri_ops(fri_folding_factor, num_fri_layers, num_remainder_coeffs)
+                .scale(num_queries),
+        }
+    }
+
+    /// Returns the number of leaf hashes and the total number of field
+    /// elements absorbed by those hashes
+    pub fn leaf_hashes(&self) -> (usize, usize) {
+        self.commitments
+            .iter()
+            .fold((0, 0), |(hashes, elements), c| {
+                (
+                    hashes + c.max_openings,
+                    elements + c.max_openings * c.row_width,
+                )
+            })
+    }
+
+    /// Upper bound on the number of two-to-one hashes across all commitments
+    pub fn max_merges(&self) -> usize {
+        self.commitments
+            .iter()
+            .map(CommitmentLayout::max_merges)
+            .sum()
+    }
+
+    /// Returns the proof element with the given name
+    pub fn proof_element(&self, name: &str) -> Option<&ProofElement> {
+        self.proof_elements.iter().find(|e| e.name == name)
+    }
+
+    /// Serializes the description as JSON
+    pub fn to_json(&self) -> String {
+        fn ops_json(ops: FieldOpCounts) -> String {
+            let FieldOpCounts {
+                add,
+                mul,
+                div,
+                neg,
+                pow,
+            } = ops;
+            format!(r#"{{"add":{add},"mul":{mul},"div":{div},"neg":{neg},"pow":{pow}}}"#)
+        }
+
+        let commitments = self
+            .commitments
+            .iter()
+            .map(|c| {
+                format!(
+                    r#"{{"name":"{}","kind":"{}","row_width":{},"num_leaves":{},"depth":{},"max_openings":{}}}"#,
+                    c.name,
+                    c.kind.name(),
+                    c.row_width,
+                    c.num_leaves,
+                    c.depth,
+                    c.max_openings
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        let proof_elements = self
+            .proof_elements
+            .iter()
+            .map(|e| {
+                format!(
+                    r#"{{"name":"{}","kind":"{}","offset":{},"len":{}}}"#,
+                    e.name,
+                    e.kind.name(),
+                    e.offset,
+                    e.len
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        let (leaf_hashes, leaf_hash_elements) = self.leaf_hashes();
+        let TranscriptOps {
+            reseeds,
+            field_draws,
+            query_draws,
+            proof_of_work_checks,
+        } = self.transcript;
+
+        let mut json = String::new();
+        write!(
+            json,
+            r#"{{"trace_len":{},"lde_domain_size":{},"num_queries":{},"grinding_factor":{},"fri_folding_factor":{},"#,
+            self.trace_len,
+            self.lde_domain_size,
+            self.num_queries,
+            self.grinding_factor,
+            self.fri_folding_factor
+        )
+        .unwrap();
+        write!(
+            json,
+            r#""commitments":[{commitments}],"proof_elements":[{proof_elements}],"#
+        )
+        .unwrap();
+        write!(
+            json,
+            r#""hashes":{{"leaf_hashes":{leaf_hashes},"leaf_hash_elements":{leaf_hash_elements},"max_merges":{}}},"#,
+            self.max_merges()
+        )
+        .unwrap();
+        write!(
+            json,
+            r#""transcript":{{"reseeds":{reseeds},"field_draws":{field_draws},"query_draws":{query_draws},"proof_of_work_checks":{proof_of_work_checks}}},"#
+        )
+        .unwrap();
+        write!(
+            json,
+            r#""field_ops":{{"ood_constraint":{},"deep_composition":{},"fri":{}}}}}"#,
+            ops_json(self.ood_constraint_ops),
+            ops_json(self.deep_composition_ops),
+            ops_json(self.fri_ops)
+        )
+        .unwrap();
+        json
+    }
+}
+
+#[derive(Default)]
+struct IndexMap {
+    elements: Vec<ProofElement>,
+}
+
+impl IndexMap {
+    fn push(&mut self, name: &str, kind: ElementKind, len: usize) {
+        let offset = self
+            .elements
+            .iter()
+            .filter(|e| e.kind == kind)
+            .map(|e| e.len)
+            .sum();
+        self.elements.push(ProofElement {
+            name: name.into(),
+            kind,
+            offset,
+            len,
+        });
+    }
+}
+
+fn ood_constraint_ops<A: AirConfig>(air: &Air<A>) -> FieldOpCounts {
+    let mut ops = FieldOpCounts::default();
+    for instruction in air.compiled_composition_constraint().instructions() {
+        match instruction {
+            Instruction::Leaf(CompositionItem::Item(AlgebraicItem::Periodic(col))) => {
+                // horner evaluation of the periodic column's polynomial
+                let num_coeffs = col.coeffs().len();
+                ops += FieldOpCounts {
+                    add: num_coeffs,
+                    mul: num_coeffs,
+                    pow: 1,
+                    ..Default::default()
+                };
+            }
+            Instruction::Leaf(_) => {}
+            Instruction::Neg(_) => ops.neg += 1,
+            Instruction::Add(..) => ops.add += 1,
+            Instruction::Mul(..) => ops.mul += 1,
+            Instruction::Div(..) => ops.div += 1,
+            Instruction::Pow(..) => ops.pow += 1,
+        }
+    }
+    // horner evaluation of the composition trace OOD evaluations
+    let ce_blowup_factor = air.ce_blowup_factor();
+    ops.add += ce_blowup_factor;
+    ops.mul += ce_blowup_factor;
+    ops
+}
+
+/// Field operations to evaluate the DEEP composition polynomial at one query
+const fn deep_composition_ops(
+    num_trace_arguments: usize,
+    num_composition_columns: usize,
+) -> FieldOpCounts {
+    FieldOpCounts {
+        // (value - ood_eval), (x - z * shift), accumulation and degree adjustment
+        add: 3 * num_trace_arguments + 3 * num_composition_columns + 1,
+        mul: 2 * num_trace_arguments + num_composition_columns + 2,
+        div: num_trace_arguments + num_composition_columns,
+        neg: 0,
+        // query point and trace argument shifts
+        pow: 1 + num_trace_arguments,
+    }
+}
+
+/// Field operations to fold all FRI layers and check the remainder at one query
+const fn fri_ops(
+    folding_factor: usize,
+    num_layers: usize,
+    num_remainder_coeffs: usize,
+) -> FieldOpCounts {
+    // interpolate the coset with an IFFT then evaluate the result at alpha
+    let log_folding_factor = folding_factor.ilog2() as usize;
+    let fold_mul = folding_factor * log_folding_factor / 2 + 2 * folding_factor;
+    let fold_add = folding_factor * log_folding_factor + folding_factor;
+    FieldOpCounts {
+        add: fold_add * num_layers + num_remainder_coeffs,
+        mul: fold_mul * num_layers + num_remainder_coeffs,
+        div: 0,
+        neg: 0,
+        // coset offset of each layer and the remainder's evaluation point
+        pow: num_layers + 1,
+    }
+}
@@ -0,0 +1,844 @@
+//! Generates Metal kernels specialized to the constraints of a specific AIR.
+//!
+//! The generic GPU evaluator ([`crate::eval_gpu`]) runs a kernel for every
+//! operation of the composition constraint and stores intermediate results in
+//! device memory. A specialized kernel evaluates the entire composition
+//! constraint for a single row in one thread. Constants are inlined and trace
+//! column reads are baked into the kernel. Challenges, hints and composition
+//! coefficients are only known during proving so they're read from a small
+//! constant buffer. Kernels are compiled once and cached.
+//!
+//! Compiling a kernel for every AIR can be slow so constraints can instead be
+//! lowered to a [`ConstraintProgram`] which is run by a precompiled
+//! interpreter kernel. The interpreter is also fused (one thread per row and
+//! intermediate values in registers) but pays for decoding instructions.
+use crate::constraints::AlgebraicItem;
+use crate::constraints::CompositionItem;
+use crate::expression::CompiledExpr;
+use crate::expression::Instruction;
+use crate::utils::FieldVariant;
+#[cfg(metal_gpu)]
+use crate::utils::GpuAllocator;
+#[cfg(metal_gpu)]
+use crate::Matrix;
+#[cfg(metal_gpu)]
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use core::fmt::Write;
+#[cfg(metal_gpu)]
+use ministark_gpu::metal;
+#[cfg(metal_gpu)]
+use ministark_gpu::prelude::*;
+#[cfg(metal_gpu)]
+use ministark_gpu::utils::buffer_mut_no_copy;
+#[cfg(metal_gpu)]
+use ministark_gpu::utils::buffer_no_copy;
+use ministark_gpu::utils::FELT_U64_METAL_SOURCE;
+#[cfg(metal_gpu)]
+use ministark_gpu::GpuAdd;
+use ministark_gpu::GpuField;
+#[cfg(metal_gpu)]
+use ministark_gpu::GpuMul;
+use snafu::Snafu;
+#[cfg(metal_gpu)]
+use std::sync::Mutex;
+
+/// Name of the entry point of generated kernels
+pub const KERNEL_NAME: &str = "eval_composition_constraint";
+
+/// Kernel generation error
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("field `{field}` has no Metal implementation"))]
+    UnsupportedField { field: String },
+    #[snafu(display("periodic columns are not supported by generated kernels"))]
+    PeriodicColumn,
+    #[snafu(display("division by an extension field element is not supported"))]
+    ExtensionFieldDivision,
+    #[snafu(display("exponent `{exponent}` does not fit in 32 bits"))]
+    ExponentTooLarge { exponent: usize },
+    #[snafu(display("column `{col}` does not exist"))]
+    InvalidColumn { col: usize },
+    #[snafu(display("kernel failed to compile: {message}"))]
+    Compile { message: String },
+    #[snafu(display("program needs {num} registers but at most {MAX_REGISTERS} are available"))]
+    TooManyRegisters { num: usize },
+    #[snafu(display("interpreter kernel is not available: {message}"))]
+    MissingKernel { message: String },
+}
+
+/// Metal type and the source that defines it for fields that have a Metal
+/// implementation
+fn metal_field(field_name: &str) -> Result<(&'static str, &'static str), Error> {
+    match field_name {
+        "p18446744069414584321_fp" => Ok(("p18446744069414584321::Fp", FELT_U64_METAL_SOURCE)),
+        "p18446744069414584321_fq2" => Ok(("p18446744069414584321::Fq2", FELT_U64_METAL_SOURCE)),
+        "p18446744069414584321_fq3" => Ok(("p18446744069414584321::Fq3", FELT_U64_METAL_SOURCE)),
+        _ => UnsupportedFieldSnafu { field: field_name }.fail(),
+    }
+}
+
+/// Returns a Metal expression that constructs `v`
+fn metal_literal<F: Field>(v: &F, metal_type: &str) -> String {
+    assert_eq!(core::mem::size_of::<F>() % 8, 0);
+    // Field elements are copied to the GPU as is so the words of an element are
+    // its representation in the kernel (i.e. Montgomery form).
+    let words = unsafe {
+        core::slice::from_raw_parts(
+            core::ptr::from_ref(v).cast::<u64>(),
+            core::mem::size_of::<F>() / 8,
+        )
+    };
+    match words {
+        [word] => format!("{metal_type}({word}ul)"),
+        words => {
+            let coeffs = words
+                .iter()
+                .map(|word| format!("Fp({word}ul)"))
+                .collect::<Vec<String>>();
+            format!("{metal_type}({})", coeffs.join(", "))
+        }
+    }
+}
+
+/// Returns the number of challenges, hints and composition coefficients
+/// referenced by a composition constraint. These are only known during proving.
+fn count_runtime_constants<Fp, Fq>(
+    expr: &CompiledExpr<CompositionItem<FieldVariant<Fp, Fq>>>,
+) -> (usize, usize, usize) {
+    use AlgebraicItem::*;
+    let mut num_challenges = 0;
+    let mut num_hints = 0;
+    let mut num_composition_coeffs = 0;
+    for leaf in expr.leaves() {
+        match *leaf {
+            CompositionItem::Item(Challenge(i)) => num_challenges = num_challenges.max(i + 1),
+            CompositionItem::Item(Hint(i)) => num_hints = num_hints.max(i + 1),
+            CompositionItem::CompositionCoeff(i) => {
+                num_composition_coeffs = num_composition_coeffs.max(i + 1);
+            }
+            CompositionItem::Item(X | Constant(_) | Periodic(_) | Trace(..)) => {}
+        }
+    }
+    (num_challenges, num_hints, num_composition_coeffs)
+}
+
+/// A Metal kernel that evaluates the composition constraint of an AIR
+#[derive(Clone, Debug)]
+pub struct MetalConstraintKernel {
+    pub source: String,
+    pub num_challenges: usize,
+    pub num_hints: usize,
+    pub num_composition_coeffs: usize,
+}
+
+impl MetalConstraintKernel {
+    /// Generates a kernel for a compiled composition constraint. Trace columns
+    /// `0..num_base_columns` are base columns and the remaining columns are
+    /// extension columns.
+    #[allow(clippy::too_many_lines)]
+    pub fn generate<Fp: GpuField + Field, Fq: GpuField + Field>(
+        expr: &CompiledExpr<CompositionItem<FieldVariant<Fp, Fq>>>,
+        num_base_columns: usize,
+        num_extension_columns: usize,
+    ) -> Result<Self, Error> {
+        use AlgebraicItem::*;
+        let (base_field_type, header) = metal_field(&Fp::field_name())?;
+        let (extension_field_type, _) = metal_field(&Fq::field_name())?;
+        let has_extension = base_field_type != extension_field_type;
+
+        let (num_challenges, num_hints, num_composition_coeffs) = count_runtime_constants(expr);
+
+        // reads a row of a column relative to the current row
+        let row = |offset: isize| match offset {
+            0 => "i".into(),
+            1.. => format!("(i + {offset} * step) & mask"),
+            _ => format!("(i - {} * step) & mask", offset.unsigned_abs()),
+        };
+
+        let mut body = String::new();
+        // tracks which registers hold extension field elements
+        let mut is_fq_register = Vec::new();
+        for (i, instruction) in expr.instructions().iter().enumerate() {
+            let (is_fq, value) = match *instruction {
+                Instruction::Leaf(ref leaf) => match *leaf {
+                    CompositionItem::Item(X) => (false, "x[i]".into()),
+                    CompositionItem::Item(Constant(FieldVariant::Fp(v))) => {
+                        (false, metal_literal(&v, base_field_type))
+                    }
+                    CompositionItem::Item(Constant(FieldVariant::Fq(v))) => {
+                        (has_extension, metal_literal(&v, extension_field_type))
+                    }
+                    CompositionItem::Item(Challenge(j)) => {
+                        (has_extension, format!("constants[{j}]"))
+                    }
+                    CompositionItem::Item(Hint(j)) => {
+                        (has_extension, format!("constants[{}]", num_challenges + j))
+                    }
+                    CompositionItem::CompositionCoeff(j) => (
+                        has_extension,
+                        format!("constants[{}]", num_challenges + num_hints + j),
+                    ),
+                    CompositionItem::Item(Trace(col, offset)) => {
+                        if col < num_base_columns {
+                            (false, format!("base.cols[{col}][{}]", row(offset)))
+                        } else if col < num_base_columns + num_extension_columns {
+                            let extension_col = col - num_base_columns;
+                            let value = format!("extension.cols[{extension_col}][{}]", row(offset));
+                            (has_extension, value)
+                        } else {
+                            return InvalidColumnSnafu { col }.fail();
+                        }
+                    }
+                    CompositionItem::Item(Periodic(_)) => return PeriodicColumnSnafu.fail(),
+                },
+                Instruction::Neg(a) => (is_fq_register[a], format!("t{a}.neg()")),
+                // extension field classes only implement `Fq op Fp`
+                Instruction::Add(a, b) if !is_fq_register[a] && is_fq_register[b] => {
+                    (true, format!("t{b} + t{a}"))
+                }
+                Instruction::Add(a, b) => (is_fq_register[a], format!("t{a} + t{b}")),
+                Instruction::Mul(a, b) if !is_fq_register[a] && is_fq_register[b] => {
+                    (true, format!("t{b} * t{a}"))
+                }
+                Instruction::Mul(a, b) => (is_fq_register[a], format!("t{a} * t{b}")),
+                Instruction::Div(_, b) if is_fq_register[b] => {
+                    return ExtensionFieldDivisionSnafu.fail()
+                }
+                Instruction::Div(a, b) => (is_fq_register[a], format!("t{a} * t{b}.inverse()")),
+                Instruction::Pow(a, exponent) => {
+                    if u32::try_from(exponent).is_err() {
+                        return ExponentTooLargeSnafu { exponent }.fail();
+                    }
+                    // `pow` modifies the value it's called on so operate on a copy
+                    let metal_type = if is_fq_register[a] { "Fq" } else { "Fp" };
+                    (
+                        is_fq_register[a],
+                        format!("{metal_type}(t{a}).pow({exponent})"),
+                    )
+                }
+            };
+            let metal_type = if is_fq { "Fq" } else { "Fp" };
+            writeln!(body, "    {metal_type} t{i} = {value};").unwrap();
+            is_fq_register.push(is_fq);
+        }
+        let res = is_fq_register.len() - 1;
+        if has_extension && !is_fq_register[res] {
+            writeln!(body, "    result[i] = Fq(t{res});").unwrap();
+        } else {
+            writeln!(body, "    result[i] = t{res};").unwrap();
+        }
+
+        let mut source = String::new();
+        writeln!(source, "#include <metal_stdlib>").unwrap();
+        writeln!(source, "{header}").unwrap();
+        writeln!(source, "using Fp = {base_field_type};").unwrap();
+        writeln!(source, "using Fq = {extension_field_type};").unwrap();
+        // columns are passed as arrays of device addresses
+        let mut column_params = String::new();
+        if num_base_columns > 0 {
+            writeln!(source, "struct BaseColumns {{").unwrap();
+            writeln!(source, "    device const Fp* cols[{num_base_columns}];").unwrap();
+            writeln!(source, "}};").unwrap();
+            writeln!(
+                column_params,
+                "        constant BaseColumns& base [[ buffer(2) ]],"
+            )
+            .unwrap();
+        }
+        if num_extension_columns > 0 {
+            writeln!(source, "struct ExtensionColumns {{").unwrap();
+            writeln!(
+                source,
+                "    device const Fq* cols[{num_extension_columns}];"
+            )
+            .unwrap();
+            writeln!(source, "}};").unwrap();
+            writeln!(
+                column_params,
+                "        constant ExtensionColumns& extension [[ buffer(3) ]],"
+            )
+            .unwrap();
+        }
+        writeln!(source, "kernel void {KERNEL_NAME}(").unwrap();
+        writeln!(source, "        device Fq* result [[ buffer(0) ]],").unwrap();
+        writeln!(source, "        device const Fp* x [[ buffer(1) ]],").unwrap();
+        write!(source, "{column_params}").unwrap();
+        writeln!(source, "        constant Fq* constants [[ buffer(4) ]],").unwrap();
+        writeln!(source, "        constant unsigned& step [[ buffer(5) ]],").unwrap();
+        writeln!(source, "        unsigned i [[ thread_position_in_grid ]],").unwrap();
+        writeln!(source, "        unsigned n [[ threads_per_grid ]]) {{").unwrap();
+        writeln!(source, "    const unsigned mask = n - 1;").unwrap();
+        write!(source, "{body}").unwrap();
+        writeln!(source, "}}").unwrap();
+
+        Ok(Self {
+            source,
+            num_challenges,
+            num_hints,
+            num_composition_coeffs,
+        })
+    }
+
+    /// Compiles the kernel for the default device. Compiled kernels are cached
+    /// so kernels can be compiled at startup to keep compilation out of
+    /// proving.
+    #[cfg(metal_gpu)]
+    pub fn compile(&self) -> Result<metal::ComputePipelineState, Error> {
+        static PIPELINES: Mutex<BTreeMap<String, metal::ComputePipelineState>> =
+            Mutex::new(BTreeMap::new());
+        let mut pipelines = PIPELINES.lock().unwrap();
+        if let Some(pipeline) = pipelines.get(&self.source) {
+            return Ok(pipeline.clone());
+        }
+        let device = get_planner().command_queue().device();
+        let compile_error = |message| CompileSnafu { message }.build();
+        let library = device
+            .new_library_with_source(&self.source, &metal::CompileOptions::new())
+            .map_err(compile_error)?;
+        let function = library
+            .get_function(KERNEL_NAME, None)
+            .map_err(compile_error)?;
+        let pipeline = device
+            .new_compute_pipeline_state_with_function(&function)
+            .map_err(compile_error)?;
+        pipelines.insert(self.source.clone(), pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Evaluates the composition constraint over the constraint evaluation
+    /// domain
+    #[cfg(metal_gpu)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn eval<Fp: GpuField + Field, Fq: GpuField + Field>(
+        &self,
+        challenges: &[Fq],
+        hints: &[Fq],
+        composition_coeffs: &[Fq],
+        lde_step: usize,
+        x_lde: &[Fp],
+        base_trace_lde_cols: &[&[Fp]],
+        extension_trace_lde_cols: Option<&[&[Fq]]>,
+    ) -> Result<Matrix<Fq>, Error> {
+        let pipeline = self.compile()?;
+        let command_queue = get_planner().command_queue();
+        let device = command_queue.device();
+        let n = x_lde.len();
+        let mut result = Vec::with_capacity_in(n, GpuAllocator);
+        result.resize(n, Fq::ZERO);
+
+        let mut constants = Vec::new();
+        constants.extend_from_slice(&challenges[..self.num_challenges]);
+        constants.extend_from_slice(&hints[..self.num_hints]);
+        constants.extend_from_slice(&composition_coeffs[..self.num_composition_coeffs]);
+        // Metal buffers can't be empty
+        constants.push(Fq::ZERO);
+        let new_buffer = |bytes: &[u8]| {
+            device.new_buffer_with_data(
+                bytes.as_ptr().cast(),
+                bytes.len() as u64,
+                metal::MTLResourceOptions::StorageModeShared,
+            )
+        };
+        let constants_buffer = new_buffer(unsafe {
+            core::slice::from_raw_parts(
+                constants.as_ptr().cast(),
+                core::mem::size_of_val(&*constants),
+            )
+        });
+
+        let result_buffer = buffer_mut_no_copy(device, &mut result);
+        let x_buffer = buffer_no_copy(device, x_lde);
+        let base_buffers = base_trace_lde_cols
+            .iter()
+            .map(|col| buffer_no_copy(device, col))
+            .collect::<Vec<metal::Buffer>>();
+        let extension_buffers = extension_trace_lde_cols
+            .into_iter()
+            .flatten()
+            .map(|col| buffer_no_copy(device, col))
+            .collect::<Vec<metal::Buffer>>();
+        let addresses_buffer = |buffers: &[metal::Buffer]| {
+            let mut bytes = Vec::new();
+            for buffer in buffers {
+                bytes.extend_from_slice(&buffer.gpu_address().to_ne_bytes());
+            }
+            bytes.resize(bytes.len().max(8), 0);
+            new_buffer(&bytes)
+        };
+        let base_addresses = addresses_buffer(&base_buffers);
+        let extension_addresses = addresses_buffer(&extension_buffers);
+        let step = u32::try_from(lde_step).unwrap();
+
+        let command_buffer = command_queue.new_command_buffer();
+        let encoder = command_buffer.new_compute_command_encoder();
+        encoder.set_compute_pipeline_state(&pipeline);
+        encoder.set_buffer(0, Some(&result_buffer), 0);
+        encoder.set_buffer(1, Some(&x_buffer), 0);
+        encoder.set_buffer(2, Some(&base_addresses), 0);
+        encoder.set_buffer(3, Some(&extension_addresses), 0);
+        encoder.set_buffer(4, Some(&constants_buffer), 0);
+        encoder.set_bytes(
+            5,
+            core::mem::size_of::<u32>() as u64,
+            core::ptr::from_ref(&step).cast(),
+        );
+        // columns are only referenced by address so must be made resident
+        for buffer in base_buffers.iter().chain(&extension_buffers) {
+            encoder.use_resource(buffer, metal::MTLResourceUsage::Read);
+        }
+        let threadgroup_size = pipeline.max_total_threads_per_threadgroup().min(n as u64);
+        encoder.dispatch_threads(
+            metal::MTLSize::new(n as u64, 1, 1),
+            metal::MTLSize::new(threadgroup_size, 1, 1),
+        );
+        encoder.end_encoding();
+        let start = std::time::Instant::now();
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+        crate::events::record_gpu_timing("constraint evaluation", start.elapsed());
+        Ok(Matrix::new(vec![result]))
+    }
+}
+
+/// Maximum number of base field and of extension field registers available to
+/// a [`ConstraintProgram`]. Must match `MAX_CONSTRAINT_REGISTERS` in
+/// `evaluation_shaders.h.metal`.
+pub const MAX_REGISTERS: usize = 32;
+
+/// Base and extension fields the interpreter kernel is instantiated for
+const INTERPRETER_FIELDS: &[(&str, &str)] = &[
+    ("p18446744069414584321_fp", "p18446744069414584321_fp"),
+    ("p18446744069414584321_fp", "p18446744069414584321_fq2"),
+    ("p18446744069414584321_fp", "p18446744069414584321_fq3"),
+    (
+        "p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp",
+        "p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp",
+    ),
+    ("p2013265921_fp", "p2013265921_fp"),
+    ("p2147483647_fp", "p2147483647_fp"),
+];
+
+/// Operations of the constraint interpreter kernel. `Fp` and `Fq` operations
+/// act on base field and extension field registers respectively.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opcode {
+    /// `fp[dst] = x`
+    LoadX = 0,
+    /// `fp[dst] = fp_constants[a]`
+    LoadFp = 1,
+    /// `fq[dst] = fq_constants[a]`
+    LoadFq = 2,
+    /// `fp[dst] = base_column_a[row + b]`
+    LoadBase = 3,
+    /// `fq[dst] = extension_column_a[row + b]`
+    LoadExtension = 4,
+    NegFp = 5,
+    NegFq = 6,
+    AddFp = 7,
+    AddFq = 8,
+    /// `fq[dst] = fq[a] + fp[b]`
+    AddFqFp = 9,
+    MulFp = 10,
+    MulFq = 11,
+    /// `fq[dst] = fq[a] * fp[b]`
+    MulFqFp = 12,
+    DivFp = 13,
+    /// `fq[dst] = fq[a] / fp[b]`
+    DivFqFp = 14,
+    /// `fp[dst] = fp[a]^b`
+    PowFp = 15,
+    /// `fq[dst] = fq[a]^b`
+    PowFq = 16,
+    ReturnFp = 17,
+    ReturnFq = 18,
+}
+
+/// Allocates registers of a single register file
+#[derive(Default)]
+struct RegisterFile {
+    free: Vec<u32>,
+    len: u32,
+}
+
+impl RegisterFile {
+    fn alloc(&mut self) -> u32 {
+        self.free.pop().unwrap_or_else(|| {
+            self.len += 1;
+            self.len - 1
+        })
+    }
+}
+
+/// A composition constraint lowered to instructions of the constraint
+/// interpreter kernel.
+///
+/// Instructions are of the form `[op, dst, a, b]`. Registers are reused once
+/// the value they hold is no longer needed. The extension field constants
+/// passed to the kernel are the challenges, hints and composition
+/// coefficients (in that order) followed by
+/// [`ConstraintProgram::fq_constants`].
+#[derive(Clone, Debug)]
+pub struct ConstraintProgram<Fp, Fq> {
+    pub code: Vec<[u32; 4]>,
+    pub fp_constants: Vec<Fp>,
+    pub fq_constants: Vec<Fq>,
+    pub num_fp_registers: usize,
+    pub num_fq_registers: usize,
+    pub num_challenges: usize,
+    pub num_hints: usize,
+    pub num_composition_coeffs: usize,
+}
+
+impl<Fp: GpuField + Field, Fq: GpuField + Field> ConstraintProgram<Fp, Fq> {
+    /// Lowers a compiled composition constraint. Trace columns
+    /// `0..num_base_columns` are base columns and the remaining columns are
+    /// extension columns.
+    #[allow(clippy::too_many_lines)]
+    pub fn lower(
+        expr: &CompiledExpr<CompositionItem<FieldVariant<Fp, Fq>>>,
+        num_base_columns: usize,
+        num_extension_columns: usize,
+    ) -> Result<Self, Error> {
+        use AlgebraicItem::*;
+        use Opcode::*;
+        let fields = (Fp::field_name(), Fq::field_name());
+        if !INTERPRETER_FIELDS
+            .iter()
+            .any(|&(fp, fq)| fp == fields.0 && fq == fields.1)
+        {
+            return UnsupportedFieldSnafu { field: fields.1 }.fail();
+        }
+        let (num_challenges, num_hints, num_composition_coeffs) = count_runtime_constants(expr);
+        let num_runtime_constants = num_challenges + num_hints + num_composition_coeffs;
+
+        let mut code = Vec::new();
+        let mut fp_constants = Vec::new();
+        let mut fq_constants = Vec::new();
+        let mut fp_registers = RegisterFile::default();
+        let mut fq_registers = RegisterFile::default();
+        // register file (`true` for extension field) and register of each
+        // instruction's output
+        let mut locations: Vec<(bool, u32)> = Vec::new();
+        let last_use = expr.last_use();
+        let index = |i: usize| u32::try_from(i).unwrap();
+        for (i, instruction) in expr.instructions().iter().enumerate() {
+            let (op, is_fq, a, b) = match *instruction {
+                Instruction::Leaf(ref leaf) => match *leaf {
+                    CompositionItem::Item(X) => (LoadX, false, 0, 0),
+                    CompositionItem::Item(Constant(FieldVariant::Fp(v))) => {
+                        fp_constants.push(v);
+                        (LoadFp, false, index(fp_constants.len() - 1), 0)
+                    }
+                    CompositionItem::Item(Constant(FieldVariant::Fq(v))) => {
+                        fq_constants.push(v);
+                        let j = num_runtime_constants + fq_constants.len() - 1;
+                        (LoadFq, true, index(j), 0)
+                    }
+                    CompositionItem::Item(Challenge(j)) => (LoadFq, true, index(j), 0),
+                    CompositionItem::Item(Hint(j)) => (LoadFq, true, index(num_challenges + j), 0),
+                    CompositionItem::CompositionCoeff(j) => {
+                        let j = num_challenges + num_hints + j;
+                        (LoadFq, true, index(j), 0)
+                    }
+                    CompositionItem::Item(Trace(col, offset)) => {
+                        // offsets are stored in two's complement
+                        #[allow(clippy::cast_sign_loss)]
+                        let offset = i32::try_from(offset).unwrap() as u32;
+                        if col < num_base_columns {
+                            (LoadBase, false, index(col), offset)
+                        } else if col < num_base_columns + num_extension_columns {
+                            let extension_col = col - num_base_columns;
+                            (LoadExtension, true, index(extension_col), offset)
+                        } else {
+                            return InvalidColumnSnafu { col }.fail();
+                        }
+                    }
+                    CompositionItem::Item(Periodic(_)) => return PeriodicColumnSnafu.fail(),
+                },
+                Instruction::Neg(a) => match locations[a] {
+                    (false, a) => (NegFp, false, a, 0),
+                    (true, a) => (NegFq, true, a, 0),
+                },
+                Instruction::Add(a, b) => match (locations[a], locations[b]) {
+                    ((false, a), (false, b)) => (AddFp, false, a, b),
+                    ((true, a), (true, b)) => (AddFq, true, a, b),
+                    ((true, fq), (false, fp)) | ((false, fp), (true, fq)) => {
+                        (AddFqFp, true, fq, fp)
+                    }
+                },
+                Instruction::Mul(a, b) => match (locations[a], locations[b]) {
+                    ((false, a), (false, b)) => (MulFp, false, a, b),
+                    ((true, a), (true, b)) => (MulFq, true, a, b),
+                    ((true, fq), (false, fp)) | ((false, fp), (true, fq)) => {
+                        (MulFqFp, true, fq, fp)
+                    }
+                },
+                Instruction::Div(a, b) => match (locations[a], locations[b]) {
+                    (_, (true, _)) => return ExtensionFieldDivisionSnafu.fail(),
+                    ((false, a), (false, b)) => (DivFp, false, a, b),
+                    ((true, a), (false, b)) => (DivFqFp, true, a, b),
+                },
+                Instruction::Pow(a, exponent) => {
+                    let exponent = u32::try_from(exponent)
+                        .map_err(|_| ExponentTooLargeSnafu { exponent }.build())?;
+                    match locations[a] {
+                        (false, a) => (PowFp, false, a, exponent),
+                        (true, a) => (PowFq, true, a, exponent),
+                    }
+                }
+            };
+
+            // free the registers of operands that aren't used again. The kernel
+            // reads operands before writing so the output can reuse them.
+            let operands = match *instruction {
+                Instruction::Leaf(_) => vec![],
+                Instruction::Neg(a) | Instruction::Pow(a, _) => vec![a],
+                Instruction::Add(a, b) | Instruction::Mul(a, b) | Instruction::Div(a, b) => {
+                    if a == b {
+                        vec![a]
+                    } else {
+                        vec![a, b]
+                    }
+                }
+            };
+            for operand in operands {
+                if last_use[operand] == i {
+                    match locations[operand] {
+                        (false, register) => fp_registers.free.push(register),
+                        (true, register) => fq_registers.free.push(register),
+                    }
+                }
+            }
+
+            let dst = if is_fq {
+                fq_registers.alloc()
+            } else {
+                fp_registers.alloc()
+            };
+            code.push([op as u32, dst, a, b]);
+            locations.push((is_fq, dst));
+        }
+        match locations.last() {
+            Some(&(false, register)) => code.push([ReturnFp as u32, 0, register, 0]),
+            Some(&(true, register)) => code.push([ReturnFq as u32, 0, register, 0]),
+            None => {}
+        }
+
+        let num_fp_registers = fp_registers.len as usize;
+        let num_fq_registers = fq_registers.len as usize;
+        let num_registers = num_fp_registers.max(num_fq_registers);
+        if num_registers > MAX_REGISTERS {
+            return TooManyRegistersSnafu { num: num_registers }.fail();
+        }
+
+        Ok(Self {
+            code,
+            fp_constants,
+            fq_constants,
+            num_fp_registers,
+            num_fq_registers,
+            num_challenges,
+            num_hints,
+            num_composition_coeffs,
+        })
+    }
+
+    /// Interprets the program for a single row on the CPU the same way the
+    /// interpreter kernel does. Useful for checking programs.
+    #[allow(clippy::too_many_arguments)]
+    pub fn eval_row(
+        &self,
+        row: usize,
+        challenges: &[Fq],
+        hints: &[Fq],
+        composition_coeffs: &[Fq],
+        lde_step: usize,
+        x_lde: &[Fp],
+        base_trace_lde_cols: &[&[Fp]],
+        extension_trace_lde_cols: Option<&[&[Fq]]>,
+    ) -> Fq
+    where
+        Fq: From<Fp>,
+    {
+        const OPCODES: [Opcode; 19] = {
+            use Opcode::*;
+            [
+                LoadX,
+                LoadFp,
+                LoadFq,
+                LoadBase,
+                LoadExtension,
+                NegFp,
+                NegFq,
+                AddFp,
+                AddFq,
+                AddFqFp,
+                MulFp,
+                MulFq,
+                MulFqFp,
+                DivFp,
+                DivFqFp,
+                PowFp,
+                PowFq,
+                ReturnFp,
+                ReturnFq,
+            ]
+        };
+        let fq_constants = self.runtime_constants(challenges, hints, composition_coeffs);
+        let extension_trace_lde_cols = extension_trace_lde_cols.unwrap_or_default();
+        let n = x_lde.len();
+        // the kernel relies on the domain size dividing 2^32 to wrap offsets
+        let row_at =
+            |offset: u32| row.wrapping_add((offset as usize).wrapping_mul(lde_step)) & (n - 1);
+        let mut fp = vec![Fp::ZERO; self.num_fp_registers];
+        let mut fq = vec![Fq::ZERO; self.num_fq_registers];
+        for &[op, dst, a, b] in &self.code {
+            use Opcode::*;
+            let (dst, a, b_idx) = (dst as usize, a as usize, b as usize);
+            match OPCODES[op as usize] {
+                LoadX => fp[dst] = x_lde[row],
+                LoadFp => fp[dst] = self.fp_constants[a],
+                LoadFq => fq[dst] = fq_constants[a],
+                LoadBase => fp[dst] = base_trace_lde_cols[a][row_at(b)],
+                LoadExtension => fq[dst] = extension_trace_lde_cols[a][row_at(b)],
+                NegFp => fp[dst] = -fp[a],
+                NegFq => fq[dst] = -fq[a],
+                AddFp => fp[dst] = fp[a] + fp[b_idx],
+                AddFq => fq[dst] = fq[a] + fq[b_idx],
+                AddFqFp => fq[dst] = fq[a] + Fq::from(fp[b_idx]),
+                MulFp => fp[dst] = fp[a] * fp[b_idx],
+                MulFq => fq[dst] = fq[a] * fq[b_idx],
+                MulFqFp => fq[dst] = fq[a] * Fq::from(fp[b_idx]),
+                DivFp => fp[dst] = fp[a] / fp[b_idx],
+                DivFqFp => fq[dst] = fq[a] / Fq::from(fp[b_idx]),
+                PowFp => fp[dst] = fp[a].pow([u64::from(b)]),
+                PowFq => fq[dst] = fq[a].pow([u64::from(b)]),
+                ReturnFp => return Fq::from(fp[a]),
+                ReturnFq => return fq[a],
+            }
+        }
+        unreachable!("programs end with a return")
+    }
+
+    /// Extension field constants in the order the kernel expects them
+    fn runtime_constants(
+        &self,
+        challenges: &[Fq],
+        hints: &[Fq],
+        composition_coeffs: &[Fq],
+    ) -> Vec<Fq> {
+        let mut constants = Vec::new();
+        constants.extend_from_slice(&challenges[..self.num_challenges]);
+        constants.extend_from_slice(&hints[..self.num_hints]);
+        constants.extend_from_slice(&composition_coeffs[..self.num_composition_coeffs]);
+        constants.extend_from_slice(&self.fq_constants);
+        constants
+    }
+
+    /// Evaluates the composition constraint over the constraint evaluation
+    /// domain with the interpreter kernel
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the precompiled Metal library doesn't have the
+    /// interpreter kernel for the fields
+    #[cfg(metal_gpu)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn eval(
+        &self,
+        challenges: &[Fq],
+        hints: &[Fq],
+        composition_coeffs: &[Fq],
+        lde_step: usize,
+        x_lde: &[Fp],
+        base_trace_lde_cols: &[&[Fp]],
+        extension_trace_lde_cols: Option<&[&[Fq]]>,
+    ) -> Result<Matrix<Fq>, Error>
+    where
+        Fq: GpuMul<Fp> + GpuAdd<Fp>,
+    {
+        fn as_bytes<T>(values: &[T]) -> &[u8] {
+            unsafe {
+                core::slice::from_raw_parts(values.as_ptr().cast(), core::mem::size_of_val(values))
+            }
+        }
+
+        let command_queue = get_planner().command_queue();
+        let library = &get_planner().library;
+        let device = command_queue.device();
+        let n = x_lde.len();
+        let stage = EvalConstraintProgramStage::<Fp, Fq>::new(library, n)
+            .map_err(|message| Error::MissingKernel { message })?;
+        let mut result = Vec::with_capacity_in(n, GpuAllocator);
+        result.resize(n, Fq::ZERO);
+
+        let new_buffer = |bytes: &[u8]| {
+            // Metal buffers can't be empty
+            let len = bytes.len().max(8);
+            let buffer =
+                device.new_buffer(len as u64, metal::MTLResourceOptions::StorageModeShared);
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    bytes.as_ptr(),
+                    buffer.contents().cast(),
+                    bytes.len(),
+                );
+            }
+            buffer
+        };
+        let fq_constants = self.runtime_constants(challenges, hints, composition_coeffs);
+        let fq_constants_buffer = new_buffer(as_bytes(&fq_constants));
+        let fp_constants_buffer = new_buffer(as_bytes(&self.fp_constants));
+        let program_buffer = new_buffer(as_bytes(&self.code));
+
+        let result_buffer = buffer_mut_no_copy(device, &mut result);
+        let x_buffer = buffer_no_copy(device, x_lde);
+        let base_buffers = base_trace_lde_cols
+            .iter()
+            .map(|col| buffer_no_copy(device, col))
+            .collect::<Vec<metal::Buffer>>();
+        let extension_buffers = extension_trace_lde_cols
+            .into_iter()
+            .flatten()
+            .map(|col| buffer_no_copy(device, col))
+            .collect::<Vec<metal::Buffer>>();
+        let addresses_buffer = |buffers: &[metal::Buffer]| {
+            let addresses = buffers
+                .iter()
+                .map(|buffer| buffer.gpu_address())
+                .collect::<Vec<u64>>();
+            new_buffer(as_bytes(&addresses))
+        };
+        let base_addresses = addresses_buffer(&base_buffers);
+        let extension_addresses = addresses_buffer(&extension_buffers);
+        let columns = base_buffers
+            .iter()
+            .chain(&extension_buffers)
+            .map(|buffer| &**buffer)
+            .collect::<Vec<&metal::BufferRef>>();
+
+        let command_buffer = command_queue.new_command_buffer();
+        stage.encode(
+            command_buffer,
+            &result_buffer,
+            &x_buffer,
+            &base_addresses,
+            &extension_addresses,
+            &columns,
+            &program_buffer,
+            self.code.len(),
+            &fp_constants_buffer,
+            &fq_constants_buffer,
+            lde_step,
+        );
+        let start = std::time::Instant::now();
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+        crate::events::record_gpu_timing("constraint evaluation", start.elapsed());
+        Ok(Matrix::new(vec![result]))
+    }
+}
@@ -1,32 +1,45 @@
 use crate::air::AirConfig;
-use crate::utils::divide_out_point_into;
-use crate::utils::divide_out_points_into;
+use crate::utils::bit_reversed_elements;
 use crate::utils::horner_evaluate;
+use crate::utils::invert_divisor;
 use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
 use crate::Air;
 use crate::Matrix;
+use crate::SegmentedMatrix;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
+use ark_ff::FftField;
 use ark_ff::Field;
 use ark_ff::Zero;
+use ark_poly::domain::DomainCoeff;
 use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::iter::zip;
+use ministark_gpu::utils::bit_reverse;
+use ministark_gpu::GpuField;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::iter::zip;
+
+/// Execution trace and composition trace OOD evaluations
+type OodEvals<F> = (Vec<F>, Vec<F>);
 
 pub struct DeepPolyComposer<'a, A: AirConfig> {
     z: A::Fq,
     air: &'a Air<A>,
-    base_trace_polys: Matrix<A::Fp>,
+    base_trace_polys: SegmentedMatrix<'a, A::Fp>,
     extension_trace_polys: Option<Matrix<A::Fq>>,
     composition_trace_polys: Matrix<A::Fq>,
+    ood_evals: Option<OodEvals<A::Fq>>,
+    out_of_order_ffts: bool,
 }
 
 impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
     pub const fn new(
         air: &'a Air<A>,
         z: A::Fq,
-        base_trace_polys: Matrix<A::Fp>,
+        base_trace_polys: SegmentedMatrix<'a, A::Fp>,
         extension_trace_polys: Option<Matrix<A::Fq>>,
         composition_trace_polys: Matrix<A::Fq>,
     ) -> Self {
@@ -36,9 +49,21 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             base_trace_polys,
             extension_trace_polys,
             composition_trace_polys,
+            ood_evals: None,
+            out_of_order_ffts: false,
         }
     }
 
+    /// Computes LDEs and the LDE domain in bit-reversed order directly rather
+    /// than permuting them (see [`Stark::BIT_REVERSED_PIPELINE`]). Evaluations
+    /// are unchanged.
+    ///
+    /// [`Stark::BIT_REVERSED_PIPELINE`]: crate::stark::Stark::BIT_REVERSED_PIPELINE
+    pub const fn with_out_of_order_ffts(mut self, out_of_order_ffts: bool) -> Self {
+        self.out_of_order_ffts = out_of_order_ffts;
+        self
+    }
+
     /// Output is of the form `(execution_trace_evals, composition_trace_evals)`
     pub fn get_ood_evals(&mut self) -> (Vec<A::Fq>, Vec<A::Fq>) {
         let Self {
@@ -47,6 +72,8 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             base_trace_polys,
             extension_trace_polys,
             composition_trace_polys,
+            ood_evals,
+            ..
         } = self;
 
         let trace_domain = air.trace_domain();
@@ -58,7 +85,7 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
         let extension_column_range = A::NUM_BASE_COLUMNS..num_columns;
 
         // generate ood evaluations for the execution trace polynomials
-        let execution_trace_evals = ark_std::cfg_into_iter!(air.trace_arguments())
+        let execution_trace_evals: Vec<A::Fq> = ark_std::cfg_into_iter!(air.trace_arguments())
             .map(|(col_idx, offset)| {
                 let generator = if offset >= 0 { g } else { g_inv };
                 let offset = offset.unsigned_abs() as u64;
@@ -78,21 +105,42 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
 
         // generate ood evaluations for the composition trace polynomials
         let z_n = self.z.pow([composition_trace_polys.num_cols() as u64]);
-        let composition_trace_evals = ark_std::cfg_iter!(composition_trace_polys)
+        let composition_trace_evals: Vec<A::Fq> = ark_std::cfg_iter!(composition_trace_polys)
             .map(|column| horner_evaluate(column, &z_n))
             .collect();
 
+        *ood_evals = Some((
+            execution_trace_evals.clone(),
+            composition_trace_evals.clone(),
+        ));
         (execution_trace_evals, composition_trace_evals)
     }
 
+    /// Evaluates the DEEP composition polynomial over the LDE domain. Output is
+    /// in bit-reversed order.
+    ///
+    /// Each quotient `(P(x) - P(z)) / (x - z)` is computed by dividing
+    /// evaluations over the LDE coset pointwise (see [`divide_on_coset`]).
+    /// Columns are extended one at a time to limit memory usage.
+    ///
+    /// [`divide_on_coset`]: crate::utils::divide_on_coset
     // <https://medium.com/starkware/starkdex-deep-dive-the-stark-core-engine-497942d0f0ab>
-    pub fn into_deep_poly(self, composition_coeffs: DeepCompositionCoeffs<A::Fq>) -> Matrix<A::Fq> {
+    pub fn into_deep_evaluations(
+        mut self,
+        composition_coeffs: DeepCompositionCoeffs<A::Fq>,
+    ) -> GpuVec<A::Fq> {
+        let (execution_trace_oods, composition_trace_oods) = self
+            .ood_evals
+            .take()
+            .unwrap_or_else(|| self.get_ood_evals());
         let Self {
             z,
             air,
             base_trace_polys,
             extension_trace_polys,
             composition_trace_polys,
+            out_of_order_ffts,
+            ..
         } = self;
 
         let DeepCompositionCoeffs {
@@ -104,90 +152,136 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
         let trace_domain = air.trace_domain();
         let g = trace_domain.group_gen();
         let g_inv = trace_domain.group_gen_inv();
+        let lde_domain = air.lde_domain();
+        let xs = if out_of_order_ffts {
+            bit_reversed_elements(&lde_domain)
+        } else {
+            let mut xs = lde_domain.elements().collect::<Vec<A::Fp>>();
+            bit_reverse(&mut xs);
+            xs
+        };
 
-        // divide out OOD point from composition trace polys
-        let z_n = self.z.pow([composition_trace_polys.num_cols() as u64]);
-        let composition_trace_quotients = ark_std::cfg_into_iter!(composition_trace_polys.0)
-            .zip(composition_trace_alphas)
-            .map(|(mut coeffs, alpha)| {
-                divide_out_point_into(&mut coeffs, &z_n, &alpha);
-                coeffs
-            });
-
-        let num_columns = A::NUM_BASE_COLUMNS + A::NUM_EXTENSION_COLUMNS;
-        let base_column_range = 0..A::NUM_BASE_COLUMNS;
-        let extension_column_range = A::NUM_BASE_COLUMNS..num_columns;
+        // inverses of `x - z_i` for every OOD point `z_i`
+        let divisor_inv = |point: A::Fq| {
+            let mut divisor_evals = ark_std::cfg_iter!(xs)
+                .map(|&x| A::Fq::from(x) - point)
+                .collect::<Vec<A::Fq>>();
+            #[cfg(feature = "op-count")]
+            crate::op_count::record(crate::op_count::OpCounts::new(0, xs.len() as u64, 0));
+            invert_divisor(&mut divisor_evals);
+            divisor_evals
+        };
+        let z_n = z.pow([composition_trace_polys.num_cols() as u64]);
+        let composition_trace_divisor_inv = divisor_inv(z_n);
         let trace_arguments = air.trace_arguments();
-        let execution_trace_xs_and_alphas = |col_idx| {
-            let mut xs = Vec::new();
-            let mut alphas = Vec::new();
-            for (&(col, offset), &alpha) in zip(&trace_arguments, &execution_trace_alphas) {
-                if col == col_idx {
-                    let generator = if offset >= 0 { g } else { g_inv };
-                    let offset = offset.unsigned_abs() as u64;
-                    let x = z * generator.pow([offset]);
-                    xs.push(x);
-                    alphas.push(alpha);
-                }
-            }
-            (xs, alphas)
+        let offsets = trace_arguments
+            .iter()
+            .map(|&(_, offset)| offset)
+            .collect::<BTreeSet<isize>>();
+        let execution_trace_divisor_invs = offsets
+            .into_iter()
+            .map(|offset| {
+                let generator = if offset >= 0 { g } else { g_inv };
+                let point = z * generator.pow([offset.unsigned_abs() as u64]);
+                (offset, divisor_inv(point))
+            })
+            .collect::<BTreeMap<isize, Vec<A::Fq>>>();
+
+        let mut evals = Vec::with_capacity_in(xs.len(), GpuAllocator);
+        evals.resize(xs.len(), A::Fq::zero());
+
+        // composition trace
+        for ((column, alpha), ood_eval) in
+            zip(composition_trace_polys.0, composition_trace_alphas).zip(composition_trace_oods)
+        {
+            let lde = bit_reversed_lde(column, lde_domain, out_of_order_ffts);
+            let quotients = [(composition_trace_divisor_inv.as_slice(), ood_eval, alpha)];
+            accumulate_quotients(&mut evals, &lde, &quotients);
+        }
+
+        // execution trace
+        let execution_trace_quotients = |col_idx| {
+            zip(
+                &trace_arguments,
+                zip(&execution_trace_alphas, &execution_trace_oods),
+            )
+            .filter(|((col, _), _)| *col == col_idx)
+            .map(|((_, offset), (&alpha, &ood_eval))| {
+                (
+                    execution_trace_divisor_invs[offset].as_slice(),
+                    ood_eval,
+                    alpha,
+                )
+            })
+            .collect::<Vec<_>>()
         };
+        for (col_idx, column) in base_trace_polys.columns().enumerate() {
+            let column = column.to_vec_in(GpuAllocator);
+            let lde = bit_reversed_lde(column, lde_domain, out_of_order_ffts);
+            accumulate_quotients(&mut evals, &lde, &execution_trace_quotients(col_idx));
+        }
+        let extension_columns = extension_trace_polys.map_or(vec![], |t| t.0);
+        for (i, column) in extension_columns.into_iter().enumerate() {
+            let col_idx = A::NUM_BASE_COLUMNS + i;
+            let lde = bit_reversed_lde(column, lde_domain, out_of_order_ffts);
+            accumulate_quotients(&mut evals, &lde, &execution_trace_quotients(col_idx));
+        }
 
-        let base_trace_quotients = ark_std::cfg_into_iter!(base_trace_polys.0)
-            .zip(base_column_range)
-            .map(|(coeffs, col_idx)| {
-                let (xs, alphas) = execution_trace_xs_and_alphas(col_idx);
-                // TODO: inefficient when Fp != Fq
-                let mut coeffs = coeffs
-                    .into_iter()
-                    .map(A::Fq::from)
-                    .collect::<Vec<_>>()
-                    .to_vec_in(GpuAllocator);
-                divide_out_points_into(&mut coeffs, &xs, &alphas);
-                coeffs
-            });
-
-        let extension_trace_quotients =
-            ark_std::cfg_into_iter!(extension_trace_polys.map_or(vec![], |t| t.0))
-                .zip(extension_column_range)
-                .map(|(mut coeffs, col_idx)| {
-                    let (xs, alphas) = execution_trace_xs_and_alphas(col_idx);
-                    divide_out_points_into(&mut coeffs, &xs, &alphas);
-                    coeffs
-                });
-
-        let quotients = Matrix::new(
-            composition_trace_quotients
-                .chain(base_trace_quotients)
-                .chain(extension_trace_quotients)
-                .collect(),
-        );
-        let mut combined_coeffs = GpuVec::try_from(quotients.sum_columns()).unwrap();
-
-        let chunk_size = 1 << 16;
-        if degree_beta.is_zero() {
-            // P(x) * alpha
-            ark_std::cfg_chunks_mut!(combined_coeffs, chunk_size).for_each(|coeff_chunk| {
-                for coeff in coeff_chunk {
-                    *coeff *= degree_alpha;
-                }
-            });
-        } else {
-            // Adjust the degree
-            // P(x) * (alpha + x * beta)
-            let mut last = A::Fq::zero();
-            for coeff in &mut combined_coeffs {
-                let tmp = *coeff;
-                *coeff *= degree_alpha;
-                *coeff += last * degree_beta;
-                last = tmp;
-            }
+        // adjust the degree
+        // P(x) * (alpha + x * beta)
+        #[cfg(feature = "op-count")]
+        {
+            let n = xs.len() as u64;
+            crate::op_count::record(crate::op_count::OpCounts::new(2 * n, n, 0));
         }
+        ark_std::cfg_iter_mut!(evals)
+            .zip(ark_std::cfg_iter!(xs))
+            .for_each(|(eval, &x)| *eval *= degree_alpha + degree_beta * x);
 
-        Matrix::new(vec![combined_coeffs])
+        evals
     }
 }
 
+/// Evaluates a polynomial over the LDE domain in bit-reversed order
+fn bit_reversed_lde<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    coeffs: GpuVec<F>,
+    lde_domain: Radix2EvaluationDomain<F::FftField>,
+    out_of_order_ffts: bool,
+) -> GpuVec<F>
+where
+    F::FftField: FftField,
+{
+    let polys = Matrix::new(vec![coeffs]);
+    let mut lde = if out_of_order_ffts {
+        polys.into_out_of_order_evaluations(lde_domain)
+    } else {
+        polys.into_bit_reversed_evaluations(lde_domain)
+    };
+    lde.0.pop().unwrap()
+}
+
+/// Adds `alpha * (P(x) - P(z)) / (x - z)` to `evals` for each quotient given
+/// as `(1 / (x - z), P(z), alpha)` where `lde` holds the evaluations of `P`
+fn accumulate_quotients<Fp: Field, Fq: Field + From<Fp>>(
+    evals: &mut [Fq],
+    lde: &[Fp],
+    quotients: &[(&[Fq], Fq, Fq)],
+) {
+    #[cfg(feature = "op-count")]
+    {
+        let n = (evals.len() * quotients.len()) as u64;
+        crate::op_count::record(crate::op_count::OpCounts::new(2 * n, 2 * n, 0));
+    }
+    ark_std::cfg_iter_mut!(evals)
+        .enumerate()
+        .for_each(|(i, eval)| {
+            let value = Fq::from(lde[i]);
+            for &(divisor_inv, ood_eval, alpha) in quotients {
+                *eval += alpha * (value - ood_eval) * divisor_inv[i];
+            }
+        });
+}
+
 pub struct DeepCompositionCoeffs<F> {
     /// Execution trace poly coefficients
     pub execution_trace: Vec<F>,
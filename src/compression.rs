@@ -0,0 +1,233 @@
+//! Compressed proof encoding with a self-describing header.
+//!
+//! FRI layer digests and merkle paths of a serialized proof repeat a lot and
+//! general purpose compressors (zstd, brotli etc.) shrink them considerably.
+//! Encoded proofs start with a header that names the codec so verifiers can
+//! decompress proofs transparently (see [`Stark::verify_bytes`]):
+//!
+//! ```text
+//! magic (4 bytes) | version (1 byte) | codec (1 byte) | decompressed length (u64 LE)
+//! ```
+//!
+//! Codecs are provided by implementing [`Compressor`]. [`Zstd`] and [`Brotli`]
+//! are behind the `compression-zstd` and `compression-brotli` features so
+//! compression libraries are only in the dependency tree of the prover and
+//! verifier when they're used.
+//!
+//! [`Stark::verify_bytes`]: crate::stark::Stark::verify_bytes
+use crate::stark::Stark;
+use crate::Proof;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use snafu::Snafu;
+
+/// Bytes every encoded proof starts with
+pub const MAGIC: [u8; 4] = *b"MSTK";
+
+/// Version of the header
+pub const VERSION: u8 = 1;
+
+const HEADER_LEN: usize = MAGIC.len() + 2 + 8;
+
+/// Identifies the compressor used to encode a proof. Identifiers from `128`
+/// onward are free for application defined codecs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Codec(pub u8);
+
+impl Codec {
+    pub const UNCOMPRESSED: Self = Self(0);
+    pub const ZSTD: Self = Self(1);
+    pub const BROTLI: Self = Self(2);
+}
+
+/// Byte level compressor for serialized proofs
+pub trait Compressor {
+    fn codec(&self) -> Codec;
+
+    fn compress(&self, bytes: &[u8]) -> Vec<u8>;
+
+    /// Decompresses `bytes`. `len` is the decompressed length given by the
+    /// header. Implementations should return an error rather than output
+    /// more than `len` bytes.
+    fn decompress(&self, bytes: &[u8], len: usize) -> Result<Vec<u8>, String>;
+}
+
+/// Stores proofs as is
+pub struct Uncompressed;
+
+impl Compressor for Uncompressed {
+    fn codec(&self) -> Codec {
+        Codec::UNCOMPRESSED
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+        bytes.to_vec()
+    }
+
+    fn decompress(&self, bytes: &[u8], _len: usize) -> Result<Vec<u8>, String> {
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Compresses proofs with [zstd](https://facebook.github.io/zstd/)
+#[cfg(feature = "compression-zstd")]
+pub struct Zstd {
+    /// Compression level from `1` to `22`
+    pub level: i32,
+}
+
+#[cfg(feature = "compression-zstd")]
+impl Default for Zstd {
+    fn default() -> Self {
+        Self {
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+}
+
+#[cfg(feature = "compression-zstd")]
+impl Compressor for Zstd {
+    fn codec(&self) -> Codec {
+        Codec::ZSTD
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+        zstd::bulk::compress(bytes, self.level).unwrap()
+    }
+
+    fn decompress(&self, bytes: &[u8], len: usize) -> Result<Vec<u8>, String> {
+        // fails if the output would be larger than `len`
+        zstd::bulk::decompress(bytes, len).map_err(|err| err.to_string())
+    }
+}
+
+/// Compresses proofs with [brotli](https://github.com/google/brotli)
+#[cfg(feature = "compression-brotli")]
+pub struct Brotli {
+    /// Compression quality from `0` to `11`
+    pub quality: u32,
+}
+
+#[cfg(feature = "compression-brotli")]
+impl Brotli {
+    // base 2 logarithm of the window size
+    const LG_WINDOW_SIZE: u32 = 22;
+    const BUFFER_SIZE: usize = 4096;
+}
+
+#[cfg(feature = "compression-brotli")]
+impl Default for Brotli {
+    fn default() -> Self {
+        Self { quality: 9 }
+    }
+}
+
+#[cfg(feature = "compression-brotli")]
+impl Compressor for Brotli {
+    fn codec(&self) -> Codec {
+        Codec::BROTLI
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut compressed = Vec::new();
+        let mut writer = brotli::CompressorWriter::new(
+            &mut compressed,
+            Self::BUFFER_SIZE,
+            self.quality,
+            Self::LG_WINDOW_SIZE,
+        );
+        writer.write_all(bytes).unwrap();
+        drop(writer);
+        compressed
+    }
+
+    fn decompress(&self, bytes: &[u8], len: usize) -> Result<Vec<u8>, String> {
+        use std::io::Read;
+        let mut decompressed = Vec::new();
+        // reads one byte past `len` to detect proofs that are too long
+        brotli::Decompressor::new(bytes, Self::BUFFER_SIZE)
+            .take(len as u64 + 1)
+            .read_to_end(&mut decompressed)
+            .map_err(|err| err.to_string())?;
+        if decompressed.len() > len {
+            return Err("decompressed proof is too long".to_string());
+        }
+        Ok(decompressed)
+    }
+}
+
+/// Errors that can occur when decoding a proof
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("proof bytes don't start with a valid header"))]
+    InvalidHeader,
+    #[snafu(display("header version `{version}` is not supported"))]
+    UnsupportedVersion { version: u8 },
+    #[snafu(display("no decompressor was provided for codec `{}`", codec.0))]
+    UnknownCodec { codec: Codec },
+    #[snafu(display("failed to decompress proof: {message}"))]
+    Decompression { message: String },
+    #[snafu(display(
+        "decompressed proof has `{actual}` bytes but the header specifies `{expected}`"
+    ))]
+    LengthMismatch { expected: usize, actual: usize },
+    #[snafu(display("failed to deserialize proof: {message}"))]
+    Deserialization { message: String },
+}
+
+/// Serializes `proof` and compresses it with `compressor`
+pub fn encode_proof<S: Stark>(proof: &Proof<S>, compressor: &dyn Compressor) -> Vec<u8> {
+    let mut proof_bytes = Vec::new();
+    proof.serialize_compressed(&mut proof_bytes).unwrap();
+    let mut bytes = Vec::with_capacity(HEADER_LEN + proof_bytes.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.push(VERSION);
+    bytes.push(compressor.codec().0);
+    bytes.extend_from_slice(&(proof_bytes.len() as u64).to_le_bytes());
+    bytes.extend(compressor.compress(&proof_bytes));
+    bytes
+}
+
+/// Decodes a proof encoded by [`encode_proof`]. Uncompressed proofs are
+/// always supported. Other codecs must be in `decompressors`.
+pub fn decode_proof<S: Stark>(
+    bytes: &[u8],
+    decompressors: &[&dyn Compressor],
+) -> Result<Proof<S>, Error> {
+    if bytes.len() < HEADER_LEN || bytes[0..MAGIC.len()] != MAGIC {
+        return Err(Error::InvalidHeader);
+    }
+    let (header, payload) = bytes.split_at(HEADER_LEN);
+    let version = header[MAGIC.len()];
+    if version != VERSION {
+        return Err(Error::UnsupportedVersion { version });
+    }
+    let codec = Codec(header[MAGIC.len() + 1]);
+    let len = u64::from_le_bytes(header[MAGIC.len() + 2..].try_into().unwrap());
+    let len = usize::try_from(len).map_err(|_| Error::InvalidHeader)?;
+
+    let proof_bytes = if codec == Codec::UNCOMPRESSED {
+        payload.to_vec()
+    } else {
+        let decompressor = decompressors
+            .iter()
+            .find(|d| d.codec() == codec)
+            .ok_or(Error::UnknownCodec { codec })?;
+        decompressor
+            .decompress(payload, len)
+            .map_err(|message| Error::Decompression { message })?
+    };
+    if proof_bytes.len() != len {
+        return Err(Error::LengthMismatch {
+            expected: len,
+            actual: proof_bytes.len(),
+        });
+    }
+    Proof::deserialize_compressed(&*proof_bytes).map_err(|err| Error::Deserialization {
+        message: err.to_string(),
+    })
+}
@@ -1,8 +1,17 @@
 use crate::expression::Expr;
 use crate::utils;
+use crate::utils::FieldVariant;
+use crate::StarkExtensionOf;
 use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ark_ff::FftField;
 use ark_ff::One;
 use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::fmt::Debug;
+use core::hash::Hash;
 use core::iter::Product;
 use core::iter::Sum;
 use core::ops::Add;
@@ -12,9 +21,8 @@ use core::ops::Div;
 use core::ops::Mul;
 use core::ops::Neg;
 use core::ops::Sub;
+use ministark_gpu::GpuFftField;
 use num_traits::Pow;
-use std::fmt::Debug;
-use std::hash::Hash;
 
 // TODO: should really remove copy as this type might change in the future
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
@@ -142,18 +150,38 @@ impl<'a, T> PeriodicColumn<'a, T> {
 }
 
 #[derive(Clone)]
-pub struct Constraint<T: 'static>(Expr<AlgebraicItem<T>>);
+pub struct Constraint<T: 'static> {
+    expression: Expr<AlgebraicItem<T>>,
+    name: Option<String>,
+}
 
 impl<T> Constraint<T> {
     pub const fn new(expression: Expr<AlgebraicItem<T>>) -> Self {
-        Self(expression)
+        Self {
+            expression,
+            name: None,
+        }
+    }
+
+    /// Creates a constraint with a name that's used when reporting failures.
+    /// Names are only used for debugging and aren't part of the composition
+    /// constraint or the transcript.
+    pub fn named(name: impl Into<String>, expression: Expr<AlgebraicItem<T>>) -> Self {
+        Self {
+            expression,
+            name: Some(name.into()),
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
     }
 
     /// Calculates an upper bound on the degree in X.
     /// Output is of the form `(numerator_degree, denominator_degree)`
     pub fn degree(&self, trace_degree: usize) -> (usize, usize) {
         let Degree(numerator_degree, denominator_degree) =
-            self.0.eval(&mut |leaf| leaf.degree(trace_degree));
+            self.expression.eval(&mut |leaf| leaf.degree(trace_degree));
         (numerator_degree, denominator_degree)
     }
 
@@ -245,7 +273,9 @@ impl<T> Constraint<T> {
             }
         }
 
-        self.0.eval(&mut |leaf| CheckedEval(Some(f(leaf)))).0
+        self.expression
+            .eval(&mut |leaf| CheckedEval(Some(f(leaf))))
+            .0
     }
 
     // Adapted from https://github.com/0xProject/OpenZKP
@@ -270,14 +300,215 @@ impl<T: 'static> Deref for Constraint<T> {
     type Target = Expr<AlgebraicItem<T>>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.expression
     }
 }
 
 impl<T> DerefMut for Constraint<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.expression
+    }
+}
+
+/// Constraints that share a divisor, i.e. that must hold over the same set of
+/// rows.
+///
+/// An AIR returns these from
+/// [`AirConfig::constraint_groups`](crate::air::AirConfig::constraint_groups)
+/// so new kinds of constraints only need a new divisor.
+#[derive(Clone)]
+pub struct ConstraintGroup<T: 'static> {
+    /// Numerators of the constraints. These must evaluate to zero on every
+    /// root of the divisor that isn't a root of the exemptions.
+    pub constraints: Vec<Expr<AlgebraicItem<T>>>,
+    pub divisor: Expr<AlgebraicItem<T>>,
+    /// Vanishes on the roots of the divisor where the constraints don't need
+    /// to hold e.g. `x - g^(n-1)` for transition constraints
+    pub exemptions: Option<Expr<AlgebraicItem<T>>>,
+    /// Names of the constraints (see [`Constraint::named`]). Constraint `i`
+    /// is named `names[i]`. Constraints without a name are unnamed.
+    pub names: Vec<String>,
+}
+
+impl<T> ConstraintGroup<T> {
+    pub const fn new(
+        constraints: Vec<Expr<AlgebraicItem<T>>>,
+        divisor: Expr<AlgebraicItem<T>>,
+    ) -> Self {
+        Self {
+            constraints,
+            divisor,
+            exemptions: None,
+            names: Vec::new(),
+        }
+    }
+
+    /// Names the constraints of the group in order
+    #[must_use]
+    pub fn with_names(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.names = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    #[must_use]
+    pub fn with_exemptions(mut self, exemptions: Expr<AlgebraicItem<T>>) -> Self {
+        self.exemptions = Some(exemptions);
+        self
+    }
+}
+
+impl<T: Clone> ConstraintGroup<T> {
+    /// Returns the constraints multiplied by `exemptions / divisor`
+    pub fn into_constraints(self) -> Vec<Constraint<T>> {
+        let Self {
+            constraints,
+            divisor,
+            exemptions,
+            names,
+        } = self;
+        let mut names = names.into_iter();
+        constraints
+            .into_iter()
+            .map(|constraint| {
+                let expression = match &exemptions {
+                    Some(exemptions) => constraint * (exemptions / &divisor),
+                    None => constraint / &divisor,
+                };
+                match names.next() {
+                    Some(name) => Constraint::named(name, expression),
+                    None => Constraint::new(expression),
+                }
+            })
+            .collect()
+    }
+}
+
+impl<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>> ConstraintGroup<FieldVariant<Fp, Fq>> {
+    /// Constraints that hold in every row. The divisor is `x^trace_len - 1`.
+    pub fn all_rows(
+        trace_len: usize,
+        constraints: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+    ) -> Self {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        Self::new(constraints, X.pow(trace_len) - one)
+    }
+
+    /// Constraints between each row and the next. These hold in every row
+    /// except the last.
+    pub fn transitions(
+        trace_len: usize,
+        constraints: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+    ) -> Self {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        let last_x = Constant(FieldVariant::Fp(last_row_x::<Fp>(trace_len)));
+        Self::new(constraints, X.pow(trace_len) - one).with_exemptions(X - last_x)
+    }
+
+    /// Constraints that hold in the first row
+    pub fn first_row(constraints: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>) -> Self {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        Self::new(constraints, X - one)
+    }
+
+    /// Constraints that hold in the last row
+    pub fn last_row(
+        trace_len: usize,
+        constraints: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+    ) -> Self {
+        use AlgebraicItem::*;
+        let last_x = Constant(FieldVariant::Fp(last_row_x::<Fp>(trace_len)));
+        Self::new(constraints, X - last_x)
+    }
+}
+
+/// Asserts a trace column holds a value in a row or in every `stride`th row.
+///
+/// An AIR returns these from
+/// [`AirConfig::assertions`](crate::air::AirConfig::assertions) and the
+/// divisor is derived from the asserted rows. Values that depend on public
+/// inputs can reference hints.
+#[derive(Clone)]
+pub struct Assertion<T: 'static> {
+    pub column: usize,
+    /// First row the value is asserted in
+    pub row: usize,
+    /// The value is asserted every `stride` rows starting at `row`. `None`
+    /// asserts the value in `row` only.
+    pub stride: Option<usize>,
+    pub value: Expr<AlgebraicItem<T>>,
+}
+
+impl<T> Assertion<T> {
+    /// Asserts `column` holds `value` in `row`
+    pub fn single(column: usize, row: usize, value: impl Into<Expr<AlgebraicItem<T>>>) -> Self {
+        Self {
+            column,
+            row,
+            stride: None,
+            value: value.into(),
+        }
     }
+
+    /// Asserts `column` holds `value` in rows `first_row + k * stride`
+    ///
+    /// # Panics
+    /// Panics if `stride` isn't a power of two or `first_row >= stride`
+    pub fn periodic(
+        column: usize,
+        first_row: usize,
+        stride: usize,
+        value: impl Into<Expr<AlgebraicItem<T>>>,
+    ) -> Self {
+        assert!(stride.is_power_of_two(), "stride must be a power of two");
+        assert!(first_row < stride, "first row must be less than the stride");
+        Self {
+            column,
+            row: first_row,
+            stride: Some(stride),
+            value: value.into(),
+        }
+    }
+}
+
+impl<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>> Assertion<FieldVariant<Fp, Fq>> {
+    /// Returns the assertion as a constraint divided by the vanishing
+    /// polynomial of the asserted rows
+    ///
+    /// # Panics
+    /// Panics if an asserted row is outside the trace
+    pub fn into_constraint(self, trace_len: usize) -> Constraint<FieldVariant<Fp, Fq>> {
+        use AlgebraicItem::*;
+        let Self {
+            column,
+            row,
+            stride,
+            value,
+        } = self;
+        let stride = stride.unwrap_or(trace_len);
+        assert!(stride <= trace_len, "stride exceeds the trace length");
+        assert!(row < trace_len, "row {row} is outside the trace");
+        // the asserted rows `x` satisfy `x^num_rows = g^(row * num_rows)`
+        let num_rows = trace_len / stride;
+        let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let root = Constant(FieldVariant::Fp(trace_domain.element(row * num_rows)));
+        let divisor = X.pow(num_rows) - root;
+        let name = if num_rows == 1 {
+            format!("assertion on column {column} in row {row}")
+        } else {
+            format!("assertion on column {column} every {stride} rows from row {row}")
+        };
+        Constraint::named(name, (column.curr() - value) / divisor)
+    }
+}
+
+/// Returns the element of the trace domain for the last row
+fn last_row_x<Fp: FftField>(trace_len: usize) -> Fp {
+    Radix2EvaluationDomain::<Fp>::new(trace_len)
+        .unwrap()
+        .element(trace_len - 1)
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
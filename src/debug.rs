@@ -1,128 +1,456 @@
 //! Tools for debugging issues that may arrive with AIR or STARK
 //! TODO:
 
+use crate::air::all_constraints;
+use crate::air::trace_domain;
+use crate::air::AirConfig;
+use crate::challenges::ChallengeAccesses;
 use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::expression::Expr;
+use crate::expression::P;
 use crate::hints::Hints;
+use crate::prover::ProvingError;
 use crate::stark::Stark;
+use crate::trace::CellType;
+use crate::utils::horner_evaluate;
+use crate::utils::FieldVariant;
+use crate::verifier::ood_constraint_evaluation;
+use crate::Air;
 use crate::Matrix;
+use crate::ProofOptions;
+use crate::SegmentedMatrix;
+use crate::StarkExtensionOf;
+use crate::Trace;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use ark_ff::BigInteger;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_ff::UniformRand;
+use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use core::fmt;
+use core::fmt::Display;
+use ministark_gpu::GpuFftField;
+use num_traits::Pow;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use snafu::Snafu;
 
-/// Checks AIR constraints are valid
-pub const fn default_validate_constraints<S: Stark>(
+/// Problem found by [`audit_challenge_accesses`]
+#[derive(Debug, Snafu)]
+pub enum ChallengeAuditError {
+    #[snafu(display("extension trace accesses challenges conditionally on their values"))]
+    ConditionalAccess {
+        lhs: ChallengeAccesses,
+        rhs: ChallengeAccesses,
+    },
+    #[snafu(display("challenge `{index}` was first accessed after challenge `{previous}`"))]
+    OutOfOrderAccess { index: usize, previous: usize },
+    #[snafu(display("hints access challenges differently for the prover and verifier"))]
+    HintsMismatch {
+        prover: ChallengeAccesses,
+        verifier: ChallengeAccesses,
+    },
+}
+
+/// Checks an AIR consumes challenges deterministically.
+///
+/// The extension trace is built twice with different random challenges and
+/// the challenges each build accesses must match. Challenges must also be
+/// first accessed in the order they are drawn. Hints are generated as the
+/// prover does (after building the extension trace) and as the verifier does
+/// and must access the same challenges. Returns the challenges accessed when
+/// building the extension trace.
+///
+/// Only accesses made by indexing [`Challenges`] are recorded. Extension
+/// traces built in parallel may access challenges in a different order
+/// between runs.
+pub fn audit_challenge_accesses<S: Stark>(
+    this: &S,
+    witness: S::Witness,
+    options: ProofOptions,
+    seed: u64,
+) -> Result<ChallengeAccesses, ChallengeAuditError> {
+    let trace = this.generate_trace(witness);
+    let air = Air::<S::AirConfig>::new(trace.len(), this.get_public_inputs(), options);
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    let mut draw_challenges = || {
+        let values = (0..air.num_challenges()).map(|_| S::Fq::rand(&mut rng));
+        Challenges::new_recorded(values.collect::<Vec<_>>())
+    };
+
+    let challenges = draw_challenges();
+    trace.build_extension_columns(&challenges);
+    let accesses = challenges.take_accesses().unwrap();
+    air.gen_hints(&challenges);
+    let prover_hint_accesses = challenges.take_accesses().unwrap();
+
+    let other_challenges = draw_challenges();
+    trace.build_extension_columns(&other_challenges);
+    let other_accesses = other_challenges.take_accesses().unwrap();
+    if accesses != other_accesses {
+        return Err(ChallengeAuditError::ConditionalAccess {
+            lhs: accesses,
+            rhs: other_accesses,
+        });
+    }
+
+    if let Some(&[previous, index]) = accesses
+        .first_access_order
+        .array_windows()
+        .find(|[previous, index]| index < previous)
+    {
+        return Err(ChallengeAuditError::OutOfOrderAccess { index, previous });
+    }
+
+    let verifier_challenges = Challenges::new_recorded(challenges.to_vec());
+    air.gen_hints(&verifier_challenges);
+    let verifier_hint_accesses = verifier_challenges.take_accesses().unwrap();
+    if prover_hint_accesses != verifier_hint_accesses {
+        return Err(ChallengeAuditError::HintsMismatch {
+            prover: prover_hint_accesses,
+            verifier: verifier_hint_accesses,
+        });
+    }
+
+    Ok(accesses)
+}
+
+/// Checks the execution trace satisfies every AIR constraint at every row.
+///
+/// Returns [`ProvingError::UnsatisfiedConstraint`] for the first constraint
+/// (by index) that doesn't evaluate to zero at a row or
+/// [`ProvingError::UnsatisfiedNamedConstraint`] if the constraint is named.
+/// Either error explains the value of every sub-expression of the failing
+/// constraint at that row (see [`explain_constraint_at`]).
+#[allow(clippy::cast_possible_wrap)]
+pub fn default_validate_constraints<S: Stark>(
     _this: &S,
-    _challenges: &Challenges<S::Fq>,
-    _hints: &Hints<S::Fq>,
-    _base_trace: &Matrix<S::Fp>,
-    _extension_trace: Option<&Matrix<S::Fq>>,
-) {
-    // ```text
-    // TODO: move constraint checking from air.rs into here
-    // #[cfg(all(feature = "std", debug_assertions))]
-    // fn validate_constraints(
-    //     &self,
-    //     challenges: &Challenges<C::Fq>,
-    //     hints: &Hints<C::Fq>,
-    //     base_trace: &crate::Matrix<C::Fp>,
-    //     extension_trace: Option<&crate::Matrix<C::Fq>>,
-    // ) {
-    //     use AlgebraicItem::*;
-    //     use Expr::*;
-
-    //     let num_execution_trace_columns = C::NUM_BASE_COLUMNS +
-    // C::NUM_EXTENSION_COLUMNS;     let mut col_indicies = vec![false;
-    // num_execution_trace_columns];     let mut challenge_indicies =
-    // vec![false; challenges.len()];     let mut hint_indicies =
-    // vec![false; hints.len()];
-
-    //     for constraint in &self.constraints {
-    //         constraint.traverse(&mut |node| match node {
-    //             Leaf(Challenge(i)) => challenge_indicies[*i] = true,
-    //             Leaf(Trace(i, _)) => col_indicies[*i] = true,
-    //             Leaf(Hint(i)) => hint_indicies[*i] = true,
-    //             _ => {}
-    //         })
-    //     }
-
-    //     for (index, exists) in col_indicies.into_iter().enumerate() {
-    //         if !exists {
-    //             // TODO: make assertion
-    //             println!("WARN: no constraints for execution trace column
-    // {index}");         }
-    //     }
-
-    //     for (index, exists) in challenge_indicies.into_iter().enumerate()
-    // {         if !exists {
-    //             // TODO: make assertion
-    //             println!("WARN: challenge at index {index} never used");
-    //         }
-    //     }
-
-    //     for (index, exists) in hint_indicies.into_iter().enumerate() {
-    //         if !exists {
-    //             // TODO: make assertion
-    //             println!("WARN: hint at index {index} never used");
-    //         }
-    //     }
-
-    //     let trace_domain = self.trace_domain();
-    //     let base_column_range = Self::base_column_range();
-    //     let extension_column_range = Self::extension_column_range();
-
-    //     // helper function to get a value from the execution trace
-    //     let get_trace_value = |row: usize, col: usize, offset: isize| {
-    //         let pos = (row as isize +
-    // offset).rem_euclid(trace_domain.size() as isize) as usize;
-    // if base_column_range.contains(&col) {
-    // FieldVariant::Fp(base_trace.0[col][pos])         } else if
-    // extension_column_range.contains(&col) {             let col =
-    // col - C::NUM_BASE_COLUMNS;
-    // FieldVariant::Fq(extension_trace.unwrap().0[col][pos])
-    //         } else {
-    //             unreachable!("requested column {col} does not exist")
-    //         }
-    //     };
-
-    //     for (c_idx, constraint) in
-    // self.constraints().into_iter().enumerate() {         for
-    // (row, x) in trace_domain.elements().enumerate() {
-    // let is_valid = constraint                 .check(&mut |leaf|
-    // match leaf {                     X => FieldVariant::Fp(x),
-    //                     &Hint(i) => FieldVariant::Fq(hints[i]),
-    //                     &Challenge(i) => FieldVariant::Fq(challenges[i]),
-    //                     &Trace(col, offset) => get_trace_value(row, col,
-    // offset),                     &Constant(c) => c,
-    //                 })
-    //                 .is_some();
-
-    //             if !is_valid {
-    //                 let mut vals = vec![format!("x = {x}")];
-    //                 constraint.traverse(&mut |node| match *node {
-    //                     // get a description of each leaf node
-    //                     Leaf(Trace(col, offset)) => vals.push(format!(
-    //                         "Trace(col={col:0>3}, offset={offset:0>3}) =
-    // {}",                         get_trace_value(row, col,
-    // offset)                     )),
-    //                     Leaf(Challenge(i)) => {
-    //                         vals.push(format!("Challenge({i}) = {}",
-    // challenges[i]))                     }
-    //                     Leaf(Hint(i)) => vals.push(format!("Hint({i}) =
-    // {}", hints[i])),                     // skip tree nodes
-    //                     _ => (),
-    //                 });
-
-    //                 vals.sort();
-    //                 vals.dedup();
-
-    //                 // TODO: display constraint? eprintln!("Constraint
-    // is:\n{constraint}\n");                 #[cfg(feature = "std")]
-    //                 eprint!("Constraint {c_idx} does not evaluate to a
-    // low degree polynomial. ");                 #[cfg(feature =
-    // "std")]                 eprintln!("Divide by zero occurs at
-    // row {row}.\n");                 #[cfg(feature = "std")]
-    //                 eprintln!("Expression values:\n{}", vals.join("\n"));
-    //                 panic!();
-    //             }
-    //         }
-    //     }
-    // }
-    // ```
+    challenges: &Challenges<S::Fq>,
+    hints: &Hints<S::Fq>,
+    base_trace: &Matrix<S::Fp>,
+    extension_trace: Option<&Matrix<S::Fq>>,
+) -> Result<(), ProvingError> {
+    let trace_len = base_trace.num_rows();
+    let trace_domain = trace_domain::<S::AirConfig>(trace_len);
+    let num_base_columns = S::AirConfig::NUM_BASE_COLUMNS;
+    let constraints = all_constraints::<S::AirConfig>(trace_len);
+    for (constraint_index, constraint) in constraints.iter().enumerate() {
+        for (row, x) in trace_domain.elements().enumerate() {
+            let trace = |col, offset: isize| {
+                let pos = (row as isize + offset).rem_euclid(trace_len as isize) as usize;
+                if col < num_base_columns {
+                    FieldVariant::Fp(base_trace[col][pos])
+                } else {
+                    let extension_trace = extension_trace.expect("no extension trace");
+                    FieldVariant::Fq(extension_trace[col - num_base_columns][pos])
+                }
+            };
+            let evaluation =
+                check_constraint_at(constraint, trace_len, x, challenges, hints, trace);
+            if evaluation.is_none() {
+                let explanation =
+                    explain_constraint_at(constraint, trace_len, x, challenges, hints, trace)
+                        .to_string();
+                return Err(match constraint.name() {
+                    Some(name) => ProvingError::UnsatisfiedNamedConstraint {
+                        name: name.into(),
+                        constraint: constraint_index,
+                        row,
+                        explanation,
+                    },
+                    None => ProvingError::UnsatisfiedConstraint {
+                        constraint: constraint_index,
+                        row,
+                        explanation,
+                    },
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the composition polynomial interpolated from its evaluations over
+/// the constraint evaluation coset agrees with the composition constraint at a
+/// random point outside the coset.
+///
+/// Quotients are computed by pointwise division on the coset. If a divisor
+/// doesn't divide its constraint the interpolated polynomial is not the
+/// quotient and the two won't agree. Returns
+/// [`ProvingError::InconsistentCosetEvaluations`] in that case.
+pub fn check_coset_consistency<A: AirConfig>(
+    air: &Air<A>,
+    composition_poly: &[A::Fq],
+    composition_coeffs: &[A::Fq],
+    challenges: &Challenges<A::Fq>,
+    hints: &Hints<A::Fq>,
+    base_trace_polys: &SegmentedMatrix<A::Fp>,
+    extension_trace_polys: Option<&Matrix<A::Fq>>,
+) -> Result<(), ProvingError> {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let x = A::Fq::rand(&mut rng);
+    let trace_domain = air.trace_domain();
+    let trace_ood_eval_map = air
+        .trace_arguments()
+        .into_iter()
+        .map(|(col, offset)| {
+            let generator = if offset >= 0 {
+                trace_domain.group_gen()
+            } else {
+                trace_domain.group_gen_inv()
+            };
+            let point = x * generator.pow([offset.unsigned_abs() as u64]);
+            let eval = if col < A::NUM_BASE_COLUMNS {
+                horner_evaluate(&base_trace_polys[col], &point)
+            } else {
+                let extension_trace_polys = extension_trace_polys.expect("no extension trace");
+                horner_evaluate(&extension_trace_polys[col - A::NUM_BASE_COLUMNS], &point)
+            };
+            ((col, offset), eval)
+        })
+        .collect::<BTreeMap<(usize, isize), A::Fq>>();
+    let expected = ood_constraint_evaluation(
+        composition_coeffs,
+        challenges,
+        hints,
+        &trace_ood_eval_map,
+        air,
+        x,
+    );
+    if horner_evaluate(composition_poly, &x) != expected {
+        return Err(ProvingError::InconsistentCosetEvaluations);
+    }
+    Ok(())
+}
+
+/// Checks every value of the base trace is a canonical field element and that
+/// declared columns hold values within the range of their
+/// [`AirConfig::BASE_COLUMN_TYPES`].
+///
+/// Non-canonical values are usually written by unsafe or GPU code that skips
+/// a modular reduction. Constraint checks and interpolation don't reliably
+/// catch these so the prover would otherwise generate a proof that fails
+/// verification. Returns an error for the first invalid value by column and
+/// then by row.
+pub fn sanitize_base_trace<A: AirConfig>(base_trace: &Matrix<A::Fp>) -> Result<(), ProvingError> {
+    for (column, values) in base_trace.iter().enumerate() {
+        let cell_type = A::BASE_COLUMN_TYPES.get(column).copied();
+        let num_bits = cell_type.and_then(CellType::num_bits);
+        for (row, value) in values.iter().enumerate() {
+            if !is_canonical(value) {
+                return Err(ProvingError::NonCanonicalValue { column, row });
+            }
+            if num_bits.is_some_and(|num_bits| !fits_in_bits(value, num_bits)) {
+                return Err(ProvingError::ValueOutOfRange {
+                    column,
+                    row,
+                    cell_type: cell_type.unwrap(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the base column polynomials are within the degree bounds of
+/// [`AirConfig::BASE_COLUMN_DEGREE_HINTS`]. A column that exceeds its bound
+/// would be extended incorrectly and the proof would fail verification.
+pub fn check_degree_hints<A: AirConfig>(
+    base_trace_polys: &Matrix<A::Fp>,
+) -> Result<(), ProvingError> {
+    for &(column, degree) in A::BASE_COLUMN_DEGREE_HINTS {
+        let Some(coeffs) = base_trace_polys.0.get(column) else {
+            continue;
+        };
+        if coeffs.iter().skip(degree + 1).any(|coeff| !coeff.is_zero()) {
+            return Err(ProvingError::DegreeHintExceeded { column, degree });
+        }
+    }
+    Ok(())
+}
+
+/// Checks every value of the extension trace is a canonical field element.
+/// Columns are numbered after the base columns.
+pub fn sanitize_extension_trace<A: AirConfig>(
+    extension_trace: &Matrix<A::Fq>,
+) -> Result<(), ProvingError> {
+    for (i, values) in extension_trace.iter().enumerate() {
+        if let Some(row) = values.iter().position(|v| !is_canonical(v)) {
+            let column = A::NUM_BASE_COLUMNS + i;
+            return Err(ProvingError::NonCanonicalValue { column, row });
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if the internal representation of each base field element of
+/// `value` is the one obtained by reducing its integer value
+fn is_canonical<F: Field>(value: &F) -> bool {
+    value
+        .to_base_prime_field_elements()
+        .all(|v| F::BasePrimeField::from_bigint(v.into_bigint()) == Some(v))
+}
+
+/// Returns true if `value` is an integer less than `2^num_bits`
+fn fits_in_bits<F: Field>(value: &F, num_bits: u32) -> bool {
+    let mut elements = value.to_base_prime_field_elements();
+    let first = elements.next().unwrap();
+    first.into_bigint().num_bits() <= num_bits && elements.all(|v| v.is_zero())
+}
+
+/// Evaluates a constraint at `x` (a point of the trace domain) with
+/// [`Constraint::check`]. `trace` returns the value of a column at an offset
+/// from the row of `x`. Returns `None` if the constraint isn't satisfied.
+pub(crate) fn check_constraint_at<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    constraint: &Constraint<FieldVariant<Fp, Fq>>,
+    trace_len: usize,
+    x: Fp,
+    challenges: &Challenges<Fq>,
+    hints: &Hints<Fq>,
+    mut trace: impl FnMut(usize, isize) -> FieldVariant<Fp, Fq>,
+) -> Option<FieldVariant<Fp, Fq>> {
+    constraint.check(&mut |leaf| evaluate_leaf(leaf, trace_len, x, challenges, hints, &mut trace))
+}
+
+fn evaluate_leaf<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    leaf: &AlgebraicItem<FieldVariant<Fp, Fq>>,
+    trace_len: usize,
+    x: Fp,
+    challenges: &Challenges<Fq>,
+    hints: &Hints<Fq>,
+    trace: &mut impl FnMut(usize, isize) -> FieldVariant<Fp, Fq>,
+) -> FieldVariant<Fp, Fq> {
+    use AlgebraicItem::*;
+    match leaf {
+        X => FieldVariant::Fp(x),
+        &Constant(v) => v,
+        &Challenge(i) => FieldVariant::Fq(challenges[i]),
+        &Hint(i) => FieldVariant::Fq(hints[i]),
+        Periodic(col) => {
+            let point = x.pow([(trace_len / col.interval_size()) as u64]);
+            let coeffs = col
+                .coeffs()
+                .iter()
+                .map(FieldVariant::as_fq)
+                .collect::<Vec<_>>();
+            FieldVariant::Fq(horner_evaluate(&coeffs, &Fq::from(point)))
+        }
+        &Trace(col, offset) => trace(col, offset),
+    }
+}
+
+/// Value of a node in the expression tree of a constraint (see
+/// [`explain_constraint_at`])
+#[derive(Clone, Debug)]
+pub struct SubExpression<Fp, Fq> {
+    /// Depth of the node. The root of the tree has depth `0`.
+    pub depth: usize,
+    /// Operation at the node (e.g. `*`) or the leaf (e.g. `trace(2, 1)`)
+    pub label: String,
+    /// Value of the sub-expression. `None` if it divides by zero.
+    pub value: Option<FieldVariant<Fp, Fq>>,
+}
+
+/// Sub-expressions of a constraint evaluated at a single row in pre-order.
+///
+/// Displays as an indented tree with one sub-expression per line.
+#[derive(Clone, Debug)]
+pub struct ConstraintExplanation<Fp, Fq>(pub Vec<SubExpression<Fp, Fq>>);
+
+impl<Fp: Display + Zero, Fq: Display + Zero> Display for ConstraintExplanation<Fp, Fq> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for SubExpression {
+            depth,
+            label,
+            value,
+        } in &self.0
+        {
+            let indent = depth * 2;
+            match value {
+                // arkworks displays zero as an empty string
+                Some(FieldVariant::Fp(v)) if v.is_zero() => {
+                    writeln!(f, "{:indent$}{label} = 0", "")
+                }
+                Some(FieldVariant::Fq(v)) if v.is_zero() => {
+                    writeln!(f, "{:indent$}{label} = 0", "")
+                }
+                Some(value) => writeln!(f, "{:indent$}{label} = {value}", ""),
+                None => writeln!(f, "{:indent$}{label} = undefined (division by zero)", ""),
+            }?;
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates every sub-expression of a constraint at `x` (a point of the trace
+/// domain).
+///
+/// `trace` returns the value of a column at an offset from the row of
+/// `x`. Use this to find which part of a constraint is wrong once
+/// [`default_validate_constraints`] reports the row it fails at.
+pub fn explain_constraint_at<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    constraint: &Constraint<FieldVariant<Fp, Fq>>,
+    trace_len: usize,
+    x: Fp,
+    challenges: &Challenges<Fq>,
+    hints: &Hints<Fq>,
+    mut trace: impl FnMut(usize, isize) -> FieldVariant<Fp, Fq>,
+) -> ConstraintExplanation<Fp, Fq> {
+    fn explain<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+        depth: usize,
+        leaf_value: &mut impl FnMut(&AlgebraicItem<FieldVariant<Fp, Fq>>) -> FieldVariant<Fp, Fq>,
+        nodes: &mut Vec<SubExpression<Fp, Fq>>,
+    ) -> Option<FieldVariant<Fp, Fq>> {
+        use AlgebraicItem::*;
+        let index = nodes.len();
+        let label = match expr {
+            Expr::Leaf(X) => "x".into(),
+            Expr::Leaf(Constant(_)) => "constant".into(),
+            Expr::Leaf(Challenge(i)) => format!("challenge({i})"),
+            Expr::Leaf(Hint(i)) => format!("hint({i})"),
+            Expr::Leaf(Periodic(_)) => "periodic".into(),
+            Expr::Leaf(Trace(col, offset)) => format!("trace({col}, {offset})"),
+            Expr::Neg(_) => "-".into(),
+            Expr::Add(_, _) => "+".into(),
+            Expr::Mul(_, _) => "*".into(),
+            Expr::Div(_, _) => "/".into(),
+            Expr::Pow(_, e) => format!("^{e}"),
+        };
+        nodes.push(SubExpression {
+            depth,
+            label,
+            value: None,
+        });
+        let mut child = |node: &P<Expr<_>>| explain(&node.read(), depth + 1, leaf_value, nodes);
+        let value = match expr {
+            Expr::Leaf(leaf) => Some(leaf_value(leaf)),
+            Expr::Neg(a) => child(a).map(|a| -a),
+            Expr::Add(a, b) => child(a).zip(child(b)).map(|(a, b)| a + b),
+            Expr::Mul(a, b) => child(a).zip(child(b)).map(|(a, b)| a * b),
+            Expr::Div(a, b) => child(a)
+                .zip(child(b))
+                .and_then(|(a, b)| (!b.is_zero()).then(|| a / b)),
+            Expr::Pow(a, e) => child(a).map(|a| a.pow(*e)),
+        };
+        nodes[index].value = value;
+        value
+    }
+
+    let mut nodes = Vec::new();
+    explain(
+        constraint,
+        0,
+        &mut |leaf| evaluate_leaf(leaf, trace_len, x, challenges, hints, &mut trace),
+        &mut nodes,
+    );
+    ConstraintExplanation(nodes)
 }
@@ -0,0 +1,75 @@
+//! Domains that execution trace columns are interpolated over and low degree
+//! extended to. The domain of an AIR is chosen with [`AirConfig::Domain`].
+//!
+//! [`AirConfig::Domain`]: crate::air::AirConfig::Domain
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+
+/// Kinds of [`TraceDomain`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DomainKind {
+    /// Multiplicative subgroups of a two-adic field and their cosets (see
+    /// [`Radix2EvaluationDomain`])
+    Radix2,
+    /// Cosets of the circle group `x^2 + y^2 = 1` (see
+    /// [`CircleDomain`](crate::circle::CircleDomain))
+    Circle,
+}
+
+/// A domain of size `2^k` with an FFT
+pub trait TraceDomain<F: FftField>: Copy + Send + Sync + Sized {
+    const KIND: DomainKind;
+
+    /// Returns a trace domain of the given size. Returns `None` if the size
+    /// isn't a power of two or is too large for the field.
+    fn trace_domain(size: usize) -> Option<Self>;
+
+    fn domain_size(&self) -> usize;
+
+    /// Returns a domain `blowup_factor` times larger than and disjoint from
+    /// `self`. Radix-2 domains use the coset with the given offset.
+    fn lde_domain(&self, blowup_factor: usize, offset: F) -> Option<Self>;
+
+    /// Returns the coefficients of the polynomial that interpolates `evals`
+    /// over the domain. Coefficients are in the domain's FFT basis.
+    fn interpolate(&self, evals: &[F]) -> Vec<F>;
+
+    /// Evaluates the polynomial with coefficients `coeffs` over the domain.
+    /// Coefficients are padded with zeros to the size of the domain.
+    fn evaluate(&self, coeffs: &[F]) -> Vec<F>;
+}
+
+impl<F: FftField> TraceDomain<F> for Radix2EvaluationDomain<F> {
+    const KIND: DomainKind = DomainKind::Radix2;
+
+    fn trace_domain(size: usize) -> Option<Self> {
+        if size.is_power_of_two() {
+            Self::new(size)
+        } else {
+            None
+        }
+    }
+
+    fn domain_size(&self) -> usize {
+        self.size()
+    }
+
+    fn lde_domain(&self, blowup_factor: usize, offset: F) -> Option<Self> {
+        let size = self.size().checked_mul(blowup_factor)?;
+        if size.is_power_of_two() {
+            Self::new_coset(size, offset)
+        } else {
+            None
+        }
+    }
+
+    fn interpolate(&self, evals: &[F]) -> Vec<F> {
+        self.ifft(evals)
+    }
+
+    fn evaluate(&self, coeffs: &[F]) -> Vec<F> {
+        self.fft(coeffs)
+    }
+}
@@ -0,0 +1,275 @@
+//! Checks proofs generated on different backends are identical.
+//!
+//! Proving is deterministic so the same witness and options must produce the
+//! same proof on every backend (CPU or Metal) and every machine. When proofs
+//! differ [`compare_proofs`] finds the first prover message, in transcript
+//! order, where they diverge. Every later message depends on the transcript so
+//! the first divergence points at the prover stage that is nondeterministic or
+//! miscompiled.
+use crate::prover::ProvingError;
+use crate::stark::Stark;
+use crate::Proof;
+use crate::ProofOptions;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ark_serialize::CanonicalSerialize;
+use core::fmt::Debug;
+use core::fmt::Display;
+
+/// Section of a proof. Sections are ordered by when the prover sends them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofSection {
+    Options,
+    TraceLen,
+    BaseTraceCommitment,
+    ExtensionTraceCommitment,
+    /// Commitments to the auxiliary segments after the first
+    AuxTraceCommitments,
+    CompositionTraceCommitment,
+    ExecutionTraceOodEvals,
+    CompositionTraceOodEvals,
+    FriLayerCommitments,
+    FriRemainderCoeffs,
+    PowNonce,
+    BaseTraceValues,
+    ExtensionTraceValues,
+    CompositionTraceValues,
+    BaseTraceProof,
+    ExtensionTraceProof,
+    /// Merkle proofs of the auxiliary segments after the first
+    AuxTraceProofs,
+    CompositionTraceProof,
+    /// Queried rows of the FRI layer with the given index
+    FriLayerValues(usize),
+    /// Merkle proof of the FRI layer with the given index
+    FriLayerProof(usize),
+}
+
+/// First point where two proofs differ
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub section: ProofSection,
+    /// Index of the first differing item within the section. For merkle proofs
+    /// this is the index of the first differing byte of the serialized proof.
+    pub index: Option<usize>,
+    pub lhs: String,
+    pub rhs: String,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "proofs diverge at {:?}", self.section)?;
+        if let Some(index) = self.index {
+            write!(f, "[{index}]")?;
+        }
+        write!(f, ": {} != {}", self.lhs, self.rhs)
+    }
+}
+
+/// Returns the first section where the proofs differ.
+///
+/// Sections are compared in transcript order. Returns `None` if the proofs are
+/// identical.
+#[allow(clippy::too_many_lines)]
+pub fn compare_proofs<S: Stark>(lhs: &Proof<S>, rhs: &Proof<S>) -> Option<Divergence> {
+    use ProofSection::*;
+    let (lhs_fri, rhs_fri) = (&lhs.fri_proof, &rhs.fri_proof);
+    let (lhs_queries, rhs_queries) = (&lhs.trace_queries, &rhs.trace_queries);
+    let lhs_fri_commitments = lhs_fri.layers.iter().map(|l| &l.commitment);
+    let rhs_fri_commitments = rhs_fri.layers.iter().map(|l| &l.commitment);
+    compare_value(Options, &lhs.options, &rhs.options)
+        .or_else(|| compare_value(TraceLen, &lhs.trace_len, &rhs.trace_len))
+        .or_else(|| {
+            compare_value(
+                BaseTraceCommitment,
+                &lhs.base_trace_commitment,
+                &rhs.base_trace_commitment,
+            )
+        })
+        .or_else(|| {
+            compare_value(
+                ExtensionTraceCommitment,
+                &lhs.extension_trace_commitment,
+                &rhs.extension_trace_commitment,
+            )
+        })
+        .or_else(|| {
+            compare_items(
+                AuxTraceCommitments,
+                &lhs.aux_trace_commitments,
+                &rhs.aux_trace_commitments,
+            )
+        })
+        .or_else(|| {
+            compare_value(
+                CompositionTraceCommitment,
+                &lhs.composition_trace_commitment,
+                &rhs.composition_trace_commitment,
+            )
+        })
+        .or_else(|| {
+            compare_items(
+                ExecutionTraceOodEvals,
+                &lhs.execution_trace_ood_evals,
+                &rhs.execution_trace_ood_evals,
+            )
+        })
+        .or_else(|| {
+            compare_items(
+                CompositionTraceOodEvals,
+                &lhs.composition_trace_ood_evals,
+                &rhs.composition_trace_ood_evals,
+            )
+        })
+        .or_else(|| {
+            compare_items(
+                FriLayerCommitments,
+                &lhs_fri_commitments.collect::<Vec<_>>(),
+                &rhs_fri_commitments.collect::<Vec<_>>(),
+            )
+        })
+        .or_else(|| {
+            compare_items(
+                FriRemainderCoeffs,
+                &lhs_fri.remainder_coeffs,
+                &rhs_fri.remainder_coeffs,
+            )
+        })
+        .or_else(|| compare_value(PowNonce, &lhs.pow_nonce, &rhs.pow_nonce))
+        .or_else(|| {
+            compare_items(
+                BaseTraceValues,
+                &lhs_queries.base_trace_values,
+                &rhs_queries.base_trace_values,
+            )
+        })
+        .or_else(|| {
+            compare_items(
+                ExtensionTraceValues,
+                &lhs_queries.extension_trace_values,
+                &rhs_queries.extension_trace_values,
+            )
+        })
+        .or_else(|| {
+            compare_items(
+                CompositionTraceValues,
+                &lhs_queries.composition_trace_values,
+                &rhs_queries.composition_trace_values,
+            )
+        })
+        .or_else(|| {
+            compare_serialized(
+                BaseTraceProof,
+                &lhs_queries.base_trace_proof,
+                &rhs_queries.base_trace_proof,
+            )
+        })
+        .or_else(|| {
+            compare_serialized(
+                ExtensionTraceProof,
+                &lhs_queries.extension_trace_proof,
+                &rhs_queries.extension_trace_proof,
+            )
+        })
+        .or_else(|| {
+            compare_serialized(
+                AuxTraceProofs,
+                &lhs_queries.aux_trace_proofs,
+                &rhs_queries.aux_trace_proofs,
+            )
+        })
+        .or_else(|| {
+            compare_serialized(
+                CompositionTraceProof,
+                &lhs_queries.composition_trace_proof,
+                &rhs_queries.composition_trace_proof,
+            )
+        })
+        .or_else(|| {
+            // the number of layers was checked along with the commitments
+            lhs_fri
+                .layers
+                .iter()
+                .zip(&rhs_fri.layers)
+                .enumerate()
+                .find_map(|(i, (lhs_layer, rhs_layer))| {
+                    compare_items(
+                        FriLayerValues(i),
+                        &lhs_layer.flattenend_rows,
+                        &rhs_layer.flattenend_rows,
+                    )
+                    .or_else(|| {
+                        compare_serialized(
+                            FriLayerProof(i),
+                            &lhs_layer.merkle_proof,
+                            &rhs_layer.merkle_proof,
+                        )
+                    })
+                })
+        })
+}
+
+/// Generates a proof and compares it against a reference proof.
+///
+/// The reference should be generated with the same witness and options on
+/// another backend or machine. Returns the generated proof along with where it
+/// diverges from the reference (if anywhere).
+///
+/// # Errors
+///
+/// Returns an error if the proof can't be generated
+pub async fn check_against_reference<S: Stark>(
+    stark: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+    reference: &Proof<S>,
+) -> Result<(Proof<S>, Option<Divergence>), ProvingError> {
+    let proof = stark.prove(options, witness).await?;
+    let divergence = compare_proofs(reference, &proof);
+    Ok((proof, divergence))
+}
+
+fn compare_value<T: Debug + PartialEq>(
+    section: ProofSection,
+    lhs: &T,
+    rhs: &T,
+) -> Option<Divergence> {
+    (lhs != rhs).then(|| Divergence {
+        section,
+        index: None,
+        lhs: format!("{lhs:?}"),
+        rhs: format!("{rhs:?}"),
+    })
+}
+
+fn compare_items<T: Debug + PartialEq>(
+    section: ProofSection,
+    lhs: &[T],
+    rhs: &[T],
+) -> Option<Divergence> {
+    let describe = |item: Option<&T>| item.map_or_else(|| "<missing>".into(), |v| format!("{v:?}"));
+    (0..lhs.len().max(rhs.len())).find_map(|i| {
+        let (lhs, rhs) = (lhs.get(i), rhs.get(i));
+        (lhs != rhs).then(|| Divergence {
+            section,
+            index: Some(i),
+            lhs: describe(lhs),
+            rhs: describe(rhs),
+        })
+    })
+}
+
+/// Compares values by their serialized bytes. Used for types that don't
+/// implement [`PartialEq`] e.g. merkle proofs.
+fn compare_serialized<T: CanonicalSerialize>(
+    section: ProofSection,
+    lhs: &T,
+    rhs: &T,
+) -> Option<Divergence> {
+    let mut lhs_bytes = Vec::new();
+    let mut rhs_bytes = Vec::new();
+    lhs.serialize_compressed(&mut lhs_bytes).unwrap();
+    rhs.serialize_compressed(&mut rhs_bytes).unwrap();
+    compare_items(section, &lhs_bytes, &rhs_bytes)
+}
@@ -1,12 +1,13 @@
 use crate::constraints::AlgebraicItem;
 use crate::constraints::PeriodicColumn;
-use crate::expression::Expr;
+use crate::expression::CompiledExpr;
 use crate::utils::FieldVariant;
 use crate::utils::GpuAllocator;
 use crate::Matrix;
 use crate::StarkExtensionOf;
 use alloc::borrow::Cow;
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 use ark_ff::batch_inversion;
 use ark_ff::FftField;
@@ -27,11 +28,10 @@ use ministark_gpu::GpuField;
 use num_traits::Pow;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::collections::BTreeMap;
 
 #[allow(clippy::too_many_arguments)]
 pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>(
-    expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    expr: &CompiledExpr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
     challenges: &[Fq],
     hints: &[Fq],
     lde_step: usize,
@@ -41,6 +41,8 @@ pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>
     extension_trace_lde_cols: Option<&[&[Fq]]>,
 ) -> Matrix<Fq> {
     let n = x_lde.len();
+    #[cfg(feature = "op-count")]
+    crate::op_count::record(count_ops(expr, n));
     let mut result = Vec::with_capacity_in(n, GpuAllocator);
     result.resize(n, Fq::zero());
     match n {
@@ -78,7 +80,7 @@ fn eval_impl<
     Fq: StarkExtensionOf<Fp>,
     const CHUNK_SIZE: usize,
 >(
-    expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    expr: &CompiledExpr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
     challenges: &[Fq],
     hints: &[Fq],
     lde_step: usize,
@@ -104,7 +106,7 @@ fn eval_impl<
         .for_each(|(i, chunk)| {
             let chunk_offset = CHUNK_SIZE * i;
             let chunk_res: [Fq; CHUNK_SIZE] = expr
-                .graph_eval(&mut |leaf| match *leaf {
+                .eval(&mut |leaf| match *leaf {
                     X => EvalItem::Evals(Box::new(FieldVariant::Fp(extract_lde_chunk(
                         x_lde,
                         chunk_offset,
@@ -149,6 +151,44 @@ fn eval_impl<
         });
 }
 
+/// Counts the operations needed to evaluate `expr` at `n` points. Operations
+/// on constants (e.g. challenges) are only performed once.
+#[cfg(feature = "op-count")]
+fn count_ops<T>(expr: &CompiledExpr<AlgebraicItem<T>>, n: usize) -> crate::op_count::OpCounts {
+    use crate::expression::Instruction;
+    use crate::op_count::OpCounts;
+    use AlgebraicItem::*;
+    let n = n as u64;
+    let mut is_constant = Vec::new();
+    let mut counts = OpCounts::default();
+    for instruction in expr.instructions() {
+        let (constant, ops) = match *instruction {
+            Instruction::Leaf(Constant(_) | Challenge(_) | Hint(_)) => (true, OpCounts::default()),
+            Instruction::Leaf(_) => (false, OpCounts::default()),
+            Instruction::Neg(a) => (is_constant[a], OpCounts::new(0, 1, 0)),
+            Instruction::Add(a, b) => (is_constant[a] && is_constant[b], OpCounts::new(0, 1, 0)),
+            Instruction::Mul(a, b) => (is_constant[a] && is_constant[b], OpCounts::new(1, 0, 0)),
+            // divisions are batch inverted (3 multiplications per element) then multiplied
+            Instruction::Div(a, b) => (is_constant[a] && is_constant[b], OpCounts::new(4, 0, 0)),
+            // square and multiply
+            Instruction::Pow(a, e) => {
+                let num_muls = e
+                    .checked_ilog2()
+                    .map_or(0, |bits| bits + e.count_ones() - 1);
+                (is_constant[a], OpCounts::new(u64::from(num_muls), 0, 0))
+            }
+        };
+        let multiplicity = if constant { 1 } else { n };
+        counts += OpCounts::new(ops.mul * multiplicity, ops.add * multiplicity, 0);
+        if matches!(instruction, Instruction::Div(..)) && !constant {
+            // one inversion per chunk (see `eval`)
+            counts.inv += if n < 512 { n } else { n.div_ceil(512) };
+        }
+        is_constant.push(constant);
+    }
+    counts
+}
+
 /// Extracts a chunk of evaluations from a low-degree-extension
 #[inline]
 pub fn extract_lde_chunk<F: Field, const CHUNK_SIZE: usize>(
@@ -179,15 +219,15 @@ pub fn build_periodic_column_evals_map<
     Fp: GpuFftField<FftField = Fp> + FftField,
     Fq: StarkExtensionOf<Fp>,
 >(
-    expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    expr: &CompiledExpr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
     domain_offset: Fp,
     trace_len: usize,
     blowup_factor: usize,
     min_domain_size: usize,
 ) -> BTreeMap<PeriodicColumn<'static, FieldVariant<Fp, Fq>>, FieldVariant<Vec<Fp>, Vec<Fq>>> {
     let mut res = BTreeMap::new();
-    expr.traverse(&mut |node| {
-        if let &Expr::Leaf(AlgebraicItem::Periodic(col)) = node {
+    for leaf in expr.leaves() {
+        if let &AlgebraicItem::Periodic(col) = leaf {
             let interval_size = col.interval_size();
             let coeffs = col.coeffs();
             let is_fp = |&v| match v {
@@ -227,7 +267,7 @@ pub fn build_periodic_column_evals_map<
 
             res.insert(col, lde);
         }
-    });
+    }
     res
 }
 
@@ -246,6 +286,8 @@ where
     let domain_size = interval_size * blowup_factor;
     let domain_offset = domain_offset.pow([(trace_len / interval_size) as u64]);
     let domain = Radix2EvaluationDomain::new_coset(domain_size, domain_offset).unwrap();
+    #[cfg(feature = "op-count")]
+    crate::op_count::record_fft(domain_size, false, true);
     let mut evals = domain.fft(periodic_column.coeffs());
     let mut i = 0;
     while evals.len() < min_len {
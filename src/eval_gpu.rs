@@ -1,4 +1,4 @@
-#![cfg(feature = "gpu")]
+#![cfg(metal_gpu)]
 
 use crate::constraints::AlgebraicItem;
 use crate::expression::Expr;
@@ -54,7 +54,7 @@ pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>
 ) -> Matrix<Fq> {
     use AlgebraicItem::*;
     let library = &get_planner().library;
-    let command_queue = &get_planner().command_queue;
+    let command_queue = get_planner().command_queue();
     let device = command_queue.device();
     let step = lde_step as isize;
     let lde_size = x_lde.len();
@@ -855,7 +855,7 @@ impl<Fp: GpuField, Fq: GpuField> LdeCache<Fp, Fq> {
     }
 
     fn get_buffer(&mut self, ty: FieldType) -> Rc<FieldVariant<Lde<Fp>, Lde<Fq>>> {
-        let command_queue = &get_planner().command_queue;
+        let command_queue = get_planner().command_queue();
         let device = command_queue.device();
         // TODO: make O(1)
         self.buffers
@@ -0,0 +1,215 @@
+//! Structured instrumentation of the prover.
+//!
+//! Provers report their progress to a [`ProverEvents`] implementation rather
+//! than printing to stdout. Events give the start and end of each
+//! [`ProvingStage`], the sizes of the prover's domains, the most memory held
+//! by [`GpuAllocator`] during each stage and time spent in GPU kernels. Use
+//! [`prove_with_events`] to receive them. [`StdoutEvents`] prints events and
+//! [`NoEvents`] ignores them. [`ProvingReport`] sums them up for benchmarks
+//! and telemetry.
+//!
+//! Memory is tracked for the whole process so concurrent provers see each
+//! other's allocations.
+//!
+//! [`GpuAllocator`]: crate::utils::GpuAllocator
+//! [`prove_with_events`]: crate::prover::prove_with_events
+use crate::checkpoint::Checkpoint;
+use crate::prover::ProvingStage;
+use crate::utils::peak_gpu_memory;
+use crate::utils::reset_peak_gpu_memory;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Receives events as a proof is generated. All methods do nothing by default.
+pub trait ProverEvents: Send {
+    /// Called once the stage's preconditions are met and work on it starts
+    fn stage_started(&mut self, _stage: ProvingStage) {}
+
+    /// Called when a stage completes
+    fn stage_finished(&mut self, _report: &StageReport) {}
+
+    /// Called once the trace length is known
+    fn domains(&mut self, _domains: &DomainSizes) {}
+
+    /// Called when a phase is restored from a checkpoint instead of being
+    /// computed (see [`crate::checkpoint`])
+    fn resumed(&mut self, _checkpoint: Checkpoint) {}
+
+    /// Called with the time a GPU kernel took. Reported at the end of the
+    /// stage that ran the kernel.
+    fn gpu_kernel(&mut self, _kernel: &'static str, _elapsed: Duration) {}
+
+    /// Called with information that's neither an error nor a measurement e.g.
+    /// falling back to the CPU
+    fn note(&mut self, _message: &str) {}
+}
+
+/// Measurements of a completed proving stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageReport {
+    pub stage: ProvingStage,
+    pub elapsed: Duration,
+    /// Most bytes held by [`GpuAllocator`](crate::utils::GpuAllocator) at once
+    /// during the stage
+    pub peak_memory: usize,
+}
+
+/// Sizes of the domains and traces of a proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DomainSizes {
+    /// Number of rows in the trace
+    pub trace: usize,
+    /// Size of the low degree extension domain
+    pub lde: usize,
+    /// Size of the constraint evaluation domain
+    pub constraint_evaluation: usize,
+    pub num_base_columns: usize,
+    pub num_extension_columns: usize,
+}
+
+/// Measurements of a whole proof returned by
+/// [`prove_with_report`](crate::prover::prove_with_report). Also a
+/// [`ProverEvents`] that collects the measurements as events arrive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProvingReport {
+    /// Time taken by each stage in the order stages ran
+    pub phase_durations: Vec<(ProvingStage, Duration)>,
+    /// Most bytes held by [`GpuAllocator`](crate::utils::GpuAllocator) at once
+    pub peak_memory: usize,
+    /// Total time spent in GPU kernels
+    pub gpu_time: Duration,
+    /// Size of the compressed proof in bytes
+    pub proof_size: usize,
+}
+
+impl ProvingReport {
+    /// Returns the time taken by all stages
+    pub fn total_duration(&self) -> Duration {
+        self.phase_durations
+            .iter()
+            .map(|(_, elapsed)| *elapsed)
+            .sum()
+    }
+}
+
+impl ProverEvents for ProvingReport {
+    fn stage_finished(&mut self, report: &StageReport) {
+        self.phase_durations.push((report.stage, report.elapsed));
+        self.peak_memory = self.peak_memory.max(report.peak_memory);
+    }
+
+    fn gpu_kernel(&mut self, _kernel: &'static str, elapsed: Duration) {
+        self.gpu_time += elapsed;
+    }
+}
+
+/// Ignores all events
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoEvents;
+
+impl ProverEvents for NoEvents {}
+
+/// Prints events to stdout
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutEvents;
+
+impl ProverEvents for StdoutEvents {
+    fn stage_finished(&mut self, report: &StageReport) {
+        println!(
+            "{:?}: {:?} (peak memory {} MiB)",
+            report.stage,
+            report.elapsed,
+            report.peak_memory >> 20
+        );
+    }
+
+    fn domains(&mut self, domains: &DomainSizes) {
+        println!(
+            "Trace (base cols={}, extension cols={}, rows={}), LDE domain size {}, constraint \
+             evaluation domain size {}",
+            domains.num_base_columns,
+            domains.num_extension_columns,
+            domains.trace,
+            domains.lde,
+            domains.constraint_evaluation,
+        );
+    }
+
+    fn resumed(&mut self, checkpoint: Checkpoint) {
+        println!("Resumed from {checkpoint:?} checkpoint");
+    }
+
+    fn gpu_kernel(&mut self, kernel: &'static str, elapsed: Duration) {
+        println!("GPU {kernel}: {elapsed:?}");
+    }
+
+    fn note(&mut self, message: &str) {
+        println!("{message}");
+    }
+}
+
+std::thread_local! {
+    /// GPU kernel timings recorded on this thread since they were last taken
+    static GPU_TIMINGS: RefCell<Vec<(&'static str, Duration)>> = RefCell::new(Vec::new());
+}
+
+/// Records the time a GPU kernel took. Kernels run on the prover's thread so
+/// the timing is reported by the stage that's in progress.
+#[cfg(metal_gpu)]
+pub(crate) fn record_gpu_timing(kernel: &'static str, elapsed: Duration) {
+    GPU_TIMINGS.with(|timings| timings.borrow_mut().push((kernel, elapsed)));
+}
+
+/// Times proving stages and forwards events to a [`ProverEvents`]
+pub(crate) struct Instrumentation<'a> {
+    events: &'a mut (dyn ProverEvents + Send),
+    current: Option<(ProvingStage, Instant)>,
+}
+
+impl<'a> Instrumentation<'a> {
+    pub fn new(events: &'a mut (dyn ProverEvents + Send)) -> Self {
+        Self {
+            events,
+            current: None,
+        }
+    }
+
+    /// Finishes the current stage and starts `stage`
+    pub fn enter(&mut self, stage: ProvingStage) {
+        self.finish();
+        GPU_TIMINGS.with(|timings| timings.borrow_mut().clear());
+        reset_peak_gpu_memory();
+        self.events.stage_started(stage);
+        self.current = Some((stage, Instant::now()));
+    }
+
+    /// Finishes the current stage if there is one
+    pub fn finish(&mut self) {
+        let Some((stage, start)) = self.current.take() else {
+            return;
+        };
+        let elapsed = start.elapsed();
+        for (kernel, elapsed) in GPU_TIMINGS.with(RefCell::take) {
+            self.events.gpu_kernel(kernel, elapsed);
+        }
+        self.events.stage_finished(&StageReport {
+            stage,
+            elapsed,
+            peak_memory: peak_gpu_memory(),
+        });
+    }
+
+    pub fn domains(&mut self, domains: &DomainSizes) {
+        self.events.domains(domains);
+    }
+
+    pub fn resumed(&mut self, checkpoint: Checkpoint) {
+        self.events.resumed(checkpoint);
+    }
+
+    pub fn note(&mut self, message: &str) {
+        self.events.note(message);
+    }
+}
@@ -2,10 +2,15 @@
 #![allow(clippy::arc_with_non_send_sync)]
 
 use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 use ark_ff::One;
 use ark_std::Zero;
+use core::cell::RefCell;
 use core::cmp::Ordering;
 use core::hash::Hash;
+use core::hash::Hasher;
 use core::iter::Product;
 use core::iter::Sum;
 use core::ops::Add;
@@ -17,14 +22,9 @@ use core::ops::MulAssign;
 use core::ops::Neg;
 use core::ops::Sub;
 use core::ops::SubAssign;
+use core::ptr::addr_of;
 use num_traits::Pow;
-use std::cell::RefCell;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hasher;
-use std::ptr::addr_of;
-use std::rc::Rc;
-use std::sync::Arc;
-use std::sync::RwLock;
+use spin::RwLock;
 
 pub type P<T> = Arc<RwLock<T>>;
 
@@ -49,10 +49,10 @@ impl<T> Expr<T> {
         use Expr::*;
         match self {
             // Tree types are recursed first
-            Neg(a) | Pow(a, _) => a.as_ref().read().unwrap().traverse(f),
+            Neg(a) | Pow(a, _) => a.as_ref().read().traverse(f),
             Add(a, b) | Mul(a, b) | Div(a, b) => {
-                a.as_ref().read().unwrap().traverse(f);
-                b.as_ref().read().unwrap().traverse(f);
+                a.as_ref().read().traverse(f);
+                b.as_ref().read().traverse(f);
             }
             Leaf(_) => {}
         }
@@ -73,19 +73,19 @@ impl<T> Expr<T> {
         let res = match self {
             // Tree types are recursed first
             Add(a, b) => Add(
-                Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f))),
-                Arc::new(RwLock::new(b.as_ref().read().unwrap().map(f))),
+                Arc::new(RwLock::new(a.as_ref().read().map(f))),
+                Arc::new(RwLock::new(b.as_ref().read().map(f))),
             ),
-            Neg(a) => Neg(Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f)))),
+            Neg(a) => Neg(Arc::new(RwLock::new(a.as_ref().read().map(f)))),
             Mul(a, b) => Mul(
-                Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f))),
-                Arc::new(RwLock::new(b.as_ref().read().unwrap().map(f))),
+                Arc::new(RwLock::new(a.as_ref().read().map(f))),
+                Arc::new(RwLock::new(b.as_ref().read().map(f))),
             ),
             Div(a, b) => Div(
-                Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f))),
-                Arc::new(RwLock::new(b.as_ref().read().unwrap().map(f))),
+                Arc::new(RwLock::new(a.as_ref().read().map(f))),
+                Arc::new(RwLock::new(b.as_ref().read().map(f))),
             ),
-            Pow(a, e) => Pow(Arc::new(RwLock::new(a.as_ref().read().unwrap().map(f))), *e),
+            Pow(a, e) => Pow(Arc::new(RwLock::new(a.as_ref().read().map(f))), *e),
 
             // Leaf types are mapped as is.
             Leaf(v) => Leaf(v.clone()),
@@ -101,10 +101,10 @@ impl<T> Expr<T> {
         match self {
             // Tree types are recursed first
             Add(a, b) | Mul(a, b) | Div(a, b) => {
-                a.write().unwrap().traverse_mut(f);
-                b.write().unwrap().traverse_mut(f);
+                a.write().traverse_mut(f);
+                b.write().traverse_mut(f);
             }
-            Neg(a) | Pow(a, _) => a.write().unwrap().traverse_mut(f),
+            Neg(a) | Pow(a, _) => a.write().traverse_mut(f),
             Leaf(_) => {}
         }
 
@@ -124,11 +124,11 @@ impl<T> Expr<T> {
         // The lint's suggestion "Using Option::map_or_else" doesn't work because
         // `.insert` requires mutable access to seen (which is already borrowed).
         #[allow(clippy::option_if_let_else)]
-        if let Some(node) = sn.get(&addr_of!(*this.read().unwrap())) {
+        if let Some(node) = sn.get(&addr_of!(*this.read())) {
             Arc::clone(node)
         } else {
             use Expr::*;
-            let res = Arc::new(RwLock::new(match &*this.read().unwrap() {
+            let res = Arc::new(RwLock::new(match &*this.read() {
                 Leaf(l) => {
                     if let Some(leaf) = sl.get(&addr_of!(*l)) {
                         return Arc::clone(leaf);
@@ -136,7 +136,7 @@ impl<T> Expr<T> {
 
                     let res = Arc::new(RwLock::new(Leaf(f(l))));
                     sl.insert(addr_of!(*l), Arc::clone(&res));
-                    sn.insert(addr_of!(*this.read().unwrap()), Arc::clone(&res));
+                    sn.insert(addr_of!(*this.read()), Arc::clone(&res));
                     return res;
                 }
                 Add(a, b) => Add(
@@ -154,7 +154,7 @@ impl<T> Expr<T> {
                     Self::_map_leaves(b, sl, sn, f),
                 ),
             }));
-            sn.insert(addr_of!(*this.read().unwrap()), Arc::clone(&res));
+            sn.insert(addr_of!(*this.read()), Arc::clone(&res));
             res
         }
     }
@@ -180,7 +180,16 @@ impl<T> Expr<T> {
         }
     }
 
-    /// Inspired by <https://neptune.cash/learn/speed-up-stark-provers-with-multicircuits/>
+    /// Rebuilds the expression so structurally equal subexpressions are a
+    /// single shared node. Shared nodes are only evaluated once (see
+    /// [`Expr::compile`]).
+    ///
+    /// Chains of additions and multiplications are flattened and their operands
+    /// put in a canonical order before they're rebuilt so subexpressions are
+    /// also shared when they only differ by the order of their operands e.g.
+    /// `a * b` and `b * a` or a selector product `s0 * s1 * s2` and the
+    /// `s1 * s0` it contains. Inspired by
+    /// <https://neptune.cash/learn/speed-up-stark-provers-with-multicircuits/>
     /// Runtime: O(n log n) where n is the number of edges TODO: check
     #[allow(clippy::too_many_lines)]
     pub fn reuse_shared_nodes(&self) -> Self
@@ -190,32 +199,109 @@ impl<T> Expr<T> {
         type Id = u64;
         type SeenSet<T> = Rc<RefCell<BTreeMap<Id, P<Expr<T>>>>>;
 
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        enum ChainOp {
+            Add,
+            Mul,
+        }
+
+        enum Value<T> {
+            Node(Id, P<Expr<T>>),
+            /// Operands of a chain of additions or multiplications. Chains are
+            /// only built once they're used by another operation so they can be
+            /// extended and reordered.
+            Chain(ChainOp, Vec<(Id, P<Expr<T>>)>),
+        }
+
         struct IdNode<T> {
-            node: P<Expr<T>>,
+            value: Value<T>,
             seen: SeenSet<T>,
-            id: Id,
         }
 
-        impl<T: Hash + Clone> IdNode<T> {
-            fn new_leaf(leaf: &T, seen: SeenSet<T>) -> Self {
+        fn hash_id(value: impl Hash) -> Id {
+            // `DefaultHasher` needs std. Ids only need to be stable within a call
+            #[allow(deprecated)]
+            let mut hasher = core::hash::SipHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        /// Returns the node with id `id` or creates it if it's not been seen
+        fn intern<T>(seen: &SeenSet<T>, id: Id, expr: impl FnOnce() -> Expr<T>) -> P<Expr<T>> {
+            let mut seen = seen.borrow_mut();
+            let node = seen
+                .entry(id)
+                .or_insert_with(|| Arc::new(RwLock::new(expr())));
+            Arc::clone(node)
+        }
+
+        impl<T> IdNode<T> {
+            fn new_leaf(leaf: &T, seen: SeenSet<T>) -> Self
+            where
+                T: Hash + Clone,
+            {
                 // `id` is the hash of the leaf
-                let mut hasher = DefaultHasher::new();
-                ("leaf", leaf).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this leaf, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Leaf(leaf.clone())));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
+                let id = hash_id(("leaf", leaf));
+                let node = intern(&seen, id, || Expr::Leaf(leaf.clone()));
+                let value = Value::Node(id, node);
+                Self { value, seen }
+            }
+
+            /// Builds the node. Chains are built as a left fold over their
+            /// operands sorted by id. Leaves come first so products of
+            /// selectors or trace cells are shared by the chains they start.
+            fn build(self) -> (Id, P<Expr<T>>, SeenSet<T>) {
+                let (op, mut operands) = match self.value {
+                    Value::Node(id, node) => return (id, node, self.seen),
+                    Value::Chain(op, operands) => (op, operands),
                 };
+                operands.sort_by_cached_key(|(id, node)| {
+                    let is_leaf = matches!(*node.read(), Expr::Leaf(_));
+                    (!is_leaf, *id)
+                });
+                let mut operands = operands.into_iter();
+                let (mut id, mut node) = operands.next().unwrap();
+                for (rhs_id, rhs) in operands {
+                    id = hash_id((op, id, rhs_id));
+                    node = intern(&self.seen, id, || match op {
+                        ChainOp::Add => Expr::Add(node, rhs),
+                        ChainOp::Mul => Expr::Mul(node, rhs),
+                    });
+                }
+                (id, node, self.seen)
+            }
+
+            /// Returns the operands of the node as part of a chain of `op`
+            fn into_operands(self, op: ChainOp) -> (Vec<(Id, P<Expr<T>>)>, SeenSet<T>) {
+                match self.value {
+                    Value::Chain(chain_op, operands) if chain_op == op => (operands, self.seen),
+                    _ => {
+                        let (id, node, seen) = self.build();
+                        (vec![(id, node)], seen)
+                    }
+                }
+            }
 
-                Self { node, seen, id }
+            fn chain(self, rhs: Self, op: ChainOp) -> Self {
+                let (mut operands, seen) = self.into_operands(op);
+                operands.extend(rhs.into_operands(op).0);
+                let value = Value::Chain(op, operands);
+                Self { value, seen }
+            }
+
+            /// Applies an operation that isn't part of a chain. `arg` tells
+            /// apart operations with the same tag.
+            fn apply(
+                self,
+                tag: &str,
+                arg: impl Hash,
+                expr: impl FnOnce(P<Expr<T>>) -> Expr<T>,
+            ) -> Self {
+                let (id, node, seen) = self.build();
+                let id = hash_id((tag, id, arg));
+                let node = intern(&seen, id, || expr(node));
+                let value = Value::Node(id, node);
+                Self { value, seen }
             }
         }
 
@@ -223,25 +309,7 @@ impl<T> Expr<T> {
             type Output = Self;
 
             fn add(self, rhs: Self) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("add", self.id, rhs.id).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this node
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Add(self.node, rhs.node)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
+                self.chain(rhs, ChainOp::Add)
             }
         }
 
@@ -249,25 +317,7 @@ impl<T> Expr<T> {
             type Output = Self;
 
             fn mul(self, rhs: Self) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("mul", self.id, rhs.id).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Mul(self.node, rhs.node)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
+                self.chain(rhs, ChainOp::Mul)
             }
         }
 
@@ -275,25 +325,8 @@ impl<T> Expr<T> {
             type Output = Self;
 
             fn div(self, rhs: Self) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("div", self.id, rhs.id).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Div(self.node, rhs.node)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
+                let (rhs_id, rhs, _) = rhs.build();
+                self.apply("div", rhs_id, |node| Expr::Div(node, rhs))
             }
         }
 
@@ -301,25 +334,7 @@ impl<T> Expr<T> {
             type Output = Self;
 
             fn neg(self) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("neg", self.id).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Neg(self.node)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
+                self.apply("neg", 0, Expr::Neg)
             }
         }
 
@@ -327,33 +342,16 @@ impl<T> Expr<T> {
             type Output = Self;
 
             fn pow(self, exp: usize) -> Self::Output {
-                let seen = self.seen;
-
-                let mut hasher = DefaultHasher::new();
-                ("pow", self.id, exp).hash(&mut hasher);
-                let id = hasher.finish();
-
-                // can't use if_let_else because it causes borrow errors
-                #[allow(clippy::option_if_let_else)]
-                let node = if seen.borrow().contains_key(&id) {
-                    // we have encountered this leaf
-                    Arc::clone(seen.borrow().get(&id).unwrap())
-                } else {
-                    // we haven't encountered this node, create a new entry
-                    let node = Arc::new(RwLock::new(Expr::Pow(self.node, exp)));
-                    seen.borrow_mut().insert(id, Arc::clone(&node));
-                    node
-                };
-
-                Self { node, seen, id }
+                self.apply("pow", exp, |node| Expr::Pow(node, exp))
             }
         }
 
         let seen = Rc::new(RefCell::new(BTreeMap::new()));
         let res = self.eval(&mut |leaf| IdNode::new_leaf(leaf, Rc::clone(&seen)));
+        let (_, node, res_seen) = res.build();
         // Drop references
-        drop((seen, res.seen));
-        Arc::into_inner(res.node).unwrap().into_inner().unwrap()
+        drop((seen, res_seen));
+        Arc::into_inner(node).unwrap().into_inner()
     }
 
     // Adapted from https://github.com/0xProject/OpenZKP
@@ -373,11 +371,11 @@ impl<T> Expr<T> {
         // println!("brr");
         match self {
             Leaf(a) => f(a),
-            Add(a, b) => a.read().unwrap().eval(f) + b.read().unwrap().eval(f),
-            Neg(a) => -a.read().unwrap().eval(f),
-            Mul(a, b) => a.read().unwrap().eval(f) * b.read().unwrap().eval(f),
-            Div(a, b) => a.read().unwrap().eval(f) / b.read().unwrap().eval(f),
-            Pow(a, e) => a.read().unwrap().eval(f).pow(*e),
+            Add(a, b) => a.read().eval(f) + b.read().eval(f),
+            Neg(a) => -a.read().eval(f),
+            Mul(a, b) => a.read().eval(f) * b.read().eval(f),
+            Div(a, b) => a.read().eval(f) / b.read().eval(f),
+            Pow(a, e) => a.read().eval(f).pow(*e),
         }
     }
 
@@ -391,7 +389,7 @@ impl<T> Expr<T> {
             + Pow<usize, Output = U>,
     {
         use Expr::*;
-        let mut node = this.write().unwrap();
+        let mut node = this.write();
         let res = match core::mem::take(&mut *node) {
             Leaf(v) => v.unwrap(),
             Neg(a) => -Self::_graph_eval(&a),
@@ -429,6 +427,196 @@ impl<T> Expr<T> {
     }
 }
 
+/// An instruction of a [`CompiledExpr`].
+/// Operands are indices of the instructions that produce them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction<T> {
+    Leaf(T),
+    Neg(usize),
+    Add(usize, usize),
+    Mul(usize, usize),
+    Div(usize, usize),
+    Pow(usize, usize),
+}
+
+/// An expression graph lowered into a flat list of instructions.
+///
+/// Instructions are in evaluation order. Shared nodes and duplicate leaves are
+/// only evaluated once. Unlike [`Expr::graph_eval`] evaluating a compiled
+/// expression doesn't rebuild the graph so it's cheap to evaluate many times.
+#[derive(Clone, Debug)]
+pub struct CompiledExpr<T> {
+    instructions: Vec<Instruction<T>>,
+    // index of the last instruction that uses each instruction's output
+    last_use: Vec<usize>,
+}
+
+impl<T> CompiledExpr<T> {
+    pub fn instructions(&self) -> &[Instruction<T>] {
+        &self.instructions
+    }
+
+    /// Index of the last instruction that uses each instruction's output
+    pub fn last_use(&self) -> &[usize] {
+        &self.last_use
+    }
+
+    /// Returns an iterator over all distinct leaves of the expression
+    pub fn leaves(&self) -> impl Iterator<Item = &T> {
+        self.instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::Leaf(v) => Some(v),
+                _ => None,
+            })
+    }
+
+    /// Maps leaves and retains the instructions
+    pub fn map_leaves<U>(&self, f: &mut impl FnMut(&T) -> U) -> CompiledExpr<U> {
+        use Instruction::*;
+        let instructions = self
+            .instructions
+            .iter()
+            .map(|instruction| match *instruction {
+                Leaf(ref v) => Leaf(f(v)),
+                Neg(a) => Neg(a),
+                Add(a, b) => Add(a, b),
+                Mul(a, b) => Mul(a, b),
+                Div(a, b) => Div(a, b),
+                Pow(a, e) => Pow(a, e),
+            })
+            .collect();
+        CompiledExpr {
+            instructions,
+            last_use: self.last_use.clone(),
+        }
+    }
+
+    /// Evaluates the instructions in order. Intermediate results are dropped
+    /// after their last use.
+    pub fn eval<U>(&self, f: &mut impl FnMut(&T) -> U) -> U
+    where
+        U: Clone
+            + Add<Output = U>
+            + Neg<Output = U>
+            + Div<Output = U>
+            + Mul<Output = U>
+            + Pow<usize, Output = U>,
+    {
+        use Instruction::*;
+        let mut registers: Vec<Option<U>> = Vec::with_capacity(self.instructions.len());
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            let res = match *instruction {
+                Leaf(ref v) => f(v),
+                Neg(a) => -self.operand(&mut registers, a, i),
+                Pow(a, e) => self.operand(&mut registers, a, i).pow(e),
+                Add(a, b) => {
+                    let (a, b) = self.operands(&mut registers, a, b, i);
+                    a + b
+                }
+                Mul(a, b) => {
+                    let (a, b) = self.operands(&mut registers, a, b, i);
+                    a * b
+                }
+                Div(a, b) => {
+                    let (a, b) = self.operands(&mut registers, a, b, i);
+                    a / b
+                }
+            };
+            registers.push(Some(res));
+        }
+        registers.pop().unwrap().unwrap()
+    }
+
+    fn operand<U: Clone>(&self, registers: &mut [Option<U>], operand: usize, i: usize) -> U {
+        if self.last_use[operand] == i {
+            registers[operand].take().unwrap()
+        } else {
+            registers[operand].clone().unwrap()
+        }
+    }
+
+    fn operands<U: Clone>(
+        &self,
+        registers: &mut [Option<U>],
+        a: usize,
+        b: usize,
+        i: usize,
+    ) -> (U, U) {
+        if a == b {
+            let v = self.operand(registers, a, i);
+            (v.clone(), v)
+        } else {
+            (self.operand(registers, a, i), self.operand(registers, b, i))
+        }
+    }
+}
+
+struct Compiler<T> {
+    instructions: Vec<Instruction<T>>,
+    leaves: BTreeMap<T, usize>,
+    nodes: BTreeMap<*const RwLock<Expr<T>>, usize>,
+}
+
+impl<T: Ord + Clone> Compiler<T> {
+    fn lower_node(&mut self, node: &P<Expr<T>>) -> usize {
+        let key = Arc::as_ptr(node);
+        if let Some(&i) = self.nodes.get(&key) {
+            return i;
+        }
+        let i = self.lower(&node.read());
+        self.nodes.insert(key, i);
+        i
+    }
+
+    fn lower(&mut self, expr: &Expr<T>) -> usize {
+        let instruction = match expr {
+            Expr::Leaf(v) => {
+                if let Some(&i) = self.leaves.get(v) {
+                    return i;
+                }
+                self.leaves.insert(v.clone(), self.instructions.len());
+                Instruction::Leaf(v.clone())
+            }
+            Expr::Neg(a) => Instruction::Neg(self.lower_node(a)),
+            Expr::Pow(a, e) => Instruction::Pow(self.lower_node(a), *e),
+            Expr::Add(a, b) => Instruction::Add(self.lower_node(a), self.lower_node(b)),
+            Expr::Mul(a, b) => Instruction::Mul(self.lower_node(a), self.lower_node(b)),
+            Expr::Div(a, b) => Instruction::Div(self.lower_node(a), self.lower_node(b)),
+        };
+        self.instructions.push(instruction);
+        self.instructions.len() - 1
+    }
+}
+
+impl<T: Ord + Clone> Expr<T> {
+    /// Lowers the expression graph into a [`CompiledExpr`]
+    pub fn compile(&self) -> CompiledExpr<T> {
+        let mut compiler = Compiler {
+            instructions: Vec::new(),
+            leaves: BTreeMap::new(),
+            nodes: BTreeMap::new(),
+        };
+        compiler.lower(self);
+        let instructions = compiler.instructions;
+        let mut last_use = (0..instructions.len()).collect::<Vec<usize>>();
+        for (i, instruction) in instructions.iter().enumerate() {
+            match *instruction {
+                Instruction::Leaf(_) => {}
+                Instruction::Neg(a) | Instruction::Pow(a, _) => last_use[a] = i,
+                Instruction::Add(a, b) | Instruction::Mul(a, b) | Instruction::Div(a, b) => {
+                    last_use[a] = i;
+                    last_use[b] = i;
+                }
+            }
+        }
+        CompiledExpr {
+            instructions,
+            last_use,
+        }
+    }
+}
+
 impl<T: Default> Default for Expr<T> {
     fn default() -> Self {
         Self::Leaf(T::default())
@@ -443,12 +631,9 @@ impl<T: PartialEq> PartialEq for Expr<T> {
         match (self, other) {
             (Add(l0, l1), Add(r0, r1))
             | (Mul(l0, l1), Mul(r0, r1))
-            | (Div(l0, l1), Div(r0, r1)) => {
-                *l0.read().unwrap() == *r0.read().unwrap()
-                    && *l1.read().unwrap() == *r1.read().unwrap()
-            }
-            (Neg(l0), Neg(r0)) => *l0.read().unwrap() == *r0.read().unwrap(),
-            (Pow(l0, l1), Pow(r0, r1)) => *l0.read().unwrap() == *r0.read().unwrap() && l1 == r1,
+            | (Div(l0, l1), Div(r0, r1)) => *l0.read() == *r0.read() && *l1.read() == *r1.read(),
+            (Neg(l0), Neg(r0)) => *l0.read() == *r0.read(),
+            (Pow(l0, l1), Pow(r0, r1)) => *l0.read() == *r0.read() && l1 == r1,
             (Leaf(l0), Leaf(r0)) => l0 == r0,
             _ => false,
         }
@@ -466,18 +651,13 @@ impl<T: Ord> Ord for Expr<T> {
         use Expr::*;
         use Ordering::*;
         match (self, other) {
-            (Pow(a, b), Pow(c, d)) => a
-                .read()
-                .unwrap()
-                .cmp(&c.read().unwrap())
-                .then_with(|| b.cmp(d)),
+            (Pow(a, b), Pow(c, d)) => a.read().cmp(&c.read()).then_with(|| b.cmp(d)),
             (Leaf(a), Leaf(b)) => a.cmp(b),
-            (Neg(a), Neg(b)) => a.read().unwrap().cmp(&b.read().unwrap()),
+            (Neg(a), Neg(b)) => a.read().cmp(&b.read()),
             (Add(a, b), Add(c, d)) | (Mul(a, b), Mul(c, d)) | (Div(a, b), Div(c, d)) => a
                 .read()
-                .unwrap()
-                .cmp(&c.read().unwrap())
-                .then_with(|| b.read().unwrap().cmp(&d.read().unwrap())),
+                .cmp(&c.read())
+                .then_with(|| b.read().cmp(&d.read())),
             (_, Leaf(_)) => Greater,
             (Leaf(_), _) => Less,
             (_, Add(_, _)) => Greater,
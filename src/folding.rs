@@ -0,0 +1,411 @@
+//! Experimental Nova-style folding of execution traces.
+//!
+//! Two instances of the same AIR are folded into a single relaxed instance.
+//! A relaxed instance `(w, u, E)` satisfies `C_hom(w, u) = E` for every
+//! constraint `C` where `C_hom(w, u) = u^d * C(w / u)` is the numerator of `C`
+//! homogenized to its degree `d` in the trace. Folding with challenge `r`
+//! produces:
+//!
+//! ```text
+//! w = w_1 + r * w_2
+//! u = u_1 + r * u_2
+//! E = E_1 + r * T_1 + ... + r^(d-1) * T_(d-1) + r^d * E_2
+//! ```
+//!
+//! where `T_j` are the cross terms. Merkle commitments are not homomorphic so
+//! the folded trace and error columns have to be recommitted. This module is a
+//! research integration point for accumulation-based recursion and doesn't
+//! provide any soundness guarantees.
+//!
+//! Constraints that use challenges or hints are not supported.
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::merkle::MatrixMerkleTree;
+use crate::random::PublicCoin;
+use crate::utils::horner_evaluate;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::iter::zip;
+use core::ops::Add;
+use core::ops::Div;
+use core::ops::Mul;
+use core::ops::Neg;
+use num_traits::Pow;
+
+/// Execution trace with a relaxation scalar and an error column per constraint
+pub struct RelaxedInstance<F: Field> {
+    pub trace: Matrix<F>,
+    pub u: F,
+    pub errors: Matrix<F>,
+}
+
+impl<F: FftField> RelaxedInstance<F> {
+    /// Creates a strict instance i.e. `u = 1` and `E = 0`
+    pub fn new(trace: Matrix<F>, num_constraints: usize) -> Self {
+        let n = trace.num_rows();
+        let errors = Matrix::new((0..num_constraints).map(|_| zeros(n)).collect());
+        Self {
+            trace,
+            u: F::one(),
+            errors,
+        }
+    }
+
+    /// Returns true if the instance satisfies the relaxed constraints
+    pub fn is_satisfied(&self, constraints: &[Constraint<FieldVariant<F, F>>]) -> bool {
+        let n = self.trace.num_rows();
+        let domain = Radix2EvaluationDomain::<F>::new(n).unwrap();
+        constraints.iter().enumerate().all(|(i, constraint)| {
+            (0..n).all(|row| {
+                let x = domain.element(row);
+                let eval = eval_homogeneous(constraint, n, x, self.u, &mut |col, offset| {
+                    self.trace[col][offset_row(row, offset, n)]
+                });
+                !eval.den.is_zero() || eval.num == self.errors[i][row]
+            })
+        })
+    }
+
+    /// Commits to the trace and error columns
+    pub fn commit<M: MatrixMerkleTree<F>>(&self) -> InstanceCommitment<F, M::Root> {
+        InstanceCommitment {
+            trace: M::from_matrix(&self.trace).root(),
+            u: self.u,
+            errors: M::from_matrix(&self.errors).root(),
+        }
+    }
+}
+
+/// Commitment to a relaxed instance
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstanceCommitment<F, D> {
+    pub trace: D,
+    pub u: F,
+    pub errors: D,
+}
+
+/// Data sent by the prover when folding two instances
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoldingProof<F, D> {
+    pub lhs: InstanceCommitment<F, D>,
+    pub rhs: InstanceCommitment<F, D>,
+    /// None if all constraints have degree one in the trace
+    pub cross_terms_commitment: Option<D>,
+    pub r: F,
+}
+
+/// Folds two relaxed instances of the same AIR into a single relaxed instance.
+///
+/// The folding challenge is drawn from the public coin after it's been
+/// reseeded with commitments to both instances and the cross terms.
+pub fn fold<F, M, P>(
+    constraints: &[Constraint<FieldVariant<F, F>>],
+    lhs: &RelaxedInstance<F>,
+    rhs: &RelaxedInstance<F>,
+    public_coin: &mut P,
+) -> (RelaxedInstance<F>, FoldingProof<F, M::Root>)
+where
+    F: FftField,
+    M: MatrixMerkleTree<F>,
+    P: PublicCoin<Field = F, Digest = M::Root>,
+{
+    let n = lhs.trace.num_rows();
+    assert_eq!(n, rhs.trace.num_rows());
+    assert_eq!(lhs.trace.num_cols(), rhs.trace.num_cols());
+    assert_eq!(constraints.len(), lhs.errors.num_cols());
+    assert_eq!(constraints.len(), rhs.errors.num_cols());
+
+    let lhs_commitment = lhs.commit::<M>();
+    let rhs_commitment = rhs.commit::<M>();
+    for commitment in [&lhs_commitment, &rhs_commitment] {
+        public_coin.reseed_with_digest(&commitment.trace);
+        public_coin.reseed_with_digest(&commitment.errors);
+        public_coin.reseed_with_field_elements(&[commitment.u]);
+    }
+
+    // cross terms of each constraint
+    let cross_terms = constraints
+        .iter()
+        .map(|constraint| gen_cross_terms(constraint, lhs, rhs))
+        .collect::<Vec<Vec<GpuVec<F>>>>();
+    let cross_terms_matrix = Matrix::new(
+        cross_terms
+            .iter()
+            .flatten()
+            .map(|col| col.to_vec_in(GpuAllocator))
+            .collect(),
+    );
+    let cross_terms_commitment =
+        (cross_terms_matrix.num_cols() != 0).then(|| M::from_matrix(&cross_terms_matrix).root());
+    if let Some(commitment) = &cross_terms_commitment {
+        public_coin.reseed_with_digest(commitment);
+    }
+
+    let r = public_coin.draw();
+    let trace = Matrix::new(
+        lhs.trace
+            .iter()
+            .zip(rhs.trace.iter())
+            .map(|(lhs_col, rhs_col)| {
+                let col = zip(lhs_col, rhs_col).map(|(&v0, &v1)| v0 + r * v1);
+                col.collect::<Vec<F>>().to_vec_in(GpuAllocator)
+            })
+            .collect(),
+    );
+    let u = lhs.u + r * rhs.u;
+    let errors = Matrix::new(
+        cross_terms
+            .into_iter()
+            .enumerate()
+            .map(|(i, cross_terms)| {
+                let degree = cross_terms.len() + 1;
+                let r_pow_degree = r.pow([degree as u64]);
+                let mut column = lhs.errors[i].to_vec_in(GpuAllocator);
+                for (row, e) in column.iter_mut().enumerate() {
+                    let mut r_pow = r;
+                    for cross_term in &cross_terms {
+                        *e += r_pow * cross_term[row];
+                        r_pow *= r;
+                    }
+                    *e += r_pow_degree * rhs.errors[i][row];
+                }
+                column
+            })
+            .collect(),
+    );
+
+    let proof = FoldingProof {
+        lhs: lhs_commitment,
+        rhs: rhs_commitment,
+        cross_terms_commitment,
+        r,
+    };
+    (RelaxedInstance { trace, u, errors }, proof)
+}
+
+/// Generates the cross terms `T_1, ..., T_(d-1)` of a constraint
+fn gen_cross_terms<F: FftField>(
+    constraint: &Constraint<FieldVariant<F, F>>,
+    lhs: &RelaxedInstance<F>,
+    rhs: &RelaxedInstance<F>,
+) -> Vec<GpuVec<F>> {
+    let trace_len = lhs.trace.num_rows();
+    let domain = Radix2EvaluationDomain::<F>::new(trace_len).unwrap();
+    let mut cross_terms = Vec::new();
+    for row in 0..trace_len {
+        let point = domain.element(row);
+        // evaluate `C_hom(w_1 + r * w_2, u_1 + r * u_2)` at `r = 0, 1, ..., d`
+        // and interpolate to get the coefficients of each power of `r`
+        let mut evals = Vec::new();
+        let mut degree = 0;
+        let mut den = F::zero();
+        let mut r = F::zero();
+        while evals.len() <= degree {
+            let folded_u = lhs.u + r * rhs.u;
+            let eval = eval_homogeneous(
+                constraint,
+                trace_len,
+                point,
+                folded_u,
+                &mut |col, offset| {
+                    let i = offset_row(row, offset, trace_len);
+                    lhs.trace[col][i] + r * rhs.trace[col][i]
+                },
+            );
+            evals.push(eval.num);
+            degree = eval.degree;
+            den = eval.den;
+            r += F::one();
+        }
+
+        if cross_terms.is_empty() {
+            cross_terms = (1..degree).map(|_| zeros(trace_len)).collect();
+        }
+
+        // cross terms are only relevant in rows where the constraint applies
+        if den.is_zero() {
+            let coeffs = interpolate(&evals);
+            for (cross_term, coeff) in cross_terms.iter_mut().zip(&coeffs[1..]) {
+                cross_term[row] = *coeff;
+            }
+        }
+    }
+    cross_terms
+}
+
+/// Evaluates a constraint homogenized with respect to `u`
+fn eval_homogeneous<F: FftField>(
+    constraint: &Constraint<FieldVariant<F, F>>,
+    trace_len: usize,
+    x: F,
+    u: F,
+    trace: &mut impl FnMut(usize, isize) -> F,
+) -> Homogeneous<F> {
+    use AlgebraicItem::*;
+    let eval = constraint.eval(&mut |leaf| match leaf {
+        X => Homogeneous::constant(x, u),
+        Constant(v) => Homogeneous::constant(v.as_fq(), u),
+        Periodic(col) => {
+            let point = x.pow([(trace_len / col.interval_size()) as u64]);
+            let coeffs = col
+                .coeffs()
+                .iter()
+                .map(FieldVariant::as_fq)
+                .collect::<Vec<_>>();
+            Homogeneous::constant(horner_evaluate(&coeffs, &point), u)
+        }
+        &Trace(col, offset) => Homogeneous {
+            num: trace(col, offset),
+            den: F::one(),
+            degree: 1,
+            u,
+        },
+        Challenge(_) | Hint(_) => panic!("folding doesn't support challenges or hints"),
+    });
+    // constraints must be at least degree one in the trace for folding
+    if eval.degree == 0 {
+        Homogeneous {
+            num: eval.num * u,
+            degree: 1,
+            ..eval
+        }
+    } else {
+        eval
+    }
+}
+
+/// Returns the coefficients of the polynomial through `(i, evals[i])`
+fn interpolate<F: Field>(evals: &[F]) -> Vec<F> {
+    let n = evals.len();
+    let mut coeffs = vec![F::zero(); n];
+    for (i, eval) in evals.iter().enumerate() {
+        // lagrange basis polynomial for point `i`
+        let mut basis = vec![F::one()];
+        let mut denominator = F::one();
+        for j in (0..n).filter(|&j| j != i) {
+            let xj = F::from(j as u64);
+            let mut next = vec![F::zero(); basis.len() + 1];
+            for (k, c) in basis.iter().enumerate() {
+                next[k + 1] += c;
+                next[k] -= xj * c;
+            }
+            basis = next;
+            denominator *= F::from(i as u64) - xj;
+        }
+        let scale = *eval / denominator;
+        for (coeff, c) in coeffs.iter_mut().zip(basis) {
+            *coeff += scale * c;
+        }
+    }
+    coeffs
+}
+
+#[allow(clippy::cast_possible_wrap)]
+const fn offset_row(row: usize, offset: isize, n: usize) -> usize {
+    (row as isize + offset).rem_euclid(n as isize) as usize
+}
+
+fn zeros<F: Field>(n: usize) -> GpuVec<F> {
+    let mut column = Vec::with_capacity_in(n, GpuAllocator);
+    column.resize(n, F::zero());
+    column
+}
+
+/// Evaluation of the numerator of a constraint homogenized to `degree` with
+/// respect to `u`. The denominator can't depend on the trace.
+#[derive(Clone, Copy)]
+struct Homogeneous<F> {
+    num: F,
+    den: F,
+    degree: usize,
+    u: F,
+}
+
+impl<F: Field> Homogeneous<F> {
+    fn constant(v: F, u: F) -> Self {
+        Self {
+            num: v,
+            den: F::one(),
+            degree: 0,
+            u,
+        }
+    }
+
+    /// Lifts the numerator to a higher degree
+    fn lift(self, degree: usize) -> F {
+        self.num * self.u.pow([(degree - self.degree) as u64])
+    }
+}
+
+impl<F: Field> Add for Homogeneous<F> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let degree = self.degree.max(rhs.degree);
+        Self {
+            num: self.lift(degree) * rhs.den + rhs.lift(degree) * self.den,
+            den: self.den * rhs.den,
+            degree,
+            u: self.u,
+        }
+    }
+}
+
+impl<F: Field> Neg for Homogeneous<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            num: -self.num,
+            ..self
+        }
+    }
+}
+
+impl<F: Field> Mul for Homogeneous<F> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            num: self.num * rhs.num,
+            den: self.den * rhs.den,
+            degree: self.degree + rhs.degree,
+            u: self.u,
+        }
+    }
+}
+
+impl<F: Field> Div for Homogeneous<F> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        assert_eq!(rhs.degree, 0, "denominators can't depend on the trace");
+        Self {
+            num: self.num * rhs.den,
+            den: self.den * rhs.num,
+            degree: self.degree,
+            u: self.u,
+        }
+    }
+}
+
+impl<F: Field> Pow<usize> for Homogeneous<F> {
+    type Output = Self;
+
+    fn pow(self, rhs: usize) -> Self {
+        let exp = [rhs as u64];
+        Self {
+            num: self.num.pow(exp),
+            den: self.den.pow(exp),
+            degree: self.degree * rhs,
+            u: self.u,
+        }
+    }
+}
@@ -17,14 +17,13 @@ use ark_poly::Polynomial;
 use ark_poly::Radix2EvaluationDomain;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use core::marker::PhantomData;
 use ministark_gpu::prelude::*;
 use ministark_gpu::utils::bit_reverse;
 use ministark_gpu::utils::bit_reverse_index;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use snafu::Snafu;
-use std::iter::zip;
-use std::marker::PhantomData;
 
 #[derive(Clone, Copy)]
 pub struct FriOptions {
@@ -46,6 +45,10 @@ impl FriOptions {
         }
     }
 
+    pub const fn blowup_factor(&self) -> usize {
+        self.blowup_factor
+    }
+
     pub const fn num_layers(&self, mut domain_size: usize) -> usize {
         let mut num_layers = 0;
         while domain_size > self.max_remainder_coeffs * self.blowup_factor {
@@ -125,6 +128,7 @@ pub struct FriProver<F: GpuField, D: Digest, M: MerkleTree> {
     options: FriOptions,
     layers: Vec<FriLayer<F, M>>,
     remainder_coeffs: Vec<F>,
+    bit_reversed_folding: bool,
     _phantom: PhantomData<D>,
 }
 
@@ -141,10 +145,18 @@ where
             options,
             layers: Vec::new(),
             remainder_coeffs: Vec::new(),
+            bit_reversed_folding: false,
             _phantom: PhantomData,
         }
     }
 
+    /// Folds layers in bit-reversed order with [`fold_bit_reversed`] rather
+    /// than with [`apply_drp`]. Layers are unchanged.
+    pub const fn with_bit_reversed_folding(mut self, bit_reversed_folding: bool) -> Self {
+        self.bit_reversed_folding = bit_reversed_folding;
+        self
+    }
+
     pub fn into_proof(self, positions: &[usize]) -> FriProof<F, D, M> {
         let folding_factor = self.options.folding_factor;
         // let (last_layer, initial_layers) = self.layers.split_last().unwrap();
@@ -194,6 +206,37 @@ where
         self.set_remainder(channel, evaluations);
     }
 
+    /// Returns the evaluations committed to in each layer and the remainder
+    /// coefficients. These are enough to [restore](Self::restore_layers) the
+    /// prover.
+    pub fn layer_evaluations(&self) -> (Vec<&Matrix<F>>, &[F]) {
+        let evaluations = self.layers.iter().map(|layer| &layer.evaluations).collect();
+        (evaluations, &self.remainder_coeffs)
+    }
+
+    /// Restores layers previously built with [`Self::build_layers`] from their
+    /// evaluations. Makes the same commitments and draws as building the
+    /// layers so `channel` ends up in the same state.
+    pub fn restore_layers(
+        &mut self,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        layer_evaluations: Vec<Matrix<F>>,
+        remainder_coeffs: Vec<F>,
+    ) {
+        assert!(self.layers.is_empty());
+        for evaluations in layer_evaluations {
+            let merkle_tree = M::from_matrix(&evaluations);
+            channel.commit_fri_layer(merkle_tree.root());
+            channel.draw_fri_alpha();
+            self.layers.push(FriLayer {
+                merkle_tree,
+                evaluations,
+            });
+        }
+        channel.commit_remainder(&remainder_coeffs);
+        self.remainder_coeffs = remainder_coeffs;
+    }
+
     /// Builds a single layer of the FRI protocol
     /// Returns the evaluations for the next layer.
     fn build_layer<const N: usize>(
@@ -222,12 +265,17 @@ where
         });
 
         // return the next evaluations
-        apply_drp(
-            evaluations,
-            F::FftField::ONE,
-            channel.draw_fri_alpha(),
-            self.options.folding_factor,
-        )
+        let alpha = channel.draw_fri_alpha();
+        if self.bit_reversed_folding {
+            fold_bit_reversed(&evaluations, alpha, self.options.folding_factor)
+        } else {
+            apply_drp(
+                evaluations,
+                F::FftField::ONE,
+                alpha,
+                self.options.folding_factor,
+            )
+        }
     }
 
     fn set_remainder(
@@ -261,6 +309,8 @@ pub enum VerificationError {
     RemainderCommitmentInvalid,
     #[snafu(display("remainder is not a degree {degree} polynomial"))]
     RemainderDegreeMismatch { degree: usize },
+    #[snafu(display("proof has no layers to open the evaluations"))]
+    NoLayers,
     #[snafu(display("{size} can't be divided by {folding_factor} (layer {layer})"))]
     CodewordTruncation {
         size: usize,
@@ -379,13 +429,6 @@ where
             .map_err(|_| VerificationError::LayerCommitmentInvalid { layer: i })?;
 
             let query_values = get_query_values(rows, &positions, &folded_positions);
-            // println!("evaluatinos: {:?}", evaluations);
-            // println!("query vals: {:?}", query_values);
-            assert_eq!(evaluations.len(), query_values.len());
-            for (i, (eval, query_val)) in zip(&evaluations, &query_values).enumerate() {
-                assert_eq!(eval, query_val, "mismatch at {i}");
-            }
-
             if evaluations != query_values {
                 return Err(VerificationError::InvalidDegreeRespectingProjection { layer: i });
             }
@@ -487,6 +530,164 @@ pub trait ProverChannel {
     fn draw_fri_alpha(&mut self) -> Self::Field;
 }
 
+/// Channel for standalone FRI proofs (see [`prove`] and [`verify`])
+pub struct FriChannel<P: PublicCoin> {
+    pub public_coin: P,
+    pub options: FriOptions,
+    pub num_queries: usize,
+}
+
+impl<P: PublicCoin> FriChannel<P> {
+    pub const fn new(public_coin: P, options: FriOptions, num_queries: usize) -> Self {
+        Self {
+            public_coin,
+            options,
+            num_queries,
+        }
+    }
+}
+
+impl<P: PublicCoin> ProverChannel for FriChannel<P>
+where
+    P::Field: GpuField,
+{
+    type Digest = P::Digest;
+    type Field = P::Field;
+
+    fn commit_fri_layer(&mut self, layer_root: P::Digest) {
+        self.public_coin.reseed_with_digest(&layer_root);
+    }
+
+    fn commit_remainder(&mut self, remainder_coeffs: &[P::Field]) {
+        self.public_coin
+            .reseed_with_field_element_vector(remainder_coeffs);
+    }
+
+    fn draw_fri_alpha(&mut self) -> P::Field {
+        self.public_coin.draw()
+    }
+}
+
+/// Proves `evaluations` are evaluations of a polynomial of degree less than
+/// `degree_bound` without an AIR.
+///
+/// Evaluations are over the coset `g * <ω>` in bit-reversed order where `g`
+/// is the generator of the field and `ω` generates the subgroup of size
+/// `degree_bound * blowup_factor`. The first layer of the proof commits to the
+/// evaluations.
+///
+/// # Panics
+/// Panics if `degree_bound` is not a power of two, the number of evaluations
+/// doesn't match the degree bound or there are too few evaluations to build a
+/// FRI layer.
+pub fn prove<F, D, M, P>(
+    evaluations: GpuVec<F>,
+    degree_bound: usize,
+    channel: &mut FriChannel<P>,
+) -> FriProof<F, D, M>
+where
+    F: GpuField + Field + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+    D: Digest,
+    M: MatrixMerkleTree<F, Root = D>,
+    P: PublicCoin<Field = F, Digest = D>,
+{
+    prove_with_positions(evaluations, degree_bound, channel).0
+}
+
+/// Same as [`prove`] but also returns the query positions. Protocols that
+/// check the queried evaluations against another commitment open it at these
+/// positions.
+pub fn prove_with_positions<F, D, M, P>(
+    evaluations: GpuVec<F>,
+    degree_bound: usize,
+    channel: &mut FriChannel<P>,
+) -> (FriProof<F, D, M>, Vec<usize>)
+where
+    F: GpuField + Field + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+    D: Digest,
+    M: MatrixMerkleTree<F, Root = D>,
+    P: PublicCoin<Field = F, Digest = D>,
+{
+    let options = channel.options;
+    let domain_size = evaluations.len();
+    assert!(degree_bound.is_power_of_two());
+    assert_eq!(domain_size, degree_bound * options.blowup_factor);
+    assert_ne!(options.num_layers(domain_size), 0, "too few evaluations");
+    let mut prover = FriProver::<F, D, M>::new(options);
+    prover.build_layers(channel, evaluations);
+    let positions = Vec::from_iter(
+        channel
+            .public_coin
+            .draw_queries(channel.num_queries, domain_size),
+    );
+    (prover.into_proof(&positions), positions)
+}
+
+/// Verifies a proof generated by [`prove`]. The evaluations at the queried
+/// positions are opened by the first layer of the proof.
+pub fn verify<F, D, M, P>(
+    proof: FriProof<F, D, M>,
+    degree_bound: usize,
+    channel: &mut FriChannel<P>,
+) -> Result<(), VerificationError>
+where
+    F: GpuField + Field + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+    D: Digest,
+    M: MatrixMerkleTree<F, Root = D>,
+    P: PublicCoin<Field = F, Digest = D>,
+{
+    verify_with_positions(proof, degree_bound, channel).map(|_| ())
+}
+
+/// Same as [`verify`] but also returns the query positions and the
+/// evaluations opened at each position
+pub fn verify_with_positions<F, D, M, P>(
+    proof: FriProof<F, D, M>,
+    degree_bound: usize,
+    channel: &mut FriChannel<P>,
+) -> Result<(Vec<usize>, Vec<F>), VerificationError>
+where
+    F: GpuField + Field + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+    D: Digest,
+    M: MatrixMerkleTree<F, Root = D>,
+    P: PublicCoin<Field = F, Digest = D>,
+{
+    let options = channel.options;
+    let folding_factor = options.folding_factor;
+    let domain_size = degree_bound * options.blowup_factor;
+    let first_layer_rows = match proof.layers.first() {
+        Some(layer) => layer.flattenend_rows.clone(),
+        None => return Err(VerificationError::NoLayers),
+    };
+    let verifier =
+        FriVerifier::<F, D, M>::new(&mut channel.public_coin, options, proof, degree_bound - 1)?;
+
+    let positions = Vec::from_iter(
+        channel
+            .public_coin
+            .draw_queries(channel.num_queries, domain_size),
+    );
+    let folded_positions = fold_positions(&positions, folding_factor);
+    if first_layer_rows.len() != folded_positions.len() * folding_factor {
+        return Err(VerificationError::NumPositionEvaluationMismatch);
+    }
+    let evaluations = positions
+        .iter()
+        .map(|position| {
+            let row = folded_positions
+                .binary_search(&(position / folding_factor))
+                .unwrap();
+            first_layer_rows[row * folding_factor + position % folding_factor]
+        })
+        .collect::<Vec<F>>();
+    verifier.verify(&positions, &evaluations)?;
+    Ok((positions, evaluations))
+}
+
 /// Performs a degree respecting projection (drp) on polynomial evaluations.
 // Example for `folding_factor = 2`:
 // ```text
@@ -537,6 +738,12 @@ where
     // TODO: integrate bit reverse into fft
     bit_reverse(&mut evals);
     let mut coeffs = ifft(evals, domain);
+    #[cfg(feature = "op-count")]
+    {
+        // scaling by the folding factor and multiplying by powers of alpha
+        let n = n as u64;
+        crate::op_count::record(crate::op_count::OpCounts::new(2 * n, n, 0));
+    }
     let fold_fact = F::from(folding_factor as u64);
     for coeff in &mut coeffs {
         *coeff *= fold_fact;
@@ -566,6 +773,75 @@ where
     evals
 }
 
+/// Folds bit-reversed evaluations over a subgroup by `folding_factor`. Gives
+/// the same result as [`apply_drp`] with a domain offset of one without any
+/// FFTs or permutations.
+///
+/// Bit-reversed evaluations `2i` and `2i + 1` are `f(x)` and `f(-x)` where `x`
+/// is the `i`th element of the subgroup of half the size in bit-reversed
+/// order. Replacing them with `f(x) + f(-x) + alpha * (f(x) - f(-x)) / x`
+/// folds by two and keeps the result in bit-reversed order. Folding by
+/// `folding_factor` folds by two `log2(folding_factor)` times using `alpha`,
+/// `alpha^2`, `alpha^4` etc.
+pub fn fold_bit_reversed<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    evals: &[F],
+    alpha: F,
+    folding_factor: usize,
+) -> GpuVec<F>
+where
+    F::FftField: FftField,
+{
+    let n = evals.len();
+    assert!(folding_factor.is_power_of_two());
+    assert!(n >= folding_factor);
+    let domain = Radix2EvaluationDomain::<F::FftField>::new(n).unwrap();
+    let x_invs = fold_twiddles(domain.group_gen_inv(), n);
+    #[cfg(feature = "op-count")]
+    {
+        // two multiplications and three additions for each folded pair
+        let pairs = (n - n / folding_factor) as u64;
+        crate::op_count::record(crate::op_count::OpCounts::new(2 * pairs, 3 * pairs, 0));
+    }
+    let mut alpha = alpha;
+    let mut evals = evals.to_vec();
+    for _ in 0..folding_factor.ilog2() {
+        evals = ark_std::cfg_chunks!(evals, 2)
+            .zip(ark_std::cfg_iter!(x_invs))
+            .map(|(pair, &x_inv)| {
+                let (lhs, rhs) = (pair[0], pair[1]);
+                let mut odd = (lhs - rhs) * alpha;
+                odd *= x_inv;
+                lhs + rhs + odd
+            })
+            .collect();
+        alpha.square_in_place();
+    }
+    evals.to_vec_in(GpuAllocator)
+}
+
+/// Returns `h^bitrev(i)` for `0 <= i < n / 2` where `h` has order `n` and
+/// `bitrev` reverses `log2(n / 2)` bits. Used to fold pairs of a bit-reversed
+/// domain of size `n`. Prefixes are the values for the domain of size `n / 2`
+/// generated by `h^2` so one table serves every fold.
+fn fold_twiddles<F: FftField>(h: F, n: usize) -> Vec<F> {
+    // `generators[k]` has order `n / 2^k`
+    let mut generators = Vec::new();
+    let mut generator = h;
+    for _ in 0..n.ilog2() {
+        generators.push(generator);
+        generator.square_in_place();
+    }
+    let mut twiddles = Vec::with_capacity(n / 2);
+    twiddles.push(F::one());
+    // the twiddles of a domain of size `2m` are those of the domain of size `m`
+    // followed by the same twiddles times a generator of order `2m`
+    for generator in generators.into_iter().rev().skip(1) {
+        let shifted = twiddles.iter().map(|&t| t * generator).collect::<Vec<F>>();
+        twiddles.extend(shifted);
+    }
+    twiddles
+}
+
 // requires ownership when the gpu feature is enabled
 #[allow(clippy::needless_pass_by_value)]
 fn ifft<F: GpuField + Field + DomainCoeff<F::FftField>>(
@@ -575,14 +851,24 @@ fn ifft<F: GpuField + Field + DomainCoeff<F::FftField>>(
 where
     F::FftField: FftField,
 {
-    #[cfg(feature = "gpu")]
-    if domain.size() >= GpuFft::<F>::MIN_SIZE {
+    #[cfg(feature = "op-count")]
+    crate::op_count::record_fft(domain.size(), true, domain.offset != F::FftField::ONE);
+    #[cfg(metal_gpu)]
+    if domain.size() >= GpuFft::<F>::MIN_SIZE && get_planner().supports_fft::<F>() {
         let mut coeffs = evals;
         let mut ifft = GpuIfft::from(domain);
         ifft.encode(&mut coeffs);
         ifft.execute();
         return coeffs;
     }
+    #[cfg(wgpu_gpu)]
+    if let Some(planner) = crate::utils::wgpu_planner::<F>(domain.size()) {
+        let mut coeffs = evals;
+        let mut ifft = planner.plan_ifft(domain);
+        ifft.encode(&mut coeffs);
+        ifft.execute();
+        return coeffs;
+    }
 
     let coeffs = domain.ifft(&evals);
     coeffs.to_vec_in(GpuAllocator)
@@ -597,14 +883,24 @@ fn fft<F: GpuField + Field + DomainCoeff<F::FftField>>(
 where
     F::FftField: FftField,
 {
-    #[cfg(feature = "gpu")]
-    if domain.size() >= GpuFft::<F>::MIN_SIZE {
+    #[cfg(feature = "op-count")]
+    crate::op_count::record_fft(domain.size(), false, domain.offset != F::FftField::ONE);
+    #[cfg(metal_gpu)]
+    if domain.size() >= GpuFft::<F>::MIN_SIZE && get_planner().supports_fft::<F>() {
         let mut evals = coeffs;
         let mut fft = GpuFft::from(domain);
         fft.encode(&mut evals);
         fft.execute();
         return evals;
     }
+    #[cfg(wgpu_gpu)]
+    if let Some(planner) = crate::utils::wgpu_planner::<F>(domain.size()) {
+        let mut evals = coeffs;
+        let mut fft = planner.plan_fft(domain);
+        fft.encode(&mut evals);
+        fft.execute();
+        return evals;
+    }
 
     let evals = domain.fft(&coeffs);
     evals.to_vec_in(GpuAllocator)
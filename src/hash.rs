@@ -1,10 +1,15 @@
 use crate::utils::SerdeOutput;
+use alloc::vec::Vec;
+use ark_ff::BigInteger;
 use ark_ff::Field;
+use ark_ff::PrimeField;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
 use core::fmt::Debug;
+use core::marker::PhantomData;
 use digest::Digest as _;
 use sha2::Sha256;
+use sha3::Keccak256;
 
 /// Trait inspired by Winterfell: <https://github.com/facebook/winterfell/blob/main/crypto/src/hash/mod.rs#L33>
 pub trait HashFn: Send + Sync + 'static {
@@ -33,11 +38,21 @@ pub trait HashFn: Send + Sync + 'static {
 ///
 /// This trait defines a hash procedure for a sequence of field elements. The
 /// elements can be either in the base field specified for this hasher, or in an
-/// extension of the base field.
+/// extension of the base field. Rows of base field trace columns and extension
+/// field composition columns are committed to with the same hasher so elements
+/// of an extension field must hash the same as their base prime field
+/// coefficients.
 /// Trait inspired by Winterfell: <https://github.com/facebook/winterfell/blob/main/crypto/src/hash/mod.rs#L55>
 pub trait ElementHashFn<F: Field>: HashFn {
     /// Returns a hash of the provided field elements.
     fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest;
+
+    /// Returns a hash of a row of field elements. Must return the same digest
+    /// as [`Self::hash_elements`]. `buffer` is scratch space that is reused
+    /// between rows so implementations can avoid allocating for every row.
+    fn hash_row(row: &[F], _buffer: &mut Vec<u8>) -> Self::Digest {
+        Self::hash_elements(row.iter().copied())
+    }
 }
 
 /// Defines output type for a cryptographic hash function.
@@ -98,3 +113,154 @@ impl<F: Field> ElementHashFn<F> for Sha256HashFn {
         Self::hash_chunks([&*byte_buffer])
     }
 }
+
+/// Context string for domain separated Blake3 hashing.
+///
+/// A context is used to derive a key (see [`blake3::Hasher::new_derive_key`])
+/// so digests computed in one context are unrelated to digests computed in
+/// another. Contexts should be hardcoded, globally unique and application
+/// specific e.g. `"my-app 2023-06-01 trace commitments"`.
+pub trait Blake3Context: Send + Sync + 'static {
+    const CONTEXT: Option<&'static str>;
+}
+
+/// No context. Digests are regular (unkeyed) Blake3 digests.
+impl Blake3Context for () {
+    const CONTEXT: Option<&'static str> = None;
+}
+
+/// Blake3 hash function. Optionally domain separated with a [`Blake3Context`].
+///
+/// Blake3 is considerably faster than SHA-256 which makes it a good fit for
+/// committing to large LDEs.
+pub struct Blake3HashFn<C: Blake3Context = ()>(PhantomData<C>);
+
+impl<C: Blake3Context> Blake3HashFn<C> {
+    fn hasher() -> blake3::Hasher {
+        C::CONTEXT.map_or_else(blake3::Hasher::new, blake3::Hasher::new_derive_key)
+    }
+
+    fn finalize(hasher: &blake3::Hasher) -> SerdeOutput<blake3::Hasher> {
+        SerdeOutput::new((*hasher.finalize().as_bytes()).into())
+    }
+}
+
+impl<C: Blake3Context> HashFn for Blake3HashFn<C> {
+    type Digest = SerdeOutput<blake3::Hasher>;
+
+    const COLLISION_RESISTANCE: u32 = 128;
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> SerdeOutput<blake3::Hasher> {
+        let bytes = bytes.into_iter().collect::<Vec<u8>>();
+        Self::hash_chunks([&*bytes])
+    }
+
+    fn hash_chunks<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> SerdeOutput<blake3::Hasher> {
+        let mut hasher = Self::hasher();
+        chunks.into_iter().for_each(|chunk| {
+            hasher.update(chunk);
+        });
+        Self::finalize(&hasher)
+    }
+
+    fn merge(
+        v0: &SerdeOutput<blake3::Hasher>,
+        v1: &SerdeOutput<blake3::Hasher>,
+    ) -> SerdeOutput<blake3::Hasher> {
+        Self::hash_chunks([&v0[..], &v1[..]])
+    }
+
+    fn merge_with_int(
+        seed: &SerdeOutput<blake3::Hasher>,
+        value: u64,
+    ) -> SerdeOutput<blake3::Hasher> {
+        Self::hash_chunks([&seed[..], &value.to_be_bytes()[..]])
+    }
+}
+
+impl<F: Field, C: Blake3Context> ElementHashFn<F> for Blake3HashFn<C> {
+    fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest {
+        let mut byte_buffer = Vec::new();
+        for element in elements {
+            element.serialize_uncompressed(&mut byte_buffer).unwrap();
+        }
+        Self::hash_chunks([&*byte_buffer])
+    }
+
+    fn hash_row(row: &[F], buffer: &mut Vec<u8>) -> Self::Digest {
+        buffer.clear();
+        for element in row {
+            element.serialize_uncompressed(&mut *buffer).unwrap();
+        }
+        Self::hash_chunks([&**buffer])
+    }
+}
+
+/// Keccak-256 hash function (the hash exposed by the EVM's `KECCAK256` opcode).
+///
+/// Inputs are encoded the way Solidity's `abi.encodePacked` encodes them so
+/// digests can be recomputed cheaply by an on-chain verifier:
+///
+/// * `merge(a, b)` is `keccak256(abi.encodePacked(a, b))` for `bytes32` a, b
+/// * `merge_with_int(seed, v)` is `keccak256(abi.encodePacked(seed, v))` for
+///   `bytes32` seed and `uint64` v
+/// * field elements are hashed as a packed sequence of `uint256` words. Each
+///   word is the canonical (non-Montgomery) value of a base field element.
+///   Extension field elements are split into their base field coefficients.
+pub struct Keccak256HashFn;
+
+impl Keccak256HashFn {
+    /// Appends field elements to `buffer` as 32 byte big-endian words
+    pub(crate) fn encode_elements<F: Field>(
+        buffer: &mut Vec<u8>,
+        elements: impl IntoIterator<Item = F>,
+    ) {
+        for element in elements {
+            for v in element.to_base_prime_field_elements() {
+                let bytes = v.into_bigint().to_bytes_be();
+                assert!(bytes.len() <= 32, "field elements must fit in a uint256");
+                buffer.resize(buffer.len() + 32 - bytes.len(), 0);
+                buffer.extend_from_slice(&bytes);
+            }
+        }
+    }
+}
+
+impl HashFn for Keccak256HashFn {
+    type Digest = SerdeOutput<Keccak256>;
+
+    const COLLISION_RESISTANCE: u32 = 128;
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> SerdeOutput<Keccak256> {
+        let bytes = bytes.into_iter().collect::<Vec<u8>>();
+        Self::hash_chunks([&*bytes])
+    }
+
+    fn hash_chunks<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> SerdeOutput<Keccak256> {
+        let mut hasher = Keccak256::new();
+        chunks.into_iter().for_each(|chunk| hasher.update(chunk));
+        SerdeOutput::new(hasher.finalize())
+    }
+
+    fn merge(v0: &SerdeOutput<Keccak256>, v1: &SerdeOutput<Keccak256>) -> SerdeOutput<Keccak256> {
+        Self::hash_chunks([&v0[..], &v1[..]])
+    }
+
+    fn merge_with_int(seed: &SerdeOutput<Keccak256>, value: u64) -> SerdeOutput<Keccak256> {
+        Self::hash_chunks([&seed[..], &value.to_be_bytes()[..]])
+    }
+}
+
+impl<F: Field> ElementHashFn<F> for Keccak256HashFn {
+    fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest {
+        let mut byte_buffer = Vec::new();
+        Self::encode_elements(&mut byte_buffer, elements);
+        Self::hash_chunks([&*byte_buffer])
+    }
+
+    fn hash_row(row: &[F], buffer: &mut Vec<u8>) -> Self::Digest {
+        buffer.clear();
+        Self::encode_elements(buffer, row.iter().copied());
+        Self::hash_chunks([&**buffer])
+    }
+}
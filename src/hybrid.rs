@@ -0,0 +1,137 @@
+//! Hybrid executor that shares work between accelerators (e.g. GPUs) and CPU
+//! threads.
+//!
+//! Items are taken from a shared queue. Accelerators take batches from the
+//! front of the queue and CPU workers take single items from the back. Batch
+//! sizes are picked from the measured throughput of each kind of worker so
+//! that all workers finish at roughly the same time. Machines with strong CPUs
+//! and modest GPUs make use of both rather than leaving the CPU idle.
+use alloc::vec::Vec;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// Throughput of a kind of worker measured in items per second
+#[derive(Clone, Copy, Default)]
+struct Throughput(Option<f64>);
+
+impl Throughput {
+    /// Records a measurement using an exponential moving average
+    fn record(&mut self, num_items: usize, start: Instant) {
+        let seconds = start.elapsed().as_secs_f64().max(f64::EPSILON);
+        #[allow(clippy::cast_precision_loss)]
+        let measurement = num_items as f64 / seconds;
+        self.0 = Some(self.0.map_or(measurement, |prev| {
+            0.75f64.mul_add(prev, 0.25 * measurement)
+        }));
+    }
+}
+
+struct Queue<'a, T> {
+    remaining: &'a mut [T],
+    accelerator_throughput: Throughput,
+    cpu_throughput: Throughput,
+}
+
+impl<'a, T> Queue<'a, T> {
+    /// Size of the next batch taken by an accelerator
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn accelerator_batch_size(&self, num_accelerators: usize, num_cpu_workers: usize) -> usize {
+        let num_remaining = self.remaining.len();
+        let share = match (self.accelerator_throughput.0, self.cpu_throughput.0) {
+            (Some(accelerator), Some(cpu)) => {
+                let accelerators = accelerator * num_accelerators as f64;
+                let cpus = cpu * num_cpu_workers as f64;
+                accelerator / (accelerators + cpus)
+            }
+            // no measurements yet so assume all workers are equal
+            _ => 1.0 / (num_accelerators + num_cpu_workers) as f64,
+        };
+        ((num_remaining as f64 * share).ceil() as usize).clamp(1, num_remaining)
+    }
+
+    fn take_front(&mut self, n: usize) -> &'a mut [T] {
+        let (batch, remaining) = core::mem::take(&mut self.remaining).split_at_mut(n);
+        self.remaining = remaining;
+        batch
+    }
+
+    fn take_back(&mut self) -> Option<&'a mut T> {
+        let (item, remaining) = core::mem::take(&mut self.remaining).split_last_mut()?;
+        self.remaining = remaining;
+        Some(item)
+    }
+}
+
+/// Applies `accelerator_fn` or `cpu_fn` to every item exactly once.
+///
+/// Each accelerator and each of the `num_cpu_workers` CPU workers runs on its
+/// own thread. `accelerator_fn` is called with batches of items and `cpu_fn`
+/// is called with single items.
+pub fn hybrid_for_each<T: Send, A: Sync>(
+    items: &mut [T],
+    accelerators: &[A],
+    num_cpu_workers: usize,
+    accelerator_fn: impl Fn(&A, &mut [T]) + Sync,
+    cpu_fn: impl Fn(&mut T) + Sync,
+) {
+    assert!(!accelerators.is_empty() || num_cpu_workers > 0);
+    let num_accelerators = accelerators.len();
+    let queue = Mutex::new(Queue {
+        remaining: items,
+        accelerator_throughput: Throughput::default(),
+        cpu_throughput: Throughput::default(),
+    });
+
+    thread::scope(|s| {
+        let queue = &queue;
+        let accelerator_fn = &accelerator_fn;
+        let cpu_fn = &cpu_fn;
+        let mut handles = Vec::new();
+
+        for accelerator in accelerators {
+            handles.push(s.spawn(move || loop {
+                let batch = {
+                    let mut queue = queue.lock().unwrap();
+                    if queue.remaining.is_empty() {
+                        break;
+                    }
+                    let n = queue.accelerator_batch_size(num_accelerators, num_cpu_workers);
+                    queue.take_front(n)
+                };
+                let start = Instant::now();
+                let num_items = batch.len();
+                accelerator_fn(accelerator, batch);
+                let mut queue = queue.lock().unwrap();
+                queue.accelerator_throughput.record(num_items, start);
+            }));
+        }
+
+        for _ in 0..num_cpu_workers {
+            handles.push(s.spawn(move || loop {
+                let Some(item) = queue.lock().unwrap().take_back() else {
+                    break;
+                };
+                let start = Instant::now();
+                cpu_fn(item);
+                queue.lock().unwrap().cpu_throughput.record(1, start);
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    });
+}
+
+/// Number of CPU workers to run alongside `num_accelerators` accelerators.
+/// One thread is reserved for driving each accelerator.
+pub fn default_num_cpu_workers(num_accelerators: usize) -> usize {
+    thread::available_parallelism()
+        .map_or(1, usize::from)
+        .saturating_sub(num_accelerators)
+}
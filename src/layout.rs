@@ -0,0 +1,85 @@
+use alloc::vec::Vec;
+
+/// A struct of named trace columns. Implemented with [`trace_layout!`].
+///
+/// The layout maps each field to a column index in declaration order. With
+/// `T = usize` the layout holds column indices that can be used in constraint
+/// expressions. With `T` a field element the layout holds a row and with
+/// `T = GpuVec<F>` it holds whole columns.
+///
+/// [`trace_layout!`]: crate::trace_layout
+pub trait TraceLayout<T>: Sized {
+    /// Column names in the order they appear in the trace
+    const COLUMN_NAMES: &'static [&'static str];
+    const NUM_COLUMNS: usize = Self::COLUMN_NAMES.len();
+
+    /// Returns the values in column order
+    fn into_values(self) -> Vec<T>;
+
+    /// Creates the layout from values in column order
+    ///
+    /// # Panics
+    /// Panics if the number of values is not [`Self::NUM_COLUMNS`]
+    fn from_values(values: impl IntoIterator<Item = T>) -> Self;
+}
+
+#[doc(hidden)]
+pub use alloc::vec::Vec as __Vec;
+
+/// Declares a struct of named trace columns and implements [`TraceLayout`]
+/// for it.
+///
+/// The struct is generic over the type of each field and `Layout::COLUMNS`
+/// holds the index of every column so constraints can refer to columns by
+/// name e.g. `Layout::COLUMNS.a.next() - Layout::COLUMNS.b.curr()`.
+#[macro_export]
+macro_rules! trace_layout {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name<T> {
+            $($(#[$field_attr])* $field_vis $field: T,)*
+        }
+
+        impl $name<usize> {
+            /// Index of each column in the trace
+            #[allow(unused_assignments)]
+            pub const COLUMNS: Self = {
+                let mut index = 0;
+                Self {
+                    $($field: {
+                        index += 1;
+                        index - 1
+                    },)*
+                }
+            };
+        }
+
+        impl<T> $crate::layout::TraceLayout<T> for $name<T> {
+            const COLUMN_NAMES: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            fn into_values(self) -> $crate::layout::__Vec<T> {
+                let mut values = $crate::layout::__Vec::with_capacity(
+                    <Self as $crate::layout::TraceLayout<T>>::NUM_COLUMNS,
+                );
+                $(values.push(self.$field);)*
+                values
+            }
+
+            fn from_values(values: impl IntoIterator<Item = T>) -> Self {
+                let mut values = values.into_iter();
+                let layout = Self {
+                    $($field: values
+                        .next()
+                        .expect(concat!("no value for column ", stringify!($field))),)*
+                };
+                assert!(values.next().is_none(), "too many values for layout");
+                layout
+            }
+        }
+    };
+}
@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(clippy::all, clippy::pedantic, clippy::cargo, clippy::nursery)]
 #![allow(
     incomplete_features,
@@ -26,63 +27,170 @@
     exclusive_range_pattern,
     vec_into_raw_parts,
     return_position_impl_trait_in_trait,
-    iter_collect_into
+    iter_collect_into,
+    associated_type_defaults
 )]
 
 // TODO: make some of these modules private
 #[macro_use]
 pub mod macros;
+#[cfg(feature = "verifier")]
 pub mod air;
+#[cfg(feature = "prover")]
+pub mod air_composition;
+#[cfg(feature = "prover")]
+pub mod cairo;
+#[cfg(feature = "verifier")]
 pub mod challenges;
+#[cfg(feature = "verifier")]
 pub mod channel;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+#[cfg(feature = "prover")]
+pub mod checkpoint;
+#[cfg(feature = "verifier")]
+pub mod circle;
+#[cfg(feature = "prover")]
+pub mod circuit;
+#[cfg(feature = "prover")]
+pub mod codegen;
+#[cfg(feature = "verifier")]
 pub mod composer;
+#[cfg(feature = "verifier")]
+pub mod compression;
+#[cfg(feature = "verifier")]
 pub mod constraints;
+#[cfg(feature = "prover")]
 pub mod debug;
+#[cfg(feature = "verifier")]
+pub mod domain;
+#[cfg(feature = "prover")]
+pub mod equivalence;
+#[cfg(feature = "verifier")]
 pub mod eval_cpu;
+#[cfg(feature = "prover")]
 pub mod eval_gpu;
+#[cfg(feature = "prover")]
+pub mod events;
+#[cfg(feature = "verifier")]
 pub mod expression;
+#[cfg(feature = "prover")]
+pub mod folding;
+#[cfg(feature = "verifier")]
 pub mod fri;
 pub mod hash;
+#[cfg(feature = "verifier")]
 pub mod hints;
+#[cfg(feature = "prover")]
+pub mod hybrid;
+#[cfg(feature = "verifier")]
+pub mod layout;
+#[cfg(feature = "prover")]
+pub mod lookup;
 pub mod matrix;
+#[cfg(feature = "merkle")]
 pub mod merkle;
+#[cfg(feature = "op-count")]
+pub mod op_count;
+#[cfg(feature = "prover")]
+pub mod pcs;
+#[cfg(feature = "prover")]
+pub mod permutation;
+#[cfg(any(feature = "merkle", feature = "channel"))]
+pub mod poseidon;
+#[cfg(feature = "verifier")]
+pub mod preprocessed;
+#[cfg(feature = "verifier")]
 pub mod proof;
+#[cfg(feature = "prover")]
 pub mod prover;
+#[cfg(feature = "channel")]
 pub mod random;
+#[cfg(feature = "prover")]
+pub mod recursion;
+#[cfg(feature = "reference-verify")]
+pub mod reference;
+#[cfg(any(feature = "merkle", feature = "channel"))]
+pub mod rescue;
+#[cfg(feature = "std")]
+pub mod spill;
+#[cfg(feature = "prover")]
+pub mod spot_check;
+#[cfg(feature = "verifier")]
 pub mod stark;
+#[cfg(feature = "prover")]
+pub mod synthetic;
+#[cfg(feature = "verifier")]
 pub mod trace;
 pub mod utils;
+#[cfg(feature = "verifier")]
 pub mod verifier;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "winterfell")]
+pub mod winterfell;
 
 #[macro_use]
 extern crate alloc;
+#[cfg(feature = "verifier")]
 pub use air::Air;
+#[cfg(feature = "verifier")]
 use alloc::vec::Vec;
+#[cfg(feature = "verifier")]
 use ark_ff::FftField;
+#[cfg(feature = "verifier")]
 use ark_ff::Field;
+#[cfg(feature = "verifier")]
 use ark_poly::domain::DomainCoeff;
+#[cfg(feature = "verifier")]
 use ark_serialize::CanonicalDeserialize;
+#[cfg(feature = "verifier")]
 use ark_serialize::CanonicalSerialize;
+#[cfg(feature = "verifier")]
 use core::ops::Add;
+#[cfg(feature = "verifier")]
 use core::ops::AddAssign;
+#[cfg(feature = "verifier")]
 use core::ops::Mul;
+#[cfg(feature = "verifier")]
 use core::ops::MulAssign;
+#[cfg(feature = "verifier")]
 use core::ops::Sub;
+#[cfg(feature = "verifier")]
 use core::ops::SubAssign;
+#[cfg(feature = "verifier")]
 use fri::FriOptions;
 pub use matrix::Matrix;
+pub use matrix::MatrixView;
+pub use matrix::SegmentedMatrix;
+#[cfg(feature = "verifier")]
 use ministark_gpu::GpuAdd;
+#[cfg(feature = "verifier")]
 use ministark_gpu::GpuFftField;
+#[cfg(feature = "verifier")]
 use ministark_gpu::GpuField;
+#[cfg(feature = "verifier")]
 use ministark_gpu::GpuFrom;
+#[cfg(feature = "verifier")]
 use ministark_gpu::GpuMul;
+#[cfg(feature = "verifier")]
 pub use proof::Proof;
+#[cfg(feature = "verifier")]
+use random::PowHash;
+#[cfg(feature = "verifier")]
+use random::QuerySampling;
+#[cfg(feature = "verifier")]
+use snafu::ensure;
+#[cfg(feature = "verifier")]
+use snafu::Snafu;
+#[cfg(feature = "verifier")]
 pub use trace::Trace;
 
 // TODO: include ability to specify:
 // - base field
 // - extension field
 // - hashing function
+#[cfg(feature = "verifier")]
 #[derive(Debug, Clone, Copy, CanonicalSerialize, CanonicalDeserialize, PartialEq, Eq)]
 pub struct ProofOptions {
     pub num_queries: u8,
@@ -90,8 +198,13 @@ pub struct ProofOptions {
     pub grinding_factor: u8,
     pub fri_folding_factor: u8,
     pub fri_max_remainder_coeffs: u8,
+    /// Hash function of the grinding proof of work
+    pub pow_hash: PowHash,
+    /// How query positions are drawn
+    pub query_sampling: QuerySampling,
 }
 
+#[cfg(feature = "verifier")]
 impl ProofOptions {
     pub const MIN_NUM_QUERIES: u8 = 1;
     pub const MAX_NUM_QUERIES: u8 = 128;
@@ -99,6 +212,31 @@ impl ProofOptions {
     pub const MAX_BLOWUP_FACTOR: u8 = 128;
     pub const MAX_GRINDING_FACTOR: u8 = 50;
 
+    /// Favours proving time. A small blowup factor keeps the LDE small at the
+    /// cost of more queries and therefore a larger proof.
+    ///
+    /// Conjectured query security is `42 * log2(4) + 16 = 100` bits.
+    pub const FAST_PROVER: Self = Self::new(42, 4, 16, 4, 64);
+
+    /// Favours proof size. A large blowup factor means few queries are needed
+    /// and a large folding factor keeps the number of FRI layers low. Proving
+    /// is several times slower than [`Self::FAST_PROVER`].
+    ///
+    /// Conjectured query security is `16 * log2(32) + 20 = 100` bits.
+    pub const SMALL_PROOF: Self = Self::new(16, 32, 20, 16, 16);
+
+    /// Targets 128 bits of security with a moderate blowup factor.
+    ///
+    /// Conjectured query security is `36 * log2(8) + 20 = 128` bits. The
+    /// security of a proof is also bounded by the size of the extension field
+    /// and the collision resistance of the hash function (see
+    /// [`Proof::security_level_bits`]) so the field and hash function must be
+    /// large enough to reach this level.
+    pub const HIGH_SECURITY_128: Self = Self::new(36, 8, 20, 8, 32);
+
+    /// Grinding factor used by the presets that target a security level
+    const PRESET_GRINDING_FACTOR: u8 = 20;
+
     pub const fn new(
         num_queries: u8,
         lde_blowup_factor: u8,
@@ -118,9 +256,242 @@ impl ProofOptions {
             grinding_factor,
             fri_folding_factor,
             fri_max_remainder_coeffs,
+            pow_hash: PowHash::PublicCoin,
+            query_sampling: QuerySampling::WithReplacement,
+        }
+    }
+
+    /// Returns options with the fewest queries that reach 100 bits of
+    /// conjectured query security for the given LDE blowup factor
+    ///
+    /// # Panics
+    ///
+    /// Panics if the blowup factor is less than 2.
+    pub const fn with_100_bit_security(lde_blowup_factor: u8) -> Self {
+        Self::with_conjectured_security(100, lde_blowup_factor)
+    }
+
+    /// Returns options with the fewest queries that reach 128 bits of
+    /// conjectured query security for the given LDE blowup factor.
+    /// `with_128_bit_security(8)` is [`Self::HIGH_SECURITY_128`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the blowup factor is less than 2.
+    pub const fn with_128_bit_security(lde_blowup_factor: u8) -> Self {
+        Self::with_conjectured_security(128, lde_blowup_factor)
+    }
+
+    const fn with_conjectured_security(bits: u32, lde_blowup_factor: u8) -> Self {
+        assert!(lde_blowup_factor >= 2);
+        let bits_per_query = lde_blowup_factor.ilog2();
+        let query_bits = bits - Self::PRESET_GRINDING_FACTOR as u32;
+        let num_queries = query_bits.div_ceil(bits_per_query);
+        #[allow(clippy::cast_possible_truncation)]
+        Self::new(
+            num_queries as u8,
+            lde_blowup_factor,
+            Self::PRESET_GRINDING_FACTOR,
+            8,
+            32,
+        )
+    }
+
+    /// Returns the `(proven, conjectured)` bits of security of a proof of a
+    /// trace with `trace_len` rows. `field_bits` is the size of the field
+    /// verifier challenges are drawn from i.e. the number of bits in the base
+    /// field times the extension degree (see [`utils::field_bits`]) and
+    /// `ce_blowup_factor` is the blowup of the composition polynomial (see
+    /// [`Air::ce_blowup_factor`]).
+    ///
+    /// Distinct query positions (see [`QuerySampling`]) give slightly more
+    /// security than the same number of independent draws, most noticeably
+    /// for small traces.
+    ///
+    /// Both estimates only account for the field and the queries. The
+    /// security of a proof is also bounded by the collision resistance of the
+    /// hash function (see [`Proof::security_level_bits`]).
+    pub fn security_level_bits(
+        self,
+        field_bits: u32,
+        trace_len: usize,
+        ce_blowup_factor: usize,
+    ) -> (u32, u32) {
+        (
+            self.proven_security_bits(field_bits, trace_len, ce_blowup_factor),
+            self.conjectured_security_bits(field_bits, trace_len),
+        )
+    }
+
+    /// Security in the unique decoding regime of FRI. Each query is rejected
+    /// with probability at least `(1 - rho) / 2` where `rho` is the code
+    /// rate and an out-of-domain point is bad with probability at most
+    /// `lde_domain_size * ce_blowup_factor / |F|`.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn proven_security_bits(
+        self,
+        field_bits: u32,
+        trace_len: usize,
+        ce_blowup_factor: usize,
+    ) -> u32 {
+        let lde_domain_size = trace_len * self.lde_blowup_factor as usize;
+        let field_security = field_bits
+            .saturating_sub(lde_domain_size.ilog2())
+            .saturating_sub(ce_blowup_factor.ilog2());
+        let rho = 1.0 / f64::from(self.lde_blowup_factor);
+        let query_bits =
+            self.query_sampling
+                .security_bits(self.num_queries, 0.5 + rho / 2.0, lde_domain_size);
+        let query_security = query_bits as u32 + u32::from(self.grinding_factor);
+        field_security.min(query_security)
+    }
+
+    /// Security assuming FRI is sound up to the list decoding radius (see
+    /// <https://github.com/starkware-libs/ethSTARK/blob/master/README.md#7-Measuring-Security>)
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub(crate) fn conjectured_security_bits(self, field_bits: u32, trace_len: usize) -> u32 {
+        let lde_domain_size = trace_len * self.lde_blowup_factor as usize;
+        let field_security = field_bits.saturating_sub(lde_domain_size.ilog2());
+        let rho = 1.0 / f64::from(self.lde_blowup_factor);
+        let query_bits = self
+            .query_sampling
+            .security_bits(self.num_queries, rho, lde_domain_size);
+        let query_security = query_bits as u32 + u32::from(self.grinding_factor);
+        field_security.min(query_security)
+    }
+
+    /// Returns the options with `num_queries` queries
+    pub const fn with_num_queries(self, num_queries: u8) -> Self {
+        Self {
+            pow_hash: self.pow_hash,
+            query_sampling: self.query_sampling,
+            ..Self::new(
+                num_queries,
+                self.lde_blowup_factor,
+                self.grinding_factor,
+                self.fri_folding_factor,
+                self.fri_max_remainder_coeffs,
+            )
+        }
+    }
+
+    /// Returns the options with an LDE blowup factor of `lde_blowup_factor`
+    pub const fn with_lde_blowup_factor(self, lde_blowup_factor: u8) -> Self {
+        Self {
+            pow_hash: self.pow_hash,
+            query_sampling: self.query_sampling,
+            ..Self::new(
+                self.num_queries,
+                lde_blowup_factor,
+                self.grinding_factor,
+                self.fri_folding_factor,
+                self.fri_max_remainder_coeffs,
+            )
+        }
+    }
+
+    /// Returns the options with a proof of work of `grinding_factor` bits
+    pub const fn with_grinding_factor(self, grinding_factor: u8) -> Self {
+        Self {
+            pow_hash: self.pow_hash,
+            query_sampling: self.query_sampling,
+            ..Self::new(
+                self.num_queries,
+                self.lde_blowup_factor,
+                grinding_factor,
+                self.fri_folding_factor,
+                self.fri_max_remainder_coeffs,
+            )
+        }
+    }
+
+    /// Returns the options with a FRI folding factor of `fri_folding_factor`
+    pub const fn with_fri_folding_factor(self, fri_folding_factor: u8) -> Self {
+        Self {
+            pow_hash: self.pow_hash,
+            query_sampling: self.query_sampling,
+            ..Self::new(
+                self.num_queries,
+                self.lde_blowup_factor,
+                self.grinding_factor,
+                fri_folding_factor,
+                self.fri_max_remainder_coeffs,
+            )
+        }
+    }
+
+    /// Returns the options with at most `fri_max_remainder_coeffs` FRI
+    /// remainder coefficients
+    pub const fn with_fri_max_remainder_coeffs(self, fri_max_remainder_coeffs: u8) -> Self {
+        Self {
+            pow_hash: self.pow_hash,
+            query_sampling: self.query_sampling,
+            ..Self::new(
+                self.num_queries,
+                self.lde_blowup_factor,
+                self.grinding_factor,
+                self.fri_folding_factor,
+                fri_max_remainder_coeffs,
+            )
+        }
+    }
+
+    /// Returns the options with a proof of work computed with `pow_hash`
+    pub const fn with_pow_hash(self, pow_hash: PowHash) -> Self {
+        Self { pow_hash, ..self }
+    }
+
+    /// Returns the options with query positions drawn using `query_sampling`
+    pub const fn with_query_sampling(self, query_sampling: QuerySampling) -> Self {
+        Self {
+            query_sampling,
+            ..self
         }
     }
 
+    /// Checks the options can be used to generate and verify proofs. Options
+    /// created with [`Self::new`] are checked when created but options can
+    /// also be deserialized from a proof.
+    pub fn validate(self) -> Result<(), InvalidProofOptions> {
+        let Self {
+            num_queries,
+            lde_blowup_factor,
+            grinding_factor,
+            fri_folding_factor,
+            fri_max_remainder_coeffs,
+            pow_hash: _,
+            query_sampling: _,
+        } = self;
+        ensure!(
+            (Self::MIN_NUM_QUERIES..=Self::MAX_NUM_QUERIES).contains(&num_queries),
+            NumQueriesSnafu { num_queries }
+        );
+        ensure!(
+            lde_blowup_factor.is_power_of_two()
+                && (Self::MIN_BLOWUP_FACTOR..=Self::MAX_BLOWUP_FACTOR).contains(&lde_blowup_factor),
+            LdeBlowupFactorSnafu { lde_blowup_factor }
+        );
+        ensure!(
+            grinding_factor <= Self::MAX_GRINDING_FACTOR,
+            GrindingFactorSnafu { grinding_factor }
+        );
+        ensure!(
+            matches!(fri_folding_factor, 2 | 4 | 8 | 16),
+            FriFoldingFactorSnafu { fri_folding_factor }
+        );
+        ensure!(
+            fri_max_remainder_coeffs.is_power_of_two(),
+            FriMaxRemainderCoeffsSnafu {
+                fri_max_remainder_coeffs
+            }
+        );
+        Ok(())
+    }
+
     pub fn into_fri_options(self) -> FriOptions {
         // TODO: move fri params into struct
         FriOptions::new(
@@ -131,6 +502,53 @@ impl ProofOptions {
     }
 }
 
+#[cfg(feature = "verifier")]
+/// Errors returned by [`ProofOptions::validate`]
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum InvalidProofOptions {
+    #[snafu(display(
+        "number of queries must be between {} and {} but is `{num_queries}`",
+        ProofOptions::MIN_NUM_QUERIES,
+        ProofOptions::MAX_NUM_QUERIES
+    ))]
+    NumQueries { num_queries: u8 },
+    #[snafu(display(
+        "LDE blowup factor must be a power of two between {} and {} but is `{lde_blowup_factor}`",
+        ProofOptions::MIN_BLOWUP_FACTOR,
+        ProofOptions::MAX_BLOWUP_FACTOR
+    ))]
+    LdeBlowupFactor { lde_blowup_factor: u8 },
+    #[snafu(display(
+        "grinding factor must be at most {} but is `{grinding_factor}`",
+        ProofOptions::MAX_GRINDING_FACTOR
+    ))]
+    GrindingFactor { grinding_factor: u8 },
+    #[snafu(display("FRI folding factor must be 2, 4, 8 or 16 but is `{fri_folding_factor}`"))]
+    FriFoldingFactor { fri_folding_factor: u8 },
+    #[snafu(display(
+        "FRI max remainder coefficients must be a power of two but is \
+         `{fri_max_remainder_coeffs}`"
+    ))]
+    FriMaxRemainderCoeffs { fri_max_remainder_coeffs: u8 },
+    #[snafu(display(
+        "LDE blowup factor `{lde_blowup_factor}` is smaller than the constraint evaluation \
+         blowup factor `{ce_blowup_factor}` inferred from the constraint degrees"
+    ))]
+    InsufficientLdeBlowupFactor {
+        lde_blowup_factor: u8,
+        ce_blowup_factor: usize,
+    },
+    #[snafu(display(
+        "`{num_queries}` distinct queries can't be drawn from an LDE domain of size \
+         `{lde_domain_size}`"
+    ))]
+    TooManyDistinctQueries {
+        num_queries: u8,
+        lde_domain_size: usize,
+    },
+}
+
+#[cfg(feature = "verifier")]
 pub trait StarkExtensionOf<Fp: GpuFftField + FftField>:
     GpuField<FftField = Fp>
     + Field<BasePrimeField = Fp>
@@ -154,6 +572,7 @@ pub trait StarkExtensionOf<Fp: GpuFftField + FftField>:
 {
 }
 
+#[cfg(feature = "verifier")]
 impl<T, F> StarkExtensionOf<F> for T
 where
     F: GpuFftField + FftField,
@@ -0,0 +1,128 @@
+//! Lookup argument based on logarithmic derivatives (`LogUp`).
+//!
+//! Proves every value of a base column appears in a table column. With a
+//! random challenge `α` the argument checks
+//!
+//! ```text
+//! Σ 1 / (α - f_i) = Σ m_i / (α - t_i)
+//! ```
+//!
+//! where `f` are the looked up values, `t` is the table and `m_i` is the
+//! number of times `t_i` is looked up. An extension column holds the running
+//! sum of `1 / (α - f_i) - m_i / (α - t_i)` which must end at zero.
+//!
+//! An AIR using a [`Lookup`] allocates its base columns (values, table and
+//! multiplicities), one extension column for the running sum and one
+//! challenge. [`Lookup::running_sum`] builds the extension column in
+//! [`Trace::build_extension_columns`](crate::Trace::build_extension_columns)
+//! and [`Lookup::constraint_groups`] is added to
+//! [`AirConfig::constraint_groups`](crate::air::AirConfig::constraint_groups).
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ConstraintGroup;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::VerifierChallenge;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use ark_ff::batch_inversion;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ministark_gpu::GpuFftField;
+
+/// Columns and challenge used by a lookup. Column indices are the same as in
+/// constraints i.e. extension columns are numbered after the base columns.
+#[derive(Clone, Copy, Debug)]
+pub struct Lookup {
+    /// Base column of looked up values
+    pub values: usize,
+    /// Base column of the table
+    pub table: usize,
+    /// Base column with the number of times each row of the table is looked
+    /// up. See [`multiplicities`].
+    pub multiplicities: usize,
+    /// Extension column with the running sum
+    pub running_sum: usize,
+    /// Index of the challenge `α`
+    pub challenge: usize,
+}
+
+impl Lookup {
+    /// Builds the running sum column from the base trace
+    pub fn running_sum<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        base_trace: &Matrix<Fp>,
+        challenges: &Challenges<Fq>,
+    ) -> GpuVec<Fq> {
+        let alpha = challenges[self.challenge];
+        let values = &base_trace[self.values];
+        let table = &base_trace[self.table];
+        let multiplicities = &base_trace[self.multiplicities];
+        let mut inverses = values
+            .iter()
+            .chain(table.iter())
+            .map(|&v| alpha - Fq::from(v))
+            .collect::<Vec<Fq>>();
+        batch_inversion(&mut inverses);
+        let (value_inverses, table_inverses) = inverses.split_at(values.len());
+
+        let mut sum = Fq::ZERO;
+        let mut column = Vec::with_capacity_in(values.len(), GpuAllocator);
+        for ((&value_inv, &table_inv), &m) in value_inverses
+            .iter()
+            .zip(table_inverses)
+            .zip(multiplicities.iter())
+        {
+            sum += value_inv - table_inv * Fq::from(m);
+            column.push(sum);
+        }
+        column
+    }
+
+    /// Constraints of the running sum. Denominators are multiplied out so
+    /// each constraint has degree 3 in the trace.
+    pub fn constraint_groups<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        trace_len: usize,
+    ) -> Vec<ConstraintGroup<FieldVariant<Fp, Fq>>> {
+        let alpha = self.challenge.challenge();
+        // running sum at the first row or the change in the running sum
+        let step = |delta: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>, offset| {
+            let value = self.values.offset(offset);
+            let table = self.table.offset(offset);
+            let multiplicity = self.multiplicities.offset(offset);
+            let value_denominator = &alpha - value;
+            let table_denominator = &alpha - table;
+            delta * &value_denominator * &table_denominator - table_denominator
+                + multiplicity * value_denominator
+        };
+        let sum = self.running_sum.curr();
+        let next_sum = self.running_sum.next();
+        vec![
+            ConstraintGroup::first_row(vec![step(sum.clone(), 0)]),
+            ConstraintGroup::transitions(trace_len, vec![step(next_sum - &sum, 1)]),
+            ConstraintGroup::last_row(trace_len, vec![sum]),
+        ]
+    }
+}
+
+/// Returns the number of times each row of `table` appears in `values`.
+///
+/// Values that appear in multiple rows of the table are counted in the first
+/// of these rows. Returns `None` if a value isn't in the table.
+pub fn multiplicities<F: Field>(values: &[F], table: &[F]) -> Option<Vec<F>> {
+    let mut rows = BTreeMap::new();
+    for (row, v) in table.iter().enumerate().rev() {
+        rows.insert(*v, row);
+    }
+    let mut counts = vec![0u64; table.len()];
+    for v in values {
+        counts[*rows.get(v)?] += 1;
+    }
+    Some(counts.into_iter().map(F::from).collect())
+}
@@ -1,22 +1,34 @@
+#[cfg(feature = "verifier")]
 use crate::constraints::ExecutionTraceColumn;
 use crate::hash::ElementHashFn;
+#[cfg(feature = "verifier")]
+use crate::layout::TraceLayout;
 use crate::utils::horner_evaluate;
+#[cfg(all(feature = "verifier", any(metal_gpu, not(feature = "parallel"))))]
+use crate::utils::out_of_order_fft_in_place;
 use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
+#[cfg(feature = "verifier")]
 use ark_ff::FftField;
 use ark_ff::Field;
+#[cfg(feature = "verifier")]
 use ark_poly::domain::DomainCoeff;
+#[cfg(feature = "verifier")]
 use ark_poly::domain::Radix2EvaluationDomain;
+#[cfg(feature = "verifier")]
 use ark_poly::EvaluationDomain;
 use core::cmp::Ordering;
 use core::ops::Add;
 use core::ops::Deref;
 use core::ops::DerefMut;
 use core::ops::Index;
+#[cfg(feature = "verifier")]
 use core::ops::IndexMut;
+use core::ops::Range;
+use core::slice::ChunksExact;
 use ministark_gpu::prelude::*;
 use ministark_gpu::utils::bit_reverse;
 #[cfg(feature = "parallel")]
@@ -31,35 +43,66 @@ impl<F: Field> Matrix<F> {
     }
 
     pub fn from_rows(rows: Vec<Vec<F>>) -> Self {
-        let num_rows = rows.len();
-        let num_cols = rows.first().map_or(0, Vec::len);
-        let mut cols = (0..num_cols)
-            .map(|_| Vec::with_capacity_in(num_rows, GpuAllocator))
-            .collect::<Vec<GpuVec<F>>>();
-        // TODO: parallelise
-        for row in rows {
-            debug_assert_eq!(row.len(), num_cols);
-            for (col, value) in cols.iter_mut().zip(row) {
-                col.push(value);
-            }
-        }
-        Self::new(cols)
+        Self::from_row_iter(rows)
     }
 
     /// Creates a matrix from row-major list of arrays
     pub fn from_arrays<const NUM_COLS: usize>(rows: &[[F; NUM_COLS]]) -> Self {
-        let num_rows = rows.len();
-        let num_cols = NUM_COLS;
-        let mut cols = (0..num_cols)
-            .map(|_| Vec::with_capacity_in(num_rows, GpuAllocator))
-            .collect::<Vec<GpuVec<F>>>();
-        // TODO: parallelise
+        let mut matrix = Self::with_capacity(NUM_COLS, rows.len());
+        matrix.extend_rows(rows);
+        matrix
+    }
+
+    /// Creates a matrix from an iterator of rows. The number of columns is
+    /// taken from the first row and all rows must have the same length.
+    pub fn from_row_iter<R: AsRef<[F]>>(rows: impl IntoIterator<Item = R>) -> Self {
+        let mut rows = rows.into_iter().peekable();
+        let num_cols = rows.peek().map_or(0, |row| row.as_ref().len());
+        let mut matrix = Self::with_capacity(num_cols, 0);
+        matrix.extend_rows(rows);
+        matrix
+    }
+
+    /// Creates a matrix from rows given as a [`TraceLayout`]. Columns are
+    /// ordered as they are declared in the layout.
+    #[cfg(feature = "verifier")]
+    pub fn from_layout_rows<L: TraceLayout<F>>(rows: impl IntoIterator<Item = L>) -> Self {
+        let mut matrix = Self::with_capacity(L::NUM_COLUMNS, 0);
         for row in rows {
-            for (col, value) in cols.iter_mut().zip(row) {
-                col.push(*value);
-            }
+            matrix.push_row(&row.into_values());
+        }
+        matrix
+    }
+
+    /// Creates a matrix with `num_cols` empty columns that can each hold
+    /// `num_rows` values without reallocating
+    pub fn with_capacity(num_cols: usize, num_rows: usize) -> Self {
+        Self::new(
+            (0..num_cols)
+                .map(|_| Vec::with_capacity_in(num_rows, GpuAllocator))
+                .collect(),
+        )
+    }
+
+    /// Appends a row to the bottom of the matrix
+    pub fn push_row(&mut self, row: &[F]) {
+        assert_eq!(self.num_cols(), row.len(), "row has an invalid length");
+        for (col, value) in self.0.iter_mut().zip(row) {
+            col.push(*value);
+        }
+    }
+
+    /// Appends rows to the bottom of the matrix. Columns are grown once
+    /// upfront using the iterator's size hint.
+    pub fn extend_rows<R: AsRef<[F]>>(&mut self, rows: impl IntoIterator<Item = R>) {
+        let rows = rows.into_iter();
+        let (num_rows, _) = rows.size_hint();
+        for col in &mut self.0 {
+            col.reserve(num_rows);
+        }
+        for row in rows {
+            self.push_row(row.as_ref());
         }
-        Self::new(cols)
     }
 
     // TODO: perhaps bring naming of rows and cols in line with
@@ -98,24 +141,82 @@ impl<F: Field> Matrix<F> {
         self.num_rows() == 0
     }
 
-    #[cfg(feature = "gpu")]
+    /// Returns true if all columns are backed by files on disk (see
+    /// [`crate::spill`])
+    #[cfg(feature = "std")]
+    pub fn is_spilled(&self) -> bool {
+        self.0
+            .iter()
+            .all(|col| crate::spill::is_spilled(col.as_ptr()))
+    }
+
+    #[cfg(metal_gpu)]
     fn into_polynomials_gpu(mut self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
         F: GpuField + DomainCoeff<F::FftField>,
         F::FftField: FftField,
     {
-        let mut ifft = GpuIfft::from(domain);
+        use crate::hybrid::default_num_cpu_workers;
+        use crate::hybrid::hybrid_for_each;
+        use crate::utils::gpu_vec_to_vec;
+        use crate::utils::vec_to_gpu_vec;
+        let planners = crate::utils::metal_fft_planners::<F>();
+        hybrid_for_each(
+            &mut self.0,
+            planners,
+            default_num_cpu_workers(planners.len()),
+            |planner, columns| {
+                let mut ifft = planner.plan_ifft(domain);
+                for column in columns {
+                    ifft.encode(column);
+                }
+                ifft.execute();
+            },
+            |column| {
+                // the length doesn't change so the page aligned memory is never reallocated
+                let mut values =
+                    gpu_vec_to_vec(core::mem::replace(column, Vec::new_in(GpuAllocator)));
+                domain.ifft_in_place(&mut values);
+                *column = vec_to_gpu_vec(values);
+            },
+        );
+
+        self
+    }
 
+    #[cfg(wgpu_gpu)]
+    fn into_polynomials_wgpu(mut self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        let Some(planner) = crate::utils::wgpu_planner::<F>(domain.size()) else {
+            return self.into_polynomials_cpu(domain);
+        };
+        let mut ifft = planner.plan_ifft(domain);
         for column in &mut self.0 {
             ifft.encode(column);
         }
-
         ifft.execute();
+        self
+    }
 
+    #[cfg(all(feature = "verifier", feature = "parallel", not(metal_gpu)))]
+    fn into_polynomials_cpu(mut self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        let planner = ministark_gpu::cpu_plan::get_planner();
+        let mut ifft = planner.plan_ifft(domain);
+        for column in &mut self.0 {
+            ifft.encode(column);
+        }
+        ifft.execute();
         self
     }
 
-    #[cfg(not(feature = "gpu"))]
+    #[cfg(all(feature = "verifier", not(feature = "parallel"), not(metal_gpu)))]
     fn into_polynomials_cpu(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
         F: GpuField + DomainCoeff<F::FftField>,
@@ -138,6 +239,7 @@ impl<F: Field> Matrix<F> {
         )
     }
 
+    #[cfg(feature = "verifier")]
     /// Interpolates the columns of the polynomials over the domain
     pub fn into_polynomials(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
@@ -147,12 +249,19 @@ impl<F: Field> Matrix<F> {
         // TODO: using the newtype pattern for type safety would be cool
         // i.e. take as input Matrix<Evaluations> and return Matrix<Polynomials>
         // https://doc.rust-lang.org/book/ch19-04-advanced-types.html
-        #[cfg(not(feature = "gpu"))]
+        #[cfg(feature = "op-count")]
+        for _ in 0..self.num_cols() {
+            crate::op_count::record_fft(domain.size(), true, domain.offset != F::FftField::ONE);
+        }
+        #[cfg(not(any(metal_gpu, wgpu_gpu)))]
         return self.into_polynomials_cpu(domain);
-        #[cfg(feature = "gpu")]
+        #[cfg(wgpu_gpu)]
+        return self.into_polynomials_wgpu(domain);
+        #[cfg(metal_gpu)]
         return self.into_polynomials_gpu(domain);
     }
 
+    #[cfg(feature = "verifier")]
     /// Interpolates the columns of the matrix over the domain
     pub fn interpolate(&self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
@@ -162,7 +271,41 @@ impl<F: Field> Matrix<F> {
         self.clone().into_polynomials(domain)
     }
 
-    #[cfg(not(feature = "gpu"))]
+    #[cfg(wgpu_gpu)]
+    fn into_evaluations_wgpu(mut self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        let Some(planner) = crate::utils::wgpu_planner::<F>(domain.size()) else {
+            return self.into_evaluations_cpu(domain);
+        };
+        let mut fft = planner.plan_fft(domain);
+        for column in &mut self.0 {
+            column.resize(domain.size(), F::zero());
+            fft.encode(column);
+        }
+        fft.execute();
+        self
+    }
+
+    #[cfg(all(feature = "verifier", feature = "parallel", not(metal_gpu)))]
+    fn into_evaluations_cpu(mut self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        let planner = ministark_gpu::cpu_plan::get_planner();
+        let mut fft = planner.plan_fft(domain);
+        for column in &mut self.0 {
+            column.resize(domain.size(), F::zero());
+            fft.encode(column);
+        }
+        fft.execute();
+        self
+    }
+
+    #[cfg(all(feature = "verifier", not(feature = "parallel"), not(metal_gpu)))]
     fn into_evaluations_cpu(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
         F: GpuField + DomainCoeff<F::FftField>,
@@ -189,24 +332,43 @@ impl<F: Field> Matrix<F> {
         )
     }
 
-    #[cfg(feature = "gpu")]
+    #[cfg(metal_gpu)]
     fn into_evaluations_gpu(mut self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
-        F: GpuField,
+        F: GpuField + DomainCoeff<F::FftField>,
         F::FftField: FftField,
     {
-        let mut fft = GpuFft::from(domain);
-
-        for column in &mut self.0 {
-            column.resize(domain.size(), F::zero());
-            fft.encode(column);
-        }
-
-        fft.execute();
+        use crate::hybrid::default_num_cpu_workers;
+        use crate::hybrid::hybrid_for_each;
+        use crate::utils::gpu_vec_to_vec;
+        use crate::utils::vec_to_gpu_vec;
+        let planners = crate::utils::metal_fft_planners::<F>();
+        hybrid_for_each(
+            &mut self.0,
+            planners,
+            default_num_cpu_workers(planners.len()),
+            |planner, columns| {
+                let mut fft = planner.plan_fft(domain);
+                for column in columns {
+                    column.resize(domain.size(), F::zero());
+                    fft.encode(column);
+                }
+                fft.execute();
+            },
+            |column| {
+                // resize while the memory is owned by the page aligned allocator
+                column.resize(domain.size(), F::zero());
+                let mut values =
+                    gpu_vec_to_vec(core::mem::replace(column, Vec::new_in(GpuAllocator)));
+                domain.fft_in_place(&mut values);
+                *column = vec_to_gpu_vec(values);
+            },
+        );
 
         self
     }
 
+    #[cfg(feature = "verifier")]
     /// Evaluates the columns of the matrix
     pub fn into_evaluations(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
@@ -216,12 +378,19 @@ impl<F: Field> Matrix<F> {
         // TODO: using the newtype pattern for type safety would be cool
         // i.e. take as input Matrix<Polynomials> and return Matrix<Evaluations>
         // https://doc.rust-lang.org/book/ch19-04-advanced-types.html
-        #[cfg(not(feature = "gpu"))]
+        #[cfg(feature = "op-count")]
+        for _ in 0..self.num_cols() {
+            crate::op_count::record_fft(domain.size(), false, domain.offset != F::FftField::ONE);
+        }
+        #[cfg(not(any(metal_gpu, wgpu_gpu)))]
         return self.into_evaluations_cpu(domain);
-        #[cfg(feature = "gpu")]
+        #[cfg(wgpu_gpu)]
+        return self.into_evaluations_wgpu(domain);
+        #[cfg(metal_gpu)]
         return self.into_evaluations_gpu(domain);
     }
 
+    #[cfg(feature = "verifier")]
     pub fn into_bit_reversed_evaluations(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
         F: GpuField + DomainCoeff<F::FftField>,
@@ -233,6 +402,104 @@ impl<F: Field> Matrix<F> {
         evaluations
     }
 
+    /// Evaluates the columns of the matrix in bit-reversed order. Gives the
+    /// same result as [`Self::into_bit_reversed_evaluations`] but the FFT
+    /// outputs bit-reversed evaluations directly so values are never permuted.
+    #[cfg(feature = "verifier")]
+    pub fn into_out_of_order_evaluations(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        #[cfg(feature = "op-count")]
+        for _ in 0..self.num_cols() {
+            crate::op_count::record_fft(domain.size(), false, domain.offset != F::FftField::ONE);
+        }
+        #[cfg(not(metal_gpu))]
+        return self.into_out_of_order_evaluations_cpu(domain);
+        #[cfg(metal_gpu)]
+        return self.into_out_of_order_evaluations_gpu(domain);
+    }
+
+    #[cfg(all(feature = "verifier", feature = "parallel", not(metal_gpu)))]
+    fn into_out_of_order_evaluations_cpu(
+        mut self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        let planner = ministark_gpu::cpu_plan::get_planner();
+        let mut fft = planner.plan_fft(domain);
+        for column in &mut self.0 {
+            column.resize(domain.size(), F::zero());
+            fft.encode_out_of_order(column);
+        }
+        fft.execute();
+        self
+    }
+
+    #[cfg(all(feature = "verifier", not(feature = "parallel"), not(metal_gpu)))]
+    fn into_out_of_order_evaluations_cpu(
+        mut self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        ark_std::cfg_iter_mut!(self.0).for_each(|column| {
+            column.resize(domain.size(), F::zero());
+            out_of_order_fft_in_place(column, &domain);
+        });
+        self
+    }
+
+    #[cfg(metal_gpu)]
+    fn into_out_of_order_evaluations_gpu(
+        mut self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+    ) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        use crate::hybrid::default_num_cpu_workers;
+        use crate::hybrid::hybrid_for_each;
+        let planners = crate::utils::metal_fft_planners::<F>();
+        hybrid_for_each(
+            &mut self.0,
+            planners,
+            default_num_cpu_workers(planners.len()),
+            |planner, columns| {
+                let mut fft = planner.plan_fft(domain);
+                for column in columns {
+                    column.resize(domain.size(), F::zero());
+                    fft.encode_out_of_order(column);
+                }
+                fft.execute();
+            },
+            |column| {
+                column.resize(domain.size(), F::zero());
+                out_of_order_fft_in_place(column, &domain);
+            },
+        );
+        self
+    }
+
+    /// Evaluates the columns of the matrix in bit-reversed order (see
+    /// [`Self::into_out_of_order_evaluations`])
+    #[cfg(feature = "verifier")]
+    pub fn out_of_order_evaluate(&self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        self.clone().into_out_of_order_evaluations(domain)
+    }
+
+    #[cfg(feature = "verifier")]
     /// Evaluates the columns of the matrix
     pub fn evaluate(&self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
@@ -242,6 +509,7 @@ impl<F: Field> Matrix<F> {
         self.clone().into_evaluations(domain)
     }
 
+    #[cfg(feature = "verifier")]
     pub fn bit_reversed_evaluate(&self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
         F: GpuField + DomainCoeff<F::FftField>,
@@ -267,13 +535,9 @@ impl<F: Field> Matrix<F> {
             .enumerate()
             .for_each(|(chunk_offset, chunk)| {
                 let offset = chunk_size * chunk_offset;
-
-                let mut row_buffer = vec![F::zero(); self.num_cols()];
-
-                for (i, row_hash) in chunk.iter_mut().enumerate() {
-                    self.read_row(offset + i, &mut row_buffer);
-                    *row_hash = H::hash_elements(row_buffer.iter().copied());
-                }
+                self.for_each_row(offset..offset + chunk.len(), |i, row| {
+                    chunk[i - offset] = H::hash_elements(row.iter().copied());
+                });
             });
 
         row_hashes
@@ -300,9 +564,121 @@ impl<F: Field> Matrix<F> {
     }
 
     pub fn rows(&self) -> Vec<Vec<F>> {
-        (0..self.num_rows())
-            .map(|row| self.get_row(row).unwrap())
-            .collect()
+        self.row_iter().collect()
+    }
+
+    /// Returns an iterator over the rows of the matrix
+    pub fn row_iter(&self) -> impl ExactSizeIterator<Item = Vec<F>> + '_ {
+        (0..self.num_rows()).map(|row| self.0.iter().map(|col| col[row]).collect())
+    }
+
+    /// Calls `f` with the index and values of each row in `rows` in order.
+    /// Rows are copied into a row-major buffer a block at a time so each
+    /// column is read linearly rather than one value per row.
+    pub fn for_each_row(&self, rows: Range<usize>, mut f: impl FnMut(usize, &[F])) {
+        let num_cols = self.num_cols();
+        let mut buffer = vec![F::zero(); ROW_BLOCK_SIZE * num_cols];
+        let mut start = rows.start;
+        while start < rows.end {
+            let end = rows.end.min(start + ROW_BLOCK_SIZE);
+            let block = &mut buffer[..(end - start) * num_cols];
+            self.write_row_major(start, block);
+            for (i, row) in RowMajorView::new(block, num_cols).rows().enumerate() {
+                f(start + i, row);
+            }
+            start = end;
+        }
+    }
+
+    /// Writes consecutive rows starting at row `start` into `dst` in
+    /// row-major order. The number of rows written is determined by the
+    /// length of `dst`.
+    pub fn write_row_major(&self, start: usize, dst: &mut [F]) {
+        let num_cols = self.num_cols();
+        let num_rows = dst.len() / num_cols;
+        for (col, column) in self.0.iter().enumerate() {
+            for (row, value) in column[start..start + num_rows].iter().enumerate() {
+                dst[row * num_cols + col] = *value;
+            }
+        }
+    }
+
+    /// Copies the matrix into a buffer in row-major order (see
+    /// [`RowMajorView`]). Transposed on the GPU when available.
+    pub fn to_row_major(&self) -> GpuVec<F>
+    where
+        F: GpuField,
+    {
+        #[cfg(not(metal_gpu))]
+        return self.to_row_major_cpu();
+        #[cfg(metal_gpu)]
+        return self
+            .to_row_major_gpu()
+            .unwrap_or_else(|| self.to_row_major_cpu());
+    }
+
+    fn to_row_major_cpu(&self) -> GpuVec<F> {
+        let num_cols = self.num_cols();
+        let len = self.num_rows() * num_cols;
+        let mut values = Vec::with_capacity_in(len, GpuAllocator);
+        values.resize(len, F::zero());
+        if num_cols != 0 {
+            ark_std::cfg_chunks_mut!(values, ROW_BLOCK_SIZE * num_cols)
+                .enumerate()
+                .for_each(|(i, block)| self.write_row_major(i * ROW_BLOCK_SIZE, block));
+        }
+        values
+    }
+
+    /// Returns [None] if the Metal library doesn't have a transpose kernel for
+    /// the field
+    #[cfg(metal_gpu)]
+    fn to_row_major_gpu(&self) -> Option<GpuVec<F>>
+    where
+        F: GpuField,
+    {
+        let num_rows = self.num_rows();
+        let num_cols = self.num_cols();
+        let mut values = Vec::with_capacity_in(num_rows * num_cols, GpuAllocator);
+        values.resize(num_rows * num_cols, F::zero());
+        if num_cols != 0 {
+            let planner = &get_planners()[0];
+            let transposer = TransposeColumnStage::<F>::new(&planner.library, num_rows).ok()?;
+            let command_queue = planner.command_queue();
+            let device = command_queue.device();
+            let command_buffer = command_queue.new_command_buffer();
+            let values_buffer = buffer_mut_no_copy(device, &mut values);
+            for (col, column) in self.0.iter().enumerate() {
+                let column_buffer = buffer_no_copy(device, column);
+                transposer.encode(
+                    command_buffer,
+                    &values_buffer,
+                    &column_buffer,
+                    num_cols,
+                    col,
+                );
+            }
+            command_buffer.commit();
+            command_buffer.wait_until_completed();
+        }
+        Some(values)
+    }
+
+    /// Returns the transpose of the matrix. Rows of the matrix are the
+    /// columns of the transpose.
+    pub fn transpose(&self) -> Self
+    where
+        F: GpuField,
+    {
+        let num_cols = self.num_cols();
+        if num_cols == 0 {
+            return Self::new(Vec::new());
+        }
+        let values = self.to_row_major();
+        let columns = ark_std::cfg_chunks!(values, num_cols)
+            .map(|row| row.to_vec_in(GpuAllocator))
+            .collect();
+        Self::new(columns)
     }
 
     pub fn column_degrees(&self) -> Vec<usize> {
@@ -319,64 +695,53 @@ impl<F: Field> Matrix<F> {
             .collect()
     }
 
-    #[cfg(not(feature = "gpu"))]
+    #[cfg(not(metal_gpu))]
     pub fn sum_columns_cpu(&self) -> Self {
-        let n = self.num_rows();
-        let mut accumulator = Vec::with_capacity_in(n, GpuAllocator);
-        accumulator.resize(n, F::zero());
-
-        if self.num_cols() != 0 {
-            #[cfg(not(feature = "parallel"))]
-            let chunk_size = accumulator.len();
-            #[cfg(feature = "parallel")]
-            let chunk_size = core::cmp::max(
-                accumulator.len() / rayon::current_num_threads().next_power_of_two(),
-                1024,
-            );
-
-            ark_std::cfg_chunks_mut!(accumulator, chunk_size)
-                .enumerate()
-                .for_each(|(chunk_offset, chunk)| {
-                    let offset = chunk_size * chunk_offset;
-                    for column in &self.0 {
-                        for i in 0..chunk_size {
-                            chunk[i] += column[offset + i];
-                        }
-                    }
-                });
-        }
-
-        Self::new(vec![accumulator])
+        Self::new(vec![MatrixView::from(self).sum_columns()])
     }
 
     pub fn bit_reverse_rows(&mut self) {
         ark_std::cfg_iter_mut!(self.0).for_each(|col| bit_reverse(col));
     }
 
-    #[cfg(feature = "gpu")]
+    #[cfg(metal_gpu)]
     pub fn sum_columns_gpu(&self) -> Self
     where
         F: GpuField,
     {
         let n = self.num_rows();
-        // TODO: add into_sum_columns and prevent having to allocate new memory
-        let mut accumulator = Vec::with_capacity_in(n, GpuAllocator);
-        accumulator.resize(n, F::zero());
+        let mut columns = self.0.iter().collect::<Vec<&GpuVec<F>>>();
+        // each device sums a batch of columns and the results are merged on the host
+        let mut partial_sums = split_across_planners(&mut columns, |planner, columns| {
+            // TODO: add into_sum_columns and prevent having to allocate new memory
+            let mut accumulator = Vec::with_capacity_in(n, GpuAllocator);
+            accumulator.resize(n, F::zero());
 
-        if self.num_cols() != 0 {
-            // TODO: could improve
-            let library = &get_planner().library;
-            let command_queue = &get_planner().command_queue;
-            let device = command_queue.device();
-            let command_buffer = command_queue.new_command_buffer();
-            let accumulator_buffer = buffer_mut_no_copy(device, &mut accumulator);
-            let adder = AddAssignStage::<F>::new(library, n);
-            for column in &self.0 {
-                let column_buffer = buffer_no_copy(command_queue.device(), column);
-                adder.encode(command_buffer, &accumulator_buffer, &column_buffer, 0);
+            if !columns.is_empty() {
+                // TODO: could improve
+                let library = &planner.library;
+                let command_queue = planner.command_queue();
+                let device = command_queue.device();
+                let command_buffer = command_queue.new_command_buffer();
+                let accumulator_buffer = buffer_mut_no_copy(device, &mut accumulator);
+                let adder = AddAssignStage::<F>::new(library, n);
+                for column in columns {
+                    let column_buffer = buffer_no_copy(command_queue.device(), column);
+                    adder.encode(command_buffer, &accumulator_buffer, &column_buffer, 0);
+                }
+                command_buffer.commit();
+                command_buffer.wait_until_completed();
+            }
+
+            accumulator
+        })
+        .into_iter();
+
+        let mut accumulator = partial_sums.next().unwrap();
+        for partial_sum in partial_sums {
+            for (acc, v) in accumulator.iter_mut().zip(partial_sum) {
+                *acc += v;
             }
-            command_buffer.commit();
-            command_buffer.wait_until_completed();
         }
 
         Self::new(vec![accumulator])
@@ -387,13 +752,53 @@ impl<F: Field> Matrix<F> {
     where
         F: GpuField,
     {
-        #[cfg(not(feature = "gpu"))]
+        #[cfg(not(metal_gpu))]
         return self.sum_columns_cpu();
-        #[cfg(feature = "gpu")]
+        #[cfg(metal_gpu)]
         return self.sum_columns_gpu();
     }
 }
 
+/// Number of rows copied at a time when reading rows of a column-major matrix
+const ROW_BLOCK_SIZE: usize = 64;
+
+/// Borrowed matrix stored as a flat buffer in row-major order (see
+/// [`Matrix::to_row_major`]). Rows are contiguous so iterating over them reads
+/// memory linearly.
+#[derive(Debug, Clone, Copy)]
+pub struct RowMajorView<'a, F> {
+    values: &'a [F],
+    num_cols: usize,
+}
+
+impl<'a, F> RowMajorView<'a, F> {
+    /// # Panics
+    ///
+    /// Panics if `num_cols` is zero or doesn't divide the number of values
+    pub fn new(values: &'a [F], num_cols: usize) -> Self {
+        assert!(num_cols != 0, "a row-major view needs at least one column");
+        assert_eq!(values.len() % num_cols, 0, "values don't fill the last row");
+        Self { values, num_cols }
+    }
+
+    pub const fn num_cols(&self) -> usize {
+        self.num_cols
+    }
+
+    pub const fn num_rows(&self) -> usize {
+        self.values.len() / self.num_cols
+    }
+
+    pub fn row(&self, row: usize) -> &'a [F] {
+        &self.values[row * self.num_cols..(row + 1) * self.num_cols]
+    }
+
+    /// Returns an iterator over the rows
+    pub fn rows(&self) -> ChunksExact<'a, F> {
+        self.values.chunks_exact(self.num_cols)
+    }
+}
+
 impl<F: Field> Clone for Matrix<F> {
     fn clone(&self) -> Self {
         Self(
@@ -428,6 +833,7 @@ impl<F: Field> Deref for Matrix<F> {
     }
 }
 
+#[cfg(feature = "verifier")]
 impl<F: GpuField, C: ExecutionTraceColumn> Index<C> for Matrix<F> {
     type Output = GpuVec<F>;
 
@@ -436,6 +842,7 @@ impl<F: GpuField, C: ExecutionTraceColumn> Index<C> for Matrix<F> {
     }
 }
 
+#[cfg(feature = "verifier")]
 impl<F: GpuField, C: ExecutionTraceColumn> IndexMut<C> for Matrix<F> {
     fn index_mut(&mut self, col: C) -> &mut Self::Output {
         &mut self.0[col.index()]
@@ -453,3 +860,263 @@ impl<F: Field> TryFrom<Matrix<F>> for GpuVec<F> {
         }
     }
 }
+
+/// Borrowed columns laid side by side. Columns can come from several
+/// matrices and be restricted to a range of rows.
+///
+/// Unlike [`Matrix::join`] and [`Matrix::append`] joining and slicing views
+/// never moves or copies column data so views can be assembled from whatever
+/// matrices hold the columns (e.g. a trace and its preprocessed columns).
+#[derive(Clone)]
+pub struct MatrixView<'a, F> {
+    columns: Vec<&'a [F]>,
+}
+
+impl<'a, F: Field> MatrixView<'a, F> {
+    /// # Panics
+    ///
+    /// Panics if the columns have different lengths
+    pub fn new(columns: Vec<&'a [F]>) -> Self {
+        let mut view = Self {
+            columns: Vec::with_capacity(columns.len()),
+        };
+        for column in columns {
+            view.push(column);
+        }
+        view
+    }
+
+    /// Appends a column after the existing columns
+    ///
+    /// # Panics
+    ///
+    /// Panics if the column has a different length than the existing columns
+    pub fn push(&mut self, column: &'a [F]) {
+        if let Some(first) = self.columns.first() {
+            assert_eq!(
+                first.len(),
+                column.len(),
+                "columns must have the same length"
+            );
+        }
+        self.columns.push(column);
+    }
+
+    /// Appends the columns of `other` after the existing columns
+    pub fn append(&mut self, other: Self) {
+        for column in other.columns {
+            self.push(column);
+        }
+    }
+
+    pub fn join(views: impl IntoIterator<Item = Self>) -> Self {
+        let mut joined = Self::new(Vec::new());
+        for view in views {
+            joined.append(view);
+        }
+        joined
+    }
+
+    /// Returns a view of `rows` of every column
+    pub fn slice_rows(&self, rows: Range<usize>) -> Self {
+        let columns = self.columns.iter().map(|column| &column[rows.clone()]);
+        Self::new(columns.collect())
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.columns.first().map_or(0, |column| column.len())
+    }
+
+    pub fn column(&self, col: usize) -> Option<&'a [F]> {
+        self.columns.get(col).copied()
+    }
+
+    pub fn get_row(&self, row: usize) -> Option<Vec<F>> {
+        if row < self.num_rows() {
+            Some(self.columns.iter().map(|col| col[row]).collect())
+        } else {
+            None
+        }
+    }
+
+    /// Sums the columns into a single column
+    pub fn sum_columns(&self) -> GpuVec<F> {
+        let n = self.num_rows();
+        let mut accumulator = Vec::with_capacity_in(n, GpuAllocator);
+        accumulator.resize(n, F::zero());
+
+        #[cfg(not(feature = "parallel"))]
+        let chunk_size = accumulator.len().max(1);
+        #[cfg(feature = "parallel")]
+        let chunk_size = core::cmp::max(
+            accumulator.len() / rayon::current_num_threads().next_power_of_two(),
+            1024,
+        );
+
+        ark_std::cfg_chunks_mut!(accumulator, chunk_size)
+            .enumerate()
+            .for_each(|(chunk_offset, chunk)| {
+                let offset = chunk_size * chunk_offset;
+                for column in &self.columns {
+                    for (acc, value) in chunk.iter_mut().zip(&column[offset..]) {
+                        *acc += value;
+                    }
+                }
+            });
+
+        accumulator
+    }
+
+    /// Copies the columns into a matrix
+    pub fn to_matrix(&self) -> Matrix<F> {
+        Matrix::new(
+            self.columns
+                .iter()
+                .map(|col| col.to_vec_in(GpuAllocator))
+                .collect(),
+        )
+    }
+}
+
+impl<'a, F> Deref for MatrixView<'a, F> {
+    type Target = [&'a [F]];
+
+    fn deref(&self) -> &Self::Target {
+        &self.columns
+    }
+}
+
+impl<'a, F: Field> From<&'a Matrix<F>> for MatrixView<'a, F> {
+    fn from(matrix: &'a Matrix<F>) -> Self {
+        Self::new(matrix.iter().map(Vec::as_slice).collect())
+    }
+}
+
+impl<'a, F: Field> From<&SegmentedMatrix<'a, F>> for MatrixView<'a, F> {
+    fn from(matrix: &SegmentedMatrix<'a, F>) -> Self {
+        Self::new(matrix.columns().collect())
+    }
+}
+
+/// A matrix made of borrowed column groups (segments) laid side by side.
+///
+/// Joining segments only records where each one starts so no column data is
+/// copied. Columns are addressed by their logical index across all segments.
+pub struct SegmentedMatrix<'a, F> {
+    segments: Vec<&'a Matrix<F>>,
+    // logical index of the first column of each segment
+    offsets: Vec<usize>,
+    num_cols: usize,
+}
+
+impl<'a, F: Field> SegmentedMatrix<'a, F> {
+    pub const fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+            offsets: Vec::new(),
+            num_cols: 0,
+        }
+    }
+
+    pub fn join(segments: impl IntoIterator<Item = &'a Matrix<F>>) -> Self {
+        let mut matrix = Self::new();
+        for segment in segments {
+            matrix.push(segment);
+        }
+        matrix
+    }
+
+    /// Appends the columns of `segment` after the existing columns
+    pub fn push(&mut self, segment: &'a Matrix<F>) {
+        if let Some(first) = self.segments.iter().find(|s| s.num_cols() != 0) {
+            if segment.num_cols() != 0 {
+                assert_eq!(
+                    first.num_rows(),
+                    segment.num_rows(),
+                    "segments must have the same number of rows"
+                );
+            }
+        }
+        self.offsets.push(self.num_cols);
+        self.num_cols += segment.num_cols();
+        self.segments.push(segment);
+    }
+
+    pub fn segments(&self) -> &[&'a Matrix<F>] {
+        &self.segments
+    }
+
+    pub const fn num_cols(&self) -> usize {
+        self.num_cols
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.segments
+            .iter()
+            .find(|s| s.num_cols() != 0)
+            .map_or(0, |s| Matrix::num_rows(s))
+    }
+
+    /// Returns the column at logical index `col`
+    pub fn column(&self, col: usize) -> Option<&'a [F]> {
+        if col >= self.num_cols {
+            return None;
+        }
+        // empty segments share their offset with the segment that follows
+        let segment = self.offsets.partition_point(|&offset| offset <= col) - 1;
+        Some(&self.segments[segment].0[col - self.offsets[segment]])
+    }
+
+    /// Returns an iterator over the columns of all segments in order
+    pub fn columns(&self) -> impl Iterator<Item = &'a [F]> + '_ {
+        self.segments
+            .iter()
+            .flat_map(|segment| segment.iter().map(Vec::as_slice))
+    }
+
+    pub fn evaluate_at<T: Field + for<'b> Add<&'b F, Output = T>>(&self, x: T) -> Vec<T> {
+        self.columns().map(|col| horner_evaluate(col, &x)).collect()
+    }
+
+    pub fn get_row(&self, row: usize) -> Option<Vec<F>> {
+        if row < self.num_rows() {
+            Some(self.columns().map(|col| col[row]).collect())
+        } else {
+            None
+        }
+    }
+
+    /// Copies the segments into a single contiguous matrix
+    pub fn to_matrix(&self) -> Matrix<F> {
+        Matrix::new(
+            self.columns()
+                .map(|col| col.to_vec_in(GpuAllocator))
+                .collect(),
+        )
+    }
+}
+
+impl<F: Field> Default for SegmentedMatrix<'_, F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, F: Field> From<&'a Matrix<F>> for SegmentedMatrix<'a, F> {
+    fn from(matrix: &'a Matrix<F>) -> Self {
+        Self::join([matrix])
+    }
+}
+
+impl<F: Field> Index<usize> for SegmentedMatrix<'_, F> {
+    type Output = [F];
+
+    fn index(&self, col: usize) -> &Self::Output {
+        self.column(col)
+            .unwrap_or_else(|| panic!("column is {col} but there are {} columns", self.num_cols))
+    }
+}
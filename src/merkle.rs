@@ -6,13 +6,13 @@ use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use core::fmt::Debug;
+use core::iter::zip;
+use core::marker::PhantomData;
+use rand::RngCore;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use snafu::Snafu;
-use std::collections::VecDeque;
-use std::fmt::Debug;
-use std::iter::zip;
-use std::marker::PhantomData;
 
 /// Merkle tree error
 #[derive(Debug, Snafu)]
@@ -21,8 +21,12 @@ pub enum Error {
     TooFewLeaves { min: usize, actual: usize },
     #[snafu(display("number of leaves must be a power of two, but `{n}` were provided"))]
     NumberOfLeavesNotPowerOfTwo { n: usize },
+    #[snafu(display("number of leaves must be a power of `{arity}`, but `{n}` were provided"))]
+    NumberOfLeavesNotPowerOfArity { n: usize, arity: usize },
     #[snafu(display("leaf index `{i}` cannot exceed the number of leaves (`{n}`)"))]
     LeafIndexOutOfBounds { i: usize, n: usize },
+    #[snafu(display("cap height `{cap_height}` must be less than the tree height `{height}`"))]
+    InvalidCapHeight { cap_height: u32, height: u32 },
     #[snafu(display("proof is invalid"))]
     InvalidProof,
 }
@@ -34,7 +38,10 @@ pub trait MerkleTree: Sized + Send + Sync + Clone {
     /// Returns the root of the merkle tree
     fn root(&self) -> Self::Root;
 
-    /// Generates a merkle proof
+    /// Generates a merkle proof for one or more leaves.
+    ///
+    /// Paths for multiple leaves are batched into a single proof where nodes
+    /// shared between paths are only included once.
     ///
     /// # Errors
     ///
@@ -58,15 +65,57 @@ pub trait MerkleTreeConfig: Send + Sync + Sized + 'static {
     type Digest: Digest;
     type Leaf: CanonicalDeserialize + CanonicalSerialize + Clone + Send + Sync + Sized + 'static;
 
+    /// Number of children of each internal node. Must be a power of two.
+    ///
+    /// Higher arity trees are shallower so proofs contain fewer levels. This
+    /// is a good fit for wide hash functions (e.g. Poseidon) that can absorb
+    /// many digests in a single permutation.
+    const ARITY: usize = 2;
+
     fn hash_leaves(depth: u32, l0: &Self::Leaf, l1: &Self::Leaf) -> Self::Digest;
 
     fn hash_nodes(depth: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest;
 
+    /// Hashes [`Self::ARITY`] sibling leaves into their parent node at `depth`.
+    ///
+    /// The default hashes the leaves as a binary subtree using
+    /// [`Self::hash_leaves`] and [`Self::hash_nodes`]. Configs with
+    /// `ARITY > 2` should override this with a single wide hash.
+    fn hash_leaf_group(depth: u32, leaves: &[Self::Leaf]) -> Self::Digest {
+        debug_assert_eq!(leaves.len(), Self::ARITY);
+        let nodes = leaves
+            .chunks_exact(2)
+            .map(|pair| Self::hash_leaves(depth, &pair[0], &pair[1]))
+            .collect::<Vec<_>>();
+        Self::hash_node_group(depth, &nodes)
+    }
+
+    /// Hashes sibling nodes into their parent node at `depth`. Takes
+    /// [`Self::ARITY`] nodes, or fewer when called by the default
+    /// [`Self::hash_leaf_group`].
+    ///
+    /// The default hashes the nodes as a binary subtree using
+    /// [`Self::hash_nodes`]. Configs with `ARITY > 2` should override this
+    /// with a single wide hash.
+    fn hash_node_group(depth: u32, nodes: &[Self::Digest]) -> Self::Digest {
+        let mut nodes = nodes.to_vec();
+        while nodes.len() > 1 {
+            nodes = nodes
+                .chunks_exact(2)
+                .map(|pair| Self::hash_nodes(depth, &pair[0], &pair[1]))
+                .collect();
+        }
+        nodes.pop().unwrap()
+    }
+
     fn security_level_bits() -> u32;
 }
 
 /// Merkle View contains information needed to verify multiple Merkle paths.
 ///
+/// Internal nodes that can be computed from the opened leaves are omitted so
+/// opening many leaves costs far less than opening each leaf separately.
+///
 /// Inspired by Starkware's Solidity verifier
 /// <https://etherscan.io/address/0xe9664D230490d5A515ef7Ef30033d8075a8D0E24#code#F24#L1>
 #[derive(Debug, Clone, PartialEq, Eq, CanonicalDeserialize, CanonicalSerialize)]
@@ -80,6 +129,40 @@ pub struct MerkleView<
     pub height: u32,
 }
 
+/// Merkle cap i.e. all nodes at a chosen depth of a merkle tree. A cap of
+/// height `0` only contains the root.
+///
+/// Committing to a cap rather than the root makes the commitment larger but
+/// every path in a proof is `cap_height` levels shorter. This is a good
+/// tradeoff when many leaves are opened.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalDeserialize, CanonicalSerialize)]
+pub struct MerkleCap<D: CanonicalDeserialize + CanonicalSerialize + Clone>(pub Vec<D>);
+
+/// Strategy for padding leaves to a number the tree supports (see
+/// [`MerkleTreeImpl::new_padded`]). Padding leaves are part of the tree so
+/// provers and verifiers must agree on the strategy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Padding<L> {
+    /// Pads with the default leaf e.g. the zero digest or an empty row
+    Zero,
+    /// Pads with copies of the last leaf
+    RepeatLast,
+    /// Pads with copies of the given leaf
+    Leaf(L),
+}
+
+impl<L: Clone + Default> Padding<L> {
+    /// Appends padding leaves until there are `n` leaves
+    fn pad(self, leaves: &mut Vec<L>, n: usize) {
+        let pad_leaf = match self {
+            Self::Zero => L::default(),
+            Self::RepeatLast => leaves.last().cloned().unwrap_or_default(),
+            Self::Leaf(leaf) => leaf,
+        };
+        leaves.resize(n, pad_leaf);
+    }
+}
+
 /// Merkle tree implemented as a full power-of-two arity tree.
 ///
 /// ```text
@@ -89,6 +172,9 @@ pub struct MerkleView<
 ///  / \     / \
 /// +   +   +   +  <- leaves
 /// ```
+///
+/// Nodes are stored level by level starting with the root at index `1`. For
+/// binary trees the children of node `i` are `2i` and `2i + 1`.
 pub struct MerkleTreeImpl<C: MerkleTreeConfig> {
     pub nodes: Vec<C::Digest>,
     pub leaves: Vec<C::Leaf>,
@@ -109,21 +195,40 @@ impl<C: MerkleTreeConfig> MerkleTreeImpl<C> {
     /// This function will return an error if:
     /// * there are less than two leaves
     /// * the number of leaves is not a power of two
+    /// * the number of leaves is not a power of [`MerkleTreeConfig::ARITY`]
     pub fn new(leaves: Vec<C::Leaf>) -> Result<Self, Error> {
-        const MIN_LEAVES: usize = 2;
+        check_num_leaves::<C>(leaves.len())?;
+        let nodes = if C::ARITY == 2 {
+            build_merkle_nodes::<C>(&leaves)
+        } else {
+            build_kary_merkle_nodes::<C>(&leaves)
+        };
+        Ok(Self { nodes, leaves })
+    }
 
-        let n = leaves.len();
-        if n < MIN_LEAVES {
-            return Err(Error::TooFewLeaves {
-                min: MIN_LEAVES,
-                actual: n,
-            });
-        } else if !n.is_power_of_two() {
-            return Err(Error::NumberOfLeavesNotPowerOfTwo { n });
+    /// Builds a tree from any non-zero number of leaves. Leaves are padded
+    /// according to `padding` up to the next power of
+    /// [`MerkleTreeConfig::ARITY`] (and at least `ARITY` leaves).
+    ///
+    /// Indices of the original leaves are unchanged so proofs for them are
+    /// generated and verified as normal.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no leaves
+    pub fn new_padded(mut leaves: Vec<C::Leaf>, padding: Padding<C::Leaf>) -> Result<Self, Error>
+    where
+        C::Leaf: Default,
+    {
+        if leaves.is_empty() {
+            return Err(Error::TooFewLeaves { min: 1, actual: 0 });
         }
-
-        let nodes = build_merkle_nodes::<C>(&leaves);
-        Ok(Self { nodes, leaves })
+        let mut n = C::ARITY;
+        while n < leaves.len() {
+            n *= C::ARITY;
+        }
+        padding.pad(&mut leaves, n);
+        Self::new(leaves)
     }
 
     /// Returns the height of the merkle tree
@@ -134,19 +239,49 @@ impl<C: MerkleTreeConfig> MerkleTreeImpl<C> {
     /// +   +
     /// ```
     fn height(&self) -> u32 {
-        self.leaves.len().ilog2()
+        self.leaves.len().ilog2() / C::ARITY.ilog2()
     }
-}
 
-impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
-    type Proof = MerkleView<C::Digest, C::Leaf>;
-    type Root = C::Digest;
+    /// Returns the nodes at `depth` (the root has depth `0`)
+    fn level(&self, depth: u32) -> &[C::Digest] {
+        let offset = level_offset(C::ARITY, depth);
+        &self.nodes[offset..offset + C::ARITY.pow(depth)]
+    }
 
-    fn root(&self) -> C::Digest {
-        self.nodes[1].clone()
+    /// Returns the merkle cap at `cap_height` i.e. the `ARITY^cap_height`
+    /// nodes at that depth.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `cap_height` is not less than the tree height.
+    pub fn cap(&self, cap_height: u32) -> Result<MerkleCap<C::Digest>, Error> {
+        self.check_cap_height(cap_height)?;
+        Ok(MerkleCap(self.level(cap_height).to_vec()))
     }
 
-    fn prove(&self, indices: &[usize]) -> Result<MerkleView<C::Digest, C::Leaf>, Error> {
+    fn check_cap_height(&self, cap_height: u32) -> Result<(), Error> {
+        let height = self.height();
+        if cap_height < height {
+            Ok(())
+        } else {
+            Err(Error::InvalidCapHeight { cap_height, height })
+        }
+    }
+
+    /// Generates a merkle proof for one or more leaves that is verified
+    /// against the merkle cap at `cap_height`. Paths stop at the cap so nodes
+    /// above the cap are never included in the proof.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a leaf index is out of bounds or `cap_height` is
+    /// not less than the tree height.
+    pub fn prove_to_cap(
+        &self,
+        indices: &[usize],
+        cap_height: u32,
+    ) -> Result<MerkleView<C::Digest, C::Leaf>, Error> {
+        self.check_cap_height(cap_height)?;
         let num_leaves = self.leaves.len();
         for &i in indices {
             if i >= num_leaves {
@@ -158,45 +293,35 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
         indices.sort_unstable();
         indices.dedup();
 
+        // handle leaves and specify the internal node positions
         let mut initial_leaves = Vec::new();
         let mut sibling_leaves = Vec::new();
-
-        // handle leaves and specify the internal node indices
-        let mut node_queue = VecDeque::new();
-        let mut leaf_queue = VecDeque::from_iter(indices);
-        while let Some(index) = leaf_queue.pop_front() {
-            initial_leaves.push(self.leaves[index].clone());
-            node_queue.push_back((num_leaves + index) >> 1);
-
-            if let Some(&next_index) = leaf_queue.front() {
-                let are_siblings = index ^ 1 == next_index;
-                if are_siblings {
-                    initial_leaves.push(self.leaves[next_index].clone());
-                    leaf_queue.pop_front();
-                    continue;
-                }
-            }
-
-            sibling_leaves.push(self.leaves[index ^ 1].clone());
-        }
+        let positions = open_siblings(
+            C::ARITY,
+            |i| self.leaves[i].clone(),
+            &indices,
+            Some(&mut initial_leaves),
+            &mut sibling_leaves,
+        );
 
         // handle internal nodes
-        let mut nodes = Vec::new();
-        while let Some(index) = node_queue.pop_front() {
-            if index > 2 {
-                node_queue.push_back(index >> 1);
-            }
-
-            if let Some(next_index) = node_queue.front() {
-                let are_siblings = index ^ 1 == *next_index;
-                if are_siblings {
-                    node_queue.pop_front();
-                    continue;
-                }
+        let nodes = open_nodes(C::ARITY, &self.nodes, positions, self.height(), cap_height);
+
+        #[cfg(feature = "chaos")]
+        let nodes = {
+            use crate::chaos::Fault;
+            let mut nodes = nodes;
+            let fault = if nodes.is_empty() {
+                None
+            } else {
+                crate::chaos::fire(Fault::CorruptMerkleNode)
+            };
+            if let Some(v) = fault {
+                let i = usize::try_from(v % nodes.len() as u64).unwrap();
+                nodes[i] = C::Digest::default();
             }
-
-            nodes.push(self.nodes[index ^ 1].clone());
-        }
+            nodes
+        };
 
         Ok(MerkleView {
             nodes,
@@ -206,13 +331,27 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
         })
     }
 
-    fn verify(
-        root: &C::Digest,
+    /// Verifies a merkle proof against a merkle cap
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the proof fails verification.
+    pub fn verify_against_cap(
+        cap: &MerkleCap<C::Digest>,
         proof: MerkleView<C::Digest, C::Leaf>,
         indices: &[usize],
     ) -> Result<(), Error> {
+        let arity = C::ARITY;
         let height = proof.height;
-        let num_leaves = 1 << height;
+        let cap_size = cap.0.len();
+        if !cap_size.is_power_of_two() || cap_size.ilog2() % arity.ilog2() != 0 {
+            return Err(Error::InvalidProof);
+        }
+        let cap_height = cap_size.ilog2() / arity.ilog2();
+        if cap_height >= height {
+            return Err(Error::InvalidProof);
+        }
+        let num_leaves = arity.checked_pow(height).ok_or(Error::InvalidProof)?;
         for &i in indices {
             if i >= num_leaves {
                 return Err(Error::LeafIndexOutOfBounds { i, n: num_leaves });
@@ -222,100 +361,654 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
         let mut indices = indices.to_vec();
         indices.sort_unstable();
         indices.dedup();
+        if indices.len() != proof.initial_leaves.len() {
+            return Err(Error::InvalidProof);
+        }
 
-        // handle leaves and specify the internal node indices
-        let mut node_queue = VecDeque::new();
-        let mut siblings = VecDeque::from_iter(proof.sibling_leaves);
-        let mut leaf_queue = zip(indices, proof.initial_leaves).collect::<VecDeque<_>>();
-        while let Some((index, leaf)) = leaf_queue.pop_front() {
-            let node_index = (num_leaves + index) >> 1;
-
-            if let Some((next_index, next_leaf)) = leaf_queue.front() {
-                let are_siblings = index ^ 1 == *next_index;
-                if are_siblings {
-                    let running_hash = C::hash_leaves(height - 1, &leaf, next_leaf);
-                    node_queue.push_back((node_index, running_hash));
-                    leaf_queue.pop_front();
-                    continue;
-                }
-            }
-
-            let sibling = siblings.pop_front().unwrap();
-            let running_hash = if index % 2 == 0 {
-                C::hash_leaves(height - 1, &leaf, &sibling)
-            } else {
-                C::hash_leaves(height - 1, &sibling, &leaf)
-            };
-            node_queue.push_back((node_index, running_hash));
+        // handle leaves and specify the internal node positions
+        let mut siblings = proof.sibling_leaves.into_iter();
+        let leaves = zip(indices, proof.initial_leaves).collect();
+        let mut level = hash_siblings(arity, leaves, &mut siblings, |leaves| {
+            C::hash_leaf_group(height - 1, leaves)
+        })?;
+        if siblings.next().is_some() {
+            return Err(Error::InvalidProof);
         }
-        assert!(siblings.is_empty());
 
         // handle internal nodes
-        let mut nodes = VecDeque::from_iter(proof.nodes);
-        while let Some((index, hash)) = node_queue.pop_front() {
-            let depth = index.ilog2();
-
-            if depth == 0 {
-                assert!(node_queue.is_empty());
-                // compare against the root
-                return if *root == hash {
-                    Ok(())
-                } else {
-                    Err(Error::InvalidProof)
-                };
+        let mut nodes = proof.nodes.into_iter();
+        for depth in (cap_height..height - 1).rev() {
+            level = hash_siblings(arity, level, &mut nodes, |nodes| {
+                C::hash_node_group(depth, nodes)
+            })?;
+        }
+        if nodes.next().is_some() {
+            return Err(Error::InvalidProof);
+        }
+
+        // compare against the cap
+        if level
+            .iter()
+            .all(|(position, hash)| *hash == cap.0[*position])
+        {
+            Ok(())
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+}
+
+impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
+    type Proof = MerkleView<C::Digest, C::Leaf>;
+    type Root = C::Digest;
+
+    fn root(&self) -> C::Digest {
+        self.nodes[1].clone()
+    }
+
+    fn prove(&self, indices: &[usize]) -> Result<MerkleView<C::Digest, C::Leaf>, Error> {
+        self.prove_to_cap(indices, 0)
+    }
+
+    fn verify(
+        root: &C::Digest,
+        proof: MerkleView<C::Digest, C::Leaf>,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        Self::verify_against_cap(&MerkleCap(vec![root.clone()]), proof, indices)
+    }
+
+    fn security_level_bits() -> u32 {
+        C::security_level_bits()
+    }
+}
+
+/// Builds a [`MerkleTreeImpl`] from leaves that are appended one at a time.
+///
+/// Nodes are hashed as soon as all their children are known so the tree is
+/// built while leaves are still being generated rather than afterwards.
+pub struct MerkleTreeBuilder<C: MerkleTreeConfig> {
+    num_leaves: usize,
+    leaves: Vec<C::Leaf>,
+    /// Nodes hashed so far at each depth (the root has depth `0`)
+    levels: Vec<Vec<C::Digest>>,
+}
+
+impl<C: MerkleTreeConfig> MerkleTreeBuilder<C> {
+    /// Creates a builder for a tree with `num_leaves` leaves
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`MerkleTreeImpl::new`]
+    pub fn new(num_leaves: usize) -> Result<Self, Error> {
+        check_num_leaves::<C>(num_leaves)?;
+        let height = num_leaves.ilog2() / C::ARITY.ilog2();
+        let levels = (0..height)
+            .map(|depth| Vec::with_capacity(C::ARITY.pow(depth)))
+            .collect();
+        Ok(Self {
+            num_leaves,
+            leaves: Vec::with_capacity(num_leaves),
+            levels,
+        })
+    }
+
+    /// Returns the height of the tree being built (see [`MerkleTreeImpl`])
+    const fn height(&self) -> u32 {
+        self.num_leaves.ilog2() / C::ARITY.ilog2()
+    }
+
+    /// Appends a leaf and hashes any nodes whose children are now all known
+    ///
+    /// # Panics
+    ///
+    /// Panics if all leaves have already been appended
+    pub fn append_leaf(&mut self, leaf: C::Leaf) {
+        assert!(self.leaves.len() < self.num_leaves, "tree is full");
+        let arity = C::ARITY;
+        self.leaves.push(leaf);
+        if self.leaves.len() % arity != 0 {
+            return;
+        }
+
+        let mut depth = self.height() - 1;
+        let siblings = &self.leaves[self.leaves.len() - arity..];
+        let node = C::hash_leaf_group(depth, siblings);
+        self.levels[depth as usize].push(node);
+        while depth > 0 && self.levels[depth as usize].len() % arity == 0 {
+            let level = &self.levels[depth as usize];
+            let node = C::hash_node_group(depth - 1, &level[level.len() - arity..]);
+            depth -= 1;
+            self.levels[depth as usize].push(node);
+        }
+    }
+
+    /// Appends leaves (see [`Self::append_leaf`])
+    pub fn append_leaves(&mut self, leaves: impl IntoIterator<Item = C::Leaf>) {
+        for leaf in leaves {
+            self.append_leaf(leaf);
+        }
+    }
+
+    /// Returns the tree once all leaves have been appended
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fewer leaves were appended than the builder was
+    /// created with
+    pub fn finalize(self) -> Result<MerkleTreeImpl<C>, Error> {
+        if self.leaves.len() != self.num_leaves {
+            return Err(Error::TooFewLeaves {
+                min: self.num_leaves,
+                actual: self.leaves.len(),
+            });
+        }
+        // node `0` is unused so the root has index `1`
+        let mut nodes = vec![C::Digest::default()];
+        self.levels
+            .into_iter()
+            .for_each(|level| nodes.extend(level));
+        Ok(MerkleTreeImpl {
+            nodes,
+            leaves: self.leaves,
+        })
+    }
+}
+
+/// Merkle tree that supports proving/verifying rows of a matrix
+///
+/// Inspired by plonky3's MMCS
+/// <https://github.com/Plonky3/Plonky3/blob/main/commit/src/mmcs.rs>
+pub trait MatrixMerkleTree<T>: MerkleTree + Sized {
+    fn from_matrix(m: &Matrix<T>) -> Self;
+
+    /// Commits to a matrix given as consecutive batches of its rows. Trees
+    /// that hash rows independently hash each batch as it's generated so
+    /// the whole matrix is never in memory. By default the batches are
+    /// joined and committed to with [`Self::from_matrix`].
+    fn from_row_batches(batches: impl IntoIterator<Item = Matrix<T>>) -> Self
+    where
+        T: Field,
+    {
+        let mut batches = batches.into_iter();
+        let mut matrix = batches.next().expect("matrix has no rows");
+        for batch in batches {
+            for (column, batch_column) in zip(&mut matrix.0, batch.0) {
+                column.extend_from_slice(&batch_column);
             }
+        }
+        Self::from_matrix(&matrix)
+    }
 
-            if let Some((next_index, next_hash)) = node_queue.front() {
-                let are_siblings = index ^ 1 == *next_index;
-                if are_siblings {
-                    let running_hash = C::hash_nodes(depth - 1, &hash, next_hash);
-                    node_queue.push_back((index >> 1, running_hash));
-                    node_queue.pop_front();
-                    continue;
-                }
+    fn prove_rows(&self, row_ids: &[usize]) -> Result<Self::Proof, Error> {
+        self.prove(row_ids)
+    }
+
+    fn verify_rows(
+        root: &Self::Root,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[T]>],
+        proof: Self::Proof,
+    ) -> Result<(), Error>;
+}
+
+/// Commits to the rows of a matrix. Leaves are the hashes of each row.
+///
+/// `ARITY` is the number of children of each node (see
+/// [`MerkleTreeConfig::ARITY`]). The number of rows must be a power of it.
+/// `HARDENED` domain separates leaf and node hashes (see
+/// [`HashedLeafConfig`]).
+pub struct MatrixMerkleTreeImpl<H: HashFn, const ARITY: usize = 2, const HARDENED: bool = false> {
+    merkle_tree: MerkleTreeImpl<HashedLeafConfig<H, ARITY, HARDENED>>,
+}
+
+/// [`MatrixMerkleTreeImpl`] with domain separated leaf and node hashes
+pub type HardenedMatrixMerkleTreeImpl<H, const ARITY: usize = 2> =
+    MatrixMerkleTreeImpl<H, ARITY, true>;
+
+impl<H: HashFn, const ARITY: usize, const HARDENED: bool> Clone
+    for MatrixMerkleTreeImpl<H, ARITY, HARDENED>
+{
+    fn clone(&self) -> Self {
+        Self {
+            merkle_tree: self.merkle_tree.clone(),
+        }
+    }
+}
+
+impl<H: HashFn, const ARITY: usize, const HARDENED: bool> MatrixMerkleTreeImpl<H, ARITY, HARDENED> {
+    fn new(leaves: Vec<H::Digest>) -> Result<Self, Error> {
+        assert!(leaves.len().is_power_of_two());
+        Ok(Self {
+            merkle_tree: MerkleTreeImpl::new(leaves)?,
+        })
+    }
+
+    /// Returns the merkle cap at `cap_height` (see [`MerkleTreeImpl::cap`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `cap_height` is not less than the tree height.
+    pub fn cap(&self, cap_height: u32) -> Result<MerkleCap<H::Digest>, Error> {
+        self.merkle_tree.cap(cap_height)
+    }
+
+    /// Generates a proof for one or more rows that is verified against the
+    /// merkle cap at `cap_height`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a row index is out of bounds or `cap_height` is
+    /// not less than the tree height.
+    pub fn prove_rows_to_cap(
+        &self,
+        row_ids: &[usize],
+        cap_height: u32,
+    ) -> Result<MerkleView<H::Digest, H::Digest>, Error> {
+        self.merkle_tree.prove_to_cap(row_ids, cap_height)
+    }
+
+    /// Verifies a proof of rows against a merkle cap
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the proof fails verification.
+    pub fn verify_rows_against_cap<F: Field>(
+        cap: &MerkleCap<H::Digest>,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[F]>],
+        proof: MerkleView<H::Digest, H::Digest>,
+    ) -> Result<(), Error>
+    where
+        H: ElementHashFn<F>,
+    {
+        let (indices, initial_leaves) = hash_opened_rows::<F, H>(row_ids, rows);
+        if proof.initial_leaves == initial_leaves {
+            MerkleTreeImpl::<HashedLeafConfig<H, ARITY, HARDENED>>::verify_against_cap(
+                cap, proof, &indices,
+            )
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+}
+
+impl<H: HashFn, const ARITY: usize, const HARDENED: bool> MerkleTree
+    for MatrixMerkleTreeImpl<H, ARITY, HARDENED>
+{
+    type Proof = MerkleView<H::Digest, H::Digest>;
+    type Root = H::Digest;
+
+    fn root(&self) -> Self::Root {
+        self.merkle_tree.root()
+    }
+
+    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error> {
+        self.merkle_tree.prove(indices)
+    }
+
+    fn verify(root: &Self::Root, proof: Self::Proof, indices: &[usize]) -> Result<(), Error> {
+        MerkleTreeImpl::<HashedLeafConfig<H, ARITY, HARDENED>>::verify(root, proof, indices)
+    }
+
+    fn security_level_bits() -> u32 {
+        H::COLLISION_RESISTANCE
+    }
+}
+
+impl<
+        F: Field,
+        H: ElementHashFn<F> + Send + Sync + 'static,
+        const ARITY: usize,
+        const HARDENED: bool,
+    > MatrixMerkleTree<F> for MatrixMerkleTreeImpl<H, ARITY, HARDENED>
+{
+    fn from_matrix(m: &Matrix<F>) -> Self {
+        Self::new(hash_rows::<F, H>(m)).unwrap()
+    }
+
+    fn from_row_batches(batches: impl IntoIterator<Item = Matrix<F>>) -> Self {
+        let leaves = batches
+            .into_iter()
+            .flat_map(|batch| hash_rows::<F, H>(&batch))
+            .collect();
+        Self::new(leaves).unwrap()
+    }
+
+    fn verify_rows(
+        root: &Self::Root,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[F]>],
+        proof: Self::Proof,
+    ) -> Result<(), Error> {
+        let (indices, initial_leaves) = hash_opened_rows::<F, H>(row_ids, rows);
+        if proof.initial_leaves == initial_leaves {
+            Self::verify(root, proof, &indices)
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+}
+
+/// Builds a [`MatrixMerkleTreeImpl`] from rows that are appended one at a time
+/// (see [`MerkleTreeBuilder`])
+pub struct MatrixMerkleTreeBuilder<H: HashFn, const ARITY: usize = 2, const HARDENED: bool = false>
+{
+    builder: MerkleTreeBuilder<HashedLeafConfig<H, ARITY, HARDENED>>,
+    byte_buffer: Vec<u8>,
+}
+
+impl<H: HashFn, const ARITY: usize, const HARDENED: bool>
+    MatrixMerkleTreeBuilder<H, ARITY, HARDENED>
+{
+    /// Creates a builder for a matrix with `num_rows` rows
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a tree can't have `num_rows` leaves (see
+    /// [`MerkleTreeImpl::new`])
+    pub fn new(num_rows: usize) -> Result<Self, Error> {
+        Ok(Self {
+            builder: MerkleTreeBuilder::new(num_rows)?,
+            byte_buffer: Vec::new(),
+        })
+    }
+
+    /// Appends the next row of the matrix
+    pub fn append_row<F: Field>(&mut self, row: &[F])
+    where
+        H: ElementHashFn<F>,
+    {
+        let leaf = H::hash_row(row, &mut self.byte_buffer);
+        self.builder.append_leaf(leaf);
+    }
+
+    /// Returns the tree once all rows have been appended
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fewer rows were appended than the builder was
+    /// created with
+    pub fn finalize(self) -> Result<MatrixMerkleTreeImpl<H, ARITY, HARDENED>, Error> {
+        Ok(MatrixMerkleTreeImpl {
+            merkle_tree: self.builder.finalize()?,
+        })
+    }
+}
+
+/// Commits to the rows of a matrix without storing the row hashes.
+///
+/// [`MatrixMerkleTreeImpl`] keeps the hash of every row as a leaf. This tree
+/// only keeps the internal nodes which roughly halves its memory for binary
+/// trees. Hashes of sibling rows are recomputed from the matrix when rows are
+/// opened and proofs omit the hashes of the opened rows since verifiers
+/// recompute them from the rows anyway. Roots match [`MatrixMerkleTreeImpl`].
+pub struct LeaflessMatrixMerkleTreeImpl<H: HashFn, const ARITY: usize = 2> {
+    nodes: Vec<H::Digest>,
+    num_rows: usize,
+}
+
+impl<H: HashFn, const ARITY: usize> Clone for LeaflessMatrixMerkleTreeImpl<H, ARITY> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            num_rows: self.num_rows,
+        }
+    }
+}
+
+impl<H: HashFn, const ARITY: usize> LeaflessMatrixMerkleTreeImpl<H, ARITY> {
+    /// # Errors
+    ///
+    /// Returns an error if a tree can't have a leaf for each row of the matrix
+    /// (see [`MerkleTreeImpl::new`])
+    pub fn from_matrix<F: Field>(m: &Matrix<F>) -> Result<Self, Error>
+    where
+        H: ElementHashFn<F>,
+    {
+        let num_rows = m.num_rows();
+        check_num_leaves::<HashedLeafConfig<H, ARITY>>(num_rows)?;
+        let leaves = hash_rows::<F, H>(m);
+        let nodes = if ARITY == 2 {
+            build_merkle_nodes::<HashedLeafConfig<H, ARITY>>(&leaves)
+        } else {
+            build_kary_merkle_nodes::<HashedLeafConfig<H, ARITY>>(&leaves)
+        };
+        Ok(Self { nodes, num_rows })
+    }
+
+    pub fn root(&self) -> H::Digest {
+        self.nodes[1].clone()
+    }
+
+    /// Generates a proof for one or more rows of `m`. `m` must be the matrix
+    /// the tree was built from. The proof has no initial leaves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a row index is out of bounds.
+    pub fn prove_rows<F: Field>(
+        &self,
+        m: &Matrix<F>,
+        row_ids: &[usize],
+    ) -> Result<MerkleView<H::Digest, H::Digest>, Error>
+    where
+        H: ElementHashFn<F>,
+    {
+        assert_eq!(m.num_rows(), self.num_rows, "matrix doesn't match the tree");
+        for &i in row_ids {
+            if i >= self.num_rows {
+                return Err(Error::LeafIndexOutOfBounds {
+                    i,
+                    n: self.num_rows,
+                });
             }
+        }
 
-            let sibling = nodes.pop_front().unwrap();
-            let running_hash = if index % 2 == 0 {
-                C::hash_nodes(depth - 1, &hash, &sibling)
-            } else {
-                C::hash_nodes(depth - 1, &sibling, &hash)
-            };
-            node_queue.push_back((index >> 1, running_hash));
+        let mut row_ids = row_ids.to_vec();
+        row_ids.sort_unstable();
+        row_ids.dedup();
+
+        let mut row = vec![F::zero(); m.num_cols()];
+        let mut byte_buffer = Vec::new();
+        let mut sibling_leaves = Vec::new();
+        let positions = open_siblings(
+            ARITY,
+            |i| {
+                m.read_row(i, &mut row);
+                H::hash_row(&row, &mut byte_buffer)
+            },
+            &row_ids,
+            None,
+            &mut sibling_leaves,
+        );
+
+        let height = self.num_rows.ilog2() / ARITY.ilog2();
+        Ok(MerkleView {
+            nodes: open_nodes(ARITY, &self.nodes, positions, height, 0),
+            initial_leaves: Vec::new(),
+            sibling_leaves,
+            height,
+        })
+    }
+
+    /// Verifies a proof generated by [`Self::prove_rows`]
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the proof fails verification.
+    pub fn verify_rows<F: Field>(
+        root: &H::Digest,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[F]>],
+        mut proof: MerkleView<H::Digest, H::Digest>,
+    ) -> Result<(), Error>
+    where
+        H: ElementHashFn<F>,
+    {
+        if !proof.initial_leaves.is_empty() {
+            return Err(Error::InvalidProof);
+        }
+        let (indices, initial_leaves) = hash_opened_rows::<F, H>(row_ids, rows);
+        proof.initial_leaves = initial_leaves;
+        MerkleTreeImpl::<HashedLeafConfig<H, ARITY>>::verify(root, proof, &indices)
+    }
+}
+
+/// Merkle proof for a [`SaltedMatrixMerkleTreeImpl`]
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalDeserialize, CanonicalSerialize)]
+pub struct SaltedMerkleProof<D: Digest> {
+    /// Salts of the opened rows ordered by (deduplicated) row index
+    pub salts: Vec<D>,
+    pub view: MerkleView<D, D>,
+}
+
+/// Matrix merkle tree that mixes per-row randomness into each leaf.
+///
+/// Leaves are `H(H(row) || salt)` where each row gets its own random salt.
+/// Salts of opened rows are included in proofs. Since sibling leaves are
+/// blinded by unknown salts, opening rows reveals nothing about the contents
+/// of the unopened rows which is a prerequisite for zero-knowledge proofs.
+pub struct SaltedMatrixMerkleTreeImpl<H: HashFn> {
+    merkle_tree: MerkleTreeImpl<HashedLeafConfig<H>>,
+    salts: Vec<H::Digest>,
+}
+
+impl<H: HashFn> Clone for SaltedMatrixMerkleTreeImpl<H> {
+    fn clone(&self) -> Self {
+        Self {
+            merkle_tree: self.merkle_tree.clone(),
+            salts: self.salts.clone(),
         }
+    }
+}
+
+impl<H: HashFn> SaltedMatrixMerkleTreeImpl<H> {
+    fn new(row_hashes: &[H::Digest], salts: Vec<H::Digest>) -> Result<Self, Error> {
+        assert_eq!(row_hashes.len(), salts.len());
+        let leaves = zip(row_hashes, &salts)
+            .map(|(row_hash, salt)| H::merge(row_hash, salt))
+            .collect();
+        Ok(Self {
+            merkle_tree: MerkleTreeImpl::new(leaves)?,
+            salts,
+        })
+    }
+
+    /// Commits to the rows of a matrix using salts sampled from `rng`
+    pub fn from_matrix_with_rng<F: Field>(m: &Matrix<F>, rng: &mut impl RngCore) -> Self
+    where
+        H: ElementHashFn<F>,
+    {
+        let salts = (0..m.num_rows())
+            .map(|_| {
+                let mut salt_bytes = [0; 32];
+                rng.fill_bytes(&mut salt_bytes);
+                H::hash(salt_bytes)
+            })
+            .collect();
+        Self::new(&hash_rows::<F, H>(m), salts).unwrap()
+    }
+
+    /// Returns the salts of every row in the tree
+    pub fn salts(&self) -> &[H::Digest] {
+        &self.salts
+    }
+
+    /// Verifies a proof for one or more rows of a matrix
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the rows or salts don't match the commitment
+    pub fn verify_rows<F: Field>(
+        root: &H::Digest,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[F]>],
+        proof: SaltedMerkleProof<H::Digest>,
+    ) -> Result<(), Error>
+    where
+        H: ElementHashFn<F>,
+    {
+        // remove duplicates and sort
+        let mut instances = zip(row_ids, rows).collect::<Vec<_>>();
+        instances.sort_unstable_by_key(|(a, _)| *a);
+        instances.dedup_by(|(a, _), (b, _)| a == b);
+
+        if instances.len() != proof.salts.len() {
+            return Err(Error::InvalidProof);
+        }
+
+        let (indices, rows): (Vec<_>, Vec<_>) = instances.into_iter().unzip();
+        let initial_leaves = zip(rows, &proof.salts)
+            .map(|(r, salt)| H::merge(&H::hash_elements(r.as_ref().iter().copied()), salt))
+            .collect::<Vec<_>>();
+        if proof.view.initial_leaves == initial_leaves {
+            Self::verify(root, proof, &indices)
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+}
+
+impl<H: HashFn> MerkleTree for SaltedMatrixMerkleTreeImpl<H> {
+    type Proof = SaltedMerkleProof<H::Digest>;
+    type Root = H::Digest;
+
+    fn root(&self) -> Self::Root {
+        self.merkle_tree.root()
+    }
+
+    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error> {
+        let view = self.merkle_tree.prove(indices)?;
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        let salts = indices.iter().map(|&i| self.salts[i].clone()).collect();
+        Ok(SaltedMerkleProof { salts, view })
+    }
 
-        Ok(())
+    fn verify(root: &Self::Root, proof: Self::Proof, indices: &[usize]) -> Result<(), Error> {
+        MerkleTreeImpl::<HashedLeafConfig<H>>::verify(root, proof.view, indices)
     }
 
     fn security_level_bits() -> u32 {
-        C::security_level_bits()
+        H::COLLISION_RESISTANCE
     }
 }
 
-/// Merkle tree that supports proving/verifying rows of a matrix
-///
-/// Inspired by plonky3's MMCS
-/// <https://github.com/Plonky3/Plonky3/blob/main/commit/src/mmcs.rs>
-pub trait MatrixMerkleTree<T>: MerkleTree + Sized {
-    fn from_matrix(m: &Matrix<T>) -> Self;
-
-    fn prove_rows(&self, row_ids: &[usize]) -> Result<Self::Proof, Error> {
-        self.prove(row_ids)
+/// Salts are drawn from the OS so this needs the `std` feature. Without it use
+/// the inherent `from_matrix_with_rng` and `verify_rows` methods.
+#[cfg(feature = "std")]
+impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static> MatrixMerkleTree<F>
+    for SaltedMatrixMerkleTreeImpl<H>
+{
+    fn from_matrix(m: &Matrix<F>) -> Self {
+        Self::from_matrix_with_rng(m, &mut rand::thread_rng())
     }
 
     fn verify_rows(
         root: &Self::Root,
         row_ids: &[usize],
-        rows: &[impl AsRef<[T]>],
+        rows: &[impl AsRef<[F]>],
         proof: Self::Proof,
-    ) -> Result<(), Error>;
+    ) -> Result<(), Error> {
+        Self::verify_rows(root, row_ids, rows, proof)
+    }
 }
 
-pub struct MatrixMerkleTreeImpl<H: HashFn> {
-    merkle_tree: MerkleTreeImpl<HashedLeafConfig<H>>,
+/// Commits to the rows of a matrix with separate row and node hashes.
+///
+/// Rows are hashed with `R` and internal nodes with `N` (see
+/// [`SplitHashConfig`]). A fast row hash such as Blake3 does most of the work
+/// for wide matrices while a recursion friendly node hash such as Poseidon
+/// keeps authentication paths cheap to verify in a recursive proof.
+pub struct SplitHashMatrixMerkleTreeImpl<R: HashFn, N: HashFn, const ARITY: usize = 2> {
+    merkle_tree: MerkleTreeImpl<SplitHashConfig<R, N, ARITY>>,
 }
 
-impl<H: HashFn> Clone for MatrixMerkleTreeImpl<H> {
+impl<R: HashFn, N: HashFn, const ARITY: usize> Clone
+    for SplitHashMatrixMerkleTreeImpl<R, N, ARITY>
+{
     fn clone(&self) -> Self {
         Self {
             merkle_tree: self.merkle_tree.clone(),
@@ -323,18 +1016,22 @@ impl<H: HashFn> Clone for MatrixMerkleTreeImpl<H> {
     }
 }
 
-impl<H: HashFn> MatrixMerkleTreeImpl<H> {
-    fn new(leaves: Vec<H::Digest>) -> Result<Self, Error> {
-        assert!(leaves.len().is_power_of_two());
-        Ok(Self {
-            merkle_tree: MerkleTreeImpl::new(leaves)?,
-        })
+impl<R: HashFn, N: HashFn, const ARITY: usize> SplitHashMatrixMerkleTreeImpl<R, N, ARITY> {
+    /// Returns the merkle cap at `cap_height` (see [`MerkleTreeImpl::cap`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `cap_height` is not less than the tree height.
+    pub fn cap(&self, cap_height: u32) -> Result<MerkleCap<N::Digest>, Error> {
+        self.merkle_tree.cap(cap_height)
     }
 }
 
-impl<H: HashFn> MerkleTree for MatrixMerkleTreeImpl<H> {
-    type Proof = MerkleView<H::Digest, H::Digest>;
-    type Root = H::Digest;
+impl<R: HashFn, N: HashFn, const ARITY: usize> MerkleTree
+    for SplitHashMatrixMerkleTreeImpl<R, N, ARITY>
+{
+    type Proof = MerkleView<N::Digest, R::Digest>;
+    type Root = N::Digest;
 
     fn root(&self) -> Self::Root {
         self.merkle_tree.root()
@@ -345,19 +1042,31 @@ impl<H: HashFn> MerkleTree for MatrixMerkleTreeImpl<H> {
     }
 
     fn verify(root: &Self::Root, proof: Self::Proof, indices: &[usize]) -> Result<(), Error> {
-        MerkleTreeImpl::<HashedLeafConfig<H>>::verify(root, proof, indices)
+        MerkleTreeImpl::<SplitHashConfig<R, N, ARITY>>::verify(root, proof, indices)
     }
 
     fn security_level_bits() -> u32 {
-        H::COLLISION_RESISTANCE
+        SplitHashConfig::<R, N, ARITY>::security_level_bits()
     }
 }
 
-impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static> MatrixMerkleTree<F>
-    for MatrixMerkleTreeImpl<H>
+impl<F: Field, R: ElementHashFn<F>, N: HashFn, const ARITY: usize> MatrixMerkleTree<F>
+    for SplitHashMatrixMerkleTreeImpl<R, N, ARITY>
 {
     fn from_matrix(m: &Matrix<F>) -> Self {
-        Self::new(hash_rows::<F, H>(m)).unwrap()
+        Self {
+            merkle_tree: MerkleTreeImpl::new(hash_rows::<F, R>(m)).unwrap(),
+        }
+    }
+
+    fn from_row_batches(batches: impl IntoIterator<Item = Matrix<F>>) -> Self {
+        let leaves = batches
+            .into_iter()
+            .flat_map(|batch| hash_rows::<F, R>(&batch))
+            .collect();
+        Self {
+            merkle_tree: MerkleTreeImpl::new(leaves).unwrap(),
+        }
     }
 
     fn verify_rows(
@@ -366,16 +1075,7 @@ impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static> MatrixMerkleTree<F>
         rows: &[impl AsRef<[F]>],
         proof: Self::Proof,
     ) -> Result<(), Error> {
-        // remove duplicates and sort
-        let mut instances = zip(row_ids, rows).collect::<Vec<_>>();
-        instances.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
-        instances.dedup_by(|(a, _), (b, _)| a == b);
-
-        let (indices, rows): (Vec<_>, Vec<_>) = instances.into_iter().unzip();
-        let initial_leaves = rows
-            .iter()
-            .map(|r| H::hash_elements(r.as_ref().iter().copied()))
-            .collect::<Vec<_>>();
+        let (indices, initial_leaves) = hash_opened_rows::<F, R>(row_ids, rows);
         if proof.initial_leaves == initial_leaves {
             Self::verify(root, proof, &indices)
         } else {
@@ -384,24 +1084,87 @@ impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static> MatrixMerkleTree<F>
     }
 }
 
-pub struct HashedLeafConfig<H: HashFn>(PhantomData<H>);
-
-impl<H: HashFn> Clone for HashedLeafConfig<H> {
+/// Config for trees whose leaves are digests e.g. the hashes of matrix rows.
+///
+/// When `HARDENED` is set each leaf is hashed again with [`LEAF_PREFIX`]
+/// before it's combined with its siblings and internal nodes are hashed with
+/// [`NODE_PREFIX`]. A node can't be passed off as a leaf or vice versa, not
+/// even by a row whose hash preimage looks like the preimage of a node. Roots
+/// differ from unhardened trees.
+pub struct HashedLeafConfig<H: HashFn, const ARITY: usize = 2, const HARDENED: bool = false>(
+    PhantomData<H>,
+);
+
+/// Prefix of the preimage of leaf hashes in hardened trees (see
+/// [`HashedLeafConfig`])
+pub const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix of the preimage of internal nodes in hardened trees (see
+/// [`HashedLeafConfig`])
+pub const NODE_PREFIX: u8 = 0x01;
+
+impl<H: HashFn, const ARITY: usize, const HARDENED: bool> Clone
+    for HashedLeafConfig<H, ARITY, HARDENED>
+{
     fn clone(&self) -> Self {
         Self(PhantomData)
     }
 }
 
-impl<H: HashFn> MerkleTreeConfig for HashedLeafConfig<H> {
+impl<H: HashFn, const ARITY: usize, const HARDENED: bool> HashedLeafConfig<H, ARITY, HARDENED> {
+    fn hash_with_prefix(prefix: u8, digests: &[H::Digest]) -> H::Digest {
+        let bytes = digests.iter().map(Digest::as_bytes).collect::<Vec<_>>();
+        H::hash_chunks(core::iter::once(&[prefix][..]).chain(bytes.iter().map(|b| &b[..])))
+    }
+
+    /// Returns the hash of a leaf in a hardened tree
+    fn hash_leaf(leaf: &H::Digest) -> H::Digest {
+        Self::hash_with_prefix(LEAF_PREFIX, core::slice::from_ref(leaf))
+    }
+}
+
+impl<H: HashFn, const ARITY: usize, const HARDENED: bool> MerkleTreeConfig
+    for HashedLeafConfig<H, ARITY, HARDENED>
+{
     type Digest = H::Digest;
     type Leaf = H::Digest;
 
+    const ARITY: usize = ARITY;
+
     fn hash_leaves(_: u32, l0: &H::Digest, l1: &H::Digest) -> H::Digest {
-        H::merge(l0, l1)
+        if HARDENED {
+            Self::hash_with_prefix(NODE_PREFIX, &[Self::hash_leaf(l0), Self::hash_leaf(l1)])
+        } else {
+            H::merge(l0, l1)
+        }
     }
 
     fn hash_nodes(_: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest {
-        H::merge(n0, n1)
+        if HARDENED {
+            Self::hash_with_prefix(NODE_PREFIX, &[n0.clone(), n1.clone()])
+        } else {
+            H::merge(n0, n1)
+        }
+    }
+
+    fn hash_leaf_group(depth: u32, leaves: &[H::Digest]) -> H::Digest {
+        if HARDENED {
+            let leaves = leaves.iter().map(Self::hash_leaf).collect::<Vec<_>>();
+            Self::hash_with_prefix(NODE_PREFIX, &leaves)
+        } else {
+            Self::hash_node_group(depth, leaves)
+        }
+    }
+
+    fn hash_node_group(_: u32, nodes: &[H::Digest]) -> H::Digest {
+        if HARDENED {
+            Self::hash_with_prefix(NODE_PREFIX, nodes)
+        } else if let [n0, n1] = nodes {
+            H::merge(n0, n1)
+        } else {
+            let bytes = nodes.iter().map(Digest::as_bytes).collect::<Vec<_>>();
+            H::hash_chunks(bytes.iter().map(|b| &b[..]))
+        }
     }
 
     fn security_level_bits() -> u32 {
@@ -409,6 +1172,63 @@ impl<H: HashFn> MerkleTreeConfig for HashedLeafConfig<H> {
     }
 }
 
+/// Config for trees whose leaves are digests of a row hash `R` and whose
+/// internal nodes are digests of a node hash `N`.
+///
+/// Nodes whose children are leaves hash the bytes of the leaves with `N`.
+/// All other nodes are hashed like an unhardened [`HashedLeafConfig`] over
+/// `N`.
+pub struct SplitHashConfig<R: HashFn, N: HashFn, const ARITY: usize = 2>(PhantomData<(R, N)>);
+
+impl<R: HashFn, N: HashFn, const ARITY: usize> Clone for SplitHashConfig<R, N, ARITY> {
+    fn clone(&self) -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<R: HashFn, N: HashFn, const ARITY: usize> MerkleTreeConfig for SplitHashConfig<R, N, ARITY> {
+    type Digest = N::Digest;
+    type Leaf = R::Digest;
+
+    const ARITY: usize = ARITY;
+
+    fn hash_leaves(depth: u32, l0: &R::Digest, l1: &R::Digest) -> N::Digest {
+        Self::hash_leaf_group(depth, &[l0.clone(), l1.clone()])
+    }
+
+    fn hash_nodes(depth: u32, n0: &N::Digest, n1: &N::Digest) -> N::Digest {
+        HashedLeafConfig::<N, ARITY>::hash_nodes(depth, n0, n1)
+    }
+
+    fn hash_leaf_group(_: u32, leaves: &[R::Digest]) -> N::Digest {
+        let bytes = leaves.iter().map(Digest::as_bytes).collect::<Vec<_>>();
+        N::hash_chunks(bytes.iter().map(|b| &b[..]))
+    }
+
+    fn hash_node_group(depth: u32, nodes: &[N::Digest]) -> N::Digest {
+        HashedLeafConfig::<N, ARITY>::hash_node_group(depth, nodes)
+    }
+
+    fn security_level_bits() -> u32 {
+        R::COLLISION_RESISTANCE.min(N::COLLISION_RESISTANCE)
+    }
+}
+
+/// Removes duplicate rows and returns the sorted row indices along with the
+/// hashes of their rows
+fn hash_opened_rows<F: Field, H: ElementHashFn<F>>(
+    row_ids: &[usize],
+    rows: &[impl AsRef<[F]>],
+) -> (Vec<usize>, Vec<H::Digest>) {
+    let mut instances = zip(row_ids, rows).collect::<Vec<_>>();
+    instances.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    instances.dedup_by(|(a, _), (b, _)| a == b);
+    instances
+        .into_iter()
+        .map(|(&i, r)| (i, H::hash_elements(r.as_ref().iter().copied())))
+        .unzip()
+}
+
 pub fn hash_rows<F: Field, H: ElementHashFn<F>>(matrix: &Matrix<F>) -> Vec<H::Digest> {
     let num_rows = matrix.num_rows();
     let mut row_hashes = vec![H::Digest::default(); num_rows];
@@ -425,16 +1245,142 @@ pub fn hash_rows<F: Field, H: ElementHashFn<F>>(matrix: &Matrix<F>) -> Vec<H::Di
         .enumerate()
         .for_each(|(chunk_offset, chunk)| {
             let offset = chunk_size * chunk_offset;
-            let mut row_buffer = vec![F::zero(); matrix.num_cols()];
-            for (i, row_hash) in chunk.iter_mut().enumerate() {
-                matrix.read_row(offset + i, &mut row_buffer);
-                *row_hash = H::hash_elements(row_buffer.iter().copied());
-            }
+            let mut byte_buffer = Vec::new();
+            matrix.for_each_row(offset..offset + chunk.len(), |i, row| {
+                chunk[i - offset] = H::hash_row(row, &mut byte_buffer);
+            });
         });
 
     row_hashes
 }
 
+/// Checks a tree with `n` leaves can be built with the config
+fn check_num_leaves<C: MerkleTreeConfig>(n: usize) -> Result<(), Error> {
+    let arity = C::ARITY;
+    assert!(arity >= 2 && arity.is_power_of_two());
+    let min_leaves = arity;
+    if n < min_leaves {
+        Err(Error::TooFewLeaves {
+            min: min_leaves,
+            actual: n,
+        })
+    } else if !n.is_power_of_two() {
+        Err(Error::NumberOfLeavesNotPowerOfTwo { n })
+    } else if n.ilog2() % arity.ilog2() != 0 {
+        Err(Error::NumberOfLeavesNotPowerOfArity { n, arity })
+    } else {
+        Ok(())
+    }
+}
+
+/// Index of the first node at `depth` in [`MerkleTreeImpl::nodes`]
+const fn level_offset(arity: usize, depth: u32) -> usize {
+    1 + (arity.pow(depth) - 1) / (arity - 1)
+}
+
+/// Adds the siblings of the opened `positions` to `siblings` and (optionally)
+/// the opened items to `opened`. Items are looked up with `item`. `positions`
+/// must be sorted and unique. Returns the sorted positions of the parent nodes.
+fn open_siblings<T>(
+    arity: usize,
+    mut item: impl FnMut(usize) -> T,
+    positions: &[usize],
+    mut opened: Option<&mut Vec<T>>,
+    siblings: &mut Vec<T>,
+) -> Vec<usize> {
+    let mut parents = Vec::new();
+    let mut positions = positions.iter().peekable();
+    while let Some(&&position) = positions.peek() {
+        let parent = position / arity;
+        for child in parent * arity..(parent + 1) * arity {
+            if positions.next_if_eq(&&child).is_some() {
+                if let Some(opened) = opened.as_deref_mut() {
+                    opened.push(item(child));
+                }
+            } else {
+                siblings.push(item(child));
+            }
+        }
+        parents.push(parent);
+    }
+    parents
+}
+
+/// Opens the internal nodes needed to hash the nodes at `positions` (at depth
+/// `height - 1`) up to the level at `cap_height`. `nodes` are laid out as in
+/// [`MerkleTreeImpl::nodes`].
+fn open_nodes<D: Clone>(
+    arity: usize,
+    nodes: &[D],
+    mut positions: Vec<usize>,
+    height: u32,
+    cap_height: u32,
+) -> Vec<D> {
+    let mut opened = Vec::new();
+    for depth in (cap_height + 1..height).rev() {
+        let level = &nodes[level_offset(arity, depth)..];
+        positions = open_siblings(arity, |i| level[i].clone(), &positions, None, &mut opened);
+    }
+    opened
+}
+
+/// Hashes opened items with their siblings taken from `siblings`. `opened`
+/// must be sorted by position. Returns the parent nodes and their positions.
+fn hash_siblings<T, D>(
+    arity: usize,
+    opened: Vec<(usize, T)>,
+    siblings: &mut impl Iterator<Item = T>,
+    hash: impl Fn(&[T]) -> D,
+) -> Result<Vec<(usize, D)>, Error> {
+    let mut parents = Vec::new();
+    let mut opened = opened.into_iter().peekable();
+    let mut children = Vec::with_capacity(arity);
+    while let Some((position, _)) = opened.peek() {
+        let parent = position / arity;
+        for child in parent * arity..(parent + 1) * arity {
+            let item = match opened.next_if(|(position, _)| *position == child) {
+                Some((_, item)) => item,
+                None => siblings.next().ok_or(Error::InvalidProof)?,
+            };
+            children.push(item);
+        }
+        parents.push((parent, hash(&children)));
+        children.clear();
+    }
+    Ok(parents)
+}
+
+/// Builds the nodes of a tree with [`MerkleTreeConfig::ARITY`] children per
+/// node. The number of leaves must be a power of the arity.
+pub fn build_kary_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Digest> {
+    let arity = C::ARITY;
+    let n = leaves.len();
+    let height = n.ilog2() / arity.ilog2();
+    assert_eq!(arity.pow(height), n);
+    let mut nodes = vec![C::Digest::default(); level_offset(arity, height)];
+
+    // generate first layer of nodes from leaf nodes
+    let depth = height - 1;
+    let offset = level_offset(arity, depth);
+    ark_std::cfg_iter_mut!(nodes[offset..])
+        .zip(ark_std::cfg_chunks!(leaves, arity))
+        .for_each(|(node, leaves)| *node = C::hash_leaf_group(depth, leaves));
+
+    // generate remaining nodes
+    for depth in (0..height - 1).rev() {
+        let offset = level_offset(arity, depth);
+        let (parents, children) = nodes.split_at_mut(level_offset(arity, depth + 1));
+        ark_std::cfg_iter_mut!(parents[offset..])
+            .zip(ark_std::cfg_chunks!(
+                children[..arity.pow(depth + 1)],
+                arity
+            ))
+            .for_each(|(node, children)| *node = C::hash_node_group(depth, children));
+    }
+
+    nodes
+}
+
 #[cfg(feature = "parallel")]
 pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Digest> {
     let n = leaves.len();
@@ -510,12 +1456,23 @@ pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Dig
 #[cfg(test)]
 mod tests {
     use super::Error;
+    use super::HardenedMatrixMerkleTreeImpl;
+    use super::LeaflessMatrixMerkleTreeImpl;
     use super::MatrixMerkleTree;
+    use super::MatrixMerkleTreeBuilder;
     use super::MatrixMerkleTreeImpl;
     use super::MerkleTree;
+    use super::MerkleTreeBuilder;
     use super::MerkleTreeConfig;
     use super::MerkleTreeImpl;
+    use super::Padding;
+    use super::SaltedMatrixMerkleTreeImpl;
+    use super::SplitHashMatrixMerkleTreeImpl;
+    use crate::hash::Blake3Context;
+    use crate::hash::Blake3HashFn;
+    use crate::hash::ElementHashFn;
     use crate::hash::HashFn;
+    use crate::hash::Keccak256HashFn;
     use crate::hash::Sha256HashFn;
     use crate::utils::GpuAllocator;
     use crate::utils::SerdeOutput;
@@ -580,6 +1537,420 @@ mod tests {
         MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, proof, &[i])
     }
 
+    #[test]
+    fn salted_rows_verify_and_hide_leaves() -> Result<(), Error> {
+        let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = SaltedMatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        let unsalted_tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        let commitment = tree.root();
+        let row_ids = [2, 0];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        let unsalted_proof = MatrixMerkleTree::<Fp>::prove_rows(&unsalted_tree, &row_ids)?;
+
+        assert_ne!(commitment, unsalted_tree.root());
+        assert_ne!(proof.view.sibling_leaves, unsalted_proof.sibling_leaves);
+        let bad_rows = row_ids.map(|i| [column[i] + Fp::from(1u8)]);
+        assert!(SaltedMatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(
+            &commitment,
+            &row_ids,
+            &bad_rows,
+            proof.clone()
+        )
+        .is_err());
+        SaltedMatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(&commitment, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn batch_proof_shares_nodes() -> Result<(), Error> {
+        let leaves = (0..1 << 10).collect::<Vec<u32>>();
+        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves)?;
+        let commitment = tree.root();
+        let indices = (0..64).map(|i| i * 13 + 5).collect::<Vec<usize>>();
+
+        let proof = tree.prove(&indices)?;
+        let num_individual_nodes = indices
+            .iter()
+            .map(|&i| tree.prove(&[i]).map(|proof| proof.nodes.len()))
+            .sum::<Result<usize, Error>>()?;
+
+        assert!(proof.nodes.len() < num_individual_nodes / 2);
+        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, proof, &indices)
+    }
+
+    #[test]
+    fn verify_kary_trees() -> Result<(), Error> {
+        let leaves = (0..1 << 12).collect::<Vec<u32>>();
+        let binary_tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves.clone())?;
+        let tree = MerkleTreeImpl::<QuaternaryLeafConfig>::new(leaves.clone())?;
+        let indices = [0, 1, 3, 378, 379, 4000];
+
+        let binary_proof = binary_tree.prove(&indices)?;
+        let proof = tree.prove(&indices)?;
+
+        assert_eq!(proof.height, 6);
+        assert_eq!(binary_proof.height, 12);
+        // the default group hashes treat each group as a binary subtree
+        assert_eq!(tree.root(), binary_tree.root());
+        assert!(matches!(
+            MerkleTreeImpl::<QuaternaryLeafConfig>::new(leaves[..1 << 11].to_vec()),
+            Err(Error::NumberOfLeavesNotPowerOfArity { n: 2048, arity: 4 })
+        ));
+        MerkleTreeImpl::<QuaternaryLeafConfig>::verify(&tree.root(), proof, &indices)
+    }
+
+    #[test]
+    fn builder_matches_tree() -> Result<(), Error> {
+        let leaves = (0..1 << 8).collect::<Vec<u32>>();
+        let mut binary_builder = MerkleTreeBuilder::<UnhashedLeafConfig>::new(leaves.len())?;
+        let mut builder = MerkleTreeBuilder::<QuaternaryLeafConfig>::new(leaves.len())?;
+        binary_builder.append_leaves(leaves.clone());
+        builder.append_leaves(leaves[..100].to_vec());
+        let binary_tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves.clone())?;
+        assert_eq!(binary_builder.finalize()?.nodes, binary_tree.nodes);
+
+        builder.append_leaves(leaves[100..].to_vec());
+        let tree = MerkleTreeImpl::<QuaternaryLeafConfig>::new(leaves)?;
+        assert_eq!(builder.finalize()?.nodes, tree.nodes);
+
+        let column = (0..64u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let mut builder = MatrixMerkleTreeBuilder::<Sha256HashFn>::new(column.len())?;
+        assert!(matches!(
+            MatrixMerkleTreeBuilder::<Sha256HashFn>::new(column.len())?.finalize(),
+            Err(Error::TooFewLeaves { min: 64, actual: 0 })
+        ));
+        for v in &column {
+            builder.append_row(&[*v]);
+        }
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        assert_eq!(builder.finalize()?.root(), tree.root());
+
+        let batches = column
+            .chunks(16)
+            .map(|rows| Matrix::new(vec![rows.to_vec_in(GpuAllocator)]));
+        let batched_tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_row_batches(batches);
+        assert_eq!(batched_tree.root(), tree.root());
+        Ok(())
+    }
+
+    #[test]
+    fn verify_padded_tree() -> Result<(), Error> {
+        let leaves = vec![1u32, 2, 3, 4, 5];
+        let tree = MerkleTreeImpl::<QuaternaryLeafConfig>::new_padded(
+            leaves.clone(),
+            Padding::RepeatLast,
+        )?;
+        assert_eq!(tree.leaves[4..], [5; 12]);
+        let zero_padded =
+            MerkleTreeImpl::<QuaternaryLeafConfig>::new_padded(leaves.clone(), Padding::Leaf(0))?;
+        assert_eq!(
+            zero_padded.root(),
+            MerkleTreeImpl::<QuaternaryLeafConfig>::new_padded(leaves, Padding::Zero)?.root()
+        );
+        assert_ne!(tree.root(), zero_padded.root());
+
+        let proof = tree.prove(&[1, 4])?;
+
+        MerkleTreeImpl::<QuaternaryLeafConfig>::verify(&tree.root(), proof, &[1, 4])?;
+        assert!(matches!(
+            MerkleTreeImpl::<QuaternaryLeafConfig>::new_padded(vec![], Padding::Zero),
+            Err(Error::TooFewLeaves { min: 1, actual: 0 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn hardened_tree_separates_leaves_from_nodes() -> Result<(), Error> {
+        type Config<const HARDENED: bool> = super::HashedLeafConfig<Sha256HashFn, 2, HARDENED>;
+        let column = (0..64u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = HardenedMatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        let unhardened_tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        assert_ne!(tree.root(), unhardened_tree.root());
+        let row_ids = [1, 40];
+        let rows = row_ids.map(|i| [column[i]]);
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        HardenedMatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(
+            &tree.root(),
+            &row_ids,
+            &rows,
+            proof,
+        )?;
+
+        // internal nodes of an unhardened tree form a smaller tree with the same root
+        // so a node digest can be opened as if it was a leaf
+        let leaves = (0..4u8)
+            .map(|i| Sha256HashFn::hash([i]))
+            .collect::<Vec<_>>();
+        let tree = MerkleTreeImpl::<Config<false>>::new(leaves.clone())?;
+        let nodes_as_leaves = MerkleTreeImpl::<Config<false>>::new(tree.nodes[2..4].to_vec())?;
+        assert_eq!(tree.root(), nodes_as_leaves.root());
+        let forged_proof = nodes_as_leaves.prove(&[0])?;
+        assert_eq!(forged_proof.initial_leaves, [tree.nodes[2].clone()]);
+        MerkleTreeImpl::<Config<false>>::verify(&tree.root(), forged_proof, &[0])?;
+
+        let tree = MerkleTreeImpl::<Config<true>>::new(leaves)?;
+        let nodes_as_leaves = MerkleTreeImpl::<Config<true>>::new(tree.nodes[2..4].to_vec())?;
+        assert_ne!(tree.root(), nodes_as_leaves.root());
+        let forged_proof = nodes_as_leaves.prove(&[0])?;
+        assert!(MerkleTreeImpl::<Config<true>>::verify(&tree.root(), forged_proof, &[0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn leafless_tree_omits_opened_leaves() -> Result<(), Error> {
+        let column = (0..64u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = LeaflessMatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix)?;
+        let full_tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        assert_eq!(tree.root(), full_tree.root());
+        let row_ids = [3, 4, 40];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = tree.prove_rows(&matrix, &row_ids)?;
+        let full_proof = MatrixMerkleTree::<Fp>::prove_rows(&full_tree, &row_ids)?;
+
+        assert!(proof.initial_leaves.is_empty());
+        assert_eq!(proof.nodes, full_proof.nodes);
+        assert_eq!(proof.sibling_leaves, full_proof.sibling_leaves);
+        let bad_rows = row_ids.map(|i| [column[i] + Fp::from(1u8)]);
+        assert!(LeaflessMatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(
+            &tree.root(),
+            &row_ids,
+            &bad_rows,
+            proof.clone()
+        )
+        .is_err());
+        LeaflessMatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(
+            &tree.root(),
+            &row_ids,
+            &rows,
+            proof,
+        )
+    }
+
+    #[test]
+    fn extension_rows_hash_as_base_field_coefficients() {
+        use ark_ff::Field;
+        use ark_ff::UniformRand;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp as Goldilocks;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+
+        let mut rng = ark_std::test_rng();
+        let row = (0..5).map(|_| Fq3::rand(&mut rng)).collect::<Vec<_>>();
+        let coeffs = row
+            .iter()
+            .flat_map(Field::to_base_prime_field_elements)
+            .collect::<Vec<Goldilocks>>();
+        let mut buffer = Vec::new();
+
+        assert_eq!(
+            <Sha256HashFn as ElementHashFn<Fq3>>::hash_row(&row, &mut buffer),
+            Sha256HashFn::hash_elements(coeffs.iter().copied())
+        );
+        assert_eq!(
+            <Blake3HashFn<()> as ElementHashFn<Fq3>>::hash_row(&row, &mut buffer),
+            Blake3HashFn::<()>::hash_elements(coeffs.iter().copied())
+        );
+        assert_eq!(
+            <Keccak256HashFn as ElementHashFn<Fq3>>::hash_row(&row, &mut buffer),
+            Keccak256HashFn::hash_elements(coeffs)
+        );
+    }
+
+    #[test]
+    fn prove_rows_of_octary_tree() -> Result<(), Error> {
+        let column = (0..64u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn, 8>::from_matrix(&matrix);
+        let commitment = tree.root();
+        let row_ids = [5, 6, 60];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+
+        assert_eq!(proof.height, 2);
+        let bad_rows = row_ids.map(|i| [column[i] + Fp::from(1u8)]);
+        assert!(MatrixMerkleTreeImpl::<Sha256HashFn, 8>::verify_rows(
+            &commitment,
+            &row_ids,
+            &bad_rows,
+            proof.clone()
+        )
+        .is_err());
+        MatrixMerkleTreeImpl::<Sha256HashFn, 8>::verify_rows(&commitment, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn prove_rows_with_blake3() -> Result<(), Error> {
+        struct TraceContext;
+
+        impl Blake3Context for TraceContext {
+            const CONTEXT: Option<&'static str> = Some("ministark tests trace commitment");
+        }
+
+        let column = (0..16u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![
+            column.to_vec_in(GpuAllocator),
+            column.to_vec_in(GpuAllocator),
+        ]);
+        let tree = MatrixMerkleTreeImpl::<Blake3HashFn>::from_matrix(&matrix);
+        let keyed_tree = MatrixMerkleTreeImpl::<Blake3HashFn<TraceContext>>::from_matrix(&matrix);
+        let commitment = tree.root();
+        let row_ids = [1, 7, 12];
+        let rows = row_ids.map(|i| [column[i], column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+
+        assert_ne!(commitment, keyed_tree.root());
+        let expected_leaf = blake3::hash(
+            &[column[7], column[7]]
+                .iter()
+                .flat_map(|v| {
+                    let mut bytes = Vec::new();
+                    ark_serialize::CanonicalSerialize::serialize_uncompressed(v, &mut bytes)
+                        .unwrap();
+                    bytes
+                })
+                .collect::<Vec<u8>>(),
+        );
+        assert_eq!(proof.initial_leaves[1].as_slice(), expected_leaf.as_bytes());
+        MatrixMerkleTreeImpl::<Blake3HashFn>::verify_rows(&commitment, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn prove_rows_with_poseidon() -> Result<(), Error> {
+        use crate::poseidon::PoseidonMatrixMerkleTree;
+        use crate::poseidon::PoseidonMerkleTreeConfig;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+
+        let column = (0..16u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![
+            column.to_vec_in(GpuAllocator),
+            column.to_vec_in(GpuAllocator),
+        ]);
+        let tree = PoseidonMatrixMerkleTree::from_matrix(&matrix);
+        let commitment = tree.root();
+        let row_ids = [3, 9];
+        let rows = row_ids.map(|i| [column[i], column[i]]);
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        PoseidonMatrixMerkleTree::verify_rows(&commitment, &row_ids, &rows, proof.clone())?;
+        let bad_rows = row_ids.map(|i| [column[i], column[i] + Fp::from(1u8)]);
+        assert!(
+            PoseidonMatrixMerkleTree::verify_rows(&commitment, &row_ids, &bad_rows, proof).is_err()
+        );
+
+        // leaves of extension field elements are absorbed as base field elements
+        let leaves = (0..8u32)
+            .map(|i| vec![Fq3::from(i), Fq3::from(i + 1)])
+            .collect::<Vec<Vec<Fq3>>>();
+        let tree = MerkleTreeImpl::<PoseidonMerkleTreeConfig<Fq3>>::new(leaves)?;
+        let proof = tree.prove(&[2, 5])?;
+        MerkleTreeImpl::<PoseidonMerkleTreeConfig<Fq3>>::verify(&tree.root(), proof, &[2, 5])
+    }
+
+    #[test]
+    fn prove_rows_with_split_hashes() -> Result<(), Error> {
+        use crate::poseidon::PoseidonHashFn;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+        type Tree = SplitHashMatrixMerkleTreeImpl<Blake3HashFn, PoseidonHashFn>;
+
+        let column = (0..16u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![
+            column.to_vec_in(GpuAllocator),
+            column.to_vec_in(GpuAllocator),
+        ]);
+        let tree = Tree::from_matrix(&matrix);
+        let commitment = tree.root();
+        let row_ids = [3, 9];
+        let rows = row_ids.map(|i| [column[i], column[i]]);
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        Tree::verify_rows(&commitment, &row_ids, &rows, proof.clone())?;
+        let bad_rows = row_ids.map(|i| [column[i], column[i] + Fp::from(1u8)]);
+        assert!(Tree::verify_rows(&commitment, &row_ids, &bad_rows, proof).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn prove_rows_with_rpo() -> Result<(), Error> {
+        use crate::rescue::Rpo256MatrixMerkleTree;
+        use crate::rescue::Rpo256MerkleTreeConfig;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+        let column = (0..8u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = Rpo256MatrixMerkleTree::from_matrix(&matrix);
+        let row_ids = [2, 5];
+        let rows = row_ids.map(|i| [column[i]]);
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        Rpo256MatrixMerkleTree::verify_rows(&tree.root(), &row_ids, &rows, proof)?;
+
+        let leaves = column.iter().map(|&v| vec![v]).collect::<Vec<Vec<Fp>>>();
+        let tree = MerkleTreeImpl::<Rpo256MerkleTreeConfig<Fp>>::new(leaves)?;
+        let proof = tree.prove(&[2, 5])?;
+        MerkleTreeImpl::<Rpo256MerkleTreeConfig<Fp>>::verify(&tree.root(), proof, &[2, 5])
+    }
+
+    #[test]
+    fn prove_rows_to_cap() -> Result<(), Error> {
+        let column = (0..64u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        let row_ids = [3, 17, 40];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let cap = tree.cap(3)?;
+        assert_eq!(cap.0.len(), 8);
+        assert_eq!(tree.cap(0)?.0, vec![tree.root()]);
+        assert!(tree.cap(6).is_err());
+
+        let proof = tree.prove_rows_to_cap(&row_ids, 3)?;
+        let root_proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        assert!(proof.nodes.len() < root_proof.nodes.len());
+        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows_against_cap(
+            &cap,
+            &row_ids,
+            &rows,
+            proof.clone(),
+        )?;
+
+        let mut invalid_cap = cap;
+        invalid_cap.0.swap(0, 2);
+        let res = MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows_against_cap(
+            &invalid_cap,
+            &row_ids,
+            &rows,
+            proof,
+        );
+        assert!(matches!(res, Err(Error::InvalidProof)));
+        Ok(())
+    }
+
+    #[test]
+    fn prove_rows_with_keccak256() -> Result<(), Error> {
+        use ark_ff::BigInteger;
+        use ark_ff::PrimeField;
+        use sha3::Keccak256;
+
+        let column = (0..8u32).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = MatrixMerkleTreeImpl::<Keccak256HashFn>::from_matrix(&matrix);
+        let row_ids = [2, 5];
+        let rows = row_ids.map(|i| [column[i]]);
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        MatrixMerkleTreeImpl::<Keccak256HashFn>::verify_rows(&tree.root(), &row_ids, &rows, proof)?;
+
+        // rows are hashed as `keccak256(abi.encodePacked(uint256(v0), ...))`
+        let expected = Keccak256::digest(column[2].into_bigint().to_bytes_be());
+        let actual = <Keccak256HashFn as ElementHashFn<Fp>>::hash_elements([column[2]]);
+        assert_eq!(expected[..], actual[..]);
+        Ok(())
+    }
+
     struct HashedLeafConfig;
 
     impl MerkleTreeConfig for HashedLeafConfig {
@@ -623,4 +1994,25 @@ mod tests {
             Sha256HashFn::COLLISION_RESISTANCE
         }
     }
+
+    struct QuaternaryLeafConfig;
+
+    impl MerkleTreeConfig for QuaternaryLeafConfig {
+        type Digest = SerdeOutput<Sha256>;
+        type Leaf = u32;
+
+        const ARITY: usize = 4;
+
+        fn hash_leaves(depth: u32, l0: &u32, l1: &u32) -> SerdeOutput<Sha256> {
+            UnhashedLeafConfig::hash_leaves(depth, l0, l1)
+        }
+
+        fn hash_nodes(depth: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest {
+            UnhashedLeafConfig::hash_nodes(depth, n0, n1)
+        }
+
+        fn security_level_bits() -> u32 {
+            Sha256HashFn::COLLISION_RESISTANCE
+        }
+    }
 }
@@ -0,0 +1,134 @@
+//! Counting of the field operations performed to generate a proof.
+//!
+//! Counts are independent of the hardware a proof is generated on which makes
+//! them useful for comparing algorithmic changes. Rather than instrumenting
+//! field arithmetic, each of the prover's hot loops (FFTs, constraint
+//! evaluation, division on the LDE coset, DEEP composition and FRI folding)
+//! records the number of operations it performs so CPU and GPU provers report
+//! the same counts. Operations performed by the AIR (e.g. generating the
+//! trace) and by hashing aren't counted.
+//!
+//! Counters are process wide so proofs generated while counting shouldn't run
+//! concurrently with other proofs.
+use crate::prover::ProvingStage;
+use alloc::collections::BTreeMap;
+use core::ops::Add;
+use core::ops::AddAssign;
+use std::sync::Mutex;
+
+/// Number of field operations. Subtractions and negations count as additions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpCounts {
+    pub mul: u64,
+    pub add: u64,
+    pub inv: u64,
+}
+
+impl OpCounts {
+    pub const fn new(mul: u64, add: u64, inv: u64) -> Self {
+        Self { mul, add, inv }
+    }
+}
+
+impl Add for OpCounts {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            mul: self.mul + rhs.mul,
+            add: self.add + rhs.add,
+            inv: self.inv + rhs.inv,
+        }
+    }
+}
+
+impl AddAssign for OpCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+struct ActiveCounter {
+    stage: ProvingStage,
+    counts: BTreeMap<ProvingStage, OpCounts>,
+}
+
+static ACTIVE_COUNTER: Mutex<Option<ActiveCounter>> = Mutex::new(None);
+
+/// Starts counting field operations. Counting stops when the returned counter
+/// is dropped.
+///
+/// # Panics
+///
+/// Panics if operations are already being counted.
+#[must_use]
+pub fn start() -> OpCounter {
+    let previous_counter = ACTIVE_COUNTER.lock().unwrap().replace(ActiveCounter {
+        stage: ProvingStage::TraceGeneration,
+        counts: BTreeMap::new(),
+    });
+    assert!(
+        previous_counter.is_none(),
+        "operations are already being counted"
+    );
+    OpCounter(())
+}
+
+/// Stops counting field operations when dropped
+pub struct OpCounter(());
+
+impl OpCounter {
+    /// Returns the operations counted so far in each proving stage
+    pub fn report(&self) -> BTreeMap<ProvingStage, OpCounts> {
+        ACTIVE_COUNTER
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .counts
+            .clone()
+    }
+
+    /// Returns the operations counted so far across all proving stages
+    pub fn total(&self) -> OpCounts {
+        self.report()
+            .into_values()
+            .fold(OpCounts::default(), Add::add)
+    }
+}
+
+impl Drop for OpCounter {
+    fn drop(&mut self) {
+        *ACTIVE_COUNTER.lock().unwrap() = None;
+    }
+}
+
+/// Called by the prover before starting each stage
+pub(crate) fn enter(stage: ProvingStage) {
+    if let Some(active) = ACTIVE_COUNTER.lock().unwrap().as_mut() {
+        active.stage = stage;
+    }
+}
+
+/// Adds `counts` to the counts of the current proving stage
+pub(crate) fn record(counts: OpCounts) {
+    if let Some(active) = ACTIVE_COUNTER.lock().unwrap().as_mut() {
+        *active.counts.entry(active.stage).or_default() += counts;
+    }
+}
+
+/// Records a radix-2 FFT over a domain of size `n`. Interpolation scales the
+/// result by `1/n` and coset FFTs scale by powers of the offset.
+pub(crate) fn record_fft(n: usize, is_inverse: bool, is_coset: bool) {
+    let n = n as u64;
+    let butterflies = n / 2 * u64::from(n.max(1).ilog2());
+    let scaling = u64::from(is_inverse) * n + u64::from(is_coset) * n;
+    record(OpCounts::new(butterflies + scaling, 2 * butterflies, 0));
+}
+
+/// Records a batch inversion of `n` elements
+pub(crate) fn record_batch_inversion(n: usize) {
+    if n != 0 {
+        record(OpCounts::new(3 * (n as u64 - 1), 0, 1));
+    }
+}
@@ -0,0 +1,311 @@
+//! Univariate polynomial commitments built on FRI and Merkle trees.
+//!
+//! [`UnivariatePcs`] is the interface of a commitment scheme: parameters are
+//! set up and trimmed to a degree, polynomials are committed to and opened at
+//! a point, and openings are checked against a transcript passed in as a
+//! sponge. [`FriPcs`] implements it. The scheme is transparent: setup samples
+//! nothing and the parameters only fix the maximum degree and FRI options.
+//!
+//! A polynomial is committed to by the Merkle root of its evaluations over a
+//! coset of the LDE domain (in bit-reversed order). Opening at `z` proves the
+//! quotient `(p(x) - p(z)) / (x - z)` is a polynomial with FRI. The verifier
+//! checks the quotient evaluations FRI opens agree with the committed
+//! evaluations of `p` at the same positions.
+use crate::fri;
+use crate::fri::FriChannel;
+use crate::fri::FriOptions;
+use crate::fri::FriProof;
+use crate::hash::Digest;
+use crate::merkle;
+use crate::merkle::MatrixMerkleTree;
+use crate::random::PublicCoin;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::EvaluationDomain;
+use ark_poly::Polynomial;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use core::iter::zip;
+use core::marker::PhantomData;
+use ministark_gpu::utils::bit_reverse;
+use ministark_gpu::utils::bit_reverse_index;
+use ministark_gpu::GpuFftField;
+use snafu::Snafu;
+
+/// Commitment scheme for univariate polynomials
+pub trait UnivariatePcs<F: Field> {
+    type UniversalParams;
+    type CommitterKey;
+    type VerifierKey;
+    type Commitment: Clone + CanonicalSerialize + CanonicalDeserialize;
+    /// Prover data kept from [`Self::commit`] to open the commitment
+    type CommitmentState;
+    type Proof: Clone + CanonicalSerialize + CanonicalDeserialize;
+    /// Transcript the opening is bound to
+    type Sponge;
+    type Error;
+
+    /// Generates parameters for polynomials of degree at most `max_degree`
+    fn setup(max_degree: usize) -> Result<Self::UniversalParams, Self::Error>;
+
+    /// Specializes the parameters to polynomials of degree at most
+    /// `supported_degree`
+    fn trim(
+        pp: &Self::UniversalParams,
+        supported_degree: usize,
+    ) -> Result<(Self::CommitterKey, Self::VerifierKey), Self::Error>;
+
+    fn commit(
+        ck: &Self::CommitterKey,
+        polynomial: &DensePolynomial<F>,
+    ) -> Result<(Self::Commitment, Self::CommitmentState), Self::Error>;
+
+    /// Proves the evaluation of a committed polynomial at `point`. For
+    /// [`FriPcs`] the point must not be on the evaluation domain.
+    fn open(
+        ck: &Self::CommitterKey,
+        polynomial: &DensePolynomial<F>,
+        commitment: &Self::Commitment,
+        state: &Self::CommitmentState,
+        point: F,
+        sponge: &mut Self::Sponge,
+    ) -> Result<Self::Proof, Self::Error>;
+
+    /// Checks `value` is the evaluation of the committed polynomial at
+    /// `point`. Returns `false` if the proof is invalid.
+    fn check(
+        vk: &Self::VerifierKey,
+        commitment: &Self::Commitment,
+        point: F,
+        value: F,
+        proof: Self::Proof,
+        sponge: &mut Self::Sponge,
+    ) -> Result<bool, Self::Error>;
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("polynomial of degree {degree} exceeds the supported degree {max_degree}"))]
+    DegreeTooLarge { degree: usize, max_degree: usize },
+    #[snafu(display("could not open the committed evaluations: {source}"))]
+    Merkle { source: merkle::Error },
+}
+
+/// Parameters of [`FriPcs`]. These double as the committer and verifier key.
+#[derive(Clone, Copy)]
+pub struct FriPcsParams {
+    pub max_degree: usize,
+    pub options: FriOptions,
+    pub num_queries: usize,
+}
+
+impl FriPcsParams {
+    /// Evaluations of committed polynomials are over a domain of this size
+    pub const fn domain_size(&self) -> usize {
+        self.degree_bound() * self.options.blowup_factor()
+    }
+
+    const fn degree_bound(&self) -> usize {
+        (self.max_degree + 1).next_power_of_two()
+    }
+}
+
+/// Prover data of a [`FriPcs`] commitment
+pub struct FriPcsState<F, M> {
+    evaluations: GpuVec<F>,
+    tree: M,
+}
+
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FriPcsProof<F: Field, D: Digest, M: MatrixMerkleTree<F, Root = D>> {
+    pub fri_proof: FriProof<F, D, M>,
+    /// Committed evaluations at the FRI query positions
+    pub evaluations: Vec<F>,
+    pub merkle_proof: M::Proof,
+}
+
+/// Polynomial commitment scheme built on FRI and a Merkle tree `M`. Openings
+/// are made non-interactive with the public coin `P`.
+pub struct FriPcs<F, M, P>(PhantomData<(F, M, P)>);
+
+impl<F, M, P> FriPcs<F, M, P>
+where
+    F: GpuFftField + FftField,
+    M: MatrixMerkleTree<F>,
+    P: PublicCoin<Field = F, Digest = M::Root>,
+{
+    /// Generates parameters with custom FRI options
+    pub const fn setup_with_options(
+        max_degree: usize,
+        options: FriOptions,
+        num_queries: usize,
+    ) -> FriPcsParams {
+        FriPcsParams {
+            max_degree,
+            options,
+            num_queries,
+        }
+    }
+
+    /// Returns the domain committed polynomials are evaluated over
+    fn domain(params: &FriPcsParams) -> Radix2EvaluationDomain<F> {
+        let offset = params.options.domain_offset::<F>();
+        Radix2EvaluationDomain::new_coset(params.domain_size(), offset).unwrap()
+    }
+
+    /// Absorbs the claim being proven and returns a FRI channel that draws
+    /// from `sponge`
+    fn channel(
+        params: &FriPcsParams,
+        commitment: &M::Root,
+        point: F,
+        value: F,
+        sponge: &mut P,
+    ) -> FriChannel<P> {
+        sponge.reseed_with_digest(commitment);
+        sponge.reseed_with_field_elements(&[point, value]);
+        let public_coin = core::mem::replace(sponge, P::new(M::Root::default()));
+        FriChannel::new(public_coin, params.options, params.num_queries)
+    }
+}
+
+impl<F, M, P> UnivariatePcs<F> for FriPcs<F, M, P>
+where
+    F: GpuFftField + FftField,
+    M: MatrixMerkleTree<F>,
+    P: PublicCoin<Field = F, Digest = M::Root>,
+{
+    type UniversalParams = FriPcsParams;
+    type CommitterKey = FriPcsParams;
+    type VerifierKey = FriPcsParams;
+    type Commitment = M::Root;
+    type CommitmentState = FriPcsState<F, M>;
+    type Proof = FriPcsProof<F, M::Root, M>;
+    type Sponge = P;
+    type Error = Error;
+
+    /// Generates parameters with a blowup factor of 4, a folding factor of 2
+    /// and 32 queries. Use [`FriPcs::setup_with_options`] to pick others.
+    fn setup(max_degree: usize) -> Result<FriPcsParams, Error> {
+        Ok(Self::setup_with_options(
+            max_degree,
+            FriOptions::new(4, 2, 8),
+            32,
+        ))
+    }
+
+    fn trim(
+        pp: &FriPcsParams,
+        supported_degree: usize,
+    ) -> Result<(FriPcsParams, FriPcsParams), Error> {
+        if supported_degree > pp.max_degree {
+            return Err(Error::DegreeTooLarge {
+                degree: supported_degree,
+                max_degree: pp.max_degree,
+            });
+        }
+        let params = FriPcsParams {
+            max_degree: supported_degree,
+            ..*pp
+        };
+        Ok((params, params))
+    }
+
+    fn commit(
+        ck: &FriPcsParams,
+        polynomial: &DensePolynomial<F>,
+    ) -> Result<(M::Root, FriPcsState<F, M>), Error> {
+        if polynomial.degree() > ck.max_degree {
+            return Err(Error::DegreeTooLarge {
+                degree: polynomial.degree(),
+                max_degree: ck.max_degree,
+            });
+        }
+        let mut evaluations = Self::domain(ck).fft(polynomial).to_vec_in(GpuAllocator);
+        bit_reverse(&mut evaluations);
+        let tree = M::from_matrix(&Matrix::new(vec![evaluations.to_vec_in(GpuAllocator)]));
+        Ok((tree.root(), FriPcsState { evaluations, tree }))
+    }
+
+    fn open(
+        ck: &FriPcsParams,
+        polynomial: &DensePolynomial<F>,
+        commitment: &M::Root,
+        state: &FriPcsState<F, M>,
+        point: F,
+        sponge: &mut P,
+    ) -> Result<Self::Proof, Error> {
+        let value = polynomial.evaluate(&point);
+        let mut xs = Self::domain(ck).elements().collect::<Vec<F>>();
+        bit_reverse(&mut xs);
+        let mut quotient = zip(&state.evaluations, &xs)
+            .map(|(&eval, &x)| (eval - value, x - point))
+            .collect::<Vec<(F, F)>>();
+        let mut denominators = quotient.iter().map(|&(_, d)| d).collect::<Vec<F>>();
+        ark_ff::batch_inversion(&mut denominators);
+        for ((numerator, _), denominator_inv) in zip(&mut quotient, denominators) {
+            *numerator *= denominator_inv;
+        }
+        let quotient = quotient.into_iter().map(|(q, _)| q).collect::<Vec<F>>();
+
+        let mut channel = Self::channel(ck, commitment, point, value, sponge);
+        let (fri_proof, positions) = fri::prove_with_positions(
+            quotient.to_vec_in(GpuAllocator),
+            ck.degree_bound(),
+            &mut channel,
+        );
+        *sponge = channel.public_coin;
+        let evaluations = positions.iter().map(|&i| state.evaluations[i]).collect();
+        let merkle_proof = state
+            .tree
+            .prove_rows(&positions)
+            .map_err(|source| Error::Merkle { source })?;
+        Ok(FriPcsProof {
+            fri_proof,
+            evaluations,
+            merkle_proof,
+        })
+    }
+
+    fn check(
+        vk: &FriPcsParams,
+        commitment: &M::Root,
+        point: F,
+        value: F,
+        proof: Self::Proof,
+        sponge: &mut P,
+    ) -> Result<bool, Error> {
+        let FriPcsProof {
+            fri_proof,
+            evaluations,
+            merkle_proof,
+        } = proof;
+        let mut channel = Self::channel(vk, commitment, point, value, sponge);
+        let result = fri::verify_with_positions(fri_proof, vk.degree_bound(), &mut channel);
+        *sponge = channel.public_coin;
+        let Ok((positions, quotients)) = result else {
+            return Ok(false);
+        };
+        if evaluations.len() != positions.len() {
+            return Ok(false);
+        }
+        let rows = evaluations.iter().map(|&v| [v]).collect::<Vec<[F; 1]>>();
+        if M::verify_rows(commitment, &positions, &rows, merkle_proof).is_err() {
+            return Ok(false);
+        }
+        let domain = Self::domain(vk);
+        let n = domain.size();
+        Ok(
+            zip(&positions, zip(evaluations, quotients)).all(|(&position, (eval, quotient))| {
+                let x = domain.element(bit_reverse_index(n, position));
+                quotient * (x - point) == eval - value
+            }),
+        )
+    }
+}
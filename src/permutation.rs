@@ -0,0 +1,122 @@
+//! Permutation (multiset equality) argument over base columns.
+//!
+//! Proves the rows of two sets of columns are permutations of each other e.g.
+//! the memory accesses of a VM in execution order and sorted by address. Rows
+//! of each set are compressed into a single value with a random challenge `β`
+//! and with a random challenge `α` the argument checks
+//!
+//! ```text
+//! Π (α - a_i) = Π (α - b_i)
+//! ```
+//!
+//! where `a_i = Σ β^j lhs_j[i]` and `b_i = Σ β^j rhs_j[i]`. An extension
+//! column holds the running product of `(α - a_i) / (α - b_i)` which must end
+//! at one.
+//!
+//! An AIR using a [`PermutationArgument`] allocates one extension column for
+//! the running product and two challenges.
+//! [`PermutationArgument::running_product`] builds the extension column in
+//! [`Trace::build_extension_columns`](crate::Trace::build_extension_columns)
+//! and [`PermutationArgument::constraint_groups`] is added to
+//! [`AirConfig::constraint_groups`](crate::air::AirConfig::constraint_groups).
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ConstraintGroup;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::VerifierChallenge;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::batch_inversion;
+use ark_ff::FftField;
+use ministark_gpu::GpuFftField;
+
+/// Columns and challenges used by a permutation argument. Column indices are
+/// the same as in constraints i.e. extension columns are numbered after the
+/// base columns.
+#[derive(Clone, Debug)]
+pub struct PermutationArgument {
+    /// Base columns of the first set of rows
+    pub lhs: Vec<usize>,
+    /// Base columns of the second set of rows. Must have the same number of
+    /// columns as `lhs`.
+    pub rhs: Vec<usize>,
+    /// Extension column with the running product
+    pub running_product: usize,
+    /// Index of the challenge `α`
+    pub alpha: usize,
+    /// Index of the challenge `β` used to compress each row. Unused if
+    /// `lhs` and `rhs` are single columns.
+    pub beta: usize,
+}
+
+impl PermutationArgument {
+    /// Builds the running product column from the base trace
+    pub fn running_product<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        base_trace: &Matrix<Fp>,
+        challenges: &Challenges<Fq>,
+    ) -> GpuVec<Fq> {
+        assert_eq!(self.lhs.len(), self.rhs.len());
+        let alpha = challenges[self.alpha];
+        let beta = challenges[self.beta];
+        let compress = |columns: &[usize], row: usize| {
+            columns.iter().rev().fold(Fq::ZERO, |acc, &c| {
+                acc * beta + Fq::from(base_trace[c][row])
+            })
+        };
+        let num_rows = base_trace.num_rows();
+        let mut denominators = (0..num_rows)
+            .map(|row| alpha - compress(&self.rhs, row))
+            .collect::<Vec<Fq>>();
+        batch_inversion(&mut denominators);
+
+        let mut product = Fq::ONE;
+        let mut column = Vec::with_capacity_in(num_rows, GpuAllocator);
+        for (row, denominator_inv) in denominators.into_iter().enumerate() {
+            product *= (alpha - compress(&self.lhs, row)) * denominator_inv;
+            column.push(product);
+        }
+        column
+    }
+
+    /// Constraints of the running product. Denominators are multiplied out so
+    /// each constraint has degree 2 in the trace.
+    pub fn constraint_groups<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        trace_len: usize,
+    ) -> Vec<ConstraintGroup<FieldVariant<Fp, Fq>>> {
+        assert_eq!(self.lhs.len(), self.rhs.len());
+        let alpha = self.alpha.challenge();
+        let beta = self.beta.challenge();
+        // `α - Σ β^j columns_j` at the row `offset` rows from the current row
+        let factor = |columns: &[usize], offset| {
+            let compressed = columns
+                .iter()
+                .rev()
+                .map(|c| c.offset(offset))
+                .reduce(|acc, column: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>| {
+                    acc * &beta + column
+                })
+                .expect("permutation argument has no columns");
+            &alpha - compressed
+        };
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::ONE));
+        let product = self.running_product.curr();
+        let next_product = self.running_product.next();
+        vec![
+            ConstraintGroup::first_row(vec![
+                product.clone() * factor(&self.rhs, 0) - factor(&self.lhs, 0),
+            ]),
+            ConstraintGroup::transitions(
+                trace_len,
+                vec![next_product * factor(&self.rhs, 1) - product.clone() * factor(&self.lhs, 1)],
+            ),
+            ConstraintGroup::last_row(trace_len, vec![product - one]),
+        ]
+    }
+}
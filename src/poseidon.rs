@@ -0,0 +1,645 @@
+//! Poseidon hash function over the 64-bit Goldilocks field (`2^64 - 2^32 + 1`).
+//!
+//! Field elements are absorbed natively rather than as bytes which makes
+//! commitments cheap to verify inside a STARK (i.e. for recursion).
+//!
+//! Parameters: state width 12, rate 8, capacity 4, `x^7` S-box, 8 full rounds
+//! and 22 partial rounds. The MDS matrix is the Cauchy matrix
+//! `M[i][j] = 1 / (i + j + 12)`. Round constants are derived from SHA-256 so
+//! digests aren't compatible with other Poseidon implementations.
+use crate::hash::Digest;
+use crate::hash::ElementHashFn;
+use crate::hash::HashFn;
+#[cfg(feature = "merkle")]
+use crate::merkle::MatrixMerkleTreeImpl;
+#[cfg(feature = "merkle")]
+use crate::merkle::MerkleTreeConfig;
+#[cfg(feature = "channel")]
+use crate::random::PublicCoin;
+use alloc::boxed::Box;
+#[cfg(feature = "channel")]
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+#[cfg(feature = "channel")]
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use digest::Digest as _;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use once_cell::race::OnceBox;
+use sha2::Sha256;
+
+pub(crate) const WIDTH: usize = 12;
+pub(crate) const RATE: usize = 8;
+pub(crate) const DIGEST_SIZE: usize = 4;
+const NUM_FULL_ROUNDS: usize = 8;
+const NUM_PARTIAL_ROUNDS: usize = 22;
+pub(crate) const NUM_ROUNDS: usize = NUM_FULL_ROUNDS + NUM_PARTIAL_ROUNDS;
+
+/// Number of bytes packed into each field element when hashing bytes
+const BYTES_PER_ELEMENT: usize = 7;
+
+/// Separates the different ways inputs are absorbed. Stored in the capacity.
+#[derive(Clone, Copy)]
+pub(crate) enum Domain {
+    Elements = 0,
+    Bytes = 1,
+    Merge = 2,
+    MergeWithInt = 3,
+    Transcript = 4,
+}
+
+pub(crate) struct Params {
+    pub round_constants: [[Fp; WIDTH]; NUM_ROUNDS],
+    pub mds: [[Fp; WIDTH]; WIDTH],
+}
+
+pub(crate) fn params() -> &'static Params {
+    static PARAMS: OnceBox<Params> = OnceBox::new();
+    PARAMS.get_or_init(|| {
+        let mut round_constants = [[Fp::ZERO; WIDTH]; NUM_ROUNDS];
+        for (round, constants) in round_constants.iter_mut().enumerate() {
+            for (i, constant) in constants.iter_mut().enumerate() {
+                let mut hasher = Sha256::new();
+                hasher.update(b"ministark-poseidon-goldilocks");
+                hasher.update((round as u64).to_le_bytes());
+                hasher.update((i as u64).to_le_bytes());
+                let digest = hasher.finalize();
+                *constant = Fp::from(u64::from_le_bytes(digest[0..8].try_into().unwrap()));
+            }
+        }
+
+        let mut mds = [[Fp::ZERO; WIDTH]; WIDTH];
+        for (i, row) in mds.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = Fp::from((i + j + WIDTH) as u64).inverse().unwrap();
+            }
+        }
+
+        Box::new(Params {
+            round_constants,
+            mds,
+        })
+    })
+}
+
+/// Applies the Poseidon permutation to `state`
+pub fn permute(state: &mut [Fp; WIDTH]) {
+    for round in 0..NUM_ROUNDS {
+        apply_round(state, round);
+    }
+}
+
+/// Returns true if the S-box is applied to the whole state in `round`.
+/// Partial rounds only apply it to the first element.
+pub(crate) const fn is_full_round(round: usize) -> bool {
+    let half_full_rounds = NUM_FULL_ROUNDS / 2;
+    round < half_full_rounds || round >= half_full_rounds + NUM_PARTIAL_ROUNDS
+}
+
+/// Applies a single round of the permutation to `state`
+pub(crate) fn apply_round(state: &mut [Fp; WIDTH], round: usize) {
+    let Params {
+        round_constants,
+        mds,
+    } = params();
+    for (v, constant) in state.iter_mut().zip(&round_constants[round]) {
+        *v += constant;
+    }
+
+    if is_full_round(round) {
+        for v in state.iter_mut() {
+            *v = v.pow([7]);
+        }
+    } else {
+        state[0] = state[0].pow([7]);
+    }
+
+    let prev = *state;
+    for (v, row) in state.iter_mut().zip(mds) {
+        *v = row.iter().zip(&prev).map(|(&m, &s)| m * s).sum();
+    }
+}
+
+/// Hashes base field elements with the sponge construction. The number of
+/// elements is stored in the capacity so inputs don't need to be padded.
+fn sponge(elements: &[Fp], domain: Domain, len: u64) -> PoseidonDigest {
+    let mut state = [Fp::ZERO; WIDTH];
+    state[RATE] = Fp::from(len);
+    state[RATE + 1] = Fp::from(domain as u64);
+    if elements.is_empty() {
+        permute(&mut state);
+    }
+    for chunk in elements.chunks(RATE) {
+        state[0..chunk.len()].copy_from_slice(chunk);
+        state[chunk.len()..RATE].fill(Fp::ZERO);
+        permute(&mut state);
+    }
+    PoseidonDigest(state[0..DIGEST_SIZE].try_into().unwrap())
+}
+
+/// Digest of the Poseidon hash function. Consists of four field elements.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PoseidonDigest(pub [Fp; DIGEST_SIZE]);
+
+impl Digest for PoseidonDigest {
+    fn as_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (chunk, v) in bytes.chunks_mut(8).zip(&self.0) {
+            chunk.copy_from_slice(&v.into_bigint().0[0].to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Poseidon hash function over the Goldilocks field
+pub struct PoseidonHashFn;
+
+impl HashFn for PoseidonHashFn {
+    type Digest = PoseidonDigest;
+
+    const COLLISION_RESISTANCE: u32 = 128;
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> PoseidonDigest {
+        let bytes = bytes.into_iter().collect::<Vec<u8>>();
+        let elements = bytes
+            .chunks(BYTES_PER_ELEMENT)
+            .map(|chunk| {
+                let mut word = [0; 8];
+                word[0..chunk.len()].copy_from_slice(chunk);
+                Fp::from(u64::from_le_bytes(word))
+            })
+            .collect::<Vec<Fp>>();
+        sponge(&elements, Domain::Bytes, bytes.len() as u64)
+    }
+
+    fn hash_chunks<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> PoseidonDigest {
+        Self::hash(chunks.into_iter().flatten().copied())
+    }
+
+    fn merge(v0: &PoseidonDigest, v1: &PoseidonDigest) -> PoseidonDigest {
+        let mut elements = [Fp::ZERO; 2 * DIGEST_SIZE];
+        elements[0..DIGEST_SIZE].copy_from_slice(&v0.0);
+        elements[DIGEST_SIZE..].copy_from_slice(&v1.0);
+        sponge(&elements, Domain::Merge, elements.len() as u64)
+    }
+
+    fn merge_with_int(seed: &PoseidonDigest, value: u64) -> PoseidonDigest {
+        // `value` is split into two elements since it can exceed the modulus
+        let mut elements = [Fp::ZERO; DIGEST_SIZE + 2];
+        elements[0..DIGEST_SIZE].copy_from_slice(&seed.0);
+        elements[DIGEST_SIZE] = Fp::from(value & 0xFFFF_FFFF);
+        elements[DIGEST_SIZE + 1] = Fp::from(value >> 32);
+        sponge(&elements, Domain::MergeWithInt, elements.len() as u64)
+    }
+}
+
+/// Elements of the base field and its extensions are absorbed as base field
+/// elements
+impl<F: Field<BasePrimeField = Fp>> ElementHashFn<F> for PoseidonHashFn {
+    fn hash_elements(elements: impl IntoIterator<Item = F>) -> PoseidonDigest {
+        let elements = elements
+            .into_iter()
+            .flat_map(|v| v.to_base_prime_field_elements().collect::<Vec<Fp>>())
+            .collect::<Vec<Fp>>();
+        sponge(&elements, Domain::Elements, elements.len() as u64)
+    }
+}
+
+/// Merkle tree config where leaves are field elements that are hashed with
+/// the Poseidon sponge
+#[cfg(feature = "merkle")]
+pub struct PoseidonMerkleTreeConfig<F>(PhantomData<F>);
+
+#[cfg(feature = "merkle")]
+impl<F> Clone for PoseidonMerkleTreeConfig<F> {
+    fn clone(&self) -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[cfg(feature = "merkle")]
+impl<F: Field<BasePrimeField = Fp>> MerkleTreeConfig for PoseidonMerkleTreeConfig<F> {
+    type Digest = PoseidonDigest;
+    type Leaf = Vec<F>;
+
+    fn hash_leaves(_: u32, l0: &Vec<F>, l1: &Vec<F>) -> PoseidonDigest {
+        let h0 = PoseidonHashFn::hash_elements(l0.iter().copied());
+        let h1 = PoseidonHashFn::hash_elements(l1.iter().copied());
+        PoseidonHashFn::merge(&h0, &h1)
+    }
+
+    fn hash_nodes(_: u32, n0: &PoseidonDigest, n1: &PoseidonDigest) -> PoseidonDigest {
+        PoseidonHashFn::merge(n0, n1)
+    }
+
+    fn security_level_bits() -> u32 {
+        PoseidonHashFn::COLLISION_RESISTANCE
+    }
+}
+
+/// Commits to the rows of a matrix. Rows are absorbed natively as field
+/// elements and hashed into leaves with the Poseidon sponge.
+#[cfg(feature = "merkle")]
+pub type PoseidonMatrixMerkleTree = MatrixMerkleTreeImpl<PoseidonHashFn>;
+
+/// Fiat-Shamir transcript built on a Poseidon duplex sponge.
+///
+/// Unlike [`PublicCoinImpl`](crate::random::PublicCoinImpl), which hashes the
+/// seed again for every input and output, the sponge state persists between
+/// operations. Absorbing up to eight field elements or squeezing up to eight
+/// challenges costs a single permutation which keeps the transcript cheap to
+/// replay inside a STARK or SNARK circuit.
+///
+/// * reseeding with a digest absorbs its four elements
+/// * reseeding with field elements absorbs their base field elements
+/// * reseeding with an integer absorbs its low and high 32 bits
+/// * reseeding with bytes absorbs the [`PoseidonHashFn`] digest of the bytes
+///
+/// Queries are the canonical value of a squeezed element modulo the domain
+/// size. Proof-of-work absorbs the nonce into a copy of the sponge and counts
+/// the leading zeros of the first squeezed element.
+#[cfg(feature = "channel")]
+#[derive(Clone)]
+pub struct PoseidonPublicCoin<F> {
+    state: [Fp; WIDTH],
+    /// Number of elements absorbed into the rate since the last permutation
+    num_absorbed: usize,
+    /// Number of unread elements in the rate that can be squeezed
+    num_squeezable: usize,
+    _phantom: PhantomData<F>,
+}
+
+#[cfg(feature = "channel")]
+impl<F> Debug for PoseidonPublicCoin<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PoseidonPublicCoin")
+            .field("state", &self.state)
+            .field("num_absorbed", &self.num_absorbed)
+            .field("num_squeezable", &self.num_squeezable)
+            .finish()
+    }
+}
+
+#[cfg(feature = "channel")]
+impl<F> PoseidonPublicCoin<F> {
+    /// Permutes the state after adding the number of absorbed elements to the
+    /// capacity. This stops absorbing zeros looking like absorbing nothing.
+    fn permute(&mut self) {
+        self.state[RATE] += Fp::from(self.num_absorbed as u64);
+        permute(&mut self.state);
+        self.num_absorbed = 0;
+    }
+
+    fn absorb(&mut self, elements: impl IntoIterator<Item = Fp>) {
+        for element in elements {
+            if self.num_absorbed == RATE {
+                self.permute();
+            }
+            self.state[self.num_absorbed] = element;
+            self.num_absorbed += 1;
+        }
+        // previously squeezed outputs must not be reused after absorbing
+        self.num_squeezable = 0;
+    }
+
+    fn squeeze(&mut self) -> Fp {
+        if self.num_squeezable == 0 {
+            self.permute();
+            self.num_squeezable = RATE;
+        }
+        let element = self.state[RATE - self.num_squeezable];
+        self.num_squeezable -= 1;
+        element
+    }
+
+    fn absorb_int(&mut self, value: u64) {
+        // `value` is split into two elements since it can exceed the modulus
+        self.absorb([Fp::from(value & 0xFFFF_FFFF), Fp::from(value >> 32)]);
+    }
+
+    /// Returns the sponge state and the number of elements absorbed into the
+    /// rate since the last permutation
+    #[cfg(feature = "prover")]
+    pub(crate) const fn sponge_state(&self) -> ([Fp; WIDTH], usize) {
+        (self.state, self.num_absorbed)
+    }
+}
+
+#[cfg(feature = "channel")]
+impl<F: Field<BasePrimeField = Fp>> PublicCoin for PoseidonPublicCoin<F> {
+    type Digest = PoseidonDigest;
+    type Field = F;
+
+    fn new(digest: PoseidonDigest) -> Self {
+        let mut state = [Fp::ZERO; WIDTH];
+        state[RATE + 1] = Fp::from(Domain::Transcript as u64);
+        let mut coin = Self {
+            state,
+            num_absorbed: 0,
+            num_squeezable: 0,
+            _phantom: PhantomData,
+        };
+        coin.absorb(digest.0);
+        coin
+    }
+
+    fn reseed_with_digest(&mut self, val: &PoseidonDigest) {
+        self.absorb(val.0);
+    }
+
+    fn reseed_with_field_elements(&mut self, vals: &[F]) {
+        for val in vals {
+            self.absorb(val.to_base_prime_field_elements());
+        }
+    }
+
+    fn reseed_with_int(&mut self, val: u64) {
+        self.absorb_int(val);
+    }
+
+    fn reseed_with_bytes(&mut self, bytes: &[u8]) {
+        self.reseed_with_digest(&PoseidonHashFn::hash(bytes.iter().copied()));
+    }
+
+    fn draw(&mut self) -> F {
+        let elements = (0..F::extension_degree())
+            .map(|_| self.squeeze())
+            .collect::<Vec<Fp>>();
+        F::from_base_prime_field_elems(&elements).unwrap()
+    }
+
+    fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize> {
+        let domain_size = u64::try_from(domain_size).unwrap();
+        (0..max_n)
+            .map(|_| {
+                let word = self.squeeze().into_bigint().0[0];
+                usize::try_from(word % domain_size).unwrap()
+            })
+            .collect()
+    }
+
+    fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool {
+        let mut coin = self.clone();
+        coin.absorb_int(nonce);
+        let word = coin.squeeze().into_bigint().0[0];
+        word.leading_zeros() >= u32::from(proof_of_work_bits)
+    }
+
+    fn security_level_bits() -> u32 {
+        PoseidonHashFn::COLLISION_RESISTANCE
+    }
+}
+
+/// Sponge state of a Poseidon transcript as laid out in GPU memory
+#[cfg(metal_gpu)]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GpuTranscriptState {
+    state: [Fp; WIDTH],
+    num_absorbed: u32,
+    num_squeezable: u32,
+}
+
+/// Poseidon parameters as laid out in GPU memory
+#[cfg(metal_gpu)]
+#[repr(C)]
+struct GpuParams {
+    round_constants: [[Fp; WIDTH]; NUM_ROUNDS],
+    mds: [[Fp; WIDTH]; WIDTH],
+    /// The integers `0..=RATE` added to the capacity before permuting
+    small_ints: [Fp; RATE + 1],
+}
+
+/// [`PoseidonPublicCoin`] whose sponge state lives in GPU memory.
+///
+/// Elements already in GPU memory (e.g. commitment roots or out-of-domain
+/// evaluations computed on the GPU) are absorbed with
+/// [`Self::reseed_with_gpu_elements`] without a round trip through the host.
+/// Absorbing doesn't wait for the GPU and only squeezed challenges are read
+/// back which suits the multi-round commitment flow on unified memory
+/// devices. Transcripts are identical to those of [`PoseidonPublicCoin`].
+#[cfg(metal_gpu)]
+pub struct GpuPoseidonPublicCoin<F> {
+    /// Work is submitted to a single queue so it executes in order
+    command_queue: &'static ministark_gpu::metal::CommandQueue,
+    stage: ministark_gpu::stage::PoseidonTranscriptStage<Fp>,
+    transcript: ministark_gpu::metal::Buffer,
+    params: ministark_gpu::metal::Buffer,
+    squeezed: ministark_gpu::metal::Buffer,
+    _phantom: PhantomData<F>,
+}
+
+// TODO: unsafe
+#[cfg(metal_gpu)]
+unsafe impl<F> Send for GpuPoseidonPublicCoin<F> {}
+#[cfg(metal_gpu)]
+unsafe impl<F> Sync for GpuPoseidonPublicCoin<F> {}
+
+#[cfg(metal_gpu)]
+impl<F> Debug for GpuPoseidonPublicCoin<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("GpuPoseidonPublicCoin")
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(metal_gpu)]
+impl<F: Field<BasePrimeField = Fp>> GpuPoseidonPublicCoin<F> {
+    /// Maximum number of elements squeezed by a single dispatch
+    const MAX_SQUEEZED: usize = 1024;
+
+    /// Moves the state of `coin` to GPU memory
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Metal library doesn't have the transcript kernel (see
+    /// [`Self::try_from_coin`])
+    pub fn from_coin(coin: &PoseidonPublicCoin<F>) -> Self {
+        Self::try_from_coin(coin)
+            .unwrap_or_else(|err| panic!("Poseidon transcript kernel is unavailable: {err}"))
+    }
+
+    /// Moves the state of `coin` to GPU memory. Callers can keep using `coin`
+    /// on the CPU if this fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Metal library doesn't have the transcript
+    /// kernel e.g. if the shaders couldn't be compiled when the crate was
+    /// built
+    pub fn try_from_coin(coin: &PoseidonPublicCoin<F>) -> Result<Self, String> {
+        let planner = ministark_gpu::prelude::get_planner();
+        let stage = ministark_gpu::stage::PoseidonTranscriptStage::new(&planner.library)?;
+        let command_queue = planner.command_queue();
+        let device = command_queue.device();
+        let Params {
+            round_constants,
+            mds,
+        } = params();
+        let gpu_params = GpuParams {
+            round_constants: *round_constants,
+            mds: *mds,
+            small_ints: core::array::from_fn(|i| Fp::from(i as u64)),
+        };
+        let gpu_transcript = GpuTranscriptState {
+            state: coin.state,
+            num_absorbed: coin.num_absorbed.try_into().unwrap(),
+            num_squeezable: coin.num_squeezable.try_into().unwrap(),
+        };
+        Ok(Self {
+            command_queue,
+            stage,
+            transcript: new_shared_buffer(device, &gpu_transcript),
+            params: new_shared_buffer(device, &gpu_params),
+            squeezed: new_shared_buffer(device, &[Fp::ZERO; Self::MAX_SQUEEZED]),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Waits for pending work and reads the sponge state back from GPU memory
+    pub fn to_coin(&self) -> PoseidonPublicCoin<F> {
+        let command_buffer = self.command_queue.new_command_buffer();
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+        // SAFETY: the buffer holds a transcript state and the GPU is idle
+        let transcript = unsafe { *self.transcript.contents().cast::<GpuTranscriptState>() };
+        PoseidonPublicCoin {
+            state: transcript.state,
+            num_absorbed: transcript.num_absorbed as usize,
+            num_squeezable: transcript.num_squeezable as usize,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Absorbs the first `num_elements` base field elements of `elements`
+    /// without reading them back to the host. The elements mustn't be
+    /// modified until the next draw.
+    pub fn reseed_with_gpu_elements(
+        &mut self,
+        elements: &ministark_gpu::metal::BufferRef,
+        num_elements: usize,
+    ) {
+        assert!(elements.length() as usize >= num_elements * core::mem::size_of::<Fp>());
+        self.dispatch(elements, num_elements, 0);
+    }
+
+    fn absorb(&mut self, elements: &[Fp]) {
+        if elements.is_empty() {
+            return;
+        }
+        let device = self.command_queue.device();
+        let buffer = device.new_buffer_with_data(
+            elements.as_ptr().cast(),
+            core::mem::size_of_val(elements) as u64,
+            ministark_gpu::metal::MTLResourceOptions::StorageModeShared,
+        );
+        self.dispatch(&buffer, elements.len(), 0);
+    }
+
+    fn squeeze(&mut self, n: usize) -> Vec<Fp> {
+        let mut squeezed = Vec::with_capacity(n);
+        while squeezed.len() < n {
+            let num_squeezed = (n - squeezed.len()).min(Self::MAX_SQUEEZED);
+            let command_buffer = self.dispatch(&self.squeezed, 0, num_squeezed);
+            command_buffer.wait_until_completed();
+            // SAFETY: the kernel wrote `num_squeezed` elements and the GPU is idle
+            squeezed.extend_from_slice(unsafe {
+                core::slice::from_raw_parts(self.squeezed.contents().cast::<Fp>(), num_squeezed)
+            });
+        }
+        squeezed
+    }
+
+    /// Commits a command buffer that absorbs and then squeezes elements
+    fn dispatch(
+        &self,
+        elements: &ministark_gpu::metal::BufferRef,
+        num_elements: usize,
+        num_squeezed: usize,
+    ) -> &ministark_gpu::metal::CommandBufferRef {
+        let command_buffer = self.command_queue.new_command_buffer();
+        self.stage.encode(
+            command_buffer,
+            &self.transcript,
+            &self.params,
+            elements,
+            num_elements.try_into().unwrap(),
+            &self.squeezed,
+            num_squeezed.try_into().unwrap(),
+        );
+        command_buffer.commit();
+        command_buffer
+    }
+}
+
+#[cfg(metal_gpu)]
+fn new_shared_buffer<T>(
+    device: &ministark_gpu::metal::DeviceRef,
+    value: &T,
+) -> ministark_gpu::metal::Buffer {
+    device.new_buffer_with_data(
+        (value as *const T).cast(),
+        core::mem::size_of::<T>() as u64,
+        ministark_gpu::metal::MTLResourceOptions::StorageModeShared,
+    )
+}
+
+#[cfg(metal_gpu)]
+impl<F: Field<BasePrimeField = Fp>> PublicCoin for GpuPoseidonPublicCoin<F> {
+    type Digest = PoseidonDigest;
+    type Field = F;
+
+    fn new(digest: PoseidonDigest) -> Self {
+        Self::from_coin(&PoseidonPublicCoin::new(digest))
+    }
+
+    fn reseed_with_digest(&mut self, val: &PoseidonDigest) {
+        self.absorb(&val.0);
+    }
+
+    fn reseed_with_field_elements(&mut self, vals: &[F]) {
+        let elements = vals
+            .iter()
+            .flat_map(|v| v.to_base_prime_field_elements())
+            .collect::<Vec<Fp>>();
+        self.absorb(&elements);
+    }
+
+    fn reseed_with_int(&mut self, val: u64) {
+        self.absorb(&[Fp::from(val & 0xFFFF_FFFF), Fp::from(val >> 32)]);
+    }
+
+    fn reseed_with_bytes(&mut self, bytes: &[u8]) {
+        self.reseed_with_digest(&PoseidonHashFn::hash(bytes.iter().copied()));
+    }
+
+    fn draw(&mut self) -> F {
+        let elements = self.squeeze(F::extension_degree() as usize);
+        F::from_base_prime_field_elems(&elements).unwrap()
+    }
+
+    fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize> {
+        let domain_size = u64::try_from(domain_size).unwrap();
+        self.squeeze(max_n)
+            .into_iter()
+            .map(|v| usize::try_from(v.into_bigint().0[0] % domain_size).unwrap())
+            .collect()
+    }
+
+    fn grind_proof_of_work(&self, proof_of_work_bits: u8) -> Option<u64> {
+        self.to_coin().grind_proof_of_work(proof_of_work_bits)
+    }
+
+    fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool {
+        self.to_coin()
+            .verify_proof_of_work(proof_of_work_bits, nonce)
+    }
+
+    fn security_level_bits() -> u32 {
+        PoseidonHashFn::COLLISION_RESISTANCE
+    }
+}
@@ -0,0 +1,99 @@
+//! Preprocessed columns i.e. base columns that are the same in every proof
+//! of an AIR (selectors, fixed tables etc.).
+//!
+//! Preprocessed columns are committed to once ahead of time rather than in
+//! each proof. Provers reuse the low degree extension and merkle tree of the
+//! columns and proofs only contain their queried rows. Verifiers get the
+//! commitment from [`Stark::preprocessed_commitment`] instead of the proof.
+//!
+//! The preprocessed columns of an AIR are the last
+//! [`AirConfig::NUM_PREPROCESSED_COLUMNS`] of its
+//! [`AirConfig::NUM_BASE_COLUMNS`] base columns. Constraints reference them
+//! like any other base column but [`Trace::base_columns`] only returns the
+//! columns before them.
+//!
+//! [`Trace::base_columns`]: crate::Trace::base_columns
+use crate::air::trace_domain;
+use crate::air::AirConfig;
+use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleTree;
+use crate::stark::Stark;
+use crate::Matrix;
+use crate::ProofOptions;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+
+/// Preprocessed columns along with their low degree extension and commitment
+pub struct PreprocessedColumns<S: Stark> {
+    options: ProofOptions,
+    columns: Matrix<S::Fp>,
+    polys: Matrix<S::Fp>,
+    lde: Matrix<S::Fp>,
+    tree: S::MerkleTree,
+}
+
+impl<S: Stark> PreprocessedColumns<S> {
+    /// Commits to `columns` for proofs generated with `options`. Columns must
+    /// have the same number of rows as the traces they are used with.
+    pub fn new(columns: Matrix<S::Fp>, options: ProofOptions) -> Self {
+        let trace_len = columns.num_rows();
+        let trace_xs = trace_domain::<S::AirConfig>(trace_len);
+        let lde_xs = Radix2EvaluationDomain::new_coset(
+            trace_len * usize::from(options.lde_blowup_factor),
+            S::AirConfig::domain_offset(),
+        )
+        .unwrap();
+        let polys = columns.interpolate(trace_xs);
+        let lde = polys.bit_reversed_evaluate(lde_xs);
+        let tree = S::MerkleTree::from_matrix(&lde);
+        Self {
+            options,
+            columns,
+            polys,
+            lde,
+            tree,
+        }
+    }
+
+    /// Commitment to the columns. This is part of the verification key of the
+    /// AIR.
+    pub fn commitment(&self) -> S::Digest {
+        self.tree.root()
+    }
+
+    /// Options of the proofs these columns can be used with
+    pub const fn options(&self) -> ProofOptions {
+        self.options
+    }
+
+    pub fn trace_len(&self) -> usize {
+        self.columns.num_rows()
+    }
+
+    /// Values of the columns over the trace domain
+    pub const fn columns(&self) -> &Matrix<S::Fp> {
+        &self.columns
+    }
+
+    /// Polynomials of the columns
+    pub const fn polys(&self) -> &Matrix<S::Fp> {
+        &self.polys
+    }
+
+    /// Evaluations of the columns over the LDE domain in bit-reversed order
+    pub const fn lde(&self) -> &Matrix<S::Fp> {
+        &self.lde
+    }
+
+    pub const fn tree(&self) -> &S::MerkleTree {
+        &self.tree
+    }
+
+    /// Returns true if the columns can be used in a proof of a trace with
+    /// `trace_len` rows generated with `options`
+    pub fn is_compatible(&self, trace_len: usize, options: ProofOptions) -> bool {
+        self.trace_len() == trace_len
+            && self.options == options
+            && self.columns.num_cols() == S::AirConfig::NUM_PREPROCESSED_COLUMNS
+    }
+}
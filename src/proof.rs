@@ -1,3 +1,4 @@
+use crate::air::AirConfig;
 use crate::fri::FriProof;
 use crate::merkle::MerkleTree;
 use crate::random::PublicCoin;
@@ -5,6 +6,7 @@ use crate::stark::Stark;
 use crate::trace::Queries;
 use crate::utils::field_bits;
 use crate::ProofOptions;
+use alloc::vec::Vec;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
 use ark_serialize::Valid;
@@ -15,6 +17,9 @@ pub struct Proof<C: Stark> {
     pub trace_len: usize,
     pub base_trace_commitment: C::Digest,
     pub extension_trace_commitment: Option<C::Digest>,
+    /// Commitments to the auxiliary segments after the first. Empty unless
+    /// the AIR has multiple auxiliary segments.
+    pub aux_trace_commitments: Vec<C::Digest>,
     pub composition_trace_commitment: C::Digest,
     pub fri_proof: FriProof<C::Fq, C::Digest, C::MerkleTree>,
     pub pow_nonce: u64,
@@ -30,6 +35,7 @@ impl<C: Stark> Clone for Proof<C> {
             trace_len: self.trace_len,
             base_trace_commitment: self.base_trace_commitment.clone(),
             extension_trace_commitment: self.extension_trace_commitment.clone(),
+            aux_trace_commitments: self.aux_trace_commitments.clone(),
             composition_trace_commitment: self.composition_trace_commitment.clone(),
             fri_proof: self.fri_proof.clone(),
             pow_nonce: self.pow_nonce,
@@ -52,6 +58,10 @@ impl<C: Stark> CanonicalSerialize for Proof<C> {
             .serialize_with_mode(&mut writer, compress)?;
         self.extension_trace_commitment
             .serialize_with_mode(&mut writer, compress)?;
+        if has_aux_segments::<C>() {
+            self.aux_trace_commitments
+                .serialize_with_mode(&mut writer, compress)?;
+        }
         self.composition_trace_commitment
             .serialize_with_mode(&mut writer, compress)?;
         self.fri_proof.serialize_with_mode(&mut writer, compress)?;
@@ -70,6 +80,7 @@ impl<C: Stark> CanonicalSerialize for Proof<C> {
             + self.trace_len.serialized_size(compress)
             + self.base_trace_commitment.serialized_size(compress)
             + self.extension_trace_commitment.serialized_size(compress)
+            + aux_trace_commitments_size::<C>(&self.aux_trace_commitments, compress)
             + self.composition_trace_commitment.serialized_size(compress)
             + self.fri_proof.serialized_size(compress)
             + self.pow_nonce.serialized_size(compress)
@@ -101,6 +112,11 @@ impl<C: Stark> CanonicalDeserialize for Proof<C> {
                 compress,
                 validate,
             )?,
+            aux_trace_commitments: deserialize_aux_trace_commitments::<C, _>(
+                &mut reader,
+                compress,
+                validate,
+            )?,
             composition_trace_commitment: <_>::deserialize_with_mode(
                 &mut reader,
                 compress,
@@ -120,29 +136,290 @@ impl<C: Stark> CanonicalDeserialize for Proof<C> {
 }
 
 impl<C: Stark> Proof<C> {
-    // adapted from Winterfell
-    // also https://github.com/starkware-libs/ethSTARK/blob/master/README.md#7-Measuring-Security
-    // https://eprint.iacr.org/2020/654.pdf section 7.2 for proven security
     pub fn security_level_bits(&self) -> u32 {
-        let field_security = {
-            let lde_domain_size = self.trace_len * self.options.lde_blowup_factor as usize;
-            let extension_field_bits = field_bits::<C::Fq>();
-            extension_field_bits - lde_domain_size.ilog2()
-        };
-
-        let fri_query_security = {
-            let grinding_factor = u32::from(self.options.grinding_factor);
-            let security_per_query = self.options.lde_blowup_factor.ilog2();
-            let num_fri_quiries = u32::from(self.options.num_queries);
-            security_per_query * num_fri_quiries + grinding_factor
-        };
-
-        let merkle_tree_security = C::MerkleTree::security_level_bits();
-        let public_coin_security = C::PublicCoin::security_level_bits();
-
-        field_security
-            .min(fri_query_security)
-            .min(merkle_tree_security)
-            .min(public_coin_security)
+        security_level_bits::<C>(self.options, self.trace_len)
+    }
+
+    /// Splits the proof into frames that can be sent over a transport one at a
+    /// time. Frames are ordered so they can be checked as they arrive by a
+    /// [`StreamingVerifier`](crate::verifier::StreamingVerifier).
+    pub fn into_frames(self) -> [ProofFrame<C>; 3] {
+        let Self {
+            options,
+            trace_len,
+            base_trace_commitment,
+            extension_trace_commitment,
+            aux_trace_commitments,
+            composition_trace_commitment,
+            fri_proof,
+            pow_nonce,
+            trace_queries,
+            execution_trace_ood_evals,
+            composition_trace_ood_evals,
+        } = self;
+        [
+            ProofFrame::Commitments {
+                options,
+                trace_len,
+                base_trace_commitment,
+                extension_trace_commitment,
+                aux_trace_commitments,
+                composition_trace_commitment,
+                execution_trace_ood_evals,
+                composition_trace_ood_evals,
+            },
+            ProofFrame::Fri {
+                fri_proof,
+                pow_nonce,
+            },
+            ProofFrame::Queries { trace_queries },
+        ]
+    }
+}
+
+// adapted from Winterfell
+// also https://github.com/starkware-libs/ethSTARK/blob/master/README.md#7-Measuring-Security
+// https://eprint.iacr.org/2020/654.pdf section 7.2 for proven security
+pub(crate) fn security_level_bits<C: Stark>(options: ProofOptions, trace_len: usize) -> u32 {
+    let query_security = options.conjectured_security_bits(field_bits::<C::Fq>(), trace_len);
+    let merkle_tree_security = C::MerkleTree::security_level_bits();
+    let public_coin_security = C::PublicCoin::security_level_bits();
+    let pow_security = match options.grinding_factor {
+        0 => None,
+        _ => options.pow_hash.security_level_bits(),
+    };
+
+    query_security
+        .min(merkle_tree_security)
+        .min(public_coin_security)
+        .min(pow_security.unwrap_or(u32::MAX))
+}
+
+/// Commitments to auxiliary segments after the first are only serialized for
+/// AIRs with multiple auxiliary segments
+pub(crate) const fn has_aux_segments<C: Stark>() -> bool {
+    C::AirConfig::AUX_SEGMENTS.len() > 1
+}
+
+fn aux_trace_commitments_size<C: Stark>(
+    aux_trace_commitments: &[C::Digest],
+    compress: ark_serialize::Compress,
+) -> usize {
+    if has_aux_segments::<C>() {
+        aux_trace_commitments.serialized_size(compress)
+    } else {
+        0
+    }
+}
+
+fn deserialize_aux_trace_commitments<C: Stark, R: ark_serialize::Read>(
+    reader: R,
+    compress: ark_serialize::Compress,
+    validate: ark_serialize::Validate,
+) -> Result<Vec<C::Digest>, ark_serialize::SerializationError> {
+    if has_aux_segments::<C>() {
+        Vec::deserialize_with_mode(reader, compress, validate)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// A section of a proof that can be sent over a transport on its own
+pub enum ProofFrame<C: Stark> {
+    /// Proof parameters, trace commitments and out-of-domain evaluations
+    Commitments {
+        options: ProofOptions,
+        trace_len: usize,
+        base_trace_commitment: C::Digest,
+        extension_trace_commitment: Option<C::Digest>,
+        aux_trace_commitments: Vec<C::Digest>,
+        composition_trace_commitment: C::Digest,
+        execution_trace_ood_evals: Vec<C::Fq>,
+        composition_trace_ood_evals: Vec<C::Fq>,
+    },
+    /// FRI layers and the proof of work nonce
+    Fri {
+        fri_proof: FriProof<C::Fq, C::Digest, C::MerkleTree>,
+        pow_nonce: u64,
+    },
+    /// Trace queries
+    Queries { trace_queries: Queries<C> },
+}
+
+impl<C: Stark> ProofFrame<C> {
+    const COMMITMENTS_TAG: u8 = 0;
+    const FRI_TAG: u8 = 1;
+    const QUERIES_TAG: u8 = 2;
+}
+
+impl<C: Stark> Clone for ProofFrame<C> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Commitments {
+                options,
+                trace_len,
+                base_trace_commitment,
+                extension_trace_commitment,
+                aux_trace_commitments,
+                composition_trace_commitment,
+                execution_trace_ood_evals,
+                composition_trace_ood_evals,
+            } => Self::Commitments {
+                options: *options,
+                trace_len: *trace_len,
+                base_trace_commitment: base_trace_commitment.clone(),
+                extension_trace_commitment: extension_trace_commitment.clone(),
+                aux_trace_commitments: aux_trace_commitments.clone(),
+                composition_trace_commitment: composition_trace_commitment.clone(),
+                execution_trace_ood_evals: execution_trace_ood_evals.clone(),
+                composition_trace_ood_evals: composition_trace_ood_evals.clone(),
+            },
+            Self::Fri {
+                fri_proof,
+                pow_nonce,
+            } => Self::Fri {
+                fri_proof: fri_proof.clone(),
+                pow_nonce: *pow_nonce,
+            },
+            Self::Queries { trace_queries } => Self::Queries {
+                trace_queries: trace_queries.clone(),
+            },
+        }
+    }
+}
+
+impl<C: Stark> CanonicalSerialize for ProofFrame<C> {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        match self {
+            Self::Commitments {
+                options,
+                trace_len,
+                base_trace_commitment,
+                extension_trace_commitment,
+                aux_trace_commitments,
+                composition_trace_commitment,
+                execution_trace_ood_evals,
+                composition_trace_ood_evals,
+            } => {
+                Self::COMMITMENTS_TAG.serialize_with_mode(&mut writer, compress)?;
+                options.serialize_with_mode(&mut writer, compress)?;
+                trace_len.serialize_with_mode(&mut writer, compress)?;
+                base_trace_commitment.serialize_with_mode(&mut writer, compress)?;
+                extension_trace_commitment.serialize_with_mode(&mut writer, compress)?;
+                if has_aux_segments::<C>() {
+                    aux_trace_commitments.serialize_with_mode(&mut writer, compress)?;
+                }
+                composition_trace_commitment.serialize_with_mode(&mut writer, compress)?;
+                execution_trace_ood_evals.serialize_with_mode(&mut writer, compress)?;
+                composition_trace_ood_evals.serialize_with_mode(&mut writer, compress)?;
+            }
+            Self::Fri {
+                fri_proof,
+                pow_nonce,
+            } => {
+                Self::FRI_TAG.serialize_with_mode(&mut writer, compress)?;
+                fri_proof.serialize_with_mode(&mut writer, compress)?;
+                pow_nonce.serialize_with_mode(&mut writer, compress)?;
+            }
+            Self::Queries { trace_queries } => {
+                Self::QUERIES_TAG.serialize_with_mode(&mut writer, compress)?;
+                trace_queries.serialize_with_mode(&mut writer, compress)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        let tag_size = 0u8.serialized_size(compress);
+        match self {
+            Self::Commitments {
+                options,
+                trace_len,
+                base_trace_commitment,
+                extension_trace_commitment,
+                aux_trace_commitments,
+                composition_trace_commitment,
+                execution_trace_ood_evals,
+                composition_trace_ood_evals,
+            } => {
+                tag_size
+                    + options.serialized_size(compress)
+                    + trace_len.serialized_size(compress)
+                    + base_trace_commitment.serialized_size(compress)
+                    + extension_trace_commitment.serialized_size(compress)
+                    + aux_trace_commitments_size::<C>(aux_trace_commitments, compress)
+                    + composition_trace_commitment.serialized_size(compress)
+                    + execution_trace_ood_evals.serialized_size(compress)
+                    + composition_trace_ood_evals.serialized_size(compress)
+            }
+            Self::Fri {
+                fri_proof,
+                pow_nonce,
+            } => {
+                tag_size + fri_proof.serialized_size(compress) + pow_nonce.serialized_size(compress)
+            }
+            Self::Queries { trace_queries } => tag_size + trace_queries.serialized_size(compress),
+        }
+    }
+}
+
+impl<C: Stark> Valid for ProofFrame<C> {
+    #[inline]
+    fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+        Ok(())
+    }
+}
+
+impl<C: Stark> CanonicalDeserialize for ProofFrame<C> {
+    fn deserialize_with_mode<R: ark_serialize::Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        let tag = u8::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(match tag {
+            Self::COMMITMENTS_TAG => Self::Commitments {
+                options: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+                trace_len: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+                base_trace_commitment: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+                extension_trace_commitment: <_>::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    validate,
+                )?,
+                aux_trace_commitments: deserialize_aux_trace_commitments::<C, _>(
+                    &mut reader,
+                    compress,
+                    validate,
+                )?,
+                composition_trace_commitment: <_>::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    validate,
+                )?,
+                execution_trace_ood_evals: <_>::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    validate,
+                )?,
+                composition_trace_ood_evals: <_>::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    validate,
+                )?,
+            },
+            Self::FRI_TAG => Self::Fri {
+                fri_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+                pow_nonce: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            },
+            Self::QUERIES_TAG => Self::Queries {
+                trace_queries: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            },
+            _ => return Err(ark_serialize::SerializationError::InvalidData),
+        })
     }
 }
@@ -1,102 +1,538 @@
 use crate::air::AirConfig;
 use crate::challenges::Challenges;
 use crate::channel::ProverChannel;
+use crate::checkpoint::read_matrix;
+use crate::checkpoint::write_matrix;
+use crate::checkpoint::Checkpoint;
+use crate::checkpoint::CheckpointError;
+use crate::checkpoint::CheckpointStore;
+use crate::checkpoint::Checkpoints;
 use crate::composer::DeepPolyComposer;
+#[cfg(debug_assertions)]
+use crate::debug::check_coset_consistency;
+use crate::debug::check_degree_hints;
+use crate::debug::sanitize_base_trace;
+use crate::debug::sanitize_extension_trace;
+use crate::domain::DomainKind;
+use crate::domain::TraceDomain;
+use crate::events::DomainSizes;
+use crate::events::Instrumentation;
+use crate::events::NoEvents;
+use crate::events::ProverEvents;
+use crate::events::ProvingReport;
 use crate::fri::FriProver;
+use crate::merkle;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
+use crate::preprocessed::PreprocessedColumns;
 use crate::random::draw_multiple;
 use crate::stark::Stark;
+use crate::trace::CellType;
 use crate::trace::Queries;
+use crate::utils::yield_now;
 use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
 use crate::Air;
+use crate::InvalidProofOptions;
 use crate::Matrix;
+use crate::MatrixView;
 use crate::Proof;
 use crate::ProofOptions;
+use crate::SegmentedMatrix;
 use crate::Trace;
 use alloc::vec::Vec;
+use ark_ff::FftField;
 use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
 use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use core::future::Future;
+use core::iter;
+use core::pin::pin;
+use core::task::Context;
+use core::task::Poll;
+use core::task::RawWaker;
+use core::task::RawWakerVTable;
+use core::task::Waker;
 use ministark_gpu::utils::bit_reverse;
+use ministark_gpu::utils::bit_reverse_index;
+use ministark_gpu::GpuField;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use snafu::ensure;
+use snafu::ResultExt;
+use snafu::Snafu;
+use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
-#[allow(clippy::too_many_lines)]
+/// Stages of proof generation. Provers yield before starting each stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProvingStage {
+    TraceGeneration,
+    BaseTraceCommitment,
+    ExtensionTraceCommitment,
+    ConstraintEvaluation,
+    CompositionTraceCommitment,
+    DeepComposition,
+    Fri,
+    ProofOfWork,
+    Queries,
+}
+
+impl ProvingStage {
+    /// Returns the fraction of proving stages that come before this stage
+    pub fn progress(self) -> f64 {
+        f64::from(self as u8) / f64::from(Self::Queries as u8 + 1)
+    }
+}
+
 pub fn default_prove<S: Stark>(
     this: &S,
     options: ProofOptions,
     witness: S::Witness,
 ) -> Result<Proof<S>, ProvingError> {
-    let now = Instant::now();
-    let trace = this.generate_trace(witness);
-    println!(
-        "Generated execution trace (cols={}, rows={}) in {:.0?}",
-        trace.base_columns().num_cols(),
-        trace.base_columns().num_rows(),
-        now.elapsed(),
-    );
+    default_prove_with_callback(this, options, witness, |_| {})
+}
+
+/// Generates a proof and calls `callback` between proving stages.
+///
+/// Proving large traces can take minutes. The callback lets cooperative
+/// schedulers (GUI event loops, actor frameworks etc.) process pending work
+/// between stages.
+pub fn default_prove_with_callback<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+    mut callback: impl FnMut(ProvingStage),
+) -> Result<Proof<S>, ProvingError> {
+    poll_ready(default_prove_async(this, options, witness, |stage| {
+        callback(stage);
+        core::future::ready(())
+    }))
+}
+
+/// Generates a proof and awaits `yield_now` between proving stages.
+///
+/// `yield_now` can be an executor's yield function (e.g.
+/// `|_| tokio::task::yield_now()`) or [`yield_now`](crate::utils::yield_now)
+/// which works with any executor. This allows the prover to run on an async
+/// runtime without starving other tasks.
+pub async fn default_prove_async<S: Stark, Y: Future<Output = ()>>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+    mut yield_now: impl FnMut(ProvingStage) -> Y,
+) -> Result<Proof<S>, ProvingError> {
+    prove_interruptible(this, options, witness, None, &mut NoEvents, |stage| {
+        let yielded = yield_now(stage);
+        async {
+            yielded.await;
+            Ok(())
+        }
+    })
+    .await
+}
+
+/// Generates a proof or fails with [`ProvingError::DeadlineExceeded`] if
+/// proving takes longer than `budget`.
+///
+/// The deadline is checked between proving stages so proving stops at most
+/// one stage after the deadline passes. Latency-sensitive services can use
+/// this to fail fast rather than wait on a proof that's no longer needed.
+pub fn generate_proof_with_deadline<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+    budget: Duration,
+) -> Result<Proof<S>, ProvingError> {
+    let deadline = Instant::now() + budget;
+    poll_ready(prove_interruptible(
+        this,
+        options,
+        witness,
+        None,
+        &mut NoEvents,
+        |phase| {
+            core::future::ready(if Instant::now() > deadline {
+                DeadlineExceededSnafu {
+                    phase,
+                    progress: phase.progress(),
+                }
+                .fail()
+            } else {
+                Ok(())
+            })
+        },
+    ))
+}
+
+/// Generates a proof, saving the state of each completed phase to `store`.
+/// Phases already saved in `store` are skipped so a prover that was
+/// interrupted resumes from its last checkpoint (see [`checkpoint`]).
+///
+/// Checkpoints are kept once the proof is generated. Use
+/// [`CheckpointStore::clear`] to remove them.
+///
+/// [`checkpoint`]: crate::checkpoint
+pub fn prove_with_checkpoints<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+    store: &mut impl CheckpointStore,
+) -> Result<Proof<S>, ProvingError> {
+    poll_ready(prove_interruptible(
+        this,
+        options,
+        witness,
+        Some(store as &mut (dyn CheckpointStore + Send)),
+        &mut NoEvents,
+        |_| core::future::ready(Ok(())),
+    ))
+}
+
+/// Generates a proof and reports the prover's progress to `events` (see
+/// [`events`]). The other prove functions report nothing.
+///
+/// [`events`]: crate::events
+pub fn prove_with_events<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+    events: &mut impl ProverEvents,
+) -> Result<Proof<S>, ProvingError> {
+    poll_ready(prove_interruptible(
+        this,
+        options,
+        witness,
+        None,
+        events,
+        |_| core::future::ready(Ok(())),
+    ))
+}
+
+/// Generates a proof along with a [`ProvingReport`] of the time and memory
+/// each stage took and the size of the proof
+pub fn prove_with_report<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+) -> Result<(Proof<S>, ProvingReport), ProvingError> {
+    let mut report = ProvingReport::default();
+    let proof = prove_with_events(this, options, witness, &mut report)?;
+    report.proof_size = proof.compressed_size();
+    Ok((proof, report))
+}
+
+/// Generates a proof on an async runtime. The returned future owns the STARK
+/// so it can be spawned as a task.
+///
+/// The future yields to the executor between proving stages (see
+/// [`default_prove_async`]) so services embedding the prover in an async
+/// runtime keep making progress on other tasks while proofs are generated.
+///
+/// Proving itself is the same as [`default_prove`]. Only low memory provers
+/// (see [`Stark::LOW_MEMORY`]) overlap evaluating LDE cosets with hashing.
+pub async fn generate_proof_async<S: Stark>(
+    this: Arc<S>,
+    options: ProofOptions,
+    witness: S::Witness,
+) -> Result<Proof<S>, ProvingError> {
+    default_prove_async(&*this, options, witness, |_| yield_now()).await
+}
+
+/// Generates a proof and awaits `yield_now` before each proving stage.
+/// Proving stops if `yield_now` resolves to an error. Completed phases are
+/// saved to and resumed from `checkpoint_store` if one is provided. Progress
+/// is reported to `events`.
+#[allow(clippy::too_many_lines)]
+async fn prove_interruptible<S: Stark, Y: Future<Output = Result<(), ProvingError>>>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+    checkpoint_store: Option<&mut (dyn CheckpointStore + Send)>,
+    events: &mut (dyn ProverEvents + Send),
+    mut yield_now: impl FnMut(ProvingStage) -> Y,
+) -> Result<Proof<S>, ProvingError> {
+    options.validate().context(InvalidOptionsSnafu)?;
+    let mut instrumentation = Instrumentation::new(events);
+    #[cfg(feature = "op-count")]
+    let mut yield_now = |stage| {
+        crate::op_count::enter(stage);
+        yield_now(stage)
+    };
+    let public_inputs = this.get_public_inputs();
+    let mut checkpoints = Checkpoints::new(checkpoint_store);
+    checkpoints.absorb(&public_inputs);
+    checkpoints.absorb(&options);
+    let resumed_trace = checkpoints
+        .load(Checkpoint::TraceCommitment, |reader| {
+            let trace_len = usize::deserialize_compressed(&mut *reader)?;
+            let base_trace_polys: Matrix<S::Fp> = read_matrix(&mut *reader)?;
+            let num_aux_segments = usize::deserialize_compressed(&mut *reader)?;
+            let aux_segment_polys = (0..num_aux_segments)
+                .map(|_| read_matrix(&mut *reader))
+                .collect::<Result<Vec<Matrix<S::Fq>>, _>>()?;
+            Ok((trace_len, base_trace_polys, aux_segment_polys))
+        })
+        .context(CheckpointSnafu)?;
+
+    yield_now(ProvingStage::TraceGeneration).await?;
+    instrumentation.enter(ProvingStage::TraceGeneration);
+    let (trace, trace_len, resumed_trace_polys) = match resumed_trace {
+        Some((trace_len, base_trace_polys, aux_segment_polys)) => {
+            instrumentation.resumed(Checkpoint::TraceCommitment);
+            (None, trace_len, Some((base_trace_polys, aux_segment_polys)))
+        }
+        None => {
+            let trace = this.generate_trace(witness);
+            let trace_len = trace.len();
+            (Some(trace), trace_len, None)
+        }
+    };
 
-    let now = Instant::now();
-    let air = Air::new(trace.len(), this.get_public_inputs(), options);
+    let domain_kind = <<S::AirConfig as AirConfig>::Domain as TraceDomain<S::Fp>>::KIND;
+    ensure!(
+        domain_kind == DomainKind::Radix2,
+        UnsupportedDomainSnafu { kind: domain_kind }
+    );
+    let lde_domain_size = trace_len * usize::from(options.lde_blowup_factor);
+    let max_domain_size = 1usize
+        .checked_shl(<S::Fp as FftField>::TWO_ADICITY)
+        .unwrap_or(usize::MAX);
+    ensure!(
+        lde_domain_size <= max_domain_size,
+        DomainTooLargeSnafu {
+            size: lde_domain_size,
+            max: max_domain_size,
+        }
+    );
+    let air = Air::try_new(trace_len, public_inputs, options).context(InvalidOptionsSnafu)?;
     let public_coin = this.gen_public_coin(&air);
     let mut channel = ProverChannel::<S>::new(&air, public_coin);
-    println!("Init air: {:?}", now.elapsed());
+    let preprocessed = if S::AirConfig::NUM_PREPROCESSED_COLUMNS == 0 {
+        None
+    } else {
+        let preprocessed = this
+            .preprocessed_columns()
+            .ok_or(ProvingError::MissingPreprocessedColumns)?;
+        ensure!(
+            preprocessed.is_compatible(trace_len, options),
+            IncompatiblePreprocessedColumnsSnafu
+        );
+        channel.commit_preprocessed_trace(&preprocessed.commitment());
+        Some(preprocessed)
+    };
+    instrumentation.domains(&DomainSizes {
+        trace: trace_len,
+        lde: air.lde_domain().size(),
+        constraint_evaluation: air.ce_domain().size(),
+        num_base_columns: S::AirConfig::NUM_BASE_COLUMNS,
+        num_extension_columns: S::AirConfig::NUM_EXTENSION_COLUMNS,
+    });
 
-    let now = Instant::now();
+    // compile the AIR's constraint evaluation kernel (or lower the constraint
+    // for the interpreter kernel) ahead of time. Constraints are evaluated on
+    // the CPU if this fails so errors can be ignored.
+    #[cfg(metal_gpu)]
+    if S::AirConfig::SPECIALIZE_GPU_KERNEL {
+        let kernel = crate::codegen::MetalConstraintKernel::generate(
+            air.compiled_composition_constraint(),
+            S::AirConfig::NUM_BASE_COLUMNS,
+            S::AirConfig::NUM_EXTENSION_COLUMNS,
+        );
+        if let Err(err) = kernel.and_then(|kernel| kernel.compile()) {
+            instrumentation.note(&format!("Using CPU constraint evaluation: {err}"));
+        }
+    } else if S::AirConfig::INTERPRET_GPU_CONSTRAINTS {
+        let program = crate::codegen::ConstraintProgram::lower(
+            air.compiled_composition_constraint(),
+            S::AirConfig::NUM_BASE_COLUMNS,
+            S::AirConfig::NUM_EXTENSION_COLUMNS,
+        );
+        if let Err(err) = program {
+            instrumentation.note(&format!("Using CPU constraint evaluation: {err}"));
+        }
+    }
+
+    yield_now(ProvingStage::BaseTraceCommitment).await?;
+    instrumentation.enter(ProvingStage::BaseTraceCommitment);
     let trace_xs = air.trace_domain();
     let lde_xs = air.lde_domain();
-    let base_trace = trace.base_columns();
-    assert_eq!(S::AirConfig::NUM_BASE_COLUMNS, base_trace.num_cols());
-    let base_trace_polys = base_trace.interpolate(trace_xs);
-    let mut base_trace_lde = base_trace_polys.bit_reversed_evaluate(lde_xs);
-    let base_trace_tree = S::MerkleTree::from_matrix(&base_trace_lde);
-    println!("Base trace commitment: {:?}", now.elapsed());
+    let (base_trace_polys, mut resumed_aux_segment_polys) = match (&trace, resumed_trace_polys) {
+        (_, Some((base_trace_polys, aux_segment_polys))) => {
+            (base_trace_polys, Some(aux_segment_polys.into_iter()))
+        }
+        (Some(trace), None) => {
+            let base_trace = trace.base_columns();
+            let num_base_cols =
+                S::AirConfig::NUM_BASE_COLUMNS - S::AirConfig::NUM_PREPROCESSED_COLUMNS;
+            ensure!(
+                base_trace.num_cols() == num_base_cols,
+                ColumnCountMismatchSnafu {
+                    expected: num_base_cols,
+                    actual: base_trace.num_cols(),
+                }
+            );
+            if S::AirConfig::SANITIZE_WITNESS {
+                sanitize_base_trace::<S::AirConfig>(base_trace)?;
+            }
+            let base_trace_polys = base_trace.interpolate(trace_xs);
+            if S::AirConfig::SANITIZE_WITNESS {
+                check_degree_hints::<S::AirConfig>(&base_trace_polys)?;
+            }
+            (base_trace_polys, None)
+        }
+        (None, None) => unreachable!("trace is generated unless it was resumed"),
+    };
+    let (mut base_trace_lde, base_trace_tree) = build_trace_commitment::<S, S::Fp>(
+        &air,
+        &base_trace_polys,
+        S::AirConfig::BASE_COLUMN_DEGREE_HINTS,
+    );
 
     channel.commit_base_trace(base_trace_tree.root());
-    let num_challenges = air.num_challenges();
-    let challenges = Challenges::new(draw_multiple(&mut channel.public_coin, num_challenges));
-    let hints = air.gen_hints(&challenges);
 
-    let now = Instant::now();
-    let extension_trace = trace.build_extension_columns(&challenges);
-    let num_extension_cols = extension_trace.as_ref().map_or(0, Matrix::num_cols);
-    assert_eq!(S::AirConfig::NUM_EXTENSION_COLUMNS, num_extension_cols);
-    let extension_trace_polys = extension_trace.as_ref().map(|t| t.interpolate(trace_xs));
-    let mut extension_trace_lde = extension_trace_polys
-        .as_ref()
-        .map(|p| p.bit_reversed_evaluate(lde_xs));
-    let extension_trace_tree = extension_trace_lde.as_ref().map(S::MerkleTree::from_matrix);
-    if let Some(t) = extension_trace_tree.as_ref() {
-        channel.commit_extension_trace(t.root());
+    yield_now(ProvingStage::ExtensionTraceCommitment).await?;
+    instrumentation.enter(ProvingStage::ExtensionTraceCommitment);
+    // extension columns are built in one round per auxiliary segment. Each
+    // round draws its challenges after the previous round was committed to.
+    let mut challenges = Vec::new();
+    let mut aux_segments = Vec::new();
+    let mut aux_segment_polys = Vec::new();
+    let mut aux_segment_ldes = Vec::new();
+    let mut aux_trees = Vec::new();
+    for (i, segment) in air.aux_segments().into_iter().enumerate() {
+        challenges.extend(draw_multiple(
+            &mut channel.public_coin,
+            segment.num_challenges,
+        ));
+        let polys = if let Some(resumed) = &mut resumed_aux_segment_polys {
+            if segment.num_columns == 0 {
+                continue;
+            }
+            resumed.next().ok_or(ProvingError::Checkpoint {
+                source: CheckpointError::Mismatch {
+                    checkpoint: Checkpoint::TraceCommitment,
+                },
+            })?
+        } else {
+            let segment_challenges = Challenges::new(challenges.clone());
+            let trace = trace.as_ref().unwrap();
+            let columns = trace.build_aux_segment(i, &aux_segments, &segment_challenges);
+            let num_columns = columns.as_ref().map_or(0, Matrix::num_cols);
+            ensure!(
+                num_columns == segment.num_columns,
+                ColumnCountMismatchSnafu {
+                    expected: segment.num_columns,
+                    actual: num_columns,
+                }
+            );
+            let Some(columns) = columns else { continue };
+            if S::AirConfig::SANITIZE_WITNESS {
+                sanitize_extension_trace::<S::AirConfig>(&columns)?;
+            }
+            let polys = columns.interpolate(trace_xs);
+            aux_segments.push(columns);
+            polys
+        };
+        let (lde, tree) = build_trace_commitment::<S, S::Fq>(&air, &polys, &[]);
+        if i == 0 {
+            channel.commit_extension_trace(tree.root());
+        } else {
+            channel.commit_aux_trace(tree.root());
+        }
+        aux_segment_polys.push(polys);
+        aux_segment_ldes.extend(lde);
+        aux_trees.push(tree);
     }
-    println!("Extension trace commitment: {:?}", now.elapsed());
+    let challenges = Challenges::new(challenges);
+    let hints = air.gen_hints(&challenges);
+    let extension_trace = (!aux_segments.is_empty()).then(|| Matrix::join(aux_segments));
+    let mut extension_trace_lde =
+        (!aux_segment_ldes.is_empty()).then(|| Matrix::join(aux_segment_ldes));
 
     #[cfg(debug_assertions)]
-    this.validate_constraints(&challenges, &hints, base_trace, extension_trace.as_ref());
+    if let Some(trace) = &trace {
+        let base_trace = trace.base_columns();
+        if let Some(preprocessed) = preprocessed {
+            let mut base_trace = base_trace.clone();
+            base_trace.append(preprocessed.columns().clone());
+            this.validate_constraints(&challenges, &hints, &base_trace, extension_trace.as_ref())?;
+        } else {
+            this.validate_constraints(&challenges, &hints, base_trace, extension_trace.as_ref())?;
+        }
+    }
+    if trace.is_some() {
+        checkpoints
+            .save(Checkpoint::TraceCommitment, |writer| {
+                trace_len.serialize_compressed(&mut *writer)?;
+                write_matrix(&base_trace_polys, &mut *writer)?;
+                aux_segment_polys.len().serialize_compressed(&mut *writer)?;
+                for polys in &aux_segment_polys {
+                    write_matrix(polys, &mut *writer)?;
+                }
+                Ok(())
+            })
+            .context(CheckpointSnafu)?;
+    }
     drop((trace, extension_trace));
+    checkpoints.absorb(&base_trace_tree.root());
+    for tree in &aux_trees {
+        checkpoints.absorb(&tree.root());
+    }
+    let extension_trace_polys =
+        (!aux_segment_polys.is_empty()).then(|| Matrix::join(aux_segment_polys));
+    let mut aux_trees = aux_trees.into_iter();
+    let extension_trace_tree = aux_trees.next();
+    let aux_trees = aux_trees.collect::<Vec<S::MerkleTree>>();
 
-    let composition_trace_polys: Matrix<S::Fq>;
-    let composition_trace_lde: Matrix<S::Fq>;
-    let composition_trace_tree: S::MerkleTree;
-    {
+    let composition_coeffs = this.gen_composition_constraint_coeffs(&mut channel.public_coin, &air);
+    let resumed_composition_trace_polys: Option<Matrix<S::Fq>> = checkpoints
+        .load(Checkpoint::ConstraintEvaluation, |reader| {
+            read_matrix(reader)
+        })
+        .context(CheckpointSnafu)?;
+
+    yield_now(ProvingStage::ConstraintEvaluation).await?;
+    instrumentation.enter(ProvingStage::ConstraintEvaluation);
+    let composition_trace_polys = if let Some(polys) = resumed_composition_trace_polys {
+        instrumentation.resumed(Checkpoint::ConstraintEvaluation);
+        polys
+    } else {
         // To prevent allocating more memory, just re-order the values in the trace to
         // be in natural order. Note that for the remainder of the protocol the trace
         // should entirely be in bit-reversed order hence why this function is
-        // called again at the end of the block.
+        // called again after constraints are evaluated.
         let ce_lde_xs = air.ce_domain();
         let ce_domain_size = ce_lde_xs.size();
-        let base_trace_ce_cols = bit_reverse_ce_trace(ce_domain_size, &mut base_trace_lde);
-        let extension_trace_ce_cols = extension_trace_lde
-            .as_mut()
-            .map(|t| bit_reverse_ce_trace(ce_domain_size, t));
+        // low memory provers have no LDEs so the trace polynomials are
+        // evaluated over the constraint evaluation domain instead
+        let mut base_trace_ce = None;
+        let mut extension_trace_ce = None;
+        let mut base_trace_ce_cols = match base_trace_lde.as_mut() {
+            Some(lde) => bit_reverse_ce_trace(ce_domain_size, lde),
+            None => MatrixView::from(&*base_trace_ce.insert(base_trace_polys.evaluate(ce_lde_xs))),
+        };
+        // the preprocessed LDE is shared so only its rows over the constraint
+        // evaluation domain are copied rather than the whole LDE
+        let preprocessed_ce = preprocessed.map(|p| copy_ce_trace(ce_domain_size, p.lde()));
+        if let Some(preprocessed_ce) = &preprocessed_ce {
+            base_trace_ce_cols.append(MatrixView::from(preprocessed_ce));
+        }
+        let extension_trace_ce_cols = match (extension_trace_lde.as_mut(), &extension_trace_polys) {
+            (Some(lde), _) => Some(bit_reverse_ce_trace(ce_domain_size, lde)),
+            (None, Some(polys)) => Some(MatrixView::from(
+                &*extension_trace_ce.insert(polys.evaluate(ce_lde_xs)),
+            )),
+            (None, None) => None,
+        };
 
-        let num_composition_coeffs = air.num_composition_constraint_coeffs();
-        let composition_coeffs = draw_multiple(&mut channel.public_coin, num_composition_coeffs);
         let x_lde = ce_lde_xs.elements().collect::<Vec<_>>();
-
-        let now = Instant::now();
         let composition_evals = S::AirConfig::eval_constraint(
-            air.composition_constraint(),
+            air.compiled_composition_constraint(),
             &challenges,
             &hints,
             &composition_coeffs,
@@ -105,11 +541,32 @@ pub fn default_prove<S: Stark>(
             &base_trace_ce_cols,
             extension_trace_ce_cols.as_deref(),
         );
-        println!("Constraint eval: {:?}", now.elapsed());
+        drop(x_lde);
+        if let Some(lde) = base_trace_lde.as_mut() {
+            bit_reverse_ce_trace(ce_domain_size, lde);
+        }
+        if let Some(lde) = extension_trace_lde.as_mut() {
+            bit_reverse_ce_trace(ce_domain_size, lde);
+        }
 
-        let now = Instant::now();
         let composition_poly =
-            GpuVec::try_from(composition_evals.into_polynomials(air.ce_domain())).unwrap();
+            GpuVec::try_from(composition_evals.into_polynomials(air.ce_domain()))
+                .map_err(|message| ProvingError::Gpu { message })?;
+        #[cfg(debug_assertions)]
+        {
+            let base_trace_polys = SegmentedMatrix::join(
+                iter::once(&base_trace_polys).chain(preprocessed.map(PreprocessedColumns::polys)),
+            );
+            check_coset_consistency(
+                &air,
+                &composition_poly,
+                &composition_coeffs,
+                &challenges,
+                &hints,
+                &base_trace_polys,
+                extension_trace_polys.as_ref(),
+            )?;
+        }
         let mut composition_trace_cols = (0..air.ce_blowup_factor())
             .map(|_| Vec::with_capacity_in(air.trace_len(), GpuAllocator))
             .collect::<Vec<_>>();
@@ -118,77 +575,367 @@ pub fn default_prove<S: Stark>(
                 composition_trace_cols[i].push(chunk[i]);
             }
         }
-        composition_trace_polys = Matrix::new(composition_trace_cols);
-        composition_trace_lde = composition_trace_polys.bit_reversed_evaluate(air.lde_domain());
-        composition_trace_tree = S::MerkleTree::from_matrix(&composition_trace_lde);
-        channel.commit_composition_trace(composition_trace_tree.root());
-        println!("Composition trace commitment: {:?}", now.elapsed());
-
-        bit_reverse_ce_trace(ce_domain_size, &mut base_trace_lde);
-        extension_trace_lde
-            .as_mut()
-            .map(|t| bit_reverse_ce_trace(ce_domain_size, t));
-    }
+        drop(composition_poly);
+        let composition_trace_polys = Matrix::new(composition_trace_cols);
+        checkpoints
+            .save(Checkpoint::ConstraintEvaluation, |writer| {
+                write_matrix(&composition_trace_polys, writer)
+            })
+            .context(CheckpointSnafu)?;
+        composition_trace_polys
+    };
+
+    yield_now(ProvingStage::CompositionTraceCommitment).await?;
+    instrumentation.enter(ProvingStage::CompositionTraceCommitment);
+    let (composition_trace_lde, composition_trace_tree) =
+        build_trace_commitment::<S, S::Fq>(&air, &composition_trace_polys, &[]);
+    checkpoints.absorb(&composition_trace_tree.root());
+    channel.commit_composition_trace(composition_trace_tree.root());
 
-    let now = Instant::now();
+    // low memory provers never materialize the LDEs. Queried rows are
+    // evaluated from the trace polynomials instead.
+    let trace_ldes =
+        base_trace_lde
+            .zip(composition_trace_lde)
+            .map(|(base_trace_lde, composition_trace_lde)| {
+                (base_trace_lde, extension_trace_lde, composition_trace_lde)
+            });
+    let trace_polys = S::LOW_MEMORY.then(|| {
+        (
+            extension_trace_polys.clone(),
+            composition_trace_polys.clone(),
+        )
+    });
+
+    yield_now(ProvingStage::DeepComposition).await?;
+    instrumentation.enter(ProvingStage::DeepComposition);
     let z = channel.get_ood_point();
+    // preprocessed polynomials are borrowed rather than copied onto the trace
     let mut deep_poly_composer = DeepPolyComposer::new(
         &air,
         z,
-        base_trace_polys,
+        SegmentedMatrix::join(
+            iter::once(&base_trace_polys).chain(preprocessed.map(PreprocessedColumns::polys)),
+        ),
         extension_trace_polys,
         composition_trace_polys,
-    );
+    )
+    .with_out_of_order_ffts(S::BIT_REVERSED_PIPELINE);
     let (execution_trace_oods, composition_trace_oods) = deep_poly_composer.get_ood_evals();
     channel.send_ood_evals(execution_trace_oods, composition_trace_oods);
 
     let deep_coeffs = this.gen_deep_coeffs(&mut channel.public_coin, &air);
-    let deep_composition_poly = deep_poly_composer.into_deep_poly(deep_coeffs);
-    // let deep_xs = Radix2EvaluationDomain::new(lde_xs.size());
-    let deep_composition_lde = deep_composition_poly.into_bit_reversed_evaluations(lde_xs);
-    println!("Deep composition: {:?}", now.elapsed());
+    let resumed_fri_layers = checkpoints
+        .load(Checkpoint::Fri, |reader| {
+            let num_layers = usize::deserialize_compressed(&mut *reader)?;
+            let layers = (0..num_layers)
+                .map(|_| read_matrix(&mut *reader))
+                .collect::<Result<Vec<Matrix<S::Fq>>, _>>()?;
+            let remainder_coeffs = Vec::<S::Fq>::deserialize_compressed(&mut *reader)?;
+            Ok((layers, remainder_coeffs))
+        })
+        .context(CheckpointSnafu)?;
+    let deep_composition_lde = resumed_fri_layers
+        .is_none()
+        .then(|| deep_poly_composer.into_deep_evaluations(deep_coeffs));
 
-    let now = Instant::now();
+    yield_now(ProvingStage::Fri).await?;
+    instrumentation.enter(ProvingStage::Fri);
     let fri_options = options.into_fri_options();
-    let mut fri_prover = FriProver::<S::Fq, S::Digest, S::MerkleTree>::new(fri_options);
-    fri_prover.build_layers(&mut channel, deep_composition_lde.try_into().unwrap());
-    println!("FRI: {:?}", now.elapsed());
+    let mut fri_prover = FriProver::<S::Fq, S::Digest, S::MerkleTree>::new(fri_options)
+        .with_bit_reversed_folding(S::BIT_REVERSED_PIPELINE);
+    if let Some((layers, remainder_coeffs)) = resumed_fri_layers {
+        instrumentation.resumed(Checkpoint::Fri);
+        fri_prover.restore_layers(&mut channel, layers, remainder_coeffs);
+    } else {
+        fri_prover.build_layers(&mut channel, deep_composition_lde.unwrap());
+        let (layers, remainder_coeffs) = fri_prover.layer_evaluations();
+        checkpoints
+            .save(Checkpoint::Fri, |writer| {
+                layers.len().serialize_compressed(&mut *writer)?;
+                for layer in layers {
+                    write_matrix(layer, &mut *writer)?;
+                }
+                remainder_coeffs.serialize_compressed(writer)
+            })
+            .context(CheckpointSnafu)?;
+    }
 
-    let now = Instant::now();
+    yield_now(ProvingStage::ProofOfWork).await?;
+    instrumentation.enter(ProvingStage::ProofOfWork);
     channel.grind_fri_commitments();
-    println!("Proof of work: {:?}", now.elapsed());
 
+    yield_now(ProvingStage::Queries).await?;
+    instrumentation.enter(ProvingStage::Queries);
     let query_positions = Vec::from_iter(channel.get_fri_query_positions());
     let fri_proof = fri_prover.into_proof(&query_positions);
 
-    let queries = Queries::new(
-        &base_trace_lde,
-        extension_trace_lde.as_ref(),
-        &composition_trace_lde,
-        &base_trace_tree,
-        extension_trace_tree.as_ref(),
-        &composition_trace_tree,
-        &query_positions,
-    );
-    Ok(channel.build_proof(queries, fri_proof))
+    let queries =
+        if let Some((base_trace_lde, extension_trace_lde, composition_trace_lde)) = &trace_ldes {
+            Queries::new(
+                base_trace_lde,
+                extension_trace_lde.as_ref(),
+                composition_trace_lde,
+                &base_trace_tree,
+                extension_trace_tree.as_ref(),
+                &composition_trace_tree,
+                &query_positions,
+            )
+            .context(MerkleSnafu)?
+        } else {
+            let (extension_trace_polys, composition_trace_polys) = trace_polys.unwrap();
+            let query_xs = query_positions
+                .iter()
+                .map(|&i| lde_xs.element(bit_reverse_index(lde_xs.size(), i)))
+                .collect::<Vec<_>>();
+            let evaluate = |polys: &Matrix<S::Fq>| {
+                query_xs
+                    .iter()
+                    .map(|&x| polys.evaluate_at(S::Fq::from(x)))
+                    .collect()
+            };
+            Queries::from_rows(
+                query_xs
+                    .iter()
+                    .map(|&x| base_trace_polys.evaluate_at(x))
+                    .collect(),
+                extension_trace_polys.as_ref().map(evaluate),
+                evaluate(&composition_trace_polys),
+                &base_trace_tree,
+                extension_trace_tree.as_ref(),
+                &composition_trace_tree,
+                &query_positions,
+            )
+            .context(MerkleSnafu)?
+        };
+    let queries = queries
+        .with_aux_segments(&aux_trees, &query_positions)
+        .context(MerkleSnafu)?;
+    let queries = match preprocessed {
+        Some(preprocessed) => queries
+            .with_preprocessed(preprocessed, &query_positions)
+            .context(MerkleSnafu)?,
+        None => queries,
+    };
+    let proof = channel.build_proof(queries, fri_proof);
+    instrumentation.finish();
+    Ok(proof)
 }
 
 /// Errors that can occur during the proving stage
-#[derive(Debug)]
+#[derive(Debug, Snafu)]
 pub enum ProvingError {
+    #[snafu(display("proof generation failed"))]
     Fail,
-    // TODO
+    /// `explanation` holds the value of each sub-expression of the constraint
+    /// at the row (see [`crate::debug::explain_constraint_at`])
+    #[snafu(display("constraint `{constraint}` is not satisfied at row `{row}`"))]
+    UnsatisfiedConstraint {
+        constraint: usize,
+        row: usize,
+        explanation: String,
+    },
+    #[snafu(display(
+        "constraint `{name}` (index `{constraint}`) is not satisfied at row `{row}`"
+    ))]
+    UnsatisfiedNamedConstraint {
+        name: String,
+        constraint: usize,
+        row: usize,
+        explanation: String,
+    },
+    #[snafu(display("domain of size `{size}` exceeds the field's maximum of `{max}`"))]
+    DomainTooLarge { size: usize, max: usize },
+    #[snafu(display("proofs over `{kind:?}` domains aren't supported"))]
+    UnsupportedDomain { kind: DomainKind },
+    #[snafu(display("expected `{expected}` trace columns, but `{actual}` were generated"))]
+    ColumnCountMismatch { expected: usize, actual: usize },
+    #[snafu(display("GPU buffer error: {message}"))]
+    Gpu { message: String },
+    #[snafu(display("failed to open trace commitments: {source}"))]
+    Merkle { source: merkle::Error },
+    #[snafu(display("invalid proof options: {source}"))]
+    InvalidOptions { source: InvalidProofOptions },
+    #[snafu(display(
+        "value at row `{row}` of column `{column}` is not a canonical field element"
+    ))]
+    NonCanonicalValue { column: usize, row: usize },
+    #[snafu(display(
+        "value at row `{row}` of column `{column}` is out of range for `{cell_type:?}`"
+    ))]
+    ValueOutOfRange {
+        column: usize,
+        row: usize,
+        cell_type: CellType,
+    },
+    #[snafu(display("polynomial of column `{column}` exceeds its degree hint of `{degree}`"))]
+    DegreeHintExceeded { column: usize, degree: usize },
+    #[snafu(display("failed to checkpoint proving state: {source}"))]
+    Checkpoint { source: CheckpointError },
+    #[snafu(display("AIR has preprocessed columns but none were provided"))]
+    MissingPreprocessedColumns,
+    #[snafu(display(
+        "preprocessed columns were committed to for a different trace length or proof options"
+    ))]
+    IncompatiblePreprocessedColumns,
+    #[snafu(display(
+        "composition polynomial is inconsistent with its evaluations over the constraint evaluation coset"
+    ))]
+    InconsistentCosetEvaluations,
+    #[snafu(display(
+        "proving exceeded its deadline before the {phase:?} stage ({:.0}% of stages complete)",
+        progress * 100.0
+    ))]
+    DeadlineExceeded { phase: ProvingStage, progress: f64 },
 }
 
-/// Bit reverses the first ce_domain_size many values of the matrix columns.
-/// Returns a slice to the portion of the columns that were bit reversed
-fn bit_reverse_ce_trace<F: Field>(ce_domain_size: usize, trace: &mut Matrix<F>) -> Vec<&[F]> {
-    trace
-        .0
-        .iter_mut()
+/// Polls a future that never returns [`Poll::Pending`] to completion
+fn poll_ready<F: Future>(future: F) -> F::Output {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RAW_WAKER, |_| {}, |_| {}, |_| {});
+    const RAW_WAKER: RawWaker = RawWaker::new(core::ptr::null(), &VTABLE);
+    // SAFETY: the vtable functions are no-ops that ignore the data pointer
+    let waker = unsafe { Waker::from_raw(RAW_WAKER) };
+    let mut cx = Context::from_waker(&waker);
+    match pin!(future).poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("future is always ready"),
+    }
+}
+
+/// Commits to the low degree extension of trace polynomials. Returns the LDE
+/// along with its commitment unless the prover is [low memory].
+///
+/// Rows of the bit-reversed LDE are grouped into cosets of the trace domain.
+/// Low memory provers evaluate and hash one coset at a time and only keep the
+/// tree so the LDE is never materialized. Evaluating a coset overlaps with
+/// hashing the previous one. Peak memory is then proportional to
+/// the trace rather than its LDE.
+///
+/// Columns with a degree hint (see [`AirConfig::BASE_COLUMN_DEGREE_HINTS`])
+/// are extended separately with [`bit_reversed_low_degree_evaluate`].
+///
+/// [low memory]: Stark::LOW_MEMORY
+fn build_trace_commitment<S: Stark, F>(
+    air: &Air<S::AirConfig>,
+    polys: &Matrix<F>,
+    degree_hints: &[(usize, usize)],
+) -> (Option<Matrix<F>>, S::MerkleTree)
+where
+    F: GpuField<FftField = S::Fp> + Field + DomainCoeff<S::Fp>,
+    S::MerkleTree: MatrixMerkleTree<F>,
+{
+    let lde_xs = air.lde_domain();
+    let degree_bounds = (0..polys.num_cols())
         .map(|column| {
-            bit_reverse(&mut column[0..ce_domain_size]);
-            &column[0..ce_domain_size]
+            let hint = degree_hints.iter().find(|&&(hinted, _)| hinted == column);
+            hint.map(|&(_, degree)| degree)
         })
-        .collect()
+        .collect::<Vec<Option<usize>>>();
+    // only columns without a hint get a full size FFT
+    let full_polys = degree_bounds.iter().any(Option::is_some).then(|| {
+        let columns = iter::zip(polys.iter(), &degree_bounds)
+            .filter(|(_, bound)| bound.is_none())
+            .map(|(column, _)| column.to_vec_in(GpuAllocator));
+        Matrix::new(columns.collect())
+    });
+    let full_polys = full_polys.as_ref().unwrap_or(polys);
+    let bit_reversed_evaluate = |domain| {
+        let full_lde = if S::BIT_REVERSED_PIPELINE {
+            full_polys.out_of_order_evaluate(domain)
+        } else {
+            full_polys.bit_reversed_evaluate(domain)
+        };
+        let mut full_columns = full_lde.0.into_iter();
+        let columns = iter::zip(polys.iter(), &degree_bounds).map(|(coeffs, bound)| match bound {
+            Some(degree) => bit_reversed_low_degree_evaluate(coeffs, domain, *degree),
+            None => full_columns.next().unwrap(),
+        });
+        Matrix::new(columns.collect())
+    };
+    if !S::LOW_MEMORY {
+        let lde = bit_reversed_evaluate(lde_xs);
+        let tree = S::MerkleTree::from_matrix(&lde);
+        return (Some(lde), tree);
+    }
+
+    // coset `i` holds rows `i * n..(i + 1) * n` of the bit-reversed LDE
+    let lde_blowup_factor = air.lde_blowup_factor();
+    let trace_len = air.trace_len();
+    let evaluate_coset = |i| {
+        let offset = lde_xs.element(bit_reverse_index(lde_blowup_factor, i));
+        let coset = Radix2EvaluationDomain::new_coset(trace_len, offset).unwrap();
+        bit_reversed_evaluate(coset)
+    };
+    // the next coset is evaluated (on the GPU if there is one) while the
+    // current coset's rows are hashed
+    let tree = std::thread::scope(|scope| {
+        let evaluate_coset = &evaluate_coset;
+        let mut next = Some(scope.spawn(move || evaluate_coset(0)));
+        let cosets = (1..=lde_blowup_factor).map_while(|i| {
+            let coset = next.take()?.join().unwrap();
+            if i < lde_blowup_factor {
+                next = Some(scope.spawn(move || evaluate_coset(i)));
+            }
+            Some(coset)
+        });
+        S::MerkleTree::from_row_batches(cosets)
+    });
+    (None, tree)
+}
+
+/// Evaluates a polynomial of at most the given degree over `domain` in
+/// bit-reversed order. Each run of `m` rows of the bit-reversed evaluations is
+/// a coset of the subgroup of order `m` so a polynomial with at most `m`
+/// coefficients is evaluated with one FFT of size `m` per coset. Constant
+/// polynomials need no FFTs at all.
+fn bit_reversed_low_degree_evaluate<F: Field + DomainCoeff<Fp>, Fp: FftField>(
+    coeffs: &[F],
+    domain: Radix2EvaluationDomain<Fp>,
+    degree: usize,
+) -> GpuVec<F> {
+    let n = domain.size();
+    let m = (degree + 1).next_power_of_two().min(n);
+    let coeffs = &coeffs[..m.min(coeffs.len())];
+    let mut evals = Vec::with_capacity_in(n, GpuAllocator);
+    if m == 1 {
+        evals.resize(n, coeffs.first().copied().unwrap_or_else(F::zero));
+        return evals;
+    }
+    evals.resize(n, F::zero());
+    let num_cosets = n / m;
+    ark_std::cfg_chunks_mut!(evals, m)
+        .enumerate()
+        .for_each(|(i, chunk)| {
+            let offset = domain.element(bit_reverse_index(num_cosets, i));
+            let coset = Radix2EvaluationDomain::new_coset(m, offset).unwrap();
+            let mut coset_evals = coset.fft(coeffs);
+            bit_reverse(&mut coset_evals);
+            chunk.copy_from_slice(&coset_evals);
+        });
+    #[cfg(feature = "op-count")]
+    for _ in 0..num_cosets {
+        crate::op_count::record_fft(m, false, true);
+    }
+    evals
+}
+
+/// Bit reverses the first ce_domain_size many values of the matrix columns.
+/// Returns a view of the portion of the columns that were bit reversed
+fn bit_reverse_ce_trace<F: Field>(ce_domain_size: usize, trace: &mut Matrix<F>) -> MatrixView<F> {
+    for column in &mut trace.0 {
+        bit_reverse(&mut column[0..ce_domain_size]);
+    }
+    MatrixView::from(&*trace).slice_rows(0..ce_domain_size)
+}
+
+/// Copies the first ce_domain_size many values of the matrix columns in bit
+/// reversed order. Used for LDEs that are shared and can't be reordered in
+/// place with [`bit_reverse_ce_trace`].
+fn copy_ce_trace<F: Field>(ce_domain_size: usize, trace: &Matrix<F>) -> Matrix<F> {
+    let columns = trace.iter().map(|column| {
+        let mut column = column[0..ce_domain_size].to_vec_in(GpuAllocator);
+        bit_reverse(&mut column);
+        column
+    });
+    Matrix::new(columns.collect())
 }
@@ -1,15 +1,26 @@
+use crate::hash::Blake3HashFn;
 use crate::hash::Digest;
 use crate::hash::ElementHashFn;
 use crate::hash::HashFn;
+use crate::hash::Keccak256HashFn;
+use crate::poseidon::PoseidonHashFn;
+use crate::rescue::Rpo256HashFn;
+use crate::utils::SerdeOutput;
+use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
+use ark_ff::BigInteger;
 use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::Valid;
+use core::fmt::Debug;
+use core::marker::PhantomData;
 use rand::Rng;
 use rand::RngCore;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::collections::BTreeSet;
-use std::fmt::Debug;
-use std::marker::PhantomData;
+use sha3::Keccak256;
 
 // TODO: alternative approach
 // trait Seedable<T>: Sync + Debug {
@@ -40,6 +51,18 @@ pub trait PublicCoin: Sized + Send + Sync + Debug {
 
     fn reseed_with_int(&mut self, val: u64);
 
+    /// Reseeds with arbitrary bytes. The default absorbs the number of bytes
+    /// followed by each 8 byte little-endian word. Implementors should hash
+    /// the bytes into a single digest instead.
+    fn reseed_with_bytes(&mut self, bytes: &[u8]) {
+        self.reseed_with_int(bytes.len() as u64);
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.reseed_with_int(u64::from_le_bytes(word));
+        }
+    }
+
     fn draw(&mut self) -> Self::Field;
 
     /// Draws a maximum of n unique queries in the range `[0, domain_size)`
@@ -76,7 +99,7 @@ impl<F: Field, H: ElementHashFn<F>> PublicCoinImpl<F, H> {
 }
 
 impl<F: Field, H: HashFn> Debug for PublicCoinImpl<F, H> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("PublicCoinImpl")
             .field("seed", &self.seed)
             .field("counter", &self.counter)
@@ -126,6 +149,10 @@ impl<F: Field, H: ElementHashFn<F>> PublicCoin for PublicCoinImpl<F, H> {
         self.bytes = Vec::new();
     }
 
+    fn reseed_with_bytes(&mut self, bytes: &[u8]) {
+        self.reseed_with_digest(&H::hash(bytes.iter().copied()));
+    }
+
     fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool {
         let digest = H::merge_with_int(&self.seed, nonce);
         leading_zeros(&digest.as_bytes()) >= u32::from(proof_of_work_bits)
@@ -136,7 +163,12 @@ impl<F: Field, H: ElementHashFn<F>> PublicCoin for PublicCoinImpl<F, H> {
     }
 
     fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize> {
-        (0..max_n).map(|_| self.gen_range(0..domain_size)).collect()
+        // positions are sampled as `u64`s since sampling a `usize` depends on the
+        // pointer width of the target
+        let domain_size = u64::try_from(domain_size).unwrap();
+        (0..max_n)
+            .map(|_| usize::try_from(self.gen_range(0..domain_size)).unwrap())
+            .collect()
     }
 
     fn security_level_bits() -> u32 {
@@ -178,6 +210,127 @@ impl<F: Field, H: HashFn> RngCore for PublicCoinImpl<F, H> {
     }
 }
 
+/// Public coin built on [`Keccak256HashFn`] that can be replayed by an EVM
+/// verifier. Every operation is a single `keccak256` over inputs encoded with
+/// `abi.encodePacked`:
+///
+/// * reseeding with a digest: `seed = keccak256(seed, digest)`
+/// * reseeding with field elements: `seed = keccak256(seed, uint256(v0), ...)`
+/// * reseeding with an integer: `seed = keccak256(seed, uint64(v))`
+/// * reseeding with bytes: `seed = keccak256(seed, keccak256(bytes))`
+/// * randomness: `keccak256(seed, uint64(counter))` for an incrementing counter
+///
+/// Field elements are drawn by interpreting the random word as a `uint256`,
+/// masking it to the bit length of the modulus and rejecting values that are
+/// not less than the modulus. Queries are drawn as `uint256(word) %
+/// domain_size`. Proof-of-work is `keccak256(seed, uint64(nonce))`.
+pub struct Keccak256PublicCoin<F: Field> {
+    pub seed: SerdeOutput<Keccak256>,
+    counter: u64,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: Field> Debug for Keccak256PublicCoin<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Keccak256PublicCoin")
+            .field("seed", &self.seed)
+            .field("counter", &self.counter)
+            .finish()
+    }
+}
+
+impl<F: Field> Keccak256PublicCoin<F> {
+    /// Updates the state by incrementing the counter and returns
+    /// `keccak256(seed, uint64(counter))`
+    fn gen_next(&mut self) -> [u8; 32] {
+        self.counter += 1;
+        Keccak256HashFn::merge_with_int(&self.seed, self.counter).as_bytes()
+    }
+
+    fn draw_base_field_element(&mut self) -> F::BasePrimeField {
+        let num_bits = F::BasePrimeField::MODULUS_BIT_SIZE as usize;
+        assert!(num_bits <= 256, "modulus must fit in a uint256");
+        let modulus_bytes = F::BasePrimeField::MODULUS.to_bytes_be();
+        let mut modulus = [0; 32];
+        modulus[32 - modulus_bytes.len()..].copy_from_slice(&modulus_bytes);
+        loop {
+            let mut word = self.gen_next();
+            // clear the bits above the bit length of the modulus
+            let num_masked_bits = 256 - num_bits;
+            word[..num_masked_bits / 8].fill(0);
+            word[num_masked_bits / 8] &= 0xFF >> (num_masked_bits % 8);
+            // big-endian byte arrays have the same ordering as the integers
+            if word < modulus {
+                return F::BasePrimeField::from_be_bytes_mod_order(&word);
+            }
+        }
+    }
+}
+
+impl<F: Field> PublicCoin for Keccak256PublicCoin<F> {
+    type Digest = SerdeOutput<Keccak256>;
+    type Field = F;
+
+    fn new(digest: SerdeOutput<Keccak256>) -> Self {
+        Self {
+            seed: digest,
+            counter: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn reseed_with_digest(&mut self, val: &SerdeOutput<Keccak256>) {
+        self.seed = Keccak256HashFn::merge(&self.seed, val);
+        self.counter = 0;
+    }
+
+    fn reseed_with_field_elements(&mut self, vals: &[F]) {
+        let mut bytes = self.seed.to_vec();
+        Keccak256HashFn::encode_elements(&mut bytes, vals.iter().copied());
+        self.seed = Keccak256HashFn::hash_chunks([&*bytes]);
+        self.counter = 0;
+    }
+
+    fn reseed_with_int(&mut self, val: u64) {
+        self.seed = Keccak256HashFn::merge_with_int(&self.seed, val);
+        self.counter = 0;
+    }
+
+    fn reseed_with_bytes(&mut self, bytes: &[u8]) {
+        self.reseed_with_digest(&Keccak256HashFn::hash(bytes.iter().copied()));
+    }
+
+    fn draw(&mut self) -> F {
+        let elements = (0..F::extension_degree())
+            .map(|_| self.draw_base_field_element())
+            .collect::<Vec<F::BasePrimeField>>();
+        F::from_base_prime_field_elems(&elements).unwrap()
+    }
+
+    fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize> {
+        let domain_size = domain_size as u128;
+        (0..max_n)
+            .map(|_| {
+                // uint256(word) % domain_size
+                let word = self.gen_next();
+                let position = word
+                    .iter()
+                    .fold(0, |acc, &b| ((acc << 8) | u128::from(b)) % domain_size);
+                usize::try_from(position).unwrap()
+            })
+            .collect()
+    }
+
+    fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool {
+        let digest = Keccak256HashFn::merge_with_int(&self.seed, nonce);
+        leading_zeros(&digest.as_bytes()) >= u32::from(proof_of_work_bits)
+    }
+
+    fn security_level_bits() -> u32 {
+        Keccak256HashFn::COLLISION_RESISTANCE
+    }
+}
+
 pub fn leading_zeros(hash: &[u8]) -> u32 {
     let mut zeros = 0;
     for byte in hash {
@@ -194,3 +347,246 @@ pub fn leading_zeros(hash: &[u8]) -> u32 {
 pub fn draw_multiple<P: PublicCoin>(public_coin: &mut P, n: usize) -> Vec<P::Field> {
     (0..n).map(|_| public_coin.draw()).collect()
 }
+
+/// Hash function of the grinding proof of work. On-chain verifiers want a
+/// cheap to verify proof of work such as Keccak256 while recursive verifiers
+/// want an algebraic hash no matter which hash the public coin uses.
+///
+/// Unless the public coin's own proof of work is used a challenge field
+/// element is drawn from the public coin. A nonce is valid if
+/// `merge_with_int(hash(challenge), nonce)` has the required number of
+/// leading zeros where `challenge` is the compressed serialization of the
+/// drawn element.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PowHash {
+    /// Uses [`PublicCoin::grind_proof_of_work`]
+    #[default]
+    PublicCoin,
+    Keccak256,
+    Blake3,
+    Rpo256,
+    Poseidon,
+}
+
+impl PowHash {
+    /// Finds a nonce with `proof_of_work_bits` bits of proof of work
+    pub fn grind<P: PublicCoin>(self, public_coin: &mut P, proof_of_work_bits: u8) -> Option<u64> {
+        fn grind<H: HashFn>(challenge: &[u8], proof_of_work_bits: u8) -> Option<u64> {
+            let seed = H::hash(challenge.iter().copied());
+            let is_valid = |&nonce: &u64| is_valid_pow::<H>(&seed, proof_of_work_bits, nonce);
+            #[cfg(not(feature = "parallel"))]
+            return (1..u64::MAX).find(is_valid);
+            #[cfg(feature = "parallel")]
+            return (1..u64::MAX).into_par_iter().find_any(is_valid);
+        }
+
+        let Some(challenge) = self.draw_challenge(public_coin) else {
+            return public_coin.grind_proof_of_work(proof_of_work_bits);
+        };
+        match self {
+            Self::PublicCoin => unreachable!(),
+            Self::Keccak256 => grind::<Keccak256HashFn>(&challenge, proof_of_work_bits),
+            Self::Blake3 => grind::<Blake3HashFn>(&challenge, proof_of_work_bits),
+            Self::Rpo256 => grind::<Rpo256HashFn>(&challenge, proof_of_work_bits),
+            Self::Poseidon => grind::<PoseidonHashFn>(&challenge, proof_of_work_bits),
+        }
+    }
+
+    /// Checks `nonce` has `proof_of_work_bits` bits of proof of work
+    pub fn verify<P: PublicCoin>(
+        self,
+        public_coin: &mut P,
+        proof_of_work_bits: u8,
+        nonce: u64,
+    ) -> bool {
+        fn verify<H: HashFn>(challenge: &[u8], proof_of_work_bits: u8, nonce: u64) -> bool {
+            let seed = H::hash(challenge.iter().copied());
+            is_valid_pow::<H>(&seed, proof_of_work_bits, nonce)
+        }
+
+        let Some(challenge) = self.draw_challenge(public_coin) else {
+            return public_coin.verify_proof_of_work(proof_of_work_bits, nonce);
+        };
+        match self {
+            Self::PublicCoin => unreachable!(),
+            Self::Keccak256 => verify::<Keccak256HashFn>(&challenge, proof_of_work_bits, nonce),
+            Self::Blake3 => verify::<Blake3HashFn>(&challenge, proof_of_work_bits, nonce),
+            Self::Rpo256 => verify::<Rpo256HashFn>(&challenge, proof_of_work_bits, nonce),
+            Self::Poseidon => verify::<PoseidonHashFn>(&challenge, proof_of_work_bits, nonce),
+        }
+    }
+
+    /// Returns the collision resistance of the hash function. Returns `None`
+    /// if the public coin's proof of work is used.
+    pub const fn security_level_bits(self) -> Option<u32> {
+        match self {
+            Self::PublicCoin => None,
+            Self::Keccak256 => Some(Keccak256HashFn::COLLISION_RESISTANCE),
+            Self::Blake3 => Some(<Blake3HashFn>::COLLISION_RESISTANCE),
+            Self::Rpo256 => Some(Rpo256HashFn::COLLISION_RESISTANCE),
+            Self::Poseidon => Some(PoseidonHashFn::COLLISION_RESISTANCE),
+        }
+    }
+
+    /// Draws the challenge of the proof of work. Returns `None` if the public
+    /// coin's proof of work is used.
+    fn draw_challenge<P: PublicCoin>(self, public_coin: &mut P) -> Option<Vec<u8>> {
+        if self == Self::PublicCoin {
+            return None;
+        }
+        let mut challenge = Vec::new();
+        public_coin
+            .draw()
+            .serialize_compressed(&mut challenge)
+            .unwrap();
+        Some(challenge)
+    }
+}
+
+fn is_valid_pow<H: HashFn>(seed: &H::Digest, proof_of_work_bits: u8, nonce: u64) -> bool {
+    let digest = H::merge_with_int(seed, nonce);
+    leading_zeros(&digest.as_bytes()) >= u32::from(proof_of_work_bits)
+}
+
+impl CanonicalSerialize for PowHash {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        (*self as u8).serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        (*self as u8).serialized_size(compress)
+    }
+}
+
+impl Valid for PowHash {
+    fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for PowHash {
+    fn deserialize_with_mode<R: ark_serialize::Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        Ok(
+            match u8::deserialize_with_mode(reader, compress, validate)? {
+                0 => Self::PublicCoin,
+                1 => Self::Keccak256,
+                2 => Self::Blake3,
+                3 => Self::Rpo256,
+                4 => Self::Poseidon,
+                _ => return Err(ark_serialize::SerializationError::InvalidData),
+            },
+        )
+    }
+}
+
+/// How query positions are sampled from the LDE domain. Verifiers that
+/// interoperate with other provers must sample the same way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum QuerySampling {
+    /// Draws `num_queries` positions independently. Positions drawn more than
+    /// once are only queried once so proofs can have fewer queries than
+    /// `num_queries`. Each draw counts towards security.
+    #[default]
+    WithReplacement,
+    /// Draws positions until `num_queries` distinct positions are found.
+    /// Positions are the first distinct values drawn by
+    /// [`QuerySampling::WithReplacement`] followed by more draws if there were
+    /// duplicates. Requires `num_queries` to be at most the LDE domain size.
+    Distinct,
+}
+
+impl QuerySampling {
+    /// Draws positions in the range `[0, domain_size)` from the public coin
+    ///
+    /// # Panics
+    ///
+    /// Panics if distinct positions are sampled and `num_queries` is greater
+    /// than `domain_size`.
+    pub fn draw<P: PublicCoin>(
+        self,
+        public_coin: &mut P,
+        num_queries: usize,
+        domain_size: usize,
+    ) -> BTreeSet<usize> {
+        let mut positions = public_coin.draw_queries(num_queries, domain_size);
+        if self == Self::Distinct {
+            assert!(num_queries <= domain_size);
+            while positions.len() < num_queries {
+                let remaining = num_queries - positions.len();
+                positions.extend(public_coin.draw_queries(remaining, domain_size));
+            }
+        }
+        positions
+    }
+
+    /// Returns the bits of security of the queries if each query passes with
+    /// probability `pass_probability`. Sampling without replacement means
+    /// each passing query leaves fewer passing positions for the next.
+    #[cfg(feature = "verifier")]
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn security_bits(
+        self,
+        num_queries: u8,
+        pass_probability: f64,
+        domain_size: usize,
+    ) -> f64 {
+        match self {
+            Self::WithReplacement => -libm::log2(pass_probability) * f64::from(num_queries),
+            Self::Distinct => {
+                let domain_size = domain_size as f64;
+                let passing = pass_probability * domain_size;
+                (0..num_queries)
+                    .map(|i| {
+                        let i = f64::from(i);
+                        // no passing positions are left once this is infinite
+                        libm::log2((domain_size - i) / (passing - i).max(0.0))
+                    })
+                    .sum()
+            }
+        }
+    }
+}
+
+impl CanonicalSerialize for QuerySampling {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        (*self as u8).serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        (*self as u8).serialized_size(compress)
+    }
+}
+
+impl Valid for QuerySampling {
+    fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for QuerySampling {
+    fn deserialize_with_mode<R: ark_serialize::Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        Ok(
+            match u8::deserialize_with_mode(reader, compress, validate)? {
+                0 => Self::WithReplacement,
+                1 => Self::Distinct,
+                _ => return Err(ark_serialize::SerializationError::InvalidData),
+            },
+        )
+    }
+}
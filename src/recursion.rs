@@ -0,0 +1,1042 @@
+//! Minimal example of verifying part of a ministark proof inside a ministark
+//! AIR.
+//!
+//! The full recursive verifier needs the whole verifier (OOD checks, FRI,
+//! grinding) expressed as an AIR. This example covers the part that
+//! dominates its cost, hashing, for proofs that use RPO (see
+//! [`crate::rescue`]). [`RpoVerifierAirConfig`] proves:
+//!
+//! 1. an opened row of the base trace hashes to a leaf
+//! 2. the leaf authenticates to the base trace commitment along a Merkle path
+//! 3. absorbing the base trace commitment and the next commitment into the
+//!    verifier's public coin gives the claimed seed
+//!
+//! Every hash is one RPO permutation laid out over a block of 8 rows (the
+//! input state followed by the state after each of the 7 rounds). The last
+//! row of a block is chained into the input of the next block. Unlike a full
+//! verifier the opened position isn't derived from the transcript. The
+//! prover chooses which path to open.
+//!
+//! The verifier's public coin absorbs the digest of the AIR a proof is for
+//! (see [`Air::digest`]) so each claim identifies the AIR of its proof.
+//! [`RpoAggregateAirConfig`] proves the claims about several proofs in a
+//! single proof. The proofs can be of different AIRs e.g. a rollup mixing
+//! transaction types proven by different AIRs.
+//!
+//! [`PoseidonVerifierAirConfig`] is the same example for proofs that use the
+//! Poseidon public coin and Merkle trees (see [`crate::poseidon`]). Each
+//! permutation takes a block of 32 rows. Instead of chaining seeds it replays
+//! the verifier's duplex sponge: absorbing the AIR digest and the base trace
+//! commitment gives the first challenge drawn after the commitment.
+//! [`poseidon_verifier_claim`] builds the public inputs and witness from a
+//! proof.
+//!
+//! Neither example is a recursive verifier. A proof of a claim doesn't show
+//! the inner proof is valid: the constraint evaluations, the extension and
+//! composition trace commitments, FRI and grinding aren't checked and only a
+//! single query is opened. The helpers that build claims verify the inner
+//! proof natively instead.
+use crate::air::periodic_column;
+use crate::air::AirConfig;
+use crate::challenges::Challenges;
+use crate::channel::absorb_instance_inputs;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::hash::HashFn;
+use crate::hints::Hints;
+use crate::merkle::MerkleView;
+use crate::poseidon;
+use crate::poseidon::is_full_round;
+use crate::poseidon::Domain;
+use crate::poseidon::PoseidonDigest;
+use crate::poseidon::PoseidonHashFn;
+use crate::poseidon::PoseidonMatrixMerkleTree;
+use crate::poseidon::PoseidonPublicCoin;
+use crate::random::PublicCoin;
+use crate::random::PublicCoinImpl;
+use crate::rescue::apply_round;
+use crate::rescue::Rpo256Digest;
+use crate::rescue::Rpo256HashFn;
+use crate::rescue::Rpo256MatrixMerkleTree;
+use crate::rescue::ARK1;
+use crate::rescue::ARK2;
+use crate::rescue::CAPACITY;
+use crate::rescue::DIGEST_SIZE;
+use crate::rescue::MDS;
+use crate::rescue::NUM_ROUNDS;
+use crate::rescue::RATE;
+use crate::rescue::STATE_WIDTH;
+use crate::stark::Stark;
+use crate::utils::FieldVariant;
+use crate::verifier::VerificationError;
+use crate::Air;
+use crate::Matrix;
+use crate::Proof;
+use crate::Trace;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use core::iter::zip;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+use snafu::Snafu;
+
+/// Number of rows used to compute a single RPO permutation
+const BLOCK_SIZE: usize = NUM_ROUNDS + 1;
+
+/// Column that selects how a block's input is chained to the previous block's
+/// output. `0` puts the previous digest in the first half of the rate and `1`
+/// puts it in the second half.
+const BIT_COLUMN: usize = STATE_WIDTH;
+
+/// Errors building the witness of [`RpoVerifierAirConfig`] from a proof
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(context(false))]
+    #[snafu(display("proof is invalid: {source}"))]
+    InvalidProof { source: VerificationError },
+    #[snafu(display("base trace rows with `{width}` columns are wider than the hash's rate"))]
+    RowTooWide { width: usize },
+    #[snafu(display("proofs of AIRs with preprocessed columns aren't supported"))]
+    PreprocessedColumns,
+}
+
+/// Public inputs of [`RpoVerifierAirConfig`]
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RpoVerifierInputs {
+    /// Sponge state that hashes the opened base trace row into a leaf
+    pub leaf_state: [Fp; STATE_WIDTH],
+    /// Number of nodes on the Merkle path
+    pub depth: u32,
+    pub base_trace_commitment: Rpo256Digest,
+    /// Digest of the AIR the proof is for (see [`Air::digest`])
+    pub air_digest: Rpo256Digest,
+    /// Seed of the verifier's public coin after absorbing the instance and
+    /// before absorbing the description of the AIR (see
+    /// [`crate::channel::absorb_instance`])
+    pub instance_seed: Rpo256Digest,
+    /// Commitment absorbed after the base trace commitment
+    pub next_commitment: Rpo256Digest,
+    /// Seed of the verifier's public coin after absorbing both commitments
+    pub seed: Rpo256Digest,
+}
+
+impl RpoVerifierInputs {
+    /// Seed of the verifier's public coin before any commitment is absorbed
+    fn initial_seed(&self) -> Rpo256Digest {
+        Rpo256HashFn::merge(&self.instance_seed, &self.air_digest)
+    }
+
+    /// Number of rows used by the claim
+    const fn num_rows(&self) -> usize {
+        (self.depth as usize + 3) * BLOCK_SIZE
+    }
+
+    /// Index of the first row of the block that absorbs the base trace
+    /// commitment into the public coin relative to the first row of the claim
+    const fn reseed_block_row(&self) -> usize {
+        (self.depth as usize + 1) * BLOCK_SIZE
+    }
+
+    /// Values of the hints of the claim when its rows start at `first_row`
+    fn hints(&self, trace_domain: &Radix2EvaluationDomain<Fp>, first_row: usize) -> Vec<Fp> {
+        let reseed_row = first_row + self.reseed_block_row();
+        let mut hints = Vec::new();
+        hints.extend(self.leaf_state);
+        hints.push(trace_domain.element(first_row));
+        hints.extend(self.base_trace_commitment.0);
+        hints.push(trace_domain.element(reseed_row - 1));
+        hints.extend(self.initial_seed().0);
+        hints.push(trace_domain.element(reseed_row));
+        hints.extend(self.next_commitment.0);
+        hints.push(trace_domain.element(reseed_row + BLOCK_SIZE));
+        hints.extend(self.seed.0);
+        hints.push(trace_domain.element(reseed_row + 2 * BLOCK_SIZE - 1));
+        debug_assert_eq!(hints.len(), hint::NUM_HINTS);
+        hints
+    }
+}
+
+/// Number of rows in the trace of claims laid out one after the other.
+/// Padding blocks chain the seed of the last claim with zero.
+fn trace_len<'a>(claims: impl IntoIterator<Item = &'a RpoVerifierInputs>) -> usize {
+    let num_rows = claims
+        .into_iter()
+        .map(RpoVerifierInputs::num_rows)
+        .sum::<usize>();
+    num_rows.next_power_of_two()
+}
+
+/// Siblings along the Merkle path of an opened base trace row
+#[derive(Debug, Clone)]
+pub struct RpoVerifierWitness {
+    pub position: usize,
+    /// Siblings from the leaf level up to the children of the root
+    pub siblings: Vec<Rpo256Digest>,
+}
+
+/// Builds the public inputs and witness of [`RpoVerifierAirConfig`] from the
+/// first opened base trace row of a proof. The proof is verified first.
+pub fn rpo_verifier_claim<S>(
+    stark: &S,
+    proof: Proof<S>,
+) -> Result<(RpoVerifierClaim, RpoVerifierWitness), Error>
+where
+    S: Stark<
+        Fp = Fp,
+        Fq = Fp,
+        Digest = Rpo256Digest,
+        MerkleTree = Rpo256MatrixMerkleTree,
+        PublicCoin = PublicCoinImpl<Fp, Rpo256HashFn>,
+    >,
+{
+    let air = Air::try_new(proof.trace_len, stark.get_public_inputs(), proof.options)
+        .map_err(|source| VerificationError::InvalidOptions { source })?;
+    let mut public_coin = stark.gen_public_coin(&air);
+    absorb_instance_inputs::<S>(&mut public_coin, &air);
+    let instance_seed = public_coin.seed;
+    let air_digest = air.digest::<Rpo256HashFn>();
+    let base_trace_commitment = proof.base_trace_commitment;
+    let next_commitment = proof
+        .extension_trace_commitment
+        .unwrap_or(proof.composition_trace_commitment);
+    let row = proof.trace_queries.base_trace_rows()[0].to_vec();
+    let base_trace_proof = proof.trace_queries.base_trace_proof.clone();
+    let positions = stark.verify(proof, 0)?.query_positions;
+    if row.len() > RATE {
+        return Err(Error::RowTooWide { width: row.len() });
+    }
+
+    // sponge state absorbing the row (see `Rpo256HashFn::hash_elements`)
+    let mut leaf_state = [Fp::ZERO; STATE_WIDTH];
+    leaf_state[CAPACITY..CAPACITY + row.len()].copy_from_slice(&row);
+    if row.len() < RATE {
+        leaf_state[0] = Fp::ONE;
+        leaf_state[CAPACITY + row.len()] = Fp::ONE;
+    }
+
+    let mut inputs = RpoVerifierInputs {
+        leaf_state,
+        depth: base_trace_proof.height,
+        base_trace_commitment,
+        air_digest,
+        instance_seed,
+        next_commitment,
+        seed: Rpo256Digest::default(),
+    };
+    let seed = Rpo256HashFn::merge(&inputs.initial_seed(), &base_trace_commitment);
+    inputs.seed = Rpo256HashFn::merge(&seed, &next_commitment);
+    let witness = RpoVerifierWitness {
+        position: positions[0],
+        siblings: merkle_path::<Rpo256HashFn>(positions[0], positions, base_trace_proof),
+    };
+    Ok((RpoVerifierClaim(inputs), witness))
+}
+
+/// Returns the siblings on the path of `position` from a verified proof of
+/// several positions. Replays [`crate::merkle::MerkleTreeImpl::verify`].
+fn merkle_path<H: HashFn>(
+    mut position: usize,
+    mut positions: Vec<usize>,
+    proof: MerkleView<H::Digest, H::Digest>,
+) -> Vec<H::Digest> {
+    positions.sort_unstable();
+    positions.dedup();
+    let mut level = zip(positions, proof.initial_leaves).collect::<Vec<_>>();
+    let mut siblings = proof.sibling_leaves.into_iter().chain(proof.nodes);
+    let mut path = Vec::new();
+    for _ in 0..proof.height {
+        let mut parents = Vec::new();
+        let mut opened = level.into_iter().peekable();
+        while let Some(&(i, _)) = opened.peek() {
+            let parent = i / 2;
+            let [left, right] = [2 * parent, 2 * parent + 1].map(|child| {
+                opened
+                    .next_if(|(i, _)| *i == child)
+                    .map_or_else(|| siblings.next().unwrap(), |(_, digest)| digest)
+            });
+            if parent == position / 2 {
+                path.push(if position % 2 == 1 {
+                    left.clone()
+                } else {
+                    right.clone()
+                });
+            }
+            parents.push((parent, H::merge(&left, &right)));
+        }
+        level = parents;
+        position /= 2;
+    }
+    path
+}
+
+pub struct RpoVerifierTrace(Matrix<Fp>);
+
+impl Trace for RpoVerifierTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> &Matrix<Fp> {
+        &self.0
+    }
+}
+
+/// Indices of the hints of a claim. Hints of the `k`-th claim of
+/// [`RpoAggregateAirConfig`] are offset by `k * NUM_HINTS`.
+mod hint {
+    use super::DIGEST_SIZE;
+    use super::STATE_WIDTH;
+
+    pub const LEAF_STATE: usize = 0;
+    /// Point of the trace domain at the first row of the claim
+    pub const LEAF_X: usize = LEAF_STATE + STATE_WIDTH;
+    pub const ROOT: usize = LEAF_X + 1;
+    /// Point of the trace domain at the row with the root
+    pub const ROOT_X: usize = ROOT + DIGEST_SIZE;
+    pub const INITIAL_SEED: usize = ROOT_X + 1;
+    /// Point of the trace domain at the first row of the block absorbing the
+    /// base trace commitment
+    pub const RESEED_X: usize = INITIAL_SEED + DIGEST_SIZE;
+    pub const NEXT_COMMITMENT: usize = RESEED_X + 1;
+    /// Point of the trace domain at the first row of the block absorbing the
+    /// next commitment
+    pub const NEXT_RESEED_X: usize = NEXT_COMMITMENT + DIGEST_SIZE;
+    pub const SEED: usize = NEXT_RESEED_X + 1;
+    /// Point of the trace domain at the row with the final seed. This is the
+    /// last row of the claim.
+    pub const SEED_X: usize = SEED + DIGEST_SIZE;
+    pub const NUM_HINTS: usize = SEED_X + 1;
+}
+
+/// Indices of the periodic columns of [`RpoVerifierAirConfig`]
+mod periodic {
+    use super::STATE_WIDTH;
+
+    /// `1` in rows that apply a round and `0` in the last row of a block
+    pub const IS_ROUND: usize = 0;
+    pub const ARK1: usize = IS_ROUND + 1;
+    pub const ARK2: usize = ARK1 + STATE_WIDTH;
+}
+
+/// Periodic columns with the round constants of the permutation
+fn rpo_periodic_columns() -> Vec<Vec<Fp>> {
+    let round_constants = |ark: &[[u64; STATE_WIDTH]; NUM_ROUNDS], i: usize| {
+        let mut values = vec![Fp::ZERO; BLOCK_SIZE];
+        for (value, constants) in values.iter_mut().zip(ark) {
+            *value = Fp::from(constants[i]);
+        }
+        values
+    };
+    let mut is_round = vec![Fp::ONE; BLOCK_SIZE];
+    is_round[NUM_ROUNDS] = Fp::ZERO;
+    let mut columns = vec![is_round];
+    columns.extend((0..STATE_WIDTH).map(|i| round_constants(&ARK1, i)));
+    columns.extend((0..STATE_WIDTH).map(|i| round_constants(&ARK2, i)));
+    columns
+}
+
+/// Hints of claims laid out one after the other
+fn claims_hints<'a>(
+    trace_len: usize,
+    claims: impl IntoIterator<Item = &'a RpoVerifierInputs>,
+) -> Hints<Fp> {
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+    let mut first_row = 0;
+    let mut hints = Vec::new();
+    for claim in claims {
+        hints.extend(claim.hints(&trace_domain, first_row));
+        first_row += claim.num_rows();
+    }
+    Hints::new(hints.into_iter().enumerate().collect())
+}
+
+/// Constraints of `num_claims` claims laid out one after the other. The
+/// output of the last block of a claim isn't chained into the next claim.
+fn claims_constraints<C: AirConfig<Fp = Fp, Fq = Fp>>(
+    trace_len: usize,
+    num_claims: usize,
+) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+    use AlgebraicItem::*;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+    let one = Constant(FieldVariant::Fp(Fp::ONE));
+    let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+    let is_round = periodic_column::<C>(periodic::IS_ROUND);
+    let is_block_end = Expr::from(one) - &is_round;
+    let mds = |i: usize, j: usize| {
+        let v = MDS[(STATE_WIDTH + j - i) % STATE_WIDTH];
+        Constant(FieldVariant::Fp(Fp::from(v)))
+    };
+    let linear = |state: &[Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>], i: usize| {
+        (0..STATE_WIDTH)
+            .map(|j| &state[j] * mds(i, j))
+            .sum::<Expr<_>>()
+    };
+    let curr = (0..STATE_WIDTH).map(|i| i.curr()).collect::<Vec<_>>();
+    let next = (0..STATE_WIDTH).map(|i| i.next()).collect::<Vec<_>>();
+    let mut constraints = Vec::new();
+
+    // rounds of the permutation. The inverse S-box is checked by raising
+    // the next state to the 7th power.
+    let sbox = (0..STATE_WIDTH)
+        .map(|i| (linear(&curr, i) + periodic_column::<C>(periodic::ARK1 + i)).pow(7))
+        .collect::<Vec<_>>();
+    for (i, next) in next.iter().enumerate() {
+        let round = next.pow(7) - linear(&sbox, i) - periodic_column::<C>(periodic::ARK2 + i);
+        constraints.push(&is_round * round / (X.pow(trace_len) - one));
+    }
+
+    // chaining the output of a block into the input of the next block
+    let claim_ends = (0..num_claims - 1)
+        .map(|k| X - Hint(k * hint::NUM_HINTS + hint::SEED_X))
+        .fold(X - last_x, |ends, end| ends * end);
+    let chain = &is_block_end * claim_ends / (X.pow(trace_len) - one);
+    let bit = BIT_COLUMN.next();
+    for next in &next[..CAPACITY] {
+        constraints.push(&chain * next);
+    }
+    for i in CAPACITY..CAPACITY + DIGEST_SIZE {
+        let into_first = &next[i] - &curr[i];
+        let into_second = &next[i + DIGEST_SIZE] - &curr[i];
+        let chained = (Expr::from(one) - &bit) * into_first + &bit * into_second;
+        constraints.push(&chain * chained);
+    }
+    let bit = BIT_COLUMN.curr();
+    constraints.push(&bit * (&bit - one) / (X.pow(trace_len) - one));
+
+    // public inputs
+    for k in 0..num_claims {
+        let claim_hint = |i: usize| Hint(k * hint::NUM_HINTS + i);
+        let x_at = |i: usize| X - claim_hint(i);
+        for (i, curr) in curr.iter().enumerate() {
+            constraints.push((curr - claim_hint(hint::LEAF_STATE + i)) / x_at(hint::LEAF_X));
+        }
+        for i in 0..DIGEST_SIZE {
+            let digest = &curr[CAPACITY + i];
+            let second_half = &curr[CAPACITY + DIGEST_SIZE + i];
+            constraints.push((digest - claim_hint(hint::ROOT + i)) / x_at(hint::ROOT_X));
+            constraints.push((digest - claim_hint(hint::INITIAL_SEED + i)) / x_at(hint::RESEED_X));
+            constraints.push(
+                (second_half - claim_hint(hint::NEXT_COMMITMENT + i)) / x_at(hint::NEXT_RESEED_X),
+            );
+            constraints.push((digest - claim_hint(hint::SEED + i)) / x_at(hint::SEED_X));
+        }
+        constraints.push((&bit - one) / x_at(hint::RESEED_X));
+        constraints.push(&bit / x_at(hint::NEXT_RESEED_X));
+    }
+
+    constraints.into_iter().map(Constraint::new).collect()
+}
+
+pub struct RpoVerifierAirConfig;
+
+impl AirConfig for RpoVerifierAirConfig {
+    const NUM_BASE_COLUMNS: usize = STATE_WIDTH + 1;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = RpoVerifierInputs;
+
+    fn periodic_columns() -> Vec<Vec<Fp>> {
+        rpo_periodic_columns()
+    }
+
+    fn gen_hints(trace_len: usize, inputs: &RpoVerifierInputs, _: &Challenges<Fp>) -> Hints<Fp> {
+        claims_hints(trace_len, [inputs])
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        claims_constraints::<Self>(trace_len, 1)
+    }
+}
+
+/// Proves the claims of [`RpoVerifierAirConfig`] about `N` proofs. Claims are
+/// laid out one after the other in the order of the public inputs and can be
+/// about proofs of different AIRs. The AIR of each proof is identified by the
+/// claim's [`RpoVerifierInputs::air_digest`].
+pub struct RpoAggregateAirConfig<const N: usize>;
+
+impl<const N: usize> AirConfig for RpoAggregateAirConfig<N> {
+    const NUM_BASE_COLUMNS: usize = STATE_WIDTH + 1;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = [RpoVerifierInputs; N];
+
+    fn periodic_columns() -> Vec<Vec<Fp>> {
+        rpo_periodic_columns()
+    }
+
+    fn gen_hints(
+        trace_len: usize,
+        claims: &[RpoVerifierInputs; N],
+        _: &Challenges<Fp>,
+    ) -> Hints<Fp> {
+        claims_hints(trace_len, claims)
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        assert!(N != 0, "at least one claim is needed");
+        claims_constraints::<Self>(trace_len, N)
+    }
+}
+
+/// Public coin of the verifier of a claim seeded with the public inputs
+fn claims_public_coin<C: AirConfig<Fp = Fp, Fq = Fp>>(
+    air: &Air<C>,
+) -> PublicCoinImpl<Fp, Rpo256HashFn> {
+    let mut seed = Vec::new();
+    air.public_inputs().serialize_compressed(&mut seed).unwrap();
+    air.trace_len().serialize_compressed(&mut seed).unwrap();
+    air.options().serialize_compressed(&mut seed).unwrap();
+    PublicCoinImpl::new(Rpo256HashFn::hash_chunks([&*seed]))
+}
+
+/// Claims the hashes described by the public inputs are computed correctly
+pub struct RpoVerifierClaim(pub RpoVerifierInputs);
+
+impl Stark for RpoVerifierClaim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = RpoVerifierAirConfig;
+    type Digest = Rpo256Digest;
+    type PublicCoin = PublicCoinImpl<Fp, Rpo256HashFn>;
+    type MerkleTree = Rpo256MatrixMerkleTree;
+    type Witness = RpoVerifierWitness;
+    type Trace = RpoVerifierTrace;
+
+    fn get_public_inputs(&self) -> RpoVerifierInputs {
+        self.0.clone()
+    }
+
+    fn gen_public_coin(&self, air: &Air<RpoVerifierAirConfig>) -> Self::PublicCoin {
+        claims_public_coin(air)
+    }
+
+    fn generate_trace(&self, witness: RpoVerifierWitness) -> RpoVerifierTrace {
+        claims_trace([(&self.0, witness)])
+    }
+}
+
+/// Claims the hashes described by each of the public inputs are computed
+/// correctly. Witnesses are given in the same order as the claims.
+pub struct RpoAggregateClaim<const N: usize>(pub [RpoVerifierInputs; N]);
+
+impl<const N: usize> Stark for RpoAggregateClaim<N> {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = RpoAggregateAirConfig<N>;
+    type Digest = Rpo256Digest;
+    type PublicCoin = PublicCoinImpl<Fp, Rpo256HashFn>;
+    type MerkleTree = Rpo256MatrixMerkleTree;
+    type Witness = [RpoVerifierWitness; N];
+    type Trace = RpoVerifierTrace;
+
+    fn get_public_inputs(&self) -> [RpoVerifierInputs; N] {
+        self.0.clone()
+    }
+
+    fn gen_public_coin(&self, air: &Air<RpoAggregateAirConfig<N>>) -> Self::PublicCoin {
+        claims_public_coin(air)
+    }
+
+    fn generate_trace(&self, witnesses: [RpoVerifierWitness; N]) -> RpoVerifierTrace {
+        claims_trace(zip(&self.0, witnesses))
+    }
+}
+
+/// Generates the rows of claims laid out one after the other
+fn claims_trace<'a>(
+    claims: impl IntoIterator<Item = (&'a RpoVerifierInputs, RpoVerifierWitness)>,
+) -> RpoVerifierTrace {
+    let mut rows = Vec::new();
+    let mut digest = Rpo256Digest::default();
+    let mut inputs = Vec::new();
+    for (claim, witness) in claims {
+        digest = hash_block(&mut rows, claim.leaf_state, false);
+        for (level, sibling) in witness.siblings.iter().enumerate() {
+            let is_right = (witness.position >> level) & 1 == 1;
+            let (left, right) = if is_right {
+                (sibling, &digest)
+            } else {
+                (&digest, sibling)
+            };
+            digest = hash_block(&mut rows, merge_state(left, right), is_right);
+        }
+        digest = hash_block(&mut rows, merge_state(&claim.initial_seed(), &digest), true);
+        digest = hash_block(
+            &mut rows,
+            merge_state(&digest, &claim.next_commitment),
+            false,
+        );
+        inputs.push(claim);
+    }
+    let len = trace_len(inputs);
+    while rows.len() < len {
+        let padding = Rpo256Digest::default();
+        digest = hash_block(&mut rows, merge_state(&digest, &padding), false);
+    }
+    RpoVerifierTrace(Matrix::from_rows(rows))
+}
+
+/// Input state of the permutation that merges two digests
+fn merge_state(left: &Rpo256Digest, right: &Rpo256Digest) -> [Fp; STATE_WIDTH] {
+    let mut state = [Fp::ZERO; STATE_WIDTH];
+    state[CAPACITY..CAPACITY + DIGEST_SIZE].copy_from_slice(&left.0);
+    state[CAPACITY + DIGEST_SIZE..].copy_from_slice(&right.0);
+    state
+}
+
+/// Appends the rows of a permutation of `state` and returns the digest
+fn hash_block(rows: &mut Vec<Vec<Fp>>, mut state: [Fp; STATE_WIDTH], bit: bool) -> Rpo256Digest {
+    let bit = Fp::from(bit);
+    for round in 0..=NUM_ROUNDS {
+        if round != 0 {
+            apply_round(&mut state, round - 1);
+        }
+        rows.push(state.iter().copied().chain([bit]).collect());
+    }
+    Rpo256Digest(state[CAPACITY..CAPACITY + DIGEST_SIZE].try_into().unwrap())
+}
+
+/// Number of rows used to compute a single Poseidon permutation: the input
+/// state, the state after each round and a copy of the output that pads the
+/// block to a power of two rows
+const POSEIDON_BLOCK_SIZE: usize = poseidon::NUM_ROUNDS + 2;
+
+/// Column of [`PoseidonVerifierAirConfig`] that selects which half of the
+/// rate a block's input takes the previous block's digest in
+const POSEIDON_BIT_COLUMN: usize = poseidon::WIDTH;
+
+/// Public inputs of [`PoseidonVerifierAirConfig`]
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PoseidonVerifierInputs {
+    /// Sponge state that hashes the opened base trace row into a leaf
+    pub leaf_state: [Fp; poseidon::WIDTH],
+    /// Number of nodes on the Merkle path
+    pub depth: u32,
+    pub base_trace_commitment: PoseidonDigest,
+    /// Digest of the AIR the proof is for (see [`Air::digest`])
+    pub air_digest: PoseidonDigest,
+    /// Sponge state of the verifier's public coin after absorbing the instance
+    /// and before absorbing the description of the AIR (see
+    /// [`crate::channel::absorb_instance`])
+    pub instance_state: [Fp; poseidon::WIDTH],
+    /// Number of elements absorbed into the rate of `instance_state` since its
+    /// last permutation. Between `1` and the rate.
+    pub num_absorbed: u32,
+    /// First element the verifier's public coin squeezes after absorbing the
+    /// base trace commitment
+    pub challenge: Fp,
+}
+
+impl PoseidonVerifierInputs {
+    /// Number of rows used by the claim
+    const fn num_rows(&self) -> usize {
+        (self.depth as usize + 3) * POSEIDON_BLOCK_SIZE
+    }
+
+    /// Returns the input of the permutation that absorbs the first elements
+    /// of the AIR digest and the base trace commitment. The input of the next
+    /// permutation is `output * mask + values` which absorbs the remaining
+    /// elements. Returns the input, the mask and the values.
+    fn transcript(&self) -> [[Fp; poseidon::WIDTH]; 3] {
+        use poseidon::RATE;
+        // out of range values can't come from a public coin. They're clamped
+        // so verifying doesn't panic.
+        let num_absorbed = (self.num_absorbed as usize).clamp(1, RATE);
+        let elements = [self.air_digest.0, self.base_trace_commitment.0].concat();
+        let (first, second) = elements.split_at(RATE - num_absorbed);
+        let mut input = self.instance_state;
+        input[num_absorbed..RATE].copy_from_slice(first);
+        // the number of absorbed elements is added to the capacity before
+        // each permutation (see `PoseidonPublicCoin::permute`)
+        input[RATE] += Fp::from(RATE as u64);
+        let mut mask = [Fp::ONE; poseidon::WIDTH];
+        let mut values = [Fp::ZERO; poseidon::WIDTH];
+        mask[..num_absorbed].fill(Fp::ZERO);
+        values[..num_absorbed].copy_from_slice(second);
+        values[RATE] = Fp::from(num_absorbed as u64);
+        [input, mask, values]
+    }
+
+    /// Values of the hints of the claim
+    fn hints(&self, trace_domain: &Radix2EvaluationDomain<Fp>) -> Vec<Fp> {
+        let depth = self.depth as usize;
+        let block_end = |block: usize| trace_domain.element((block + 1) * POSEIDON_BLOCK_SIZE - 1);
+        let [transcript_state, absorb_mask, absorb_values] = self.transcript();
+        let mut hints = Vec::new();
+        hints.extend(self.leaf_state);
+        hints.push(trace_domain.element(0));
+        hints.extend(self.base_trace_commitment.0);
+        hints.push(block_end(depth));
+        hints.extend(transcript_state);
+        hints.push(trace_domain.element((depth + 1) * POSEIDON_BLOCK_SIZE));
+        hints.extend(absorb_mask);
+        hints.extend(absorb_values);
+        hints.push(block_end(depth + 1));
+        hints.push(self.challenge);
+        hints.push(block_end(depth + 2));
+        debug_assert_eq!(hints.len(), poseidon_hint::NUM_HINTS);
+        hints
+    }
+}
+
+/// Siblings along the Merkle path of an opened base trace row
+#[derive(Debug, Clone)]
+pub struct PoseidonVerifierWitness {
+    pub position: usize,
+    /// Siblings from the leaf level up to the children of the root
+    pub siblings: Vec<PoseidonDigest>,
+}
+
+/// Builds the public inputs and witness of [`PoseidonVerifierAirConfig`] from
+/// the first opened base trace row of a proof. The proof is verified natively
+/// first since the claim only covers some of its hashes.
+pub fn poseidon_verifier_claim<S>(
+    stark: &S,
+    proof: Proof<S>,
+) -> Result<(PoseidonVerifierClaim, PoseidonVerifierWitness), Error>
+where
+    S: Stark<
+        Fp = Fp,
+        Fq = Fp,
+        Digest = PoseidonDigest,
+        MerkleTree = PoseidonMatrixMerkleTree,
+        PublicCoin = PoseidonPublicCoin<Fp>,
+    >,
+{
+    // preprocessed commitments are absorbed before the base trace commitment
+    if S::AirConfig::NUM_PREPROCESSED_COLUMNS != 0 {
+        return Err(Error::PreprocessedColumns);
+    }
+    let air = Air::try_new(proof.trace_len, stark.get_public_inputs(), proof.options)
+        .map_err(|source| VerificationError::InvalidOptions { source })?;
+    let mut public_coin = stark.gen_public_coin(&air);
+    absorb_instance_inputs::<S>(&mut public_coin, &air);
+    let (instance_state, num_absorbed) = public_coin.sponge_state();
+    let air_digest = air.digest::<PoseidonHashFn>();
+    let base_trace_commitment = proof.base_trace_commitment;
+    public_coin.reseed_with_digest(&air_digest);
+    public_coin.reseed_with_digest(&base_trace_commitment);
+    let challenge = public_coin.draw();
+    let row = proof.trace_queries.base_trace_rows()[0].to_vec();
+    let base_trace_proof = proof.trace_queries.base_trace_proof.clone();
+    let positions = stark.verify(proof, 0)?.query_positions;
+    if row.len() > poseidon::RATE {
+        return Err(Error::RowTooWide { width: row.len() });
+    }
+
+    // sponge state absorbing the row (see `PoseidonHashFn::hash_elements`)
+    let mut leaf_state = [Fp::ZERO; poseidon::WIDTH];
+    leaf_state[..row.len()].copy_from_slice(&row);
+    leaf_state[poseidon::RATE] = Fp::from(row.len() as u64);
+    leaf_state[poseidon::RATE + 1] = Fp::from(Domain::Elements as u64);
+
+    let inputs = PoseidonVerifierInputs {
+        leaf_state,
+        depth: base_trace_proof.height,
+        base_trace_commitment,
+        air_digest,
+        instance_state,
+        num_absorbed: u32::try_from(num_absorbed).unwrap(),
+        challenge,
+    };
+    let witness = PoseidonVerifierWitness {
+        position: positions[0],
+        siblings: merkle_path::<PoseidonHashFn>(positions[0], positions, base_trace_proof),
+    };
+    Ok((PoseidonVerifierClaim(inputs), witness))
+}
+
+pub struct PoseidonVerifierTrace(Matrix<Fp>);
+
+impl Trace for PoseidonVerifierTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> &Matrix<Fp> {
+        &self.0
+    }
+}
+
+/// Indices of the hints of [`PoseidonVerifierAirConfig`]
+mod poseidon_hint {
+    use crate::poseidon::DIGEST_SIZE;
+    use crate::poseidon::WIDTH;
+
+    pub const LEAF_STATE: usize = 0;
+    /// Point of the trace domain at the first row
+    pub const LEAF_X: usize = LEAF_STATE + WIDTH;
+    pub const ROOT: usize = LEAF_X + 1;
+    /// Point of the trace domain at the row with the root
+    pub const ROOT_X: usize = ROOT + DIGEST_SIZE;
+    /// Input of the first permutation of the verifier's public coin
+    pub const TRANSCRIPT_STATE: usize = ROOT_X + 1;
+    /// Point of the trace domain at the first row of the first permutation of
+    /// the verifier's public coin
+    pub const TRANSCRIPT_X: usize = TRANSCRIPT_STATE + WIDTH;
+    /// Input of the second permutation of the verifier's public coin is
+    /// `output * ABSORB_MASK + ABSORB_VALUES` where `output` is the output of
+    /// the first permutation
+    pub const ABSORB_MASK: usize = TRANSCRIPT_X + 1;
+    pub const ABSORB_VALUES: usize = ABSORB_MASK + WIDTH;
+    /// Point of the trace domain at the row with the output of the first
+    /// permutation of the verifier's public coin
+    pub const ABSORB_X: usize = ABSORB_VALUES + WIDTH;
+    pub const CHALLENGE: usize = ABSORB_X + 1;
+    /// Point of the trace domain at the row with the challenge. This is the
+    /// last row of the claim.
+    pub const CHALLENGE_X: usize = CHALLENGE + 1;
+    pub const NUM_HINTS: usize = CHALLENGE_X + 1;
+}
+
+/// Indices of the periodic columns of [`PoseidonVerifierAirConfig`]
+mod poseidon_periodic {
+    /// `1` in rows that apply a round
+    pub const IS_ROUND: usize = 0;
+    /// `1` in the row with the output of the permutation. The output is
+    /// copied into the last row of the block.
+    pub const IS_COPY: usize = IS_ROUND + 1;
+    /// `1` in rows that apply a full round
+    pub const IS_FULL_ROUND: usize = IS_COPY + 1;
+    pub const ARK: usize = IS_FULL_ROUND + 1;
+}
+
+/// Periodic columns with the round constants of the Poseidon permutation
+fn poseidon_periodic_columns() -> Vec<Vec<Fp>> {
+    use poseidon::NUM_ROUNDS;
+    let round_constants = &poseidon::params().round_constants;
+    let rows = || 0..POSEIDON_BLOCK_SIZE;
+    let is_round = rows().map(|row| Fp::from(row < NUM_ROUNDS));
+    let is_copy = rows().map(|row| Fp::from(row == NUM_ROUNDS));
+    let is_full = rows().map(|row| Fp::from(row < NUM_ROUNDS && is_full_round(row)));
+    let mut columns = vec![
+        is_round.collect::<Vec<Fp>>(),
+        is_copy.collect(),
+        is_full.collect(),
+    ];
+    columns.extend((0..poseidon::WIDTH).map(|i| {
+        rows()
+            .map(|row| {
+                round_constants
+                    .get(row)
+                    .map_or(Fp::ZERO, |constants| constants[i])
+            })
+            .collect()
+    }));
+    columns
+}
+
+/// Constraints of [`PoseidonVerifierAirConfig`]
+fn poseidon_constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+    use poseidon::DIGEST_SIZE;
+    use poseidon::RATE;
+    use poseidon::WIDTH;
+    use poseidon_hint::*;
+    use AlgebraicItem::*;
+    type C = PoseidonVerifierAirConfig;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+    let constant = |v: Fp| Constant(FieldVariant::Fp(v));
+    let one = constant(Fp::ONE);
+    let last_x = constant(trace_domain.element(trace_len - 1));
+    let is_round = periodic_column::<C>(poseidon_periodic::IS_ROUND);
+    let is_copy = periodic_column::<C>(poseidon_periodic::IS_COPY);
+    let is_full_round = periodic_column::<C>(poseidon_periodic::IS_FULL_ROUND);
+    let is_block_end = Expr::from(one) - &is_round - &is_copy;
+    let mds = &poseidon::params().mds;
+    let curr = (0..WIDTH).map(|i| i.curr()).collect::<Vec<_>>();
+    let next = (0..WIDTH).map(|i| i.next()).collect::<Vec<_>>();
+    let mut constraints = Vec::new();
+
+    // rounds of the permutation. Partial rounds only apply the S-box to the
+    // first element of the state.
+    let sbox = (0..WIDTH)
+        .map(|i| {
+            let v = &curr[i] + periodic_column::<C>(poseidon_periodic::ARK + i);
+            if i == 0 {
+                v.pow(7)
+            } else {
+                &v + &is_full_round * (v.clone().pow(7) - &v)
+            }
+        })
+        .collect::<Vec<_>>();
+    for (next, mds_row) in zip(&next, mds) {
+        let linear = zip(&sbox, mds_row)
+            .map(|(v, &m)| v * constant(m))
+            .sum::<Expr<_>>();
+        constraints.push(&is_round * (next - linear) / (X.pow(trace_len) - one));
+    }
+    for (curr, next) in zip(&curr, &next) {
+        constraints.push(&is_copy * (next - curr) / (X.pow(trace_len) - one));
+    }
+
+    // chaining the digest of a block into the input of the next Merkle node.
+    // The root and the permutations of the public coin aren't chained this way.
+    let block_ends = [ROOT_X, ABSORB_X, CHALLENGE_X]
+        .map(|i| X - Hint(i))
+        .into_iter()
+        .fold(X - last_x, |ends, end| ends * end);
+    let chain = &is_block_end * block_ends / (X.pow(trace_len) - one);
+    let merge_input = poseidon_merge_state(&PoseidonDigest::default(), &PoseidonDigest::default());
+    for (next, &v) in zip(&next, &merge_input).skip(RATE) {
+        constraints.push(&chain * (next - constant(v)));
+    }
+    let bit = POSEIDON_BIT_COLUMN.next();
+    for i in 0..DIGEST_SIZE {
+        let into_first = &next[i] - &curr[i];
+        let into_second = &next[i + DIGEST_SIZE] - &curr[i];
+        let chained = (Expr::from(one) - &bit) * into_first + &bit * into_second;
+        constraints.push(&chain * chained);
+    }
+    let bit = POSEIDON_BIT_COLUMN.curr();
+    constraints.push(&bit * (&bit - one) / (X.pow(trace_len) - one));
+
+    // public inputs
+    let x_at = |i: usize| X - Hint(i);
+    for (i, curr) in curr.iter().enumerate() {
+        constraints.push((curr - Hint(LEAF_STATE + i)) / x_at(LEAF_X));
+        constraints.push((curr - Hint(TRANSCRIPT_STATE + i)) / x_at(TRANSCRIPT_X));
+    }
+    for (i, (curr, next)) in zip(&curr, &next).enumerate() {
+        let absorbed = curr * Hint(ABSORB_MASK + i) + Hint(ABSORB_VALUES + i);
+        constraints.push((next - absorbed) / x_at(ABSORB_X));
+    }
+    for (i, digest) in curr[..DIGEST_SIZE].iter().enumerate() {
+        constraints.push((digest - Hint(ROOT + i)) / x_at(ROOT_X));
+    }
+    constraints.push((&curr[0] - Hint(CHALLENGE)) / x_at(CHALLENGE_X));
+
+    constraints.into_iter().map(Constraint::new).collect()
+}
+
+/// Example AIR that proves an opened base trace row of a proof that uses the
+/// Poseidon public coin and Merkle trees authenticates to the base trace
+/// commitment and that the verifier's public coin squeezes the claimed
+/// challenge after absorbing the commitment. Doesn't verify the proof (see
+/// the [module docs](self)).
+pub struct PoseidonVerifierAirConfig;
+
+impl AirConfig for PoseidonVerifierAirConfig {
+    const NUM_BASE_COLUMNS: usize = poseidon::WIDTH + 1;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = PoseidonVerifierInputs;
+
+    fn periodic_columns() -> Vec<Vec<Fp>> {
+        poseidon_periodic_columns()
+    }
+
+    fn gen_hints(
+        trace_len: usize,
+        inputs: &PoseidonVerifierInputs,
+        _: &Challenges<Fp>,
+    ) -> Hints<Fp> {
+        let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        Hints::new(
+            inputs
+                .hints(&trace_domain)
+                .into_iter()
+                .enumerate()
+                .collect(),
+        )
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        poseidon_constraints(trace_len)
+    }
+}
+
+/// Claims the hashes described by the public inputs are computed correctly.
+/// Proofs of this claim use Poseidon too so their hashes can be checked by
+/// another claim in turn.
+pub struct PoseidonVerifierClaim(pub PoseidonVerifierInputs);
+
+impl Stark for PoseidonVerifierClaim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = PoseidonVerifierAirConfig;
+    type Digest = PoseidonDigest;
+    type PublicCoin = PoseidonPublicCoin<Fp>;
+    type MerkleTree = PoseidonMatrixMerkleTree;
+    type Witness = PoseidonVerifierWitness;
+    type Trace = PoseidonVerifierTrace;
+
+    fn get_public_inputs(&self) -> PoseidonVerifierInputs {
+        self.0.clone()
+    }
+
+    fn gen_public_coin(&self, air: &Air<PoseidonVerifierAirConfig>) -> Self::PublicCoin {
+        let mut seed = Vec::new();
+        air.public_inputs().serialize_compressed(&mut seed).unwrap();
+        air.trace_len().serialize_compressed(&mut seed).unwrap();
+        air.options().serialize_compressed(&mut seed).unwrap();
+        PoseidonPublicCoin::new(PoseidonHashFn::hash_chunks([&*seed]))
+    }
+
+    fn generate_trace(&self, witness: PoseidonVerifierWitness) -> PoseidonVerifierTrace {
+        let claim = &self.0;
+        let mut rows = Vec::new();
+        let mut state = poseidon_block(&mut rows, claim.leaf_state, false);
+        for (level, sibling) in witness.siblings.iter().enumerate() {
+            let is_right = (witness.position >> level) & 1 == 1;
+            let digest = poseidon_digest(&state);
+            let (left, right) = if is_right {
+                (sibling, &digest)
+            } else {
+                (&digest, sibling)
+            };
+            state = poseidon_block(&mut rows, poseidon_merge_state(left, right), is_right);
+        }
+        let [transcript_state, absorb_mask, absorb_values] = claim.transcript();
+        state = poseidon_block(&mut rows, transcript_state, false);
+        for ((v, mask), value) in zip(zip(&mut state, absorb_mask), absorb_values) {
+            *v = *v * mask + value;
+        }
+        state = poseidon_block(&mut rows, state, false);
+        while rows.len() < claim.num_rows().next_power_of_two() {
+            let padding = PoseidonDigest::default();
+            let input = poseidon_merge_state(&poseidon_digest(&state), &padding);
+            state = poseidon_block(&mut rows, input, false);
+        }
+        PoseidonVerifierTrace(Matrix::from_rows(rows))
+    }
+}
+
+/// Input state of the Poseidon permutation that merges two digests (see
+/// `PoseidonHashFn::merge`)
+fn poseidon_merge_state(left: &PoseidonDigest, right: &PoseidonDigest) -> [Fp; poseidon::WIDTH] {
+    use poseidon::DIGEST_SIZE;
+    use poseidon::RATE;
+    let mut state = [Fp::ZERO; poseidon::WIDTH];
+    state[..DIGEST_SIZE].copy_from_slice(&left.0);
+    state[DIGEST_SIZE..RATE].copy_from_slice(&right.0);
+    state[RATE] = Fp::from(RATE as u64);
+    state[RATE + 1] = Fp::from(Domain::Merge as u64);
+    state
+}
+
+fn poseidon_digest(state: &[Fp; poseidon::WIDTH]) -> PoseidonDigest {
+    PoseidonDigest(state[..poseidon::DIGEST_SIZE].try_into().unwrap())
+}
+
+/// Appends the rows of a Poseidon permutation of `state` and returns the
+/// output state
+fn poseidon_block(
+    rows: &mut Vec<Vec<Fp>>,
+    mut state: [Fp; poseidon::WIDTH],
+    bit: bool,
+) -> [Fp; poseidon::WIDTH] {
+    let bit = Fp::from(bit);
+    let row = |state: &[Fp; poseidon::WIDTH]| state.iter().copied().chain([bit]).collect();
+    for round in 0..poseidon::NUM_ROUNDS {
+        rows.push(row(&state));
+        poseidon::apply_round(&mut state, round);
+    }
+    // the output and its copy
+    rows.push(row(&state));
+    rows.push(row(&state));
+    state
+}
@@ -0,0 +1,473 @@
+//! Reference verifier for differential testing of the optimized verifier.
+//!
+//! [`verify`] checks a proof the most direct way possible. It evaluates the
+//! uncompiled composition constraint at the out-of-domain point, computes
+//! every queried point with an explicit exponentiation and folds FRI layers by
+//! Lagrange interpolation. The only code it shares with the [verifier] is the
+//! public coin, Merkle proof verification and the AIR's constraints. It's slow
+//! and allocates freely so it's only meant for tests.
+//!
+//! With the `reference-verify` feature every proof checked by
+//! [`Stark::verify`] is also checked by the reference verifier and a panic is
+//! raised if they disagree. A disagreement means an optimization changed what
+//! the verifier accepts.
+//!
+//! [verifier]: crate::verifier
+use crate::air::AirConfig;
+use crate::challenges::Challenges;
+use crate::channel::absorb_instance;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::CompositionItem;
+use crate::merkle::MatrixMerkleTree;
+use crate::proof::security_level_bits;
+use crate::random::draw_multiple;
+use crate::random::PublicCoin;
+use crate::stark::Stark;
+use crate::utils::FieldVariant;
+use crate::verifier::CommittedTrace;
+use crate::Air;
+use crate::Proof;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::Zero;
+use core::fmt::Display;
+use ministark_gpu::utils::bit_reverse_index;
+use snafu::ensure;
+use snafu::Snafu;
+
+/// Reasons the reference verifier rejects a proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+pub enum Error {
+    #[snafu(display("invalid proof options or trace length"))]
+    InvalidOptions,
+    #[snafu(display(
+        "proof has `{security_level}` bits of security but `{required}` are required"
+    ))]
+    InsufficientSecurity { security_level: u32, required: u32 },
+    #[snafu(display("AIR has preprocessed columns but no preprocessed commitment was provided"))]
+    MissingPreprocessedCommitment,
+    #[snafu(display("proof has the wrong number of auxiliary segment commitments"))]
+    AuxSegmentCount,
+    #[snafu(display("proof has the wrong number of out-of-domain evaluations"))]
+    OodEvaluationCount,
+    #[snafu(display("constraints don't match the composition trace at the out-of-domain point"))]
+    OodConstraintEvaluation,
+    #[snafu(display("proof has `{actual}` FRI layers but `{expected}` are expected"))]
+    FriLayerCount { expected: usize, actual: usize },
+    #[snafu(display("invalid proof of work"))]
+    ProofOfWork,
+    #[snafu(display("queried rows don't resolve to the {trace} trace commitment"))]
+    TraceCommitment { trace: CommittedTrace },
+    #[snafu(display("queried rows don't resolve to the commitment of FRI layer {layer}"))]
+    FriLayerCommitment { layer: usize },
+    #[snafu(display("FRI layer {layer} doesn't contain the folded values of the previous layer"))]
+    FriFolding { layer: usize },
+    #[snafu(display("FRI remainder has a degree larger than {max_degree}"))]
+    RemainderDegree { max_degree: usize },
+    #[snafu(display("FRI remainder doesn't match the last layer"))]
+    RemainderEvaluation,
+}
+
+/// Verifies `proof` without any of the optimized verifier's machinery
+#[allow(clippy::too_many_lines)]
+pub fn verify<S: Stark>(
+    stark: &S,
+    proof: &Proof<S>,
+    required_security_bits: u32,
+) -> Result<(), Error> {
+    let options = proof.options;
+    let trace_len = proof.trace_len;
+    options.validate().map_err(|_| Error::InvalidOptions)?;
+    let air = Air::try_new(trace_len, stark.get_public_inputs(), options)
+        .map_err(|_| Error::InvalidOptions)?;
+    let security_level = security_level_bits::<S>(options, trace_len);
+    ensure!(
+        security_level >= required_security_bits,
+        InsufficientSecuritySnafu {
+            security_level,
+            required: required_security_bits,
+        }
+    );
+
+    // replay the prover's transcript
+    let mut public_coin = stark.gen_public_coin(&air);
+    absorb_instance::<S>(&mut public_coin, &air);
+    let preprocessed_commitment = if S::AirConfig::NUM_PREPROCESSED_COLUMNS == 0 {
+        None
+    } else {
+        let commitment = stark
+            .preprocessed_commitment()
+            .ok_or(Error::MissingPreprocessedCommitment)?;
+        public_coin.reseed_with_digest(&commitment);
+        Some(commitment)
+    };
+    public_coin.reseed_with_digest(&proof.base_trace_commitment);
+    // only auxiliary segments with columns are committed to
+    let aux_segments = air.aux_segments();
+    let segment_commitments = proof
+        .extension_trace_commitment
+        .iter()
+        .chain(&proof.aux_trace_commitments)
+        .collect::<Vec<&S::Digest>>();
+    let num_committed_segments = aux_segments.iter().filter(|s| s.num_columns != 0).count();
+    ensure!(
+        segment_commitments.len() == num_committed_segments
+            && (proof.extension_trace_commitment.is_some()
+                || proof.aux_trace_commitments.is_empty()),
+        AuxSegmentCountSnafu
+    );
+    let mut challenges = Vec::new();
+    let mut commitments = segment_commitments.iter();
+    for segment in &aux_segments {
+        challenges.extend(draw_multiple(&mut public_coin, segment.num_challenges));
+        if segment.num_columns != 0 {
+            public_coin.reseed_with_digest(commitments.next().unwrap());
+        }
+    }
+    let challenges = Challenges::new(challenges);
+    let hints = air.gen_hints(&challenges);
+    let composition_coeffs = stark.gen_composition_constraint_coeffs(&mut public_coin, &air);
+    public_coin.reseed_with_digest(&proof.composition_trace_commitment);
+    let z: S::Fq = public_coin.draw();
+    let mut ood_evals = proof.execution_trace_ood_evals.clone();
+    ood_evals.extend(proof.composition_trace_ood_evals.iter().copied());
+    public_coin.reseed_with_field_elements(&ood_evals);
+
+    // the constraints must agree with the composition trace at z
+    let trace_arguments = air
+        .trace_arguments()
+        .into_iter()
+        .collect::<Vec<(usize, isize)>>();
+    ensure!(
+        trace_arguments.len() == proof.execution_trace_ood_evals.len()
+            && proof.composition_trace_ood_evals.len() == air.ce_blowup_factor(),
+        OodEvaluationCountSnafu
+    );
+    let trace_ood_eval = |argument: (usize, isize)| {
+        let i = trace_arguments.iter().position(|&a| a == argument).unwrap();
+        proof.execution_trace_ood_evals[i]
+    };
+    let constraint_evaluation = air
+        .composition_constraint()
+        .eval(&mut |leaf| {
+            use AlgebraicItem::*;
+            use CompositionItem::*;
+            match leaf {
+                Item(X) => FieldVariant::Fq(z),
+                &Item(Constant(v)) => v,
+                &Item(Challenge(i)) => FieldVariant::Fq(challenges[i]),
+                &Item(Hint(i)) => FieldVariant::Fq(hints[i]),
+                Item(Periodic(col)) => {
+                    let point = z.pow([(trace_len / col.interval_size()) as u64]);
+                    FieldVariant::Fq(power_sum(
+                        col.coeffs().iter().map(FieldVariant::as_fq),
+                        point,
+                    ))
+                }
+                &Item(Trace(i, j)) => FieldVariant::Fq(trace_ood_eval((i, j))),
+                &CompositionCoeff(i) => FieldVariant::Fq(composition_coeffs[i]),
+            }
+        })
+        .as_fq();
+    let composition_evaluation = power_sum(proof.composition_trace_ood_evals.iter().copied(), z);
+    ensure!(
+        constraint_evaluation == composition_evaluation,
+        OodConstraintEvaluationSnafu
+    );
+
+    let deep_coeffs = stark.gen_deep_coeffs(&mut public_coin, &air);
+    let lde_domain_size = trace_len * air.lde_blowup_factor();
+    let folding_factor = usize::from(options.fri_folding_factor);
+    let fri_layers = &proof.fri_proof.layers;
+    let num_fri_layers = options.into_fri_options().num_layers(lde_domain_size);
+    ensure!(
+        fri_layers.len() == num_fri_layers,
+        FriLayerCountSnafu {
+            expected: num_fri_layers,
+            actual: fri_layers.len(),
+        }
+    );
+    let mut fri_alphas = Vec::new();
+    for layer in fri_layers {
+        public_coin.reseed_with_digest(&layer.commitment);
+        fri_alphas.push(public_coin.draw());
+    }
+    public_coin.reseed_with_field_element_vector(&proof.fri_proof.remainder_coeffs);
+    if options.grinding_factor != 0 {
+        ensure!(
+            options
+                .pow_hash
+                .verify(&mut public_coin, options.grinding_factor, proof.pow_nonce),
+            ProofOfWorkSnafu
+        );
+        public_coin.reseed_with_int(proof.pow_nonce);
+    }
+    let positions = options
+        .query_sampling
+        .draw(
+            &mut public_coin,
+            options.num_queries.into(),
+            lde_domain_size,
+        )
+        .into_iter()
+        .collect::<Vec<usize>>();
+
+    // queried rows must resolve to their commitments
+    let queries = &proof.trace_queries;
+    let num_queries = positions.len();
+    let num_base_columns = S::AirConfig::NUM_BASE_COLUMNS - S::AirConfig::NUM_PREPROCESSED_COLUMNS;
+    let invalid = |trace| move |_| Error::TraceCommitment { trace };
+    let base_rows = split_rows(&queries.base_trace_values, num_base_columns, num_queries).ok_or(
+        Error::TraceCommitment {
+            trace: CommittedTrace::Base,
+        },
+    )?;
+    S::MerkleTree::verify_rows(
+        &proof.base_trace_commitment,
+        &positions,
+        &base_rows,
+        queries.base_trace_proof.clone(),
+    )
+    .map_err(invalid(CommittedTrace::Base))?;
+
+    let preprocessed_rows = split_rows(
+        &queries.preprocessed_trace_values,
+        S::AirConfig::NUM_PREPROCESSED_COLUMNS,
+        num_queries,
+    )
+    .ok_or(Error::TraceCommitment {
+        trace: CommittedTrace::Preprocessed,
+    })?;
+    if let Some(commitment) = &preprocessed_commitment {
+        let proof = queries
+            .preprocessed_trace_proof
+            .clone()
+            .ok_or(Error::TraceCommitment {
+                trace: CommittedTrace::Preprocessed,
+            })?;
+        S::MerkleTree::verify_rows(commitment, &positions, &preprocessed_rows, proof)
+            .map_err(invalid(CommittedTrace::Preprocessed))?;
+    }
+
+    let extension_rows = split_rows(
+        &queries.extension_trace_values,
+        S::AirConfig::NUM_EXTENSION_COLUMNS,
+        num_queries,
+    )
+    .ok_or(Error::TraceCommitment {
+        trace: CommittedTrace::Extension,
+    })?;
+    ensure!(
+        queries.aux_trace_proofs.len() == proof.aux_trace_commitments.len(),
+        TraceCommitmentSnafu {
+            trace: CommittedTrace::AuxSegment(proof.aux_trace_commitments.len()),
+        }
+    );
+    let mut segment_proofs = queries
+        .extension_trace_proof
+        .iter()
+        .chain(&queries.aux_trace_proofs);
+    let mut first_column = 0;
+    let committed_segments = aux_segments.iter().filter(|s| s.num_columns != 0);
+    for (k, (segment, commitment)) in committed_segments.zip(&segment_commitments).enumerate() {
+        let trace = if k == 0 {
+            CommittedTrace::Extension
+        } else {
+            CommittedTrace::AuxSegment(k)
+        };
+        let columns = first_column..first_column + segment.num_columns;
+        let rows = extension_rows
+            .iter()
+            .map(|row| row[columns.clone()].to_vec())
+            .collect::<Vec<Vec<S::Fq>>>();
+        let segment_proof = segment_proofs
+            .next()
+            .ok_or(Error::TraceCommitment { trace })?;
+        S::MerkleTree::verify_rows(commitment, &positions, &rows, segment_proof.clone())
+            .map_err(invalid(trace))?;
+        first_column = columns.end;
+    }
+
+    let composition_rows = split_rows(
+        &queries.composition_trace_values,
+        air.ce_blowup_factor(),
+        num_queries,
+    )
+    .ok_or(Error::TraceCommitment {
+        trace: CommittedTrace::Composition,
+    })?;
+    S::MerkleTree::verify_rows(
+        &proof.composition_trace_commitment,
+        &positions,
+        &composition_rows,
+        queries.composition_trace_proof.clone(),
+    )
+    .map_err(invalid(CommittedTrace::Composition))?;
+
+    // evaluate the DEEP composition polynomial at each queried point
+    let lde_generator = S::Fp::get_root_of_unity(lde_domain_size as u64).unwrap();
+    let trace_generator = S::Fp::get_root_of_unity(trace_len as u64).unwrap();
+    let domain_offset = S::AirConfig::domain_offset();
+    let z_n = z.pow([air.ce_blowup_factor() as u64]);
+    let mut evaluations = Vec::new();
+    for (i, &position) in positions.iter().enumerate() {
+        let exponent = bit_reverse_index(lde_domain_size, position) as u64;
+        let x = S::Fq::from(domain_offset * lde_generator.pow([exponent]));
+        let mut evaluation = S::Fq::zero();
+        for (j, &(column, offset)) in trace_arguments.iter().enumerate() {
+            let value = if column < num_base_columns {
+                S::Fq::from(base_rows[i][column])
+            } else if column < S::AirConfig::NUM_BASE_COLUMNS {
+                S::Fq::from(preprocessed_rows[i][column - num_base_columns])
+            } else {
+                extension_rows[i][column - S::AirConfig::NUM_BASE_COLUMNS]
+            };
+            // g^offset for negative offsets is g^(n - |offset|)
+            let shift_exponent = if offset >= 0 {
+                offset.unsigned_abs()
+            } else {
+                trace_len - offset.unsigned_abs()
+            };
+            let shift = S::Fq::from(trace_generator.pow([shift_exponent as u64]));
+            let ood_eval = proof.execution_trace_ood_evals[j];
+            evaluation += deep_coeffs.execution_trace[j] * (value - ood_eval) / (x - z * shift);
+        }
+        for (j, &value) in composition_rows[i].iter().enumerate() {
+            let ood_eval = proof.composition_trace_ood_evals[j];
+            evaluation += deep_coeffs.composition_trace[j] * (value - ood_eval) / (x - z_n);
+        }
+        let (alpha, beta) = deep_coeffs.degree;
+        evaluations.push(evaluation * (alpha + beta * x));
+    }
+
+    // fold the evaluations through each FRI layer. Layers hold bit-reversed
+    // evaluations over a subgroup in rows of `folding_factor` values.
+    let mut positions = positions;
+    let mut domain_size = lde_domain_size;
+    let mut generator = lde_generator;
+    for (layer_index, (layer, &alpha)) in fri_layers.iter().zip(&fri_alphas).enumerate() {
+        let folded_positions = positions
+            .iter()
+            .map(|position| position / folding_factor)
+            .collect::<BTreeSet<usize>>()
+            .into_iter()
+            .collect::<Vec<usize>>();
+        let rows = split_rows(
+            &layer.flattenend_rows,
+            folding_factor,
+            folded_positions.len(),
+        )
+        .ok_or(Error::FriLayerCommitment { layer: layer_index })?;
+        S::MerkleTree::verify_rows(
+            &layer.commitment,
+            &folded_positions,
+            &rows,
+            layer.merkle_proof.clone(),
+        )
+        .map_err(|_| Error::FriLayerCommitment { layer: layer_index })?;
+
+        for (&position, evaluation) in positions.iter().zip(&evaluations) {
+            let row = folded_positions
+                .iter()
+                .position(|&p| p == position / folding_factor)
+                .unwrap();
+            ensure!(
+                rows[row][position % folding_factor] == *evaluation,
+                FriFoldingSnafu { layer: layer_index }
+            );
+        }
+
+        evaluations = folded_positions
+            .iter()
+            .zip(&rows)
+            .map(|(&position, row)| {
+                let xs = (0..folding_factor)
+                    .map(|k| {
+                        let exponent =
+                            bit_reverse_index(domain_size, position * folding_factor + k);
+                        S::Fq::from(generator.pow([exponent as u64]))
+                    })
+                    .collect::<Vec<S::Fq>>();
+                lagrange_evaluate(&xs, row, alpha) * S::Fq::from(folding_factor as u64)
+            })
+            .collect();
+        positions = folded_positions;
+        generator = generator.pow([folding_factor as u64]);
+        domain_size /= folding_factor;
+    }
+
+    let remainder_coeffs = &proof.fri_proof.remainder_coeffs;
+    let max_degree = domain_size / air.lde_blowup_factor() - 1;
+    ensure!(
+        remainder_coeffs
+            .iter()
+            .skip(max_degree + 1)
+            .all(Zero::is_zero),
+        RemainderDegreeSnafu { max_degree }
+    );
+    for (&position, evaluation) in positions.iter().zip(&evaluations) {
+        let exponent = bit_reverse_index(domain_size, position) as u64;
+        let x = S::Fq::from(generator.pow([exponent]));
+        ensure!(
+            power_sum(remainder_coeffs.iter().copied(), x) == *evaluation,
+            RemainderEvaluationSnafu
+        );
+    }
+    Ok(())
+}
+
+/// Panics if the optimized verifier and the reference verifier disagree on
+/// whether a proof is valid
+pub(crate) fn assert_agreement<T, E: Display>(
+    verified: &Result<T, E>,
+    reference: &Result<(), Error>,
+) {
+    match (verified, reference) {
+        (Ok(_), Err(err)) => {
+            panic!("verifier accepted a proof the reference verifier rejected: {err}")
+        }
+        (Err(err), Ok(())) => {
+            panic!("verifier rejected a proof the reference verifier accepted: {err}")
+        }
+        _ => {}
+    }
+}
+
+/// Returns `Σ coeffs[i] * x^i` with every power computed from scratch
+fn power_sum<F: Field>(coeffs: impl IntoIterator<Item = F>, x: F) -> F {
+    coeffs
+        .into_iter()
+        .enumerate()
+        .map(|(i, coeff)| coeff * x.pow([i as u64]))
+        .sum()
+}
+
+/// Evaluates the polynomial through the points `(xs[i], ys[i])` at `x`
+fn lagrange_evaluate<F: Field>(xs: &[F], ys: &[F], x: F) -> F {
+    let mut result = F::zero();
+    for (i, (&x_i, &y_i)) in xs.iter().zip(ys).enumerate() {
+        let mut basis = F::one();
+        for (j, &x_j) in xs.iter().enumerate() {
+            if i != j {
+                basis *= (x - x_j) / (x_i - x_j);
+            }
+        }
+        result += y_i * basis;
+    }
+    result
+}
+
+/// Splits `values` into `num_rows` rows of `num_columns` values. Returns
+/// `None` if there are too many or too few values.
+fn split_rows<F: Copy>(values: &[F], num_columns: usize, num_rows: usize) -> Option<Vec<Vec<F>>> {
+    if values.len() != num_columns * num_rows {
+        return None;
+    }
+    Some(
+        (0..num_rows)
+            .map(|i| values[i * num_columns..(i + 1) * num_columns].to_vec())
+            .collect(),
+    )
+}
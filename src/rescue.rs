@@ -0,0 +1,411 @@
+//! Rescue Prime Optimized (RPO) hash function over the 64-bit Goldilocks field
+//! (`2^64 - 2^32 + 1`) <https://eprint.iacr.org/2022/1577.pdf>.
+//!
+//! Uses the parameters for 128 bit security (state width 12, rate 8, 7
+//! rounds) and the same round constants and MDS matrix as the GPU
+//! implementation in `ministark-gpu`. Hashing field elements and merging
+//! digests follows Miden's `Rpo256`.
+use crate::hash::Digest;
+use crate::hash::ElementHashFn;
+use crate::hash::HashFn;
+#[cfg(feature = "merkle")]
+use crate::merkle::MatrixMerkleTreeImpl;
+#[cfg(feature = "merkle")]
+use crate::merkle::MerkleTreeConfig;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+#[cfg(feature = "merkle")]
+use core::marker::PhantomData;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+pub(crate) const STATE_WIDTH: usize = 12;
+pub(crate) const CAPACITY: usize = 4;
+pub(crate) const RATE: usize = 8;
+pub(crate) const DIGEST_SIZE: usize = 4;
+pub(crate) const NUM_ROUNDS: usize = 7;
+
+/// Number of bytes packed into each field element when hashing bytes
+const BYTES_PER_ELEMENT: usize = 7;
+
+/// `x^(1/7)` is `x^INV_ALPHA`
+#[allow(clippy::unreadable_literal)]
+const INV_ALPHA: u64 = 10540996611094048183;
+
+/// First row of the circulant MDS matrix
+pub(crate) const MDS: [u64; STATE_WIDTH] = [7, 23, 8, 26, 13, 10, 9, 7, 6, 22, 21, 8];
+
+/// Round constants added before the `x^7` S-box
+#[allow(clippy::unreadable_literal)]
+pub(crate) const ARK1: [[u64; STATE_WIDTH]; NUM_ROUNDS] = [
+    [
+        5789762306288267392,
+        6522564764413701783,
+        17809893479458208203,
+        107145243989736508,
+        6388978042437517382,
+        15844067734406016715,
+        9975000513555218239,
+        3344984123768313364,
+        9959189626657347191,
+        12960773468763563665,
+        9602914297752488475,
+        16657542370200465908,
+    ],
+    [
+        12987190162843096997,
+        653957632802705281,
+        4441654670647621225,
+        4038207883745915761,
+        5613464648874830118,
+        13222989726778338773,
+        3037761201230264149,
+        16683759727265180203,
+        8337364536491240715,
+        3227397518293416448,
+        8110510111539674682,
+        2872078294163232137,
+    ],
+    [
+        18072785500942327487,
+        6200974112677013481,
+        17682092219085884187,
+        10599526828986756440,
+        975003873302957338,
+        8264241093196931281,
+        10065763900435475170,
+        2181131744534710197,
+        6317303992309418647,
+        1401440938888741532,
+        8884468225181997494,
+        13066900325715521532,
+    ],
+    [
+        5674685213610121970,
+        5759084860419474071,
+        13943282657648897737,
+        1352748651966375394,
+        17110913224029905221,
+        1003883795902368422,
+        4141870621881018291,
+        8121410972417424656,
+        14300518605864919529,
+        13712227150607670181,
+        17021852944633065291,
+        6252096473787587650,
+    ],
+    [
+        4887609836208846458,
+        3027115137917284492,
+        9595098600469470675,
+        10528569829048484079,
+        7864689113198939815,
+        17533723827845969040,
+        5781638039037710951,
+        17024078752430719006,
+        109659393484013511,
+        7158933660534805869,
+        2955076958026921730,
+        7433723648458773977,
+    ],
+    [
+        16308865189192447297,
+        11977192855656444890,
+        12532242556065780287,
+        14594890931430968898,
+        7291784239689209784,
+        5514718540551361949,
+        10025733853830934803,
+        7293794580341021693,
+        6728552937464861756,
+        6332385040983343262,
+        13277683694236792804,
+        2600778905124452676,
+    ],
+    [
+        7123075680859040534,
+        1034205548717903090,
+        7717824418247931797,
+        3019070937878604058,
+        11403792746066867460,
+        10280580802233112374,
+        337153209462421218,
+        13333398568519923717,
+        3596153696935337464,
+        8104208463525993784,
+        14345062289456085693,
+        17036731477169661256,
+    ],
+];
+/// Round constants added before the `x^(1/7)` S-box
+#[allow(clippy::unreadable_literal)]
+pub(crate) const ARK2: [[u64; STATE_WIDTH]; NUM_ROUNDS] = [
+    [
+        6077062762357204287,
+        15277620170502011191,
+        5358738125714196705,
+        14233283787297595718,
+        13792579614346651365,
+        11614812331536767105,
+        14871063686742261166,
+        10148237148793043499,
+        4457428952329675767,
+        15590786458219172475,
+        10063319113072092615,
+        14200078843431360086,
+    ],
+    [
+        6202948458916099932,
+        17690140365333231091,
+        3595001575307484651,
+        373995945117666487,
+        1235734395091296013,
+        14172757457833931602,
+        707573103686350224,
+        15453217512188187135,
+        219777875004506018,
+        17876696346199469008,
+        17731621626449383378,
+        2897136237748376248,
+    ],
+    [
+        8023374565629191455,
+        15013690343205953430,
+        4485500052507912973,
+        12489737547229155153,
+        9500452585969030576,
+        2054001340201038870,
+        12420704059284934186,
+        355990932618543755,
+        9071225051243523860,
+        12766199826003448536,
+        9045979173463556963,
+        12934431667190679898,
+    ],
+    [
+        18389244934624494276,
+        16731736864863925227,
+        4440209734760478192,
+        17208448209698888938,
+        8739495587021565984,
+        17000774922218161967,
+        13533282547195532087,
+        525402848358706231,
+        16987541523062161972,
+        5466806524462797102,
+        14512769585918244983,
+        10973956031244051118,
+    ],
+    [
+        6982293561042362913,
+        14065426295947720331,
+        16451845770444974180,
+        7139138592091306727,
+        9012006439959783127,
+        14619614108529063361,
+        1394813199588124371,
+        4635111139507788575,
+        16217473952264203365,
+        10782018226466330683,
+        6844229992533662050,
+        7446486531695178711,
+    ],
+    [
+        3736792340494631448,
+        577852220195055341,
+        6689998335515779805,
+        13886063479078013492,
+        14358505101923202168,
+        7744142531772274164,
+        16135070735728404443,
+        12290902521256031137,
+        12059913662657709804,
+        16456018495793751911,
+        4571485474751953524,
+        17200392109565783176,
+    ],
+    [
+        17130398059294018733,
+        519782857322261988,
+        9625384390925085478,
+        1664893052631119222,
+        7629576092524553570,
+        3485239601103661425,
+        9755891797164033838,
+        15218148195153269027,
+        16460604813734957368,
+        9643968136937729763,
+        3611348709641382851,
+        18256379591337759196,
+    ],
+];
+
+fn apply_mds(state: &mut [Fp; STATE_WIDTH]) {
+    let prev = *state;
+    for (i, v) in state.iter_mut().enumerate() {
+        *v = (0..STATE_WIDTH)
+            .map(|j| Fp::from(MDS[(STATE_WIDTH + j - i) % STATE_WIDTH]) * prev[j])
+            .sum();
+    }
+}
+
+fn add_constants(state: &mut [Fp; STATE_WIDTH], constants: &[u64; STATE_WIDTH]) {
+    for (v, &constant) in state.iter_mut().zip(constants) {
+        *v += Fp::from(constant);
+    }
+}
+
+/// Applies the RPO permutation to `state`
+pub fn permute(state: &mut [Fp; STATE_WIDTH]) {
+    for round in 0..NUM_ROUNDS {
+        apply_round(state, round);
+    }
+}
+
+/// Applies round `round` of the RPO permutation to `state`
+pub(crate) fn apply_round(state: &mut [Fp; STATE_WIDTH], round: usize) {
+    apply_mds(state);
+    add_constants(state, &ARK1[round]);
+    for v in state.iter_mut() {
+        *v = v.pow([7]);
+    }
+    apply_mds(state);
+    add_constants(state, &ARK2[round]);
+    for v in state.iter_mut() {
+        *v = v.pow([INV_ALPHA]);
+    }
+}
+
+/// Absorbs elements into the rate. The capacity is set to one and the input
+/// padded with a one followed by zeros if the number of elements isn't a
+/// multiple of the rate.
+fn sponge(elements: &[Fp]) -> Rpo256Digest {
+    let mut state = [Fp::ZERO; STATE_WIDTH];
+    if elements
+        .chunks(RATE)
+        .last()
+        .is_some_and(|chunk| chunk.len() < RATE)
+    {
+        state[0] = Fp::ONE;
+    }
+    for chunk in elements.chunks(RATE) {
+        state[CAPACITY..CAPACITY + chunk.len()].copy_from_slice(chunk);
+        if chunk.len() < RATE {
+            state[CAPACITY + chunk.len()] = Fp::ONE;
+            state[CAPACITY + chunk.len() + 1..].fill(Fp::ZERO);
+        }
+        permute(&mut state);
+    }
+    Rpo256Digest(state[CAPACITY..CAPACITY + DIGEST_SIZE].try_into().unwrap())
+}
+
+/// Digest of the RPO hash function. Consists of four field elements.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Rpo256Digest(pub [Fp; DIGEST_SIZE]);
+
+impl Digest for Rpo256Digest {
+    fn as_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (chunk, v) in bytes.chunks_mut(8).zip(&self.0) {
+            chunk.copy_from_slice(&v.into_bigint().0[0].to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Rescue Prime Optimized hash function over the Goldilocks field
+pub struct Rpo256HashFn;
+
+impl HashFn for Rpo256HashFn {
+    type Digest = Rpo256Digest;
+
+    const COLLISION_RESISTANCE: u32 = 128;
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> Rpo256Digest {
+        // the number of bytes is absorbed so different inputs don't collide
+        // after being packed into field elements
+        let bytes = bytes.into_iter().collect::<Vec<u8>>();
+        let mut elements = vec![Fp::from(bytes.len() as u64)];
+        elements.extend(bytes.chunks(BYTES_PER_ELEMENT).map(|chunk| {
+            let mut word = [0; 8];
+            word[0..chunk.len()].copy_from_slice(chunk);
+            Fp::from(u64::from_le_bytes(word))
+        }));
+        sponge(&elements)
+    }
+
+    fn hash_chunks<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> Rpo256Digest {
+        Self::hash(chunks.into_iter().flatten().copied())
+    }
+
+    fn merge(v0: &Rpo256Digest, v1: &Rpo256Digest) -> Rpo256Digest {
+        let mut state = [Fp::ZERO; STATE_WIDTH];
+        state[CAPACITY..CAPACITY + DIGEST_SIZE].copy_from_slice(&v0.0);
+        state[CAPACITY + DIGEST_SIZE..].copy_from_slice(&v1.0);
+        permute(&mut state);
+        Rpo256Digest(state[CAPACITY..CAPACITY + DIGEST_SIZE].try_into().unwrap())
+    }
+
+    fn merge_with_int(seed: &Rpo256Digest, value: u64) -> Rpo256Digest {
+        // `value` is split into two elements since it can exceed the modulus.
+        // The capacity holds the number of absorbed elements.
+        let mut state = [Fp::ZERO; STATE_WIDTH];
+        state[CAPACITY..CAPACITY + DIGEST_SIZE].copy_from_slice(&seed.0);
+        state[CAPACITY + DIGEST_SIZE] = Fp::from(value & 0xFFFF_FFFF);
+        state[CAPACITY + DIGEST_SIZE + 1] = Fp::from(value >> 32);
+        state[0] = Fp::from((DIGEST_SIZE + 2) as u64);
+        permute(&mut state);
+        Rpo256Digest(state[CAPACITY..CAPACITY + DIGEST_SIZE].try_into().unwrap())
+    }
+}
+
+/// Elements of the base field and its extensions are absorbed as base field
+/// elements
+impl<F: Field<BasePrimeField = Fp>> ElementHashFn<F> for Rpo256HashFn {
+    fn hash_elements(elements: impl IntoIterator<Item = F>) -> Rpo256Digest {
+        let elements = elements
+            .into_iter()
+            .flat_map(|v| v.to_base_prime_field_elements().collect::<Vec<Fp>>())
+            .collect::<Vec<Fp>>();
+        sponge(&elements)
+    }
+}
+
+/// Merkle tree config where leaves are field elements that are hashed with
+/// RPO
+#[cfg(feature = "merkle")]
+pub struct Rpo256MerkleTreeConfig<F>(PhantomData<F>);
+
+#[cfg(feature = "merkle")]
+impl<F> Clone for Rpo256MerkleTreeConfig<F> {
+    fn clone(&self) -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[cfg(feature = "merkle")]
+impl<F: Field<BasePrimeField = Fp>> MerkleTreeConfig for Rpo256MerkleTreeConfig<F> {
+    type Digest = Rpo256Digest;
+    type Leaf = Vec<F>;
+
+    fn hash_leaves(_: u32, l0: &Vec<F>, l1: &Vec<F>) -> Rpo256Digest {
+        let h0 = Rpo256HashFn::hash_elements(l0.iter().copied());
+        let h1 = Rpo256HashFn::hash_elements(l1.iter().copied());
+        Rpo256HashFn::merge(&h0, &h1)
+    }
+
+    fn hash_nodes(_: u32, n0: &Rpo256Digest, n1: &Rpo256Digest) -> Rpo256Digest {
+        Rpo256HashFn::merge(n0, n1)
+    }
+
+    fn security_level_bits() -> u32 {
+        Rpo256HashFn::COLLISION_RESISTANCE
+    }
+}
+
+/// Commits to the rows of a matrix. Rows are absorbed natively as field
+/// elements and hashed into leaves with RPO.
+#[cfg(feature = "merkle")]
+pub type Rpo256MatrixMerkleTree = MatrixMerkleTreeImpl<Rpo256HashFn>;
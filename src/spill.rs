@@ -0,0 +1,171 @@
+//! Spilling large allocations of [`GpuAllocator`] to disk.
+//!
+//! The base trace, its LDE and the composition trace of traces with `2^25`
+//! rows or more don't fit in memory. Once spilling is enabled every
+//! allocation of a [`GpuVec`] (the columns of a [`Matrix`]) of at least
+//! [`SpillConfig::min_size`] bytes is backed by a memory mapped file in
+//! [`SpillConfig::dir`]. The OS pages columns in and out as the prover
+//! streams over them. Mapped memory is page aligned so columns can still be
+//! shared with the GPU without copying.
+//!
+//! Files are unlinked as soon as they are mapped so nothing is left on disk
+//! once a column is dropped, even if the prover panics. Allocations fall back
+//! to memory if a file can't be mapped. Spilling is only supported on unix
+//! platforms and is a no-op elsewhere.
+//!
+//! [`GpuAllocator`]: crate::utils::GpuAllocator
+//! [`GpuVec`]: crate::utils::GpuVec
+//! [`Matrix`]: crate::Matrix
+use core::alloc::Layout;
+use core::ptr::NonNull;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Where and when allocations are spilled to disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpillConfig {
+    /// Directory the backing files are created in. Should be on a fast local
+    /// disk with enough space for the spilled columns.
+    pub dir: PathBuf,
+    /// Allocations smaller than this many bytes stay in memory
+    pub min_size: usize,
+}
+
+static CONFIG: Mutex<Option<SpillConfig>> = Mutex::new(None);
+
+/// Spills allocations made from now on as described by `config`. Existing
+/// allocations aren't moved.
+pub fn enable(config: SpillConfig) {
+    *CONFIG.lock().unwrap() = Some(config);
+}
+
+/// Stops spilling new allocations. Spilled allocations stay on disk until
+/// they're freed.
+pub fn disable() {
+    *CONFIG.lock().unwrap() = None;
+}
+
+/// Returns the current spill configuration
+pub fn config() -> Option<SpillConfig> {
+    CONFIG.lock().unwrap().clone()
+}
+
+/// Returns true if the allocation starting at `ptr` is backed by a file
+pub fn is_spilled<T>(ptr: *const T) -> bool {
+    imp::is_spilled(ptr.cast())
+}
+
+/// Allocates `layout` in a memory mapped file if spilling is enabled and the
+/// allocation is large enough. Returns `None` if memory should be used.
+pub(crate) fn allocate(layout: Layout) -> Option<NonNull<[u8]>> {
+    let config = CONFIG.lock().unwrap().clone()?;
+    if layout.size() == 0 || layout.size() < config.min_size {
+        return None;
+    }
+    imp::allocate(&config, layout)
+}
+
+/// Frees the allocation at `ptr` if it was spilled. Returns false if the
+/// allocation is in memory.
+///
+/// # Safety
+/// `ptr` must have been allocated by
+/// [`GpuAllocator`](crate::utils::GpuAllocator) and must not be used after this
+/// call if it was spilled.
+pub(crate) unsafe fn deallocate(ptr: NonNull<u8>) -> bool {
+    imp::deallocate(ptr)
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::SpillConfig;
+    use alloc::collections::BTreeMap;
+    use core::alloc::Layout;
+    use core::ptr::NonNull;
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering;
+    use std::fs::OpenOptions;
+    use std::os::unix::io::AsRawFd;
+    use std::sync::Mutex;
+
+    /// Lengths of the mappings of spilled allocations keyed by address
+    static MAPPINGS: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+    /// Counter used to give each backing file a unique name
+    static NUM_FILES: AtomicUsize = AtomicUsize::new(0);
+
+    fn page_size() -> usize {
+        // SAFETY: sysconf has no preconditions
+        usize::try_from(unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).unwrap()
+    }
+
+    pub fn is_spilled(ptr: *const u8) -> bool {
+        MAPPINGS.lock().unwrap().contains_key(&(ptr as usize))
+    }
+
+    pub fn allocate(config: &SpillConfig, layout: Layout) -> Option<NonNull<[u8]>> {
+        let page_size = page_size();
+        if layout.align() > page_size {
+            return None;
+        }
+        let len = layout.size().next_multiple_of(page_size);
+        let path = config.dir.join(format!(
+            "ministark-{}-{}.spill",
+            std::process::id(),
+            NUM_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .ok()?;
+        let mapped = file.set_len(len as u64).ok().and_then(|()| {
+            // SAFETY: the file is at least `len` bytes and stays mapped after the
+            // file is closed
+            let ptr = unsafe {
+                libc::mmap(
+                    core::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            (ptr != libc::MAP_FAILED).then_some(ptr.cast::<u8>())
+        });
+        // the mapping keeps the file's contents alive
+        let _ = std::fs::remove_file(&path);
+        let ptr = NonNull::new(mapped?)?;
+        MAPPINGS.lock().unwrap().insert(ptr.as_ptr() as usize, len);
+        Some(NonNull::slice_from_raw_parts(ptr, len))
+    }
+
+    pub unsafe fn deallocate(ptr: NonNull<u8>) -> bool {
+        let Some(len) = MAPPINGS.lock().unwrap().remove(&(ptr.as_ptr() as usize)) else {
+            return false;
+        };
+        libc::munmap(ptr.as_ptr().cast(), len);
+        true
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use super::SpillConfig;
+    use core::alloc::Layout;
+    use core::ptr::NonNull;
+
+    pub fn is_spilled(_: *const u8) -> bool {
+        false
+    }
+
+    pub fn allocate(_: &SpillConfig, _: Layout) -> Option<NonNull<[u8]>> {
+        None
+    }
+
+    pub unsafe fn deallocate(_: NonNull<u8>) -> bool {
+        false
+    }
+}
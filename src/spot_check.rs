@@ -0,0 +1,348 @@
+//! Probabilistic trace auditing for light clients.
+//!
+//! A prover commits to its execution trace (rather than the trace's low degree
+//! extension) and opens rows a client samples at random. The client checks the
+//! openings resolve to the commitment and evaluates the AIR constraints at each
+//! sampled row locally. No FRI is involved so spot checks are cheap for both
+//! parties. If `m` of the `n` rows of a trace violate a constraint then `k`
+//! sampled rows miss every invalid row with probability at most
+//! `(1 - m/n)^k`. Spot checks complement full proofs and don't replace them.
+use crate::air::all_constraints;
+use crate::air::AirConfig;
+use crate::challenges::Challenges;
+use crate::debug::check_constraint_at;
+use crate::hints::Hints;
+use crate::merkle;
+use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleTree;
+use crate::random::draw_multiple;
+use crate::random::PublicCoin;
+use crate::stark::Stark;
+use crate::utils::FieldVariant;
+use crate::Air;
+use crate::Matrix;
+use crate::ProofOptions;
+use crate::Trace;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::Valid;
+use rand::Rng;
+use snafu::ResultExt;
+use snafu::Snafu;
+
+/// Errors found by [`SpotCheckVerifier::verify`]
+#[derive(Debug, Snafu)]
+pub enum SpotCheckError {
+    #[snafu(display("opening doesn't contain the rows needed to check the sampled rows"))]
+    PositionsMismatch,
+    #[snafu(display("opened rows don't resolve to the trace commitment: {source}"))]
+    InvalidOpening { source: merkle::Error },
+    #[snafu(display("constraint `{constraint}` is not satisfied at row `{row}`"))]
+    UnsatisfiedConstraint { constraint: usize, row: usize },
+}
+
+/// Commitment to an execution trace in natural order
+pub struct TraceCommitment<S: Stark> {
+    pub trace_len: usize,
+    pub base_trace_root: S::Digest,
+    pub extension_trace_root: Option<S::Digest>,
+}
+
+impl<S: Stark> Clone for TraceCommitment<S> {
+    fn clone(&self) -> Self {
+        Self {
+            trace_len: self.trace_len,
+            base_trace_root: self.base_trace_root.clone(),
+            extension_trace_root: self.extension_trace_root.clone(),
+        }
+    }
+}
+
+/// Rows of the execution trace at `positions`. Positions are sorted and
+/// contain every row the constraints reference from the sampled rows.
+pub struct SpotCheckOpening<S: Stark> {
+    pub positions: Vec<usize>,
+    pub base_trace_rows: Vec<Vec<S::Fp>>,
+    pub extension_trace_rows: Option<Vec<Vec<S::Fq>>>,
+    pub base_trace_proof: <S::MerkleTree as MerkleTree>::Proof,
+    pub extension_trace_proof: Option<<S::MerkleTree as MerkleTree>::Proof>,
+}
+
+impl<S: Stark> CanonicalSerialize for SpotCheckOpening<S> {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        self.positions.serialize_with_mode(&mut writer, compress)?;
+        self.base_trace_rows
+            .serialize_with_mode(&mut writer, compress)?;
+        self.extension_trace_rows
+            .serialize_with_mode(&mut writer, compress)?;
+        self.base_trace_proof
+            .serialize_with_mode(&mut writer, compress)?;
+        self.extension_trace_proof
+            .serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.positions.serialized_size(compress)
+            + self.base_trace_rows.serialized_size(compress)
+            + self.extension_trace_rows.serialized_size(compress)
+            + self.base_trace_proof.serialized_size(compress)
+            + self.extension_trace_proof.serialized_size(compress)
+    }
+}
+
+impl<S: Stark> Valid for SpotCheckOpening<S> {
+    fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+        Ok(())
+    }
+}
+
+impl<S: Stark> CanonicalDeserialize for SpotCheckOpening<S> {
+    fn deserialize_with_mode<R: ark_serialize::Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        Ok(Self {
+            positions: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            base_trace_rows: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            extension_trace_rows: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            base_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            extension_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
+
+/// Commits to an execution trace and opens rows requested by a client
+pub struct SpotCheckProver<S: Stark> {
+    base_trace: Matrix<S::Fp>,
+    extension_trace: Option<Matrix<S::Fq>>,
+    base_trace_tree: S::MerkleTree,
+    extension_trace_tree: Option<S::MerkleTree>,
+    constraint_offsets: BTreeSet<isize>,
+}
+
+impl<S: Stark> SpotCheckProver<S> {
+    /// Generates the trace of `witness` and commits to it. Challenges for the
+    /// extension trace are drawn from the public coin after absorbing the base
+    /// trace commitment. Only AIRs with a single auxiliary segment are
+    /// supported.
+    pub fn new(this: &S, witness: S::Witness, options: ProofOptions) -> Self {
+        let trace = this.generate_trace(witness);
+        let air = Air::new(trace.len(), this.get_public_inputs(), options);
+        assert_eq!(
+            air.num_aux_segments(),
+            1,
+            "spot checks need a single auxiliary segment"
+        );
+        let mut public_coin = this.gen_public_coin(&air);
+        let base_trace = trace.base_columns().clone();
+        let base_trace_tree = S::MerkleTree::from_matrix(&base_trace);
+        public_coin.reseed_with_digest(&base_trace_tree.root());
+        let challenges = Challenges::new(draw_multiple(&mut public_coin, air.num_challenges()));
+        let extension_trace = trace.build_extension_columns(&challenges);
+        let extension_trace_tree = extension_trace.as_ref().map(S::MerkleTree::from_matrix);
+        Self {
+            base_trace,
+            extension_trace,
+            base_trace_tree,
+            extension_trace_tree,
+            constraint_offsets: constraint_offsets(&air),
+        }
+    }
+
+    pub fn commitment(&self) -> TraceCommitment<S> {
+        TraceCommitment {
+            trace_len: self.base_trace.num_rows(),
+            base_trace_root: self.base_trace_tree.root(),
+            extension_trace_root: self.extension_trace_tree.as_ref().map(MerkleTree::root),
+        }
+    }
+
+    /// Opens the rows needed to check the constraints at each of `rows`
+    ///
+    /// Returns an error if a row is out of bounds.
+    pub fn open(&self, rows: &[usize]) -> Result<SpotCheckOpening<S>, merkle::Error> {
+        fn get_rows<F: Field>(m: &Matrix<F>, positions: &[usize]) -> Vec<Vec<F>> {
+            positions.iter().map(|&i| m.get_row(i).unwrap()).collect()
+        }
+        let trace_len = self.base_trace.num_rows();
+        if let Some(&i) = rows.iter().find(|&&i| i >= trace_len) {
+            return Err(merkle::Error::LeafIndexOutOfBounds { i, n: trace_len });
+        }
+        let positions = opened_positions(&self.constraint_offsets, trace_len, rows);
+        let base_trace_proof =
+            MatrixMerkleTree::<S::Fp>::prove_rows(&self.base_trace_tree, &positions)?;
+        let extension_trace_proof = self
+            .extension_trace_tree
+            .as_ref()
+            .map(|tree| MatrixMerkleTree::<S::Fq>::prove_rows(tree, &positions))
+            .transpose()?;
+        Ok(SpotCheckOpening {
+            base_trace_rows: get_rows(&self.base_trace, &positions),
+            extension_trace_rows: self
+                .extension_trace
+                .as_ref()
+                .map(|m| get_rows(m, &positions)),
+            positions,
+            base_trace_proof,
+            extension_trace_proof,
+        })
+    }
+}
+
+/// Samples rows of a committed trace and checks their openings
+pub struct SpotCheckVerifier<S: Stark> {
+    air: Air<S::AirConfig>,
+    commitment: TraceCommitment<S>,
+    challenges: Challenges<S::Fq>,
+    hints: Hints<S::Fq>,
+}
+
+impl<S: Stark> SpotCheckVerifier<S> {
+    /// Derives the challenges and hints from `commitment` as
+    /// [`SpotCheckProver::new`] does
+    pub fn new(this: &S, commitment: TraceCommitment<S>, options: ProofOptions) -> Self {
+        let air = Air::new(commitment.trace_len, this.get_public_inputs(), options);
+        let mut public_coin = this.gen_public_coin(&air);
+        public_coin.reseed_with_digest(&commitment.base_trace_root);
+        let challenges = Challenges::new(draw_multiple(&mut public_coin, air.num_challenges()));
+        let hints = air.gen_hints(&challenges);
+        Self {
+            air,
+            commitment,
+            challenges,
+            hints,
+        }
+    }
+
+    /// Samples `k` distinct rows uniformly at random (or every row if the
+    /// trace has fewer than `k` rows). Rows must be sampled after the prover
+    /// has committed to the trace.
+    pub fn sample_rows(&self, rng: &mut impl Rng, k: usize) -> Vec<usize> {
+        let trace_len = self.commitment.trace_len;
+        let mut rows = BTreeSet::new();
+        while rows.len() < k.min(trace_len) {
+            rows.insert(rng.gen_range(0..trace_len));
+        }
+        rows.into_iter().collect()
+    }
+
+    /// Checks `opening` resolves to the trace commitment and the constraints
+    /// are satisfied at each of `rows`. Returns the evaluation of every
+    /// constraint at each row.
+    #[allow(clippy::type_complexity)]
+    pub fn verify(
+        &self,
+        rows: &[usize],
+        opening: SpotCheckOpening<S>,
+    ) -> Result<Vec<Vec<FieldVariant<S::Fp, S::Fq>>>, SpotCheckError> {
+        let trace_len = self.commitment.trace_len;
+        let offsets = constraint_offsets(&self.air);
+        if rows.iter().any(|&i| i >= trace_len)
+            || opening.positions != opened_positions(&offsets, trace_len, rows)
+            || opening.base_trace_rows.len() != opening.positions.len()
+        {
+            return Err(SpotCheckError::PositionsMismatch);
+        }
+
+        S::MerkleTree::verify_rows(
+            &self.commitment.base_trace_root,
+            &opening.positions,
+            &opening.base_trace_rows,
+            opening.base_trace_proof,
+        )
+        .context(InvalidOpeningSnafu)?;
+        match (
+            &self.commitment.extension_trace_root,
+            &opening.extension_trace_rows,
+            opening.extension_trace_proof,
+        ) {
+            (Some(root), Some(extension_trace_rows), Some(proof)) => {
+                if extension_trace_rows.len() != opening.positions.len() {
+                    return Err(SpotCheckError::PositionsMismatch);
+                }
+                S::MerkleTree::verify_rows(root, &opening.positions, extension_trace_rows, proof)
+                    .context(InvalidOpeningSnafu)?;
+            }
+            (None, None, None) => {}
+            _ => return Err(SpotCheckError::PositionsMismatch),
+        }
+
+        let row_index = opening
+            .positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| (position, i))
+            .collect::<BTreeMap<_, _>>();
+        let num_base_columns = S::AirConfig::NUM_BASE_COLUMNS;
+        let trace_domain = self.air.trace_domain();
+        let constraints = all_constraints::<S::AirConfig>(trace_len);
+        let mut evaluations = Vec::new();
+        for &row in rows {
+            let x = trace_domain.element(row);
+            let mut row_evaluations = Vec::new();
+            for (constraint_index, constraint) in constraints.iter().enumerate() {
+                let evaluation = check_constraint_at(
+                    constraint,
+                    trace_len,
+                    x,
+                    &self.challenges,
+                    &self.hints,
+                    |col, offset| {
+                        let i = row_index[&offset_row(row, offset, trace_len)];
+                        if col < num_base_columns {
+                            FieldVariant::Fp(opening.base_trace_rows[i][col])
+                        } else {
+                            let extension_trace_rows = opening.extension_trace_rows.as_ref();
+                            FieldVariant::Fq(
+                                extension_trace_rows.unwrap()[i][col - num_base_columns],
+                            )
+                        }
+                    },
+                );
+                row_evaluations.push(evaluation.ok_or(SpotCheckError::UnsatisfiedConstraint {
+                    constraint: constraint_index,
+                    row,
+                })?);
+            }
+            evaluations.push(row_evaluations);
+        }
+        Ok(evaluations)
+    }
+}
+
+/// Returns the distinct row offsets the AIR's constraints reference
+fn constraint_offsets<C: AirConfig>(air: &Air<C>) -> BTreeSet<isize> {
+    air.trace_arguments()
+        .into_iter()
+        .map(|(_, offset)| offset)
+        .collect()
+}
+
+/// Returns the sorted rows that must be opened to check constraints at `rows`
+fn opened_positions(offsets: &BTreeSet<isize>, trace_len: usize, rows: &[usize]) -> Vec<usize> {
+    let mut positions = BTreeSet::new();
+    for &row in rows {
+        positions.extend(
+            offsets
+                .iter()
+                .map(|&offset| offset_row(row, offset, trace_len)),
+        );
+    }
+    positions.into_iter().collect()
+}
+
+#[allow(clippy::cast_possible_wrap)]
+const fn offset_row(row: usize, offset: isize, n: usize) -> usize {
+    (row as isize + offset).rem_euclid(n as isize) as usize
+}
@@ -1,17 +1,28 @@
 use crate::air::AirConfig;
+#[cfg(feature = "prover")]
 use crate::challenges::Challenges;
 use crate::channel::VerifierChannelArtifacts;
 use crate::composer::DeepCompositionCoeffs;
+use crate::compression::decode_proof;
+use crate::compression::Compressor;
+#[cfg(feature = "prover")]
 use crate::debug::default_validate_constraints;
 use crate::hash::Digest;
+#[cfg(feature = "prover")]
 use crate::hints::Hints;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
-use crate::prover::default_prove;
+use crate::preprocessed::PreprocessedColumns;
+#[cfg(feature = "prover")]
+use crate::prover::default_prove_async;
+#[cfg(feature = "prover")]
 use crate::prover::ProvingError;
 use crate::random::draw_multiple;
 use crate::random::PublicCoin;
+#[cfg(feature = "prover")]
+use crate::utils::yield_now;
 use crate::verifier::default_verify;
+use crate::verifier::default_verify_with_preprocessed_columns;
 use crate::verifier::VerificationError;
 use crate::Air;
 use crate::Matrix;
@@ -19,6 +30,7 @@ use crate::Proof;
 use crate::ProofOptions;
 use crate::StarkExtensionOf;
 use crate::Trace;
+use alloc::vec::Vec;
 use ark_ff::FftField;
 use ministark_gpu::GpuFftField;
 
@@ -34,10 +46,46 @@ pub trait Stark: Sized + Send + Sync {
     type Digest: Digest;
     type Witness;
 
+    /// Never materializes the trace LDEs. Each LDE is evaluated and hashed
+    /// one coset of the trace domain at a time while only the trace
+    /// polynomials and merkle trees are kept. Constraints are evaluated from
+    /// the polynomials and so are queried rows. This lowers peak memory usage
+    /// at the cost of extra FFTs and work when opening queries. Proofs are
+    /// unchanged.
+    const LOW_MEMORY: bool = false;
+
+    /// Computes LDEs and folds FRI layers directly in bit-reversed order.
+    /// Commitments are always made to bit-reversed evaluations so query
+    /// positions index Merkle rows as is. By default evaluations are computed
+    /// in natural order and permuted which costs an extra pass over memory
+    /// for every LDE and FRI layer. Here FFTs output bit-reversed evaluations
+    /// and FRI layers are folded in place. Constraints are still evaluated in
+    /// natural order. Proofs are unchanged.
+    const BIT_REVERSED_PIPELINE: bool = false;
+
     fn get_public_inputs(&self) -> <Self::AirConfig as AirConfig>::PublicInputs;
 
     fn gen_public_coin(&self, air: &Air<Self::AirConfig>) -> Self::PublicCoin;
 
+    /// Draws the verifier's random coefficients for the composition
+    /// constraint. The composition constraint is a random linear combination
+    /// of all AIR constraints. Constraint `i` (in the order returned by
+    /// [`AirConfig::constraints`]) is combined using coefficients `2i` and
+    /// `2i + 1`:
+    ///
+    /// `(alpha_i * x^(degree_adjustment_i) + beta_i) * constraint_i(x)`
+    ///
+    /// These must be drawn after committing to the base trace and extension
+    /// trace (if any) and before committing to the composition trace.
+    fn gen_composition_constraint_coeffs(
+        &self,
+        public_coin: &mut Self::PublicCoin,
+        air: &Air<Self::AirConfig>,
+    ) -> Vec<Self::Fq> {
+        let num_composition_coeffs = air.num_composition_constraint_coeffs();
+        draw_multiple(public_coin, num_composition_coeffs)
+    }
+
     fn gen_deep_coeffs(
         &self,
         public_coin: &mut Self::PublicCoin,
@@ -54,24 +102,39 @@ pub trait Stark: Sized + Send + Sync {
 
     fn generate_trace(&self, witness: Self::Witness) -> Self::Trace;
 
+    /// Preprocessed columns used by the prover. Required if the AIR has
+    /// [`AirConfig::NUM_PREPROCESSED_COLUMNS`].
+    fn preprocessed_columns(&self) -> Option<&PreprocessedColumns<Self>> {
+        None
+    }
+
+    /// Commitment to the preprocessed columns used by the verifier. Verifiers
+    /// that don't have the columns should return a commitment obtained out of
+    /// band.
+    fn preprocessed_commitment(&self) -> Option<Self::Digest> {
+        self.preprocessed_columns()
+            .map(PreprocessedColumns::commitment)
+    }
+
+    #[cfg(feature = "prover")]
     async fn prove(
         &self,
         options: ProofOptions,
         witness: Self::Witness,
     ) -> Result<Proof<Self>, ProvingError> {
-        default_prove(self, options, witness)
+        default_prove_async(self, options, witness, |_| yield_now()).await
     }
 
     /// Check the AIR constraints are valid
+    #[cfg(feature = "prover")]
     fn validate_constraints(
         &self,
         challenges: &Challenges<Self::Fq>,
         hints: &Hints<Self::Fq>,
         base_trace: &Matrix<Self::Fp>,
         extension_trace: Option<&Matrix<Self::Fq>>,
-    ) {
-        // TODO: this is unfinished
-        default_validate_constraints(self, challenges, hints, base_trace, extension_trace);
+    ) -> Result<(), ProvingError> {
+        default_validate_constraints(self, challenges, hints, base_trace, extension_trace)
     }
 
     #[allow(clippy::too_many_lines)]
@@ -82,4 +145,35 @@ pub trait Stark: Sized + Send + Sync {
     ) -> Result<VerifierChannelArtifacts<Self::Fq>, VerificationError> {
         default_verify(self, proof, required_security_bits)
     }
+
+    /// Verifies a proof with the commitment to the preprocessed columns
+    /// re-derived from `preprocessed_columns` rather than trusting
+    /// [`Self::preprocessed_commitment`]. If a commitment is available it must
+    /// match the derived commitment.
+    fn verify_with_preprocessed_columns(
+        &self,
+        proof: Proof<Self>,
+        required_security_bits: u32,
+        preprocessed_columns: &Matrix<Self::Fp>,
+    ) -> Result<VerifierChannelArtifacts<Self::Fq>, VerificationError> {
+        default_verify_with_preprocessed_columns(
+            self,
+            proof,
+            required_security_bits,
+            preprocessed_columns,
+        )
+    }
+
+    /// Verifies a proof encoded with
+    /// [`encode_proof`](crate::compression::encode_proof). Proofs compressed
+    /// with a codec other than the ones in `decompressors` are rejected.
+    fn verify_bytes(
+        &self,
+        bytes: &[u8],
+        required_security_bits: u32,
+        decompressors: &[&dyn Compressor],
+    ) -> Result<VerifierChannelArtifacts<Self::Fq>, VerificationError> {
+        let proof = decode_proof(bytes, decompressors)?;
+        self.verify(proof, required_security_bits)
+    }
 }
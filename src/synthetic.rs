@@ -0,0 +1,169 @@
+//! Synthetic AIR for benchmarking and capacity testing provers.
+//!
+//! [`SyntheticAirConfig`] has `NUM_COLUMNS` columns and `NUM_CONSTRAINTS`
+//! transition constraints of degree `DEGREE`. Column `j` is a geometric
+//! sequence with ratio `j + 2` that starts at `seed + j + 1`. Constraint `k`
+//! checks the ratio of column `k % NUM_COLUMNS` multiplied by a power of
+//! another column to reach the configured degree:
+//!
+//! ```text
+//! (next_j - (j + 2) * curr_j) * curr_m^(DEGREE - 1)
+//! ```
+//!
+//! Any row of the trace can be computed from its index so traces of `2^26`
+//! rows and more are generated in parallel (see
+//! [`TraceBuilder::fill_parallel`]). Traces only depend on the seed and the
+//! number of rows.
+use crate::air::AirConfig;
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::hash::HashFn;
+use crate::hash::Sha256HashFn;
+use crate::hints::Hints;
+use crate::merkle::MatrixMerkleTreeImpl;
+use crate::random::PublicCoin;
+use crate::random::PublicCoinImpl;
+use crate::stark::Stark;
+use crate::trace::TraceBuilder;
+use crate::utils::FieldVariant;
+use crate::Air;
+use crate::Matrix;
+use crate::Trace;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalSerialize;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+
+/// Rows generated sequentially by each parallel task
+const CHUNK_SIZE: usize = 1 << 14;
+
+/// Ratio of the geometric sequence in column `j`
+fn ratio(j: usize) -> Fp {
+    Fp::from(j as u64 + 2)
+}
+
+/// Value of column `j` in the first row
+fn initial_value(seed: u64, j: usize) -> Fp {
+    Fp::from(seed) + Fp::from(j as u64 + 1)
+}
+
+pub struct SyntheticAirConfig<
+    const NUM_COLUMNS: usize,
+    const NUM_CONSTRAINTS: usize,
+    const DEGREE: usize,
+>;
+
+impl<const NUM_COLUMNS: usize, const NUM_CONSTRAINTS: usize, const DEGREE: usize> AirConfig
+    for SyntheticAirConfig<NUM_COLUMNS, NUM_CONSTRAINTS, DEGREE>
+{
+    const NUM_BASE_COLUMNS: usize = NUM_COLUMNS;
+    type Fp = Fp;
+    type Fq = Fp;
+    /// Seed of the trace
+    type PublicInputs = u64;
+
+    fn gen_hints(_: usize, seed: &u64, _: &Challenges<Fp>) -> Hints<Fp> {
+        Hints::new(
+            (0..NUM_COLUMNS)
+                .map(|j| (j, initial_value(*seed, j)))
+                .collect(),
+        )
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        use AlgebraicItem::*;
+        assert!(NUM_COLUMNS != 0, "synthetic AIR needs at least one column");
+        assert!(DEGREE != 0, "constraints must have a positive degree");
+        let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let one = Constant(FieldVariant::Fp(Fp::ONE));
+        let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+        let mut constraints = Vec::new();
+
+        // first row
+        for j in 0..NUM_COLUMNS {
+            constraints.push((j.curr() - Hint(j)) / (X - one));
+        }
+
+        // transitions
+        for k in 0..NUM_CONSTRAINTS {
+            let j = k % NUM_COLUMNS;
+            let m = (j + k / NUM_COLUMNS + 1) % NUM_COLUMNS;
+            let mut step = j.next() - j.curr() * Constant(FieldVariant::Fp(ratio(j)));
+            if DEGREE > 1 {
+                step *= m.curr().pow(DEGREE - 1);
+            }
+            constraints.push(step * ((X - last_x) / (X.pow(trace_len) - one)));
+        }
+
+        constraints.into_iter().map(Constraint::new).collect()
+    }
+}
+
+pub struct SyntheticTrace(Matrix<Fp>);
+
+impl Trace for SyntheticTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> &Matrix<Fp> {
+        &self.0
+    }
+}
+
+/// Proves a synthetic trace generated from `seed`. The witness is the number
+/// of rows in the trace which must be a power of two.
+pub struct SyntheticStark<
+    const NUM_COLUMNS: usize,
+    const NUM_CONSTRAINTS: usize,
+    const DEGREE: usize,
+> {
+    pub seed: u64,
+}
+
+impl<const NUM_COLUMNS: usize, const NUM_CONSTRAINTS: usize, const DEGREE: usize> Stark
+    for SyntheticStark<NUM_COLUMNS, NUM_CONSTRAINTS, DEGREE>
+{
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = SyntheticAirConfig<NUM_COLUMNS, NUM_CONSTRAINTS, DEGREE>;
+    type Digest = <Sha256HashFn as HashFn>::Digest;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = usize;
+    type Trace = SyntheticTrace;
+
+    fn get_public_inputs(&self) -> u64 {
+        self.seed
+    }
+
+    fn gen_public_coin(&self, air: &Air<Self::AirConfig>) -> Self::PublicCoin {
+        let mut seed = Vec::new();
+        air.public_inputs().serialize_compressed(&mut seed).unwrap();
+        air.trace_len().serialize_compressed(&mut seed).unwrap();
+        air.options().serialize_compressed(&mut seed).unwrap();
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
+    }
+
+    fn generate_trace(&self, num_rows: usize) -> SyntheticTrace {
+        SyntheticTrace(synthetic_trace(self.seed, NUM_COLUMNS, num_rows))
+    }
+}
+
+/// Generates the columns of a synthetic trace. Rows are generated in parallel
+/// chunks with the `parallel` feature.
+pub fn synthetic_trace(seed: u64, num_columns: usize, num_rows: usize) -> Matrix<Fp> {
+    let ratios = (0..num_columns).map(ratio).collect::<Vec<Fp>>();
+    TraceBuilder::fill_parallel(num_columns, num_rows, CHUNK_SIZE, |i, prev_row, row| {
+        for (j, (value, ratio)) in row.iter_mut().zip(&ratios).enumerate() {
+            *value = match prev_row {
+                Some(prev_row) => prev_row[j] * ratio,
+                None => initial_value(seed, j) * Field::pow(ratio, [i as u64]),
+            };
+        }
+    })
+}
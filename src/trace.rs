@@ -1,14 +1,33 @@
+use crate::air::AirConfig;
 use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ConstraintGroup;
+use crate::constraints::ExecutionTraceColumn;
+use crate::merkle;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
+use crate::preprocessed::PreprocessedColumns;
+use crate::proof::has_aux_segments;
 use crate::stark::Stark;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::verifier::CommittedTrace;
+use crate::verifier::VerificationError;
+use crate::Air;
 use crate::Matrix;
+use crate::StarkExtensionOf;
 use alloc::vec::Vec;
+use ark_ff::BigInteger;
 use ark_ff::FftField;
 use ark_ff::Field;
+use ark_ff::PrimeField;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
 use ark_serialize::Valid;
+use ministark_gpu::GpuFftField;
+use snafu::Snafu;
 
 /// STARK execution trace
 #[allow(clippy::len_without_is_empty)]
@@ -33,6 +52,339 @@ pub trait Trace: Send + Sync {
     ) -> Option<Matrix<Self::Fq>> {
         None
     }
+
+    /// Builds and returns the columns of auxiliary segment `segment` (see
+    /// [`AirConfig::AUX_SEGMENTS`]). `challenges` holds the challenges of this
+    /// and all previous segments. `aux_segments` holds the columns of the
+    /// previous segments. The first segment is built with
+    /// [`Self::build_extension_columns`] by default.
+    fn build_aux_segment(
+        &self,
+        segment: usize,
+        _aux_segments: &[Matrix<Self::Fq>],
+        challenges: &Challenges<Self::Fq>,
+    ) -> Option<Matrix<Self::Fq>> {
+        if segment == 0 {
+            self.build_extension_columns(challenges)
+        } else {
+            None
+        }
+    }
+}
+
+/// Type of the values stored in a base trace column. Values are embedded into
+/// the field when the trace is built.
+///
+/// Columns of type [`CellType::U8`], [`CellType::U16`] and [`CellType::U64`]
+/// are range checked with helper columns that hold the bit decomposition of
+/// each value. Helper
+/// columns are appended after the declared columns in declaration order (see
+/// [`num_columns`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CellType {
+    /// Any field element. No range constraint.
+    Felt,
+    /// Constrained to `0` or `1`.
+    Bool,
+    /// Constrained to `0..2^8` using 8 helper columns.
+    U8,
+    /// Constrained to `0..2^16` using 16 helper columns.
+    U16,
+    /// Constrained to `0..2^64` using 64 helper columns. Values must also be
+    /// smaller than the field modulus. In fields smaller than `2^64` (e.g.
+    /// Goldilocks) every field element is in range.
+    U64,
+}
+
+impl CellType {
+    /// Returns the number of bits values of this type are constrained to.
+    /// Returns None if values are unconstrained.
+    pub const fn num_bits(self) -> Option<u32> {
+        match self {
+            Self::Bool => Some(1),
+            Self::U8 => Some(8),
+            Self::U16 => Some(16),
+            Self::U64 => Some(64),
+            Self::Felt => None,
+        }
+    }
+
+    /// Returns the number of helper columns needed to range check this type
+    pub const fn num_helper_columns(self) -> usize {
+        match self {
+            Self::U8 => 8,
+            Self::U16 => 16,
+            Self::U64 => 64,
+            Self::Felt | Self::Bool => 0,
+        }
+    }
+}
+
+/// Returns the total number of base columns (declared columns and helper
+/// columns) needed for a trace with the given column types.
+pub const fn num_columns(column_types: &[CellType]) -> usize {
+    let mut num_columns = column_types.len();
+    let mut i = 0;
+    while i < column_types.len() {
+        num_columns += column_types[i].num_helper_columns();
+        i += 1;
+    }
+    num_columns
+}
+
+/// Generates range constraints for the given column types. Constraints apply
+/// to all rows of the trace.
+pub fn range_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    column_types: &[CellType],
+    trace_len: usize,
+) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+    use AlgebraicItem::*;
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let is_binary = |col: usize| col.curr() * (col.curr() - one);
+
+    let mut constraints = Vec::new();
+    let mut helper_col = column_types.len();
+    for (col, column_type) in column_types.iter().enumerate() {
+        match column_type {
+            CellType::Felt => {}
+            CellType::Bool => constraints.push(is_binary(col)),
+            CellType::U8 | CellType::U16 | CellType::U64 => {
+                let bit_cols = helper_col..helper_col + column_type.num_helper_columns();
+                helper_col = bit_cols.end;
+                let recomposition = bit_cols
+                    .clone()
+                    .enumerate()
+                    .map(|(i, bit_col)| {
+                        // wraps around the modulus in fields smaller than 2^64
+                        let weight = Constant(FieldVariant::Fp(Fp::from(1u64 << i)));
+                        bit_col.curr() * weight
+                    })
+                    .sum::<crate::expression::Expr<_>>();
+                constraints.push(col.curr() - recomposition);
+                constraints.extend(bit_cols.map(is_binary));
+            }
+        }
+    }
+
+    // ensure constraints hold in all rows
+    ConstraintGroup::all_rows(trace_len, constraints).into_constraints()
+}
+
+/// A single cell of an execution trace
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell<F> {
+    Felt(F),
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U64(u64),
+}
+
+impl<F: PrimeField> Cell<F> {
+    /// Returns the cell as an integer if it fits in 64 bits
+    fn to_u64(self) -> Option<u64> {
+        match self {
+            Self::Felt(v) => {
+                let v = v.into_bigint();
+                let (lo, hi) = v.as_ref().split_first()?;
+                hi.iter().all(|limb| *limb == 0).then_some(*lo)
+            }
+            Self::Bool(v) => Some(v.into()),
+            Self::U8(v) => Some(v.into()),
+            Self::U16(v) => Some(v.into()),
+            Self::U64(v) => Some(v),
+        }
+    }
+
+    /// Embeds the cell into the field. Returns None if the value is out of
+    /// range for the column type.
+    fn embed(self, column_type: CellType) -> Option<F> {
+        if let (Self::Felt(v), CellType::Felt) = (self, column_type) {
+            return Some(v);
+        }
+        let v = self.to_u64()?;
+        match column_type.num_bits() {
+            Some(num_bits) if v.checked_shr(num_bits).unwrap_or(0) != 0 => None,
+            _ => F::from_bigint(v.into()),
+        }
+    }
+}
+
+impl<F> From<bool> for Cell<F> {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl<F> From<u8> for Cell<F> {
+    fn from(value: u8) -> Self {
+        Self::U8(value)
+    }
+}
+
+impl<F> From<u16> for Cell<F> {
+    fn from(value: u16) -> Self {
+        Self::U16(value)
+    }
+}
+
+impl<F> From<u64> for Cell<F> {
+    fn from(value: u64) -> Self {
+        Self::U64(value)
+    }
+}
+
+/// Builds base trace columns from cells of different types. Cells are
+/// embedded into the field and range check helper columns are generated when
+/// the trace is built.
+pub struct TraceBuilder<F> {
+    column_types: Vec<CellType>,
+    columns: Vec<Vec<Cell<F>>>,
+}
+
+impl<F: PrimeField> TraceBuilder<F> {
+    pub fn new(column_types: &[CellType]) -> Self {
+        Self {
+            column_types: column_types.to_vec(),
+            columns: vec![Vec::new(); column_types.len()],
+        }
+    }
+
+    /// Appends a value to the end of a column
+    pub fn push(&mut self, col: usize, value: impl Into<Cell<F>>) {
+        self.columns[col].push(value.into());
+    }
+
+    /// Appends a value to the end of each column
+    pub fn push_row<T: Into<Cell<F>>>(&mut self, row: impl IntoIterator<Item = T>) {
+        let mut num_values = 0;
+        for (column, value) in self.columns.iter_mut().zip(row) {
+            column.push(value.into());
+            num_values += 1;
+        }
+        assert_eq!(num_values, self.columns.len(), "row has the wrong length");
+    }
+
+    /// Embeds all cells into the field and appends range check helper columns.
+    ///
+    /// # Errors
+    /// Returns an error if columns have different lengths or if a value is out
+    /// of range for its column's type.
+    pub fn build(self) -> Result<Matrix<F>, TraceBuilderError> {
+        let num_rows = self.columns.first().map_or(0, Vec::len);
+        let mut base_columns = Vec::new();
+        let mut helper_columns = Vec::new();
+        for (col, (column_type, cells)) in
+            self.column_types.into_iter().zip(self.columns).enumerate()
+        {
+            if cells.len() != num_rows {
+                return Err(TraceBuilderError::ColumnLengthMismatch {
+                    column: col,
+                    len: cells.len(),
+                    expected: num_rows,
+                });
+            }
+            let mut column: GpuVec<F> = Vec::with_capacity_in(cells.len(), GpuAllocator);
+            let mut bit_columns = (0..column_type.num_helper_columns())
+                .map(|_| Vec::with_capacity_in(cells.len(), GpuAllocator))
+                .collect::<Vec<GpuVec<F>>>();
+            for (row, cell) in cells.into_iter().enumerate() {
+                let value = cell
+                    .embed(column_type)
+                    .ok_or(TraceBuilderError::ValueOutOfRange {
+                        column: col,
+                        row,
+                        cell_type: column_type,
+                    })?;
+                column.push(value);
+                if !bit_columns.is_empty() {
+                    let v = value.into_bigint();
+                    for (i, bit_column) in bit_columns.iter_mut().enumerate() {
+                        bit_column.push(F::from(v.get_bit(i)));
+                    }
+                }
+            }
+            base_columns.push(column);
+            helper_columns.extend(bit_columns);
+        }
+        base_columns.extend(helper_columns);
+        Ok(Matrix::new(base_columns))
+    }
+
+    /// Generates `num_rows` rows of field elements in chunks of `chunk_size`
+    /// rows. Chunks are generated in parallel with the `parallel` feature and
+    /// rows within a chunk are generated in order. Values are written straight
+    /// into the page aligned columns of the returned matrix.
+    ///
+    /// `gen_row(i, prev_row, row)` fills row `i`. `prev_row` is row `i - 1`
+    /// except in the first row of each chunk where it's `None` and the row has
+    /// to be computed from `i` alone. Transitions that can only be computed
+    /// sequentially should use a `chunk_size` of `num_rows`.
+    ///
+    /// # Panics
+    /// Panics if `chunk_size` is zero
+    pub fn fill_parallel(
+        num_columns: usize,
+        num_rows: usize,
+        chunk_size: usize,
+        gen_row: impl Fn(usize, Option<&[F]>, &mut [F]) + Send + Sync,
+    ) -> Matrix<F> {
+        assert!(chunk_size != 0, "chunk size must be positive");
+        let mut columns = (0..num_columns)
+            .map(|_| {
+                let mut column = Vec::with_capacity_in(num_rows, GpuAllocator);
+                column.resize(num_rows, F::zero());
+                column
+            })
+            .collect::<Vec<GpuVec<F>>>();
+
+        // the rows of each chunk across all columns
+        let num_chunks = num_rows.div_ceil(chunk_size);
+        let mut chunks = (0..num_chunks)
+            .map(|_| Vec::with_capacity(num_columns))
+            .collect::<Vec<Vec<&mut [F]>>>();
+        for column in &mut columns {
+            for (chunk, rows) in chunks.iter_mut().zip(column.chunks_mut(chunk_size)) {
+                chunk.push(rows);
+            }
+        }
+
+        ark_std::cfg_into_iter!(chunks)
+            .enumerate()
+            .for_each(|(chunk_index, mut chunk)| {
+                let offset = chunk_index * chunk_size;
+                let mut prev_row = vec![F::zero(); num_columns];
+                let mut row = vec![F::zero(); num_columns];
+                for (j, i) in (offset..num_rows.min(offset + chunk_size)).enumerate() {
+                    gen_row(i, (j != 0).then_some(prev_row.as_slice()), &mut row);
+                    for (rows, value) in chunk.iter_mut().zip(&row) {
+                        rows[j] = *value;
+                    }
+                    prev_row.copy_from_slice(&row);
+                }
+            });
+
+        Matrix::new(columns)
+    }
+}
+
+/// Errors returned when building a trace with a [`TraceBuilder`]
+#[derive(Debug, Snafu)]
+pub enum TraceBuilderError {
+    #[snafu(display(
+        "value at row `{row}` of column `{column}` is out of range for `{cell_type:?}`"
+    ))]
+    ValueOutOfRange {
+        column: usize,
+        row: usize,
+        cell_type: CellType,
+    },
+    #[snafu(display("column `{column}` has `{len}` rows but `{expected}` are expected"))]
+    ColumnLengthMismatch {
+        column: usize,
+        len: usize,
+        expected: usize,
+    },
 }
 
 pub struct Queries<C: Stark> {
@@ -42,6 +394,13 @@ pub struct Queries<C: Stark> {
     pub base_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
     pub extension_trace_proof: Option<<C::MerkleTree as MerkleTree>::Proof>,
     pub composition_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
+    /// Empty if the AIR has no preprocessed columns
+    pub preprocessed_trace_values: Vec<C::Fp>,
+    pub preprocessed_trace_proof: Option<<C::MerkleTree as MerkleTree>::Proof>,
+    /// Proofs of the auxiliary segments after the first. Their rows are part
+    /// of `extension_trace_values`. Empty unless the AIR has multiple
+    /// auxiliary segments.
+    pub aux_trace_proofs: Vec<<C::MerkleTree as MerkleTree>::Proof>,
 }
 
 impl<C: Stark> CanonicalSerialize for Queries<C> {
@@ -62,16 +421,41 @@ impl<C: Stark> CanonicalSerialize for Queries<C> {
             .serialize_with_mode(&mut writer, compress)?;
         self.composition_trace_proof
             .serialize_with_mode(&mut writer, compress)?;
+        // omitted for AIRs without preprocessed columns
+        if C::AirConfig::NUM_PREPROCESSED_COLUMNS != 0 {
+            self.preprocessed_trace_values
+                .serialize_with_mode(&mut writer, compress)?;
+            self.preprocessed_trace_proof
+                .serialize_with_mode(&mut writer, compress)?;
+        }
+        // omitted for AIRs with a single auxiliary segment
+        if has_aux_segments::<C>() {
+            self.aux_trace_proofs
+                .serialize_with_mode(&mut writer, compress)?;
+        }
         Ok(())
     }
 
     fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        let preprocessed_size = if C::AirConfig::NUM_PREPROCESSED_COLUMNS == 0 {
+            0
+        } else {
+            self.preprocessed_trace_values.serialized_size(compress)
+                + self.preprocessed_trace_proof.serialized_size(compress)
+        };
+        let aux_size = if has_aux_segments::<C>() {
+            self.aux_trace_proofs.serialized_size(compress)
+        } else {
+            0
+        };
         self.base_trace_values.serialized_size(compress)
             + self.extension_trace_values.serialized_size(compress)
             + self.composition_trace_values.serialized_size(compress)
             + self.base_trace_proof.serialized_size(compress)
             + self.extension_trace_proof.serialized_size(compress)
             + self.composition_trace_proof.serialized_size(compress)
+            + preprocessed_size
+            + aux_size
     }
 }
 
@@ -87,14 +471,27 @@ impl<C: Stark> CanonicalDeserialize for Queries<C> {
         compress: ark_serialize::Compress,
         validate: ark_serialize::Validate,
     ) -> Result<Self, ark_serialize::SerializationError> {
-        Ok(Self {
+        let mut queries = Self {
             base_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             extension_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             composition_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             base_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             extension_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             composition_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-        })
+            preprocessed_trace_values: Vec::new(),
+            preprocessed_trace_proof: None,
+            aux_trace_proofs: Vec::new(),
+        };
+        if C::AirConfig::NUM_PREPROCESSED_COLUMNS != 0 {
+            queries.preprocessed_trace_values =
+                <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+            queries.preprocessed_trace_proof =
+                <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        }
+        if has_aux_segments::<C>() {
+            queries.aux_trace_proofs = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        }
+        Ok(queries)
     }
 }
 
@@ -107,6 +504,9 @@ impl<C: Stark> Clone for Queries<C> {
             base_trace_proof: self.base_trace_proof.clone(),
             extension_trace_proof: self.extension_trace_proof.clone(),
             composition_trace_proof: self.composition_trace_proof.clone(),
+            preprocessed_trace_values: self.preprocessed_trace_values.clone(),
+            preprocessed_trace_proof: self.preprocessed_trace_proof.clone(),
+            aux_trace_proofs: self.aux_trace_proofs.clone(),
         }
     }
 }
@@ -120,39 +520,368 @@ impl<C: Stark> Queries<C> {
         extension_tree: Option<&C::MerkleTree>,
         composition_tree: &C::MerkleTree,
         positions: &[usize],
-    ) -> Self {
-        let base_trace_proof = MatrixMerkleTree::<C::Fp>::prove_rows(base_tree, positions).unwrap();
-        let extension_trace_proof = extension_tree.map(|extension_tree| {
-            MatrixMerkleTree::<C::Fq>::prove_rows(extension_tree, positions).unwrap()
-        });
+    ) -> Result<Self, merkle::Error> {
+        fn rows<F: Field>(lde: &Matrix<F>, positions: &[usize]) -> Vec<Vec<F>> {
+            positions
+                .iter()
+                .map(|&position| lde.get_row(position).unwrap())
+                .collect()
+        }
+        Self::from_rows(
+            rows(base_trace_lde, positions),
+            extension_trace_lde.map(|lde| rows(lde, positions)),
+            rows(composition_trace_lde, positions),
+            base_tree,
+            extension_tree,
+            composition_tree,
+            positions,
+        )
+    }
+
+    /// Creates queries from the rows of the trace LDEs at each position. Rows
+    /// are in the same order as `positions`.
+    ///
+    /// Returns an error if a position is out of bounds for the trees.
+    pub fn from_rows(
+        base_trace_rows: Vec<Vec<C::Fp>>,
+        extension_trace_rows: Option<Vec<Vec<C::Fq>>>,
+        composition_trace_rows: Vec<Vec<C::Fq>>,
+        base_tree: &C::MerkleTree,
+        extension_tree: Option<&C::MerkleTree>,
+        composition_tree: &C::MerkleTree,
+        positions: &[usize],
+    ) -> Result<Self, merkle::Error> {
+        let base_trace_proof = MatrixMerkleTree::<C::Fp>::prove_rows(base_tree, positions)?;
+        let extension_trace_proof = extension_tree
+            .map(|extension_tree| MatrixMerkleTree::<C::Fq>::prove_rows(extension_tree, positions))
+            .transpose()?;
         let composition_trace_proof =
-            MatrixMerkleTree::<C::Fq>::prove_rows(composition_tree, positions).unwrap();
-
-        let mut base_trace_values = Vec::new();
-        let mut extension_trace_values = Vec::new();
-        let mut composition_trace_values = Vec::new();
-        for &position in positions {
-            // execution trace
-            let base_trace_row = base_trace_lde.get_row(position).unwrap();
-            base_trace_values.extend(base_trace_row);
-
-            if let Some(extension_trace_lde) = extension_trace_lde {
-                // TODO: suport ark DomainCoeff on evaluate_at
-                let extension_trace_row = extension_trace_lde.get_row(position).unwrap();
-                extension_trace_values.extend(extension_trace_row);
-            }
+            MatrixMerkleTree::<C::Fq>::prove_rows(composition_tree, positions)?;
+
+        Ok(Self {
+            base_trace_values: base_trace_rows.into_iter().flatten().collect(),
+            extension_trace_values: extension_trace_rows
+                .into_iter()
+                .flatten()
+                .flatten()
+                .collect(),
+            composition_trace_values: composition_trace_rows.into_iter().flatten().collect(),
+            base_trace_proof,
+            extension_trace_proof,
+            composition_trace_proof,
+            preprocessed_trace_values: Vec::new(),
+            preprocessed_trace_proof: None,
+            aux_trace_proofs: Vec::new(),
+        })
+    }
 
-            // composition trace
-            let composition_trace_row = composition_trace_lde.get_row(position).unwrap();
-            composition_trace_values.extend(composition_trace_row);
+    /// Adds the rows of the preprocessed columns at each position
+    ///
+    /// Returns an error if a position is out of bounds for the tree.
+    pub fn with_preprocessed(
+        mut self,
+        preprocessed: &PreprocessedColumns<C>,
+        positions: &[usize],
+    ) -> Result<Self, merkle::Error> {
+        let tree = preprocessed.tree();
+        self.preprocessed_trace_proof =
+            Some(MatrixMerkleTree::<C::Fp>::prove_rows(tree, positions)?);
+        self.preprocessed_trace_values = positions
+            .iter()
+            .flat_map(|&position| preprocessed.lde().get_row(position).unwrap())
+            .collect();
+        Ok(self)
+    }
+
+    /// Adds proofs for the auxiliary segments after the first. `trees` are
+    /// the trees of those segments. Rows are taken from the extension trace
+    /// rows the queries were created with.
+    ///
+    /// Returns an error if a position is out of bounds for the trees.
+    pub fn with_aux_segments(
+        mut self,
+        trees: &[C::MerkleTree],
+        positions: &[usize],
+    ) -> Result<Self, merkle::Error> {
+        self.aux_trace_proofs = trees
+            .iter()
+            .map(|tree| MatrixMerkleTree::<C::Fq>::prove_rows(tree, positions))
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+
+    /// Returns the queried rows of the base trace. Excludes the preprocessed
+    /// columns (see [`Self::preprocessed_trace_rows`]).
+    pub fn base_trace_rows(&self) -> Vec<&[C::Fp]> {
+        let num_columns = C::AirConfig::NUM_BASE_COLUMNS - C::AirConfig::NUM_PREPROCESSED_COLUMNS;
+        self.base_trace_values.chunks(num_columns).collect()
+    }
+
+    /// Returns the queried rows of the preprocessed columns. Empty if the AIR
+    /// has no preprocessed columns.
+    pub fn preprocessed_trace_rows(&self) -> Vec<&[C::Fp]> {
+        if C::AirConfig::NUM_PREPROCESSED_COLUMNS == 0 {
+            Vec::new()
+        } else {
+            self.preprocessed_trace_values
+                .chunks(C::AirConfig::NUM_PREPROCESSED_COLUMNS)
+                .collect()
+        }
+    }
+
+    /// Returns the queried rows of all base columns i.e. the rows of the base
+    /// trace followed by the rows of the preprocessed columns
+    pub fn all_base_rows(&self) -> Vec<Vec<C::Fp>> {
+        let preprocessed_rows = self.preprocessed_trace_rows();
+        self.base_trace_rows()
+            .into_iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let preprocessed_row = preprocessed_rows.get(i).copied().unwrap_or_default();
+                [row, preprocessed_row].concat()
+            })
+            .collect()
+    }
+
+    /// Returns the queried rows of the extension trace. Empty if the AIR has
+    /// no extension columns.
+    pub fn extension_trace_rows(&self) -> Vec<&[C::Fq]> {
+        if C::AirConfig::NUM_EXTENSION_COLUMNS == 0 {
+            Vec::new()
+        } else {
+            self.extension_trace_values
+                .chunks(C::AirConfig::NUM_EXTENSION_COLUMNS)
+                .collect()
+        }
+    }
+
+    /// Returns the queried rows of the extension columns in auxiliary segment
+    /// `segment`
+    pub fn aux_segment_rows(&self, air: &Air<C::AirConfig>, segment: usize) -> Vec<&[C::Fq]> {
+        let aux_segments = air.aux_segments();
+        let start = aux_segments[..segment]
+            .iter()
+            .map(|s| s.num_columns)
+            .sum::<usize>();
+        let end = start + aux_segments[segment].num_columns;
+        self.extension_trace_rows()
+            .into_iter()
+            .map(|row| &row[start..end])
+            .collect()
+    }
+
+    /// Returns the queried rows of the composition trace
+    pub fn composition_trace_rows(&self, air: &Air<C::AirConfig>) -> Vec<&[C::Fq]> {
+        self.composition_trace_values
+            .chunks(air.ce_blowup_factor())
+            .collect()
+    }
+
+    /// Checks the queried rows of each trace resolve to their commitment
+    pub fn verify(
+        &self,
+        air: &Air<C::AirConfig>,
+        base_trace_commitment: &C::Digest,
+        extension_trace_commitment: Option<&C::Digest>,
+        composition_trace_commitment: &C::Digest,
+        positions: &[usize],
+    ) -> Result<(), VerificationError> {
+        let invalid = |trace| {
+            move |source| VerificationError::TraceQueryDoesNotMatchCommitment { trace, source }
+        };
+        C::MerkleTree::verify_rows(
+            base_trace_commitment,
+            positions,
+            &self.base_trace_rows(),
+            self.base_trace_proof.clone(),
+        )
+        .map_err(invalid(CommittedTrace::Base))?;
+
+        if let Some(extension_trace_commitment) = extension_trace_commitment {
+            let extension_trace_proof = self.extension_trace_proof.clone();
+            extension_trace_proof
+                .ok_or(merkle::Error::InvalidProof)
+                .and_then(|proof| {
+                    C::MerkleTree::verify_rows(
+                        extension_trace_commitment,
+                        positions,
+                        &self.aux_segment_rows(air, 0),
+                        proof,
+                    )
+                })
+                .map_err(invalid(CommittedTrace::Extension))?;
+        }
+
+        C::MerkleTree::verify_rows(
+            composition_trace_commitment,
+            positions,
+            &self.composition_trace_rows(air),
+            self.composition_trace_proof.clone(),
+        )
+        .map_err(invalid(CommittedTrace::Composition))
+    }
+
+    /// Checks the queried rows of the auxiliary segments after the first
+    /// resolve to their commitments
+    pub fn verify_aux_segments(
+        &self,
+        air: &Air<C::AirConfig>,
+        aux_trace_commitments: &[C::Digest],
+        positions: &[usize],
+    ) -> Result<(), VerificationError> {
+        if self.aux_trace_proofs.len() != aux_trace_commitments.len() {
+            return Err(VerificationError::TraceQueryDoesNotMatchCommitment {
+                trace: CommittedTrace::AuxSegment(
+                    aux_trace_commitments.len().min(self.aux_trace_proofs.len()) + 1,
+                ),
+                source: merkle::Error::InvalidProof,
+            });
         }
+        for (i, (commitment, proof)) in aux_trace_commitments
+            .iter()
+            .zip(&self.aux_trace_proofs)
+            .enumerate()
+        {
+            let segment = i + 1;
+            C::MerkleTree::verify_rows(
+                commitment,
+                positions,
+                &self.aux_segment_rows(air, segment),
+                proof.clone(),
+            )
+            .map_err(
+                |source| VerificationError::TraceQueryDoesNotMatchCommitment {
+                    trace: CommittedTrace::AuxSegment(segment),
+                    source,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Checks the queried rows of the preprocessed columns resolve to their
+    /// commitment
+    pub fn verify_preprocessed(
+        &self,
+        preprocessed_commitment: &C::Digest,
+        positions: &[usize],
+    ) -> Result<(), VerificationError> {
+        self.preprocessed_trace_proof
+            .clone()
+            .ok_or(merkle::Error::InvalidProof)
+            .and_then(|proof| {
+                C::MerkleTree::verify_rows(
+                    preprocessed_commitment,
+                    positions,
+                    &self.preprocessed_trace_rows(),
+                    proof,
+                )
+            })
+            .map_err(
+                |source| VerificationError::TraceQueryDoesNotMatchCommitment {
+                    trace: CommittedTrace::Preprocessed,
+                    source,
+                },
+            )
+    }
+}
+
+/// Rows of the base trace, extension trace and composition trace at a single
+/// query position along with their merkle proofs. [`Queries`] is the batched
+/// equivalent for all query positions.
+pub struct QueryProof<C: Stark> {
+    pub position: usize,
+    pub base_trace_row: Vec<C::Fp>,
+    /// Empty if the AIR has no extension columns
+    pub extension_trace_row: Vec<C::Fq>,
+    pub composition_trace_row: Vec<C::Fq>,
+    pub base_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
+    pub extension_trace_proof: Option<<C::MerkleTree as MerkleTree>::Proof>,
+    pub composition_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
+}
+
+impl<C: Stark> Clone for QueryProof<C> {
+    fn clone(&self) -> Self {
         Self {
+            position: self.position,
+            base_trace_row: self.base_trace_row.clone(),
+            extension_trace_row: self.extension_trace_row.clone(),
+            composition_trace_row: self.composition_trace_row.clone(),
+            base_trace_proof: self.base_trace_proof.clone(),
+            extension_trace_proof: self.extension_trace_proof.clone(),
+            composition_trace_proof: self.composition_trace_proof.clone(),
+        }
+    }
+}
+
+impl<C: Stark> QueryProof<C> {
+    pub fn new(
+        base_trace_lde: &Matrix<C::Fp>,
+        extension_trace_lde: Option<&Matrix<C::Fq>>,
+        composition_trace_lde: &Matrix<C::Fq>,
+        base_tree: &C::MerkleTree,
+        extension_tree: Option<&C::MerkleTree>,
+        composition_tree: &C::MerkleTree,
+        position: usize,
+    ) -> Result<Self, merkle::Error> {
+        let Queries {
             base_trace_values,
             extension_trace_values,
             composition_trace_values,
             base_trace_proof,
             extension_trace_proof,
             composition_trace_proof,
+            ..
+        } = Queries::<C>::new(
+            base_trace_lde,
+            extension_trace_lde,
+            composition_trace_lde,
+            base_tree,
+            extension_tree,
+            composition_tree,
+            &[position],
+        )?;
+        Ok(Self {
+            position,
+            base_trace_row: base_trace_values,
+            extension_trace_row: extension_trace_values,
+            composition_trace_row: composition_trace_values,
+            base_trace_proof,
+            extension_trace_proof,
+            composition_trace_proof,
+        })
+    }
+
+    /// Checks the rows resolve to their commitment (see [`Queries::verify`])
+    pub fn verify(
+        &self,
+        air: &Air<C::AirConfig>,
+        base_trace_commitment: &C::Digest,
+        extension_trace_commitment: Option<&C::Digest>,
+        composition_trace_commitment: &C::Digest,
+    ) -> Result<(), VerificationError> {
+        Queries::from(self.clone()).verify(
+            air,
+            base_trace_commitment,
+            extension_trace_commitment,
+            composition_trace_commitment,
+            &[self.position],
+        )
+    }
+}
+
+impl<C: Stark> From<QueryProof<C>> for Queries<C> {
+    fn from(query: QueryProof<C>) -> Self {
+        Self {
+            base_trace_values: query.base_trace_row,
+            extension_trace_values: query.extension_trace_row,
+            composition_trace_values: query.composition_trace_row,
+            base_trace_proof: query.base_trace_proof,
+            extension_trace_proof: query.extension_trace_proof,
+            composition_trace_proof: query.composition_trace_proof,
+            preprocessed_trace_values: Vec::new(),
+            preprocessed_trace_proof: None,
+            aux_trace_proofs: Vec::new(),
         }
     }
 }
@@ -1,12 +1,18 @@
 use crate::hash::Digest;
 use alloc::vec::Vec;
+use ark_ff::batch_inversion;
 use ark_ff::BigInteger;
+#[cfg(feature = "verifier")]
 use ark_ff::FftField;
 use ark_ff::Field;
 use ark_ff::One;
 use ark_ff::PrimeField;
 use ark_ff::Zero;
+#[cfg(feature = "verifier")]
+use ark_poly::domain::DomainCoeff;
+#[cfg(feature = "verifier")]
 use ark_poly::domain::Radix2EvaluationDomain;
+#[cfg(feature = "verifier")]
 use ark_poly::EvaluationDomain;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
@@ -14,39 +20,52 @@ use ark_serialize::Valid;
 use core::alloc::AllocError;
 use core::alloc::Allocator;
 use core::alloc::Layout;
+use core::fmt::Debug;
 use core::fmt::Display;
+use core::future::Future;
+use core::iter::zip;
 use core::ops::Add;
 use core::ops::AddAssign;
+use core::ops::Deref;
+use core::ops::DerefMut;
 use core::ops::Div;
 use core::ops::Mul;
 use core::ops::Neg;
+use core::pin::Pin;
 use core::ptr::NonNull;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+use core::task::Context;
+use core::task::Poll;
 use num_traits::Pow;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::fmt::Debug;
-use std::iter::zip;
-use std::ops::Deref;
-use std::ops::DerefMut;
 
-#[cfg(feature = "std")]
-pub struct Timer<'a> {
-    name: &'a str,
-    start: std::time::Instant,
+/// Returns a future that yields to the executor once before completing.
+///
+/// Unlike executor specific yield functions (e.g. `tokio::task::yield_now`)
+/// this works with any executor.
+pub const fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
 }
 
-#[cfg(feature = "std")]
-impl<'a> Timer<'a> {
-    pub fn new(name: &'a str) -> Timer<'a> {
-        let start = std::time::Instant::now();
-        Timer { name, start }
-    }
+/// Future returned by [`yield_now`]
+#[must_use = "futures do nothing unless polled"]
+pub struct YieldNow {
+    yielded: bool,
 }
 
-#[cfg(feature = "std")]
-impl<'a> Drop for Timer<'a> {
-    fn drop(&mut self) {
-        println!("{} in {:?}", self.name, self.start.elapsed());
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
     }
 }
 
@@ -83,6 +102,7 @@ pub const fn ceil_power_of_two(value: usize) -> usize {
 
 // from arkworks
 /// This evaluates the vanishing polynomial for this domain at tau.
+#[cfg(feature = "verifier")]
 pub fn evaluate_vanishing_polynomial<F: FftField + Into<T>, T: Field>(
     domain: &Radix2EvaluationDomain<F>,
     tau: T,
@@ -92,6 +112,7 @@ pub fn evaluate_vanishing_polynomial<F: FftField + Into<T>, T: Field>(
 
 // Evaluates the vanishing polynomial for `vanish_domain` over `eval_domain`
 // E.g. evaluates `(x - v_0)(x - v_1)...(x - v_n-1)` over `eval_domain`
+#[cfg(feature = "verifier")]
 pub fn fill_vanishing_polynomial<F: FftField>(
     dst: &mut [F],
     vanish_domain: &Radix2EvaluationDomain<F>,
@@ -118,6 +139,99 @@ pub fn fill_vanishing_polynomial<F: FftField>(
         });
 }
 
+/// Returns the elements of `domain` in bit-reversed order without permuting
+/// them. Element `i` is `offset * g^bitrev(i)`.
+#[cfg(feature = "verifier")]
+pub fn bit_reversed_elements<F: FftField>(domain: &Radix2EvaluationDomain<F>) -> Vec<F> {
+    let n = domain.size();
+    let mut generators = Vec::new();
+    let mut generator = domain.group_gen();
+    for _ in 0..n.ilog2() {
+        generators.push(generator);
+        generator.square_in_place();
+    }
+    // the bit-reversed elements of a domain of size 2k are those of the domain
+    // of size k generated by g^2 followed by the same elements times g
+    let mut elements = Vec::with_capacity(n);
+    elements.push(domain.coset_offset());
+    for generator in generators.into_iter().rev() {
+        let shifted = elements.iter().map(|&x| x * generator).collect::<Vec<F>>();
+        elements.extend(shifted);
+    }
+    elements
+}
+
+/// Returns the Metal planners if their library has FFT kernels for `F`.
+/// Returns no planners otherwise so FFTs over `F` run on the CPU.
+#[cfg(metal_gpu)]
+pub(crate) fn metal_fft_planners<F: ministark_gpu::GpuField>(
+) -> &'static [ministark_gpu::plan::Planner] {
+    let planners = ministark_gpu::prelude::get_planners();
+    if planners[0].supports_fft::<F>() {
+        planners
+    } else {
+        &[]
+    }
+}
+
+/// Returns the wgpu planner if it has kernels for `F` and a domain of
+/// `domain_size` is large enough to be worth transforming on the GPU
+#[cfg(wgpu_gpu)]
+pub(crate) fn wgpu_planner<F: ministark_gpu::GpuField + Field>(
+    domain_size: usize,
+) -> Option<&'static ministark_gpu::wgpu_plan::Planner>
+where
+    F::FftField: FftField,
+{
+    use ministark_gpu::wgpu_plan::GpuFft;
+    use ministark_gpu::wgpu_plan::GpuIfft;
+    use ministark_gpu::wgpu_plan::Planner;
+    let min_size = GpuFft::<F>::MIN_SIZE.max(GpuIfft::<F>::MIN_SIZE);
+    if domain_size < min_size || !Planner::supports::<F>() {
+        return None;
+    }
+    ministark_gpu::wgpu_plan::try_get_planner()
+}
+
+/// Evaluates a polynomial over `domain` in place. Coefficients are in natural
+/// order and evaluations are output in bit-reversed order. Uses a decimation
+/// in frequency FFT so unlike [`EvaluationDomain::fft_in_place`] followed by a
+/// bit reversal values are never permuted.
+#[cfg(feature = "verifier")]
+pub fn out_of_order_fft_in_place<F: FftField, T: DomainCoeff<F>>(
+    values: &mut [T],
+    domain: &Radix2EvaluationDomain<F>,
+) {
+    let n = domain.size();
+    assert_eq!(values.len(), n);
+    if domain.coset_offset() != F::one() {
+        Radix2EvaluationDomain::distribute_powers(values, domain.coset_offset());
+    }
+    let root = domain.group_gen();
+    let twiddles = (0..n / 2)
+        .scan(F::one(), |twiddle, _| {
+            let current = *twiddle;
+            *twiddle *= root;
+            Some(current)
+        })
+        .collect::<Vec<F>>();
+    let mut half = n / 2;
+    let mut stride = 1;
+    while half > 0 {
+        for block in values.chunks_exact_mut(2 * half) {
+            let (lhs, rhs) = block.split_at_mut(half);
+            for (j, (u, v)) in zip(lhs, rhs).enumerate() {
+                let sum = *u + *v;
+                *v = *u - *v;
+                *v *= twiddles[j * stride];
+                *u = sum;
+            }
+        }
+        half /= 2;
+        stride *= 2;
+    }
+}
+
 // taken from arkworks-rs
 /// Horner's method for polynomial evaluation
 #[inline]
@@ -174,6 +288,43 @@ pub fn divide_out_points_into<F: Field>(p_coeffs: &mut [F], zs: &[F], cs: &[F])
     }
 }
 
+/// Divides evaluations over a coset by the evaluations of a divisor over the
+/// same coset pointwise.
+///
+/// Quotients in the prover are computed this way rather
+/// than with polynomial division. Custom arguments with their own divisors
+/// should use this too.
+///
+/// # Panics
+///
+/// Panics if the divisor vanishes anywhere on the coset
+pub fn divide_on_coset<F: Field>(evals: &mut [F], divisor_evals: &[F]) {
+    assert_eq!(evals.len(), divisor_evals.len());
+    let mut divisor_inv = divisor_evals.to_vec();
+    invert_divisor(&mut divisor_inv);
+    #[cfg(feature = "op-count")]
+    crate::op_count::record(crate::op_count::OpCounts::new(evals.len() as u64, 0, 0));
+    ark_std::cfg_iter_mut!(evals)
+        .zip(ark_std::cfg_iter!(divisor_inv))
+        .for_each(|(eval, inv)| *eval *= inv);
+}
+
+/// Inverts the evaluations of a divisor over a coset so they can be reused to
+/// divide several columns by the same divisor (see [`divide_on_coset`]).
+///
+/// # Panics
+///
+/// Panics if the divisor vanishes anywhere on the coset
+pub fn invert_divisor<F: Field>(divisor_evals: &mut [F]) {
+    assert!(
+        !ark_std::cfg_iter!(divisor_evals).any(F::is_zero),
+        "divisor vanishes on the coset"
+    );
+    #[cfg(feature = "op-count")]
+    crate::op_count::record_batch_inversion(divisor_evals.len());
+    batch_inversion(divisor_evals);
+}
+
 pub fn field_bits<F: Field>() -> u32 {
     let base_field_modulus = <F::BasePrimeField as PrimeField>::MODULUS;
     let base_field_bits = base_field_modulus.num_bits();
@@ -435,37 +586,96 @@ impl<Fp: CanonicalDeserialize, Fq: CanonicalDeserialize> CanonicalDeserialize
     }
 }
 
+static GPU_MEMORY_IN_USE: AtomicUsize = AtomicUsize::new(0);
+static GPU_MEMORY_PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of bytes currently held by [`GpuAllocator`]
+pub fn gpu_memory_in_use() -> usize {
+    GPU_MEMORY_IN_USE.load(Ordering::Relaxed)
+}
+
+/// Returns the most bytes held by [`GpuAllocator`] at once since the last call
+/// to [`reset_peak_gpu_memory`]
+pub fn peak_gpu_memory() -> usize {
+    GPU_MEMORY_PEAK.load(Ordering::Relaxed)
+}
+
+/// Sets the peak returned by [`peak_gpu_memory`] to the memory currently in use
+pub fn reset_peak_gpu_memory() {
+    GPU_MEMORY_PEAK.store(gpu_memory_in_use(), Ordering::Relaxed);
+}
+
+fn track_gpu_allocation(size: usize) {
+    let in_use = GPU_MEMORY_IN_USE.fetch_add(size, Ordering::Relaxed) + size;
+    GPU_MEMORY_PEAK.fetch_max(in_use, Ordering::Relaxed);
+}
+
+fn track_gpu_deallocation(size: usize) {
+    GPU_MEMORY_IN_USE.fetch_sub(size, Ordering::Relaxed);
+}
+
 /// Shared vec between GPU and CPU.
 /// Requirement is that the vec's memory is page aligned.
 pub type GpuVec<T> = Vec<T, GpuAllocator>;
 
 /// Allocator with page aligned allocations on Apple Silicon.
-/// Uses global allocator on all other platforms.
+/// Uses global allocator on all other platforms. Large allocations are backed
+/// by memory mapped files if spilling is enabled (see [`crate::spill`]).
 pub struct GpuAllocator;
 
 unsafe impl Allocator for GpuAllocator {
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
-        #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
-        return page_aligned_allocator::PageAlignedAllocator.allocate(layout);
-        #[cfg(not(all(target_arch = "aarch64", target_os = "macos")))]
-        return ark_std::alloc::Global.allocate(layout);
+        // spilling needs a file system
+        #[cfg(feature = "std")]
+        let spilled = crate::spill::allocate(layout);
+        #[cfg(not(feature = "std"))]
+        let spilled = None;
+        let ptr = match spilled {
+            Some(ptr) => ptr,
+            #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+            None => page_aligned_allocator::PageAlignedAllocator.allocate(layout)?,
+            #[cfg(not(all(target_arch = "aarch64", target_os = "macos")))]
+            None => alloc::alloc::Global.allocate(layout)?,
+        };
+        track_gpu_allocation(layout.size());
+        Ok(ptr)
     }
 
     unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        track_gpu_deallocation(layout.size());
+        #[cfg(feature = "std")]
+        if crate::spill::deallocate(ptr) {
+            return;
+        }
         #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
         return page_aligned_allocator::PageAlignedAllocator.deallocate(ptr, layout);
         #[cfg(not(all(target_arch = "aarch64", target_os = "macos")))]
-        return ark_std::alloc::Global.deallocate(ptr, layout);
+        return alloc::alloc::Global.deallocate(ptr, layout);
     }
 }
 
 pub fn gpu_vec_to_vec<T>(v: GpuVec<T>) -> Vec<T> {
-    let (ptr, length, capacity) = v.into_raw_parts();
+    #[cfg(feature = "std")]
+    if crate::spill::is_spilled(v.as_ptr()) {
+        // memory mapped allocations can't be freed by the global allocator
+        let mut res = Vec::with_capacity(v.len());
+        res.extend(v);
+        return res;
+    }
+    let (ptr, length, capacity) = {
+        let (p, l, c, _) = v.into_raw_parts_with_alloc();
+        (p, l, c)
+    };
+    track_gpu_deallocation(capacity * core::mem::size_of::<T>());
     unsafe { Vec::from_raw_parts(ptr, length, capacity) }
 }
 
 pub fn vec_to_gpu_vec<T>(v: Vec<T>) -> GpuVec<T> {
-    let (ptr, length, capacity) = v.into_raw_parts();
+    let (ptr, length, capacity) = {
+        let (p, l, c, _) = v.into_raw_parts_with_alloc();
+        (p, l, c)
+    };
+    track_gpu_allocation(capacity * core::mem::size_of::<T>());
     unsafe { Vec::from_raw_parts_in(ptr, length, capacity, GpuAllocator) }
 }
 
@@ -518,7 +728,7 @@ impl<D: digest::Digest> PartialEq for SerdeOutput<D> {
 }
 
 impl<D: digest::Digest> Debug for SerdeOutput<D> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_tuple("SerdeOutput").field(&self.0).finish()
     }
 }
@@ -595,9 +805,14 @@ impl<D: digest::Digest> DerefMut for SerdeOutput<D> {
     }
 }
 
+#[cfg(feature = "channel")]
 pub mod tests {
     use super::GpuAllocator;
+    use crate::hash::Digest;
+    use crate::random::PublicCoin;
     use crate::Matrix;
+    use alloc::collections::BTreeSet;
+    use alloc::collections::VecDeque;
     use alloc::vec::Vec;
     use ark_ff::Field;
     use rand::Rng;
@@ -652,4 +867,108 @@ pub mod tests {
         col.resize_with(n, || if rng.gen() { v1 } else { v2 });
         Matrix::new(vec![col])
     }
+
+    /// Value absorbed by a [`MockChannel`]
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum Absorb<D, F> {
+        Digest(D),
+        FieldElements(Vec<F>),
+        Int(u64),
+        Bytes(Vec<u8>),
+    }
+
+    /// Public coin for tests that returns caller-scripted challenges instead of
+    /// hash outputs. Everything absorbed is recorded so tests can check what
+    /// the prover or verifier committed to.
+    ///
+    /// Draws pop values from the front of the script and panic once it is
+    /// exhausted. Proof-of-work checks always pass. Never use outside of tests.
+    #[derive(Debug)]
+    pub struct MockChannel<D: Digest, F: Field> {
+        pub seed: D,
+        draws: VecDeque<F>,
+        queries: VecDeque<BTreeSet<usize>>,
+        absorbs: Vec<Absorb<D, F>>,
+    }
+
+    impl<D: Digest, F: Field> MockChannel<D, F> {
+        /// Appends values to be returned by [`PublicCoin::draw`]
+        #[must_use]
+        pub fn with_draws(mut self, draws: impl IntoIterator<Item = F>) -> Self {
+            self.draws.extend(draws);
+            self
+        }
+
+        /// Appends query positions to be returned by
+        /// [`PublicCoin::draw_queries`]
+        #[must_use]
+        pub fn with_queries(mut self, queries: impl IntoIterator<Item = BTreeSet<usize>>) -> Self {
+            self.queries.extend(queries);
+            self
+        }
+
+        /// Returns everything absorbed since the channel was created
+        pub fn absorbs(&self) -> &[Absorb<D, F>] {
+            &self.absorbs
+        }
+
+        /// Returns the number of scripted draws that have not been returned yet
+        pub fn remaining_draws(&self) -> usize {
+            self.draws.len()
+        }
+    }
+
+    impl<D: Digest, F: Field> PublicCoin for MockChannel<D, F> {
+        type Digest = D;
+        type Field = F;
+
+        fn new(digest: D) -> Self {
+            Self {
+                seed: digest,
+                draws: VecDeque::new(),
+                queries: VecDeque::new(),
+                absorbs: Vec::new(),
+            }
+        }
+
+        fn reseed_with_digest(&mut self, val: &D) {
+            self.absorbs.push(Absorb::Digest(val.clone()));
+        }
+
+        fn reseed_with_field_elements(&mut self, vals: &[F]) {
+            self.absorbs.push(Absorb::FieldElements(vals.to_vec()));
+        }
+
+        fn reseed_with_int(&mut self, val: u64) {
+            self.absorbs.push(Absorb::Int(val));
+        }
+
+        fn reseed_with_bytes(&mut self, bytes: &[u8]) {
+            self.absorbs.push(Absorb::Bytes(bytes.to_vec()));
+        }
+
+        fn draw(&mut self) -> F {
+            self.draws
+                .pop_front()
+                .expect("mock channel has no scripted draws left")
+        }
+
+        fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize> {
+            let queries = self
+                .queries
+                .pop_front()
+                .expect("mock channel has no scripted queries left");
+            assert!(queries.len() <= max_n, "too many scripted queries");
+            assert!(queries.iter().all(|&position| position < domain_size));
+            queries
+        }
+
+        fn verify_proof_of_work(&self, _proof_of_work_bits: u8, _nonce: u64) -> bool {
+            true
+        }
+
+        fn security_level_bits() -> u32 {
+            256
+        }
+    }
 }
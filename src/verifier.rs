@@ -1,205 +1,730 @@
 use crate::air::AirConfig;
 use crate::challenges::Challenges;
+use crate::channel::absorb_instance;
 use crate::channel::VerifierChannelArtifacts;
 use crate::composer::DeepCompositionCoeffs;
+use crate::compression;
 use crate::constraints::AlgebraicItem;
 use crate::constraints::CompositionItem;
 use crate::fri;
+use crate::fri::FriProof;
 use crate::fri::FriVerifier;
 use crate::hints::Hints;
-use crate::merkle::MatrixMerkleTree;
+use crate::merkle;
+use crate::merkle::MerkleTree;
+use crate::preprocessed::PreprocessedColumns;
+use crate::proof::security_level_bits;
+use crate::proof::ProofFrame;
 use crate::random::draw_multiple;
 use crate::random::PublicCoin;
+use crate::random::QuerySampling;
 use crate::stark::Stark;
+use crate::trace::Queries;
+use crate::utils::field_bits;
 use crate::utils::horner_evaluate;
 use crate::utils::FieldVariant;
 use crate::Air;
+use crate::InvalidProofOptions;
+use crate::Matrix;
 use crate::Proof;
+use crate::ProofOptions;
+use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_ff::Zero;
 use ark_poly::EvaluationDomain;
+use core::fmt::Display;
+use core::iter;
 use ministark_gpu::utils::bit_reverse_index;
 use snafu::Snafu;
 
-#[allow(clippy::too_many_lines)]
 pub fn default_verify<S: Stark>(
     this: &S,
     proof: Proof<S>,
     required_security_bits: u32,
 ) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
-    use VerificationError::*;
+    #[cfg(feature = "reference-verify")]
+    let reference = crate::reference::verify(this, &proof, required_security_bits);
+    let verified = verify_frames(StreamingVerifier::new(this, required_security_bits), proof);
+    #[cfg(feature = "reference-verify")]
+    crate::reference::assert_agreement(&verified, &reference);
+    verified
+}
+
+/// Verifies a proof with the preprocessed commitment re-derived from
+/// `preprocessed_columns` (see
+/// [`StreamingVerifier::with_preprocessed_columns`])
+pub fn default_verify_with_preprocessed_columns<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    required_security_bits: u32,
+    preprocessed_columns: &Matrix<S::Fp>,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    let verifier = StreamingVerifier::new(this, required_security_bits)
+        .with_preprocessed_columns(preprocessed_columns);
+    verify_frames(verifier, proof)
+}
+
+fn verify_frames<S: Stark>(
+    mut verifier: StreamingVerifier<'_, S>,
+    proof: Proof<S>,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    let [commitments_frame, fri_frame, queries_frame] = proof.into_frames();
+    verifier.verify_frame(commitments_frame)?;
+    verifier.verify_frame(fri_frame)?;
+    Ok(verifier.verify_frame(queries_frame)?.unwrap())
+}
+
+/// Verifies a proof (see [`Stark::verify`]) and reports the soundness achieved
+/// by the proof's parameters and the positions it was queried at
+///
+/// # Errors
+///
+/// Returns an error if the proof is invalid.
+pub fn verify_with_report<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    required_security_bits: u32,
+) -> Result<(VerifierChannelArtifacts<S::Fq>, SoundnessReport), VerificationError> {
+    let options = proof.options;
+    let trace_len = proof.trace_len;
+    let artifacts = this.verify(proof, required_security_bits)?;
+    let report = SoundnessReport::new::<S>(options, trace_len, artifacts.query_positions.len());
+    Ok((artifacts, report))
+}
+
+/// Concrete soundness of a verified proof. Unlike
+/// [`Proof::security_level_bits`] this accounts for the positions the proof
+/// was actually queried at so auditors can confirm the security margin of a
+/// deployment proof by proof.
+///
+/// Errors assume FRI is sound up to the list decoding radius (see
+/// [`ProofOptions::security_level_bits`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundnessReport {
+    /// Number of query positions drawn
+    pub num_queries: u8,
+    /// Number of distinct positions the queries hit. Positions drawn more
+    /// than once with [`QuerySampling::WithReplacement`] are only checked
+    /// once.
+    pub distinct_positions: usize,
+    pub query_sampling: QuerySampling,
+    /// Bits of proof of work
+    pub grinding_factor: u8,
+    /// Number of bits in the field challenges are drawn from (see
+    /// [`field_bits`])
+    pub field_bits: u32,
+    pub lde_domain_size: usize,
+    /// Domain size of each committed FRI layer. Each layer is the previous
+    /// one folded by the FRI folding factor.
+    pub fri_layer_sizes: Vec<usize>,
+    /// Size of the domain the FRI remainder is evaluated over
+    pub fri_remainder_size: usize,
+    /// Probability a proof that isn't close to a valid one passes the proof
+    /// of work and the checks at the distinct query positions
+    pub query_error: f64,
+    /// Probability the out-of-domain point or a FRI folding challenge is bad.
+    /// Each is bad with probability at most `lde_domain_size / |F|`.
+    pub field_error: f64,
+    /// Collision resistance of the Merkle trees, the public coin and the proof
+    /// of work hash
+    pub hash_security_bits: u32,
+}
+
+impl SoundnessReport {
+    #[allow(clippy::cast_precision_loss)]
+    fn new<S: Stark>(options: ProofOptions, trace_len: usize, distinct_positions: usize) -> Self {
+        let fri_options = options.into_fri_options();
+        let lde_domain_size = trace_len * usize::from(options.lde_blowup_factor);
+        let folding_factor = usize::from(options.fri_folding_factor);
+        let fri_layer_sizes =
+            iter::successors(Some(lde_domain_size), |size| Some(size / folding_factor))
+                .take(fri_options.num_layers(lde_domain_size))
+                .collect::<Vec<usize>>();
+        let field_bits = field_bits::<S::Fq>();
+        let num_challenges = fri_layer_sizes.len() + 1;
+        let field_error =
+            num_challenges as f64 * (lde_domain_size as f64 / libm::exp2(f64::from(field_bits)));
+        // distinct positions are a sample without replacement however they were drawn
+        let rho = 1.0 / f64::from(options.lde_blowup_factor);
+        let query_bits = QuerySampling::Distinct.security_bits(
+            u8::try_from(distinct_positions).unwrap(),
+            rho,
+            lde_domain_size,
+        ) + f64::from(options.grinding_factor);
+        let pow_security = match options.grinding_factor {
+            0 => None,
+            _ => options.pow_hash.security_level_bits(),
+        };
+        let hash_security_bits = S::MerkleTree::security_level_bits()
+            .min(S::PublicCoin::security_level_bits())
+            .min(pow_security.unwrap_or(u32::MAX));
+        Self {
+            num_queries: options.num_queries,
+            distinct_positions,
+            query_sampling: options.query_sampling,
+            grinding_factor: options.grinding_factor,
+            field_bits,
+            lde_domain_size,
+            fri_layer_sizes,
+            fri_remainder_size: fri_options.remainder_size(lde_domain_size),
+            query_error: libm::exp2(-query_bits),
+            field_error,
+            hash_security_bits,
+        }
+    }
+
+    /// Upper bound on the probability the verifier accepted a proof of a false
+    /// statement by chance i.e. the sum of the query and field errors
+    pub fn soundness_error(&self) -> f64 {
+        self.query_error + self.field_error
+    }
+
+    /// Bits of security of the proof. Bounded by the soundness error and the
+    /// collision resistance of the hash functions.
+    pub fn security_bits(&self) -> f64 {
+        (-libm::log2(self.soundness_error())).min(f64::from(self.hash_security_bits))
+    }
+}
+
+/// Work done by the verifier that only depends on the AIR, the trace length
+/// and the proof options.
+///
+/// Building the constraints of an AIR and compiling its composition
+/// constraint usually dominates the cost of verifying small proofs. Services
+/// that verify many proofs of the same AIR can prepare a key once and reuse it
+/// for every proof. Proofs with a different trace length or options than the
+/// key are still verified but don't benefit from the key.
+pub struct PreparedVerifyingKey<S: Stark> {
+    air: Air<S::AirConfig>,
+    /// Security level of proofs that use the key's trace length and options
+    security_level: u32,
+}
+
+impl<S: Stark> PreparedVerifyingKey<S> {
+    /// Prepares a key for proofs of traces with `trace_len` rows generated
+    /// with `options`
+    pub fn new(stark: &S, trace_len: usize, options: ProofOptions) -> Self {
+        Self {
+            air: Air::new(trace_len, stark.get_public_inputs(), options),
+            security_level: security_level_bits::<S>(options, trace_len),
+        }
+    }
+
+    pub const fn air(&self) -> &Air<S::AirConfig> {
+        &self.air
+    }
+
+    /// Verifies a proof of `stark` (see [`Stark::verify`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the proof is invalid.
+    pub fn verify(
+        &self,
+        stark: &S,
+        proof: Proof<S>,
+        required_security_bits: u32,
+    ) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+        #[cfg(feature = "reference-verify")]
+        let reference = crate::reference::verify(stark, &proof, required_security_bits);
+        let verifier = StreamingVerifier::with_key(stark, self, required_security_bits);
+        let verified = verify_frames(verifier, proof);
+        #[cfg(feature = "reference-verify")]
+        crate::reference::assert_agreement(&verified, &reference);
+        verified
+    }
+
+    fn prepared_air(
+        &self,
+        stark: &S,
+        trace_len: usize,
+        options: ProofOptions,
+    ) -> Option<(Air<S::AirConfig>, u32)> {
+        (self.air.trace_len() == trace_len && self.air.options() == options).then(|| {
+            let air = self.air.with_public_inputs(stark.get_public_inputs());
+            (air, self.security_level)
+        })
+    }
+}
+
+/// Verifies a proof one [`ProofFrame`] at a time as it arrives over a stream.
+///
+/// Frames must be provided in the order returned by [`Proof::into_frames`].
+/// Each frame is checked as soon as it is received so an invalid proof can be
+/// rejected without waiting for the remaining frames. Once a frame has been
+/// rejected all subsequent frames are rejected too.
+pub struct StreamingVerifier<'a, S: Stark> {
+    stark: &'a S,
+    key: Option<&'a PreparedVerifyingKey<S>>,
+    required_security_bits: u32,
+    preprocessed_columns: Option<&'a Matrix<S::Fp>>,
+    stage: StreamingStage<S>,
+}
+
+enum StreamingStage<S: Stark> {
+    AwaitingCommitments,
+    AwaitingFri(Box<CommitmentsStage<S>>),
+    AwaitingQueries(Box<FriStage<S>>),
+    Finished,
+}
+
+struct CommitmentsStage<S: Stark> {
+    air: Air<S::AirConfig>,
+    public_coin: S::PublicCoin,
+    air_challenges: Challenges<S::Fq>,
+    air_hints: Hints<S::Fq>,
+    preprocessed_commitment: Option<S::Digest>,
+    base_trace_commitment: S::Digest,
+    extension_trace_commitment: Option<S::Digest>,
+    aux_trace_commitments: Vec<S::Digest>,
+    composition_trace_commitment: S::Digest,
+    trace_ood_eval_map: BTreeMap<(usize, isize), S::Fq>,
+    composition_trace_ood_evals: Vec<S::Fq>,
+    deep_coeffs: DeepCompositionCoeffs<S::Fq>,
+    z: S::Fq,
+}
+
+struct FriStage<S: Stark> {
+    commitments: CommitmentsStage<S>,
+    fri_verifier: FriVerifier<S::Fq, S::Digest, S::MerkleTree>,
+    query_positions: Vec<usize>,
+}
+
+impl<'a, S: Stark> StreamingVerifier<'a, S> {
+    pub const fn new(stark: &'a S, required_security_bits: u32) -> Self {
+        Self {
+            stark,
+            key: None,
+            required_security_bits,
+            preprocessed_columns: None,
+            stage: StreamingStage::AwaitingCommitments,
+        }
+    }
 
-    if proof.security_level_bits() < required_security_bits {
-        return Err(InvalidProofSecurity);
+    /// Creates a verifier that reuses the setup work of `key`
+    pub const fn with_key(
+        stark: &'a S,
+        key: &'a PreparedVerifyingKey<S>,
+        required_security_bits: u32,
+    ) -> Self {
+        Self {
+            stark,
+            key: Some(key),
+            required_security_bits,
+            preprocessed_columns: None,
+            stage: StreamingStage::AwaitingCommitments,
+        }
+    }
+
+    /// Re-derives the commitment to the preprocessed columns from
+    /// `preprocessed_columns` instead of trusting
+    /// [`Stark::preprocessed_commitment`]. If the stark also provides a
+    /// commitment it must match the derived one. Useful for auditors and when
+    /// establishing a verifying key for the first time.
+    #[must_use]
+    pub const fn with_preprocessed_columns(
+        mut self,
+        preprocessed_columns: &'a Matrix<S::Fp>,
+    ) -> Self {
+        self.preprocessed_columns = Some(preprocessed_columns);
+        self
     }
 
-    let Proof {
-        options,
-        base_trace_commitment,
-        extension_trace_commitment,
-        composition_trace_commitment,
-        execution_trace_ood_evals,
-        composition_trace_ood_evals,
-        trace_queries,
-        trace_len,
-        fri_proof,
-        pow_nonce,
-        ..
-    } = proof;
-
-    let air = Air::new(trace_len, this.get_public_inputs(), options);
-    let mut public_coin = this.gen_public_coin(&air);
-
-    public_coin.reseed_with_digest(&base_trace_commitment);
-    let num_challenges = air.num_challenges();
-    let air_challenges = Challenges::new(draw_multiple(&mut public_coin, num_challenges));
-    let air_hints = air.gen_hints(&air_challenges);
-
-    let extension_trace_commitment = extension_trace_commitment.map(|commitment| {
-        public_coin.reseed_with_digest(&commitment);
-        commitment
-    });
-
-    let num_composition_coeffs = air.num_composition_constraint_coeffs();
-    let composition_coeffs = draw_multiple(&mut public_coin, num_composition_coeffs);
-    public_coin.reseed_with_digest(&composition_trace_commitment);
-
-    let z = public_coin.draw();
-    let ood_evals = [
-        execution_trace_ood_evals.clone(),
-        composition_trace_ood_evals.clone(),
-    ]
-    .concat();
-    public_coin.reseed_with_field_elements(&ood_evals);
-    // execution trace ood evaluation map
-    let trace_ood_eval_map = air
-        .trace_arguments()
-        .into_iter()
-        .zip(execution_trace_ood_evals)
-        .collect::<BTreeMap<(usize, isize), S::Fq>>();
-    let calculated_ood_constraint_evaluation = ood_constraint_evaluation::<S::AirConfig>(
-        &composition_coeffs,
-        &air_challenges,
-        &air_hints,
-        &trace_ood_eval_map,
-        &air,
-        z,
-    );
-
-    let provided_ood_constraint_evaluation = horner_evaluate(&composition_trace_ood_evals, &z);
-
-    if calculated_ood_constraint_evaluation != provided_ood_constraint_evaluation {
-        return Err(InconsistentOodConstraintEvaluations);
+    /// Returns true once all frames have been verified or a frame was rejected
+    pub const fn is_finished(&self) -> bool {
+        matches!(self.stage, StreamingStage::Finished)
     }
 
-    let deep_coeffs = this.gen_deep_coeffs(&mut public_coin, &air);
-    let fri_verifier = FriVerifier::<S::Fq, S::Digest, S::MerkleTree>::new(
-        &mut public_coin,
-        options.into_fri_options(),
-        fri_proof,
-        trace_len - 1,
-    )?;
-
-    if options.grinding_factor != 0 {
-        if !public_coin.verify_proof_of_work(options.grinding_factor, pow_nonce) {
-            return Err(FriProofOfWork);
+    /// Verifies the next frame of the proof. Returns the verifier's channel
+    /// artifacts once the final frame has been verified.
+    pub fn verify_frame(
+        &mut self,
+        frame: ProofFrame<S>,
+    ) -> Result<Option<VerifierChannelArtifacts<S::Fq>>, VerificationError> {
+        use StreamingStage::*;
+        // the stage is left as `Finished` if the frame is rejected
+        let stage = core::mem::replace(&mut self.stage, Finished);
+        match (stage, frame) {
+            (
+                AwaitingCommitments,
+                ProofFrame::Commitments {
+                    options,
+                    trace_len,
+                    base_trace_commitment,
+                    extension_trace_commitment,
+                    aux_trace_commitments,
+                    composition_trace_commitment,
+                    execution_trace_ood_evals,
+                    composition_trace_ood_evals,
+                },
+            ) => {
+                options
+                    .validate()
+                    .map_err(|source| VerificationError::InvalidOptions { source })?;
+                let prepared = self
+                    .key
+                    .and_then(|key| key.prepared_air(self.stark, trace_len, options));
+                let (air, security_level) = if let Some(prepared) = prepared {
+                    prepared
+                } else {
+                    let public_inputs = self.stark.get_public_inputs();
+                    let air = Air::try_new(trace_len, public_inputs, options)
+                        .map_err(|source| VerificationError::InvalidOptions { source })?;
+                    (air, security_level_bits::<S>(options, trace_len))
+                };
+                if security_level < self.required_security_bits {
+                    return Err(VerificationError::InvalidProofSecurity {
+                        security_level,
+                        required: self.required_security_bits,
+                    });
+                }
+
+                let commitments = self.verify_commitments(
+                    air,
+                    base_trace_commitment,
+                    extension_trace_commitment,
+                    aux_trace_commitments,
+                    composition_trace_commitment,
+                    execution_trace_ood_evals,
+                    composition_trace_ood_evals,
+                )?;
+                self.stage = AwaitingFri(Box::new(commitments));
+                Ok(None)
+            }
+            (
+                AwaitingFri(commitments),
+                ProofFrame::Fri {
+                    fri_proof,
+                    pow_nonce,
+                },
+            ) => {
+                let fri = Self::verify_fri(*commitments, fri_proof, pow_nonce)?;
+                self.stage = AwaitingQueries(Box::new(fri));
+                Ok(None)
+            }
+            (AwaitingQueries(fri), ProofFrame::Queries { trace_queries }) => {
+                Self::verify_queries(*fri, &trace_queries).map(Some)
+            }
+            _ => Err(VerificationError::UnexpectedProofFrame),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn verify_commitments(
+        &self,
+        air: Air<S::AirConfig>,
+        base_trace_commitment: S::Digest,
+        extension_trace_commitment: Option<S::Digest>,
+        aux_trace_commitments: Vec<S::Digest>,
+        composition_trace_commitment: S::Digest,
+        execution_trace_ood_evals: Vec<S::Fq>,
+        composition_trace_ood_evals: Vec<S::Fq>,
+    ) -> Result<CommitmentsStage<S>, VerificationError> {
+        let mut public_coin = self.stark.gen_public_coin(&air);
+        absorb_instance::<S>(&mut public_coin, &air);
+
+        let preprocessed_commitment = if S::AirConfig::NUM_PREPROCESSED_COLUMNS == 0 {
+            None
+        } else {
+            let commitment = match self.preprocessed_columns {
+                Some(columns) => self.derive_preprocessed_commitment(&air, columns)?,
+                None => self
+                    .stark
+                    .preprocessed_commitment()
+                    .ok_or(VerificationError::MissingPreprocessedCommitment)?,
+            };
+            public_coin.reseed_with_digest(&commitment);
+            Some(commitment)
+        };
+
+        public_coin.reseed_with_digest(&base_trace_commitment);
+        let aux_segments = air.aux_segments();
+        let expected = aux_segments.iter().filter(|s| s.num_columns != 0).count();
+        let actual =
+            usize::from(extension_trace_commitment.is_some()) + aux_trace_commitments.len();
+        if actual != expected || (extension_trace_commitment.is_none() && actual != 0) {
+            return Err(VerificationError::AuxSegmentCountMismatch { expected, actual });
         }
-        public_coin.reseed_with_int(pow_nonce);
+        // each auxiliary segment draws its challenges and then is committed to
+        let mut challenges = Vec::new();
+        let segment_commitments = extension_trace_commitment
+            .iter()
+            .chain(&aux_trace_commitments);
+        for (segment, commitment) in aux_segments.iter().zip(segment_commitments) {
+            challenges.extend(draw_multiple(&mut public_coin, segment.num_challenges));
+            public_coin.reseed_with_digest(commitment);
+        }
+        for segment in aux_segments.iter().skip(expected) {
+            challenges.extend(draw_multiple(&mut public_coin, segment.num_challenges));
+        }
+        let air_challenges = Challenges::new(challenges);
+        let air_hints = air.gen_hints(&air_challenges);
+
+        let composition_coeffs = self
+            .stark
+            .gen_composition_constraint_coeffs(&mut public_coin, &air);
+        public_coin.reseed_with_digest(&composition_trace_commitment);
+
+        let z = public_coin.draw();
+        let ood_evals = [
+            execution_trace_ood_evals.clone(),
+            composition_trace_ood_evals.clone(),
+        ]
+        .concat();
+        public_coin.reseed_with_field_elements(&ood_evals);
+        // execution trace ood evaluation map
+        let trace_ood_eval_map = air
+            .trace_arguments()
+            .into_iter()
+            .zip(execution_trace_ood_evals)
+            .collect::<BTreeMap<(usize, isize), S::Fq>>();
+        let calculated_ood_constraint_evaluation = ood_constraint_evaluation::<S::AirConfig>(
+            &composition_coeffs,
+            &air_challenges,
+            &air_hints,
+            &trace_ood_eval_map,
+            &air,
+            z,
+        );
+
+        let provided_ood_constraint_evaluation = horner_evaluate(&composition_trace_ood_evals, &z);
+
+        if calculated_ood_constraint_evaluation != provided_ood_constraint_evaluation {
+            return Err(VerificationError::InconsistentOodConstraintEvaluations {
+                calculated: calculated_ood_constraint_evaluation.to_string(),
+                provided: provided_ood_constraint_evaluation.to_string(),
+            });
+        }
+
+        let deep_coeffs = self.stark.gen_deep_coeffs(&mut public_coin, &air);
+
+        Ok(CommitmentsStage {
+            air,
+            public_coin,
+            air_challenges,
+            air_hints,
+            preprocessed_commitment,
+            base_trace_commitment,
+            extension_trace_commitment,
+            aux_trace_commitments,
+            composition_trace_commitment,
+            trace_ood_eval_map,
+            composition_trace_ood_evals,
+            deep_coeffs,
+            z,
+        })
     }
 
-    let lde_domain_size = air.trace_len() * air.lde_blowup_factor();
-    let query_positions =
-        Vec::from_iter(public_coin.draw_queries(options.num_queries.into(), lde_domain_size));
-
-    let base_trace_rows = trace_queries
-        .base_trace_values
-        .chunks(S::AirConfig::NUM_BASE_COLUMNS)
-        .collect::<Vec<_>>();
-    let extension_trace_rows = if S::AirConfig::NUM_EXTENSION_COLUMNS == 0 {
-        Vec::new()
-    } else {
-        trace_queries
-            .extension_trace_values
-            .chunks(S::AirConfig::NUM_EXTENSION_COLUMNS)
-            .collect::<Vec<_>>()
-    };
-
-    let composition_trace_rows = trace_queries
-        .composition_trace_values
-        .chunks(air.ce_blowup_factor())
-        .collect::<Vec<&[S::Fq]>>();
-
-    // base trace positions
-    S::MerkleTree::verify_rows(
-        &base_trace_commitment,
-        &query_positions,
-        &base_trace_rows,
-        trace_queries.base_trace_proof,
-    )
-    .map_err(|_| BaseTraceQueryDoesNotMatchCommitment)?;
-
-    if let Some(extension_trace_commitment) = extension_trace_commitment {
-        S::MerkleTree::verify_rows(
-            &extension_trace_commitment,
+    fn derive_preprocessed_commitment(
+        &self,
+        air: &Air<S::AirConfig>,
+        columns: &Matrix<S::Fp>,
+    ) -> Result<S::Digest, VerificationError> {
+        if columns.num_rows() != air.trace_len()
+            || columns.num_cols() != S::AirConfig::NUM_PREPROCESSED_COLUMNS
+        {
+            return Err(VerificationError::InvalidPreprocessedColumns {
+                rows: columns.num_rows(),
+                columns: columns.num_cols(),
+            });
+        }
+        let derived = PreprocessedColumns::<S>::new(columns.clone(), air.options()).commitment();
+        match self.stark.preprocessed_commitment() {
+            Some(trusted) if trusted != derived => {
+                Err(VerificationError::PreprocessedCommitmentMismatch)
+            }
+            _ => Ok(derived),
+        }
+    }
+
+    fn verify_fri(
+        mut commitments: CommitmentsStage<S>,
+        fri_proof: FriProof<S::Fq, S::Digest, S::MerkleTree>,
+        pow_nonce: u64,
+    ) -> Result<FriStage<S>, VerificationError> {
+        let air = &commitments.air;
+        let options = air.options();
+        let public_coin = &mut commitments.public_coin;
+        let fri_verifier = FriVerifier::<S::Fq, S::Digest, S::MerkleTree>::new(
+            public_coin,
+            options.into_fri_options(),
+            fri_proof,
+            air.trace_len() - 1,
+        )?;
+
+        if options.grinding_factor != 0 {
+            if !options
+                .pow_hash
+                .verify(public_coin, options.grinding_factor, pow_nonce)
+            {
+                return Err(VerificationError::FriProofOfWork {
+                    grinding_factor: options.grinding_factor,
+                    nonce: pow_nonce,
+                });
+            }
+            public_coin.reseed_with_int(pow_nonce);
+        }
+
+        let lde_domain_size = air.trace_len() * air.lde_blowup_factor();
+        let query_positions = Vec::from_iter(options.query_sampling.draw(
+            public_coin,
+            options.num_queries.into(),
+            lde_domain_size,
+        ));
+
+        Ok(FriStage {
+            commitments,
+            fri_verifier,
+            query_positions,
+        })
+    }
+
+    fn verify_queries(
+        fri: FriStage<S>,
+        trace_queries: &Queries<S>,
+    ) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+        let FriStage {
+            commitments,
+            fri_verifier,
+            query_positions,
+        } = fri;
+        let CommitmentsStage {
+            air,
+            air_challenges,
+            air_hints,
+            preprocessed_commitment,
+            base_trace_commitment,
+            extension_trace_commitment,
+            aux_trace_commitments,
+            composition_trace_commitment,
+            trace_ood_eval_map,
+            composition_trace_ood_evals,
+            deep_coeffs,
+            z,
+            ..
+        } = commitments;
+
+        trace_queries.verify(
+            &air,
+            &base_trace_commitment,
+            extension_trace_commitment.as_ref(),
+            &composition_trace_commitment,
             &query_positions,
+        )?;
+        trace_queries.verify_aux_segments(&air, &aux_trace_commitments, &query_positions)?;
+        if let Some(preprocessed_commitment) = &preprocessed_commitment {
+            trace_queries.verify_preprocessed(preprocessed_commitment, &query_positions)?;
+        }
+        let base_trace_rows = trace_queries.all_base_rows();
+        let base_trace_rows = base_trace_rows
+            .iter()
+            .map(Vec::as_slice)
+            .collect::<Vec<_>>();
+        let extension_trace_rows = trace_queries.extension_trace_rows();
+        let composition_trace_rows = trace_queries.composition_trace_rows(&air);
+
+        let deep_evaluations = deep_composition_evaluations(
+            &air,
+            &query_positions,
+            &deep_coeffs,
+            &base_trace_rows,
             &extension_trace_rows,
-            trace_queries.extension_trace_proof.unwrap(),
-        )
-        .map_err(|_| ExtensionTraceQueryDoesNotMatchCommitment)?;
-    }
+            &composition_trace_rows,
+            &trace_ood_eval_map,
+            &composition_trace_ood_evals,
+            z,
+        );
+
+        let fri_alphas = fri_verifier.layer_alphas.clone();
+        fri_verifier.verify(&query_positions, &deep_evaluations)?;
 
-    // composition trace positions
-    S::MerkleTree::verify_rows(
-        &composition_trace_commitment,
-        &query_positions,
-        &composition_trace_rows,
-        trace_queries.composition_trace_proof,
-    )
-    .map_err(|_| CompositionTraceQueryDoesNotMatchCommitment)?;
-
-    let deep_evaluations = deep_composition_evaluations(
-        &air,
-        &query_positions,
-        &deep_coeffs,
-        &base_trace_rows,
-        &extension_trace_rows,
-        &composition_trace_rows,
-        &trace_ood_eval_map,
-        &composition_trace_ood_evals,
-        z,
-    );
-
-    let fri_alphas = fri_verifier.layer_alphas.clone();
-    fri_verifier.verify(&query_positions, &deep_evaluations)?;
-
-    Ok(VerifierChannelArtifacts {
-        air_challenges,
-        air_hints,
-        fri_alphas,
-        query_positions,
-    })
+        Ok(VerifierChannelArtifacts {
+            air_challenges,
+            air_hints,
+            fri_alphas,
+            query_positions,
+        })
+    }
 }
 
 /// Errors that are returned during verification of a STARK proof
 #[derive(Debug, Snafu)]
 pub enum VerificationError {
-    #[snafu(display("proof params do not satisfy security requirements"))]
-    InvalidProofSecurity,
-    #[snafu(display("constraint evaluations at the out-of-domain point are inconsistent"))]
-    InconsistentOodConstraintEvaluations,
+    #[snafu(display(
+        "proof has `{security_level}` bits of security but `{required}` are required"
+    ))]
+    InvalidProofSecurity { security_level: u32, required: u32 },
+    #[snafu(display(
+        "constraint evaluation at the out-of-domain point is `{calculated}` but the composition \
+         trace evaluates to `{provided}`"
+    ))]
+    InconsistentOodConstraintEvaluations {
+        calculated: String,
+        provided: String,
+    },
     #[snafu(context(false))]
     #[snafu(display("fri verification failed: {source}"))]
     FriVerification { source: fri::VerificationError },
-    #[snafu(display("query does not resolve to the base trace commitment"))]
-    BaseTraceQueryDoesNotMatchCommitment,
-    #[snafu(display("query does not resolve to the extension trace commitment"))]
-    ExtensionTraceQueryDoesNotMatchCommitment,
-    #[snafu(display("query does not resolve to the composition trace commitment"))]
-    CompositionTraceQueryDoesNotMatchCommitment,
-    #[snafu(display("insufficient proof of work on fri commitments"))]
-    FriProofOfWork,
+    #[snafu(display("queries do not resolve to the {trace} trace commitment: {source}"))]
+    TraceQueryDoesNotMatchCommitment {
+        trace: CommittedTrace,
+        source: merkle::Error,
+    },
+    #[snafu(display(
+        "nonce `{nonce}` is not a valid proof of work with grinding factor `{grinding_factor}`"
+    ))]
+    FriProofOfWork { grinding_factor: u8, nonce: u64 },
+    #[snafu(display("proof frame was received out of order"))]
+    UnexpectedProofFrame,
+    #[snafu(display("invalid proof options: {source}"))]
+    InvalidOptions { source: InvalidProofOptions },
+    #[snafu(display("AIR has preprocessed columns but no preprocessed commitment was provided"))]
+    MissingPreprocessedCommitment,
+    #[snafu(display(
+        "preprocessed columns have `{rows}` rows and `{columns}` columns which doesn't match the \
+         AIR"
+    ))]
+    InvalidPreprocessedColumns { rows: usize, columns: usize },
+    #[snafu(display(
+        "preprocessed commitment doesn't match the commitment derived from the preprocessed \
+         columns"
+    ))]
+    PreprocessedCommitmentMismatch,
+    #[snafu(display(
+        "proof commits to `{actual}` auxiliary segments but the AIR has `{expected}`"
+    ))]
+    AuxSegmentCountMismatch { expected: usize, actual: usize },
+    #[snafu(context(false))]
+    #[snafu(display("failed to decode proof: {source}"))]
+    ProofDecoding { source: compression::Error },
+}
+
+/// Trace the prover commits to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommittedTrace {
+    Base,
+    Extension,
+    Composition,
+    Preprocessed,
+    /// Auxiliary segment other than the first (see
+    /// [`AirConfig::AUX_SEGMENTS`])
+    AuxSegment(usize),
+}
+
+impl Display for CommittedTrace {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Base => write!(f, "base"),
+            Self::Extension => write!(f, "extension"),
+            Self::Composition => write!(f, "composition"),
+            Self::Preprocessed => write!(f, "preprocessed"),
+            Self::AuxSegment(segment) => write!(f, "auxiliary segment {segment}"),
+        }
+    }
 }
 
 pub fn ood_constraint_evaluation<A: AirConfig>(
@@ -212,8 +737,8 @@ pub fn ood_constraint_evaluation<A: AirConfig>(
 ) -> A::Fq {
     use AlgebraicItem::*;
     use CompositionItem::*;
-    air.composition_constraint()
-        .graph_eval(&mut |leaf| match leaf {
+    air.compiled_composition_constraint()
+        .eval(&mut |leaf| match leaf {
             Item(X) => FieldVariant::Fq(x),
             &Item(Constant(v)) => v,
             &Item(Challenge(i)) => FieldVariant::Fq(challenges[i]),
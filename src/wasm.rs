@@ -0,0 +1,84 @@
+//! WebAssembly bindings for verifying proofs in browsers and light clients.
+//!
+//! [`wasm_verifier!`](crate::wasm_verifier) exports a
+//! `verify(proof_bytes, public_input_bytes) -> bool` function through
+//! [wasm-bindgen](https://rustwasm.github.io/docs/wasm-bindgen/) for a
+//! [`WasmStark`]. Proofs are encoded with
+//! [`encode_proof`](crate::compression::encode_proof) and public inputs with
+//! [`CanonicalSerialize::serialize_compressed`].
+//!
+//! Verification doesn't measure time or spawn threads so it runs on
+//! `wasm32-unknown-unknown` where `std::time::Instant` and `std::thread`
+//! panic. The `parallel` feature spawns threads and can't be used there.
+//! Build bundles without default features so the prover isn't included.
+//!
+//! ```ignore
+//! impl WasmStark for FibClaim {
+//!     const REQUIRED_SECURITY_BITS: u32 = 100;
+//!
+//!     fn from_public_inputs(public_inputs: Fp) -> Self {
+//!         FibClaim(public_inputs)
+//!     }
+//! }
+//!
+//! ministark::wasm_verifier!(FibClaim);
+//! ```
+//!
+//! [`CanonicalSerialize::serialize_compressed`]: ark_serialize::CanonicalSerialize::serialize_compressed
+use crate::air::AirConfig;
+use crate::compression::Compressor;
+use crate::stark::Stark;
+use ark_serialize::CanonicalDeserialize;
+pub use wasm_bindgen;
+
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+compile_error!("the `parallel` feature spawns threads which isn't supported on wasm32");
+
+/// A STARK that can be constructed from its public inputs alone. Verifiers
+/// exported by [`wasm_verifier!`](crate::wasm_verifier) only receive the
+/// serialized public inputs.
+pub trait WasmStark: Stark {
+    /// Bits of security a proof must have to be accepted
+    const REQUIRED_SECURITY_BITS: u32;
+
+    fn from_public_inputs(public_inputs: <Self::AirConfig as AirConfig>::PublicInputs) -> Self;
+}
+
+/// Verifies a proof encoded with
+/// [`encode_proof`](crate::compression::encode_proof) for the serialized
+/// public inputs. Returns false if either can't be decoded or the proof is
+/// invalid. Proofs compressed with a codec other than the ones in
+/// `decompressors` are rejected.
+pub fn verify<S: WasmStark>(
+    proof_bytes: &[u8],
+    public_input_bytes: &[u8],
+    decompressors: &[&dyn Compressor],
+) -> bool {
+    let public_inputs =
+        <S::AirConfig as AirConfig>::PublicInputs::deserialize_compressed(public_input_bytes);
+    public_inputs.is_ok_and(|public_inputs| {
+        S::from_public_inputs(public_inputs)
+            .verify_bytes(proof_bytes, S::REQUIRED_SECURITY_BITS, decompressors)
+            .is_ok()
+    })
+}
+
+/// Exports `verify(proof_bytes: &[u8], public_input_bytes: &[u8]) -> bool`
+/// through wasm-bindgen for a [`WasmStark`](crate::wasm::WasmStark).
+/// Decompressors for compressed proofs can be listed after the STARK.
+#[macro_export]
+macro_rules! wasm_verifier {
+    ($stark:ty $(, $decompressor:expr)* $(,)?) => {
+        /// Returns true if the proof is valid for the public inputs
+        #[$crate::wasm::wasm_bindgen::prelude::wasm_bindgen(
+            wasm_bindgen = $crate::wasm::wasm_bindgen
+        )]
+        pub fn verify(proof_bytes: &[u8], public_input_bytes: &[u8]) -> bool {
+            $crate::wasm::verify::<$stark>(
+                proof_bytes,
+                public_input_bytes,
+                &[$(&$decompressor),*],
+            )
+        }
+    };
+}
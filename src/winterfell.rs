@@ -0,0 +1,606 @@
+//! Running [Winterfell](https://github.com/facebook/winterfell) AIRs on the
+//! ministark prover.
+//!
+//! [`WinterfellAirConfig`] turns an implementation of [`winter_air::Air`] into
+//! a ministark [`AirConfig`] so existing Winterfell circuits can be
+//! benchmarked on the GPU prover without a rewrite. The AIR's base field must
+//! have an arkworks equivalent (see [`ArkField`]).
+//!
+//! Winterfell evaluates constraints numerically while ministark needs them
+//! symbolically. [`Air::evaluate_transition`] is called once with symbolic
+//! field elements that record the operations applied to them and the
+//! resulting expressions become the AIR's transition constraints.
+//!
+//! ministark derives an AIR's constraints from the trace length alone so the
+//! AIR is instantiated with default public inputs to derive them. Transition
+//! constraints and the cells pinned by assertions can't depend on the public
+//! inputs. The values of assertions can. They are the public inputs of the
+//! ministark AIR (see [`WinterfellAirConfig::public_inputs`]) and referenced
+//! by the assertions as hints.
+//!
+//! Auxiliary trace segments (Winterfell's `evaluate_aux_transition`) aren't
+//! supported. Constraint degrees are derived from the constraints rather than
+//! taken from the AIR's [`AirContext`](winter_air::AirContext).
+//!
+//! ```ignore
+//! struct FibClaim(BaseElement);
+//!
+//! impl Stark for FibClaim {
+//!     type Fp = Fp;
+//!     type Fq = Fp;
+//!     type AirConfig = WinterfellAirConfig<FibAir, 2>;
+//!     type Trace = WinterfellTrace<BaseElement>;
+//!     // ...
+//!
+//!     fn get_public_inputs(&self) -> Vec<Fp> {
+//!         WinterfellAirConfig::<FibAir, 2>::public_inputs(TRACE_LEN, self.0).unwrap()
+//!     }
+//! }
+//! ```
+use crate::air::periodic_column;
+use crate::air::AirConfig;
+use crate::challenges::Challenges;
+use crate::constraints;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ConstraintGroup;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::hints::Hints;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use crate::Trace;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt::Display;
+use core::fmt::Formatter;
+use core::marker::PhantomData;
+use core::ops::Add;
+use core::ops::AddAssign;
+use core::ops::Div;
+use core::ops::DivAssign;
+use core::ops::Mul;
+use core::ops::MulAssign;
+use core::ops::Neg;
+use core::ops::Sub;
+use core::ops::SubAssign;
+use core::slice;
+use ministark_gpu::GpuFftField;
+use num_traits::Pow;
+use snafu::ensure;
+use snafu::Snafu;
+use winter_air::Air;
+use winter_air::Assertion;
+use winter_air::EvaluationFrame;
+use winter_air::FieldExtension;
+use winter_air::TraceInfo;
+use winter_math::ExtensionOf;
+use winter_math::FieldElement;
+use winter_math::StarkField;
+use winter_utils::AsBytes;
+use winter_utils::ByteReader;
+use winter_utils::ByteWriter;
+use winter_utils::Deserializable;
+use winter_utils::DeserializationError;
+use winter_utils::Randomizable;
+use winter_utils::Serializable;
+
+/// Winterfell field with an arkworks equivalent that the ministark prover
+/// runs over
+pub trait ArkField: StarkField + 'static {
+    type Fp: GpuFftField<FftField = Self::Fp> + FftField + StarkExtensionOf<Self::Fp>;
+
+    fn to_ark(self) -> Self::Fp;
+}
+
+impl ArkField for winter_math::fields::f64::BaseElement {
+    type Fp = ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    fn to_ark(self) -> Self::Fp {
+        self.as_int().into()
+    }
+}
+
+/// Blowup factor of the options Winterfell AIRs are instantiated with. The
+/// largest Winterfell allows so AIRs with high degree constraints can be
+/// instantiated. ministark uses its own [`ProofOptions`](crate::ProofOptions).
+const BLOWUP_FACTOR: usize = 128;
+
+/// Shortest trace Winterfell AIRs can be instantiated with
+const MIN_TRACE_LEN: usize = 8;
+
+/// Instantiates a Winterfell AIR for a trace of length `trace_len`
+fn new_air<A: Air, const TRACE_WIDTH: usize>(
+    trace_len: usize,
+    public_inputs: A::PublicInputs,
+) -> A {
+    let options = winter_air::ProofOptions::new(1, BLOWUP_FACTOR, 0, FieldExtension::None, 2, 7);
+    A::new(
+        TraceInfo::new(TRACE_WIDTH, trace_len),
+        public_inputs,
+        options,
+    )
+}
+
+/// AIR that enforces the constraints of a Winterfell [`Air`] over a trace
+/// with `TRACE_WIDTH` columns. Public inputs are the values of the
+/// Winterfell AIR's assertions (see [`WinterfellAirConfig::public_inputs`]).
+pub struct WinterfellAirConfig<A, const TRACE_WIDTH: usize>(PhantomData<A>);
+
+impl<A, const TRACE_WIDTH: usize> WinterfellAirConfig<A, TRACE_WIDTH>
+where
+    A: Air + 'static,
+    A::BaseField: ArkField,
+    A::PublicInputs: Default,
+{
+    /// Returns the public inputs of a proof of a trace of length `trace_len`
+    /// i.e. the values of the assertions of the Winterfell AIR instantiated
+    /// with `public_inputs`
+    ///
+    /// # Errors
+    /// Returns an error if the cells pinned by the assertions depend on the
+    /// public inputs
+    pub fn public_inputs(
+        trace_len: usize,
+        public_inputs: A::PublicInputs,
+    ) -> Result<Vec<<A::BaseField as ArkField>::Fp>, Error> {
+        let assertions = new_air::<A, TRACE_WIDTH>(trace_len, public_inputs).get_assertions();
+        let default_assertions =
+            new_air::<A, TRACE_WIDTH>(trace_len, A::PublicInputs::default()).get_assertions();
+        ensure!(
+            asserted_cells(&assertions) == asserted_cells(&default_assertions),
+            AssertedCellsSnafu
+        );
+        Ok(assertions
+            .iter()
+            .flat_map(|assertion| assertion.values().iter().map(|&v| v.to_ark()))
+            .collect())
+    }
+}
+
+/// Errors running a Winterfell AIR on the ministark prover
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum Error {
+    #[snafu(display("asserted cells can't depend on the public inputs"))]
+    AssertedCells,
+}
+
+/// Returns the column, first step, stride and number of values of each
+/// assertion
+fn asserted_cells<E: FieldElement>(
+    assertions: &[Assertion<E>],
+) -> Vec<(usize, usize, usize, usize)> {
+    let cells = assertions.iter().map(|assertion| {
+        let num_values = assertion.values().len();
+        (
+            assertion.column(),
+            assertion.first_step(),
+            assertion.stride(),
+            num_values,
+        )
+    });
+    cells.collect()
+}
+
+impl<A, const TRACE_WIDTH: usize> AirConfig for WinterfellAirConfig<A, TRACE_WIDTH>
+where
+    A: Air + 'static,
+    A::BaseField: ArkField,
+    A::PublicInputs: Default,
+{
+    const NUM_BASE_COLUMNS: usize = TRACE_WIDTH;
+    type Fp = <A::BaseField as ArkField>::Fp;
+    type Fq = Self::Fp;
+    type PublicInputs = Vec<Self::Fp>;
+
+    fn constraint_groups(
+        trace_len: usize,
+    ) -> Vec<ConstraintGroup<FieldVariant<Self::Fp, Self::Fp>>> {
+        let air = new_air::<A, TRACE_WIDTH>(trace_len, A::PublicInputs::default());
+        let num_periodic_columns = air.get_periodic_column_values().len();
+        with_symbols(|| {
+            let current = (0..TRACE_WIDTH).map(|i| Symbol::new(i.curr()));
+            let next = (0..TRACE_WIDTH).map(|i| Symbol::new(i.next()));
+            let frame = EvaluationFrame::from_rows(current.collect(), next.collect());
+            let periodic_values = (0..num_periodic_columns)
+                .map(|i| Symbol::new(periodic_column::<Self>(i)))
+                .collect::<Vec<_>>();
+            let mut result = vec![Symbol::ZERO; air.context().num_main_transition_constraints()];
+            air.evaluate_transition(&frame, &periodic_values, &mut result);
+            let constraints = result.into_iter().map(Symbol::into_expr).collect();
+            vec![ConstraintGroup::transitions(trace_len, constraints)]
+        })
+    }
+
+    fn assertions(
+        trace_len: usize,
+    ) -> Vec<constraints::Assertion<FieldVariant<Self::Fp, Self::Fp>>> {
+        let air = new_air::<A, TRACE_WIDTH>(trace_len, A::PublicInputs::default());
+        let mut hints = (0..).map(|i| Expr::from(AlgebraicItem::Hint(i)));
+        let mut assertions = Vec::new();
+        for assertion in air.get_assertions() {
+            let (column, first_step, stride) = (
+                assertion.column(),
+                assertion.first_step(),
+                assertion.stride(),
+            );
+            match assertion.values().len() {
+                1 if stride == 0 => assertions.push(constraints::Assertion::single(
+                    column,
+                    first_step,
+                    hints.next().unwrap(),
+                )),
+                1 => assertions.push(constraints::Assertion::periodic(
+                    column,
+                    first_step,
+                    stride,
+                    hints.next().unwrap(),
+                )),
+                // sequences are asserted one row at a time
+                num_values => {
+                    assert_eq!(
+                        num_values * stride,
+                        trace_len,
+                        "sequence must have a value for every {stride} rows"
+                    );
+                    for i in 0..num_values {
+                        assertions.push(constraints::Assertion::single(
+                            column,
+                            first_step + i * stride,
+                            hints.next().unwrap(),
+                        ));
+                    }
+                }
+            }
+        }
+        assertions
+    }
+
+    fn periodic_columns() -> Vec<Vec<Self::Fp>> {
+        // periodic columns can't depend on the trace length either
+        let air = new_air::<A, TRACE_WIDTH>(MIN_TRACE_LEN, A::PublicInputs::default());
+        air.get_periodic_column_values()
+            .into_iter()
+            .map(|column| column.into_iter().map(ArkField::to_ark).collect())
+            .collect()
+    }
+
+    fn gen_hints(
+        trace_len: usize,
+        public_inputs: &Vec<Self::Fp>,
+        _challenges: &Challenges<Self::Fp>,
+    ) -> Hints<Self::Fp> {
+        let air = new_air::<A, TRACE_WIDTH>(trace_len, A::PublicInputs::default());
+        let num_values = air
+            .get_assertions()
+            .iter()
+            .map(|assertion| assertion.values().len())
+            .sum::<usize>();
+        assert_eq!(
+            public_inputs.len(),
+            num_values,
+            "expected {num_values} public input elements"
+        );
+        Hints::new(public_inputs.iter().copied().enumerate().collect())
+    }
+}
+
+/// Execution trace of a Winterfell AIR. Matches Winterfell's `TraceTable`.
+pub struct WinterfellTrace<B: ArkField>(Matrix<B::Fp>);
+
+impl<B: ArkField> WinterfellTrace<B> {
+    /// Creates a trace from its columns
+    ///
+    /// # Panics
+    /// Panics if the columns have different lengths
+    pub fn init(columns: Vec<Vec<B>>) -> Self {
+        let columns = columns
+            .into_iter()
+            .map(|column| {
+                let mut res = Vec::with_capacity_in(column.len(), GpuAllocator);
+                res.extend(column.into_iter().map(ArkField::to_ark));
+                res
+            })
+            .collect();
+        Self(Matrix::new(columns))
+    }
+}
+
+impl<B: ArkField> Trace for WinterfellTrace<B> {
+    type Fp = B::Fp;
+    type Fq = B::Fp;
+
+    fn base_columns(&self) -> &Matrix<B::Fp> {
+        &self.0
+    }
+}
+
+type SymbolicExpr<F> = Expr<AlgebraicItem<FieldVariant<F, F>>>;
+
+thread_local! {
+    /// Handles and expressions of the symbolic elements in use on this
+    /// thread. Holds pairs of a handle and a [`SymbolicExpr`] of the field
+    /// constraints are being evaluated over.
+    static SYMBOLS: RefCell<Vec<Box<dyn Any>>> = RefCell::new(Vec::new());
+}
+
+/// Runs `f` with its own expressions for symbolic elements
+fn with_symbols<R>(f: impl FnOnce() -> R) -> R {
+    let outer = SYMBOLS.with(RefCell::take);
+    let res = f();
+    SYMBOLS.with(|symbols| *symbols.borrow_mut() = outer);
+    res
+}
+
+/// Element constraints are evaluated with to record them symbolically.
+///
+/// An element holds a field element: either a constant, which is folded, or
+/// the handle of an expression in [`SYMBOLS`]. Handles are consecutive powers
+/// of the field's generator starting from a pseudorandom power so they don't
+/// collide with the constants of an AIR. Elements have the layout of
+/// Winterfell's field elements and are [`Copy`], [`Send`] and [`Sync`] like
+/// them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(transparent)]
+struct Symbol<B>(B);
+
+impl<B: ArkField> Symbol<B> {
+    fn new(expr: SymbolicExpr<B::Fp>) -> Self {
+        SYMBOLS.with(|symbols| {
+            let mut symbols = symbols.borrow_mut();
+            let handle = match symbols.last() {
+                Some(last) => Self::downcast(&**last).0 * B::GENERATOR,
+                None => B::GENERATOR.exp(0x9e37_79b9_7f4a_7c15u64.into()),
+            };
+            symbols.push(Box::new((handle, expr)));
+            Self(handle)
+        })
+    }
+
+    fn downcast(symbol: &dyn Any) -> &(B, SymbolicExpr<B::Fp>) {
+        symbol.downcast_ref().unwrap()
+    }
+
+    /// Returns the expression of the element or `None` if it's a constant
+    fn expr(self) -> Option<SymbolicExpr<B::Fp>> {
+        SYMBOLS.with(|symbols| {
+            symbols.borrow().iter().rev().find_map(|symbol| {
+                let (handle, expr) = Self::downcast(&**symbol);
+                (*handle == self.0).then(|| expr.clone())
+            })
+        })
+    }
+
+    fn into_expr(self) -> SymbolicExpr<B::Fp> {
+        self.expr().unwrap_or_else(|| self.constant_expr())
+    }
+
+    fn constant_expr(self) -> SymbolicExpr<B::Fp> {
+        AlgebraicItem::Constant(FieldVariant::Fp(self.0.to_ark())).into()
+    }
+
+    fn unary_op(
+        self,
+        fold: impl FnOnce(B) -> B,
+        op: impl FnOnce(SymbolicExpr<B::Fp>) -> SymbolicExpr<B::Fp>,
+    ) -> Self {
+        match self.expr() {
+            Some(expr) => Self::new(op(expr)),
+            None => Self(fold(self.0)),
+        }
+    }
+
+    fn binary_op(
+        self,
+        rhs: Self,
+        fold: impl FnOnce(B, B) -> B,
+        op: impl FnOnce(SymbolicExpr<B::Fp>, SymbolicExpr<B::Fp>) -> SymbolicExpr<B::Fp>,
+    ) -> Self {
+        match (self.expr(), rhs.expr()) {
+            (None, None) => Self(fold(self.0, rhs.0)),
+            (a, b) => Self::new(op(
+                a.unwrap_or_else(|| self.constant_expr()),
+                b.unwrap_or_else(|| rhs.constant_expr()),
+            )),
+        }
+    }
+}
+
+impl<B: ArkField> Default for Symbol<B> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl<B: ArkField> Display for Symbol<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.expr() {
+            Some(_) => write!(f, "symbol({})", self.0),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl<B: ArkField> From<B> for Symbol<B> {
+    fn from(value: B) -> Self {
+        Self(value)
+    }
+}
+
+impl<B: ArkField> From<u32> for Symbol<B> {
+    fn from(value: u32) -> Self {
+        Self(value.into())
+    }
+}
+
+impl<B: ArkField> From<u16> for Symbol<B> {
+    fn from(value: u16) -> Self {
+        Self(value.into())
+    }
+}
+
+impl<B: ArkField> From<u8> for Symbol<B> {
+    fn from(value: u8) -> Self {
+        Self(value.into())
+    }
+}
+
+impl<'a, B: ArkField> TryFrom<&'a [u8]> for Symbol<B> {
+    type Error = <B as TryFrom<&'a [u8]>>::Error;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        B::try_from(bytes).map(Self)
+    }
+}
+
+impl<B: ArkField> Add for Symbol<B> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.binary_op(rhs, |a, b| a + b, |a, b| a + b)
+    }
+}
+
+impl<B: ArkField> Sub for Symbol<B> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.binary_op(rhs, |a, b| a - b, |a, b| a - b)
+    }
+}
+
+impl<B: ArkField> Mul for Symbol<B> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.binary_op(rhs, |a, b| a * b, |a, b| a * b)
+    }
+}
+
+impl<B: ArkField> Div for Symbol<B> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        self.binary_op(rhs, |a, b| a / b, |a, b| a / b)
+    }
+}
+
+impl<B: ArkField> Neg for Symbol<B> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.unary_op(|a| -a, |a| -a)
+    }
+}
+
+impl<B: ArkField> AddAssign for Symbol<B> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<B: ArkField> SubAssign for Symbol<B> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<B: ArkField> MulAssign for Symbol<B> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<B: ArkField> DivAssign for Symbol<B> {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl<B: ArkField> ExtensionOf<B> for Symbol<B> {
+    fn mul_base(self, other: B) -> Self {
+        self * Self(other)
+    }
+}
+
+// Symbolic elements are only used to record constraints. Operations that need
+// the value of an element see the handle of a symbolic element.
+
+impl<B: ArkField> AsBytes for Symbol<B> {
+    fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl<B: ArkField> Randomizable for Symbol<B> {
+    const VALUE_SIZE: usize = B::VALUE_SIZE;
+
+    fn from_random_bytes(source: &[u8]) -> Option<Self> {
+        B::from_random_bytes(source).map(Self)
+    }
+}
+
+impl<B: ArkField> Serializable for Symbol<B> {
+    fn write_into<W: ByteWriter>(&self, target: &mut W) {
+        self.0.write_into(target);
+    }
+}
+
+impl<B: ArkField> Deserializable for Symbol<B> {
+    fn read_from<R: ByteReader>(source: &mut R) -> Result<Self, DeserializationError> {
+        B::read_from(source).map(Self)
+    }
+}
+
+impl<B: ArkField> FieldElement for Symbol<B> {
+    type PositiveInteger = u64;
+    type BaseField = B;
+
+    const EXTENSION_DEGREE: usize = 1;
+    const ELEMENT_BYTES: usize = B::ELEMENT_BYTES;
+    const IS_CANONICAL: bool = B::IS_CANONICAL;
+    const ZERO: Self = Self(B::ZERO);
+    const ONE: Self = Self(B::ONE);
+
+    fn exp(self, power: u64) -> Self {
+        self.unary_op(
+            |a| a.exp(power.into()),
+            |a| a.pow(usize::try_from(power).unwrap()),
+        )
+    }
+
+    fn inv(self) -> Self {
+        Self::ONE / self
+    }
+
+    fn conjugate(&self) -> Self {
+        *self
+    }
+
+    fn base_element(&self, i: usize) -> B {
+        assert_eq!(i, 0, "element index must be 0");
+        self.0
+    }
+
+    fn slice_as_base_elements(elements: &[Self]) -> &[B] {
+        // SAFETY: `Symbol<B>` is a transparent wrapper of `B`
+        unsafe { slice::from_raw_parts(elements.as_ptr().cast(), elements.len()) }
+    }
+
+    fn slice_from_base_elements(elements: &[B]) -> &[Self] {
+        // SAFETY: `Symbol<B>` is a transparent wrapper of `B`
+        unsafe { slice::from_raw_parts(elements.as_ptr().cast(), elements.len()) }
+    }
+
+    fn elements_as_bytes(elements: &[Self]) -> &[u8] {
+        B::elements_as_bytes(Self::slice_as_base_elements(elements))
+    }
+
+    unsafe fn bytes_as_elements(bytes: &[u8]) -> Result<&[Self], DeserializationError> {
+        B::bytes_as_elements(bytes).map(Self::slice_from_base_elements)
+    }
+}
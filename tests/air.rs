@@ -0,0 +1,409 @@
+#![feature(allocator_api)]
+#![cfg(feature = "prover")]
+mod common;
+
+use ark_ff::Field;
+use ark_ff::One;
+use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use common::memory_accesses;
+use common::memory_columns;
+use common::square_column;
+use common::ColumnsTrace;
+use common::SquareAirConfig;
+use common::TRACE_LEN;
+use ministark::air::AirConfig;
+use ministark::air_composition::AirComposition;
+use ministark::air_composition::Subdomain;
+use ministark::air_composition::SubdomainTrace;
+use ministark::air_composition::TraceComposition;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ConstraintGroup;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::debug;
+use ministark::expression::Expr;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::permutation::PermutationArgument;
+use ministark::preprocessed::PreprocessedColumns;
+use ministark::prover::ProvingError;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::utils::SerdeOutput;
+use ministark::verifier::VerificationError;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::Proof;
+use ministark::ProofOptions;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+use sha2::Sha256;
+
+#[test]
+fn preprocessed_columns_are_committed_once() {
+    struct StepAirConfig;
+
+    impl AirConfig for StepAirConfig {
+        const NUM_BASE_COLUMNS: usize = 2;
+        const NUM_PREPROCESSED_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        // column 1 is preprocessed. Column 0 increases by its square each row
+        fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+            vec![
+                ConstraintGroup::first_row(vec![0.curr()]),
+                ConstraintGroup::transitions(
+                    trace_len,
+                    vec![0.next() - 0.curr() - 1.curr() * 1.curr()],
+                ),
+            ]
+        }
+    }
+
+    struct StepClaim {
+        preprocessed: Option<PreprocessedColumns<StepClaim>>,
+        commitment: SerdeOutput<Sha256>,
+    }
+
+    impl Stark for StepClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = StepAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = ();
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<StepAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"preprocessed"))
+        }
+
+        fn generate_trace(&self, _: ()) -> ColumnsTrace {
+            let steps = self.preprocessed.as_ref().unwrap().columns();
+            let mut sum = Fp::zero();
+            let column = steps[0]
+                .iter()
+                .map(|step| {
+                    let value = sum;
+                    sum += step.square();
+                    value
+                })
+                .collect::<Vec<Fp>>();
+            ColumnsTrace(Matrix::new(vec![column.to_vec_in(GpuAllocator)]))
+        }
+
+        fn preprocessed_columns(&self) -> Option<&PreprocessedColumns<Self>> {
+            self.preprocessed.as_ref()
+        }
+
+        fn preprocessed_commitment(&self) -> Option<SerdeOutput<Sha256>> {
+            Some(self.commitment.clone())
+        }
+    }
+
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let preprocess = |steps: fn(u64) -> u64| {
+        let column = (0..64).map(|i| Fp::from(steps(i))).collect::<Vec<Fp>>();
+        PreprocessedColumns::<StepClaim>::new(
+            Matrix::new(vec![column.to_vec_in(GpuAllocator)]),
+            options,
+        )
+    };
+    let preprocessed = preprocess(|i| i % 3);
+    let commitment = preprocessed.commitment();
+    let prover = StepClaim {
+        preprocessed: Some(preprocessed),
+        commitment: commitment.clone(),
+    };
+    let proof = pollster::block_on(prover.prove(options, ())).unwrap();
+    assert!(proof
+        .trace_queries
+        .base_trace_rows()
+        .iter()
+        .all(|row| row.len() == 1));
+
+    // verifiers only need the commitment
+    let verifier = StepClaim {
+        preprocessed: None,
+        commitment,
+    };
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    let proof = Proof::<StepClaim>::deserialize_compressed(&*bytes).unwrap();
+    verifier.verify(proof.clone(), 1).unwrap();
+
+    let other_verifier = StepClaim {
+        preprocessed: None,
+        commitment: preprocess(|i| i % 5).commitment(),
+    };
+    assert!(other_verifier.verify(proof.clone(), 1).is_err());
+
+    // auditors can re-derive the commitment from the raw columns
+    let columns = |steps: fn(u64) -> u64| preprocess(steps).columns().clone();
+    verifier
+        .verify_with_preprocessed_columns(proof.clone(), 1, &columns(|i| i % 3))
+        .unwrap();
+    assert!(matches!(
+        verifier.verify_with_preprocessed_columns(proof.clone(), 1, &columns(|i| i % 5)),
+        Err(VerificationError::PreprocessedCommitmentMismatch)
+    ));
+    assert!(matches!(
+        other_verifier.verify_with_preprocessed_columns(proof, 1, &columns(|i| i % 3)),
+        Err(VerificationError::PreprocessedCommitmentMismatch)
+    ));
+}
+
+#[test]
+fn composed_airs_are_proven_together() {
+    fn permutation() -> PermutationArgument {
+        PermutationArgument {
+            lhs: vec![0, 1],
+            rhs: vec![2, 3],
+            running_product: 4,
+            alpha: 0,
+            beta: 1,
+        }
+    }
+
+    struct MemoryTrace(Matrix<Fp>);
+
+    impl Trace for MemoryTrace {
+        type Fp = Fp;
+        type Fq = Fp;
+
+        fn base_columns(&self) -> &Matrix<Fp> {
+            &self.0
+        }
+
+        fn build_extension_columns(&self, challenges: &Challenges<Fp>) -> Option<Matrix<Fp>> {
+            Some(Matrix::new(vec![
+                permutation().running_product(&self.0, challenges)
+            ]))
+        }
+    }
+
+    struct MemoryAirConfig;
+
+    impl AirConfig for MemoryAirConfig {
+        const NUM_BASE_COLUMNS: usize = 4;
+        const NUM_EXTENSION_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+            permutation().constraint_groups(trace_len)
+        }
+    }
+
+    // memory and square AIRs followed by a second memory AIR whose challenges
+    // are numbered after the first
+    type ComposedAirConfig =
+        AirComposition<MemoryAirConfig, AirComposition<SquareAirConfig, MemoryAirConfig>>;
+    type ComposedTrace = TraceComposition<MemoryTrace, TraceComposition<ColumnsTrace, MemoryTrace>>;
+
+    struct ComposedClaim;
+
+    impl Stark for ComposedClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = ComposedAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = Vec<(u64, u64)>;
+        type Trace = ComposedTrace;
+
+        fn get_public_inputs(&self) -> ((), ((), ())) {
+            ((), ((), ()))
+        }
+
+        fn gen_public_coin(&self, _: &Air<ComposedAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"composition"))
+        }
+
+        fn generate_trace(&self, accesses: Vec<(u64, u64)>) -> ComposedTrace {
+            let reversed = accesses.iter().rev().copied().collect::<Vec<_>>();
+            compose(
+                memory_trace(&accesses),
+                square_trace(accesses.len()),
+                memory_trace(&reversed),
+            )
+        }
+    }
+
+    fn memory_trace(accesses: &[(u64, u64)]) -> MemoryTrace {
+        MemoryTrace(memory_columns(accesses))
+    }
+
+    fn square_trace(n: usize) -> ColumnsTrace {
+        ColumnsTrace(square_column(Fp::from(3u8), n))
+    }
+
+    fn compose(a: MemoryTrace, b: ColumnsTrace, c: MemoryTrace) -> ComposedTrace {
+        ComposedAirConfig::compose_traces(
+            a,
+            AirComposition::<SquareAirConfig, MemoryAirConfig>::compose_traces(b, c),
+        )
+    }
+
+    let accesses = memory_accesses();
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let air = Air::<ComposedAirConfig>::new(64, ((), ((), ())), options);
+    assert_eq!(air.num_challenges(), 4);
+    let proof = pollster::block_on(ComposedClaim.prove(options, accesses.clone())).unwrap();
+    ComposedClaim.verify(proof, 1).unwrap();
+
+    // breaking the permutation of the second memory AIR is caught by its
+    // translated constraints
+    let mut broken_memory_trace = memory_trace(&accesses);
+    broken_memory_trace.0 .0[2][5] += Fp::one();
+    let trace = compose(
+        memory_trace(&accesses),
+        square_trace(TRACE_LEN),
+        broken_memory_trace,
+    );
+    let challenges = Challenges::new([12345u32, 678, 91011, 1213].map(Fp::from).to_vec());
+    let extension_trace = trace.build_extension_columns(&challenges).unwrap();
+    let num_memory_constraints = permutation()
+        .constraint_groups::<Fp, Fp>(64)
+        .into_iter()
+        .map(|group| group.into_constraints().len())
+        .sum::<usize>();
+    assert!(matches!(
+        debug::default_validate_constraints(
+            &ComposedClaim,
+            &challenges,
+            &Hints::default(),
+            trace.base_columns(),
+            Some(&extension_trace),
+        ),
+        Err(ProvingError::UnsatisfiedConstraint { constraint, .. })
+            if constraint > num_memory_constraints
+    ));
+}
+
+#[test]
+fn short_tables_are_proven_over_their_own_subdomain() {
+    struct CounterAirConfig;
+
+    impl AirConfig for CounterAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+            vec![Constraint::new(
+                (0.next() - 0.curr() - one) * ((X - last_x) / (X.pow(trace_len) - one)),
+            )]
+        }
+    }
+
+    // fibonacci table with one eighth of the counter's rows
+    struct FibonacciAirConfig;
+
+    impl AirConfig for FibonacciAirConfig {
+        const NUM_BASE_COLUMNS: usize = 2;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+            let first_row = Expr::from(one) / (X - one);
+            let transition = (X - last_x) / (X.pow(trace_len) - one);
+            vec![
+                Constraint::named("first", (0.curr() - one) * &first_row),
+                Constraint::named("next", (0.next() - 1.curr()) * &transition),
+                Constraint::named("sum", (1.next() - 0.curr() - 1.curr()) * &transition),
+            ]
+        }
+    }
+
+    type ComposedAirConfig = AirComposition<CounterAirConfig, Subdomain<FibonacciAirConfig, 8>>;
+    type ComposedTrace = TraceComposition<ColumnsTrace, SubdomainTrace<ColumnsTrace>>;
+
+    struct ComposedClaim;
+
+    impl Stark for ComposedClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = ComposedAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = u64;
+        type Trace = ComposedTrace;
+
+        fn get_public_inputs(&self) -> ((), ()) {
+            ((), ())
+        }
+
+        fn gen_public_coin(&self, _: &Air<ComposedAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"subdomain"))
+        }
+
+        fn generate_trace(&self, first: u64) -> ComposedTrace {
+            compose(Fp::from(first))
+        }
+    }
+
+    fn compose(first_fibonacci: Fp) -> ComposedTrace {
+        let counter = (0..64u64).map(|i| [Fp::from(i)]);
+        let mut row = [first_fibonacci, Fp::one()];
+        let fibonacci = (0..8).map(|_| {
+            let current = row;
+            row = [row[1], row[0] + row[1]];
+            current
+        });
+        ComposedAirConfig::compose_traces(
+            ColumnsTrace(Matrix::from_row_iter(counter)),
+            Subdomain::<FibonacciAirConfig, 8>::extend_trace(ColumnsTrace(Matrix::from_row_iter(
+                fibonacci,
+            ))),
+        )
+    }
+
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(ComposedClaim.prove(options, 1)).unwrap();
+    ComposedClaim.verify(proof, 1).unwrap();
+
+    // the first row constraint of the fibonacci table only applies to the
+    // first row of each repetition of the table
+    let trace = compose(Fp::from(2u8));
+    assert_eq!(trace.base_columns().num_rows(), 64);
+    assert!(matches!(
+        debug::default_validate_constraints(
+            &ComposedClaim,
+            &Challenges::default(),
+            &Hints::default(),
+            trace.base_columns(),
+            None,
+        ),
+        Err(ProvingError::UnsatisfiedNamedConstraint { name, row: 0, .. }) if name == "first"
+    ));
+}
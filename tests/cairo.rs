@@ -0,0 +1,119 @@
+use ministark::cairo::parse_memory;
+use ministark::cairo::parse_trace;
+use ministark::cairo::CairoClaim;
+use ministark::cairo::CairoExecution;
+use ministark::cairo::CairoTrace;
+use ministark::cairo::RegisterState;
+use ministark::stark::Stark;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+use std::collections::BTreeMap;
+
+/// Memory of a run of the following program with `--proof_mode`:
+///
+/// ```text
+/// [ap] = 5, ap++;                   // address 1
+/// [ap] = [ap - 1] * [ap - 1], ap++;  // address 3
+/// jmp rel 0;                         // address 4
+/// ```
+///
+/// Execution starts with `ap = fp = 8` and the program's output is written to
+/// addresses 8 and 9.
+const MEMORY: [(u64, u64); 8] = [
+    (1, 0x4806_8001_7fff_8000),
+    (2, 5),
+    (3, 0x4850_7fff_7fff_8000),
+    (4, 0x0107_8001_7fff_7fff),
+    (5, 0),
+    (7, 0),
+    (8, 5),
+    (9, 25),
+];
+
+const PROGRAM_ADDRESSES: [u64; 5] = [1, 2, 3, 4, 5];
+
+fn registers() -> Vec<RegisterState> {
+    let state = |ap, fp, pc| RegisterState { ap, fp, pc };
+    let mut registers = vec![state(8, 8, 1), state(9, 8, 3)];
+    // `jmp rel 0` loops until the number of steps is a power of two
+    registers.resize(32, state(10, 8, 4));
+    registers
+}
+
+fn trace_bytes(registers: &[RegisterState]) -> Vec<u8> {
+    registers
+        .iter()
+        .flat_map(|r| [r.ap, r.fp, r.pc])
+        .flat_map(u64::to_le_bytes)
+        .collect()
+}
+
+fn memory_bytes(memory: &[(u64, u64)]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for &(address, value) in memory {
+        bytes.extend(address.to_le_bytes());
+        bytes.extend(value.to_le_bytes());
+        bytes.extend([0; 24]);
+    }
+    bytes
+}
+
+fn execution() -> CairoExecution {
+    CairoExecution {
+        registers: registers(),
+        memory: MEMORY
+            .into_iter()
+            .map(|(address, value)| (address, Fp::from(value)))
+            .collect(),
+    }
+}
+
+#[test]
+fn parses_cairo_run_output() {
+    let registers = registers();
+    assert_eq!(registers, parse_trace(&trace_bytes(&registers)).unwrap());
+    let memory = parse_memory(&memory_bytes(&MEMORY)).unwrap();
+    assert_eq!(execution().memory, memory);
+
+    assert!(parse_trace(&trace_bytes(&registers)[1..]).is_err());
+    assert!(parse_memory(&memory_bytes(&MEMORY)[..50]).is_err());
+    assert!(parse_memory(&memory_bytes(&[(1, 2), (1, 3)])).is_err());
+}
+
+#[test]
+fn rejects_invalid_executions() {
+    // the number of steps must be a power of two
+    let mut odd_length = execution();
+    odd_length.registers.pop();
+    assert!(CairoTrace::new(&odd_length, PROGRAM_ADDRESSES).is_err());
+
+    let mut missing_memory = execution();
+    missing_memory.memory = BTreeMap::from([(1, Fp::from(MEMORY[0].1))]);
+    assert!(CairoTrace::new(&missing_memory, PROGRAM_ADDRESSES).is_err());
+}
+
+#[test]
+fn cairo_program_proves_and_verifies() {
+    let options = ProofOptions::new(16, 16, 0, 2, 4);
+    let trace = CairoTrace::new(&execution(), PROGRAM_ADDRESSES).unwrap();
+    let public_inputs = trace.public_inputs().clone();
+    assert_eq!(1, public_inputs.initial_pc);
+    assert_eq!(4, public_inputs.final_pc);
+    assert_eq!(10, public_inputs.final_ap);
+    let claim = CairoClaim(public_inputs);
+    let proof = pollster::block_on(claim.prove(options, trace)).unwrap();
+    claim.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn proof_is_bound_to_public_memory() {
+    let options = ProofOptions::new(16, 16, 0, 2, 4);
+    let trace = CairoTrace::new(&execution(), PROGRAM_ADDRESSES).unwrap();
+    let mut public_inputs = trace.public_inputs().clone();
+    let claim = CairoClaim(public_inputs.clone());
+    let proof = pollster::block_on(claim.prove(options, trace)).unwrap();
+
+    // claim the program squares 6 instead of 5
+    public_inputs.public_memory[1].1 = Fp::from(6u8);
+    assert!(CairoClaim(public_inputs).verify(proof, 1).is_err());
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "prover")]
+mod common;
+
+use common::PoseidonSquareClaim;
+use common::TRACE_LEN;
+use ministark::random::PublicCoin;
+use ministark::stark::Stark;
+use ministark::Air;
+use ministark::ProofOptions;
+
+#[test]
+fn sponge_transcript_proves_and_verifies() {
+    let options = ProofOptions::new(16, 4, 8, 2, 4);
+    let proof = pollster::block_on(PoseidonSquareClaim.prove(options, ())).unwrap();
+    let mut invalid_proof = proof.clone();
+    invalid_proof.pow_nonce += 1;
+    assert!(PoseidonSquareClaim.verify(invalid_proof, 1).is_err());
+    PoseidonSquareClaim.verify(proof, 1).unwrap();
+
+    // absorbing anything changes subsequent challenges
+    let mut coin = PoseidonSquareClaim.gen_public_coin(&Air::new(TRACE_LEN, (), options));
+    let mut reseeded_coin = coin.clone();
+    reseeded_coin.reseed_with_int(0);
+    assert_ne!(coin.draw(), reseeded_coin.draw());
+}
+
+#[cfg(metal_gpu)]
+#[test]
+fn gpu_poseidon_transcript_matches_cpu_transcript() {
+    use ministark::hash::HashFn;
+    use ministark::poseidon::GpuPoseidonPublicCoin;
+    use ministark::poseidon::PoseidonHashFn;
+    use ministark::poseidon::PoseidonPublicCoin;
+    use ministark::random::draw_multiple;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use ministark_gpu::metal::MTLResourceOptions;
+
+    let seed = PoseidonHashFn::hash(*b"transcript");
+    let mut cpu_coin = PoseidonPublicCoin::<Fp>::new(seed);
+    let mut gpu_coin = GpuPoseidonPublicCoin::<Fp>::new(seed);
+    let elements = (0..19u64).map(Fp::from).collect::<Vec<Fp>>();
+    let device = ministark_gpu::prelude::get_planner().library.device();
+    let elements_buffer = device.new_buffer_with_data(
+        elements.as_ptr().cast(),
+        core::mem::size_of_val(&elements[..]) as u64,
+        MTLResourceOptions::StorageModeShared,
+    );
+
+    cpu_coin.reseed_with_field_elements(&elements);
+    gpu_coin.reseed_with_gpu_elements(&elements_buffer, elements.len());
+    cpu_coin.reseed_with_int(u64::MAX);
+    gpu_coin.reseed_with_int(u64::MAX);
+    assert_eq!(cpu_coin.draw(), gpu_coin.draw());
+    cpu_coin.reseed_with_digest(&seed);
+    gpu_coin.reseed_with_digest(&seed);
+    assert_eq!(
+        draw_multiple(&mut cpu_coin, 20),
+        draw_multiple(&mut gpu_coin, 20)
+    );
+    assert_eq!(
+        cpu_coin.draw_queries(30, 1024),
+        gpu_coin.draw_queries(30, 1024)
+    );
+    assert!(gpu_coin.verify_proof_of_work(0, 1));
+}
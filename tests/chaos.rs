@@ -0,0 +1,39 @@
+#![cfg(feature = "chaos")]
+mod common;
+
+use common::SquareClaim;
+use ministark::chaos;
+use ministark::chaos::Fault;
+use ministark::stark::Stark;
+use ministark::ProofOptions;
+
+/// Injects each fault at every site it can occur and checks the verifier
+/// rejects every resulting proof
+#[test]
+fn verifier_rejects_every_injected_fault() {
+    let options = ProofOptions::new(16, 4, 2, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    SquareClaim::<false>.verify(proof, 1).unwrap();
+
+    for fault in [
+        Fault::CorruptMerkleNode,
+        Fault::SkipTranscriptAbsorb,
+        Fault::DropQuery,
+    ] {
+        let mut occurrence = 0;
+        loop {
+            let guard = chaos::inject(fault, occurrence, occurrence as u64);
+            let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+            if !guard.is_injected() {
+                break;
+            }
+            drop(guard);
+            assert!(
+                SquareClaim::<false>.verify(proof, 1).is_err(),
+                "{fault:?} at occurrence {occurrence} was not detected"
+            );
+            occurrence += 1;
+        }
+        assert!(occurrence > 0, "{fault:?} was never injected");
+    }
+}
@@ -0,0 +1,172 @@
+#![feature(allocator_api)]
+#![cfg(feature = "prover")]
+use ark_ff::One;
+use ark_ff::UniformRand;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark::air::AirConfig;
+use ministark::circuit::VerifierCircuit;
+use ministark::codegen::ConstraintProgram;
+use ministark::codegen::MetalConstraintKernel;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::Air;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+
+#[test]
+fn verifier_circuit_describes_proof_layout() {
+    struct SquareAirConfig;
+
+    impl AirConfig for SquareAirConfig {
+        const NUM_BASE_COLUMNS: usize = 2;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+            vec![
+                Constraint::new(
+                    (0.next() - 0.curr() * 1.curr()) * ((X - last_x) / (X.pow(trace_len) - one)),
+                ),
+                Constraint::new(1.curr() * (1.curr() - one) / (X.pow(trace_len) - one)),
+            ]
+        }
+    }
+
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let air = Air::<SquareAirConfig>::new(1 << 10, (), options);
+    let circuit = VerifierCircuit::new(&air);
+
+    let lde_domain_size = 1 << 13;
+    assert_eq!(circuit.lde_domain_size, lde_domain_size);
+    assert_eq!(circuit.commitments[0].row_width, 2);
+    assert_eq!(circuit.commitments[0].depth, 13);
+    // base trace, composition trace and one commitment per FRI layer
+    let num_fri_layers = options.into_fri_options().num_layers(lde_domain_size);
+    assert_eq!(circuit.commitments.len(), 2 + num_fri_layers);
+    assert_eq!(
+        circuit.commitments.last().unwrap().depth,
+        13 - num_fri_layers as u32
+    );
+    let base_trace_values = circuit.proof_element("base_trace_values").unwrap();
+    assert_eq!(base_trace_values.offset, 0);
+    assert_eq!(base_trace_values.len, 16 * 2);
+    let ood_evals = circuit.proof_element("execution_trace_ood_evals").unwrap();
+    assert_eq!(ood_evals.len, air.trace_arguments().len());
+    assert!(circuit.ood_constraint_ops.mul > 0);
+    assert!(circuit
+        .to_json()
+        .contains(r#""name":"fri_layer_0","kind":"fq","row_width":2"#));
+}
+
+#[test]
+fn metal_kernel_inlines_constants_and_columns() {
+    struct QuinticAirConfig;
+
+    impl AirConfig for QuinticAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            let seven = Constant(FieldVariant::Fp(Fp::from(7u8)));
+            vec![Constraint::new(
+                (0.next() - 0.curr().pow(3) - seven) / (X.pow(trace_len) - one),
+            )]
+        }
+    }
+
+    let air = Air::<QuinticAirConfig>::new(1 << 6, (), ProofOptions::new(16, 8, 0, 2, 4));
+    let kernel =
+        MetalConstraintKernel::generate(air.compiled_composition_constraint(), 1, 0).unwrap();
+
+    assert_eq!(kernel.num_challenges, 0);
+    assert_eq!(kernel.num_composition_coeffs, 2);
+    assert!(kernel
+        .source
+        .contains("base.cols[0][(i + 1 * step) & mask]"));
+    // constants are inlined in Montgomery form (7 * 2^64 mod p)
+    let seven = (7u128 << 64) % 18446744069414584321;
+    assert!(kernel.source.contains(&format!("Fp({seven}ul)")));
+    assert!(!kernel.source.contains("ExtensionColumns"));
+}
+
+#[test]
+fn constraint_program_matches_cpu_evaluation() {
+    struct ProgramAirConfig;
+
+    impl AirConfig for ProgramAirConfig {
+        const NUM_BASE_COLUMNS: usize = 2;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            let seven = Constant(FieldVariant::Fp(Fp::from(7u8)));
+            let alpha = Challenge(0);
+            vec![
+                Constraint::new((0.next() - 0.curr().pow(3) - seven) / (X.pow(trace_len) - one)),
+                Constraint::new((1.offset(-1) * alpha - 0.curr() * 1.curr()) / (X - one)),
+            ]
+        }
+    }
+
+    let n = 64;
+    let lde_step = 4;
+    let air = Air::<ProgramAirConfig>::new(n / lde_step, (), ProofOptions::new(16, 8, 0, 2, 4));
+    let composition_constraint = air.compiled_composition_constraint();
+    let program = ConstraintProgram::lower(composition_constraint, 2, 0).unwrap();
+    assert_eq!(program.num_challenges, 1);
+    let num_coeffs = air.num_composition_constraint_coeffs();
+    assert_eq!(program.num_composition_coeffs, num_coeffs);
+    // registers are reused once values are no longer needed
+    assert!(program.num_fp_registers + program.num_fq_registers < program.code.len());
+
+    let mut rng = ark_std::test_rng();
+    let mut random_column = || (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    let base_trace_lde_cols = [random_column(), random_column()];
+    let base_trace_lde_cols = [&base_trace_lde_cols[0][..], &base_trace_lde_cols[1][..]];
+    let x_lde = random_column().to_vec_in(GpuAllocator);
+    let challenges = [Fp::rand(&mut rng)];
+    let composition_coeffs = (0..num_coeffs)
+        .map(|_| Fp::rand(&mut rng))
+        .collect::<Vec<Fp>>();
+
+    let expected = ProgramAirConfig::eval_constraint(
+        composition_constraint,
+        &challenges,
+        &[],
+        &composition_coeffs,
+        lde_step,
+        x_lde.to_vec_in(GpuAllocator),
+        &base_trace_lde_cols,
+        None,
+    );
+    for (row, expected) in expected[0].iter().enumerate() {
+        let actual = program.eval_row(
+            row,
+            &challenges,
+            &[],
+            &composition_coeffs,
+            lde_step,
+            &x_lde,
+            &base_trace_lde_cols,
+            None,
+        );
+        assert_eq!(*expected, actual, "row {row}");
+    }
+}
@@ -0,0 +1,159 @@
+//! Fixtures shared by the integration tests. Not every test binary uses all
+//! of them.
+#![allow(dead_code)]
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::One;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::iter::zip;
+use core::marker::PhantomData;
+use ministark::air::AirConfig;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::poseidon::PoseidonDigest;
+use ministark::poseidon::PoseidonHashFn;
+use ministark::poseidon::PoseidonMatrixMerkleTree;
+use ministark::poseidon::PoseidonPublicCoin;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::StarkExtensionOf;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::GpuFftField;
+use num_traits::Pow;
+use sha2::Sha256;
+
+/// Number of rows in the traces of [`SquareClaim`] and [`PoseidonSquareClaim`]
+pub const TRACE_LEN: usize = 64;
+
+/// Execution trace with base columns only
+pub struct ColumnsTrace<F = Fp>(pub Matrix<F>);
+
+impl<F: FftField<BasePrimeField = F>> Trace for ColumnsTrace<F> {
+    type Fp = F;
+    type Fq = F;
+
+    fn base_columns(&self) -> &Matrix<F> {
+        &self.0
+    }
+}
+
+/// Returns a column of `n` rows where each value is the square of the value
+/// in the previous row. The first value is `start`.
+pub fn square_column<F: Field>(start: F, n: usize) -> Matrix<F> {
+    let mut v = start;
+    Matrix::from_row_iter((0..n).map(|_| {
+        let row = [v];
+        v.square_in_place();
+        row
+    }))
+}
+
+/// Constrains each value of column 0 to be the square of the value in the
+/// previous row
+pub fn square_transition<F: GpuFftField + FftField>(
+    trace_len: usize,
+) -> Constraint<FieldVariant<F, F>> {
+    use AlgebraicItem::*;
+    let trace_domain = Radix2EvaluationDomain::<F>::new(trace_len).unwrap();
+    let one = Constant(FieldVariant::Fp(F::one()));
+    let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+    Constraint::new((0.next() - 0.curr() * 0.curr()) * ((X - last_x) / (X.pow(trace_len) - one)))
+}
+
+/// Single column AIR where each row squares the value in the previous row
+pub struct SquareAirConfig<F = Fp>(PhantomData<F>);
+
+impl<F> AirConfig for SquareAirConfig<F>
+where
+    F: GpuFftField<FftField = F> + FftField + StarkExtensionOf<F>,
+{
+    const NUM_BASE_COLUMNS: usize = 1;
+    type Fp = F;
+    type Fq = F;
+    type PublicInputs = ();
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<F, F>>> {
+        vec![square_transition(trace_len)]
+    }
+}
+
+/// Claims to know [`TRACE_LEN`] repeated squares of 3. The witness is a row
+/// to corrupt, if any, for checking invalid traces are caught.
+pub struct SquareClaim<const LOW_MEMORY: bool = false>;
+
+impl<const LOW_MEMORY: bool> Stark for SquareClaim<LOW_MEMORY> {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = SquareAirConfig;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = Option<usize>;
+    type Trace = ColumnsTrace;
+
+    const LOW_MEMORY: bool = LOW_MEMORY;
+
+    fn get_public_inputs(&self) {}
+
+    fn gen_public_coin(&self, _: &Air<SquareAirConfig>) -> Self::PublicCoin {
+        PublicCoinImpl::new(Sha256HashFn::hash(*b"square"))
+    }
+
+    fn generate_trace(&self, corrupt_row: Option<usize>) -> ColumnsTrace {
+        let mut column = square_column(Fp::from(3u8), TRACE_LEN);
+        if let Some(row) = corrupt_row {
+            column[0][row] += Fp::one();
+        }
+        ColumnsTrace(column)
+    }
+}
+
+/// [`SquareClaim`] committed to with Poseidon and a sponge transcript
+pub struct PoseidonSquareClaim;
+
+impl Stark for PoseidonSquareClaim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = SquareAirConfig;
+    type Digest = PoseidonDigest;
+    type PublicCoin = PoseidonPublicCoin<Fp>;
+    type MerkleTree = PoseidonMatrixMerkleTree;
+    type Witness = ();
+    type Trace = ColumnsTrace;
+
+    fn get_public_inputs(&self) {}
+
+    fn gen_public_coin(&self, _: &Air<SquareAirConfig>) -> Self::PublicCoin {
+        PoseidonPublicCoin::new(PoseidonHashFn::hash(*b"square"))
+    }
+
+    fn generate_trace(&self, _: ()) -> ColumnsTrace {
+        ColumnsTrace(square_column(Fp::from(3u8), TRACE_LEN))
+    }
+}
+
+/// Returns [`TRACE_LEN`] memory accesses as `(address, value)` pairs
+pub fn memory_accesses() -> Vec<(u64, u64)> {
+    (0..TRACE_LEN as u64).map(|i| (i * 7 % 13, i % 5)).collect()
+}
+
+/// Returns columns of the accesses' addresses and values in access order
+/// followed by the addresses and values sorted by address
+pub fn memory_columns(accesses: &[(u64, u64)]) -> Matrix<Fp> {
+    let mut sorted = accesses.to_vec();
+    sorted.sort_unstable();
+    let rows = zip(accesses, &sorted)
+        .map(|(&(a, v), &(sorted_a, sorted_v))| [a, v, sorted_a, sorted_v].map(Fp::from));
+    Matrix::from_row_iter(rows)
+}
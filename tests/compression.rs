@@ -0,0 +1,113 @@
+#![cfg(feature = "prover")]
+mod common;
+
+use common::SquareClaim;
+use ministark::compression;
+use ministark::compression::Codec;
+use ministark::compression::Compressor;
+use ministark::stark::Stark;
+use ministark::verifier::VerificationError;
+use ministark::ProofOptions;
+
+#[test]
+fn compressed_proofs_verify_from_bytes() {
+    // run-length encodes bytes as (count, byte) pairs
+    struct RunLength;
+
+    impl Compressor for RunLength {
+        fn codec(&self) -> Codec {
+            Codec(200)
+        }
+
+        fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+            let mut compressed = Vec::new();
+            for run in bytes.chunk_by(|a, b| a == b) {
+                for chunk in run.chunks(255) {
+                    compressed.extend([chunk.len() as u8, chunk[0]]);
+                }
+            }
+            compressed
+        }
+
+        fn decompress(&self, bytes: &[u8], len: usize) -> Result<Vec<u8>, String> {
+            let mut decompressed = Vec::new();
+            for pair in bytes.chunks(2) {
+                let [count, byte] = *pair else {
+                    return Err("truncated run".to_string());
+                };
+                decompressed.extend(core::iter::repeat_n(byte, count.into()));
+                if decompressed.len() > len {
+                    return Err("too many bytes".to_string());
+                }
+            }
+            Ok(decompressed)
+        }
+    }
+
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let bytes = compression::encode_proof(&proof, &RunLength);
+    SquareClaim::<false>
+        .verify_bytes(&bytes, 1, &[&RunLength])
+        .unwrap();
+    let bytes = compression::encode_proof(&proof, &compression::Uncompressed);
+    SquareClaim::<false>.verify_bytes(&bytes, 1, &[]).unwrap();
+
+    // the codec must be known to the verifier
+    let bytes = compression::encode_proof(&proof, &RunLength);
+    assert!(matches!(
+        SquareClaim::<false>.verify_bytes(&bytes, 1, &[]),
+        Err(VerificationError::ProofDecoding {
+            source: compression::Error::UnknownCodec { .. }
+        })
+    ));
+}
+
+#[cfg(feature = "compression-zstd")]
+#[test]
+fn zstd_proofs_verify_from_bytes() {
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let zstd = compression::Zstd::default();
+    let bytes = compression::encode_proof(&proof, &zstd);
+    assert_eq!(Codec::ZSTD.0, bytes[compression::MAGIC.len() + 1]);
+    let uncompressed = compression::encode_proof(&proof, &compression::Uncompressed);
+    assert!(bytes.len() < uncompressed.len());
+    SquareClaim::<false>
+        .verify_bytes(&bytes, 1, &[&zstd])
+        .unwrap();
+
+    // a corrupted payload is rejected before the proof is deserialized
+    let mut corrupted = bytes;
+    corrupted.truncate(corrupted.len() - 8);
+    assert!(matches!(
+        SquareClaim::<false>.verify_bytes(&corrupted, 1, &[&zstd]),
+        Err(VerificationError::ProofDecoding {
+            source: compression::Error::Decompression { .. }
+        })
+    ));
+}
+
+#[cfg(feature = "compression-brotli")]
+#[test]
+fn brotli_proofs_verify_from_bytes() {
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let brotli = compression::Brotli::default();
+    let bytes = compression::encode_proof(&proof, &brotli);
+    assert_eq!(Codec::BROTLI.0, bytes[compression::MAGIC.len() + 1]);
+    let uncompressed = compression::encode_proof(&proof, &compression::Uncompressed);
+    assert!(bytes.len() < uncompressed.len());
+    SquareClaim::<false>
+        .verify_bytes(&bytes, 1, &[&brotli])
+        .unwrap();
+
+    // decompressors are matched by codec
+    #[cfg(feature = "compression-zstd")]
+    assert!(matches!(
+        SquareClaim::<false>.verify_bytes(&bytes, 1, &[&compression::Zstd::default()]),
+        Err(VerificationError::ProofDecoding {
+            source: compression::Error::UnknownCodec { .. }
+        })
+    ));
+}
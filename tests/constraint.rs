@@ -1,4 +1,7 @@
 #![feature(allocator_api)]
+#![cfg(feature = "prover")]
+mod common;
+
 use ark_ff::FftField;
 use ark_ff::Field;
 use ark_ff::One;
@@ -9,23 +12,51 @@ use ark_poly::DenseUVPolynomial;
 use ark_poly::EvaluationDomain;
 use ark_poly::Polynomial;
 use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalSerialize;
 use ark_std::rand::seq::SliceRandom;
+use common::square_column;
+use common::ColumnsTrace;
+use common::SquareAirConfig;
+use common::TRACE_LEN;
+use core::marker::PhantomData;
+use ministark::air::periodic_column;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
 use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Assertion;
 use ministark::constraints::Constraint;
+use ministark::constraints::ConstraintGroup;
 use ministark::constraints::ExecutionTraceColumn;
 use ministark::constraints::VerifierChallenge;
+use ministark::debug;
 use ministark::expression::Expr;
+use ministark::hash::ElementHashFn;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::prover::ProvingError;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::rescue::Rpo256HashFn;
+use ministark::stark::Stark;
 use ministark::utils;
 use ministark::utils::tests::gen_binary_valued_matrix;
 use ministark::utils::tests::gen_fib_matrix;
 use ministark::utils::FieldVariant;
 use ministark::utils::GpuAllocator;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
 use ministark::Matrix;
+use ministark::ProofOptions;
 use ministark::StarkExtensionOf;
+use ministark::Trace;
 use ministark_gpu::fields::p18446744069414584321::ark::Fp;
 use ministark_gpu::GpuFftField;
 use ministark_gpu::GpuField;
 use num_traits::Pow;
+use sha2::Digest;
+use sha2::Sha256;
 
 // TODO: handle
 // #[test]
@@ -386,3 +417,392 @@ fn evaluate_symbolic<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
 
     Matrix::new(vec![result])
 }
+
+#[test]
+fn compiled_expression_matches_tree_evaluation() {
+    let mut rng = ark_std::test_rng();
+    let shared: Expr<AlgebraicItem<Fp>> = 0.curr() * 1.next() + 0.challenge();
+    let expr = (&shared * &shared - 1.curr()) / (shared.clone() + AlgebraicItem::X).pow(3);
+    let x = Fp::rand(&mut rng);
+    let challenges = [Fp::rand(&mut rng)];
+    let trace_values = [Fp::rand(&mut rng), Fp::rand(&mut rng), Fp::rand(&mut rng)];
+    let mut leaf_eval = |leaf: &AlgebraicItem<Fp>| {
+        use AlgebraicItem::*;
+        FieldVariant::<Fp, Fp>::Fp(match leaf {
+            X => x,
+            &Constant(v) => v,
+            &Challenge(i) => challenges[i],
+            &Trace(0, 0) => trace_values[0],
+            &Trace(1, 0) => trace_values[1],
+            &Trace(1, 1) => trace_values[2],
+            _ => unreachable!(),
+        })
+    };
+
+    let expected = expr.eval(&mut leaf_eval);
+    let compiled = expr.compile();
+
+    assert_eq!(expected, compiled.eval(&mut leaf_eval));
+    assert_eq!(5, compiled.leaves().count());
+}
+
+#[test]
+fn shared_nodes_are_reused_regardless_of_operand_order() {
+    use AlgebraicItem::*;
+    let mut rng = ark_std::test_rng();
+    let constraint: Expr<AlgebraicItem<Fp>> =
+        0.curr() * 1.curr() * 2.curr() * (3.next() - 3.curr()) + 1.curr() * 0.curr() * X;
+    // same as `constraint` with the operands of every addition and
+    // multiplication reordered
+    let reordered: Expr<AlgebraicItem<Fp>> = Expr::from(X) * (0.curr() * 1.curr())
+        + (-3.curr() + 3.next()) * (2.curr() * (1.curr() * 0.curr()));
+    let expr = constraint.clone() * 0.challenge() + reordered * 1.challenge();
+    let trace_values = (0..5).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    let x = Fp::rand(&mut rng);
+    let challenges = [Fp::rand(&mut rng), Fp::rand(&mut rng)];
+    let mut leaf_eval = |leaf: &AlgebraicItem<Fp>| {
+        FieldVariant::<Fp, Fp>::Fp(match *leaf {
+            X => x,
+            Constant(v) => v,
+            Challenge(i) => challenges[i],
+            Trace(col, 0) => trace_values[col],
+            Trace(3, 1) => trace_values[4],
+            _ => unreachable!(),
+        })
+    };
+
+    let compiled = expr.reuse_shared_nodes().compile();
+    let compiled_constraint = constraint.reuse_shared_nodes().compile();
+
+    assert_eq!(expr.eval(&mut leaf_eval), compiled.eval(&mut leaf_eval));
+    // both constraints are a single node. Only the challenges, the products
+    // with them and their sum are added.
+    assert_eq!(
+        compiled_constraint.instructions().len() + 5,
+        compiled.instructions().len()
+    );
+}
+
+/// Proofs are pinned to known bytes so a proof generated on any target
+/// (including big-endian targets like s390x) verifies on any other target
+#[test]
+fn proofs_are_identical_across_targets() {
+    struct SquareClaim<H>(PhantomData<H>);
+
+    impl<H: ElementHashFn<Fp> + Send + Sync + 'static> Stark for SquareClaim<H> {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = SquareAirConfig;
+        type Digest = H::Digest;
+        type PublicCoin = PublicCoinImpl<Fp, H>;
+        type MerkleTree = MatrixMerkleTreeImpl<H>;
+        type Witness = ();
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, air: &Air<SquareAirConfig>) -> Self::PublicCoin {
+            let mut seed = Vec::new();
+            air.trace_len().serialize_compressed(&mut seed).unwrap();
+            air.options().serialize_compressed(&mut seed).unwrap();
+            PublicCoinImpl::new(H::hash(seed))
+        }
+
+        fn generate_trace(&self, _: ()) -> ColumnsTrace {
+            ColumnsTrace(square_column(Fp::from(3u8), TRACE_LEN))
+        }
+    }
+
+    fn proof_digest<H: ElementHashFn<Fp> + Send + Sync + 'static>() -> String {
+        let claim = SquareClaim::<H>(PhantomData);
+        let options = ProofOptions::new(8, 4, 4, 2, 4);
+        let proof = pollster::block_on(claim.prove(options, ())).unwrap();
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        claim.verify(proof, 1).unwrap();
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    assert_eq!(
+        proof_digest::<Sha256HashFn>(),
+        "3b9830702cb28b60c486d49b4c697d22cfe87fb207893ab65cbd44fa9e2ba3a7"
+    );
+    assert_eq!(
+        proof_digest::<Rpo256HashFn>(),
+        "5e82ca81f7627a84d272e6ad11ac1e970b0b21467363fffdac360196c7519ea0"
+    );
+}
+
+#[test]
+fn constraint_groups_apply_their_divisor() {
+    struct GroupedSquareAirConfig;
+
+    impl AirConfig for GroupedSquareAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let start = Constant(FieldVariant::Fp(Fp::from(3u8)));
+            vec![
+                ConstraintGroup::first_row(vec![0.curr() - start]),
+                ConstraintGroup::transitions(trace_len, vec![0.next() - 0.curr() * 0.curr()]),
+            ]
+        }
+    }
+
+    struct GroupedSquareClaim;
+
+    impl Stark for GroupedSquareClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = GroupedSquareAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = usize;
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<GroupedSquareAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"square"))
+        }
+
+        // generates a trace with an invalid value at row `corrupt_row` (if
+        // it's in the trace)
+        fn generate_trace(&self, corrupt_row: usize) -> ColumnsTrace {
+            let mut column = square_column(Fp::from(3u8), TRACE_LEN);
+            if let Some(value) = column[0].get_mut(corrupt_row) {
+                *value += Fp::one();
+            }
+            ColumnsTrace(column)
+        }
+    }
+
+    let validate = |corrupt_row| {
+        let trace = GroupedSquareClaim.generate_trace(corrupt_row);
+        debug::default_validate_constraints(
+            &GroupedSquareClaim,
+            &Challenges::default(),
+            &Hints::default(),
+            trace.base_columns(),
+            None,
+        )
+    };
+    // constraints are numbered in the order of their groups
+    assert!(matches!(
+        validate(0),
+        Err(ProvingError::UnsatisfiedConstraint {
+            constraint: 0,
+            row: 0,
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate(5),
+        Err(ProvingError::UnsatisfiedConstraint {
+            constraint: 1,
+            row: 4,
+            ..
+        })
+    ));
+
+    // transitions don't wrap around from the last row to the first
+    validate(64).unwrap();
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(GroupedSquareClaim.prove(options, 64)).unwrap();
+    GroupedSquareClaim.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn periodic_columns_repeat_their_values() {
+    struct CycleAirConfig;
+
+    impl AirConfig for CycleAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn periodic_columns() -> Vec<Vec<Fp>> {
+            vec![[1u8, 2, 3, 4].map(Fp::from).to_vec()]
+        }
+
+        fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+            let cycle = periodic_column::<Self>(0);
+            let step = 0.next() - 0.curr() * cycle;
+            vec![ConstraintGroup::transitions(trace_len, vec![step])]
+        }
+    }
+
+    struct CycleClaim;
+
+    impl Stark for CycleClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = CycleAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = usize;
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<CycleAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"cycle"))
+        }
+
+        // generates a trace where row `i + 1` is row `i` multiplied by the
+        // cycle shifted by `offset` rows
+        fn generate_trace(&self, offset: usize) -> ColumnsTrace {
+            let mut v = Fp::one();
+            let rows = (0..64).map(|i| {
+                let row = [v];
+                v *= Fp::from(((i + offset) % 4 + 1) as u8);
+                row
+            });
+            ColumnsTrace(Matrix::from_row_iter(rows))
+        }
+    }
+
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(CycleClaim.prove(options, 0)).unwrap();
+    CycleClaim.verify(proof, 1).unwrap();
+
+    let trace = CycleClaim.generate_trace(1);
+    assert!(matches!(
+        debug::default_validate_constraints(
+            &CycleClaim,
+            &Challenges::default(),
+            &Hints::default(),
+            trace.base_columns(),
+            None,
+        ),
+        Err(ProvingError::UnsatisfiedConstraint {
+            constraint: 0,
+            row: 0,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn coset_division_matches_quotient() {
+    let n = 16;
+    let mut rng = ark_std::test_rng();
+    let quotient = DensePolynomial::<Fp>::rand(2 * n - 1, &mut rng);
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let numerator = &quotient * &DensePolynomial::from(trace_domain.vanishing_polynomial());
+    let coset = Radix2EvaluationDomain::new_coset(4 * n, Fp::GENERATOR).unwrap();
+
+    let mut evals = coset.fft(&numerator);
+    let divisor_evals = coset
+        .elements()
+        .map(|x| trace_domain.evaluate_vanishing_polynomial(x))
+        .collect::<Vec<Fp>>();
+    utils::divide_on_coset(&mut evals, &divisor_evals);
+
+    assert_eq!(quotient.coeffs, coset.ifft(&evals)[..2 * n]);
+    assert!(coset.ifft(&evals)[2 * n..].iter().all(Zero::is_zero));
+}
+
+#[test]
+fn assertions_pin_values_in_any_row() {
+    struct AssertedSquareAirConfig;
+
+    impl AirConfig for AssertedSquareAirConfig {
+        const NUM_BASE_COLUMNS: usize = 2;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = Fp;
+
+        fn gen_hints(_: usize, fifth_square: &Fp, _: &Challenges<Fp>) -> Hints<Fp> {
+            Hints::new(vec![(0, *fifth_square)])
+        }
+
+        fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+            vec![ConstraintGroup::transitions(
+                trace_len,
+                vec![0.next() - 0.curr() * 0.curr()],
+            )]
+        }
+
+        fn assertions(_trace_len: usize) -> Vec<Assertion<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            vec![
+                Assertion::single(0, 0, Constant(FieldVariant::Fp(Fp::from(3u8)))),
+                Assertion::single(0, 5, Hint(0)),
+                Assertion::periodic(1, 2, 4, Constant(FieldVariant::Fp(Fp::from(7u8)))),
+            ]
+        }
+    }
+
+    struct AssertedSquareClaim(Fp);
+
+    impl Stark for AssertedSquareClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = AssertedSquareAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = Matrix<Fp>;
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) -> Fp {
+            self.0
+        }
+
+        fn gen_public_coin(&self, _: &Air<AssertedSquareAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"assertions"))
+        }
+
+        fn generate_trace(&self, columns: Matrix<Fp>) -> ColumnsTrace {
+            ColumnsTrace(columns)
+        }
+    }
+
+    let mut v = Fp::from(3u8);
+    let columns = Matrix::from_row_iter((0..64u64).map(|i| {
+        let row = [v, Fp::from(if i % 4 == 2 { 7u8 } else { 1 })];
+        v.square_in_place();
+        row
+    }));
+    let fifth_square = columns.0[0][5];
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof =
+        pollster::block_on(AssertedSquareClaim(fifth_square).prove(options, columns.clone()))
+            .unwrap();
+    AssertedSquareClaim(fifth_square).verify(proof, 1).unwrap();
+
+    // wrong values in the middle of the trace are caught
+    let wrong_square = fifth_square + Fp::one();
+    assert!(matches!(
+        debug::default_validate_constraints(
+            &AssertedSquareClaim(wrong_square),
+            &Challenges::default(),
+            &Hints::new(vec![(0, wrong_square)]),
+            &columns,
+            None,
+        ),
+        Err(ProvingError::UnsatisfiedNamedConstraint { name, row: 5, .. })
+            if name == "assertion on column 0 in row 5"
+    ));
+    let mut broken_columns = columns;
+    broken_columns.0[1][38] = Fp::one();
+    assert!(matches!(
+        debug::default_validate_constraints(
+            &AssertedSquareClaim(fifth_square),
+            &Challenges::default(),
+            &Hints::new(vec![(0, fifth_square)]),
+            &broken_columns,
+            None,
+        ),
+        Err(ProvingError::UnsatisfiedNamedConstraint { name, row: 38, .. })
+            if name == "assertion on column 1 every 4 rows from row 2"
+    ));
+}
@@ -0,0 +1,285 @@
+#![feature(allocator_api)]
+#![cfg(feature = "prover")]
+mod common;
+
+use ark_ff::One;
+use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalSerialize;
+use common::square_column;
+use common::ColumnsTrace;
+use common::SquareClaim;
+use common::TRACE_LEN;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ConstraintGroup;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::debug;
+use ministark::debug::ChallengeAuditError;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::prover::ProvingError;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::spot_check::SpotCheckError;
+use ministark::spot_check::SpotCheckProver;
+use ministark::spot_check::SpotCheckVerifier;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Sha256;
+
+#[test]
+fn challenge_audit_detects_out_of_order_access() {
+    struct LinearTrace<const OUT_OF_ORDER: bool>(Matrix<Fp>);
+
+    impl<const OUT_OF_ORDER: bool> Trace for LinearTrace<OUT_OF_ORDER> {
+        type Fp = Fp;
+        type Fq = Fp;
+
+        fn base_columns(&self) -> &Matrix<Fp> {
+            &self.0
+        }
+
+        fn build_extension_columns(&self, challenges: &Challenges<Fp>) -> Option<Matrix<Fp>> {
+            let (alpha, beta) = if OUT_OF_ORDER {
+                let beta = challenges[1];
+                (challenges[0], beta)
+            } else {
+                (challenges[0], challenges[1])
+            };
+            let column = self.0[0]
+                .iter()
+                .map(|&v| v * alpha + beta)
+                .collect::<Vec<_>>();
+            Some(Matrix::new(vec![column.to_vec_in(GpuAllocator)]))
+        }
+    }
+
+    struct LinearAirConfig;
+
+    impl AirConfig for LinearAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        const NUM_EXTENSION_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+            let first_x = Constant(FieldVariant::Fp(trace_domain.element(0)));
+            vec![Constraint::new(
+                (1.curr() - 0.curr() * Challenge(0) - Challenge(1)) / (X - first_x),
+            )]
+        }
+    }
+
+    struct LinearClaim<const OUT_OF_ORDER: bool>;
+
+    impl<const OUT_OF_ORDER: bool> Stark for LinearClaim<OUT_OF_ORDER> {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = LinearAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = ();
+        type Trace = LinearTrace<OUT_OF_ORDER>;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<LinearAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"linear"))
+        }
+
+        fn generate_trace(&self, _: ()) -> LinearTrace<OUT_OF_ORDER> {
+            LinearTrace(Matrix::from_row_iter((0..64u32).map(|i| [Fp::from(i)])))
+        }
+    }
+
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let accesses = debug::audit_challenge_accesses(&LinearClaim::<false>, (), options, 0).unwrap();
+    assert_eq!(accesses.first_access_order, [0, 1]);
+    assert!(matches!(
+        debug::audit_challenge_accesses(&LinearClaim::<true>, (), options, 0),
+        Err(ChallengeAuditError::OutOfOrderAccess {
+            index: 0,
+            previous: 1
+        })
+    ));
+}
+
+#[test]
+fn validation_reports_unsatisfied_constraint() {
+    let trace = SquareClaim::<false>.generate_trace(Some(5));
+    let result = debug::default_validate_constraints(
+        &SquareClaim::<false>,
+        &Challenges::default(),
+        &Hints::default(),
+        trace.base_columns(),
+        None,
+    );
+    // row 4 transitions to the corrupted value at row 5
+    let Err(ProvingError::UnsatisfiedConstraint {
+        constraint: 0,
+        row: 4,
+        explanation,
+    }) = result
+    else {
+        panic!("expected constraint 0 to fail at row 4")
+    };
+    // the error explains the value of each sub-expression at the row
+    assert!(explanation.contains("trace(0, 1) = "));
+
+    if cfg!(debug_assertions) {
+        let options = ProofOptions::new(16, 4, 0, 2, 4);
+        assert!(matches!(
+            pollster::block_on(SquareClaim::<false>.prove(options, Some(5))),
+            Err(ProvingError::UnsatisfiedConstraint { .. })
+        ));
+    }
+}
+
+#[test]
+fn spot_check_detects_invalid_rows() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let mut rng = ark_std::test_rng();
+
+    let prover = SpotCheckProver::new(&SquareClaim::<false>, None, options);
+    let verifier = SpotCheckVerifier::new(&SquareClaim::<false>, prover.commitment(), options);
+    let rows = verifier.sample_rows(&mut rng, 8);
+    assert_eq!(8, rows.len());
+    let evaluations = verifier.verify(&rows, prover.open(&rows).unwrap()).unwrap();
+    assert!(evaluations.iter().flatten().all(Zero::is_zero));
+
+    // row 4 transitions to the corrupted value at row 5
+    let prover = SpotCheckProver::new(&SquareClaim::<false>, Some(5), options);
+    let verifier = SpotCheckVerifier::new(&SquareClaim::<false>, prover.commitment(), options);
+    assert!(verifier.verify(&[3], prover.open(&[3]).unwrap()).is_ok());
+    assert!(matches!(
+        verifier.verify(&[4], prover.open(&[4]).unwrap()),
+        Err(SpotCheckError::UnsatisfiedConstraint {
+            constraint: 0,
+            row: 4
+        })
+    ));
+
+    // opened values must match the commitment
+    let mut opening = prover.open(&[4]).unwrap();
+    opening.base_trace_rows[1][0] -= Fp::one();
+    assert!(matches!(
+        verifier.verify(&[4], opening),
+        Err(SpotCheckError::InvalidOpening { .. })
+    ));
+}
+
+#[test]
+fn validation_reports_constraint_names() {
+    struct NamedSquareAirConfig<const NAMED: bool>;
+
+    impl<const NAMED: bool> AirConfig for NamedSquareAirConfig<NAMED> {
+        const NUM_BASE_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+            let square = 0.next() - 0.curr() * 0.curr();
+            let group = ConstraintGroup::transitions(trace_len, vec![square]);
+            vec![if NAMED {
+                group.with_names(["square"])
+            } else {
+                group
+            }]
+        }
+    }
+
+    struct NamedSquareClaim<const NAMED: bool>;
+
+    impl<const NAMED: bool> Stark for NamedSquareClaim<NAMED> {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = NamedSquareAirConfig<NAMED>;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = Option<usize>;
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<NamedSquareAirConfig<NAMED>>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"square"))
+        }
+
+        // generates a trace with an invalid value at row `corrupt_row`
+        fn generate_trace(&self, corrupt_row: Option<usize>) -> ColumnsTrace {
+            let mut column = square_column(Fp::from(3u8), TRACE_LEN);
+            if let Some(row) = corrupt_row {
+                column[0][row] += Fp::one();
+            }
+            ColumnsTrace(column)
+        }
+    }
+
+    let trace = NamedSquareClaim::<true>.generate_trace(Some(5));
+    let err = debug::default_validate_constraints(
+        &NamedSquareClaim::<true>,
+        &Challenges::default(),
+        &Hints::default(),
+        trace.base_columns(),
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "constraint `square` (index `0`) is not satisfied at row `4`"
+    );
+
+    // names don't affect the proof
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let named = pollster::block_on(NamedSquareClaim::<true>.prove(options, None)).unwrap();
+    let unnamed = pollster::block_on(NamedSquareClaim::<false>.prove(options, None)).unwrap();
+    let mut named_bytes = Vec::new();
+    let mut unnamed_bytes = Vec::new();
+    named.serialize_compressed(&mut named_bytes).unwrap();
+    unnamed.serialize_compressed(&mut unnamed_bytes).unwrap();
+    assert_eq!(named_bytes, unnamed_bytes);
+}
+
+#[test]
+fn explanation_shows_value_of_each_sub_expression() {
+    use AlgebraicItem::*;
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let constraint = Constraint::new((0.next() - 0.curr() * 0.curr()) / (X - one));
+    let trace = |_col, offset| FieldVariant::Fp(Fp::from(if offset == 0 { 3u8 } else { 10u8 }));
+    let challenges = Challenges::<Fp>::new(vec![]);
+    let hints = Hints::default();
+
+    let explanation =
+        debug::explain_constraint_at(&constraint, 8, Fp::from(2u8), &challenges, &hints, trace);
+    assert_eq!(explanation.0[0].label, "/");
+    assert_eq!(explanation.0[0].value, Some(FieldVariant::Fp(Fp::one())));
+    let lines = explanation.to_string();
+    assert!(lines.contains("\n  +"));
+    assert!(lines.contains("trace(0, 1) = 10"));
+    assert!(lines.contains("trace(0, 0) = 3"));
+
+    // dividing by zero at x = 1 leaves the quotient undefined
+    let explanation =
+        debug::explain_constraint_at(&constraint, 8, Fp::one(), &challenges, &hints, trace);
+    assert_eq!(explanation.0[0].value, None);
+    assert!(explanation.to_string().starts_with("/ = undefined"));
+}
@@ -0,0 +1,140 @@
+#![feature(allocator_api)]
+#![cfg(feature = "prover")]
+mod common;
+
+use ark_ff::Field;
+use ark_ff::One;
+use ark_ff::UniformRand;
+use ark_ff::Zero;
+use common::square_column;
+use common::ColumnsTrace;
+use common::SquareAirConfig;
+use common::TRACE_LEN;
+use core::iter::zip;
+use ministark::air::AirConfig;
+use ministark::circle::evaluate_at_point;
+use ministark::circle::CircleDomain;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::domain::TraceDomain;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::fields::p18446744069414584321::ark::Fq2;
+use ministark_gpu::fields::p2147483647::ark::Fp as Mersenne31;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp as Fp252;
+use sha2::Sha256;
+
+#[test]
+fn circle_domain_interpolation_matches_point_evaluation() {
+    let mut rng = ark_std::test_rng();
+    let trace_domain = CircleDomain::<Mersenne31>::new(5).unwrap();
+    let lde_domain = trace_domain.lde_domain(4, Mersenne31::one()).unwrap();
+    let evals = (0..trace_domain.size())
+        .map(|_| Mersenne31::rand(&mut rng))
+        .collect::<Vec<Mersenne31>>();
+
+    let coeffs = trace_domain.interpolate(&evals);
+    assert_eq!(trace_domain.evaluate(&coeffs), evals);
+    let lde = lde_domain.evaluate(&coeffs);
+    for (point, eval) in zip(lde_domain.elements(), &lde) {
+        assert!((point.x.square() + point.y.square()).is_one());
+        assert_eq!(evaluate_at_point(&coeffs, point), *eval);
+    }
+    // the LDE interpolates to the same polynomial
+    let mut lde_coeffs = lde_domain.interpolate(&lde);
+    assert!(lde_coeffs.split_off(coeffs.len()).iter().all(Zero::is_zero));
+    assert_eq!(lde_coeffs, coeffs);
+}
+
+#[test]
+fn air_extends_trace_over_circle_domain() {
+    struct CircleAirConfig;
+
+    impl AirConfig for CircleAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        type Fp = Mersenne31;
+        type Fq = Mersenne31;
+        type PublicInputs = ();
+        type Domain = CircleDomain<Mersenne31>;
+
+        fn constraints(_: usize) -> Vec<Constraint<FieldVariant<Mersenne31, Mersenne31>>> {
+            vec![Constraint::new(0.next() - 0.curr())]
+        }
+    }
+
+    let trace_len = 16;
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let air = Air::<CircleAirConfig>::new(trace_len, (), options);
+    let mut rng = ark_std::test_rng();
+    let column = (0..trace_len)
+        .map(|_| Mersenne31::rand(&mut rng))
+        .collect::<Vec<Mersenne31>>();
+
+    let lde = air.extend_trace(&Matrix::new(vec![column.to_vec_in(GpuAllocator)]));
+
+    let trace_domain = air.evaluation_domain();
+    let coeffs = trace_domain.interpolate(&column);
+    let lde_domain = CircleDomain::<Mersenne31>::new(6).unwrap();
+    assert_eq!(lde[0].len(), lde_domain.size());
+    for (point, eval) in zip(lde_domain.elements(), &lde[0]) {
+        assert_eq!(evaluate_at_point(&coeffs, point), *eval);
+    }
+}
+
+#[test]
+fn proofs_over_stark252_field_verify() {
+    struct Stark252SquareClaim;
+
+    impl Stark for Stark252SquareClaim {
+        type Fp = Fp252;
+        type Fq = Fp252;
+        type AirConfig = SquareAirConfig<Fp252>;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp252, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = ();
+        type Trace = ColumnsTrace<Fp252>;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<SquareAirConfig<Fp252>>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"square"))
+        }
+
+        fn generate_trace(&self, _: ()) -> ColumnsTrace<Fp252> {
+            ColumnsTrace(square_column(Fp252::from(3u8), TRACE_LEN))
+        }
+    }
+
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(Stark252SquareClaim.prove(options, ())).unwrap();
+    Stark252SquareClaim.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn quadratic_extension_of_fp64_is_a_field() {
+    let seven = Fp::from(7u8);
+    // `X^2 - 7` is irreducible since `7` has no square root in `Fp`
+    assert!(seven.sqrt().is_none());
+    let x = Fq2::from_base_prime_field_elems(&[Fp::zero(), Fp::one()]).unwrap();
+    assert_eq!(x.square(), Fq2::from(seven));
+
+    let mut rng = ark_std::test_rng();
+    for _ in 0..16 {
+        let a = Fq2::rand(&mut rng);
+        let b = Fp::rand(&mut rng);
+        assert!((a * a.inverse().unwrap()).is_one());
+        assert_eq!(a * b, a * Fq2::from(b));
+    }
+}
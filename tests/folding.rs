@@ -0,0 +1,72 @@
+#![cfg(feature = "prover")]
+use ark_ff::Field;
+use ark_ff::One;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::folding::fold;
+use ministark::folding::RelaxedInstance;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::utils::FieldVariant;
+use ministark::Matrix;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+
+#[test]
+fn folded_instance_satisfies_relaxed_constraints() {
+    use AlgebraicItem::*;
+    let n = 16;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let last_x = Constant(FieldVariant::Fp(trace_domain.element(n - 1)));
+    let constraints = [
+        // column 0 squares itself each row
+        (0.next() - 0.curr() * 0.curr()) * ((X - last_x) / (X.pow(n) - one)),
+        // column 1 is binary
+        1.curr() * (1.curr() - one) / (X.pow(n) - one),
+        // column 2 cubes column 0
+        (2.curr() - 0.curr().pow(3)) / (X.pow(n) - one),
+    ]
+    .map(Constraint::new);
+    let gen_trace = |start: u64| {
+        let mut rows = Vec::new();
+        let mut v = Fp::from(start);
+        for i in 0..n {
+            rows.push(vec![v, Fp::from((i as u64 + start) % 2), v.pow([3])]);
+            v.square_in_place();
+        }
+        Matrix::from_rows(rows)
+    };
+
+    let lhs = RelaxedInstance::new(gen_trace(2), constraints.len());
+    let rhs = RelaxedInstance::new(gen_trace(3), constraints.len());
+    assert!(lhs.is_satisfied(&constraints));
+    assert!(rhs.is_satisfied(&constraints));
+
+    let mut public_coin = PublicCoinImpl::<Fp, Sha256HashFn>::new(Default::default());
+    let (folded, proof) = fold::<Fp, MatrixMerkleTreeImpl<Sha256HashFn>, _>(
+        &constraints,
+        &lhs,
+        &rhs,
+        &mut public_coin,
+    );
+    assert!(proof.cross_terms_commitment.is_some());
+    assert!(folded.is_satisfied(&constraints));
+
+    let mut public_coin = PublicCoinImpl::<Fp, Sha256HashFn>::new(Default::default());
+    let (mut folded, _) = fold::<Fp, MatrixMerkleTreeImpl<Sha256HashFn>, _>(
+        &constraints,
+        &folded,
+        &lhs,
+        &mut public_coin,
+    );
+    assert!(folded.is_satisfied(&constraints));
+
+    folded.trace[1][5] += Fp::one();
+    assert!(!folded.is_satisfied(&constraints));
+}
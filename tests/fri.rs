@@ -0,0 +1,96 @@
+#![feature(allocator_api)]
+#![cfg(feature = "prover")]
+use ark_ff::FftField;
+use ark_ff::One;
+use ark_ff::UniformRand;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::DenseUVPolynomial;
+use ark_poly::EvaluationDomain;
+use ark_poly::Polynomial;
+use ark_poly::Radix2EvaluationDomain;
+use ministark::fri;
+use ministark::fri::FriChannel;
+use ministark::fri::FriOptions;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::pcs;
+use ministark::pcs::FriPcs;
+use ministark::pcs::UnivariatePcs;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::utils::GpuAllocator;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::utils::bit_reverse;
+
+type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+
+fn channel() -> FriChannel<PublicCoinImpl<Fp, Sha256HashFn>> {
+    let options = FriOptions::new(4, 2, 8);
+    FriChannel::new(PublicCoinImpl::new(Default::default()), options, 20)
+}
+
+fn bit_reversed_evaluations(degree: usize, domain_size: usize) -> Vec<Fp, GpuAllocator> {
+    let mut rng = ark_std::test_rng();
+    let coeffs = (0..=degree).map(|_| Fp::rand(&mut rng)).collect();
+    let poly = DensePolynomial::from_coefficients_vec(coeffs);
+    let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+    let mut evaluations = domain.fft(&poly).to_vec_in(GpuAllocator);
+    bit_reverse(&mut evaluations);
+    evaluations
+}
+
+#[test]
+fn standalone_fri_proves_low_degree() {
+    let degree_bound = 64;
+    let evaluations = bit_reversed_evaluations(degree_bound - 1, degree_bound * 4);
+
+    let proof = fri::prove::<_, _, MerkleTree, _>(evaluations, degree_bound, &mut channel());
+
+    assert!(fri::verify(proof, degree_bound, &mut channel()).is_ok());
+}
+
+#[test]
+fn standalone_fri_rejects_modified_evaluations() {
+    let degree_bound = 64;
+    let evaluations = bit_reversed_evaluations(degree_bound - 1, degree_bound * 4);
+
+    let mut proof = fri::prove::<_, _, MerkleTree, _>(evaluations, degree_bound, &mut channel());
+    proof.layers[0].flattenend_rows[0] += Fp::one();
+
+    assert!(fri::verify(proof, degree_bound, &mut channel()).is_err());
+}
+
+#[test]
+fn fri_pcs_opens_committed_polynomials() {
+    type Pcs = FriPcs<Fp, MerkleTree, PublicCoinImpl<Fp, Sha256HashFn>>;
+    let mut rng = ark_std::test_rng();
+    let poly =
+        DensePolynomial::from_coefficients_vec((0..100).map(|_| Fp::rand(&mut rng)).collect());
+    let pp = Pcs::setup(127).unwrap();
+    let (ck, vk) = Pcs::trim(&pp, 99).unwrap();
+    let (commitment, state) = Pcs::commit(&ck, &poly).unwrap();
+
+    let point = Fp::rand(&mut rng);
+    let value = poly.evaluate(&point);
+    let sponge = || PublicCoinImpl::new(Default::default());
+    let proof = Pcs::open(&ck, &poly, &commitment, &state, point, &mut sponge()).unwrap();
+    assert!(Pcs::check(&vk, &commitment, point, value, proof.clone(), &mut sponge()).unwrap());
+    assert!(!Pcs::check(
+        &vk,
+        &commitment,
+        point,
+        value + Fp::one(),
+        proof,
+        &mut sponge()
+    )
+    .unwrap());
+
+    let large_poly = DensePolynomial::from_coefficients_vec(vec![Fp::one(); 101]);
+    assert!(matches!(
+        Pcs::commit(&ck, &large_poly),
+        Err(pcs::Error::DegreeTooLarge {
+            degree: 100,
+            max_degree: 99
+        })
+    ));
+}
@@ -0,0 +1,378 @@
+#![feature(allocator_api)]
+#![cfg(feature = "prover")]
+mod common;
+
+use ark_ff::Field;
+use ark_ff::One;
+use ark_ff::Zero;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use common::memory_accesses;
+use common::memory_columns;
+use ministark::air::AirConfig;
+use ministark::air::AuxSegment;
+use ministark::challenges::Challenges;
+use ministark::constraints::ConstraintGroup;
+use ministark::debug;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::lookup;
+use ministark::lookup::Lookup;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::permutation::PermutationArgument;
+use ministark::prover::ProvingError;
+use ministark::random::draw_multiple;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::tests::Absorb;
+use ministark::utils::tests::MockChannel;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::utils::SerdeOutput;
+use ministark::verifier::VerificationError;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::Proof;
+use ministark::ProofOptions;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Sha256;
+
+#[test]
+fn lookup_proves_values_are_in_table() {
+    const LOOKUP: Lookup = Lookup {
+        values: 0,
+        table: 1,
+        multiplicities: 2,
+        running_sum: 3,
+        challenge: 0,
+    };
+
+    struct LookupTrace(Matrix<Fp>);
+
+    impl Trace for LookupTrace {
+        type Fp = Fp;
+        type Fq = Fp;
+
+        fn base_columns(&self) -> &Matrix<Fp> {
+            &self.0
+        }
+
+        fn build_extension_columns(&self, challenges: &Challenges<Fp>) -> Option<Matrix<Fp>> {
+            Some(Matrix::new(vec![LOOKUP.running_sum(&self.0, challenges)]))
+        }
+    }
+
+    struct LookupAirConfig;
+
+    impl AirConfig for LookupAirConfig {
+        const NUM_BASE_COLUMNS: usize = 3;
+        const NUM_EXTENSION_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+            LOOKUP.constraint_groups(trace_len)
+        }
+    }
+
+    struct LookupClaim;
+
+    impl Stark for LookupClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = LookupAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = Vec<Fp>;
+        type Trace = LookupTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<LookupAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"lookup"))
+        }
+
+        // looks up `values` in a table of the squares of `0..64`
+        fn generate_trace(&self, values: Vec<Fp>) -> LookupTrace {
+            let table = (0..64u64).map(|i| Fp::from(i * i)).collect::<Vec<_>>();
+            // values that aren't in the table are given zero multiplicities
+            let multiplicities = lookup::multiplicities(&values, &table)
+                .unwrap_or_else(|| vec![Fp::zero(); table.len()]);
+            LookupTrace(Matrix::new(vec![
+                values.to_vec_in(GpuAllocator),
+                table.to_vec_in(GpuAllocator),
+                multiplicities.to_vec_in(GpuAllocator),
+            ]))
+        }
+    }
+
+    let values = (0..64u64)
+        .map(|i| Fp::from((i * 7 % 13).pow(2)))
+        .collect::<Vec<_>>();
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(LookupClaim.prove(options, values.clone())).unwrap();
+    LookupClaim.verify(proof, 1).unwrap();
+
+    // 2 isn't a square
+    let mut invalid_values = values;
+    invalid_values[9] = Fp::from(2u8);
+    let trace = LookupClaim.generate_trace(invalid_values);
+    let challenges = Challenges::new(vec![Fp::from(12345u32)]);
+    let extension_trace = trace.build_extension_columns(&challenges).unwrap();
+    assert!(matches!(
+        debug::default_validate_constraints(
+            &LookupClaim,
+            &challenges,
+            &Hints::default(),
+            trace.base_columns(),
+            Some(&extension_trace),
+        ),
+        Err(ProvingError::UnsatisfiedConstraint { .. })
+    ));
+}
+
+#[test]
+fn permutation_argument_proves_rows_are_shuffled() {
+    // rows of (address, value) in access order and sorted by address
+    fn permutation() -> PermutationArgument {
+        PermutationArgument {
+            lhs: vec![0, 1],
+            rhs: vec![2, 3],
+            running_product: 4,
+            alpha: 0,
+            beta: 1,
+        }
+    }
+
+    struct MemoryTrace(Matrix<Fp>);
+
+    impl Trace for MemoryTrace {
+        type Fp = Fp;
+        type Fq = Fp;
+
+        fn base_columns(&self) -> &Matrix<Fp> {
+            &self.0
+        }
+
+        fn build_extension_columns(&self, challenges: &Challenges<Fp>) -> Option<Matrix<Fp>> {
+            Some(Matrix::new(vec![
+                permutation().running_product(&self.0, challenges)
+            ]))
+        }
+    }
+
+    struct MemoryAirConfig;
+
+    impl AirConfig for MemoryAirConfig {
+        const NUM_BASE_COLUMNS: usize = 4;
+        const NUM_EXTENSION_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+            permutation().constraint_groups(trace_len)
+        }
+    }
+
+    struct MemoryClaim;
+
+    impl Stark for MemoryClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = MemoryAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = Vec<(u64, u64)>;
+        type Trace = MemoryTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<MemoryAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"permutation"))
+        }
+
+        fn generate_trace(&self, accesses: Vec<(u64, u64)>) -> MemoryTrace {
+            MemoryTrace(memory_columns(&accesses))
+        }
+    }
+
+    let accesses = memory_accesses();
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(MemoryClaim.prove(options, accesses.clone())).unwrap();
+    MemoryClaim.verify(proof, 1).unwrap();
+
+    // a sorted row that was never accessed breaks the permutation
+    let mut trace = MemoryClaim.generate_trace(accesses);
+    trace.0 .0[3][5] += Fp::one();
+    let challenges = Challenges::new(vec![Fp::from(12345u32), Fp::from(678u32)]);
+    let extension_trace = trace.build_extension_columns(&challenges).unwrap();
+    assert!(matches!(
+        debug::default_validate_constraints(
+            &MemoryClaim,
+            &challenges,
+            &Hints::default(),
+            trace.base_columns(),
+            Some(&extension_trace),
+        ),
+        Err(ProvingError::UnsatisfiedConstraint { .. })
+    ));
+}
+
+#[test]
+fn aux_segments_are_committed_in_separate_rounds() {
+    // rows of (address, value) are a permutation and so are the addresses alone.
+    // The second argument draws its challenges after the first is committed to
+    const PERMUTATIONS: [fn() -> PermutationArgument; 2] = [
+        || PermutationArgument {
+            lhs: vec![0, 1],
+            rhs: vec![2, 3],
+            running_product: 4,
+            alpha: 0,
+            beta: 1,
+        },
+        || PermutationArgument {
+            lhs: vec![0],
+            rhs: vec![2],
+            running_product: 5,
+            alpha: 2,
+            beta: 3,
+        },
+    ];
+
+    struct MemoryTrace(Matrix<Fp>);
+
+    impl Trace for MemoryTrace {
+        type Fp = Fp;
+        type Fq = Fp;
+
+        fn base_columns(&self) -> &Matrix<Fp> {
+            &self.0
+        }
+
+        fn build_aux_segment(
+            &self,
+            segment: usize,
+            aux_segments: &[Matrix<Fp>],
+            challenges: &Challenges<Fp>,
+        ) -> Option<Matrix<Fp>> {
+            assert_eq!(aux_segments.len(), segment);
+            let permutation = PERMUTATIONS[segment]();
+            Some(Matrix::new(vec![
+                permutation.running_product(&self.0, challenges)
+            ]))
+        }
+    }
+
+    struct MemoryAirConfig;
+
+    impl AirConfig for MemoryAirConfig {
+        const NUM_BASE_COLUMNS: usize = 4;
+        const NUM_EXTENSION_COLUMNS: usize = 2;
+        const AUX_SEGMENTS: &'static [AuxSegment] = &[
+            AuxSegment {
+                num_columns: 1,
+                num_challenges: 2,
+            },
+            AuxSegment {
+                num_columns: 1,
+                num_challenges: 2,
+            },
+        ];
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+            PERMUTATIONS
+                .iter()
+                .flat_map(|permutation| permutation().constraint_groups(trace_len))
+                .collect()
+        }
+    }
+
+    struct MemoryClaim;
+
+    impl Stark for MemoryClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = MemoryAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = Vec<(u64, u64)>;
+        type Trace = MemoryTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<MemoryAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"aux segments"))
+        }
+
+        fn generate_trace(&self, accesses: Vec<(u64, u64)>) -> MemoryTrace {
+            MemoryTrace(memory_columns(&accesses))
+        }
+    }
+
+    let accesses = memory_accesses();
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(MemoryClaim.prove(options, accesses)).unwrap();
+    assert!(proof.extension_trace_commitment.is_some());
+    assert_eq!(proof.aux_trace_commitments.len(), 1);
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    let proof = Proof::<MemoryClaim>::deserialize_compressed(&*bytes).unwrap();
+    MemoryClaim.verify(proof.clone(), 1).unwrap();
+
+    // the second segment must be committed to
+    let mut missing_segment = proof.clone();
+    missing_segment.aux_trace_commitments.clear();
+    assert!(matches!(
+        MemoryClaim.verify(missing_segment, 1),
+        Err(VerificationError::AuxSegmentCountMismatch {
+            expected: 2,
+            actual: 1
+        })
+    ));
+
+    // queries of the second segment must match its commitment
+    let mut wrong_commitment = proof;
+    wrong_commitment.aux_trace_commitments[0] = wrong_commitment.base_trace_commitment.clone();
+    assert!(MemoryClaim.verify(wrong_commitment, 1).is_err());
+}
+
+#[test]
+fn mock_channel_pins_extension_column_challenges() {
+    let permutation = PermutationArgument {
+        lhs: vec![0, 1],
+        rhs: vec![2, 3],
+        running_product: 4,
+        alpha: 0,
+        beta: 1,
+    };
+    // rows of (address, value) in access order and sorted by address
+    let base_trace = Matrix::from_row_iter([
+        [Fp::from(1u8), Fp::from(5u8), Fp::from(0u8), Fp::from(7u8)],
+        [Fp::from(0u8), Fp::from(7u8), Fp::from(1u8), Fp::from(5u8)],
+    ]);
+
+    let digest = Sha256HashFn::hash(*b"mock");
+    let mut channel = MockChannel::<SerdeOutput<Sha256>, Fp>::new(digest.clone())
+        .with_draws([Fp::from(10u8), Fp::from(2u8)]);
+    channel.reseed_with_digest(&digest);
+    channel.reseed_with_int(3);
+    let challenges = Challenges::new(draw_multiple(&mut channel, 2));
+    assert_eq!(channel.remaining_draws(), 0);
+    assert_eq!(channel.absorbs(), [Absorb::Digest(digest), Absorb::Int(3)]);
+
+    // (α - (1 + 5β)) / (α - (0 + 7β)) = (10 - 11) / (10 - 14)
+    let running_product = permutation.running_product(&base_trace, &challenges);
+    assert_eq!(running_product[0], Fp::from(4u8).inverse().unwrap());
+    assert_eq!(running_product[1], Fp::one());
+}
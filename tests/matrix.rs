@@ -0,0 +1,124 @@
+#![feature(allocator_api)]
+#![cfg(feature = "prover")]
+use ark_ff::FftField;
+use ark_ff::UniformRand;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark::matrix::RowMajorView;
+use ministark::utils::GpuAllocator;
+use ministark::Matrix;
+use ministark::MatrixView;
+use ministark::SegmentedMatrix;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn matrix_transposes_into_row_major_order() {
+    // enough rows to span several blocks of rows
+    let (num_rows, num_cols) = (200, 3);
+    let mut rng = ark_std::test_rng();
+    let rows = (0..num_rows)
+        .map(|_| (0..num_cols).map(|_| Fp::rand(&mut rng)).collect())
+        .collect::<Vec<Vec<Fp>>>();
+    let matrix = Matrix::from_rows(rows.clone());
+
+    let row_major = matrix.to_row_major();
+    let view = RowMajorView::new(&row_major, num_cols);
+    assert_eq!(view.num_rows(), num_rows);
+    assert!(view.rows().eq(rows.iter().map(Vec::as_slice)));
+    assert_eq!(view.row(num_rows - 1), rows[num_rows - 1].as_slice());
+
+    let transpose = matrix.transpose();
+    assert_eq!(transpose.num_cols(), num_rows);
+    assert_eq!(transpose.num_rows(), num_cols);
+    assert_eq!(transpose.transpose().0, matrix.0);
+
+    let mut visited = Vec::new();
+    matrix.for_each_row(5..150, |i, row| {
+        assert_eq!(row, rows[i].as_slice());
+        visited.push(i);
+    });
+    assert_eq!(visited, (5..150).collect::<Vec<usize>>());
+}
+
+#[test]
+fn segmented_matrix_indexes_columns_across_segments() {
+    let n = 8;
+    let mut rng = ark_std::test_rng();
+    let mut random_matrix = |num_cols: usize| {
+        Matrix::new(
+            (0..num_cols)
+                .map(|_| (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>())
+                .map(|col| col.to_vec_in(GpuAllocator))
+                .collect(),
+        )
+    };
+    let segments = [random_matrix(3), random_matrix(0), random_matrix(2)];
+    let x = Fp::rand(&mut rng);
+
+    let segmented = SegmentedMatrix::join(&segments);
+    let joined = Matrix::join(segments.to_vec());
+
+    assert_eq!(5, segmented.num_cols());
+    assert_eq!(n, segmented.num_rows());
+    for col in 0..joined.num_cols() {
+        assert_eq!(joined[col].as_slice(), &segmented[col]);
+    }
+    assert!(segmented.column(5).is_none());
+    assert_eq!(joined.get_row(n - 1), segmented.get_row(n - 1));
+    assert_eq!(joined.evaluate_at(x), segmented.evaluate_at(x));
+    assert_eq!(joined.0, segmented.to_matrix().0);
+}
+
+#[test]
+fn matrix_view_borrows_columns_across_matrices() {
+    let n = 8;
+    let mut rng = ark_std::test_rng();
+    let mut random_matrix = |num_cols: usize| {
+        Matrix::new(
+            (0..num_cols)
+                .map(|_| (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>())
+                .map(|col| col.to_vec_in(GpuAllocator))
+                .collect(),
+        )
+    };
+    let matrices = [random_matrix(2), random_matrix(0), random_matrix(3)];
+
+    let view = MatrixView::join(matrices.iter().map(MatrixView::from));
+    let joined = Matrix::join(matrices.to_vec());
+
+    assert_eq!(5, view.num_cols());
+    assert_eq!(n, view.num_rows());
+    assert!(view.column(5).is_none());
+    assert_eq!(joined.get_row(3), view.get_row(3));
+    assert_eq!(joined.0, view.to_matrix().0);
+    let sums = (0..n)
+        .map(|row| joined.iter().map(|col| col[row]).sum::<Fp>())
+        .collect::<Vec<Fp>>();
+    assert_eq!(sums, view.sum_columns().to_vec());
+
+    let rows = view.slice_rows(2..6);
+    assert_eq!(4, rows.num_rows());
+    assert_eq!(joined.get_row(2), rows.get_row(0));
+    assert_eq!(&sums[2..6], rows.sum_columns().as_slice());
+}
+
+#[test]
+fn matrix_interpolates_and_evaluates_large_domains() {
+    // large enough to run on the GPU when the `gpu` or `wgpu` feature is enabled
+    let n = 4096;
+    let mut rng = ark_std::test_rng();
+    let column = (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let lde_domain = Radix2EvaluationDomain::<Fp>::new(n * 2)
+        .unwrap()
+        .get_coset(Fp::GENERATOR)
+        .unwrap();
+
+    let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+    let polynomials = matrix.into_polynomials(trace_domain);
+    let coeffs = trace_domain.ifft(&column);
+    assert_eq!(coeffs, polynomials[0].to_vec());
+
+    let evaluations = polynomials.into_evaluations(lde_domain);
+    assert_eq!(lde_domain.fft(&coeffs), evaluations[0].to_vec());
+}
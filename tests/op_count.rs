@@ -0,0 +1,49 @@
+#![cfg(feature = "op-count")]
+mod common;
+
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use common::SquareClaim;
+use common::TRACE_LEN;
+use ministark::op_count;
+use ministark::op_count::OpCounts;
+use ministark::prover::ProvingStage;
+use ministark::stark::Stark;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+/// Checks the field operations counted in each proving stage
+#[test]
+fn operations_are_counted_per_stage() {
+    let domain = Radix2EvaluationDomain::<Fp>::new(TRACE_LEN).unwrap();
+    let counter = op_count::start();
+    let column = SquareClaim::<false>.generate_trace(None).0;
+    let _ = column.interpolate(domain);
+    // `n/2 log(n)` butterflies and scaling by `1/n`
+    assert_eq!(
+        counter.report()[&ProvingStage::TraceGeneration],
+        OpCounts::new(32 * 6 + 64, 64 * 6, 0)
+    );
+    drop(counter);
+
+    let options = ProofOptions::new(16, 4, 2, 2, 4);
+    let counter = op_count::start();
+    pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let report = counter.report();
+    drop(counter);
+    for stage in [
+        ProvingStage::BaseTraceCommitment,
+        ProvingStage::ConstraintEvaluation,
+        ProvingStage::CompositionTraceCommitment,
+        ProvingStage::DeepComposition,
+        ProvingStage::Fri,
+    ] {
+        assert_ne!(report[&stage].mul, 0, "no multiplications in {stage:?}");
+    }
+    assert_ne!(report[&ProvingStage::ConstraintEvaluation].inv, 0);
+
+    // counts don't depend on the hardware or timing
+    let counter = op_count::start();
+    pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    assert_eq!(counter.report(), report);
+}
@@ -0,0 +1,226 @@
+#![cfg(feature = "prover")]
+mod common;
+
+use ark_ff::Field;
+use ark_ff::One;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use common::ColumnsTrace;
+use common::SquareClaim;
+use ministark::air::AirConfig;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::prover::ProvingError;
+use ministark::random::PowHash;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::random::QuerySampling;
+use ministark::stark::Stark;
+use ministark::synthetic::SyntheticStark;
+use ministark::utils::FieldVariant;
+use ministark::utils::SerdeOutput;
+use ministark::verifier::VerificationError;
+use ministark::Air;
+use ministark::InvalidProofOptions;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+use sha2::Sha256;
+
+#[test]
+fn security_level_estimates() {
+    assert_eq!(
+        ProofOptions::with_128_bit_security(8),
+        ProofOptions::HIGH_SECURITY_128
+    );
+    assert_eq!(ProofOptions::with_100_bit_security(4).num_queries, 40);
+
+    // Goldilocks cubic extension
+    let options = ProofOptions::HIGH_SECURITY_128;
+    let (proven, conjectured) = options.security_level_bits(192, 1 << 20, 4);
+    assert_eq!(conjectured, 128);
+    assert!(proven < conjectured);
+    // the field bounds the security of large traces over small fields
+    let (proven, conjectured) = options.security_level_bits(64, 1 << 20, 4);
+    assert_eq!(conjectured, 64 - 23);
+    assert_eq!(proven, 64 - 23 - 2);
+}
+
+#[test]
+fn proof_options_are_validated_and_bound_to_the_transcript() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let mut invalid_options = options;
+    invalid_options.num_queries = 0;
+    assert!(matches!(
+        pollster::block_on(SquareClaim::<false>.prove(invalid_options, None)),
+        Err(ProvingError::InvalidOptions {
+            source: InvalidProofOptions::NumQueries { num_queries: 0 }
+        })
+    ));
+
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let mut invalid_proof = proof.clone();
+    invalid_proof.options.fri_folding_factor = 3;
+    assert!(matches!(
+        SquareClaim::<false>.verify(invalid_proof, 1),
+        Err(VerificationError::InvalidOptions {
+            source: InvalidProofOptions::FriFoldingFactor {
+                fri_folding_factor: 3
+            }
+        })
+    ));
+
+    // a proof is only valid for the options it was generated with
+    let mut modified_proof = proof.clone();
+    modified_proof.options.num_queries = 15;
+    assert!(SquareClaim::<false>.verify(modified_proof, 1).is_err());
+    SquareClaim::<false>.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn proof_of_work_hash_is_selectable() {
+    let stark = SyntheticStark::<2, 2, 2> { seed: 3 };
+    let options = ProofOptions::new(16, 4, 8, 2, 4).with_pow_hash(PowHash::Keccak256);
+    let proof = pollster::block_on(stark.prove(options, 64)).unwrap();
+
+    // the nonce is found with a hash other than the public coin's
+    let coin = || stark.gen_public_coin(&Air::new(64, 3, options));
+    let nonce = PowHash::Blake3.grind(&mut coin(), 8).unwrap();
+    assert!(PowHash::Blake3.verify(&mut coin(), 8, nonce));
+
+    let mut invalid_proof = proof.clone();
+    invalid_proof.pow_nonce += 1;
+    assert!(stark.verify(invalid_proof, 1).is_err());
+    let mut invalid_proof = proof.clone();
+    invalid_proof.options.pow_hash = PowHash::Blake3;
+    assert!(stark.verify(invalid_proof, 1).is_err());
+    stark.verify(proof, 1).unwrap();
+
+    assert_eq!(PowHash::PublicCoin.security_level_bits(), None);
+    assert_eq!(PowHash::Rpo256.security_level_bits(), Some(128));
+}
+
+#[test]
+fn query_sampling_is_selectable() {
+    let stark = SyntheticStark::<2, 2, 2> { seed: 3 };
+    // enough queries that drawing with replacement almost surely has duplicates
+    let options = ProofOptions::new(64, 4, 0, 2, 4).with_query_sampling(QuerySampling::Distinct);
+    let proof = pollster::block_on(stark.prove(options, 64)).unwrap();
+
+    let mut invalid_proof = proof.clone();
+    invalid_proof.options.query_sampling = QuerySampling::WithReplacement;
+    assert!(stark.verify(invalid_proof, 1).is_err());
+    let artifacts = stark.verify(proof, 1).unwrap();
+    assert_eq!(artifacts.query_positions.len(), 64);
+
+    // the LDE domain only has 32 positions
+    let res = pollster::block_on(stark.prove(options, 8));
+    assert!(matches!(
+        res,
+        Err(ProvingError::InvalidOptions {
+            source: InvalidProofOptions::TooManyDistinctQueries {
+                num_queries: 64,
+                lde_domain_size: 32,
+            }
+        })
+    ));
+
+    // distinct positions are worth more for small domains
+    let with_replacement = ProofOptions::new(16, 4, 0, 2, 4);
+    let distinct = with_replacement.with_query_sampling(QuerySampling::Distinct);
+    let (_, conjectured) = with_replacement.security_level_bits(192, 64, 2);
+    assert_eq!(conjectured, 32);
+    let (_, conjectured) = distinct.security_level_bits(192, 64, 2);
+    assert!(conjectured > 32);
+    let (_, conjectured) = distinct.security_level_bits(192, 1 << 20, 2);
+    assert_eq!(conjectured, 32);
+}
+
+#[test]
+fn insufficient_lde_blowup_factor_is_rejected() {
+    struct QuinticAirConfig;
+
+    impl AirConfig for QuinticAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+            vec![Constraint::new(
+                (0.next() - 0.curr() * 0.curr() * 0.curr() * 0.curr() * 0.curr())
+                    * ((X - last_x) / (X.pow(trace_len) - one)),
+            )]
+        }
+    }
+
+    struct QuinticClaim;
+
+    impl Stark for QuinticClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = QuinticAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = ();
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<QuinticAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"quintic"))
+        }
+
+        fn generate_trace(&self, _: ()) -> ColumnsTrace {
+            let mut v = Fp::from(3u8);
+            let rows = (0..64).map(|_| {
+                let row = [v];
+                v = v.pow([5]);
+                row
+            });
+            ColumnsTrace(Matrix::from_row_iter(rows))
+        }
+    }
+
+    // the degree 5 transition constraint needs a blowup factor of 4 or more
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let Ok(air) = Air::<QuinticAirConfig>::try_new(64, (), options) else {
+        panic!("LDE blowup factor is sufficient")
+    };
+    let ce_blowup_factor = air.ce_blowup_factor();
+    assert_eq!(ce_blowup_factor, 4);
+
+    let mut insufficient_options = options;
+    insufficient_options.lde_blowup_factor = 2;
+    assert!(matches!(
+        pollster::block_on(QuinticClaim.prove(insufficient_options, ())),
+        Err(ProvingError::InvalidOptions {
+            source: InvalidProofOptions::InsufficientLdeBlowupFactor {
+                lde_blowup_factor: 2,
+                ce_blowup_factor: 4,
+            }
+        })
+    ));
+
+    // verifiers reject the options of a proof rather than panicking
+    let proof = pollster::block_on(QuinticClaim.prove(options, ())).unwrap();
+    let mut invalid_proof = proof.clone();
+    invalid_proof.options.lde_blowup_factor = 2;
+    assert!(matches!(
+        QuinticClaim.verify(invalid_proof, 1),
+        Err(VerificationError::InvalidOptions {
+            source: InvalidProofOptions::InsufficientLdeBlowupFactor { .. }
+        })
+    ));
+    QuinticClaim.verify(proof, 1).unwrap();
+}
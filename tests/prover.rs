@@ -0,0 +1,477 @@
+#![cfg(feature = "prover")]
+mod common;
+
+use ark_ff::Field;
+use ark_ff::One;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalSerialize;
+use common::square_column;
+use common::square_transition;
+use common::ColumnsTrace;
+use common::SquareAirConfig;
+use common::SquareClaim;
+use common::TRACE_LEN;
+use core::future::Future;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering;
+use core::task::Context;
+use core::task::Waker;
+use core::time::Duration;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
+use ministark::checkpoint::Checkpoint;
+use ministark::checkpoint::CheckpointError;
+use ministark::checkpoint::MemoryCheckpointStore;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::events::DomainSizes;
+use ministark::events::ProverEvents;
+use ministark::events::StageReport;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::prover;
+use ministark::prover::ProvingError;
+use ministark::prover::ProvingStage;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::synthetic::SyntheticStark;
+use ministark::utils::yield_now;
+use ministark::utils::FieldVariant;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::Proof;
+use ministark::ProofOptions;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::task::Wake;
+
+fn proof_bytes<S: Stark>(proof: &Proof<S>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn low_memory_prover_generates_identical_proofs() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let low_memory_proof = pollster::block_on(SquareClaim::<true>.prove(options, None)).unwrap();
+    assert_eq!(proof_bytes(&proof), proof_bytes(&low_memory_proof));
+    SquareClaim::<true>.verify(low_memory_proof, 1).unwrap();
+}
+
+#[test]
+fn bit_reversed_pipeline_generates_identical_proofs() {
+    // computes LDEs and folds FRI layers in bit-reversed order
+    struct BitReversedSquareClaim<const LOW_MEMORY: bool>;
+
+    impl<const LOW_MEMORY: bool> Stark for BitReversedSquareClaim<LOW_MEMORY> {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = SquareAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = ();
+        type Trace = ColumnsTrace;
+
+        const LOW_MEMORY: bool = LOW_MEMORY;
+        const BIT_REVERSED_PIPELINE: bool = true;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<SquareAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"square"))
+        }
+
+        fn generate_trace(&self, _: ()) -> ColumnsTrace {
+            ColumnsTrace(square_column(Fp::from(3u8), TRACE_LEN))
+        }
+    }
+
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let bit_reversed_proof =
+        pollster::block_on(BitReversedSquareClaim::<false>.prove(options, ())).unwrap();
+    let low_memory_bit_reversed_proof =
+        pollster::block_on(BitReversedSquareClaim::<true>.prove(options, ())).unwrap();
+    assert_eq!(proof_bytes(&proof), proof_bytes(&bit_reversed_proof));
+    assert_eq!(
+        proof_bytes(&proof),
+        proof_bytes(&low_memory_bit_reversed_proof)
+    );
+    BitReversedSquareClaim::<false>
+        .verify(bit_reversed_proof, 1)
+        .unwrap();
+}
+
+#[test]
+fn async_provers_are_send() {
+    fn assert_send<T: Send>(_: &T) {}
+
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let claim = SquareClaim::<false>;
+    assert_send(&claim.prove(options, None));
+    assert_send(&prover::default_prove_async(&claim, options, None, |_| {
+        yield_now()
+    }));
+}
+
+#[test]
+fn async_provers_yield_to_the_executor() {
+    struct FlagWaker(AtomicBool);
+
+    impl Wake for FlagWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+
+    let mut task = Box::pin(prover::generate_proof_async(
+        Arc::new(SquareClaim::<false>),
+        options,
+        None,
+    ));
+    let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+    let waker = Waker::from(Arc::clone(&flag));
+    // the task yields before the first stage and asks to be polled again
+    assert!(task
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+    assert!(flag.0.load(Ordering::SeqCst));
+
+    // the task can be finished by an executor on another thread
+    let async_proof = std::thread::spawn(move || pollster::block_on(task))
+        .join()
+        .unwrap()
+        .unwrap();
+    assert_eq!(proof_bytes(&proof), proof_bytes(&async_proof));
+    SquareClaim::<false>.verify(async_proof, 1).unwrap();
+}
+
+#[test]
+fn degree_hints_generate_identical_proofs() {
+    // `HINTS` selects no hints, correct hints or a hint that's too low
+    struct FlagAirConfig<const HINTS: usize>;
+
+    impl<const HINTS: usize> AirConfig for FlagAirConfig<HINTS> {
+        const NUM_BASE_COLUMNS: usize = 3;
+        const BASE_COLUMN_DEGREE_HINTS: &'static [(usize, usize)] = match HINTS {
+            0 => &[],
+            1 => &[(1, 0), (2, 1)],
+            _ => &[(0, 1)],
+        };
+        const SANITIZE_WITNESS: bool = true;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            let g = Constant(FieldVariant::Fp(trace_domain.group_gen()));
+            let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+            let transition = (X - last_x) / (X.pow(trace_len) - one);
+            vec![
+                Constraint::new((0.next() - 0.curr() * 0.curr()) * transition.clone()),
+                Constraint::new((1.next() - 1.curr()) * transition.clone()),
+                Constraint::new((2.next() - 2.curr() * g) * transition),
+            ]
+        }
+    }
+
+    struct FlagClaim<const HINTS: usize, const LOW_MEMORY: bool>;
+
+    impl<const HINTS: usize, const LOW_MEMORY: bool> Stark for FlagClaim<HINTS, LOW_MEMORY> {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = FlagAirConfig<HINTS>;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = ();
+        type Trace = ColumnsTrace;
+
+        const LOW_MEMORY: bool = LOW_MEMORY;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<FlagAirConfig<HINTS>>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"flags"))
+        }
+
+        fn generate_trace(&self, _: ()) -> ColumnsTrace {
+            let trace_domain = Radix2EvaluationDomain::<Fp>::new(256).unwrap();
+            let mut v = Fp::from(3u8);
+            let rows = trace_domain.elements().map(|x| {
+                let row = [v, Fp::from(5u8), x];
+                v.square_in_place();
+                row
+            });
+            ColumnsTrace(Matrix::from_row_iter(rows))
+        }
+    }
+
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(FlagClaim::<0, false>.prove(options, ())).unwrap();
+    let hinted_proof = pollster::block_on(FlagClaim::<1, false>.prove(options, ())).unwrap();
+    let low_memory_hinted_proof =
+        pollster::block_on(FlagClaim::<1, true>.prove(options, ())).unwrap();
+    FlagClaim::<1, false>
+        .verify(hinted_proof.clone(), 1)
+        .unwrap();
+    assert_eq!(proof_bytes(&proof), proof_bytes(&hinted_proof));
+    assert_eq!(proof_bytes(&proof), proof_bytes(&low_memory_hinted_proof));
+
+    assert!(matches!(
+        pollster::block_on(FlagClaim::<2, false>.prove(options, ())),
+        Err(ProvingError::DegreeHintExceeded {
+            column: 0,
+            degree: 1
+        })
+    ));
+}
+
+#[test]
+fn proving_stops_when_the_deadline_passes() {
+    // trace generation takes the given duration
+    struct SlowSquareClaim;
+
+    impl Stark for SlowSquareClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = SquareAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = Duration;
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<SquareAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"deadline"))
+        }
+
+        fn generate_trace(&self, duration: Duration) -> ColumnsTrace {
+            std::thread::sleep(duration);
+            ColumnsTrace(square_column(Fp::from(3u8), 64))
+        }
+    }
+
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = prover::generate_proof_with_deadline(
+        &SlowSquareClaim,
+        options,
+        Duration::ZERO,
+        Duration::from_secs(60),
+    )
+    .unwrap();
+    SlowSquareClaim.verify(proof, 1).unwrap();
+
+    let res = prover::generate_proof_with_deadline(
+        &SlowSquareClaim,
+        options,
+        Duration::from_millis(100),
+        Duration::from_millis(10),
+    );
+    assert!(matches!(
+        res,
+        Err(ProvingError::DeadlineExceeded {
+            phase: ProvingStage::BaseTraceCommitment,
+            progress,
+        }) if progress > 0.0 && progress < 1.0
+    ));
+}
+
+#[test]
+fn provers_resume_from_checkpoints() {
+    let stark = SyntheticStark::<2, 2, 2> { seed: 3 };
+    let options = ProofOptions::new(16, 4, 8, 2, 4);
+    let proof = prover::default_prove(&stark, options, 64).unwrap();
+
+    let mut store = MemoryCheckpointStore::default();
+    let checkpointed_proof =
+        prover::prove_with_checkpoints(&stark, options, 64, &mut store).unwrap();
+    assert!(store.contains(Checkpoint::TraceCommitment));
+    assert!(store.contains(Checkpoint::ConstraintEvaluation));
+    assert!(store.contains(Checkpoint::Fri));
+    assert_eq!(proof_bytes(&proof), proof_bytes(&checkpointed_proof));
+
+    // the trace isn't regenerated once it's checkpointed so the witness is unused
+    store.remove(Checkpoint::Fri);
+    let resumed_proof = prover::prove_with_checkpoints(&stark, options, 0, &mut store).unwrap();
+    assert!(store.contains(Checkpoint::Fri));
+    assert_eq!(proof_bytes(&proof), proof_bytes(&resumed_proof));
+
+    // checkpoints of other public inputs are ignored
+    let other_stark = SyntheticStark::<2, 2, 2> { seed: 4 };
+    let other_proof =
+        prover::prove_with_checkpoints(&other_stark, options, 64, &mut store).unwrap();
+    other_stark.verify(other_proof, 1).unwrap();
+    stark.verify(resumed_proof, 1).unwrap();
+}
+
+#[test]
+fn provers_report_each_stage_to_events() {
+    #[derive(Default)]
+    struct RecordedEvents {
+        started: Vec<ProvingStage>,
+        finished: Vec<StageReport>,
+        domains: Option<DomainSizes>,
+    }
+
+    impl ProverEvents for RecordedEvents {
+        fn stage_started(&mut self, stage: ProvingStage) {
+            self.started.push(stage);
+        }
+
+        fn stage_finished(&mut self, report: &StageReport) {
+            self.finished.push(*report);
+        }
+
+        fn domains(&mut self, domains: &DomainSizes) {
+            self.domains = Some(*domains);
+        }
+    }
+
+    let stark = SyntheticStark::<2, 2, 2> { seed: 3 };
+    let options = ProofOptions::new(16, 4, 8, 2, 4);
+    let mut events = RecordedEvents::default();
+    let proof = prover::prove_with_events(&stark, options, 64, &mut events).unwrap();
+
+    let stages = [
+        ProvingStage::TraceGeneration,
+        ProvingStage::BaseTraceCommitment,
+        ProvingStage::ExtensionTraceCommitment,
+        ProvingStage::ConstraintEvaluation,
+        ProvingStage::CompositionTraceCommitment,
+        ProvingStage::DeepComposition,
+        ProvingStage::Fri,
+        ProvingStage::ProofOfWork,
+        ProvingStage::Queries,
+    ];
+    assert_eq!(events.started, stages);
+    assert_eq!(
+        events
+            .finished
+            .iter()
+            .map(|report| report.stage)
+            .collect::<Vec<_>>(),
+        stages
+    );
+    // traces are held in GPU allocations while they're committed to
+    assert!(events.finished[1].peak_memory > 0);
+    let domains = events.domains.unwrap();
+    assert_eq!(domains.trace, 64);
+    assert_eq!(domains.lde, 64 * 4);
+    assert_eq!(domains.num_base_columns, 2);
+    assert_eq!(domains.num_extension_columns, 0);
+    stark.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn provers_report_resources_used() {
+    let stark = SyntheticStark::<2, 2, 2> { seed: 3 };
+    let options = ProofOptions::new(16, 4, 8, 2, 4);
+    let (proof, report) = prover::prove_with_report(&stark, options, 64).unwrap();
+
+    let stages = report
+        .phase_durations
+        .iter()
+        .map(|(stage, _)| *stage)
+        .collect::<Vec<_>>();
+    assert_eq!(stages.first(), Some(&ProvingStage::TraceGeneration));
+    assert_eq!(stages.last(), Some(&ProvingStage::Queries));
+    assert!(stages.windows(2).all(|stages| stages[0] < stages[1]));
+    assert!(report.peak_memory > 0);
+    assert_eq!(report.proof_size, proof.compressed_size());
+    stark.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn resuming_from_a_checkpoint_of_another_air_fails() {
+    // squares in column 0 and shifts them by a challenge in extension column 1
+    struct ShiftedSquareAirConfig;
+
+    impl AirConfig for ShiftedSquareAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        const NUM_EXTENSION_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            let shift = (1.curr() - 0.curr() - Challenge(0)) / (X - one);
+            vec![square_transition(trace_len), Constraint::new(shift)]
+        }
+    }
+
+    struct ShiftedSquareTrace(Matrix<Fp>);
+
+    impl Trace for ShiftedSquareTrace {
+        type Fp = Fp;
+        type Fq = Fp;
+
+        fn base_columns(&self) -> &Matrix<Fp> {
+            &self.0
+        }
+
+        fn build_extension_columns(&self, challenges: &Challenges<Fp>) -> Option<Matrix<Fp>> {
+            let rows = self.0[0].iter().map(|&v| [v + challenges[0]]);
+            Some(Matrix::from_row_iter(rows))
+        }
+    }
+
+    struct ShiftedSquareClaim;
+
+    impl Stark for ShiftedSquareClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = ShiftedSquareAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = ();
+        type Trace = ShiftedSquareTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<ShiftedSquareAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"square"))
+        }
+
+        fn generate_trace(&self, _: ()) -> ShiftedSquareTrace {
+            ShiftedSquareTrace(square_column(Fp::from(3u8), TRACE_LEN))
+        }
+    }
+
+    // the checkpoint of an AIR without extension columns has the same
+    // fingerprint but none of the extension trace
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let mut store = MemoryCheckpointStore::default();
+    prover::prove_with_checkpoints(&SquareClaim::<false>, options, None, &mut store).unwrap();
+    assert!(matches!(
+        prover::prove_with_checkpoints(&ShiftedSquareClaim, options, (), &mut store),
+        Err(ProvingError::Checkpoint {
+            source: CheckpointError::Mismatch {
+                checkpoint: Checkpoint::TraceCommitment
+            }
+        })
+    ));
+}
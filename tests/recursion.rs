@@ -0,0 +1,181 @@
+#![cfg(feature = "prover")]
+mod common;
+
+use ark_ff::Field;
+use ark_ff::One;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use common::square_column;
+use common::ColumnsTrace;
+use common::PoseidonSquareClaim;
+use common::SquareAirConfig;
+use common::TRACE_LEN;
+use ministark::air::AirConfig;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hash::HashFn;
+use ministark::prover::ProvingError;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::recursion;
+use ministark::rescue::Rpo256Digest;
+use ministark::rescue::Rpo256HashFn;
+use ministark::rescue::Rpo256MatrixMerkleTree;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+
+#[test]
+fn recursive_proof_of_merkle_path_and_channel_hashes() {
+    struct RpoSquareClaim;
+
+    impl Stark for RpoSquareClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = SquareAirConfig;
+        type Digest = Rpo256Digest;
+        type PublicCoin = PublicCoinImpl<Fp, Rpo256HashFn>;
+        type MerkleTree = Rpo256MatrixMerkleTree;
+        type Witness = ();
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<SquareAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Rpo256HashFn::hash(*b"square"))
+        }
+
+        fn generate_trace(&self, _: ()) -> ColumnsTrace {
+            ColumnsTrace(square_column(Fp::from(3u8), TRACE_LEN))
+        }
+    }
+
+    let options = ProofOptions::new(8, 4, 0, 2, 4);
+    let proof = pollster::block_on(RpoSquareClaim.prove(options, ())).unwrap();
+    let (claim, witness) = recursion::rpo_verifier_claim(&RpoSquareClaim, proof).unwrap();
+
+    let options = ProofOptions::new(8, 16, 0, 2, 4);
+    let mut wrong_seed = recursion::RpoVerifierClaim(claim.0.clone());
+    wrong_seed.0.seed.0[0] += Fp::one();
+    assert!(matches!(
+        pollster::block_on(wrong_seed.prove(options, witness.clone())),
+        Err(ProvingError::UnsatisfiedConstraint { .. })
+    ));
+    let recursive_proof = pollster::block_on(claim.prove(options, witness)).unwrap();
+    claim.verify(recursive_proof, 1).unwrap();
+}
+
+#[test]
+fn aggregate_proof_of_claims_about_different_airs() {
+    /// Each row is the previous row raised to the power `E`
+    struct PowAirConfig<const E: usize>;
+
+    impl<const E: usize> AirConfig for PowAirConfig<E> {
+        const NUM_BASE_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            let last_x = Constant(FieldVariant::Fp(trace_domain.element(trace_len - 1)));
+            vec![Constraint::new(
+                (0.next() - 0.curr().pow(E)) * ((X - last_x) / (X.pow(trace_len) - one)),
+            )]
+        }
+    }
+
+    struct PowClaim<const E: usize>(usize);
+
+    impl<const E: usize> Stark for PowClaim<E> {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = PowAirConfig<E>;
+        type Digest = Rpo256Digest;
+        type PublicCoin = PublicCoinImpl<Fp, Rpo256HashFn>;
+        type MerkleTree = Rpo256MatrixMerkleTree;
+        type Witness = ();
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<PowAirConfig<E>>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Rpo256HashFn::hash(*b"pow"))
+        }
+
+        fn generate_trace(&self, _: ()) -> ColumnsTrace {
+            let mut v = Fp::from(3u8);
+            let rows = (0..self.0).map(|_| {
+                let row = [v];
+                v = v.pow([E as u64]);
+                row
+            });
+            ColumnsTrace(Matrix::from_row_iter(rows))
+        }
+    }
+
+    let options = ProofOptions::new(8, 4, 0, 2, 4);
+    let square_proof = pollster::block_on(PowClaim::<2>(64).prove(options, ())).unwrap();
+    let cube_proof = pollster::block_on(PowClaim::<3>(32).prove(options, ())).unwrap();
+    let (square, square_witness) =
+        recursion::rpo_verifier_claim(&PowClaim::<2>(64), square_proof).unwrap();
+    let (cube, cube_witness) =
+        recursion::rpo_verifier_claim(&PowClaim::<3>(32), cube_proof).unwrap();
+
+    // claims identify the AIR of their proof
+    let square_air = Air::<PowAirConfig<2>>::try_new(64, (), options).unwrap();
+    assert_eq!(square.0.air_digest, square_air.digest::<Rpo256HashFn>());
+    assert_ne!(square.0.air_digest, cube.0.air_digest);
+
+    let options = ProofOptions::new(8, 16, 0, 2, 4);
+    let witnesses = [square_witness, cube_witness];
+    let mut wrong_air = recursion::RpoAggregateClaim([square.0.clone(), cube.0.clone()]);
+    wrong_air.0[1].air_digest = square.0.air_digest;
+    assert!(matches!(
+        pollster::block_on(wrong_air.prove(options, witnesses.clone())),
+        Err(ProvingError::UnsatisfiedConstraint { .. })
+    ));
+    let aggregate = recursion::RpoAggregateClaim([square.0, cube.0]);
+    let aggregate_proof = pollster::block_on(aggregate.prove(options, witnesses)).unwrap();
+    aggregate.verify(aggregate_proof, 1).unwrap();
+}
+
+#[test]
+fn recursive_proof_of_poseidon_merkle_path_and_transcript() {
+    let options = ProofOptions::new(8, 4, 0, 2, 4);
+    let proof = pollster::block_on(PoseidonSquareClaim.prove(options, ())).unwrap();
+    let composition_coeff = {
+        // the first challenge after the base trace commitment is the first
+        // composition coefficient since the AIR has no extension columns
+        let air = Air::<SquareAirConfig>::try_new(TRACE_LEN, (), options).unwrap();
+        let mut public_coin = PoseidonSquareClaim.gen_public_coin(&air);
+        ministark::channel::absorb_instance::<PoseidonSquareClaim>(&mut public_coin, &air);
+        public_coin.reseed_with_digest(&proof.base_trace_commitment);
+        public_coin.draw()
+    };
+    let (claim, witness) = recursion::poseidon_verifier_claim(&PoseidonSquareClaim, proof).unwrap();
+    assert_eq!(composition_coeff, claim.0.challenge);
+
+    let options = ProofOptions::new(8, 16, 0, 2, 4);
+    let mut wrong_challenge = recursion::PoseidonVerifierClaim(claim.0.clone());
+    wrong_challenge.0.challenge += Fp::one();
+    assert!(matches!(
+        pollster::block_on(wrong_challenge.prove(options, witness.clone())),
+        Err(ProvingError::UnsatisfiedConstraint { .. })
+    ));
+    let mut wrong_commitment = recursion::PoseidonVerifierClaim(claim.0.clone());
+    wrong_commitment.0.base_trace_commitment.0[0] += Fp::one();
+    assert!(matches!(
+        pollster::block_on(wrong_commitment.prove(options, witness.clone())),
+        Err(ProvingError::UnsatisfiedConstraint { .. })
+    ));
+    let recursive_proof = pollster::block_on(claim.prove(options, witness)).unwrap();
+    claim.verify(recursive_proof, 1).unwrap();
+}
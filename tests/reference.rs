@@ -0,0 +1,45 @@
+#![cfg(feature = "reference-verify")]
+use ark_ff::Field;
+use ministark::reference;
+use ministark::stark::Stark;
+use ministark::synthetic::SyntheticStark;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn reference_verifier_accepts_valid_proofs() {
+    let stark = SyntheticStark::<3, 5, 3> { seed: 7 };
+    for options in [
+        ProofOptions::new(16, 4, 0, 2, 4),
+        ProofOptions::new(16, 8, 8, 4, 2),
+    ] {
+        let proof = pollster::block_on(stark.prove(options, 128)).unwrap();
+        reference::verify(&stark, &proof, 1).unwrap();
+        // also checked by the reference verifier with the feature enabled
+        stark.verify(proof, 1).unwrap();
+    }
+}
+
+#[test]
+fn reference_verifier_rejects_tampered_proofs() {
+    let stark = SyntheticStark::<3, 5, 3> { seed: 7 };
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(stark.prove(options, 128)).unwrap();
+
+    let mut invalid_proof = proof.clone();
+    invalid_proof.composition_trace_ood_evals[0] += Fp::ONE;
+    assert_eq!(
+        reference::verify(&stark, &invalid_proof, 1),
+        Err(reference::Error::OodConstraintEvaluation)
+    );
+    assert!(stark.verify(invalid_proof, 1).is_err());
+
+    let mut invalid_proof = proof.clone();
+    invalid_proof.fri_proof.remainder_coeffs[0] += Fp::ONE;
+    assert!(reference::verify(&stark, &invalid_proof, 1).is_err());
+    assert!(stark.verify(invalid_proof, 1).is_err());
+
+    let other_stark = SyntheticStark::<3, 5, 3> { seed: 8 };
+    assert!(reference::verify(&other_stark, &proof, 1).is_err());
+    assert!(other_stark.verify(proof, 1).is_err());
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "prover")]
+use ark_serialize::CanonicalSerialize;
+use ministark::spill;
+use ministark::spill::SpillConfig;
+use ministark::stark::Stark;
+use ministark::synthetic::synthetic_trace;
+use ministark::synthetic::SyntheticStark;
+use ministark::ProofOptions;
+
+#[test]
+fn spilled_matrices_give_identical_proofs() {
+    let stark = SyntheticStark::<4, 4, 2> { seed: 3 };
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let prove = || {
+        let proof = pollster::block_on(stark.prove(options, 1 << 12)).unwrap();
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+    let in_memory = prove();
+
+    spill::enable(SpillConfig {
+        dir: std::env::temp_dir(),
+        min_size: 1 << 14,
+    });
+    let columns = synthetic_trace(3, 4, 1 << 12);
+    let small_columns = synthetic_trace(3, 4, 1 << 4);
+    let spilled = prove();
+    spill::disable();
+
+    assert!(columns.is_spilled());
+    assert!(!small_columns.is_spilled());
+    assert!(!synthetic_trace(3, 4, 1 << 12).is_spilled());
+    assert_eq!(in_memory, spilled);
+}
@@ -0,0 +1,302 @@
+#![cfg(feature = "prover")]
+mod common;
+
+use ark_ff::BigInt;
+use ark_ff::One;
+use ark_ff::UniformRand;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use common::ColumnsTrace;
+use core::marker::PhantomData;
+use ministark::air::AirConfig;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::debug;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::layout::TraceLayout;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::prover::ProvingError;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::synthetic::synthetic_trace;
+use ministark::synthetic::SyntheticStark;
+use ministark::trace::num_columns;
+use ministark::trace::range_constraints;
+use ministark::trace::Cell;
+use ministark::trace::CellType;
+use ministark::trace::TraceBuilder;
+use ministark::trace::TraceBuilderError;
+use ministark::utils::FieldVariant;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp as WideFp;
+use sha2::Sha256;
+
+#[test]
+fn trace_builder_range_constraints_hold() {
+    use AlgebraicItem::*;
+    let n = 16;
+    let column_types = [
+        CellType::U8,
+        CellType::Bool,
+        CellType::Felt,
+        CellType::U16,
+        CellType::U64,
+    ];
+    let mut builder = TraceBuilder::<Fp>::new(&column_types);
+    let mut rng = ark_std::test_rng();
+    for i in 0..n {
+        builder.push(0, (i * 17) as u8);
+        builder.push(1, i % 3 == 0);
+        builder.push(2, Cell::Felt(Fp::rand(&mut rng)));
+        builder.push(3, Cell::Felt(Fp::from(i as u64 * 4099)));
+        builder.push(4, (i as u64) << 40);
+    }
+    let mut matrix = builder.build().unwrap();
+    assert_eq!(num_columns(&column_types), matrix.num_cols());
+    assert_eq!(Fp::from(17u8), matrix[0][1]);
+
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let constraints = range_constraints::<Fp, Fp>(&column_types, n);
+    let check_row = |matrix: &Matrix<Fp>, row: usize| {
+        constraints.iter().all(|constraint| {
+            let result = constraint.check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(trace_domain.element(row)),
+                &Constant(v) => v,
+                &Trace(col, 0) => FieldVariant::Fp(matrix[col][row]),
+                _ => unreachable!(),
+            });
+            result.is_some()
+        })
+    };
+    assert!((0..n).all(|row| check_row(&matrix, row)));
+
+    // flip a bit in the decomposition of the u8 column
+    let bit_col = column_types.len();
+    matrix[bit_col][3] = Fp::one() - matrix[bit_col][3];
+    assert!(!check_row(&matrix, 3));
+}
+
+#[test]
+fn trace_layout_names_columns() {
+    use AlgebraicItem::*;
+    ministark::trace_layout! {
+        struct FibColumns {
+            a,
+            b,
+        }
+    }
+    const COL: FibColumns<usize> = FibColumns::COLUMNS;
+    let n = 8;
+    let rows = (0..n).scan((Fp::one(), Fp::one()), |(a, b), _| {
+        let row = FibColumns { a: *a, b: *b };
+        (*a, *b) = (*b, *a + *b);
+        Some(row)
+    });
+    let matrix = Matrix::from_layout_rows(rows);
+    assert_eq!(vec!["a", "b"], FibColumns::<Fp>::COLUMN_NAMES);
+    assert_eq!(FibColumns::<Fp>::NUM_COLUMNS, matrix.num_cols());
+    assert_eq!((0, 1), (COL.a, COL.b));
+    let last_row = FibColumns::from_values(matrix.get_row(n - 1).unwrap());
+    assert_eq!(Fp::from(21u8), last_row.a);
+    assert_eq!(Fp::from(34u8), last_row.b);
+
+    let constraints: [Constraint<FieldVariant<Fp, Fp>>; 2] = [
+        Constraint::new(COL.a.next() - COL.b.curr()),
+        Constraint::new(COL.b.next() - COL.a.curr() - COL.b.curr()),
+    ];
+    for row in 0..n - 1 {
+        for constraint in &constraints {
+            let result = constraint.check(&mut |leaf| match leaf {
+                &Trace(col, offset) => FieldVariant::Fp(matrix[col][row + offset as usize]),
+                _ => unreachable!(),
+            });
+            assert!(result.is_some());
+        }
+    }
+}
+
+#[test]
+fn trace_builder_rejects_out_of_range_values() {
+    let mut builder = TraceBuilder::<Fp>::new(&[CellType::Bool, CellType::U8]);
+    builder.push(0, true);
+    builder.push(1, 3u8);
+    builder.push(0, false);
+    builder.push(1, Cell::Felt(Fp::from(256u32)));
+    assert!(matches!(
+        builder.build(),
+        Err(TraceBuilderError::ValueOutOfRange {
+            column: 1,
+            row: 1,
+            cell_type: CellType::U8
+        })
+    ));
+
+    // the modulus of the Goldilocks field is less than `2^64`
+    let mut builder = TraceBuilder::<Fp>::new(&[CellType::U64]);
+    builder.push(0, u64::MAX);
+    assert!(matches!(
+        builder.build(),
+        Err(TraceBuilderError::ValueOutOfRange { row: 0, .. })
+    ));
+
+    let mut builder = TraceBuilder::<Fp>::new(&[CellType::U8, CellType::U8]);
+    builder.push(0, 1u8);
+    assert!(matches!(
+        builder.build(),
+        Err(TraceBuilderError::ColumnLengthMismatch {
+            column: 1,
+            len: 0,
+            expected: 1
+        })
+    ));
+}
+
+#[test]
+fn trace_builder_range_checks_u64_in_wide_fields() {
+    use AlgebraicItem::*;
+    let n = 4;
+    let column_types = [CellType::U64];
+    let mut builder = TraceBuilder::<WideFp>::new(&column_types);
+    for v in [0, 1, u64::MAX, 1 << 63] {
+        builder.push(0, v);
+    }
+    let mut matrix = builder.build().unwrap();
+    assert_eq!(65, matrix.num_cols());
+
+    let trace_domain = Radix2EvaluationDomain::<WideFp>::new(n).unwrap();
+    let constraints = range_constraints::<WideFp, WideFp>(&column_types, n);
+    let check_row = |matrix: &Matrix<WideFp>, row: usize| {
+        constraints.iter().all(|constraint| {
+            let result = constraint.check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(trace_domain.element(row)),
+                &Constant(v) => v,
+                &Trace(col, 0) => FieldVariant::Fp(matrix[col][row]),
+                _ => unreachable!(),
+            });
+            result.is_some()
+        })
+    };
+    assert!((0..n).all(|row| check_row(&matrix, row)));
+
+    // `2^64` has no 64 bit decomposition
+    matrix[0][2] += WideFp::one();
+    assert!(!check_row(&matrix, 2));
+
+    let mut builder = TraceBuilder::<WideFp>::new(&column_types);
+    builder.push(0, Cell::Felt(WideFp::from(u64::MAX) + WideFp::one()));
+    assert!(matches!(
+        builder.build(),
+        Err(TraceBuilderError::ValueOutOfRange {
+            column: 0,
+            row: 0,
+            cell_type: CellType::U64
+        })
+    ));
+}
+
+#[test]
+fn trace_builder_fills_rows_in_chunks() {
+    // column 0 is the row index and column 1 is the sum of all previous indices
+    let gen_row = |i: usize, prev_row: Option<&[Fp]>, row: &mut [Fp]| {
+        row[0] = Fp::from(i as u64);
+        row[1] = match prev_row {
+            Some(prev_row) => prev_row[0] + prev_row[1],
+            None => Fp::from((i * i.saturating_sub(1) / 2) as u64),
+        };
+    };
+
+    let sequential = TraceBuilder::<Fp>::fill_parallel(2, 100, 100, gen_row);
+    let chunked = TraceBuilder::<Fp>::fill_parallel(2, 100, 16, gen_row);
+
+    assert_eq!(sequential.num_rows(), 100);
+    assert_eq!(sequential.0, chunked.0);
+    assert_eq!(chunked.0[1][99], Fp::from(99 * 98 / 2u64));
+}
+
+#[test]
+fn synthetic_traces_are_deterministic_and_provable() {
+    type Synthetic = SyntheticStark<4, 6, 3>;
+    let stark = Synthetic { seed: 7 };
+
+    let trace = stark.generate_trace(256);
+    assert_eq!(trace.base_columns().0, synthetic_trace(7, 4, 256).0);
+    assert_ne!(trace.base_columns().0, synthetic_trace(8, 4, 256).0);
+
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(stark.prove(options, 256)).unwrap();
+    stark.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn witness_sanitization_rejects_invalid_values() {
+    struct BitAirConfig;
+
+    impl AirConfig for BitAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        const BASE_COLUMN_TYPES: &'static [CellType] = &[CellType::Bool];
+        const SANITIZE_WITNESS: bool = true;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+    }
+
+    struct BitClaim;
+
+    impl Stark for BitClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = BitAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = (usize, Fp);
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) {}
+
+        fn gen_public_coin(&self, _: &Air<BitAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(*b"bits"))
+        }
+
+        // generates a trace of bits with `value` at row `row`
+        fn generate_trace(&self, (row, value): (usize, Fp)) -> ColumnsTrace {
+            let mut rows = (0..64).map(|i| [Fp::from(i % 2)]).collect::<Vec<_>>();
+            rows[row][0] = value;
+            ColumnsTrace(Matrix::from_row_iter(rows))
+        }
+    }
+
+    let sanitize = |row, value| {
+        let trace = BitClaim.generate_trace((row, value));
+        debug::sanitize_base_trace::<BitAirConfig>(trace.base_columns())
+    };
+    sanitize(0, Fp::one()).unwrap();
+    assert!(matches!(
+        sanitize(3, Fp::from(2u8)),
+        Err(ProvingError::ValueOutOfRange {
+            column: 0,
+            row: 3,
+            cell_type: CellType::Bool
+        })
+    ));
+    // the modulus of the Goldilocks field is `2^64 - 2^32 + 1`
+    let non_canonical = ark_ff::Fp(BigInt([u64::MAX]), PhantomData);
+    assert!(matches!(
+        sanitize(7, non_canonical),
+        Err(ProvingError::NonCanonicalValue { column: 0, row: 7 })
+    ));
+
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    assert!(matches!(
+        pollster::block_on(BitClaim.prove(options, (7, non_canonical))),
+        Err(ProvingError::NonCanonicalValue { column: 0, row: 7 })
+    ));
+}
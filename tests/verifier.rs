@@ -0,0 +1,214 @@
+#![cfg(feature = "prover")]
+mod common;
+
+use ark_ff::One;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use common::square_column;
+use common::square_transition;
+use common::ColumnsTrace;
+use common::SquareClaim;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::proof::ProofFrame;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::synthetic::SyntheticStark;
+use ministark::utils::FieldVariant;
+use ministark::utils::SerdeOutput;
+use ministark::verifier;
+use ministark::verifier::PreparedVerifyingKey;
+use ministark::verifier::StreamingVerifier;
+use ministark::verifier::VerificationError;
+use ministark::Air;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Sha256;
+
+#[test]
+fn prepared_verifying_key_verifies_proofs_of_any_public_inputs() {
+    struct StartAirConfig;
+
+    impl AirConfig for StartAirConfig {
+        const NUM_BASE_COLUMNS: usize = 1;
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = u64;
+
+        fn gen_hints(_: usize, start: &u64, _: &Challenges<Fp>) -> Hints<Fp> {
+            Hints::new(vec![(0, Fp::from(*start))])
+        }
+
+        fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let one = Constant(FieldVariant::Fp(Fp::one()));
+            vec![
+                square_transition(trace_len),
+                Constraint::new((0.curr() - Hint(0)) / (X - one)),
+            ]
+        }
+    }
+
+    struct StartClaim(u64);
+
+    impl Stark for StartClaim {
+        type Fp = Fp;
+        type Fq = Fp;
+        type AirConfig = StartAirConfig;
+        type Digest = SerdeOutput<Sha256>;
+        type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+        type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        type Witness = ();
+        type Trace = ColumnsTrace;
+
+        fn get_public_inputs(&self) -> u64 {
+            self.0
+        }
+
+        fn gen_public_coin(&self, _: &Air<StartAirConfig>) -> Self::PublicCoin {
+            PublicCoinImpl::new(Sha256HashFn::hash(self.0.to_le_bytes()))
+        }
+
+        fn generate_trace(&self, _: ()) -> ColumnsTrace {
+            ColumnsTrace(square_column(Fp::from(self.0), 64))
+        }
+    }
+
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let key = PreparedVerifyingKey::new(&StartClaim(3), 64, options);
+    for start in [3, 5] {
+        let proof = pollster::block_on(StartClaim(start).prove(options, ())).unwrap();
+        let artifacts = key.verify(&StartClaim(start), proof.clone(), 1).unwrap();
+        assert_eq!(artifacts.air_hints[0], Fp::from(start));
+        assert!(key.verify(&StartClaim(start + 1), proof, 1).is_err());
+    }
+
+    // proofs with different options are verified without the key's setup work
+    let options = options.with_num_queries(20);
+    let proof = pollster::block_on(StartClaim(7).prove(options, ())).unwrap();
+    key.verify(&StartClaim(7), proof, 1).unwrap();
+}
+
+#[test]
+fn verifier_reports_soundness_of_each_proof() {
+    let stark = SyntheticStark::<2, 2, 2> { seed: 3 };
+    let options = ProofOptions::new(16, 4, 8, 2, 4);
+    let proof = pollster::block_on(stark.prove(options, 64)).unwrap();
+    let lde_domain_size = proof.trace_len * 4;
+    let (artifacts, report) = verifier::verify_with_report(&stark, proof, 1).unwrap();
+
+    assert_eq!(report.num_queries, 16);
+    assert_eq!(report.distinct_positions, artifacts.query_positions.len());
+    assert_eq!(report.lde_domain_size, lde_domain_size);
+    assert_eq!(report.fri_layer_sizes.first(), Some(&lde_domain_size));
+    assert!(report
+        .fri_layer_sizes
+        .windows(2)
+        .all(|sizes| sizes[1] == sizes[0] / 2));
+    assert!(report.fri_remainder_size <= 4 * 4);
+    // each distinct position passes with probability at most `1 / 4`
+    let distinct_positions = i32::try_from(report.distinct_positions).unwrap();
+    assert!(report.query_error <= 2f64.powi(-2 * distinct_positions - 8));
+    assert!(report.field_error < report.query_error);
+    assert_eq!(
+        report.soundness_error(),
+        report.query_error + report.field_error
+    );
+    assert!(report.security_bits() <= f64::from(report.hash_security_bits));
+}
+
+#[test]
+fn streaming_verifier_verifies_one_frame_at_a_time() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let expected = SquareClaim::<false>.verify(proof.clone(), 1).unwrap();
+
+    let mut verifier = StreamingVerifier::new(&SquareClaim::<false>, 1);
+    let [commitments, fri, queries] = proof.into_frames();
+    assert!(verifier.verify_frame(commitments).unwrap().is_none());
+    assert!(!verifier.is_finished());
+    assert!(verifier.verify_frame(fri).unwrap().is_none());
+    assert!(!verifier.is_finished());
+    let artifacts = verifier.verify_frame(queries).unwrap().unwrap();
+    assert_eq!(expected.fri_alphas, artifacts.fri_alphas);
+    assert_eq!(expected.query_positions, artifacts.query_positions);
+    assert!(verifier.is_finished());
+}
+
+#[test]
+fn streaming_verifier_rejects_bad_frames_early() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let [mut commitments, fri, _] = proof.into_frames();
+    let ProofFrame::Commitments {
+        composition_trace_ood_evals,
+        ..
+    } = &mut commitments
+    else {
+        unreachable!()
+    };
+    composition_trace_ood_evals[0] += Fp::one();
+
+    // the proof is rejected before the FRI and query frames arrive
+    let mut verifier = StreamingVerifier::new(&SquareClaim::<false>, 1);
+    assert!(matches!(
+        verifier.verify_frame(commitments),
+        Err(VerificationError::InconsistentOodConstraintEvaluations { .. })
+    ));
+    assert!(verifier.is_finished());
+    assert!(matches!(
+        verifier.verify_frame(fri),
+        Err(VerificationError::UnexpectedProofFrame)
+    ));
+}
+
+#[test]
+fn streaming_verifier_rejects_out_of_order_frames() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let [commitments, fri, queries] = proof.into_frames();
+
+    let mut verifier = StreamingVerifier::new(&SquareClaim::<false>, 1);
+    assert!(matches!(
+        verifier.verify_frame(fri.clone()),
+        Err(VerificationError::UnexpectedProofFrame)
+    ));
+    assert!(verifier.is_finished());
+
+    let mut verifier = StreamingVerifier::new(&SquareClaim::<false>, 1);
+    verifier.verify_frame(commitments).unwrap();
+    assert!(matches!(
+        verifier.verify_frame(queries),
+        Err(VerificationError::UnexpectedProofFrame)
+    ));
+}
+
+#[test]
+fn proof_frames_serialize_round_trip() {
+    let options = ProofOptions::new(16, 4, 0, 2, 4);
+    let proof = pollster::block_on(SquareClaim::<false>.prove(options, None)).unwrap();
+    let expected = SquareClaim::<false>.verify(proof.clone(), 1).unwrap();
+
+    let mut verifier = StreamingVerifier::new(&SquareClaim::<false>, 1);
+    let mut artifacts = None;
+    for frame in proof.into_frames() {
+        let mut bytes = Vec::new();
+        frame.serialize_compressed(&mut bytes).unwrap();
+        let decoded = ProofFrame::<SquareClaim>::deserialize_compressed(&*bytes).unwrap();
+        let mut decoded_bytes = Vec::new();
+        decoded.serialize_compressed(&mut decoded_bytes).unwrap();
+        assert_eq!(bytes, decoded_bytes);
+        artifacts = verifier.verify_frame(decoded).unwrap();
+    }
+    let artifacts = artifacts.unwrap();
+    assert_eq!(expected.fri_alphas, artifacts.fri_alphas);
+    assert_eq!(expected.query_positions, artifacts.query_positions);
+}
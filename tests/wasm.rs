@@ -0,0 +1,101 @@
+#![cfg(all(feature = "wasm", feature = "prover"))]
+mod common;
+
+use ark_serialize::CanonicalSerialize;
+use common::square_column;
+use common::ColumnsTrace;
+use common::TRACE_LEN;
+use ministark::air::AirConfig;
+use ministark::compression;
+use ministark::constraints::ConstraintGroup;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::SerdeOutput;
+use ministark::wasm::WasmStark;
+use ministark::Air;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Sha256;
+
+struct PublicSquareAirConfig;
+
+impl AirConfig for PublicSquareAirConfig {
+    const NUM_BASE_COLUMNS: usize = 1;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = u64;
+
+    fn constraint_groups(trace_len: usize) -> Vec<ConstraintGroup<FieldVariant<Fp, Fp>>> {
+        vec![ConstraintGroup::transitions(
+            trace_len,
+            vec![0.next() - 0.curr() * 0.curr()],
+        )]
+    }
+}
+
+/// Claims to know the repeated squares of the public input
+struct PublicSquareClaim(u64);
+
+impl Stark for PublicSquareClaim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = PublicSquareAirConfig;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = ();
+    type Trace = ColumnsTrace;
+
+    fn get_public_inputs(&self) -> u64 {
+        self.0
+    }
+
+    fn gen_public_coin(&self, air: &Air<PublicSquareAirConfig>) -> Self::PublicCoin {
+        let mut seed = Vec::new();
+        air.public_inputs().serialize_compressed(&mut seed).unwrap();
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
+    }
+
+    fn generate_trace(&self, _: ()) -> ColumnsTrace {
+        ColumnsTrace(square_column(Fp::from(self.0), TRACE_LEN))
+    }
+}
+
+impl WasmStark for PublicSquareClaim {
+    const REQUIRED_SECURITY_BITS: u32 = 1;
+
+    fn from_public_inputs(public_inputs: u64) -> Self {
+        Self(public_inputs)
+    }
+}
+
+mod exported {
+    ministark::wasm_verifier!(super::PublicSquareClaim);
+}
+
+#[test]
+fn exported_verifier_checks_proofs_against_public_inputs() {
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(PublicSquareClaim(3).prove(options, ())).unwrap();
+    let proof_bytes = compression::encode_proof(&proof, &compression::Uncompressed);
+    let public_input_bytes = |public_inputs: u64| {
+        let mut bytes = Vec::new();
+        public_inputs.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+
+    assert!(exported::verify(&proof_bytes, &public_input_bytes(3)));
+    assert!(!exported::verify(&proof_bytes, &public_input_bytes(4)));
+    // malformed inputs are rejected rather than panicking
+    assert!(!exported::verify(&proof_bytes, &[]));
+    assert!(!exported::verify(
+        &proof_bytes[..16],
+        &public_input_bytes(3)
+    ));
+}
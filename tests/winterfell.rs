@@ -0,0 +1,335 @@
+#![cfg(feature = "winterfell")]
+use ark_serialize::CanonicalSerialize;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::SerdeOutput;
+use ministark::winterfell::ArkField;
+use ministark::winterfell::Error;
+use ministark::winterfell::WinterfellAirConfig;
+use ministark::winterfell::WinterfellTrace;
+use ministark::Air;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Sha256;
+use winter_air::Air as WinterAir;
+use winter_air::AirContext;
+use winter_air::Assertion;
+use winter_air::EvaluationFrame;
+use winter_air::FieldExtension;
+use winter_air::TraceInfo;
+use winter_air::TransitionConstraintDegree;
+use winter_math::fields::f64::BaseElement;
+use winter_math::FieldElement;
+use winter_math::ToElements;
+
+const TRACE_LEN: usize = 32;
+
+/// Result of a Fibonacci computation
+#[derive(Clone, Copy, Default)]
+struct FibInputs {
+    result: BaseElement,
+}
+
+impl ToElements<BaseElement> for FibInputs {
+    fn to_elements(&self) -> Vec<BaseElement> {
+        vec![self.result]
+    }
+}
+
+/// The two column Fibonacci AIR from Winterfell's examples. Each row holds two
+/// consecutive Fibonacci numbers.
+struct FibAir {
+    context: AirContext<BaseElement>,
+    result: BaseElement,
+}
+
+impl WinterAir for FibAir {
+    type BaseField = BaseElement;
+    type PublicInputs = FibInputs;
+
+    fn new(
+        trace_info: TraceInfo,
+        pub_inputs: FibInputs,
+        options: winter_air::ProofOptions,
+    ) -> Self {
+        let degrees = vec![
+            TransitionConstraintDegree::new(1),
+            TransitionConstraintDegree::new(1),
+        ];
+        Self {
+            context: AirContext::new(trace_info, degrees, 3, options),
+            result: pub_inputs.result,
+        }
+    }
+
+    fn context(&self) -> &AirContext<BaseElement> {
+        &self.context
+    }
+
+    fn evaluate_transition<E: FieldElement + From<BaseElement>>(
+        &self,
+        frame: &EvaluationFrame<E>,
+        _periodic_values: &[E],
+        result: &mut [E],
+    ) {
+        let current = frame.current();
+        let next = frame.next();
+        result[0] = next[0] - (current[0] + current[1]);
+        result[1] = next[1] - (current[1] + next[0]);
+    }
+
+    fn get_assertions(&self) -> Vec<Assertion<BaseElement>> {
+        let last_step = self.trace_length() - 1;
+        vec![
+            Assertion::single(0, 0, BaseElement::ONE),
+            Assertion::single(1, 0, BaseElement::ONE),
+            Assertion::single(1, last_step, self.result),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct NoInputs;
+
+impl ToElements<BaseElement> for NoInputs {
+    fn to_elements(&self) -> Vec<BaseElement> {
+        Vec::new()
+    }
+}
+
+/// Column 0 accumulates a periodic column and column 1 cubes its value in
+/// every row. Exercises periodic columns and every kind of assertion.
+struct AccumulatorAir(AirContext<BaseElement>);
+
+impl WinterAir for AccumulatorAir {
+    type BaseField = BaseElement;
+    type PublicInputs = NoInputs;
+
+    fn new(trace_info: TraceInfo, _: NoInputs, options: winter_air::ProofOptions) -> Self {
+        let degrees = vec![
+            TransitionConstraintDegree::with_cycles(1, vec![2]),
+            TransitionConstraintDegree::new(3),
+        ];
+        Self(AirContext::new(trace_info, degrees, 3, options))
+    }
+
+    fn context(&self) -> &AirContext<BaseElement> {
+        &self.0
+    }
+
+    fn evaluate_transition<E: FieldElement + From<BaseElement>>(
+        &self,
+        frame: &EvaluationFrame<E>,
+        periodic_values: &[E],
+        result: &mut [E],
+    ) {
+        let current = frame.current();
+        let next = frame.next();
+        result[0] = next[0] - (current[0] + periodic_values[0]);
+        result[1] = next[1] - current[1].exp(3u32.into());
+    }
+
+    fn get_assertions(&self) -> Vec<Assertion<BaseElement>> {
+        let values = [0u8, 12, 24, 36].map(BaseElement::from);
+        vec![
+            Assertion::sequence(0, 0, 8, values.to_vec()),
+            Assertion::periodic(1, 0, 2, BaseElement::ONE),
+            Assertion::single(1, 1, BaseElement::ONE),
+        ]
+    }
+
+    fn get_periodic_column_values(&self) -> Vec<Vec<BaseElement>> {
+        vec![vec![BaseElement::ONE, BaseElement::from(2u8)]]
+    }
+}
+
+/// Row of the cell [`MovingAssertionAir`] asserts
+#[derive(Clone, Copy, Default)]
+struct AssertedRow(u32);
+
+impl ToElements<BaseElement> for AssertedRow {
+    fn to_elements(&self) -> Vec<BaseElement> {
+        vec![BaseElement::from(self.0)]
+    }
+}
+
+/// Column 0 is constant and one of its cells is asserted to be one. The
+/// asserted cell depends on the public inputs.
+struct MovingAssertionAir {
+    context: AirContext<BaseElement>,
+    row: usize,
+}
+
+impl WinterAir for MovingAssertionAir {
+    type BaseField = BaseElement;
+    type PublicInputs = AssertedRow;
+
+    fn new(
+        trace_info: TraceInfo,
+        pub_inputs: AssertedRow,
+        options: winter_air::ProofOptions,
+    ) -> Self {
+        let degrees = vec![TransitionConstraintDegree::new(1)];
+        Self {
+            context: AirContext::new(trace_info, degrees, 1, options),
+            row: pub_inputs.0 as usize,
+        }
+    }
+
+    fn context(&self) -> &AirContext<BaseElement> {
+        &self.context
+    }
+
+    fn evaluate_transition<E: FieldElement + From<BaseElement>>(
+        &self,
+        frame: &EvaluationFrame<E>,
+        _periodic_values: &[E],
+        result: &mut [E],
+    ) {
+        result[0] = frame.next()[0] - frame.current()[0];
+    }
+
+    fn get_assertions(&self) -> Vec<Assertion<BaseElement>> {
+        vec![Assertion::single(0, self.row, BaseElement::ONE)]
+    }
+}
+
+struct FibClaim(BaseElement);
+
+impl Stark for FibClaim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = WinterfellAirConfig<FibAir, 2>;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = ();
+    type Trace = WinterfellTrace<BaseElement>;
+
+    fn get_public_inputs(&self) -> Vec<Fp> {
+        let inputs = FibInputs { result: self.0 };
+        WinterfellAirConfig::<FibAir, 2>::public_inputs(TRACE_LEN, inputs).unwrap()
+    }
+
+    fn gen_public_coin(&self, air: &Air<WinterfellAirConfig<FibAir, 2>>) -> Self::PublicCoin {
+        let mut seed = Vec::new();
+        air.public_inputs().serialize_compressed(&mut seed).unwrap();
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
+    }
+
+    fn generate_trace(&self, _: ()) -> WinterfellTrace<BaseElement> {
+        let mut columns = [vec![BaseElement::ONE], vec![BaseElement::ONE]];
+        for i in 1..TRACE_LEN {
+            let a = columns[0][i - 1] + columns[1][i - 1];
+            let b = columns[1][i - 1] + a;
+            columns[0].push(a);
+            columns[1].push(b);
+        }
+        WinterfellTrace::init(columns.to_vec())
+    }
+}
+
+struct AccumulatorClaim;
+
+impl Stark for AccumulatorClaim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = WinterfellAirConfig<AccumulatorAir, 2>;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = ();
+    type Trace = WinterfellTrace<BaseElement>;
+
+    fn get_public_inputs(&self) -> Vec<Fp> {
+        WinterfellAirConfig::<AccumulatorAir, 2>::public_inputs(TRACE_LEN, NoInputs).unwrap()
+    }
+
+    fn gen_public_coin(&self, _: &Air<WinterfellAirConfig<AccumulatorAir, 2>>) -> Self::PublicCoin {
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([]))
+    }
+
+    fn generate_trace(&self, _: ()) -> WinterfellTrace<BaseElement> {
+        let periodic_values = [BaseElement::ONE, BaseElement::from(2u8)];
+        let accumulator = (0..TRACE_LEN).scan(BaseElement::ZERO, |acc, i| {
+            let value = *acc;
+            *acc += periodic_values[i % periodic_values.len()];
+            Some(value)
+        });
+        WinterfellTrace::init(vec![
+            accumulator.collect(),
+            vec![BaseElement::ONE; TRACE_LEN],
+        ])
+    }
+}
+
+fn nth_fib_number(n: usize) -> BaseElement {
+    let (mut a, mut b) = (BaseElement::ONE, BaseElement::ONE);
+    for _ in 1..n {
+        (a, b) = (b, a + b);
+    }
+    a
+}
+
+#[test]
+fn fib_air_proves_and_verifies() {
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    // the last row holds fib numbers `2 * TRACE_LEN - 1` and `2 * TRACE_LEN`
+    let result = nth_fib_number(2 * TRACE_LEN);
+    let proof = pollster::block_on(FibClaim(result).prove(options, ())).unwrap();
+    FibClaim(result).verify(proof, 1).unwrap();
+
+    let proof = pollster::block_on(FibClaim(result).prove(options, ())).unwrap();
+    assert!(FibClaim(result + BaseElement::ONE)
+        .verify(proof, 1)
+        .is_err());
+}
+
+#[test]
+fn public_inputs_are_assertion_values() {
+    let result = nth_fib_number(2 * TRACE_LEN);
+    assert_eq!(
+        FibClaim(result).get_public_inputs(),
+        [Fp::from(1u8), Fp::from(1u8), result.to_ark()]
+    );
+    // sequences contribute a value for each asserted row
+    assert_eq!(AccumulatorClaim.get_public_inputs().len(), 6);
+}
+
+#[test]
+fn asserted_cells_cant_depend_on_public_inputs() {
+    assert_eq!(
+        WinterfellAirConfig::<MovingAssertionAir, 1>::public_inputs(TRACE_LEN, AssertedRow(0))
+            .unwrap(),
+        [Fp::from(1u8)]
+    );
+    assert_eq!(
+        WinterfellAirConfig::<MovingAssertionAir, 1>::public_inputs(TRACE_LEN, AssertedRow(3)),
+        Err(Error::AssertedCells)
+    );
+}
+
+#[test]
+fn periodic_columns_and_assertions_are_enforced() {
+    let options = ProofOptions::new(16, 8, 0, 2, 4);
+    let proof = pollster::block_on(AccumulatorClaim.prove(options, ())).unwrap();
+    AccumulatorClaim.verify(proof, 1).unwrap();
+}
+
+#[test]
+fn winterfell_air_evaluates_numerically() {
+    let options = winter_air::ProofOptions::new(16, 8, 0, FieldExtension::None, 4, 7);
+    let inputs = FibInputs::default();
+    let air = FibAir::new(TraceInfo::new(2, TRACE_LEN), inputs, options);
+    let frame = EvaluationFrame::from_rows(
+        vec![BaseElement::from(3u8), BaseElement::from(5u8)],
+        vec![BaseElement::from(8u8), BaseElement::from(13u8)],
+    );
+    let mut result = [BaseElement::ONE; 2];
+    air.evaluate_transition(&frame, &[], &mut result);
+    assert_eq!([BaseElement::ZERO; 2], result);
+}